@@ -1,9 +1,11 @@
 // MCTier 后端模块
 pub mod modules;
 
-use log::{error, info};
+use log::{error, info, warn};
 use modules::app_core::AppCore;
+use modules::voice_service::MicMode;
 use modules::tauri_commands::AppState;
+use modules::error::CommandError;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tauri::Manager;
@@ -26,6 +28,9 @@ fn apply_gpu_settings_on_startup() {
     
     // 读取配置文件
     if let Ok(content) = std::fs::read_to_string(&config_path) {
+        // 配置文件可能已用 DPAPI 加密，解密失败时按默认设置处理
+        let content = modules::config_crypto::decrypt_config(&content, &modules::config_crypto::DpapiProtector)
+            .unwrap_or_default();
         if let Ok(config) = serde_json::from_str::<serde_json::Value>(&content) {
             // 检查 GPU 渲染设置（配置文件使用 snake_case）
             let enable_gpu = config.get("enable_gpu_rendering")
@@ -51,30 +56,35 @@ fn apply_gpu_settings_on_startup() {
 
 
 use modules::tauri_commands::{
-    create_lobby, join_lobby, leave_lobby,
-    toggle_mic, mute_player, mute_all,
-    get_config, update_config, save_opacity,
-    get_audio_devices, get_app_state, get_current_lobby, get_players,
+    create_lobby, join_lobby, leave_lobby, kick_player,
+    toggle_mic, set_mic_mode, get_mic_mode, mute_player, get_persisted_mutes, clear_persisted_mutes, set_remember_mutes_by_name, mute_all,
+    set_player_volume, get_player_volume,
+    get_config, get_config_schema, update_hotkeys, update_config, save_opacity,
+    get_audio_devices, select_microphone, select_speaker, get_app_state, get_current_lobby, get_players, set_player_sort_order,
     get_mic_status, get_global_mute_status, is_player_muted,
-    get_network_status, get_virtual_ip, get_peer_connection_types,
+    get_network_status, get_virtual_ip, get_peer_connection_types, get_network_peers, get_peer_alignment, get_room_health_score, get_traffic_stats,
     set_always_on_top, toggle_mini_mode, set_window_opacity,
-    send_signaling_message, broadcast_status_update, send_heartbeat,
+    send_signaling_message, seal_voice_signaling, open_voice_signaling, broadcast_status_update, set_ready_status, send_heartbeat,
+    report_audio_level, get_speaking_players, get_speaking_activity, send_message_reaction,
     force_stop_easytier,
-    cancel_lobby_connecting,
+    detect_easytier_instances, cleanup_easytier_instances, set_auto_cleanup_easytier_instances,
+    set_http_tls_enabled, set_p2p_signaling_plaintext_compat, get_http_tls_fingerprint, set_reconnect_grace_secs, set_connect_timeout_secs, cancel_connecting, run_network_reconnect_supervisor, run_creator_election_supervisor, run_ip_conflict_supervisor, run_audio_device_watcher, run_mic_level_monitor, run_room_health_monitor, run_config_file_watcher, run_chat_mention_notifier, run_mc_lan_detector, run_presence_monitor, run_tray_status_updater,
+    set_chat_image_max_bytes, set_upload_max_bytes, set_easytier_launch_options, set_preferred_relay, set_voice_loudness_normalization, set_vad_threshold, get_vad_threshold, calibrate_vad_threshold, set_encrypt_config_file, set_upnp_port_mapping, set_port_config, get_service_ports, set_presence, set_share_activity_presence, set_voice_feedback_sound, set_lobby_password_policy,
+    get_recent_lobbies, clear_recent_lobbies, create_room_code, resolve_room_code, generate_invite_link, parse_invite_link,
     download_and_run_installer,
-    check_virtual_adapter, check_firewall_rules, ping_virtual_ip, check_udp_port,
-    is_admin, add_firewall_rules, restart_as_admin,
+    check_virtual_adapter, check_firewall_rules, ping_virtual_ip, ping_virtual_ip_detailed, check_udp_port,
+    is_admin, get_easytier_version, add_firewall_rules, restart_as_admin,
     save_window_position, exit_app,
     add_player_domain, remove_player_domain,
     get_folder_name, get_folder_info, list_directory_files,
-    read_file_bytes, write_file_bytes, select_folder, select_file, select_save_location,
+    read_file_bytes, write_file_bytes, select_folder, select_file, select_upload_file, select_save_location,
     save_file, save_chat_image, read_file, delete_file, extract_zip,
     open_file_location, open_folder,
     start_file_server, stop_file_server, check_file_server_status,
-    add_shared_folder, remove_shared_folder, get_local_shares,
-    cleanup_expired_shares, get_remote_shares, get_remote_files,
-    verify_share_password, get_download_url, diagnose_file_share_connection,
-    download_remote_file, cancel_remote_download, export_logs, test_node_latency,
+    add_shared_folder, remove_shared_folder, get_local_shares, set_file_note, export_shares, import_shares,
+    cleanup_expired_shares, get_share_download_stats, get_remote_shares, get_all_remote_shares, get_remote_files,
+    verify_share_password, get_download_url, get_remote_file_thumbnail, diagnose_file_share_connection,
+    download_remote_file, download_share_as_zip, upload_file_to_peer, cancel_remote_download, pause_download, resume_download, get_resumable_downloads, export_logs, health_check, test_node_latency,
     download_remote_batch, detect_security_software,
     send_p2p_chat_message, get_p2p_chat_messages, clear_p2p_chat_messages,
     open_screen_viewer_window,
@@ -82,10 +92,10 @@ use modules::tauri_commands::{
     set_danmaku_ignore_cursor, danmaku_cursor_pos, save_danmaku_image,
     open_game_hud_window, close_game_hud_window,
     set_gamehud_ignore_cursor, gamehud_cursor_pos,
-    open_log_folder, open_log_file, get_log_file_path,
+    open_log_folder, open_log_file, get_log_file_path, set_log_level, set_close_to_tray,
     save_settings, get_settings, set_auto_start, check_auto_start,
     reset_config_to_default, save_voice_volume,
-    export_config, import_config,
+    export_config, import_config, export_config_data, import_config_data,
     restart_app_with_gpu_settings,
     save_exit_node_advanced_config, get_exit_node_advanced_config,
 };
@@ -101,6 +111,11 @@ use modules::minecraft_discovery::{
 };
 
 use modules::mc_lan_bridge::{start_mc_lan_broadcast, stop_mc_lan_broadcast};
+use modules::mc_chat_bridge::{start_mc_chat_bridge, stop_mc_chat_bridge, test_mc_rcon};
+use modules::lobby_auto_actions::set_lobby_auto_actions;
+use modules::connection_repair::repair_connection;
+use modules::voice_session::{establish_voice_connection, close_voice_connection};
+use modules::auto_doctor::auto_doctor;
 
 use modules::remote_control::remote_inject_input;
 
@@ -252,50 +267,63 @@ fn install_minimize_to_hide(window: &tauri::WebviewWindow) {
     }
 }
 
-/// 由前端按当前界面语言更新系统托盘菜单文本（显示/退出）。
-/// 保持菜单项 id 不变（show_main / exit_app），故已注册的 on_menu_event 仍生效。
+/// 由前端按当前界面语言更新系统托盘菜单文本。
+/// 保持菜单项 id 不变（见 modules::tray），故已注册的 on_menu_event 仍生效。
+/// 麦克风 / 状态文本未传时沿用中文默认值（兼容旧版前端）。
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn set_tray_menu_texts(
     app: tauri::AppHandle,
     show_text: String,
     exit_text: String,
-) -> Result<(), String> {
-    use tauri::menu::{MenuBuilder, MenuItem};
-    if let Some(tray) = app.tray_by_id("main-tray") {
-        let show_item = MenuItem::with_id(&app, "show_main", show_text, true, None::<&str>)
-            .map_err(|e| e.to_string())?;
-        let exit_item = MenuItem::with_id(&app, "exit_app", exit_text, true, None::<&str>)
-            .map_err(|e| e.to_string())?;
-        let menu = MenuBuilder::new(&app)
-            .item(&show_item)
-            .separator()
-            .item(&exit_item)
-            .build()
-            .map_err(|e| e.to_string())?;
-        tray.set_menu(Some(menu)).map_err(|e| e.to_string())?;
-    }
+    mic_on_text: Option<String>,
+    mic_off_text: Option<String>,
+    idle_text: Option<String>,
+    in_lobby_text: Option<String>,
+) -> Result<(), CommandError> {
+    let defaults = modules::tray::TrayTexts::default();
+    let texts = modules::tray::TrayTexts {
+        show: show_text,
+        exit: exit_text,
+        mic_on: mic_on_text.unwrap_or(defaults.mic_on),
+        mic_off: mic_off_text.unwrap_or(defaults.mic_off),
+        idle: idle_text.unwrap_or(defaults.idle),
+        in_lobby: in_lobby_text.unwrap_or(defaults.in_lobby),
+    };
+    modules::tray::set_texts(&app, texts).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// 执行完整关闭流程（退出大厅、停止服务）后退出应用。
+/// 托盘「退出」与未开启「关闭时最小化到托盘」时的关闭主窗口都走这里。
+fn shutdown_and_exit(app: &tauri::AppHandle) {
+    use tauri::Manager;
+    modules::tray::mark_quitting();
+    let ah = app.clone();
+    if let Some(state) = ah.try_state::<AppState>() {
+        let core = Arc::clone(&state.core);
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = core.lock().await.shutdown().await { error!("关闭错误: {}", e); }
+            if let Some(w) = ah.get_webview_window("main") { let _ = w.close(); }
+            ah.exit(0);
+        });
+    } else {
+        if let Some(w) = ah.get_webview_window("main") { let _ = w.close(); }
+        ah.exit(0);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 以管理员身份重启的新实例：等旧实例退出后再启动，避免被单实例检测拦下
+    if let Some(pid) = modules::network_service::parse_elevated_restart_pid(std::env::args()) {
+        modules::network_service::wait_for_process_exit(pid, std::time::Duration::from_secs(10));
+    }
+
     // 在应用启动时检查并应用 GPU 设置
     apply_gpu_settings_on_startup();
     
-    use std::fs::OpenOptions;
-    let log_path = if let Some(data_dir) = dirs::data_local_dir() {
-        let mctier_dir = data_dir.join("MCTier");
-        let _ = std::fs::create_dir_all(&mctier_dir);
-        mctier_dir.join("mctier.log")
-    } else {
-        std::path::PathBuf::from("mctier.log")
-    };
-    let log_file = OpenOptions::new().create(true).append(true).open(&log_path).expect("无法创建日志文件");
-    env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Info)
-        .format_timestamp_millis()
-        .target(env_logger::Target::Pipe(Box::new(log_file)))
-        .init();
+    let log_path = modules::log_file::init_logger();
     info!("MCTier 应用程序启动中...");
     info!("日志文件位置: {:?}", log_path);
 
@@ -304,6 +332,8 @@ pub fn run() {
         match AppCore::new().await {
             Ok(core) => {
                 info!("应用核心初始化成功");
+                let log_level = core.get_config_manager().lock().await.get_config().log_level.unwrap_or_default();
+                modules::log_file::set_log_level(log_level);
                 if let Err(e) = core.start().await { error!("应用启动失败: {}", e); }
                 core
             }
@@ -311,7 +341,7 @@ pub fn run() {
         }
     });
 
-    let app_state = AppState { core: Arc::new(Mutex::new(app_core)) };
+    let app_state = AppState::new(app_core);
 
     let result = tauri::Builder::default()
         .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
@@ -330,30 +360,35 @@ pub fn run() {
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             greet, open_devtools,
-            create_lobby, join_lobby, leave_lobby,
-            toggle_mic, mute_player, mute_all,
-            get_config, update_config, save_opacity,
-            get_audio_devices, get_app_state, get_current_lobby, get_players,
+            create_lobby, join_lobby, leave_lobby, kick_player,
+            toggle_mic, set_mic_mode, get_mic_mode, mute_player, get_persisted_mutes, clear_persisted_mutes, set_remember_mutes_by_name, mute_all,
+            set_player_volume, get_player_volume,
+            get_config, get_config_schema, update_hotkeys, update_config, save_opacity,
+            get_audio_devices, select_microphone, select_speaker, get_app_state, get_current_lobby, get_players, set_player_sort_order,
             get_mic_status, get_global_mute_status, is_player_muted,
-            get_network_status, get_virtual_ip, get_peer_connection_types,
+            get_network_status, get_virtual_ip, get_peer_connection_types, get_network_peers, get_peer_alignment, get_room_health_score, get_traffic_stats,
             set_always_on_top, toggle_mini_mode, set_window_opacity,
-            send_signaling_message, broadcast_status_update, send_heartbeat,
+            send_signaling_message, seal_voice_signaling, open_voice_signaling, broadcast_status_update, set_ready_status, send_heartbeat,
+            report_audio_level, get_speaking_players, get_speaking_activity, send_message_reaction,
             force_stop_easytier,
-            cancel_lobby_connecting,
+            detect_easytier_instances, cleanup_easytier_instances, set_auto_cleanup_easytier_instances,
+            set_http_tls_enabled, set_p2p_signaling_plaintext_compat, get_http_tls_fingerprint, set_reconnect_grace_secs, set_connect_timeout_secs, cancel_connecting,
+            set_chat_image_max_bytes, set_upload_max_bytes, set_easytier_launch_options, set_preferred_relay, set_voice_loudness_normalization, set_vad_threshold, get_vad_threshold, calibrate_vad_threshold, set_encrypt_config_file, set_upnp_port_mapping, set_port_config, get_service_ports, set_presence, set_share_activity_presence, set_voice_feedback_sound, set_lobby_password_policy,
+            get_recent_lobbies, clear_recent_lobbies, create_room_code, resolve_room_code, generate_invite_link, parse_invite_link,
             download_and_run_installer,
-            check_virtual_adapter, check_firewall_rules, ping_virtual_ip, check_udp_port,
-            is_admin, add_firewall_rules, restart_as_admin,
+            check_virtual_adapter, check_firewall_rules, ping_virtual_ip, ping_virtual_ip_detailed, check_udp_port,
+            is_admin, get_easytier_version, add_firewall_rules, restart_as_admin,
             save_window_position, exit_app,
             add_player_domain, remove_player_domain,
             get_folder_name, get_folder_info, list_directory_files,
-            read_file_bytes, write_file_bytes, select_folder, select_file, select_save_location,
+            read_file_bytes, write_file_bytes, select_folder, select_file, select_upload_file, select_save_location,
             save_file, save_chat_image, read_file, delete_file, extract_zip,
             open_file_location, open_folder,
             start_file_server, stop_file_server, check_file_server_status,
-            add_shared_folder, remove_shared_folder, get_local_shares,
-            cleanup_expired_shares, get_remote_shares, get_remote_files,
-            verify_share_password, get_download_url, diagnose_file_share_connection,
-            download_remote_file, cancel_remote_download, export_logs, test_node_latency,
+            add_shared_folder, remove_shared_folder, get_local_shares, set_file_note, export_shares, import_shares,
+            cleanup_expired_shares, get_share_download_stats, get_remote_shares, get_all_remote_shares, get_remote_files,
+            verify_share_password, get_download_url, get_remote_file_thumbnail, diagnose_file_share_connection,
+            download_remote_file, download_share_as_zip, upload_file_to_peer, cancel_remote_download, pause_download, resume_download, get_resumable_downloads, export_logs, health_check, test_node_latency,
             download_remote_batch, detect_security_software,
             send_p2p_chat_message, get_p2p_chat_messages, clear_p2p_chat_messages,
             open_screen_viewer_window,
@@ -361,10 +396,10 @@ pub fn run() {
             set_danmaku_ignore_cursor, danmaku_cursor_pos, save_danmaku_image,
             open_game_hud_window, close_game_hud_window,
             set_gamehud_ignore_cursor, gamehud_cursor_pos,
-            open_log_folder, open_log_file, get_log_file_path,
+            open_log_folder, open_log_file, get_log_file_path, set_log_level, set_close_to_tray,
             save_settings, get_settings, set_auto_start, check_auto_start,
             reset_config_to_default, save_voice_volume,
-            export_config, import_config,
+            export_config, import_config, export_config_data, import_config_data,
             restart_app_with_gpu_settings,
             save_exit_node_advanced_config, get_exit_node_advanced_config,
             save_global_easytier_advanced_config, get_global_easytier_advanced_config,
@@ -372,6 +407,12 @@ pub fn run() {
             clear_lobby_easytier_advanced_config,
             scan_minecraft_servers, query_minecraft_server, measure_peers_latency,
             start_mc_lan_broadcast, stop_mc_lan_broadcast,
+            start_mc_chat_bridge, stop_mc_chat_bridge, test_mc_rcon,
+            set_lobby_auto_actions,
+            repair_connection,
+            establish_voice_connection,
+            close_voice_connection,
+            auto_doctor,
             set_tray_menu_texts,
             remote_inject_input,
         ])
@@ -387,23 +428,19 @@ pub fn run() {
             }
 
             {
-                use tauri::menu::{MenuBuilder, MenuItem};
                 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-                let show_item = MenuItem::with_id(app, "show_main", "显示 MCTier", true, None::<&str>)?;
-                let exit_item = MenuItem::with_id(app, "exit_app", "退出 MCTier", true, None::<&str>)?;
-                let tray_menu = MenuBuilder::new(app)
-                    .item(&show_item)
-                    .separator()
-                    .item(&exit_item)
-                    .build()?;
-                TrayIconBuilder::with_id("main-tray")
+                let tray_menu = modules::tray::build_menu(app.handle())?;
+                TrayIconBuilder::with_id(modules::tray::TRAY_ID)
                     .tooltip("MCTier")
                     .icon(app.default_window_icon().cloned().unwrap())
                     .menu(&tray_menu)
                     .show_menu_on_left_click(false)
                     .on_menu_event(|app, event| match event.id().as_ref() {
-                        "show_main" => restore_main_window(app),
-                        "exit_app" => app.exit(0),
+                        modules::tray::MENU_SHOW => restore_main_window(app),
+                        modules::tray::MENU_TOGGLE_MIC => {
+                            tauri::async_runtime::spawn(modules::tray::toggle_mic(app.clone()));
+                        }
+                        modules::tray::MENU_EXIT => shutdown_and_exit(app),
                         _ => {}
                     })
                     .on_tray_icon_event(|tray, event| {
@@ -417,6 +454,9 @@ pub fn run() {
             }
 
 
+            // 服务端口被占用而顺延时向前端推送 service-port-changed
+            modules::service_ports::set_app_handle(app_handle.clone());
+
             // 邀请 deep link：注册运行时 scheme 并监听冷启动/运行时打开的链接
             {
                 use tauri_plugin_deep_link::DeepLinkExt;
@@ -438,14 +478,17 @@ pub fn run() {
                 println!("✅ [Setup] 成功获取 AppState");
                 let core_hk = Arc::clone(&state.core);
                 
-                // 使用固定的快捷键（不再从配置文件读取）
-                let mic_hotkey = "CommandOrControl+M";
-                let global_mute_hotkey = "CommandOrControl+T";
-                let push_to_talk_hotkey = "F2";
-                let summon_hotkey = "CommandOrControl+Alt+M";
-                
-                info!("注册固定快捷键: 麦克风=Ctrl+M, 全局静音=Ctrl+T, 临时开麦=F2, 唤出窗口=Ctrl+Alt+M");
-                println!("🔑 [快捷键] 注册固定快捷键: 麦克风=Ctrl+M, 全局静音=Ctrl+T, 临时开麦=F2, 唤出窗口=Ctrl+Alt+M");
+                use modules::hotkeys::{PUSH_TO_TALK_HOTKEY, SUMMON_HOTKEY};
+                let push_to_talk_hotkey = PUSH_TO_TALK_HOTKEY;
+                let summon_hotkey = SUMMON_HOTKEY;
+
+                // 麦克风与全局静音快捷键读取配置中的绑定，无效时回退默认值
+                let (mic_hotkey, global_mute_hotkey) = tauri::async_runtime::block_on(async {
+                    let cfg = { let cl = core_hk.lock().await; cl.get_config_manager().lock().await.get_config_clone() };
+                    (cfg.mic_hotkey, cfg.global_mute_hotkey)
+                });
+                modules::hotkeys::install_hotkeys(&app_handle, state.get_voice_service(), mic_hotkey.as_deref(), global_mute_hotkey.as_deref());
+                println!("🔑 [快捷键] 注册固定快捷键: 临时开麦=F2, 唤出窗口=Ctrl+Alt+M");
 
                 // 注册「唤出窗口」快捷键：作为 Win+D/任务栏最小化后无法唤出的可靠兜底
                 let hs = app_handle.clone();
@@ -456,59 +499,8 @@ pub fn run() {
                     println!("⚠️ [快捷键] 注册唤出窗口快捷键失败: {}", e);
                 }
                 
-                let ltm = Arc::new(Mutex::new(std::time::Instant::now() - std::time::Duration::from_millis(500)));
-                let ltt = Arc::new(Mutex::new(std::time::Instant::now() - std::time::Duration::from_millis(500)));
                 let ltf = Arc::new(Mutex::new((false, false))); // (is_pressed, original_mic_state)
                 
-                // 注册麦克风快捷键
-                let cm = Arc::clone(&core_hk); let hm = app_handle.clone(); let lm = Arc::clone(&ltm);
-                if let Err(e) = app.global_shortcut().on_shortcut(mic_hotkey, move |_,_,ev| {
-                    if ev.state == tauri_plugin_global_shortcut::ShortcutState::Released { return; }
-                    let mut lt = match lm.try_lock() { Ok(g) => g, Err(_) => return };
-                    let now = std::time::Instant::now();
-                    if now.duration_since(*lt) < std::time::Duration::from_millis(200) { return; }
-                    *lt = now; drop(lt);
-                    let c = Arc::clone(&cm); let h = hm.clone();
-                    tauri::async_runtime::spawn(async move {
-                        match c.lock().await.toggle_mic().await {
-                            Ok(s) => { let _ = h.emit("mic-toggled", s); }
-                            Err(e) => { error!("切换麦克风失败: {}", e); }
-                        }
-                    });
-                }) { 
-                    error!("麦克风快捷键 Ctrl+M 注册失败: {}", e);
-                    println!("❌ [快捷键] 麦克风快捷键 Ctrl+M 注册失败: {}", e);
-                } else { 
-                    info!("麦克风快捷键 Ctrl+M 注册成功");
-                    println!("✅ [快捷键] 麦克风快捷键 Ctrl+M 注册成功");
-                }
-                
-                // 注册全局静音快捷键
-                let ct = Arc::clone(&core_hk); let ht = app_handle.clone(); let lt2 = Arc::clone(&ltt);
-                if let Err(e) = app.global_shortcut().on_shortcut(global_mute_hotkey, move |_,_,ev| {
-                    if ev.state == tauri_plugin_global_shortcut::ShortcutState::Released { return; }
-                    let mut lt = match lt2.try_lock() { Ok(g) => g, Err(_) => return };
-                    let now = std::time::Instant::now();
-                    if now.duration_since(*lt) < std::time::Duration::from_millis(200) { return; }
-                    *lt = now; drop(lt);
-                    let c = Arc::clone(&ct); let h = ht.clone();
-                    tauri::async_runtime::spawn(async move {
-                        let vs = c.lock().await.get_voice_service();
-                        let v = vs.lock().await;
-                        let ns = !v.is_global_muted();
-                        match v.mute_all(ns).await {
-                            Ok(_) => { let _ = h.emit("global-mute-toggled", ns); }
-                            Err(e) => { error!("切换静音失败: {}", e); }
-                        }
-                    });
-                }) { 
-                    error!("全局静音快捷键 Ctrl+T 注册失败: {}", e);
-                    println!("❌ [快捷键] 全局静音快捷键 Ctrl+T 注册失败: {}", e);
-                } else { 
-                    info!("全局静音快捷键 Ctrl+T 注册成功");
-                    println!("✅ [快捷键] 全局静音快捷键 Ctrl+T 注册成功");
-                }
-                
                 // 注册 F2 临时开麦快捷键（按下开麦，松开闭麦）
                 let cf = Arc::clone(&core_hk); let hf = app_handle.clone(); let ltf2 = Arc::clone(&ltf);
                 if let Err(e) = app.global_shortcut().on_shortcut(push_to_talk_hotkey, move |_,_,ev| {
@@ -519,6 +511,9 @@ pub fn run() {
                             Err(_) => return,
                         };
                         
+                        // 按键说话模式：按下即发声、松开即静音；其它模式下为临时开麦，松开后恢复原状态
+                        let mode = c.lock().await.get_voice_service().lock().await.get_mic_mode().await;
+                        
                         if ev.state == tauri_plugin_global_shortcut::ShortcutState::Pressed {
                             // 按下 F2
                             if state.0 { return; } // 已经按下，防止重复触发
@@ -531,8 +526,8 @@ pub fn run() {
                             
                             // 如果麦克风是关闭的，则开启
                             if !current_mic_state {
-                                info!("F2 临时开麦：开启麦克风");
-                                match c.lock().await.toggle_mic().await {
+                                info!("F2 按下：开启麦克风（模式: {}）", mode.as_str());
+                                match c.lock().await.set_mic_enabled(true).await {
                                     Ok(s) => { let _ = h.emit("mic-toggled", s); }
                                     Err(e) => { error!("F2 开启麦克风失败: {}", e); }
                                 }
@@ -544,12 +539,12 @@ pub fn run() {
                             state.0 = false;
                             drop(state);
                             
-                            // 如果原来麦克风是关闭的，则恢复关闭状态
-                            if !original_state {
-                                info!("F2 临时开麦：恢复麦克风状态");
-                                match c.lock().await.toggle_mic().await {
+                            // 按键说话模式总是静音；其它模式下若原来麦克风是关闭的，则恢复关闭状态
+                            if mode == MicMode::PushToTalk || !original_state {
+                                info!("F2 松开：关闭麦克风（模式: {}）", mode.as_str());
+                                match c.lock().await.set_mic_enabled(false).await {
                                     Ok(s) => { let _ = h.emit("mic-toggled", s); }
-                                    Err(e) => { error!("F2 恢复麦克风状态失败: {}", e); }
+                                    Err(e) => { error!("F2 关闭麦克风失败: {}", e); }
                                 }
                             }
                         }
@@ -603,12 +598,31 @@ pub fn run() {
                         // 应用窗口位置设置
                         let remember_position = config.remember_window_position.unwrap_or(false);
                         if remember_position {
-                            if let Some(pos) = &config.window_position {
+                            if let Some(saved) = &config.window_position {
                                 use tauri::PhysicalPosition;
+                                // 校验保存的位置仍在可见显示器范围内（屏幕被拔掉时回退到主屏）
+                                let primary = win.primary_monitor().ok().flatten().map(|m| *m.position());
+                                let monitors: Vec<modules::config_manager::MonitorArea> = win
+                                    .available_monitors()
+                                    .unwrap_or_default()
+                                    .iter()
+                                    .map(|m| modules::config_manager::MonitorArea {
+                                        name: m.name().cloned(),
+                                        x: m.position().x,
+                                        y: m.position().y,
+                                        width: m.size().width,
+                                        height: m.size().height,
+                                        primary: Some(*m.position()) == primary,
+                                    })
+                                    .collect();
+                                let pos = saved.resolve_on(&monitors);
+                                if pos.x != saved.x || pos.y != saved.y {
+                                    log::warn!("保存的窗口位置不可见（x={}, y={}, 显示器={:?}），已调整", saved.x, saved.y, saved.monitor);
+                                }
                                 if let Err(e) = win.set_position(PhysicalPosition::new(pos.x, pos.y)) {
                                     error!("设置窗口位置失败: {}", e);
                                 } else {
-                                    info!("窗口位置已恢复: x={}, y={}", pos.x, pos.y);
+                                    info!("窗口位置已恢复: x={}, y={}, 显示器={:?}", pos.x, pos.y, pos.monitor);
                                 }
                             }
                         }
@@ -622,6 +636,29 @@ pub fn run() {
                     core.lock().await.set_app_handle(ah2).await;
                     info!("应用句柄已设置到 AppCore");
                 });
+                tauri::async_runtime::spawn(run_network_reconnect_supervisor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_creator_election_supervisor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_ip_conflict_supervisor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_audio_device_watcher(app.handle().clone()));
+                tauri::async_runtime::spawn(run_mic_level_monitor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_room_health_monitor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_config_file_watcher(app.handle().clone()));
+                tauri::async_runtime::spawn(run_chat_mention_notifier(app.handle().clone()));
+                tauri::async_runtime::spawn(run_mc_lan_detector(app.handle().clone()));
+                tauri::async_runtime::spawn(run_presence_monitor(app.handle().clone()));
+                tauri::async_runtime::spawn(run_tray_status_updater(app.handle().clone()));
+            }
+            // 非管理员启动时尽早提示（创建虚拟网卡需要管理员权限），前端显示常驻横幅
+            if !modules::network_service::is_elevated() {
+                warn!("当前未以管理员身份运行，创建大厅将失败");
+                let ah_elev = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    let _ = ah_elev.emit(
+                        "elevation-required",
+                        serde_json::json!({ "message": "请以管理员身份运行 MCTier，否则无法创建虚拟网卡" }),
+                    );
+                });
             }
             if let Some(state) = app.try_state::<AppState>() {
                 let core = Arc::clone(&state.core);
@@ -654,19 +691,28 @@ pub fn run() {
                 if label != "main" {
                     return;
                 }
+                // 已进入退出流程（托盘退出后关闭窗口），不再拦截
+                if modules::tray::is_quitting() {
+                    return;
+                }
                 api.prevent_close();
                 let ah = window.app_handle().clone();
-                if let Some(state) = ah.try_state::<AppState>() {
-                    let core = Arc::clone(&state.core);
-                    tauri::async_runtime::spawn(async move {
-                        if let Err(e) = core.lock().await.shutdown().await { error!("关闭错误: {}", e); }
-                        if let Some(w) = ah.get_webview_window("main") { let _ = w.close(); }
-                        ah.exit(0);
-                    });
-                } else {
-                    let _ = window.close();
-                    window.app_handle().exit(0);
-                }
+                let Some(state) = ah.try_state::<AppState>() else {
+                    shutdown_and_exit(&ah);
+                    return;
+                };
+                let core = Arc::clone(&state.core);
+                tauri::async_runtime::spawn(async move {
+                    let config_manager = core.lock().await.get_config_manager();
+                    let close_to_tray = config_manager.lock().await.get_config().close_to_tray.unwrap_or(false);
+                    if close_to_tray {
+                        // 后台静默运行：只隐藏主窗口，组网与语音保持，通过托盘或快捷键唤回
+                        info!("关闭主窗口：已最小化到托盘");
+                        if let Some(w) = ah.get_webview_window("main") { let _ = w.hide(); }
+                    } else {
+                        shutdown_and_exit(&ah);
+                    }
+                });
             }
         })
         .run(tauri::generate_context!());
@@ -2,12 +2,13 @@
 // 负责应用程序生命周期管理、模块初始化、全局状态维护
 
 use std::sync::Arc;
+use serde::Serialize;
 use tokio::sync::Mutex;
 use log::{info, warn};
 
 use super::config_manager::ConfigManager;
 use super::lobby_manager::LobbyManager;
-use super::network_service::{NetworkService, NetworkConfig};
+use super::network_service::{ConnectionStatus, NetworkService, NetworkConfig};
 use super::voice_service::VoiceService;
 use super::p2p_signaling::P2PSignalingService;
 use super::websocket_signaling::WebSocketSignalingServer;
@@ -28,6 +29,62 @@ pub enum AppState {
     Error(String),
 }
 
+/// 子服务健康检查报告（设置页"诊断"一键展示，也可附在问题反馈里）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    /// 生成时间（Unix 毫秒）
+    pub generated_at: i64,
+    /// 应用状态
+    pub app_state: String,
+    /// 是否在大厅中
+    pub in_lobby: bool,
+    /// EasyTier 进程是否在运行
+    pub easytier_running: bool,
+    /// 虚拟网络连接状态
+    pub connection_status: ConnectionStatus,
+    /// 已分配的虚拟 IP
+    pub virtual_ip: Option<String>,
+    /// 文件服务器监听地址（按需启动，未启动时为 None）
+    pub file_server: Option<String>,
+    /// 本机共享数量
+    pub share_count: usize,
+    /// P2P 信令套接字端口（未活跃时为 None）
+    pub p2p_signaling_port: Option<u16>,
+    /// 聊天服务器监听地址
+    pub chat_server: Option<String>,
+    /// P2P 信令发现的其他成员数量
+    pub peer_count: usize,
+    /// 发现的问题（为空表示各子服务正常）
+    pub issues: Vec<String>,
+}
+
+impl HealthReport {
+    /// 根据各子服务状态列出问题（不在大厅中时子服务本就未启动，不算问题）
+    fn collect_issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !self.in_lobby {
+            return issues;
+        }
+        if !self.easytier_running {
+            issues.push("EasyTier 进程未运行".to_string());
+        }
+        if self.virtual_ip.is_none() {
+            issues.push("虚拟 IP 尚未分配".to_string());
+        }
+        if self.p2p_signaling_port.is_none() {
+            issues.push("P2P 信令套接字未活跃，无法发现其他成员".to_string());
+        }
+        if self.chat_server.is_none() {
+            issues.push("聊天服务器未监听".to_string());
+        }
+        if self.share_count > 0 && self.file_server.is_none() {
+            issues.push(format!("有 {} 个共享但文件服务器未监听", self.share_count));
+        }
+        issues
+    }
+}
+
 /// 应用程序核心结构体
 /// 
 /// 负责协调所有子模块的交互，管理应用程序的生命周期
@@ -95,15 +152,24 @@ impl AppCore {
         info!("网络服务初始化成功");
 
         // 初始化大厅管理器
-        let lobby_manager = Arc::new(Mutex::new(LobbyManager::new()));
+        let lobby_manager = LobbyManager::new();
+        let display_names = lobby_manager.display_names();
+        let lobby_manager = Arc::new(Mutex::new(lobby_manager));
         info!("大厅管理器初始化成功");
 
         // 初始化语音服务
         let voice_service = Arc::new(Mutex::new(VoiceService::new()));
         info!("语音服务初始化成功");
 
+        // 应用配置中的服务端口（信令 / 文件共享 / 聊天）
+        let port_config = config_manager.lock().await.get_config().port_config.unwrap_or_default();
+        crate::modules::service_ports::configure(port_config);
+
         // 初始化P2P信令服务
-        let p2p_signaling = Arc::new(Mutex::new(P2PSignalingService::new(47777)));
+        let mut p2p_signaling = P2PSignalingService::new(port_config.signaling);
+        // 与大厅管理器共用显示名去重表，重名玩家在两处得到一致的后缀
+        p2p_signaling.set_display_names(display_names);
+        let p2p_signaling = Arc::new(Mutex::new(p2p_signaling));
         info!("P2P信令服务初始化成功");
 
         // 初始化WebSocket信令服务器（初始为None，创建大厅时才创建）
@@ -155,6 +221,13 @@ impl AppCore {
         }
         drop(current_state);
 
+        // 清理上次崩溃遗留的 MCTier hosts 记录（单实例运行，此时不可能有正在使用的记录）
+        match crate::modules::hosts_manager::HostsManager::cleanup_all_mctier_entries() {
+            Ok(0) => {}
+            Ok(count) => info!("已清理上次遗留的 {} 个MCTier hosts记录块", count),
+            Err(e) => warn!("清理遗留hosts记录失败: {}", e),
+        }
+
         // 初始化语音服务
         match self.voice_service.lock().await.initialize().await {
             Ok(_) => info!("语音服务启动成功"),
@@ -164,6 +237,75 @@ impl AppCore {
             }
         }
 
+        // 恢复上次选择的音频设备（设备已拔出时保留默认设备）
+        let (mic_device, speaker_device) = {
+            let config_manager = self.config_manager.lock().await;
+            let config = config_manager.get_config();
+            (config.audio_device_id.clone(), config.speaker_device_id.clone())
+        };
+        if let Some(device_id) = mic_device {
+            if let Err(e) = self.voice_service.lock().await.select_microphone(&device_id).await {
+                warn!("恢复上次选择的麦克风失败，使用默认设备: {}", e);
+            }
+        }
+        if let Some(device_id) = speaker_device {
+            if let Err(e) = self.voice_service.lock().await.select_speaker(&device_id).await {
+                warn!("恢复上次选择的扬声器失败，使用默认设备: {}", e);
+            }
+        }
+
+        // 应用配置中的麦克风模式
+        let mic_mode = self.config_manager.lock().await.get_config().mic_mode.unwrap_or_default();
+        self.voice_service.lock().await.set_mic_mode(mic_mode).await;
+
+        // 应用配置中的说话阈值
+        let vad_threshold = self.config_manager.lock().await.get_config().vad_threshold;
+        if let Some(threshold) = vad_threshold {
+            self.voice_service.lock().await.set_vad_threshold(threshold);
+        }
+
+        // 应用配置中的语音响度均衡开关
+        let loudness_normalization = self.config_manager.lock().await.get_config().voice_loudness_normalization.unwrap_or(false);
+        self.voice_service.lock().await.set_loudness_normalization(loudness_normalization).await;
+
+        // 应用配置中的 UPnP 端口映射开关
+        let upnp_port_mapping = self.config_manager.lock().await.get_config().upnp_port_mapping.unwrap_or(false);
+        self.p2p_signaling.lock().await.set_upnp_enabled(upnp_port_mapping);
+
+        // 应用配置中的共享浏览状态广播开关
+        let share_activity_presence = self.config_manager.lock().await.get_config().share_activity_presence.unwrap_or(true);
+        crate::modules::share_presence::presence_slot().lock().set_enabled(share_activity_presence);
+
+        // 应用配置中的开关麦提示音开关
+        let voice_feedback_sound = self.config_manager.lock().await.get_config().voice_feedback_sound.unwrap_or(true);
+        crate::modules::voice_feedback::set_sound_enabled(voice_feedback_sound);
+
+        // 应用配置中的大厅密码强度策略
+        let password_policy = self.config_manager.lock().await.get_config().lobby_password_policy.unwrap_or_default();
+        self.lobby_manager.lock().await.set_password_policy(password_policy);
+
+        // 应用配置中的 EasyTier 多实例处理策略
+        let auto_cleanup = self.config_manager.lock().await.get_config().auto_cleanup_easytier_instances.unwrap_or(true);
+        self.network_service.lock().await.set_auto_cleanup_instances(auto_cleanup).await;
+
+        // 应用配置中的 EasyTier 启动选项
+        let launch = self.config_manager.lock().await.get_config().easytier_launch_options.clone().unwrap_or_default();
+        self.network_service.lock().await.set_launch_options(launch);
+
+        // 应用配置中的偏好中继节点
+        let preferred_relay = self.config_manager.lock().await.get_config().preferred_relay.clone();
+        self.network_service.lock().await.set_preferred_relay(preferred_relay);
+
+        // 应用配置中的上传大小上限
+        let upload_max_bytes = self.config_manager.lock().await.get_config().upload_max_bytes
+            .unwrap_or(crate::modules::file_transfer::DEFAULT_UPLOAD_MAX_BYTES);
+        self.file_transfer.lock().await.set_upload_max_bytes(upload_max_bytes);
+
+        // 应用配置中的连接超时
+        let connect_timeout = self.config_manager.lock().await.get_config().connect_timeout_secs
+            .unwrap_or(crate::modules::network_service::DEFAULT_CONNECT_TIMEOUT_SECS);
+        self.network_service.lock().await.set_connect_timeout_secs(connect_timeout);
+
         info!("应用启动完成");
         Ok(())
     }
@@ -249,6 +391,7 @@ impl AppCore {
         info!("设置 Tauri 应用句柄");
         self.network_service.lock().await.set_app_handle(app_handle.clone());
         self.p2p_signaling.lock().await.set_app_handle(app_handle.clone()).await;
+        self.file_transfer.lock().await.set_app_handle(app_handle.clone());
         
         // 如果WebSocket信令服务器已创建，也设置其app_handle
         if let Some(ws_server) = self.websocket_signaling.lock().await.as_ref() {
@@ -352,6 +495,47 @@ impl AppCore {
         Ok(())
     }
 
+    /// 健康检查：汇总各子服务的运行状态
+    ///
+    /// 依次短暂锁定各子服务读取状态，不会长时间占用任何一个服务的锁。
+    ///
+    /// # 返回
+    ///
+    /// 结构化的健康报告，`issues` 为空表示各子服务正常
+    pub async fn health_check(&self) -> HealthReport {
+        let state = self.get_state().await;
+        let (easytier_running, connection_status, virtual_ip) = {
+            let network = self.network_service.lock().await;
+            (network.is_process_alive().await, network.check_connection().await, network.get_virtual_ip().await)
+        };
+        let (file_server, share_count) = {
+            let file_transfer = self.file_transfer.lock().await;
+            (file_transfer.bound_addr().map(|addr| addr.to_string()), file_transfer.get_shares().len())
+        };
+        let (p2p_signaling_port, peer_count) = {
+            let p2p = self.p2p_signaling.lock().await;
+            (p2p.active_port().await, p2p.get_peers().await.len())
+        };
+        let chat_server = self.chat_service.lock().await.bound_addr().map(|addr| addr.to_string());
+
+        let mut report = HealthReport {
+            generated_at: chrono::Utc::now().timestamp_millis(),
+            app_state: format!("{:?}", state),
+            in_lobby: state == AppState::InLobby,
+            easytier_running,
+            connection_status,
+            virtual_ip,
+            file_server,
+            share_count,
+            p2p_signaling_port,
+            chat_server,
+            peer_count,
+            issues: Vec::new(),
+        };
+        report.issues = report.collect_issues();
+        report
+    }
+
     /// 切换麦克风状态
     /// 
     /// # 返回
@@ -379,6 +563,28 @@ impl AppCore {
             }
         }
     }
+
+    /// 设置麦克风状态（按键说话的按下/松开使用，结果与当前状态无关）
+    /// 
+    /// # 参数
+    /// 
+    /// * `enabled` - true 表示开启麦克风，false 表示关闭
+    /// 
+    /// # 返回
+    /// 
+    /// * `Ok(bool)` - 新的麦克风状态
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_mic_enabled(&self, enabled: bool) -> Result<bool, AppError> {
+        self.voice_service
+            .lock()
+            .await
+            .set_mic_enabled(enabled)
+            .await
+            .map_err(|e| {
+                warn!("设置麦克风状态失败: {}", e);
+                AppError::VoiceError(e.to_string())
+            })
+    }
 }
 
 // 实现 Drop trait 以确保资源正确清理
@@ -450,6 +656,30 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_health_check() {
+        // 空闲状态下子服务都未启动，不算问题
+        let app_core = AppCore::new().await.unwrap();
+        let report = app_core.health_check().await;
+        assert!(!report.in_lobby);
+        assert!(!report.easytier_running);
+        assert_eq!(report.p2p_signaling_port, None);
+        assert!(report.issues.is_empty());
+
+        // 在大厅中但子服务未运行时逐项列出
+        let mut in_lobby = HealthReport { in_lobby: true, ..report };
+        let issues = in_lobby.collect_issues();
+        assert_eq!(issues.len(), 4);
+        assert!(issues[0].contains("EasyTier"));
+
+        in_lobby.easytier_running = true;
+        in_lobby.virtual_ip = Some("10.126.126.2".to_string());
+        in_lobby.p2p_signaling_port = Some(47777);
+        in_lobby.chat_server = Some("10.126.126.2:14540".to_string());
+        in_lobby.share_count = 1;
+        assert_eq!(in_lobby.collect_issues(), vec!["有 1 个共享但文件服务器未监听".to_string()]);
+    }
+
     #[tokio::test]
     async fn test_module_references() {
         // 测试模块引用获取
@@ -0,0 +1,412 @@
+// 联机会话一键巡检（自动医生）
+//
+// 把分散的检查与修复串成一条流水线：按 权限 → 驱动 → 防火墙 → 网络 → 端口 → 服务 的顺序逐项检查，
+// 发现可自动修复的问题（添加防火墙规则、重启卡死的网络、清理残留实例、启动文件/聊天服务）就地尝试修复，
+// 最后返回一份"发现问题 → 已修复 / 需手动处理"的报告。每一步独立执行，单步检查或修复失败不会中断后续步骤。
+// 每完成一步推送 `auto-doctor-progress` 事件。
+
+use std::future::Future;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::modules::app_core::AppState as CoreAppState;
+use crate::modules::error::CommandError;
+use crate::modules::network_service::{ConnectionStatus, NetworkService};
+use crate::modules::tauri_commands::AppState;
+
+/// 巡检项目
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoctorCheck {
+    /// 管理员权限
+    Permission,
+    /// 虚拟网卡驱动
+    Driver,
+    /// 防火墙放行规则
+    Firewall,
+    /// 虚拟网络连接
+    Network,
+    /// 端口占用（残留 EasyTier 实例）
+    Port,
+    /// 文件 / 聊天服务
+    Services,
+}
+
+impl DoctorCheck {
+    /// 巡检顺序：底层问题在前，先修好底层再查依赖它的上层
+    pub const ORDER: [DoctorCheck; 6] = [
+        DoctorCheck::Permission,
+        DoctorCheck::Driver,
+        DoctorCheck::Firewall,
+        DoctorCheck::Network,
+        DoctorCheck::Port,
+        DoctorCheck::Services,
+    ];
+}
+
+/// 检查发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorFinding {
+    /// 问题描述
+    pub problem: String,
+    /// 是否可以自动修复
+    pub fixable: bool,
+}
+
+impl DoctorFinding {
+    /// 可自动修复的问题
+    pub fn fixable(problem: impl Into<String>) -> Self {
+        Self { problem: problem.into(), fixable: true }
+    }
+
+    /// 需要用户手动处理的问题
+    pub fn manual(problem: impl Into<String>) -> Self {
+        Self { problem: problem.into(), fixable: false }
+    }
+}
+
+/// 单项巡检结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoctorOutcome {
+    /// 未发现问题
+    Healthy,
+    /// 发现问题并已自动修复
+    Fixed,
+    /// 发现问题，需要手动处理（不可自动修复或修复失败）
+    NeedsManual,
+    /// 检查本身执行失败
+    CheckFailed,
+}
+
+/// 单项巡检记录
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorStep {
+    /// 巡检项目
+    pub check: DoctorCheck,
+    /// 结果
+    pub outcome: DoctorOutcome,
+    /// 发现的问题
+    pub problem: Option<String>,
+    /// 修复结果或失败原因
+    pub detail: Option<String>,
+}
+
+/// 巡检报告
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    /// 各项结果（按巡检顺序）
+    pub steps: Vec<DoctorStep>,
+    /// 已自动修复的问题数
+    pub fixed: usize,
+    /// 需要手动处理的问题数（含检查失败）
+    pub needs_manual: usize,
+}
+
+/// 巡检中各项目的具体检查与修复操作
+pub trait DoctorDriver {
+    /// 检查一项，`Ok(None)` 表示未发现问题，`Err` 表示检查本身失败
+    fn check(&self, check: DoctorCheck) -> impl Future<Output = Result<Option<DoctorFinding>, String>> + Send;
+    /// 修复一项，返回修复结果说明
+    fn fix(&self, check: DoctorCheck) -> impl Future<Output = Result<String, String>> + Send;
+}
+
+/// 执行一项巡检（检查，必要时修复）
+async fn run_step<D: DoctorDriver>(driver: &D, check: DoctorCheck) -> DoctorStep {
+    let finding = match driver.check(check).await {
+        Ok(None) => {
+            return DoctorStep { check, outcome: DoctorOutcome::Healthy, problem: None, detail: None };
+        }
+        Ok(Some(finding)) => finding,
+        Err(e) => {
+            return DoctorStep { check, outcome: DoctorOutcome::CheckFailed, problem: None, detail: Some(e) };
+        }
+    };
+    if !finding.fixable {
+        return DoctorStep {
+            check,
+            outcome: DoctorOutcome::NeedsManual,
+            problem: Some(finding.problem),
+            detail: None,
+        };
+    }
+    let (outcome, detail) = match driver.fix(check).await {
+        Ok(detail) => (DoctorOutcome::Fixed, detail),
+        Err(e) => (DoctorOutcome::NeedsManual, e),
+    };
+    DoctorStep { check, outcome, problem: Some(finding.problem), detail: Some(detail) }
+}
+
+/// 按 `DoctorCheck::ORDER` 依次执行全部巡检
+///
+/// # 参数
+/// * `driver` - 各项目的具体操作
+/// * `on_step` - 每完成一项时回调
+pub async fn run_auto_doctor<D: DoctorDriver>(driver: &D, mut on_step: impl FnMut(&DoctorStep)) -> DoctorReport {
+    let mut steps = Vec::with_capacity(DoctorCheck::ORDER.len());
+    for check in DoctorCheck::ORDER {
+        let step = run_step(driver, check).await;
+        on_step(&step);
+        steps.push(step);
+    }
+    let count = |outcomes: &[DoctorOutcome]| steps.iter().filter(|s| outcomes.contains(&s.outcome)).count();
+    DoctorReport {
+        fixed: count(&[DoctorOutcome::Fixed]),
+        needs_manual: count(&[DoctorOutcome::NeedsManual, DoctorOutcome::CheckFailed]),
+        steps,
+    }
+}
+
+/// 基于应用状态的巡检操作
+struct AppDoctorDriver {
+    app: tauri::AppHandle,
+}
+
+impl AppDoctorDriver {
+    async fn in_lobby(&self) -> bool {
+        let state = self.app.state::<AppState>();
+        let in_lobby = matches!(state.core.lock().await.get_state().await, CoreAppState::InLobby);
+        in_lobby
+    }
+}
+
+impl DoctorDriver for AppDoctorDriver {
+    async fn check(&self, check: DoctorCheck) -> Result<Option<DoctorFinding>, String> {
+        let state = self.app.state::<AppState>();
+        match check {
+            DoctorCheck::Permission => Ok((!crate::modules::tauri_commands::is_admin().await)
+                .then(|| DoctorFinding::manual("未以管理员身份运行，虚拟网卡可能无法创建，请以管理员身份重启"))),
+            DoctorCheck::Driver => {
+                let dll = crate::modules::resource_manager::ResourceManager::get_wintun_dll_path(&self.app)
+                    .map_err(|e| e.to_string())?;
+                if cfg!(windows) && !dll.exists() {
+                    return Ok(Some(DoctorFinding::manual("缺少 WinTun 驱动文件，请重新安装 MCTier")));
+                }
+                if self.in_lobby().await {
+                    let adapter = crate::modules::network_diagnostics::check_virtual_adapter().await;
+                    if !adapter.success {
+                        return Ok(Some(DoctorFinding::manual(adapter.detail)));
+                    }
+                }
+                Ok(None)
+            }
+            DoctorCheck::Firewall => {
+                let result = crate::modules::network_diagnostics::check_firewall_rules().await;
+                Ok((!result.success).then(|| DoctorFinding::fixable(result.detail)))
+            }
+            DoctorCheck::Network => {
+                if !self.in_lobby().await {
+                    return Ok(None);
+                }
+                let network_service = state.get_network_service();
+                let status = network_service.lock().await.check_connection().await;
+                Ok(match status {
+                    ConnectionStatus::Connected(_) => None,
+                    // 断线重连守护任务正在处理，不重复拉起
+                    ConnectionStatus::Reconnecting => Some(DoctorFinding::manual("虚拟网络正在自动重连，请稍候")),
+                    ConnectionStatus::Error(e) => Some(DoctorFinding::fixable(format!("虚拟网络异常: {}", e))),
+                    ConnectionStatus::Disconnected | ConnectionStatus::Connecting => {
+                        Some(DoctorFinding::fixable("已在大厅中但虚拟网络未连接"))
+                    }
+                })
+            }
+            DoctorCheck::Port => {
+                // 大厅中的 easytier-core 是本机自己的实例
+                if self.in_lobby().await {
+                    return Ok(None);
+                }
+                let pids = NetworkService::list_easytier_processes().await;
+                Ok((!pids.is_empty()).then(|| {
+                    DoctorFinding::fixable(format!("发现 {} 个残留的 EasyTier 实例占用端口", pids.len()))
+                }))
+            }
+            DoctorCheck::Services => {
+                if !self.in_lobby().await {
+                    return Ok(None);
+                }
+                let file_transfer = state.get_file_transfer();
+                let chat_service = state.get_chat_service();
+                let mut stopped = Vec::new();
+                {
+                    let ft = file_transfer.lock().await;
+                    // 文件服务器按需启动：没有共享时不运行是正常的
+                    if !ft.get_shares().is_empty() && !ft.is_running() {
+                        stopped.push("文件共享服务");
+                    }
+                }
+                if !chat_service.lock().await.is_running() {
+                    stopped.push("聊天服务");
+                }
+                Ok((!stopped.is_empty()).then(|| DoctorFinding::fixable(format!("{}未运行", stopped.join("、")))))
+            }
+        }
+    }
+
+    async fn fix(&self, check: DoctorCheck) -> Result<String, String> {
+        let state = self.app.state::<AppState>();
+        match check {
+            DoctorCheck::Firewall => crate::modules::tauri_commands::add_firewall_rules(self.app.clone()).await.map_err(|e| e.to_string()),
+            DoctorCheck::Network => {
+                let core = state.core.lock().await;
+                let network_service = core.get_network_service();
+                let file_transfer = core.get_file_transfer();
+                let chat_service = core.get_chat_service();
+                drop(core);
+                let outcome = network_service.lock().await.relaunch(&self.app).await.map_err(|e| e.to_string())?;
+                let ip = outcome.virtual_ip;
+                file_transfer.lock().await.set_virtual_ip(ip.clone());
+                chat_service.lock().await.set_virtual_ip(ip.clone());
+                crate::modules::peer_access::update_local_ip(&ip);
+                let _ = crate::modules::tauri_events::emit_network_status_change(&self.app, ConnectionStatus::Connected(ip.clone()));
+                Ok(format!("已重启虚拟网络，虚拟 IP: {}", ip))
+            }
+            DoctorCheck::Port => {
+                NetworkService::cleanup_orphan_processes().await;
+                let remaining = NetworkService::list_easytier_processes().await;
+                if remaining.is_empty() {
+                    Ok("已清理残留的 EasyTier 实例".to_string())
+                } else {
+                    Err(format!("仍有 {} 个 EasyTier 实例无法结束，请在任务管理器中手动结束", remaining.len()))
+                }
+            }
+            DoctorCheck::Services => {
+                let core = state.core.lock().await;
+                let file_transfer = core.get_file_transfer();
+                let chat_service = core.get_chat_service();
+                drop(core);
+                let mut errors = Vec::new();
+                {
+                    let ft = file_transfer.lock().await;
+                    if !ft.get_shares().is_empty() && !ft.is_running() {
+                        if let Err(e) = ft.start_server().await {
+                            errors.push(format!("文件共享服务启动失败: {}", e));
+                        }
+                    }
+                }
+                {
+                    let chat = chat_service.lock().await;
+                    if !chat.is_running() {
+                        if let Err(e) = chat.start_server().await {
+                            errors.push(format!("聊天服务启动失败: {}", e));
+                        }
+                    }
+                }
+                if errors.is_empty() {
+                    Ok("已重新启动服务".to_string())
+                } else {
+                    Err(errors.join("；"))
+                }
+            }
+            DoctorCheck::Permission | DoctorCheck::Driver => Err("该问题无法自动修复".to_string()),
+        }
+    }
+}
+
+/// 一键巡检并自动修复联机环境
+///
+/// 每完成一项推送 `auto-doctor-progress` 事件（内容为单项记录）。
+///
+/// # 返回
+/// * `Ok(DoctorReport)` - 巡检报告
+#[tauri::command]
+pub async fn auto_doctor(app: tauri::AppHandle) -> Result<DoctorReport, CommandError> {
+    log::info!("🩺 开始一键巡检");
+    let driver = AppDoctorDriver { app: app.clone() };
+    let report = run_auto_doctor(&driver, |step| {
+        log::info!("🩺 {:?} -> {:?} {:?} {:?}", step.check, step.outcome, step.problem, step.detail);
+        let _ = app.emit("auto-doctor-progress", step);
+    })
+    .await;
+    log::info!("🩺 巡检完成：已修复 {} 项，需手动处理 {} 项", report.fixed, report.needs_manual);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex as StdMutex;
+
+    /// 按预设结果执行检查与修复，并记录调用顺序
+    #[derive(Default)]
+    struct ScriptedDriver {
+        findings: HashMap<DoctorCheck, Result<Option<DoctorFinding>, String>>,
+        fixes: HashMap<DoctorCheck, Result<String, String>>,
+        calls: StdMutex<Vec<String>>,
+    }
+
+    impl DoctorDriver for ScriptedDriver {
+        async fn check(&self, check: DoctorCheck) -> Result<Option<DoctorFinding>, String> {
+            self.calls.lock().unwrap().push(format!("check {:?}", check));
+            self.findings.get(&check).cloned().unwrap_or(Ok(None))
+        }
+
+        async fn fix(&self, check: DoctorCheck) -> Result<String, String> {
+            self.calls.lock().unwrap().push(format!("fix {:?}", check));
+            self.fixes.get(&check).cloned().unwrap_or_else(|| Ok("ok".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checks_run_in_order_and_fix_follows_its_check() {
+        let mut driver = ScriptedDriver::default();
+        driver.findings.insert(DoctorCheck::Firewall, Ok(Some(DoctorFinding::fixable("未找到防火墙规则"))));
+        driver.findings.insert(DoctorCheck::Port, Ok(Some(DoctorFinding::fixable("残留实例"))));
+
+        let mut progress = Vec::new();
+        let report = run_auto_doctor(&driver, |step| progress.push(step.check)).await;
+
+        assert_eq!(progress, DoctorCheck::ORDER.to_vec());
+        assert_eq!(
+            *driver.calls.lock().unwrap(),
+            vec![
+                "check Permission",
+                "check Driver",
+                "check Firewall",
+                "fix Firewall",
+                "check Network",
+                "check Port",
+                "fix Port",
+                "check Services",
+            ]
+        );
+        assert_eq!((report.fixed, report.needs_manual), (2, 0));
+        assert_eq!(report.steps[2].outcome, DoctorOutcome::Fixed);
+        assert_eq!(report.steps[2].problem.as_deref(), Some("未找到防火墙规则"));
+    }
+
+    #[tokio::test]
+    async fn test_partial_failures_do_not_stop_later_steps() {
+        let mut driver = ScriptedDriver::default();
+        // 不可自动修复：不调用 fix
+        driver.findings.insert(DoctorCheck::Permission, Ok(Some(DoctorFinding::manual("未以管理员身份运行"))));
+        // 检查本身失败
+        driver.findings.insert(DoctorCheck::Driver, Err("读取驱动失败".to_string()));
+        // 修复失败
+        driver.findings.insert(DoctorCheck::Network, Ok(Some(DoctorFinding::fixable("虚拟网络异常"))));
+        driver.fixes.insert(DoctorCheck::Network, Err("重启失败".to_string()));
+        driver.findings.insert(DoctorCheck::Services, Ok(Some(DoctorFinding::fixable("聊天服务未运行"))));
+
+        let report = run_auto_doctor(&driver, |_| {}).await;
+        let outcomes: Vec<DoctorOutcome> = report.steps.iter().map(|s| s.outcome).collect();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                DoctorOutcome::NeedsManual,
+                DoctorOutcome::CheckFailed,
+                DoctorOutcome::Healthy,
+                DoctorOutcome::NeedsManual,
+                DoctorOutcome::Healthy,
+                DoctorOutcome::Fixed,
+            ]
+        );
+        assert!(!driver.calls.lock().unwrap().contains(&"fix Permission".to_string()));
+        assert_eq!(report.steps[1].detail.as_deref(), Some("读取驱动失败"));
+        assert_eq!(report.steps[3].detail.as_deref(), Some("重启失败"));
+        assert_eq!((report.fixed, report.needs_manual), (1, 3));
+    }
+}
@@ -4,7 +4,8 @@
  * 不依赖中心服务器，直接在虚拟局域网中传输
  */
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -23,8 +24,22 @@ use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
 use tower_http::cors::CorsLayer;
 
-const CHAT_SERVER_PORT: u16 = 14540; // 聊天服务端口
+use crate::modules::input_limits;
+
+pub const CHAT_SERVER_PORT: u16 = crate::modules::service_ports::DEFAULT_CHAT_PORT; // 默认聊天服务端口，可在端口配置中覆盖
 const MAX_MESSAGES_PER_PLAYER: usize = 1000; // 每个玩家最多保存1000条消息
+/// 聊天图片默认大小上限（5MB）
+pub const DEFAULT_CHAT_IMAGE_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// 聊天图片大小上限允许设置的最大值（50MB）
+pub const MAX_CHAT_IMAGE_MAX_BYTES: u64 = 50 * 1024 * 1024;
+const CHAT_IMAGE_MAX_DIMENSION: u32 = 1920; // 重编码后图片最长边
+const CHAT_IMAGE_JPEG_QUALITY: u8 = 80; // 重编码 JPEG 质量
+const MAX_REACTION_EMOJI_LEN: usize = 32; // 单个表情回应的最大字节数
+const MAX_CHAT_MENTIONS: usize = 32; // 单条消息最多 @ 的玩家数
+const MAX_CHAT_REF_ID_LEN: usize = 128; // 回复的消息 ID / 被 @ 的玩家 ID 的最大字节数
+
+/// 收到 @ 本机玩家的消息时推送的事件
+pub const CHAT_MENTION_EVENT: &str = "chat-mention";
 
 /// 聊天消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +51,17 @@ pub struct ChatMessage {
     pub message_type: MessageType,
     pub timestamp: u64,
     pub image_data: Option<Vec<u8>>, // 图片数据（Base64编码后的字节）
+    /// 本机逻辑时钟：消息进入本机消息队列时分配的递增序号，与发送方的系统时钟无关
+    ///
+    /// 对外提供时是本机分配的序号，对方按此推进拉取游标；旧版本没有该字段，反序列化为 0。
+    #[serde(default)]
+    pub seq: u64,
+    /// 回复（引用）的消息 ID；旧版本没有该字段
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// 被 @ 的玩家 ID；旧版本没有该字段
+    #[serde(default)]
+    pub mentions: Vec<String>,
 }
 
 /// 消息类型
@@ -54,12 +80,79 @@ pub enum MessageType {
     Todo,
     /// 共享白板（控制消息，content 为单笔画/清空指令 JSON）
     Whiteboard,
+    /// 系统通知（加入 / 踢出 / 创建者变更等），各端本地生成，不经网络收发
+    System,
+}
+
+/// 系统消息的发送者 ID
+pub const SYSTEM_PLAYER_ID: &str = "system";
+
+/// 进程内消息 ID 计数器
+static MESSAGE_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl ChatMessage {
+    /// 生成消息 ID：`msg-{玩家}-{毫秒}-{计数}-{随机数}`
+    ///
+    /// 同一毫秒内连续发送时靠进程内递增计数区分，随机后缀避免不同设备使用相同玩家 ID 时冲突。
+    pub fn new_id(player_id: &str) -> String {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let counter = MESSAGE_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+        format!("msg-{}-{}-{}-{:08x}", player_id, millis, counter, rand::random::<u32>())
+    }
+
+    /// 创建一条系统消息
+    pub fn system(content: String) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let counter = MESSAGE_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self {
+            id: format!("sys-{}-{}", now.as_millis(), counter),
+            player_id: SYSTEM_PLAYER_ID.to_string(),
+            player_name: "系统".to_string(),
+            content,
+            message_type: MessageType::System,
+            timestamp: now.as_secs(),
+            image_data: None,
+            seq: 0,
+            reply_to: None,
+            mentions: Vec::new(),
+        }
+    }
+
+    /// 是否只在本机可见（系统消息不提供给其他成员拉取）
+    pub fn is_local_only(&self) -> bool {
+        self.message_type == MessageType::System
+    }
+
+    /// 是否 @ 了指定玩家（自己发送的消息不算）
+    pub fn mentions_player(&self, player_id: &str) -> bool {
+        self.player_id != player_id && self.mentions.iter().any(|id| id == player_id)
+    }
+}
+
+/// 清理回复与 @ 引用：过长的 ID 丢弃，@ 列表去重并限制数量
+///
+/// # 返回
+/// (回复的消息 ID, 被 @ 的玩家 ID)
+pub fn sanitize_references(reply_to: Option<String>, mentions: Vec<String>) -> (Option<String>, Vec<String>) {
+    let valid = |id: &String| !id.is_empty() && id.len() <= MAX_CHAT_REF_ID_LEN;
+    let reply_to = reply_to.filter(valid);
+    let mut seen = HashSet::new();
+    let mentions = mentions
+        .into_iter()
+        .filter(valid)
+        .filter(|id| seen.insert(id.clone()))
+        .take(MAX_CHAT_MENTIONS)
+        .collect();
+    (reply_to, mentions)
 }
 
 /// 获取消息请求参数
 #[derive(Debug, Deserialize)]
 pub struct GetMessagesQuery {
-    pub since: Option<u64>, // 获取此时间戳之后的消息
+    pub since: Option<u64>, // 获取此时间戳之后的消息（旧版本使用，受双方时钟偏差影响）
+    /// 获取本机序号大于该值的消息
+    #[serde(default)]
+    pub after_seq: Option<u64>,
 }
 
 /// 发送消息请求
@@ -73,18 +166,259 @@ pub struct SendMessageRequest {
     pub content: String,
     pub message_type: MessageType,
     pub image_data: Option<Vec<u8>>,
+    /// 回复（引用）的消息 ID
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// 被 @ 的玩家 ID
+    #[serde(default)]
+    pub mentions: Vec<String>,
+}
+
+/// 聊天图片处理错误
+#[derive(Debug, thiserror::Error)]
+pub enum ChatImageError {
+    #[error("图片消息缺少图片数据")]
+    Missing,
+
+    #[error("图片过大: {size} 字节，上限 {max} 字节")]
+    TooLarge { size: u64, max: u64 },
+
+    #[error("不是有效的图片数据: {0}")]
+    InvalidImage(String),
+
+    #[error("图片压缩失败: {0}")]
+    EncodeFailed(String),
+}
+
+/// 校验并压缩待发送的聊天图片
+///
+/// 超过大小上限或无法解码的数据直接拒绝；有效图片缩放到最长边不超过 1920px 后统一重编码为 JPEG
+/// （动图只保留第一帧，透明通道会被丢弃）。
+///
+/// # 参数
+/// * `data` - 原始图片数据
+/// * `max_bytes` - 原始数据大小上限
+///
+/// # 返回
+/// 重编码后的 JPEG 数据
+pub fn prepare_chat_image(data: &[u8], max_bytes: u64) -> Result<Vec<u8>, ChatImageError> {
+    if data.is_empty() {
+        return Err(ChatImageError::Missing);
+    }
+    let size = data.len() as u64;
+    if size > max_bytes {
+        return Err(ChatImageError::TooLarge { size, max: max_bytes });
+    }
+
+    let mut image = image::load_from_memory(data).map_err(|e| ChatImageError::InvalidImage(e.to_string()))?;
+    if image.width().max(image.height()) > CHAT_IMAGE_MAX_DIMENSION {
+        image = image.resize(
+            CHAT_IMAGE_MAX_DIMENSION,
+            CHAT_IMAGE_MAX_DIMENSION,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+
+    let rgb = image.to_rgb8();
+    let mut output = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, CHAT_IMAGE_JPEG_QUALITY)
+        .encode_image(&rgb)
+        .map_err(|e| ChatImageError::EncodeFailed(e.to_string()))?;
+
+    log::info!(
+        "🖼️ [ChatService] 图片已压缩: {} 字节 -> {} 字节 ({}x{})",
+        size,
+        output.len(),
+        rgb.width(),
+        rgb.height()
+    );
+    Ok(output)
+}
+
+/// 单个表情在某条消息上的回应统计
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: usize,
+    /// 回应过该表情的玩家 ID
+    pub player_ids: Vec<String>,
+}
+
+/// 消息表情回应聚合
+///
+/// 同一玩家对同一消息的同一表情只计一次；只保留最近若干条消息的回应，与消息队列上限一致。
+#[derive(Debug, Default)]
+pub struct MessageReactions {
+    /// message_id -> emoji -> 回应的玩家
+    reactions: HashMap<String, BTreeMap<String, BTreeSet<String>>>,
+    /// 按首次回应时间排列的消息 ID，用于淘汰最旧的记录
+    order: VecDeque<String>,
+}
+
+impl MessageReactions {
+    /// 应用一次回应增删
+    ///
+    /// # 返回
+    /// 计数发生变化时返回该消息最新的回应统计，重复添加或删除不存在的回应时返回 None
+    pub fn apply(&mut self, message_id: &str, emoji: &str, player_id: &str, added: bool) -> Option<Vec<ReactionCount>> {
+        if message_id.is_empty() || emoji.is_empty() || emoji.len() > MAX_REACTION_EMOJI_LEN {
+            log::warn!("⚠️ [ChatService] 忽略无效的表情回应: message={} emoji={:?}", message_id, emoji);
+            return None;
+        }
+
+        let changed = if added {
+            if !self.reactions.contains_key(message_id) {
+                self.order.push_back(message_id.to_string());
+                while self.order.len() > MAX_MESSAGES_PER_PLAYER {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.reactions.remove(&oldest);
+                    }
+                }
+            }
+            self.reactions
+                .entry(message_id.to_string())
+                .or_default()
+                .entry(emoji.to_string())
+                .or_default()
+                .insert(player_id.to_string())
+        } else {
+            let emojis = self.reactions.get_mut(message_id)?;
+            let removed = emojis.get_mut(emoji).is_some_and(|players| players.remove(player_id));
+            emojis.retain(|_, players| !players.is_empty());
+            removed
+        };
+
+        changed.then(|| self.counts(message_id))
+    }
+
+    /// 获取某条消息的回应统计（按表情排序）
+    pub fn counts(&self, message_id: &str) -> Vec<ReactionCount> {
+        self.reactions
+            .get(message_id)
+            .map(|emojis| {
+                emojis
+                    .iter()
+                    .map(|(emoji, players)| ReactionCount {
+                        emoji: emoji.clone(),
+                        count: players.len(),
+                        player_ids: players.iter().cloned().collect(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 清空所有回应
+    pub fn clear(&mut self) {
+        self.reactions.clear();
+        self.order.clear();
+    }
+}
+
+/// 本机消息队列
+///
+/// 按 ID 去重，并为每条进入队列的消息分配递增序号作为逻辑时钟。
+/// 增量拉取按序号而不是发送方时间戳过滤：各端系统时钟可能相差数秒甚至更多，
+/// 按时间戳过滤会漏掉时钟偏慢的成员发来的消息。
+#[derive(Debug)]
+pub struct MessageLog {
+    messages: VecDeque<ChatMessage>,
+    ids: HashSet<String>,
+    /// 最近分配的序号；清空队列时不重置，已发出的游标始终有效
+    last_seq: u64,
+}
+
+impl Default for MessageLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageLog {
+    /// 创建空队列
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            ids: HashSet::new(),
+            last_seq: 0,
+        }
+    }
+
+    /// 加入一条消息并分配序号
+    ///
+    /// # 返回
+    /// 新消息返回分配了序号的副本；ID 已存在（重复投递、重试、多端同步）时返回 None
+    pub fn push(&mut self, mut message: ChatMessage) -> Option<ChatMessage> {
+        if !self.ids.insert(message.id.clone()) {
+            return None;
+        }
+        self.last_seq += 1;
+        message.seq = self.last_seq;
+        self.messages.push_back(message.clone());
+
+        // 限制消息数量
+        while self.messages.len() > MAX_MESSAGES_PER_PLAYER {
+            if let Some(oldest) = self.messages.pop_front() {
+                self.ids.remove(&oldest.id);
+            }
+        }
+        Some(message)
+    }
+
+    /// 按 ID 查找消息
+    pub fn get(&self, id: &str) -> Option<&ChatMessage> {
+        if !self.ids.contains(id) {
+            return None;
+        }
+        self.messages.iter().find(|msg| msg.id == id)
+    }
+
+    /// 最近分配的序号
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// 序号大于 `after_seq` 的消息（按进入队列的顺序）
+    pub fn after(&self, after_seq: u64) -> impl Iterator<Item = &ChatMessage> {
+        self.messages.iter().filter(move |msg| msg.seq > after_seq)
+    }
+
+    /// 队列中的消息数
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// 清空消息（序号继续递增）
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.ids.clear();
+    }
 }
 
 /// 聊天服务状态
 pub struct ChatService {
     /// 本地消息队列（保存自己发送的消息）
-    local_messages: Arc<RwLock<VecDeque<ChatMessage>>>,
+    local_messages: Arc<RwLock<MessageLog>>,
+    /// 各成员的拉取游标：peer IP -> 已收到的对方最大序号
+    peer_cursors: Arc<RwLock<HashMap<String, u64>>>,
     /// 虚拟IP地址
     virtual_ip: Arc<RwLock<Option<String>>>,
     /// 服务器句柄
     server_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    /// 服务器实际监听的地址（停止时用于确认端口已释放）
+    bound_addr: Arc<RwLock<Option<std::net::SocketAddr>>>,
     /// 消息广播通道（用于SSE推送）
     message_tx: broadcast::Sender<ChatMessage>,
+    /// 消息表情回应
+    reactions: Arc<RwLock<MessageReactions>>,
+    /// @ 提及通知
+    mentions: MentionWatch,
 }
 
 impl ChatService {
@@ -93,10 +427,14 @@ impl ChatService {
         let (tx, _rx) = broadcast::channel(500);
         
         Self {
-            local_messages: Arc::new(RwLock::new(VecDeque::new())),
+            local_messages: Arc::new(RwLock::new(MessageLog::new())),
+            peer_cursors: Arc::new(RwLock::new(HashMap::new())),
             virtual_ip: Arc::new(RwLock::new(None)),
             server_handle: Arc::new(RwLock::new(None)),
+            bound_addr: Arc::new(RwLock::new(None)),
             message_tx: tx,
+            reactions: Arc::new(RwLock::new(MessageReactions::default())),
+            mentions: MentionWatch::new(),
         }
     }
 
@@ -111,6 +449,11 @@ impl ChatService {
         self.virtual_ip.read().clone()
     }
 
+    /// 设置本机玩家 ID（用于识别 @ 本机的消息）
+    pub fn set_local_player_id(&self, player_id: String) {
+        *self.mentions.local_player_id.write() = Some(player_id);
+    }
+
     /// 启动HTTP聊天服务器
     pub async fn start_server(&self) -> Result<(), Box<dyn std::error::Error>> {
         // 【修复】启动前先停止可能存在的旧实例，避免端口占用与任务句柄泄漏（重进大厅场景）
@@ -148,8 +491,8 @@ impl ChatService {
             }
         }
 
-        let addr = format!("{}:{}", virtual_ip, CHAT_SERVER_PORT);
-        log::info!("📍 [ChatService] 聊天服务器将监听虚拟IP: {}", addr);
+        let port = crate::modules::service_ports::configured_ports().chat;
+        log::info!("📍 [ChatService] 聊天服务器将监听虚拟IP: {}:{}", virtual_ip, port);
 
         let local_messages = self.local_messages.clone();
         let message_tx = self.message_tx.clone();
@@ -159,30 +502,38 @@ impl ChatService {
             .route("/api/chat/messages", get(get_messages))
             .route("/api/chat/send", post(send_message))
             .route("/api/chat/stream", get(stream_messages)) // 新增SSE端点
+            .layer(axum::middleware::from_fn_with_state(
+                crate::modules::http_auth::AuthPolicy::lobby_only(),
+                crate::modules::http_auth::require_auth,
+            ))
             .layer(CorsLayer::permissive())
             .with_state(AppState {
                 local_messages: local_messages.clone(),
                 message_tx: message_tx.clone(),
+                mentions: self.mentions.clone(),
             });
 
         log::info!("🚀 [ChatService] 正在启动聊天服务器...");
 
-        // 绑定端口
-        let listener = match tokio::net::TcpListener::bind(&addr).await {
-            Ok(l) => {
-                log::info!("✅ [ChatService] 成功绑定端口 {}", CHAT_SERVER_PORT);
-                l
+        // 绑定端口（被占用时顺延到后续端口）
+        let (listener, port) = match crate::modules::service_ports::bind_tcp_with_fallback(&virtual_ip, port).await {
+            Ok((l, actual)) => {
+                log::info!("✅ [ChatService] 成功绑定端口 {}", actual);
+                crate::modules::service_ports::record_local_port(crate::modules::service_ports::ServiceKind::Chat, actual);
+                (l, actual)
             }
             Err(e) => {
-                log::error!("❌ [ChatService] 绑定端口失败: {} - 错误: {}", CHAT_SERVER_PORT, e);
+                log::error!("❌ [ChatService] 绑定端口失败: {} - 错误: {}", port, e);
                 return Err(format!("绑定端口失败: {}", e).into());
             }
         };
 
+        let bound_addr = listener.local_addr()?;
+
         // 启动服务器
         let server_task = tokio::spawn(async move {
             log::info!("🌐 [ChatService] 聊天服务器开始监听请求...");
-            if let Err(e) = axum::serve(listener, app).await {
+            if let Err(e) = crate::modules::http_tls::serve(listener, app).await {
                 log::error!("❌ [ChatService] 服务器运行错误: {}", e);
             } else {
                 log::info!("🛑 [ChatService] 聊天服务器已正常停止");
@@ -190,9 +541,10 @@ impl ChatService {
         });
 
         *self.server_handle.write() = Some(server_task);
+        *self.bound_addr.write() = Some(bound_addr);
 
         log::info!("✅ [ChatService] 聊天服务器启动成功！");
-        log::info!("📡 [ChatService] 监听地址: {}:{}（仅虚拟网卡）", virtual_ip, CHAT_SERVER_PORT);
+        log::info!("📡 [ChatService] 监听地址: {}:{}（仅虚拟网卡）", virtual_ip, port);
         log::info!("📡 [ChatService] 虚拟IP: {}", virtual_ip);
         
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
@@ -203,8 +555,10 @@ impl ChatService {
 
     /// 停止聊天服务器
     pub async fn stop_server(&self) {
-        if let Some(handle) = self.server_handle.write().take() {
-            handle.abort();
+        let handle = self.server_handle.write().take();
+        let bound_addr = self.bound_addr.write().take();
+        if let Some(handle) = handle {
+            crate::modules::service_ports::shutdown_tcp_service(handle, bound_addr).await;
             log::info!("🛑 [ChatService] 聊天服务器已停止");
         }
     }
@@ -214,47 +568,132 @@ impl ChatService {
         self.server_handle.read().is_some()
     }
 
-    /// 添加本地消息
+    /// 服务器实际监听的地址（未运行时为 None）
+    pub fn bound_addr(&self) -> Option<std::net::SocketAddr> {
+        *self.bound_addr.read()
+    }
+
+    /// 添加本地消息（ID 重复的消息忽略）
     pub fn add_local_message(&self, message: ChatMessage) {
-        let mut messages = self.local_messages.write();
-        messages.push_back(message.clone());
-        
-        // 限制消息数量
-        while messages.len() > MAX_MESSAGES_PER_PLAYER {
-            messages.pop_front();
-        }
-        
+        let Some(message) = self.local_messages.write().push(message) else {
+            log::debug!("[ChatService] 忽略重复消息");
+            return;
+        };
+
         // 广播消息到所有SSE订阅者
         let _ = self.message_tx.send(message);
     }
 
     /// 获取本地消息
-    pub fn get_local_messages(&self, since: Option<u64>) -> Vec<ChatMessage> {
-        let messages = self.local_messages.read();
-        
-        if let Some(timestamp) = since {
-            messages
-                .iter()
-                .filter(|msg| msg.timestamp > timestamp)
-                .cloned()
-                .collect()
-        } else {
-            messages.iter().cloned().collect()
+    ///
+    /// # 参数
+    /// * `after_seq` - 只返回本机序号大于该值的消息，调用方以返回消息中最大的 `seq` 作为下次的游标
+    pub fn get_local_messages(&self, after_seq: Option<u64>) -> Vec<ChatMessage> {
+        self.local_messages
+            .read()
+            .after(after_seq.unwrap_or(0))
+            .cloned()
+            .collect()
+    }
+
+    /// 获取向某个成员增量拉取时使用的游标
+    pub fn peer_cursor(&self, peer_ip: &str) -> Option<u64> {
+        self.peer_cursors.read().get(peer_ip).copied()
+    }
+
+    /// 合并从某个成员拉取到的消息
+    ///
+    /// 消息按 ID 去重后加入本机队列（分配本机序号，不再广播，@ 本机的仍会通知），并把该成员的游标推进到返回消息中最大的序号。
+    /// 对方重启后序号从头开始，会返回游标之前的消息，此时游标随之回退。
+    ///
+    /// # 返回
+    /// 新加入的消息数
+    pub fn merge_peer_messages(&self, peer_ip: &str, messages: Vec<ChatMessage>) -> usize {
+        if let Some(max_seq) = messages.iter().map(|msg| msg.seq).max() {
+            self.peer_cursors.write().insert(peer_ip.to_string(), max_seq);
         }
+        let mut log = self.local_messages.write();
+        messages
+            .into_iter()
+            // 系统消息只能由本机生成，忽略其他成员返回的
+            .filter(|msg| !msg.is_local_only())
+            .filter_map(|msg| log.push(msg))
+            .inspect(|msg| self.mentions.notify(msg))
+            .count()
+    }
+
+    /// 生成一条系统消息并加入本地消息队列
+    pub fn add_system_message(&self, content: String) -> ChatMessage {
+        let message = ChatMessage::system(content);
+        log::info!("📢 [ChatService] 系统消息: {}", message.content);
+        self.add_local_message(message.clone());
+        message
+    }
+
+    /// 订阅消息广播（本地发送与收到的消息都会推送）
+    pub fn subscribe(&self) -> broadcast::Receiver<ChatMessage> {
+        self.message_tx.subscribe()
+    }
+
+    /// 订阅 @ 本机玩家的消息（HTTP 推送与增量拉取收到的都会通知，同一条消息只通知一次）
+    pub fn subscribe_mentions(&self) -> broadcast::Receiver<ChatMessage> {
+        self.mentions.tx.subscribe()
+    }
+
+    /// 应用表情回应增删
+    ///
+    /// # 返回
+    /// 计数发生变化时返回该消息最新的回应统计
+    pub fn apply_reaction(&self, message_id: &str, emoji: &str, player_id: &str, added: bool) -> Option<Vec<ReactionCount>> {
+        self.reactions.write().apply(message_id, emoji, player_id, added)
     }
 
     /// 清空本地消息
     pub fn clear_local_messages(&self) {
         self.local_messages.write().clear();
+        self.peer_cursors.write().clear();
+        self.reactions.write().clear();
         log::info!("🗑️ [ChatService] 已清空本地消息");
     }
 }
 
+/// @ 提及通知：新收到的消息 @ 了本机玩家时推送给订阅者
+#[derive(Clone)]
+struct MentionWatch {
+    /// 本机玩家 ID
+    local_player_id: Arc<RwLock<Option<String>>>,
+    tx: broadcast::Sender<ChatMessage>,
+}
+
+impl MentionWatch {
+    fn new() -> Self {
+        let (tx, _rx) = broadcast::channel(64);
+        Self {
+            local_player_id: Arc::new(RwLock::new(None)),
+            tx,
+        }
+    }
+
+    /// 新消息 @ 了本机玩家时通知
+    fn notify(&self, message: &ChatMessage) {
+        let mentioned = self
+            .local_player_id
+            .read()
+            .as_deref()
+            .is_some_and(|id| message.mentions_player(id));
+        if mentioned {
+            log::info!("🔔 [ChatService] {} 在消息中 @ 了你", message.player_name);
+            let _ = self.tx.send(message.clone());
+        }
+    }
+}
+
 /// Axum 应用状态
 #[derive(Clone)]
 struct AppState {
-    local_messages: Arc<RwLock<VecDeque<ChatMessage>>>,
+    local_messages: Arc<RwLock<MessageLog>>,
     message_tx: broadcast::Sender<ChatMessage>,
+    mentions: MentionWatch,
 }
 
 /// 获取消息列表
@@ -263,16 +702,15 @@ async fn get_messages(
     Query(params): Query<GetMessagesQuery>,
 ) -> Json<Vec<ChatMessage>> {
     let messages = state.local_messages.read();
-    
-    let result: Vec<ChatMessage> = if let Some(since) = params.since {
-        messages
-            .iter()
-            .filter(|msg| msg.timestamp > since)
-            .cloned()
-            .collect()
-    } else {
-        messages.iter().cloned().collect()
-    };
+
+    // 游标超过本机最新序号说明本机重启过，序号已重新开始，返回全部消息让对方重新同步
+    let after_seq = params.after_seq.filter(|seq| *seq <= messages.last_seq()).unwrap_or(0);
+    let result: Vec<ChatMessage> = messages
+        .after(after_seq)
+        .filter(|msg| !msg.is_local_only())
+        .filter(|msg| params.since.is_none_or(|since| msg.timestamp > since))
+        .cloned()
+        .collect();
     
     log::info!("📋 [ChatService] 收到获取消息请求，返回 {} 条消息", result.len());
     
@@ -312,39 +750,68 @@ fn rate_limit_allow(player_id: &str) -> bool {
     true
 }
 
+/// 按消息类型限制内容长度
+///
+/// 文本消息超长时截断；控制消息的内容是 JSON，截断会破坏结构，超过字节上限时返回 `None` 表示拒绝。
+fn limit_message_content(message_type: &MessageType, content: String) -> Option<String> {
+    match message_type {
+        MessageType::Text | MessageType::Image | MessageType::Announce | MessageType::System => {
+            Some(input_limits::clamp_remote("聊天消息", content, input_limits::MAX_CHAT_MESSAGE_CHARS))
+        }
+        _ => (content.len() <= input_limits::MAX_CONTROL_MESSAGE_BYTES).then_some(content),
+    }
+}
+
 /// 发送消息（接收其他玩家发送的消息）
 async fn send_message(
     State(state): State<AppState>,
     Json(req): Json<SendMessageRequest>,
 ) -> Result<Json<ChatMessage>, StatusCode> {
+    // 系统消息只能由本机生成
+    if req.message_type == MessageType::System {
+        log::warn!("⚠️ [ChatService] 拒绝玩家 {} 发送的系统消息", req.player_id);
+        return Err(StatusCode::FORBIDDEN);
+    }
     // 限流：防止恶意成员刷屏（控制类消息如 voicegroup 不计入更严格限制，这里统一按 player 限流）
     if !req.player_id.is_empty() && !rate_limit_allow(&req.player_id) {
         log::warn!("⚠️ [ChatService] 玩家 {} 发送过于频繁，已限流", req.player_id);
         return Err(StatusCode::TOO_MANY_REQUESTS);
     }
-    log::info!("💬 [ChatService] 收到消息: {} - {}", req.player_name, req.content);
+    let content = limit_message_content(&req.message_type, req.content).ok_or_else(|| {
+        log::warn!("⚠️ [ChatService] 玩家 {} 发送的控制消息过大，已拒绝", req.player_id);
+        StatusCode::PAYLOAD_TOO_LARGE
+    })?;
+    let player_name = input_limits::clamp_remote("玩家名称", req.player_name, input_limits::MAX_PLAYER_NAME_CHARS);
+    let (reply_to, mentions) = sanitize_references(req.reply_to, req.mentions);
+    log::info!("💬 [ChatService] 收到消息: {} - {}", player_name, content);
     
     let message = ChatMessage {
-        id: req.id.clone().unwrap_or_else(|| format!("msg-{}-{}", req.player_id, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis())),
+        id: req.id.clone().unwrap_or_else(|| ChatMessage::new_id(&req.player_id)),
         player_id: req.player_id,
-        player_name: req.player_name,
-        content: req.content,
+        player_name,
+        content,
         message_type: req.message_type,
         timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
         image_data: req.image_data,
+        seq: 0,
+        reply_to,
+        mentions,
     };
     
-    // 保存到本地消息队列
+    // 保存到本地消息队列；发送方超时重试会带着同一 ID 再次投递，直接返回已保存的消息，不重复广播
     let mut messages = state.local_messages.write();
-    messages.push_back(message.clone());
-    
-    // 限制消息数量
-    while messages.len() > MAX_MESSAGES_PER_PLAYER {
-        messages.pop_front();
+    if let Some(existing) = messages.get(&message.id) {
+        log::debug!("[ChatService] 忽略重复投递的消息: {}", message.id);
+        return Ok(Json(existing.clone()));
     }
+    let Some(message) = messages.push(message) else {
+        return Err(StatusCode::CONFLICT);
+    };
+    drop(messages);
     
     // 广播消息到所有SSE订阅者
     let _ = state.message_tx.send(message.clone());
+    state.mentions.notify(&message);
     
     Ok(Json(message))
 }
@@ -360,6 +827,8 @@ async fn stream_messages(
     
     let stream = stream.filter_map(|result| {
         match result {
+            // 系统消息通过 Tauri 事件推送给本机前端，不经 HTTP 对外提供
+            Ok(message) if message.is_local_only() => None,
             Ok(message) => {
                 // 将消息序列化为JSON
                 match serde_json::to_string(&message) {
@@ -383,3 +852,344 @@ async fn stream_messages(
             .text("keep-alive")
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([30, 120, 200, 255]));
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_reaction_counts_each_player_once() {
+        let mut reactions = MessageReactions::default();
+
+        let counts = reactions.apply("msg-1", "👍", "alice", true).unwrap();
+        assert_eq!(counts[0].count, 1);
+        // 同一人重复点同一表情不重复计数
+        assert!(reactions.apply("msg-1", "👍", "alice", true).is_none());
+
+        let counts = reactions.apply("msg-1", "👍", "bob", true).unwrap();
+        assert_eq!(counts, vec![ReactionCount {
+            emoji: "👍".to_string(),
+            count: 2,
+            player_ids: vec!["alice".to_string(), "bob".to_string()],
+        }]);
+
+        let counts = reactions.apply("msg-1", "😂", "alice", true).unwrap();
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn test_message_type_serialization_compat() {
+        // 旧版本写出的消息类型仍能解析
+        for (raw, expected) in [
+            ("\"text\"", MessageType::Text),
+            ("\"image\"", MessageType::Image),
+            ("\"announce\"", MessageType::Announce),
+            ("\"voicegroup\"", MessageType::VoiceGroup),
+            ("\"whiteboard\"", MessageType::Whiteboard),
+        ] {
+            assert_eq!(serde_json::from_str::<MessageType>(raw).unwrap(), expected);
+        }
+        assert_eq!(serde_json::to_string(&MessageType::System).unwrap(), "\"system\"");
+
+        let message = ChatMessage::system("Alice 加入了大厅".to_string());
+        assert!(message.is_local_only());
+        assert_eq!(message.player_id, SYSTEM_PLAYER_ID);
+        assert_ne!(message.id, ChatMessage::system(String::new()).id);
+        let parsed: ChatMessage = serde_json::from_str(&serde_json::to_string(&message).unwrap()).unwrap();
+        assert_eq!(parsed.message_type, MessageType::System);
+    }
+
+    #[tokio::test]
+    async fn test_system_messages_stay_local() {
+        let service = ChatService::new();
+        service.add_system_message("Bob 被踢出大厅".to_string());
+        let mut text = ChatMessage::system("hi".to_string());
+        text.message_type = MessageType::Text;
+        service.add_local_message(text);
+        assert_eq!(service.get_local_messages(None).len(), 2);
+
+        // 其他成员拉取时看不到系统消息
+        let state = AppState {
+            local_messages: service.local_messages.clone(),
+            message_tx: service.message_tx.clone(),
+            mentions: service.mentions.clone(),
+        };
+        let Json(served) = get_messages(State(state), Query(GetMessagesQuery { since: None, after_seq: None })).await;
+        assert_eq!(served.len(), 1);
+        assert_eq!(served[0].message_type, MessageType::Text);
+    }
+
+    fn text_message(id: &str, timestamp: u64) -> ChatMessage {
+        let mut message = ChatMessage::system(id.to_string());
+        message.id = id.to_string();
+        message.player_id = "alice".to_string();
+        message.message_type = MessageType::Text;
+        message.timestamp = timestamp;
+        message
+    }
+
+    #[test]
+    fn test_concurrent_messages_get_unique_ids_and_seqs() {
+        let log = Arc::new(RwLock::new(MessageLog::new()));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    // 同一玩家在同一毫秒内连续发送
+                    for _ in 0..100 {
+                        let message = text_message(&ChatMessage::new_id("alice"), 0);
+                        assert!(log.write().push(message).is_some());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let log = log.read();
+        assert_eq!(log.len(), 800);
+        assert_eq!(log.last_seq(), 800);
+        let seqs: HashSet<u64> = log.after(0).map(|msg| msg.seq).collect();
+        assert_eq!(seqs, (1..=800).collect());
+    }
+
+    #[test]
+    fn test_message_log_dedup_and_trim() {
+        let mut log = MessageLog::new();
+        assert_eq!(log.push(text_message("a", 1)).unwrap().seq, 1);
+        assert!(log.push(text_message("a", 1)).is_none());
+        for i in 0..MAX_MESSAGES_PER_PLAYER {
+            log.push(text_message(&format!("m{}", i), 1));
+        }
+        assert_eq!(log.len(), MAX_MESSAGES_PER_PLAYER);
+        // 被淘汰的消息不再参与去重，清空后序号继续递增
+        assert!(log.get("a").is_none());
+        log.clear();
+        assert!(log.is_empty());
+        assert_eq!(log.push(text_message("a", 1)).unwrap().seq, MAX_MESSAGES_PER_PLAYER as u64 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_sync_tolerates_clock_drift() {
+        let service = ChatService::new();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        service.add_local_message(text_message("local", now));
+        let cursor = service.get_local_messages(None).last().unwrap().seq;
+
+        // 对方时钟慢了一小时，按时间戳过滤会漏掉这条消息
+        let mut remote = text_message("remote", now - 3600);
+        remote.seq = 7;
+        assert_eq!(service.merge_peer_messages("10.0.0.2", vec![remote.clone()]), 1);
+        let fresh = service.get_local_messages(Some(cursor));
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].id, "remote");
+        assert_eq!(service.peer_cursor("10.0.0.2"), Some(7));
+
+        // 重复拉取不重复加入；对方重启后序号回退，游标随之回退
+        remote.seq = 1;
+        assert_eq!(service.merge_peer_messages("10.0.0.2", vec![remote]), 0);
+        assert_eq!(service.peer_cursor("10.0.0.2"), Some(1));
+        assert!(service.get_local_messages(Some(fresh[0].seq)).is_empty());
+
+        // 游标超过本机最新序号时（本机重启过）返回全部消息
+        let state = AppState {
+            local_messages: service.local_messages.clone(),
+            message_tx: service.message_tx.clone(),
+            mentions: service.mentions.clone(),
+        };
+        let query = GetMessagesQuery { since: None, after_seq: Some(100) };
+        let Json(served) = get_messages(State(state.clone()), Query(query)).await;
+        assert_eq!(served.len(), 2);
+        let query = GetMessagesQuery { since: None, after_seq: Some(cursor) };
+        let Json(served) = get_messages(State(state), Query(query)).await;
+        assert_eq!(served.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mentions_and_reply_references() {
+        // 旧版本的消息没有回复 / @ 字段
+        let legacy = r#"{"id":"m1","player_id":"bob","player_name":"Bob","content":"hi","message_type":"text","timestamp":1,"image_data":null}"#;
+        let parsed: ChatMessage = serde_json::from_str(legacy).unwrap();
+        assert!(parsed.reply_to.is_none() && parsed.mentions.is_empty());
+
+        let (reply_to, mentions) = sanitize_references(
+            Some("x".repeat(MAX_CHAT_REF_ID_LEN + 1)),
+            vec!["alice".into(), "alice".into(), String::new()],
+        );
+        assert!(reply_to.is_none());
+        assert_eq!(mentions, vec!["alice".to_string()]);
+
+        let service = ChatService::new();
+        service.set_local_player_id("alice".to_string());
+        let mut mention_rx = service.subscribe_mentions();
+        let state = AppState {
+            local_messages: service.local_messages.clone(),
+            message_tx: service.message_tx.clone(),
+            mentions: service.mentions.clone(),
+        };
+        let request = SendMessageRequest {
+            id: Some("msg-bob-mention".to_string()),
+            player_id: "bob-mention".to_string(),
+            player_name: "Bob".to_string(),
+            content: "@Alice 看这里".to_string(),
+            message_type: MessageType::Text,
+            image_data: None,
+            reply_to: Some("msg-alice-1".to_string()),
+            mentions: vec!["alice".to_string()],
+        };
+        let Json(saved) = send_message(State(state), Json(request)).await.unwrap();
+        assert_eq!(saved.reply_to.as_deref(), Some("msg-alice-1"));
+        assert_eq!(mention_rx.try_recv().unwrap().id, "msg-bob-mention");
+
+        // 拉取到的消息同样通知，未 @ 本机或自己发送的不通知
+        let mut pulled = text_message("pulled", 1);
+        pulled.player_id = "carol".to_string();
+        pulled.mentions = vec!["alice".to_string()];
+        let mut own = text_message("own", 1);
+        own.mentions = vec!["alice".to_string()];
+        let other = text_message("other", 1);
+        assert_eq!(service.merge_peer_messages("10.0.0.3", vec![pulled, own, other]), 3);
+        assert_eq!(mention_rx.try_recv().unwrap().id, "pulled");
+        assert!(mention_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retried_send_is_not_duplicated() {
+        let service = ChatService::new();
+        let state = AppState {
+            local_messages: service.local_messages.clone(),
+            message_tx: service.message_tx.clone(),
+            mentions: service.mentions.clone(),
+        };
+        let mut rx = service.subscribe();
+        let request = || SendMessageRequest {
+            id: Some("msg-bob-1".to_string()),
+            player_id: "bob-retry".to_string(),
+            player_name: "Bob".to_string(),
+            content: "hi".to_string(),
+            message_type: MessageType::Text,
+            image_data: None,
+            reply_to: None,
+            mentions: Vec::new(),
+        };
+
+        let Json(first) = send_message(State(state.clone()), Json(request())).await.unwrap();
+        let Json(second) = send_message(State(state), Json(request())).await.unwrap();
+        assert_eq!(first.seq, second.seq);
+        assert_eq!(service.get_local_messages(None).len(), 1);
+        assert_eq!(rx.try_recv().unwrap().id, "msg-bob-1");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restart_immediately_after_stop() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 退出大厅后立即重进：停止时等待端口释放，重新启动应绑定回同一端口而不是顺延
+        let service = ChatService::new();
+        service.set_virtual_ip("127.0.0.1".to_string());
+        service.start_server().await.unwrap();
+        let addr = service.bound_addr.read().unwrap();
+
+        // 保持一个已建立的 keep-alive 连接（类似 SSE 聊天流），停止服务时应一并关闭
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET /api/chat/messages HTTP/1.1\r\nHost: test\r\n\r\n").await.unwrap();
+        let mut buf = [0u8; 1024];
+        assert!(client.read(&mut buf).await.unwrap() > 0);
+
+        service.stop_server().await;
+        assert!(!service.is_running());
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(2), client.read(&mut buf)).await.unwrap();
+        assert!(matches!(closed, Ok(0) | Err(_)));
+
+        service.start_server().await.unwrap();
+        assert_eq!(*service.bound_addr.read(), Some(addr));
+        service.stop_server().await;
+    }
+
+    #[test]
+    fn test_reaction_removal() {
+        let mut reactions = MessageReactions::default();
+        reactions.apply("msg-1", "👍", "alice", true);
+        reactions.apply("msg-1", "👍", "bob", true);
+
+        let counts = reactions.apply("msg-1", "👍", "alice", false).unwrap();
+        assert_eq!(counts[0].count, 1);
+        assert_eq!(counts[0].player_ids, vec!["bob".to_string()]);
+
+        // 删除不存在的回应不产生更新
+        assert!(reactions.apply("msg-1", "👍", "alice", false).is_none());
+        assert!(reactions.apply("msg-2", "👍", "alice", false).is_none());
+
+        // 最后一个回应移除后该表情不再出现
+        assert!(reactions.apply("msg-1", "👍", "bob", false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_reaction_is_ignored() {
+        let mut reactions = MessageReactions::default();
+        assert!(reactions.apply("msg-1", "", "alice", true).is_none());
+        assert!(reactions.apply("msg-1", &"x".repeat(MAX_REACTION_EMOJI_LEN + 1), "alice", true).is_none());
+        assert!(reactions.counts("msg-1").is_empty());
+    }
+
+    #[test]
+    fn test_large_image_is_downscaled_to_jpeg() {
+        let png = encode_png(3840, 1080);
+        let jpeg = prepare_chat_image(&png, DEFAULT_CHAT_IMAGE_MAX_BYTES).unwrap();
+
+        assert_eq!(image::guess_format(&jpeg).unwrap(), image::ImageFormat::Jpeg);
+        let decoded = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1920, 540));
+    }
+
+    #[test]
+    fn test_small_image_keeps_dimensions() {
+        let png = encode_png(64, 32);
+        let jpeg = prepare_chat_image(&png, DEFAULT_CHAT_IMAGE_MAX_BYTES).unwrap();
+
+        let decoded = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 32));
+    }
+
+    #[test]
+    fn test_oversized_image_is_rejected() {
+        let png = encode_png(64, 64);
+        let max = png.len() as u64 - 1;
+        assert!(matches!(
+            prepare_chat_image(&png, max),
+            Err(ChatImageError::TooLarge { size, max: m }) if size == png.len() as u64 && m == max
+        ));
+    }
+
+    #[test]
+    fn test_non_image_data_is_rejected() {
+        assert!(matches!(
+            prepare_chat_image(b"definitely not an image", DEFAULT_CHAT_IMAGE_MAX_BYTES),
+            Err(ChatImageError::InvalidImage(_))
+        ));
+        assert!(matches!(prepare_chat_image(&[], DEFAULT_CHAT_IMAGE_MAX_BYTES), Err(ChatImageError::Missing)));
+    }
+
+    #[test]
+    fn test_message_content_length_limits() {
+        // 文本消息超长截断
+        let text = limit_message_content(&MessageType::Text, "长".repeat(input_limits::MAX_CHAT_MESSAGE_CHARS * 2)).unwrap();
+        assert_eq!(text.chars().count(), input_limits::MAX_CHAT_MESSAGE_CHARS);
+        assert_eq!(limit_message_content(&MessageType::Text, "hi".to_string()).as_deref(), Some("hi"));
+
+        // 控制消息是 JSON，不截断，超过字节上限直接拒绝
+        let stroke = "x".repeat(input_limits::MAX_CHAT_MESSAGE_CHARS * 2);
+        assert_eq!(limit_message_content(&MessageType::Whiteboard, stroke.clone()), Some(stroke));
+        let huge = "x".repeat(input_limits::MAX_CONTROL_MESSAGE_BYTES + 1);
+        assert!(limit_message_content(&MessageType::Todo, huge).is_none());
+    }
+}
@@ -0,0 +1,168 @@
+// 配置文件加密
+//
+// 开启后整个配置文件用 Windows DPAPI（CryptProtectData，绑定当前用户）加密后再写盘，
+// 其它用户或把文件拷到别的机器上都无法解密。加密文件以固定前缀开头，后面是 base64 编码的密文，
+// 读取时按前缀区分明文 JSON 与加密内容，因此开关切换前后的文件都能正常加载。
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// 加密配置文件的前缀（含格式版本）
+pub const ENCRYPTED_CONFIG_PREFIX: &str = "MCTIER-ENC1:";
+
+/// DPAPI 附加熵，避免其它程序用同一用户身份直接解密
+const DPAPI_ENTROPY: &[u8] = b"mctier-config-v1";
+
+/// 配置加密错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ConfigCryptoError {
+    #[error("当前平台不支持配置文件加密")]
+    Unsupported,
+
+    #[error("加密配置失败: {0}")]
+    ProtectFailed(String),
+
+    #[error("解密配置失败（可能来自其它用户或其它机器）: {0}")]
+    UnprotectFailed(String),
+
+    #[error("加密配置格式错误: {0}")]
+    Malformed(String),
+}
+
+/// 配置内容的加密器
+pub trait ConfigProtector {
+    /// 加密数据
+    fn protect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError>;
+    /// 解密数据
+    fn unprotect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError>;
+}
+
+/// 基于 Windows DPAPI 的加密器（绑定当前 Windows 用户）
+pub struct DpapiProtector;
+
+#[cfg(windows)]
+impl DpapiProtector {
+    fn blob(data: &[u8]) -> windows::Win32::Security::Cryptography::CRYPT_INTEGER_BLOB {
+        windows::Win32::Security::Cryptography::CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr() as *mut u8,
+        }
+    }
+
+    /// 复制 DPAPI 输出并释放其内存
+    unsafe fn take_blob(blob: windows::Win32::Security::Cryptography::CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data = std::slice::from_raw_parts(blob.pbData, blob.cbData as usize).to_vec();
+        let _ = windows::Win32::Foundation::LocalFree(windows::Win32::Foundation::HLOCAL(blob.pbData as *mut _));
+        data
+    }
+}
+
+#[cfg(windows)]
+impl ConfigProtector for DpapiProtector {
+    fn protect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+        use windows::core::PCWSTR;
+        use windows::Win32::Security::Cryptography::{CryptProtectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB};
+
+        let input = Self::blob(data);
+        let entropy = Self::blob(DPAPI_ENTROPY);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(&input, PCWSTR::null(), Some(&entropy), None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| ConfigCryptoError::ProtectFailed(e.to_string()))?;
+            Ok(Self::take_blob(output))
+        }
+    }
+
+    fn unprotect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+        use windows::Win32::Security::Cryptography::{CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB};
+
+        let input = Self::blob(data);
+        let entropy = Self::blob(DPAPI_ENTROPY);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(&input, None, Some(&entropy), None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+                .map_err(|e| ConfigCryptoError::UnprotectFailed(e.to_string()))?;
+            Ok(Self::take_blob(output))
+        }
+    }
+}
+
+#[cfg(not(windows))]
+impl ConfigProtector for DpapiProtector {
+    fn protect(&self, _data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+        Err(ConfigCryptoError::Unsupported)
+    }
+
+    fn unprotect(&self, _data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+        Err(ConfigCryptoError::Unsupported)
+    }
+}
+
+/// 判断配置文件内容是否为加密格式
+pub fn is_encrypted_config(content: &str) -> bool {
+    content.starts_with(ENCRYPTED_CONFIG_PREFIX)
+}
+
+/// 将配置 JSON 加密为文件内容
+pub fn encrypt_config(json: &str, protector: &dyn ConfigProtector) -> Result<String, ConfigCryptoError> {
+    let sealed = protector.protect(json.as_bytes())?;
+    Ok(format!("{}{}", ENCRYPTED_CONFIG_PREFIX, STANDARD.encode(sealed)))
+}
+
+/// 将文件内容还原为配置 JSON（明文内容原样返回）
+pub fn decrypt_config(content: &str, protector: &dyn ConfigProtector) -> Result<String, ConfigCryptoError> {
+    let Some(encoded) = content.strip_prefix(ENCRYPTED_CONFIG_PREFIX) else {
+        return Ok(content.to_string());
+    };
+    let sealed = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| ConfigCryptoError::Malformed(e.to_string()))?;
+    let plain = protector.unprotect(&sealed)?;
+    String::from_utf8(plain).map_err(|e| ConfigCryptoError::Malformed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用加密器：按字节异或，解密时校验首字节为 `{`，密钥不同即报错
+    struct XorProtector(u8);
+
+    impl ConfigProtector for XorProtector {
+        fn protect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+            Ok(data.iter().map(|b| b ^ self.0).collect())
+        }
+
+        fn unprotect(&self, data: &[u8]) -> Result<Vec<u8>, ConfigCryptoError> {
+            if data.first().map(|b| b ^ self.0) != Some(b'{') {
+                return Err(ConfigCryptoError::UnprotectFailed("密钥不匹配".to_string()));
+            }
+            self.protect(data)
+        }
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_config() {
+        let json = r#"{"player_name":"Steve"}"#;
+        let content = encrypt_config(json, &XorProtector(0x5a)).unwrap();
+
+        assert!(is_encrypted_config(&content));
+        assert!(!content.contains("Steve"));
+        assert_eq!(decrypt_config(&content, &XorProtector(0x5a)).unwrap(), json);
+        // 明文配置原样读取
+        assert_eq!(decrypt_config(json, &XorProtector(0x5a)).unwrap(), json);
+    }
+
+    #[test]
+    fn test_decrypt_config_failures() {
+        let content = encrypt_config("{}", &XorProtector(0x5a)).unwrap();
+
+        assert!(matches!(
+            decrypt_config(&content, &XorProtector(0x33)),
+            Err(ConfigCryptoError::UnprotectFailed(_))
+        ));
+        assert!(matches!(
+            decrypt_config(&format!("{}!!!", ENCRYPTED_CONFIG_PREFIX), &XorProtector(0x5a)),
+            Err(ConfigCryptoError::Malformed(_))
+        ));
+    }
+}
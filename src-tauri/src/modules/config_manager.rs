@@ -1,10 +1,18 @@
+use crate::modules::config_crypto::{decrypt_config, encrypt_config, is_encrypted_config, DpapiProtector};
+use crate::modules::config_schema::config_schema;
 use crate::modules::error::AppError;
+use crate::modules::lobby_manager::PasswordPolicy;
+use crate::modules::service_ports::PortConfig;
+use crate::modules::voice_service::MicMode;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
 /// 窗口位置信息
+///
+/// 坐标为虚拟桌面的全局物理坐标（多显示器时可能为负数）。
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WindowPosition {
     /// X 坐标
@@ -15,6 +23,9 @@ pub struct WindowPosition {
     pub width: u32,
     /// 窗口高度
     pub height: u32,
+    /// 窗口所在显示器名称（旧配置不携带该字段）
+    #[serde(default)]
+    pub monitor: Option<String>,
 }
 
 impl Default for WindowPosition {
@@ -24,6 +35,97 @@ impl Default for WindowPosition {
             y: 100,
             width: 300,
             height: 400,
+            monitor: None,
+        }
+    }
+}
+
+/// 窗口在显示器上至少需要可见的边长（像素），小于此值视为不可见
+const MIN_VISIBLE_EDGE: i64 = 48;
+
+/// 显示器区域（全局物理坐标）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorArea {
+    /// 显示器名称
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// 是否为主显示器
+    pub primary: bool,
+}
+
+impl MonitorArea {
+    /// 窗口在该显示器上是否足够可见（横纵方向都至少露出 MIN_VISIBLE_EDGE，窗口更小时要求完全露出）
+    fn shows(&self, pos: &WindowPosition) -> bool {
+        let overlap = |start: i32, len: u32, area_start: i32, area_len: u32| {
+            let end = start as i64 + len as i64;
+            let area_end = area_start as i64 + area_len as i64;
+            end.min(area_end) - (start as i64).max(area_start as i64)
+        };
+        let ox = overlap(pos.x, pos.width, self.x, self.width);
+        let oy = overlap(pos.y, pos.height, self.y, self.height);
+        ox >= MIN_VISIBLE_EDGE.min(pos.width as i64) && oy >= MIN_VISIBLE_EDGE.min(pos.height as i64)
+    }
+
+    /// 把窗口收进该显示器范围内（窗口比显示器大时贴左上角）
+    fn clamp(&self, pos: &WindowPosition) -> WindowPosition {
+        let clamp_axis = |start: i32, len: u32, area_start: i32, area_len: u32| {
+            let max = area_start as i64 + area_len as i64 - len as i64;
+            (start as i64).min(max).max(area_start as i64) as i32
+        };
+        WindowPosition {
+            x: clamp_axis(pos.x, pos.width, self.x, self.width),
+            y: clamp_axis(pos.y, pos.height, self.y, self.height),
+            width: pos.width,
+            height: pos.height,
+            monitor: self.name.clone(),
+        }
+    }
+
+    /// 把窗口居中放到该显示器
+    fn center(&self, pos: &WindowPosition) -> WindowPosition {
+        let centered = WindowPosition {
+            x: self.x + (self.width as i32 - pos.width as i32) / 2,
+            y: self.y + (self.height as i32 - pos.height as i32) / 2,
+            ..pos.clone()
+        };
+        self.clamp(&centered)
+    }
+}
+
+impl WindowPosition {
+    /// 根据当前可用的显示器校正保存的窗口位置，避免窗口开到屏幕外看不见
+    ///
+    /// - 记录的显示器仍在：窗口在其上可见则保持原位，否则收进该显示器范围（分辨率变小的情况）
+    /// - 记录的显示器已拔掉，或旧配置未记录显示器且窗口不在任何显示器上：回退到主显示器居中
+    ///
+    /// # 参数
+    /// * `monitors` - 当前可用的显示器
+    ///
+    /// # 返回
+    /// 校正后的位置；没有任何显示器信息时原样返回
+    pub fn resolve_on(&self, monitors: &[MonitorArea]) -> WindowPosition {
+        let Some(fallback) = monitors.iter().find(|m| m.primary).or_else(|| monitors.first()) else {
+            return self.clone();
+        };
+
+        let saved_monitor = self
+            .monitor
+            .as_ref()
+            .and_then(|name| monitors.iter().find(|m| m.name.as_ref() == Some(name)));
+        match saved_monitor {
+            Some(monitor) if monitor.shows(self) => self.clone(),
+            Some(monitor) => monitor.clamp(self),
+            None if self.monitor.is_none() => match monitors.iter().find(|m| m.shows(self)) {
+                Some(monitor) => WindowPosition {
+                    monitor: monitor.name.clone(),
+                    ..self.clone()
+                },
+                None => fallback.center(self),
+            },
+            None => fallback.center(self),
         }
     }
 }
@@ -45,6 +147,252 @@ pub struct AutoLobbyConfig {
     pub virtual_domain: Option<String>,
 }
 
+/// 最近加入的大厅最多保留条数
+pub const MAX_RECENT_LOBBIES: usize = 10;
+
+/// 最近加入的大厅（用于快速重连，不保存密码）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecentLobby {
+    /// 大厅名称
+    pub name: String,
+    /// 服务器节点地址
+    pub server_node: String,
+    /// 是否使用虚拟域名
+    pub use_domain: bool,
+    /// 最近一次创建/加入的时间（Unix timestamp，秒）
+    pub last_joined: u64,
+}
+
+/// 记录一条最近大厅：同名同节点的旧记录会被替换，按时间倒序最多保留 `MAX_RECENT_LOBBIES` 条
+pub fn push_recent_lobby(list: &mut Vec<RecentLobby>, entry: RecentLobby) {
+    list.retain(|l| !(l.name == entry.name && l.server_node == entry.server_node));
+    list.push(entry);
+    list.sort_by_key(|l| std::cmp::Reverse(l.last_joined));
+    list.truncate(MAX_RECENT_LOBBIES);
+}
+
+/// 记住的静音玩家最多保留条数（ID 与昵称分别计）
+pub const MAX_PERSISTED_MUTES: usize = 200;
+
+/// 跨会话记住的静音玩家
+///
+/// 玩家 ID 每次启动都会变化，按 ID 记住只在对方未重启时有效；开启 `by_name` 后同时按昵称记住。
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PersistedMutes {
+    /// 是否按昵称记住静音
+    #[serde(default)]
+    pub by_name: bool,
+    /// 按玩家 ID 记住的静音
+    #[serde(default)]
+    pub player_ids: Vec<String>,
+    /// 按昵称记住的静音
+    #[serde(default)]
+    pub player_names: Vec<String>,
+}
+
+impl PersistedMutes {
+    /// 记录一次静音 / 取消静音
+    ///
+    /// 静音时总是记住 ID，开启按昵称模式时同时记住昵称；取消静音时 ID 与昵称都会移除，
+    /// 避免关闭按昵称模式后旧的昵称记录仍然生效。每个列表最多保留 `MAX_PERSISTED_MUTES` 条，超出时丢弃最早的。
+    pub fn record(&mut self, player_id: &str, player_name: Option<&str>, muted: bool) {
+        let player_name = player_name.map(str::trim).filter(|n| !n.is_empty());
+        self.player_ids.retain(|id| id != player_id);
+        if let Some(name) = player_name {
+            self.player_names.retain(|n| n != name);
+        }
+        if !muted {
+            return;
+        }
+        push_bounded(&mut self.player_ids, player_id.to_string());
+        if let (true, Some(name)) = (self.by_name, player_name) {
+            push_bounded(&mut self.player_names, name.to_string());
+        }
+    }
+
+    /// 玩家是否应被静音
+    pub fn is_muted(&self, player_id: &str, player_name: Option<&str>) -> bool {
+        self.player_ids.iter().any(|id| id == player_id)
+            || (self.by_name && player_name.is_some_and(|name| self.player_names.iter().any(|n| n == name.trim())))
+    }
+}
+
+fn push_bounded(list: &mut Vec<String>, value: String) {
+    list.push(value);
+    if list.len() > MAX_PERSISTED_MUTES {
+        list.drain(..list.len() - MAX_PERSISTED_MUTES);
+    }
+}
+
+/// 短房间码长度
+pub const ROOM_CODE_LEN: usize = 6;
+
+/// 房间码映射表最多保留条数
+pub const MAX_ROOM_CODES: usize = 50;
+
+/// 短房间码字符集（去掉易混淆的 0/O、1/I/L，便于口头分享）
+const ROOM_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+
+/// 短房间码与大厅参数的映射（不保存密码）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoomCode {
+    /// 短房间码
+    pub code: String,
+    /// 大厅名称
+    pub name: String,
+    /// 服务器节点地址
+    pub server_node: String,
+    /// 是否使用虚拟域名
+    pub use_domain: bool,
+    /// 生成时间（Unix timestamp，秒）
+    pub created_at: u64,
+}
+
+/// 由大厅名和服务器节点计算短房间码
+///
+/// 同一大厅总是得到相同的码；`attempt` 用于与其它大厅撞码时重新生成。
+pub fn room_code_candidate(name: &str, server_node: &str, attempt: u32) -> String {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::new()
+        .chain_update(name.as_bytes())
+        .chain_update([0u8])
+        .chain_update(server_node.as_bytes())
+        .chain_update(attempt.to_be_bytes())
+        .finalize();
+    digest
+        .iter()
+        .take(ROOM_CODE_LEN)
+        .map(|b| ROOM_CODE_ALPHABET[*b as usize % ROOM_CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+/// 规范化用户输入的房间码（忽略大小写、空白和分隔符）
+pub fn normalize_room_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// 为大厅分配短房间码：已有映射时直接复用，撞码时换下一个候选，超出上限时淘汰最旧的映射
+pub fn assign_room_code(list: &mut Vec<RoomCode>, name: &str, server_node: &str, use_domain: bool, now: u64) -> String {
+    if let Some(existing) = list.iter_mut().find(|r| r.name == name && r.server_node == server_node) {
+        existing.use_domain = use_domain;
+        return existing.code.clone();
+    }
+    let code = (0..)
+        .map(|attempt| room_code_candidate(name, server_node, attempt))
+        .find(|code| list.iter().all(|r| &r.code != code))
+        .expect("候选房间码无穷多");
+    list.push(RoomCode {
+        code: code.clone(),
+        name: name.to_string(),
+        server_node: server_node.to_string(),
+        use_domain,
+        created_at: now,
+    });
+    list.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+    list.truncate(MAX_ROOM_CODES);
+    code
+}
+
+/// 在映射表中查找房间码对应的大厅参数
+pub fn resolve_room_code<'a>(list: &'a [RoomCode], code: &str) -> Option<&'a RoomCode> {
+    let code = normalize_room_code(code);
+    list.iter().find(|r| r.code == code)
+}
+
+/// Minecraft 聊天互通配置（RCON + 服务器日志）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct McChatBridgeConfig {
+    /// 是否启用聊天互通，默认关闭
+    pub enabled: bool,
+    /// RCON 地址，未设置时使用 127.0.0.1:25575
+    pub rcon_address: Option<String>,
+    /// RCON 密码
+    pub rcon_password: Option<String>,
+    /// MC 服务器日志路径（如 logs/latest.log），未设置时不导入游戏内聊天
+    pub log_path: Option<String>,
+}
+
+/// EasyTier 启动选项（覆盖 MCTier 写死的默认启动参数）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EasyTierLaunchOptions {
+    /// 监听器协议，默认按服务器节点协议自动选择
+    #[serde(default)]
+    pub listener_protocol: crate::modules::network_service::ListenerProtocol,
+    /// 使用内置公共节点时是否同时连接其它内置节点作为冗余，默认 true
+    #[serde(default = "default_redundant_public_peers")]
+    pub redundant_public_peers: bool,
+    /// 追加到命令行末尾的自定义参数（如 `["--mtu", "1300"]`）
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// 数据压缩算法（none / zstd），高延迟链路开启可节省带宽，默认 none
+    #[serde(default)]
+    pub compression: crate::modules::network_service::CompressionAlgorithm,
+    /// 加密选项（default / aes-gcm / aes-256-gcm / chacha20 / xor / disabled），低端机关闭可省 CPU，
+    /// 需大厅内所有成员一致，默认 default
+    #[serde(default)]
+    pub encryption: crate::modules::network_service::EncryptionMode,
+}
+
+fn default_redundant_public_peers() -> bool {
+    true
+}
+
+impl Default for EasyTierLaunchOptions {
+    fn default() -> Self {
+        Self {
+            listener_protocol: Default::default(),
+            redundant_public_peers: true,
+            extra_args: Vec::new(),
+            compression: Default::default(),
+            encryption: Default::default(),
+        }
+    }
+}
+
+/// 进入大厅后自动执行的动作序列的最大长度
+pub const MAX_LOBBY_AUTO_ACTIONS: usize = 20;
+
+/// 进入大厅后自动执行的动作（声明式 JSON，如 `{"type":"send_message","content":"大家好"}`）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LobbyAutoAction {
+    /// 开启麦克风
+    EnableMic,
+    /// 共享本地文件夹
+    ShareFolder {
+        /// 文件夹路径
+        path: String,
+        /// 共享名称，未设置时使用文件夹名
+        #[serde(default)]
+        name: Option<String>,
+        /// 共享密码
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// 发送聊天消息
+    SendMessage {
+        /// 消息内容
+        content: String,
+    },
+}
+
+/// 玩家列表排序偏好（仅影响展示顺序）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerSortOrder {
+    /// 按玩家名称排序
+    Name,
+    /// 按加入顺序排序
+    JoinOrder,
+    /// 正在说话（麦克风开启）的玩家在前
+    Speaking,
+    /// 已准备的玩家在前
+    Ready,
+}
+
 /// EasyTier 节点配置
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EasyTierNode {
@@ -351,10 +699,14 @@ pub struct UserConfig {
     pub global_mute_hotkey: Option<String>,
     /// F2临时开麦快捷键
     pub push_to_talk_hotkey: Option<String>,
+    /// 麦克风模式（toggle / push_to_talk / voice_activated），默认 toggle
+    pub mic_mode: Option<MicMode>,
     /// 状态窗口位置
     pub window_position: Option<WindowPosition>,
-    /// 音频设备 ID
+    /// 麦克风设备 ID（上次选择的设备，启动时恢复）
     pub audio_device_id: Option<String>,
+    /// 扬声器设备 ID（上次选择的设备，启动时恢复）
+    pub speaker_device_id: Option<String>,
     /// 窗口透明度 (0.0-1.0)，默认 0.95
     pub opacity: Option<f64>,
     /// 是否开机自启
@@ -383,6 +735,56 @@ pub struct UserConfig {
     pub global_easytier_advanced_config: Option<EasyTierAdvancedConfig>,
     /// 大厅 EasyTier 高级配置（覆盖全局配置）
     pub lobby_easytier_advanced_config: Option<EasyTierAdvancedConfig>,
+    /// 玩家列表排序偏好，未设置时按加入顺序
+    pub player_sort_order: Option<PlayerSortOrder>,
+    /// 启动前发现其它 EasyTier 实例时是否自动清理，默认 true
+    pub auto_cleanup_easytier_instances: Option<bool>,
+    /// Minecraft 聊天互通配置
+    pub mc_chat_bridge: Option<McChatBridgeConfig>,
+    /// 文件/聊天 HTTP 服务是否启用 TLS（需大厅成员一致），默认 false
+    pub enable_http_tls: Option<bool>,
+    /// 最近加入的大厅（按时间倒序）
+    pub recent_lobbies: Option<Vec<RecentLobby>>,
+    /// 虚拟网络断线后的重连宽限期（秒），0 表示断线立即退出大厅，默认 30
+    pub reconnect_grace_secs: Option<u64>,
+    /// 进入大厅后按序自动执行的动作
+    pub lobby_auto_actions: Option<Vec<LobbyAutoAction>>,
+    /// 聊天图片大小上限（字节），默认 5MB
+    pub chat_image_max_bytes: Option<u64>,
+    /// P2P 信令加密后是否仍兼容旧版本的明文发现，默认 false
+    pub p2p_signaling_plaintext_compat: Option<bool>,
+    /// EasyTier 启动选项
+    pub easytier_launch_options: Option<EasyTierLaunchOptions>,
+    /// 是否启用跨玩家语音响度均衡，默认 false
+    pub voice_loudness_normalization: Option<bool>,
+    /// 短房间码映射表
+    pub room_codes: Option<Vec<RoomCode>>,
+    /// 是否用 Windows DPAPI 加密整个配置文件，默认 false
+    pub encrypt_config_file: Option<bool>,
+    /// 是否通过 UPnP 在路由器上映射 P2P 信令端口，默认 false
+    pub upnp_port_mapping: Option<bool>,
+    /// 浏览 / 下载他人共享时是否告知共享者，默认 true
+    pub share_activity_presence: Option<bool>,
+    /// 创建大厅时的密码强度策略（strict / relaxed / none），默认 strict
+    pub lobby_password_policy: Option<PasswordPolicy>,
+    /// 切换麦克风 / 听筒静音时是否播放提示音，默认 true
+    pub voice_feedback_sound: Option<bool>,
+    /// P2P 信令 / 文件共享 / 聊天服务端口
+    pub port_config: Option<PortConfig>,
+    /// 跨会话记住的静音玩家
+    pub persisted_mutes: Option<PersistedMutes>,
+    /// 日志级别（error / warn / info / debug / trace），默认 info
+    pub log_level: Option<crate::modules::log_file::LogLevel>,
+    /// 偏好的中继节点（P2P 打洞失败时优先经由该节点中继）
+    pub preferred_relay: Option<String>,
+    /// 说话检测的麦克风电平阈值（0.0-1.0），默认 0.02
+    pub vad_threshold: Option<f32>,
+    /// 关闭主窗口时最小化到托盘（后台继续运行），默认 false
+    pub close_to_tray: Option<bool>,
+    /// 启动组网后等待获取虚拟 IP 的超时时间（秒），默认 60
+    pub connect_timeout_secs: Option<u64>,
+    /// 其他成员向可写共享上传单个文件的大小上限（字节），默认 2GB
+    pub upload_max_bytes: Option<u64>,
 }
 
 impl Default for UserConfig {
@@ -393,8 +795,10 @@ impl Default for UserConfig {
             mic_hotkey: Some("Ctrl+M".to_string()),
             global_mute_hotkey: Some("Ctrl+T".to_string()),
             push_to_talk_hotkey: Some("F2".to_string()),
+            mic_mode: Some(MicMode::Toggle),
             window_position: Some(WindowPosition::default()),
             audio_device_id: None,
+            speaker_device_id: None,
             opacity: Some(0.95),
             auto_startup: Some(false),
             auto_lobby: Some(AutoLobbyConfig::default()),
@@ -409,16 +813,190 @@ impl Default for UserConfig {
             exit_node_config: Some(ExitNodeConfig::default()),
             global_easytier_advanced_config: None,
             lobby_easytier_advanced_config: None,
+            player_sort_order: None,
+            auto_cleanup_easytier_instances: Some(true),
+            mc_chat_bridge: Some(McChatBridgeConfig::default()),
+            enable_http_tls: Some(false),
+            recent_lobbies: Some(Vec::new()),
+            reconnect_grace_secs: Some(crate::modules::network_service::DEFAULT_RECONNECT_GRACE_SECS),
+            lobby_auto_actions: Some(Vec::new()),
+            chat_image_max_bytes: Some(crate::modules::chat_service::DEFAULT_CHAT_IMAGE_MAX_BYTES),
+            p2p_signaling_plaintext_compat: Some(false),
+            easytier_launch_options: Some(EasyTierLaunchOptions::default()),
+            voice_loudness_normalization: Some(false),
+            room_codes: Some(Vec::new()),
+            encrypt_config_file: Some(false),
+            upnp_port_mapping: Some(false),
+            share_activity_presence: Some(true),
+            lobby_password_policy: Some(PasswordPolicy::Strict),
+            voice_feedback_sound: Some(true),
+            port_config: Some(PortConfig::default()),
+            persisted_mutes: Some(PersistedMutes::default()),
+            log_level: Some(crate::modules::log_file::LogLevel::Info),
+            preferred_relay: None,
+            vad_threshold: Some(crate::modules::voice_service::SPEAKING_LEVEL_THRESHOLD),
+            close_to_tray: Some(false),
+            connect_timeout_secs: Some(crate::modules::network_service::DEFAULT_CONNECT_TIMEOUT_SECS),
+            upload_max_bytes: Some(crate::modules::file_transfer::DEFAULT_UPLOAD_MAX_BYTES),
         }
     }
 }
 
+/// 导出配置的格式标识
+pub const CONFIG_EXPORT_FORMAT: &str = "mctier-config";
+
+/// 导出配置的格式版本
+pub const CONFIG_EXPORT_VERSION: u64 = 1;
+
+/// 与本机硬件 / 环境相关的字段，跨设备同步时可选排除
+pub const MACHINE_SPECIFIC_FIELDS: &[&str] = &[
+    "window_position",
+    "audio_device_id",
+    "speaker_device_id",
+    "auto_startup",
+    "enable_gpu_rendering",
+    "vad_threshold",
+];
+
+/// 配置导入结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImportReport {
+    /// 已导入的字段
+    pub applied: Vec<String>,
+    /// 被忽略的字段及原因
+    pub warnings: Vec<String>,
+}
+
+/// 构造导出内容（带格式标识与版本号的 JSON）
+///
+/// # 参数
+/// * `config` - 要导出的配置
+/// * `exclude_machine_specific` - 是否排除 `MACHINE_SPECIFIC_FIELDS` 中的字段
+pub fn build_config_export(config: &UserConfig, exclude_machine_specific: bool) -> Result<serde_json::Value, AppError> {
+    let mut value = serde_json::to_value(config).map_err(|e| {
+        AppError::ConfigError(format!("序列化配置失败: {}", e))
+    })?;
+    if exclude_machine_specific {
+        if let Some(fields) = value.as_object_mut() {
+            for field in MACHINE_SPECIFIC_FIELDS {
+                fields.remove(*field);
+            }
+        }
+    }
+    Ok(serde_json::json!({
+        "format": CONFIG_EXPORT_FORMAT,
+        "version": CONFIG_EXPORT_VERSION,
+        "exportedAt": chrono::Local::now().to_rfc3339(),
+        "config": value,
+    }))
+}
+
+/// 解码导入内容：支持 JSON 原文与 base64 编码的 JSON
+fn decode_config_import(data: &str) -> Result<serde_json::Value, AppError> {
+    let data = data.trim();
+    let json = if data.starts_with('{') {
+        data.to_string()
+    } else {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|_| AppError::ConfigError("导入内容既不是 JSON 也不是有效的配置码".to_string()))?;
+        String::from_utf8(bytes)
+            .map_err(|_| AppError::ConfigError("配置码内容不是有效的文本".to_string()))?
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        AppError::ConfigError(format!("解析导入内容失败: {}", e))
+    })
+}
+
+/// 把导入内容合并到当前配置
+///
+/// 兼容旧版直接导出的 `UserConfig` JSON。逐字段校验：未知字段、类型不符或超出范围的字段
+/// 忽略并记录警告，其余字段覆盖当前配置；导入内容中没有的字段（如导出时排除的机器相关字段）保留当前值。
+///
+/// # 返回
+/// * `Ok((UserConfig, ConfigImportReport))` - 合并后的配置与导入结果
+/// * `Err(AppError)` - 内容无法解析、格式不符或版本无效
+pub fn merge_config_import(current: &UserConfig, data: &str) -> Result<(UserConfig, ConfigImportReport), AppError> {
+    let root = decode_config_import(data)?;
+    let mut report = ConfigImportReport::default();
+
+    let imported = match root.get("format") {
+        Some(format) => {
+            if format.as_str() != Some(CONFIG_EXPORT_FORMAT) {
+                return Err(AppError::ConfigError(format!("不支持的配置格式: {}", format)));
+            }
+            let version = root
+                .get("version")
+                .and_then(|v| v.as_u64())
+                .filter(|v| *v > 0)
+                .ok_or_else(|| AppError::ConfigError("导入内容缺少有效的版本号".to_string()))?;
+            if version > CONFIG_EXPORT_VERSION {
+                report.warnings.push(format!(
+                    "配置由更新版本的 MCTier 导出（格式版本 {}），无法识别的字段将被忽略",
+                    version
+                ));
+            }
+            root.get("config").cloned().unwrap_or(serde_json::Value::Null)
+        }
+        // 旧版导出文件直接是 UserConfig 本身
+        None => root,
+    };
+    let fields = imported
+        .as_object()
+        .ok_or_else(|| AppError::ConfigError("导入内容中没有配置项".to_string()))?;
+
+    let schema = config_schema();
+    let mut merged = serde_json::to_value(current).map_err(|e| {
+        AppError::ConfigError(format!("序列化配置失败: {}", e))
+    })?;
+    for (key, value) in fields {
+        let Some(field) = schema.iter().find(|f| f.name == key) else {
+            report.warnings.push(format!("未知配置项 {}，已忽略", key));
+            continue;
+        };
+        if let (Some(min), Some(max), Some(n)) = (field.min, field.max, value.as_f64()) {
+            if !(min..=max).contains(&n) {
+                report.warnings.push(format!("配置项 {} 的值 {} 超出范围 {}~{}，已忽略", key, n, min, max));
+                continue;
+            }
+        }
+        let mut candidate = merged.clone();
+        candidate[key.as_str()] = value.clone();
+        if let Err(e) = serde_json::from_value::<UserConfig>(candidate.clone()) {
+            report.warnings.push(format!("配置项 {} 的值不合法（{}），已忽略", key, e));
+            continue;
+        }
+        merged = candidate;
+        report.applied.push(key.clone());
+    }
+
+    for warning in &report.warnings {
+        log::warn!("导入配置: {}", warning);
+    }
+    let config = serde_json::from_value(merged).map_err(|e| {
+        AppError::ConfigError(format!("合并导入配置失败: {}", e))
+    })?;
+    Ok((config, report))
+}
+
+/// 配置文件外部修改的检查间隔
+pub const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 配置文件原始内容的摘要
+fn content_digest(content: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(content).into()
+}
+
 /// 配置管理器
 pub struct ConfigManager {
     /// 配置文件路径
     config_path: PathBuf,
     /// 当前配置
     config: UserConfig,
+    /// 本实例最近一次读取或写入的配置文件内容摘要，用于区分自身保存与外部修改
+    synced_digest: parking_lot::Mutex<Option<[u8; 32]>>,
 }
 
 impl Default for ConfigManager {
@@ -430,6 +1008,7 @@ impl Default for ConfigManager {
         Self {
             config_path,
             config: UserConfig::default(),
+            synced_digest: parking_lot::Mutex::new(None),
         }
     }
 }
@@ -468,13 +1047,17 @@ impl ConfigManager {
             }
             Err(e) => {
                 log::warn!("加载配置文件失败，使用默认配置: {}", e);
+                Self::backup_undecryptable(&config_path).await;
                 UserConfig::default()
             }
         };
 
+        let synced_digest = fs::read(&config_path).await.ok().map(|raw| content_digest(&raw));
+
         Ok(Self {
             config_path,
             config,
+            synced_digest: parking_lot::Mutex::new(synced_digest),
         })
     }
 
@@ -494,6 +1077,21 @@ impl ConfigManager {
         Ok(app_config_dir.join(Self::CONFIG_FILE_NAME))
     }
 
+    /// 加密配置无法解密时（如换了用户或机器）另存一份，避免随后的保存覆盖原文件
+    async fn backup_undecryptable(path: &PathBuf) {
+        let Ok(content) = fs::read_to_string(path).await else {
+            return;
+        };
+        if !is_encrypted_config(&content) {
+            return;
+        }
+        let backup = path.with_extension("json.undecryptable");
+        match fs::rename(path, &backup).await {
+            Ok(()) => log::warn!("无法解密的配置文件已另存为: {:?}", backup),
+            Err(e) => log::warn!("备份无法解密的配置文件失败: {}", e),
+        }
+    }
+
     /// 从文件加载配置
     /// 
     /// # 参数
@@ -513,6 +1111,16 @@ impl ConfigManager {
             AppError::ConfigError(format!("读取配置文件失败: {}", e))
         })?;
 
+        Self::parse_content(&content)
+    }
+
+    /// 解析配置文件内容（加密的配置先解密）
+    fn parse_content(content: &str) -> Result<UserConfig, AppError> {
+        // 加密的配置先解密（明文配置原样返回）
+        let content = decrypt_config(content, &DpapiProtector).map_err(|e| {
+            AppError::ConfigError(e.to_string())
+        })?;
+
         // 解析 JSON
         let config: UserConfig = serde_json::from_str(&content).map_err(|e| {
             AppError::ConfigError(format!("解析配置文件失败: {}", e))
@@ -535,9 +1143,14 @@ impl ConfigManager {
         }
 
         // 序列化配置为 JSON（格式化输出，便于阅读）
-        let json_content = serde_json::to_string_pretty(&self.config).map_err(|e| {
+        let mut json_content = serde_json::to_string_pretty(&self.config).map_err(|e| {
             AppError::ConfigError(format!("序列化配置失败: {}", e))
         })?;
+        if self.config.encrypt_config_file.unwrap_or(false) {
+            json_content = encrypt_config(&json_content, &DpapiProtector).map_err(|e| {
+                AppError::ConfigError(e.to_string())
+            })?;
+        }
 
         // 写入文件（使用临时文件 + 原子重命名，防止写入过程中断导致文件损坏）
         let temp_path = self.config_path.with_extension("json.tmp");
@@ -561,11 +1174,41 @@ impl ConfigManager {
             AppError::ConfigError(format!("重命名配置文件失败: {}", e))
         })?;
 
+        *self.synced_digest.lock() = Some(content_digest(json_content.as_bytes()));
         log::info!("配置已保存到: {:?}", self.config_path);
 
         Ok(())
     }
 
+    /// 检查配置文件是否被外部修改（手动编辑或其他实例保存），是则重新加载
+    ///
+    /// 与本实例最近一次读写的内容一致时视为未变化，因此自身的 `save` 不会触发重载。
+    /// 新内容解析失败时保留当前配置；同一份损坏内容只报告一次。
+    ///
+    /// # 返回
+    /// * `Ok(true)` - 检测到外部修改并已重新加载
+    /// * `Ok(false)` - 文件未变化或暂时不可读（如正在被替换）
+    /// * `Err(AppError)` - 新内容无法解析，已保留旧配置
+    pub async fn reload_if_changed(&mut self) -> Result<bool, AppError> {
+        let Ok(raw) = fs::read(&self.config_path).await else {
+            return Ok(false);
+        };
+        let digest = content_digest(&raw);
+        {
+            let mut synced = self.synced_digest.lock();
+            if *synced == Some(digest) {
+                return Ok(false);
+            }
+            *synced = Some(digest);
+        }
+
+        let content = String::from_utf8(raw)
+            .map_err(|_| AppError::ConfigError("配置文件不是有效的 UTF-8 文本".to_string()))?;
+        self.config = Self::parse_content(&content)?;
+        log::info!("检测到配置文件被外部修改，已重新加载: {:?}", self.config_path);
+        Ok(true)
+    }
+
     /// 获取当前配置的引用
     /// 
     /// # 返回
@@ -654,6 +1297,440 @@ impl ConfigManager {
         }).await
     }
 
+    /// 设置全局静音快捷键
+    /// 
+    /// # 参数
+    /// * `hotkey` - 快捷键字符串
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_global_mute_hotkey(&mut self, hotkey: String) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.global_mute_hotkey = Some(hotkey);
+        }).await
+    }
+
+    /// 设置麦克风模式
+    /// 
+    /// # 参数
+    /// * `mode` - 麦克风模式
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_mic_mode(&mut self, mode: MicMode) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.mic_mode = Some(mode);
+        }).await
+    }
+
+    /// 设置是否自动清理已存在的 EasyTier 实例
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否自动清理
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_auto_cleanup_easytier_instances(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.auto_cleanup_easytier_instances = Some(enabled);
+        }).await
+    }
+
+    /// 记录最近创建/加入的大厅
+    /// 
+    /// # 参数
+    /// * `name` - 大厅名称
+    /// * `server_node` - 服务器节点地址
+    /// * `use_domain` - 是否使用虚拟域名
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 记录成功
+    /// * `Err(AppError)` - 保存失败
+    pub async fn record_recent_lobby(&mut self, name: String, server_node: String, use_domain: bool) -> Result<(), AppError> {
+        let last_joined = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.update_config(|config| {
+            let list = config.recent_lobbies.get_or_insert_with(Vec::new);
+            push_recent_lobby(list, RecentLobby { name, server_node, use_domain, last_joined });
+        }).await
+    }
+
+    /// 清空最近大厅记录
+    pub async fn clear_recent_lobbies(&mut self) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.recent_lobbies = Some(Vec::new());
+        }).await
+    }
+
+    /// 记录玩家的静音状态，供之后的会话恢复
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家 ID
+    /// * `player_name` - 玩家昵称（按昵称记住静音时使用）
+    /// * `muted` - true=静音，false=取消静音
+    pub async fn record_player_mute(&mut self, player_id: &str, player_name: Option<&str>, muted: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config
+                .persisted_mutes
+                .get_or_insert_with(PersistedMutes::default)
+                .record(player_id, player_name, muted);
+        }).await
+    }
+
+    /// 设置是否按昵称记住静音
+    pub async fn set_remember_mutes_by_name(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.persisted_mutes.get_or_insert_with(PersistedMutes::default).by_name = enabled;
+        }).await
+    }
+
+    /// 清空记住的静音玩家（保留按昵称模式的开关）
+    pub async fn clear_persisted_mutes(&mut self) -> Result<(), AppError> {
+        self.update_config(|config| {
+            let by_name = config.persisted_mutes.as_ref().is_some_and(|m| m.by_name);
+            config.persisted_mutes = Some(PersistedMutes { by_name, ..Default::default() });
+        }).await
+    }
+
+    /// 为大厅生成（或复用）短房间码并保存映射
+    /// 
+    /// # 参数
+    /// * `name` - 大厅名称
+    /// * `server_node` - 服务器节点地址
+    /// * `use_domain` - 是否使用虚拟域名
+    /// 
+    /// # 返回
+    /// * `Ok(String)` - 短房间码
+    /// * `Err(AppError)` - 保存失败
+    pub async fn create_room_code(&mut self, name: String, server_node: String, use_domain: bool) -> Result<String, AppError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut code = String::new();
+        self.update_config(|config| {
+            let list = config.room_codes.get_or_insert_with(Vec::new);
+            code = assign_room_code(list, &name, &server_node, use_domain, now);
+        }).await?;
+        Ok(code)
+    }
+
+    /// 设置文件/聊天 HTTP 服务是否启用 TLS
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用 TLS
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_enable_http_tls(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.enable_http_tls = Some(enabled);
+        }).await
+    }
+
+    /// 设置 P2P 信令是否兼容旧版本的明文发现
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否兼容（下次进入大厅时生效）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_p2p_signaling_plaintext_compat(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.p2p_signaling_plaintext_compat = Some(enabled);
+        }).await
+    }
+
+    /// 设置断线重连宽限期
+    /// 
+    /// # 参数
+    /// * `secs` - 宽限期秒数（0 表示断线立即退出大厅）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 超出允许范围或保存失败
+    pub async fn set_reconnect_grace_secs(&mut self, secs: u64) -> Result<(), AppError> {
+        use crate::modules::network_service::MAX_RECONNECT_GRACE_SECS;
+        if secs > MAX_RECONNECT_GRACE_SECS {
+            return Err(AppError::ValidationError(format!(
+                "重连宽限期不能超过 {} 秒",
+                MAX_RECONNECT_GRACE_SECS
+            )));
+        }
+        self.update_config(|config| {
+            config.reconnect_grace_secs = Some(secs);
+        }).await
+    }
+
+    /// 设置进入大厅后自动执行的动作序列
+    /// 
+    /// # 参数
+    /// * `actions` - 动作序列（按顺序执行）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 动作过多或保存失败
+    pub async fn set_lobby_auto_actions(&mut self, actions: Vec<LobbyAutoAction>) -> Result<(), AppError> {
+        if actions.len() > MAX_LOBBY_AUTO_ACTIONS {
+            return Err(AppError::ValidationError(format!(
+                "自动动作最多 {} 个",
+                MAX_LOBBY_AUTO_ACTIONS
+            )));
+        }
+        self.update_config(|config| {
+            config.lobby_auto_actions = Some(actions);
+        }).await
+    }
+
+    /// 设置聊天图片大小上限
+    /// 
+    /// # 参数
+    /// * `max_bytes` - 图片原始数据大小上限（字节）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 超出允许范围或保存失败
+    pub async fn set_chat_image_max_bytes(&mut self, max_bytes: u64) -> Result<(), AppError> {
+        use crate::modules::chat_service::MAX_CHAT_IMAGE_MAX_BYTES;
+        if max_bytes == 0 || max_bytes > MAX_CHAT_IMAGE_MAX_BYTES {
+            return Err(AppError::ValidationError(format!(
+                "聊天图片大小上限必须在 1 到 {} 字节之间",
+                MAX_CHAT_IMAGE_MAX_BYTES
+            )));
+        }
+        self.update_config(|config| {
+            config.chat_image_max_bytes = Some(max_bytes);
+        }).await
+    }
+
+    /// 设置可写共享的上传大小上限
+    /// 
+    /// # 参数
+    /// * `max_bytes` - 单个上传文件的大小上限（字节）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 超出允许范围或保存失败
+    pub async fn set_upload_max_bytes(&mut self, max_bytes: u64) -> Result<(), AppError> {
+        use crate::modules::file_transfer::MAX_UPLOAD_MAX_BYTES;
+        if max_bytes == 0 || max_bytes > MAX_UPLOAD_MAX_BYTES {
+            return Err(AppError::ValidationError(format!(
+                "上传大小上限必须在 1 到 {} 字节之间",
+                MAX_UPLOAD_MAX_BYTES
+            )));
+        }
+        self.update_config(|config| {
+            config.upload_max_bytes = Some(max_bytes);
+        }).await
+    }
+
+    /// 设置是否加密配置文件
+    /// 
+    /// 开启前先试加密一次，当前平台不支持或 DPAPI 不可用时直接返回错误，不改变配置。
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否加密
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 无法加密或保存失败
+    pub async fn set_encrypt_config_file(&mut self, enabled: bool) -> Result<(), AppError> {
+        if enabled {
+            encrypt_config("{}", &DpapiProtector).map_err(|e| AppError::ConfigError(e.to_string()))?;
+        }
+        self.update_config(|config| {
+            config.encrypt_config_file = Some(enabled);
+        }).await
+    }
+
+    /// 设置是否通过 UPnP 映射 P2P 信令端口
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_upnp_port_mapping(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.upnp_port_mapping = Some(enabled);
+        }).await
+    }
+
+    /// 设置浏览 / 下载他人共享时是否告知共享者
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_share_activity_presence(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.share_activity_presence = Some(enabled);
+        }).await
+    }
+
+    /// 设置说话检测阈值（超出范围时限制到 0.0-0.9）
+    /// 
+    /// # 参数
+    /// * `threshold` - 麦克风电平阈值（0.0-1.0）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_vad_threshold(&mut self, threshold: f32) -> Result<(), AppError> {
+        let threshold = crate::modules::voice_service::clamp_vad_threshold(threshold);
+        self.update_config(|config| {
+            config.vad_threshold = Some(threshold);
+        }).await
+    }
+
+    /// 设置偏好的中继节点
+    /// 
+    /// # 参数
+    /// * `node` - 中继节点地址（如 `tcp://relay.example.com:11010`），None 表示不指定
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 地址无效或保存失败
+    pub async fn set_preferred_relay(&mut self, node: Option<String>) -> Result<(), AppError> {
+        if let Some(node) = node.as_deref() {
+            crate::modules::network_service::validate_relay_node(node)?;
+        }
+        self.update_config(|config| {
+            config.preferred_relay = node;
+        }).await
+    }
+
+    /// 设置日志级别
+    /// 
+    /// # 参数
+    /// * `level` - 日志级别
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_log_level(&mut self, level: crate::modules::log_file::LogLevel) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.log_level = Some(level);
+        }).await
+    }
+
+    /// 设置关闭主窗口时是否最小化到托盘
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_close_to_tray(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.close_to_tray = Some(enabled);
+        }).await
+    }
+
+    /// 设置连接超时
+    /// 
+    /// # 参数
+    /// * `secs` - 等待获取虚拟 IP 的超时秒数
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 超出允许范围或保存失败
+    pub async fn set_connect_timeout_secs(&mut self, secs: u64) -> Result<(), AppError> {
+        use crate::modules::network_service::{MAX_CONNECT_TIMEOUT_SECS, MIN_CONNECT_TIMEOUT_SECS};
+        if !(MIN_CONNECT_TIMEOUT_SECS..=MAX_CONNECT_TIMEOUT_SECS).contains(&secs) {
+            return Err(AppError::ValidationError(format!(
+                "连接超时需在 {} 到 {} 秒之间",
+                MIN_CONNECT_TIMEOUT_SECS, MAX_CONNECT_TIMEOUT_SECS
+            )));
+        }
+        self.update_config(|config| {
+            config.connect_timeout_secs = Some(secs);
+        }).await
+    }
+
+    /// 设置切换麦克风 / 听筒静音时是否播放提示音
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_voice_feedback_sound(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.voice_feedback_sound = Some(enabled);
+        }).await
+    }
+
+    /// 设置大厅服务端口
+    /// 
+    /// # 参数
+    /// * `ports` - 信令 / 文件共享 / 聊天端口
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 端口不合法或保存失败
+    pub async fn set_port_config(&mut self, ports: PortConfig) -> Result<(), AppError> {
+        ports.validate().map_err(|e| AppError::ValidationError(e.to_string()))?;
+        self.update_config(|config| {
+            config.port_config = Some(ports);
+        }).await
+    }
+
+    /// 设置创建大厅时的密码强度策略
+    /// 
+    /// # 参数
+    /// * `policy` - 密码强度策略
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_lobby_password_policy(&mut self, policy: PasswordPolicy) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.lobby_password_policy = Some(policy);
+        }).await
+    }
+
+    /// 设置是否启用跨玩家语音响度均衡
+    /// 
+    /// # 参数
+    /// * `enabled` - 是否启用
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_voice_loudness_normalization(&mut self, enabled: bool) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.voice_loudness_normalization = Some(enabled);
+        }).await
+    }
+
+    /// 设置 EasyTier 启动选项
+    /// 
+    /// # 参数
+    /// * `options` - 启动选项
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 自定义参数不合法或保存失败
+    pub async fn set_easytier_launch_options(&mut self, options: EasyTierLaunchOptions) -> Result<(), AppError> {
+        crate::modules::network_service::validate_extra_args(&options.extra_args)?;
+        self.update_config(|config| {
+            config.easytier_launch_options = Some(options);
+        }).await
+    }
+
     /// 设置窗口位置
     /// 
     /// # 参数
@@ -682,17 +1759,31 @@ impl ConfigManager {
         }).await
     }
 
+    /// 设置扬声器设备
+    /// 
+    /// # 参数
+    /// * `device_id` - 扬声器设备 ID
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_speaker_device_id(&mut self, device_id: String) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.speaker_device_id = Some(device_id);
+        }).await
+    }
+
     /// 设置窗口透明度
     /// 
     /// # 参数
-    /// * `opacity` - 透明度值 (0.0-1.0)
+    /// * `opacity` - 透明度值 (0.3-1.0，超出范围时截断)
     /// 
     /// # 返回
     /// * `Ok(())` - 设置成功
     /// * `Err(AppError)` - 设置失败
     pub async fn set_opacity(&mut self, opacity: f64) -> Result<(), AppError> {
         // 验证透明度范围
-        let clamped_opacity = opacity.clamp(0.0, 1.0);
+        let clamped_opacity = crate::modules::window_opacity::clamp_window_opacity(opacity);
         
         self.update_config(|config| {
             config.opacity = Some(clamped_opacity);
@@ -772,6 +1863,20 @@ impl ConfigManager {
         }).await
     }
 
+    /// 设置玩家列表排序偏好
+    /// 
+    /// # 参数
+    /// * `order` - 排序偏好，`None` 表示恢复默认（按加入顺序）
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 设置成功
+    /// * `Err(AppError)` - 设置失败
+    pub async fn set_player_sort_order(&mut self, order: Option<PlayerSortOrder>) -> Result<(), AppError> {
+        self.update_config(|config| {
+            config.player_sort_order = order;
+        }).await
+    }
+
     /// 重置为默认配置
     /// 
     /// # 返回
@@ -822,7 +1927,8 @@ impl ConfigManager {
     /// * `Err(AppError)` - 导出失败
     pub async fn export_config(&self, export_path: PathBuf) -> Result<(), AppError> {
         // 序列化配置为 JSON（格式化输出）
-        let json_content = serde_json::to_string_pretty(&self.config).map_err(|e| {
+        let export = build_config_export(&self.config, false)?;
+        let json_content = serde_json::to_string_pretty(&export).map_err(|e| {
             AppError::ConfigError(format!("序列化配置失败: {}", e))
         })?;
 
@@ -842,28 +1948,45 @@ impl ConfigManager {
     /// * `import_path` - 导入文件路径
     /// 
     /// # 返回
-    /// * `Ok(())` - 导入成功
+    /// * `Ok(ConfigImportReport)` - 导入成功，附带被忽略的字段
     /// * `Err(AppError)` - 导入失败
-    pub async fn import_config(&mut self, import_path: PathBuf) -> Result<(), AppError> {
+    pub async fn import_config(&mut self, import_path: PathBuf) -> Result<ConfigImportReport, AppError> {
         // 读取文件内容
         let content = fs::read_to_string(&import_path).await.map_err(|e| {
             AppError::ConfigError(format!("读取导入文件失败: {}", e))
         })?;
 
-        // 解析 JSON
-        let imported_config: UserConfig = serde_json::from_str(&content).map_err(|e| {
-            AppError::ConfigError(format!("解析导入文件失败: {}", e))
-        })?;
+        let report = self.import_config_data(&content).await?;
 
-        // 更新配置
-        self.config = imported_config;
-        
-        // 保存到配置文件
+        log::info!("配置已从 {:?} 导入", import_path);
+
+        Ok(report)
+    }
+
+    /// 导出配置为可复制分享的配置码（base64 编码的 JSON）
+    ///
+    /// # 参数
+    /// * `exclude_machine_specific` - 是否排除窗口位置、音频设备等本机相关字段
+    pub fn export_config_data(&self, exclude_machine_specific: bool) -> Result<String, AppError> {
+        let export = build_config_export(&self.config, exclude_machine_specific)?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(export.to_string()))
+    }
+
+    /// 从配置码或 JSON 导入配置并保存
+    ///
+    /// 不兼容的字段忽略并记录在返回结果中，不会导致导入失败。
+    pub async fn import_config_data(&mut self, data: &str) -> Result<ConfigImportReport, AppError> {
+        let (config, report) = merge_config_import(&self.config, data)?;
+        self.config = config;
         self.save().await?;
 
-        log::info!("配置已从 {:?} 导入", import_path);
+        log::info!(
+            "配置已导入: {} 项生效，{} 项忽略",
+            report.applied.len(),
+            report.warnings.len()
+        );
 
-        Ok(())
+        Ok(report)
     }
 }
 
@@ -878,6 +2001,7 @@ mod tests {
         ConfigManager {
             config_path,
             config: UserConfig::default(),
+            synced_digest: parking_lot::Mutex::new(None),
         }
     }
 
@@ -903,6 +2027,69 @@ mod tests {
         assert_eq!(pos.height, 400);
     }
 
+    fn monitor(name: &str, x: i32, y: i32, primary: bool) -> MonitorArea {
+        MonitorArea {
+            name: Some(name.to_string()),
+            x,
+            y,
+            width: 1920,
+            height: 1080,
+            primary,
+        }
+    }
+
+    fn window_at(x: i32, y: i32, monitor: Option<&str>) -> WindowPosition {
+        WindowPosition {
+            x,
+            y,
+            width: 300,
+            height: 400,
+            monitor: monitor.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_window_position_kept_on_secondary_monitor() {
+        // 副屏在主屏左侧，坐标为负
+        let monitors = [monitor("DISPLAY1", 0, 0, true), monitor("DISPLAY2", -1920, 0, false)];
+        let saved = window_at(-1500, 200, Some("DISPLAY2"));
+        assert_eq!(saved.resolve_on(&monitors), saved);
+
+        // 旧配置未记录显示器，但位置可见时保持原位并补上显示器名称
+        let legacy = window_at(-1500, 200, None);
+        assert_eq!(legacy.resolve_on(&monitors), window_at(-1500, 200, Some("DISPLAY2")));
+    }
+
+    #[test]
+    fn test_window_position_out_of_bounds_falls_back_to_primary() {
+        let monitors = [monitor("DISPLAY2", 1920, 0, false), monitor("DISPLAY1", 0, 0, true)];
+
+        // 副屏已拔掉：回退到主屏居中
+        let unplugged = window_at(-1500, 200, Some("DISPLAY3"));
+        assert_eq!(unplugged.resolve_on(&monitors), window_at(810, 340, Some("DISPLAY1")));
+
+        // 旧配置的位置不在任何显示器上
+        let lost = window_at(5000, 5000, None);
+        assert_eq!(lost.resolve_on(&monitors), window_at(810, 340, Some("DISPLAY1")));
+
+        // 只露出一条边也视为看不见
+        let edge = window_at(-290, 100, None);
+        assert_eq!(edge.resolve_on(&monitors), window_at(810, 340, Some("DISPLAY1")));
+    }
+
+    #[test]
+    fn test_window_position_clamped_when_resolution_shrinks() {
+        let mut small = monitor("DISPLAY1", 0, 0, true);
+        small.width = 1280;
+        small.height = 720;
+        // 之前在 1920x1080 下保存在右下角
+        let saved = window_at(1600, 650, Some("DISPLAY1"));
+        assert_eq!(saved.resolve_on(&[small]), window_at(980, 320, Some("DISPLAY1")));
+
+        // 没有显示器信息时原样返回
+        assert_eq!(saved.resolve_on(&[]), saved);
+    }
+
     #[tokio::test]
     async fn test_save_and_load_config() {
         let temp_dir = TempDir::new().unwrap();
@@ -980,6 +2167,7 @@ mod tests {
             y: 300,
             width: 400,
             height: 500,
+            monitor: Some("\\\\.\\DISPLAY2".to_string()),
         };
 
         manager.set_window_position(new_pos.clone()).await.unwrap();
@@ -987,6 +2175,130 @@ mod tests {
         assert_eq!(manager.config.window_position, Some(new_pos));
     }
 
+    #[tokio::test]
+    async fn test_set_player_sort_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = create_test_config_manager(&temp_dir).await;
+        assert_eq!(manager.config.player_sort_order, None);
+
+        manager.set_player_sort_order(Some(PlayerSortOrder::Speaking)).await.unwrap();
+        let loaded = ConfigManager::load_from_file(&manager.config_path).await.unwrap();
+        assert_eq!(loaded.player_sort_order, Some(PlayerSortOrder::Speaking));
+
+        // 偏好以 snake_case 持久化
+        let json = serde_json::to_string(&PlayerSortOrder::JoinOrder).unwrap();
+        assert_eq!(json, "\"join_order\"");
+    }
+
+    #[test]
+    fn test_persisted_mutes_record_and_match() {
+        let mut mutes = PersistedMutes::default();
+        mutes.record("id-1", Some("Steve"), true);
+        assert!(mutes.is_muted("id-1", None));
+        // 未开启按昵称模式时不记昵称，换了 ID 的同名玩家不受影响
+        assert!(!mutes.is_muted("id-2", Some("Steve")));
+
+        mutes.by_name = true;
+        mutes.record("id-1", Some(" Steve "), true);
+        assert_eq!(mutes.player_ids, vec!["id-1".to_string()]);
+        assert!(mutes.is_muted("id-2", Some("Steve")));
+
+        // 取消静音同时移除 ID 与昵称
+        mutes.record("id-2", Some("Steve"), false);
+        mutes.record("id-1", None, false);
+        assert!(!mutes.is_muted("id-1", Some("Steve")));
+
+        for i in 0..MAX_PERSISTED_MUTES + 5 {
+            mutes.record(&format!("id-{}", i), None, true);
+        }
+        assert_eq!(mutes.player_ids.len(), MAX_PERSISTED_MUTES);
+        assert_eq!(mutes.player_ids[0], "id-5");
+    }
+
+    #[test]
+    fn test_push_recent_lobby_dedup_order_and_limit() {
+        let entry = |name: &str, t: u64| RecentLobby {
+            name: name.to_string(),
+            server_node: "tcp://node:11010".to_string(),
+            use_domain: false,
+            last_joined: t,
+        };
+
+        let mut list = Vec::new();
+        for i in 0..15 {
+            push_recent_lobby(&mut list, entry(&format!("大厅{}", i), i));
+        }
+        assert_eq!(list.len(), MAX_RECENT_LOBBIES);
+        assert_eq!(list[0].name, "大厅14");
+        assert_eq!(list[MAX_RECENT_LOBBIES - 1].name, "大厅5");
+
+        // 再次加入已有大厅：移到最前且不重复
+        push_recent_lobby(&mut list, entry("大厅7", 100));
+        assert_eq!(list.len(), MAX_RECENT_LOBBIES);
+        assert_eq!(list[0].name, "大厅7");
+        assert_eq!(list.iter().filter(|l| l.name == "大厅7").count(), 1);
+    }
+
+    #[test]
+    fn test_assign_room_code_unique_and_stable() {
+        let mut list = Vec::new();
+        let code = assign_room_code(&mut list, "周末生存服大厅", "tcp://node:11010", false, 1);
+        assert_eq!(code.len(), ROOM_CODE_LEN);
+        assert!(code.bytes().all(|b| ROOM_CODE_ALPHABET.contains(&b)));
+
+        // 同一大厅复用同一个码，不同服务器的同名大厅得到不同的码
+        assert_eq!(assign_room_code(&mut list, "周末生存服大厅", "tcp://node:11010", true, 2), code);
+        assert_eq!(list.len(), 1);
+        assert!(list[0].use_domain);
+        let other = assign_room_code(&mut list, "周末生存服大厅", "udp://other:11010", false, 3);
+        assert_ne!(other, code);
+
+        // 撞码时换下一个候选
+        let mut taken = vec![RoomCode {
+            code: room_code_candidate("大厅", "tcp://node:11010", 0),
+            name: "别的大厅".to_string(),
+            server_node: "tcp://node:11010".to_string(),
+            use_domain: false,
+            created_at: 0,
+        }];
+        let code = assign_room_code(&mut taken, "大厅", "tcp://node:11010", false, 1);
+        assert_eq!(code, room_code_candidate("大厅", "tcp://node:11010", 1));
+        assert_ne!(taken[0].code, taken[1].code);
+    }
+
+    #[test]
+    fn test_resolve_room_code() {
+        let mut list = Vec::new();
+        let code = assign_room_code(&mut list, "大厅", "tcp://node:11010", true, 1);
+
+        let resolved = resolve_room_code(&list, &format!(" {}-{} ", &code[..3], code[3..].to_lowercase())).unwrap();
+        assert_eq!(resolved.name, "大厅");
+        assert_eq!(resolved.server_node, "tcp://node:11010");
+        assert!(resolved.use_domain);
+        // 字符集不含 O，不可能是已分配的码
+        assert!(resolve_room_code(&list, "OOOOOO").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_clear_recent_lobbies() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = create_test_config_manager(&temp_dir).await;
+
+        manager.record_recent_lobby("大厅".to_string(), "tcp://node:11010".to_string(), true).await.unwrap();
+        let loaded = ConfigManager::load_from_file(&manager.config_path).await.unwrap();
+        let recent = loaded.recent_lobbies.unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].name, "大厅");
+        assert!(recent[0].use_domain);
+
+        // 不保存密码
+        let json = serde_json::to_string(&recent[0]).unwrap();
+        assert!(!json.contains("password"));
+
+        manager.clear_recent_lobbies().await.unwrap();
+        assert_eq!(manager.get_config().recent_lobbies, Some(Vec::new()));
+    }
+
     #[tokio::test]
     async fn test_reset_to_default() {
         let temp_dir = TempDir::new().unwrap();
@@ -1018,6 +2330,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_reload_if_changed_ignores_own_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = create_test_config_manager(&temp_dir).await;
+        // 文件尚不存在
+        assert!(!manager.reload_if_changed().await.unwrap());
+
+        manager.config.player_name = Some("本机".to_string());
+        manager.save().await.unwrap();
+        assert!(!manager.reload_if_changed().await.unwrap());
+
+        // 外部修改：重新加载
+        let mut external = manager.config.clone();
+        external.player_name = Some("外部编辑".to_string());
+        fs::write(&manager.config_path, serde_json::to_string_pretty(&external).unwrap()).await.unwrap();
+        assert!(manager.reload_if_changed().await.unwrap());
+        assert_eq!(manager.config.player_name.as_deref(), Some("外部编辑"));
+        assert!(!manager.reload_if_changed().await.unwrap());
+
+        // 损坏内容：保留旧配置，且只报告一次
+        fs::write(&manager.config_path, "{\"player_name\": ").await.unwrap();
+        assert!(manager.reload_if_changed().await.is_err());
+        assert_eq!(manager.config.player_name.as_deref(), Some("外部编辑"));
+        assert!(!manager.reload_if_changed().await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_load_nonexistent_config() {
         let temp_dir = TempDir::new().unwrap();
@@ -1039,6 +2377,7 @@ mod tests {
                 y: 200,
                 width: 300,
                 height: 400,
+                monitor: None,
             }),
             audio_device_id: Some("device123".to_string()),
             opacity: Some(0.85),
@@ -1074,4 +2413,72 @@ mod tests {
         let backup_config: UserConfig = serde_json::from_str(&backup_content).unwrap();
         assert_eq!(backup_config.player_name, Some("测试".to_string()));
     }
+
+    #[test]
+    fn test_export_excludes_machine_specific_fields() {
+        let config = UserConfig {
+            player_name: Some("测试".to_string()),
+            audio_device_id: Some("device123".to_string()),
+            ..UserConfig::default()
+        };
+
+        let full = build_config_export(&config, false).unwrap();
+        assert_eq!(full["format"], CONFIG_EXPORT_FORMAT);
+        assert_eq!(full["version"], CONFIG_EXPORT_VERSION);
+        assert_eq!(full["config"]["audio_device_id"], "device123");
+
+        let portable = build_config_export(&config, true).unwrap();
+        for field in MACHINE_SPECIFIC_FIELDS {
+            assert!(portable["config"].get(*field).is_none(), "{} 未被排除", field);
+        }
+        assert_eq!(portable["config"]["player_name"], "测试");
+
+        // 导入到另一台设备时保留该设备自己的机器相关字段
+        let other = UserConfig {
+            audio_device_id: Some("other-device".to_string()),
+            ..UserConfig::default()
+        };
+        let (merged, report) = merge_config_import(&other, &portable.to_string()).unwrap();
+        assert_eq!(merged.player_name, Some("测试".to_string()));
+        assert_eq!(merged.audio_device_id, Some("other-device".to_string()));
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_import_skips_incompatible_fields() {
+        let current = UserConfig::default();
+        let data = serde_json::json!({
+            "format": CONFIG_EXPORT_FORMAT,
+            "version": CONFIG_EXPORT_VERSION + 1,
+            "config": {
+                "player_name": "新名字",
+                "opacity": 5.0,
+                "mic_mode": "shout",
+                "some_future_option": true,
+            },
+        });
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data.to_string());
+
+        let (merged, report) = merge_config_import(&current, &encoded).unwrap();
+        assert_eq!(merged.player_name, Some("新名字".to_string()));
+        assert_eq!(merged.opacity, current.opacity);
+        assert_eq!(merged.mic_mode, current.mic_mode);
+        assert_eq!(report.applied, vec!["player_name".to_string()]);
+        // 版本提示 + 超出范围 + 非法枚举 + 未知字段
+        assert_eq!(report.warnings.len(), 4);
+    }
+
+    #[test]
+    fn test_import_format_checks() {
+        let current = UserConfig::default();
+
+        // 旧版直接导出的配置文件
+        let legacy = serde_json::json!({ "player_name": "旧版" }).to_string();
+        let (merged, _) = merge_config_import(&current, &legacy).unwrap();
+        assert_eq!(merged.player_name, Some("旧版".to_string()));
+
+        assert!(merge_config_import(&current, "not a config").is_err());
+        assert!(merge_config_import(&current, r#"{"format":"other","version":1,"config":{}}"#).is_err());
+        assert!(merge_config_import(&current, r#"{"format":"mctier-config","config":{}}"#).is_err());
+    }
 }
@@ -0,0 +1,225 @@
+// 用户配置的 schema 导出
+//
+// 为 `UserConfig` 的每个字段描述名称、类型、默认值、范围/选项与分组，前端据此自动渲染设置界面，
+// 新增配置项时只需在这里补一行描述。默认值直接取自 `UserConfig::default()` 的序列化结果，
+// 不会与实际默认值不一致。
+
+use serde::Serialize;
+
+use crate::modules::config_manager::UserConfig;
+
+/// 配置字段类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFieldType {
+    /// 布尔开关
+    Bool,
+    /// 整数
+    Integer,
+    /// 小数
+    Number,
+    /// 文本
+    String,
+    /// 从固定选项中选择
+    Enum,
+    /// 结构化对象（由专门的界面编辑）
+    Object,
+    /// 列表（由专门的界面编辑）
+    List,
+}
+
+/// 配置字段分组
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigGroup {
+    /// 基础
+    General,
+    /// 语音
+    Voice,
+    /// 快捷键
+    Hotkeys,
+    /// 窗口
+    Window,
+    /// 网络
+    Network,
+    /// 大厅
+    Lobby,
+    /// 文件与聊天
+    Sharing,
+    /// 安全
+    Security,
+    /// 由程序维护，不在设置界面展示
+    Internal,
+}
+
+/// 单个配置字段的元数据
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigFieldSchema {
+    /// 字段名（与配置 JSON 的键一致）
+    pub name: &'static str,
+    /// 显示名称
+    pub label: &'static str,
+    /// 字段类型
+    #[serde(rename = "type")]
+    pub field_type: ConfigFieldType,
+    /// 分组
+    pub group: ConfigGroup,
+    /// 默认值
+    pub default: serde_json::Value,
+    /// 最小值（数值类型）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// 最大值（数值类型）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// 可选值（枚举类型）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<&'static [&'static str]>,
+}
+
+/// 字段描述：名称、显示名称、类型、分组
+struct FieldSpec(&'static str, &'static str, ConfigFieldType, ConfigGroup);
+
+impl FieldSpec {
+    /// 数值范围
+    fn range(&self) -> Option<(f64, f64)> {
+        match self.0 {
+            "opacity" => Some((
+                crate::modules::window_opacity::MIN_WINDOW_OPACITY,
+                crate::modules::window_opacity::MAX_WINDOW_OPACITY,
+            )),
+            "voice_volume" => Some((0.0, 1.0)),
+            "vad_threshold" => Some((0.0, crate::modules::voice_service::MAX_VAD_THRESHOLD as f64)),
+            "reconnect_grace_secs" => Some((0.0, crate::modules::network_service::MAX_RECONNECT_GRACE_SECS as f64)),
+            "connect_timeout_secs" => Some((
+                crate::modules::network_service::MIN_CONNECT_TIMEOUT_SECS as f64,
+                crate::modules::network_service::MAX_CONNECT_TIMEOUT_SECS as f64,
+            )),
+            "chat_image_max_bytes" => Some((1.0, crate::modules::chat_service::MAX_CHAT_IMAGE_MAX_BYTES as f64)),
+            "upload_max_bytes" => Some((1.0, crate::modules::file_transfer::MAX_UPLOAD_MAX_BYTES as f64)),
+            _ => None,
+        }
+    }
+
+    /// 枚举选项（与对应枚举的 serde 名称一致）
+    fn options(&self) -> Option<&'static [&'static str]> {
+        match self.0 {
+            "mic_mode" => Some(&["toggle", "push_to_talk", "voice_activated"]),
+            "player_sort_order" => Some(&["name", "join_order", "speaking", "ready"]),
+            "lobby_password_policy" => Some(&["strict", "relaxed", "none"]),
+            "log_level" => Some(&["error", "warn", "info", "debug", "trace"]),
+            _ => None,
+        }
+    }
+}
+
+use ConfigFieldType as T;
+use ConfigGroup as G;
+
+/// `UserConfig` 全部字段的描述（顺序即界面展示顺序）
+const FIELDS: &[FieldSpec] = &[
+    FieldSpec("player_name", "玩家名称", T::String, G::General),
+    FieldSpec("auto_startup", "开机自启", T::Bool, G::General),
+    FieldSpec("enable_gpu_rendering", "GPU 渲染", T::Bool, G::General),
+    FieldSpec("player_sort_order", "玩家列表排序", T::Enum, G::General),
+    FieldSpec("log_level", "日志级别", T::Enum, G::General),
+    FieldSpec("mic_mode", "麦克风模式", T::Enum, G::Voice),
+    FieldSpec("audio_device_id", "麦克风设备", T::String, G::Voice),
+    FieldSpec("speaker_device_id", "扬声器设备", T::String, G::Voice),
+    FieldSpec("voice_volume", "语音音量", T::Number, G::Voice),
+    FieldSpec("vad_threshold", "麦克风灵敏度阈值", T::Number, G::Voice),
+    FieldSpec("voice_loudness_normalization", "跨玩家响度均衡", T::Bool, G::Voice),
+    FieldSpec("voice_feedback_sound", "开关麦提示音", T::Bool, G::Voice),
+    FieldSpec("persisted_mutes", "记住的静音玩家", T::Object, G::Voice),
+    FieldSpec("mic_hotkey", "麦克风快捷键", T::String, G::Hotkeys),
+    FieldSpec("global_mute_hotkey", "全局听筒快捷键", T::String, G::Hotkeys),
+    FieldSpec("push_to_talk_hotkey", "按住说话快捷键", T::String, G::Hotkeys),
+    FieldSpec("opacity", "窗口透明度", T::Number, G::Window),
+    FieldSpec("always_on_top", "窗口置顶", T::Bool, G::Window),
+    FieldSpec("remember_window_position", "记住窗口位置", T::Bool, G::Window),
+    FieldSpec("close_to_tray", "关闭时最小化到托盘", T::Bool, G::Window),
+    FieldSpec("preferred_server", "首选服务器节点", T::String, G::Network),
+    FieldSpec("use_private_server", "使用私有服务器", T::Bool, G::Network),
+    FieldSpec("private_easytier_server", "私有 EasyTier 节点", T::String, G::Network),
+    FieldSpec("private_signaling_server", "私有信令服务器", T::String, G::Network),
+    FieldSpec("custom_easytier_nodes", "自定义 EasyTier 节点", T::List, G::Network),
+    FieldSpec("reconnect_grace_secs", "断线重连宽限期（秒）", T::Integer, G::Network),
+    FieldSpec("connect_timeout_secs", "连接超时（秒）", T::Integer, G::Network),
+    FieldSpec("auto_cleanup_easytier_instances", "自动清理其它 EasyTier 实例", T::Bool, G::Network),
+    FieldSpec("upnp_port_mapping", "UPnP 端口映射", T::Bool, G::Network),
+    FieldSpec("preferred_relay", "偏好中继节点", T::String, G::Network),
+    FieldSpec("port_config", "服务端口（信令 / 文件 / 聊天）", T::Object, G::Network),
+    FieldSpec("global_easytier_advanced_config", "EasyTier 全局高级配置", T::Object, G::Network),
+    FieldSpec("lobby_easytier_advanced_config", "EasyTier 大厅高级配置", T::Object, G::Network),
+    FieldSpec("easytier_launch_options", "EasyTier 启动选项", T::Object, G::Network),
+    FieldSpec("exit_node_config", "出口节点（已废弃）", T::Object, G::Internal),
+    FieldSpec("auto_lobby", "自动进入大厅", T::Object, G::Lobby),
+    FieldSpec("lobby_password_policy", "大厅密码强度要求", T::Enum, G::Lobby),
+    FieldSpec("lobby_auto_actions", "进入大厅后的自动动作", T::List, G::Lobby),
+    FieldSpec("mc_chat_bridge", "Minecraft 聊天互通", T::Object, G::Lobby),
+    FieldSpec("enable_http_tls", "文件/聊天服务 TLS", T::Bool, G::Sharing),
+    FieldSpec("chat_image_max_bytes", "聊天图片大小上限（字节）", T::Integer, G::Sharing),
+    FieldSpec("upload_max_bytes", "上传文件大小上限（字节）", T::Integer, G::Sharing),
+    FieldSpec("share_activity_presence", "浏览共享时告知共享者", T::Bool, G::Sharing),
+    FieldSpec("p2p_signaling_plaintext_compat", "兼容旧版明文信令", T::Bool, G::Security),
+    FieldSpec("encrypt_config_file", "加密配置文件", T::Bool, G::Security),
+    FieldSpec("window_position", "窗口位置", T::Object, G::Internal),
+    FieldSpec("recent_lobbies", "最近大厅", T::List, G::Internal),
+    FieldSpec("room_codes", "房间码", T::List, G::Internal),
+];
+
+/// 导出 `UserConfig` 的 schema
+pub fn config_schema() -> Vec<ConfigFieldSchema> {
+    let defaults = serde_json::to_value(UserConfig::default()).unwrap_or(serde_json::Value::Null);
+    FIELDS
+        .iter()
+        .map(|spec| {
+            let range = spec.range();
+            ConfigFieldSchema {
+                name: spec.0,
+                label: spec.1,
+                field_type: spec.2,
+                group: spec.3,
+                default: defaults.get(spec.0).cloned().unwrap_or(serde_json::Value::Null),
+                min: range.map(|r| r.0),
+                max: range.map(|r| r.1),
+                options: spec.options(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_schema_matches_user_config_fields() {
+        let defaults = serde_json::to_value(UserConfig::default()).unwrap();
+        let config_fields: HashSet<&str> = defaults.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        let schema = config_schema();
+        let schema_fields: HashSet<&str> = schema.iter().map(|f| f.name).collect();
+
+        assert_eq!(schema.len(), config_fields.len(), "schema 字段数与 UserConfig 不一致");
+        assert_eq!(schema_fields, config_fields);
+    }
+
+    #[test]
+    fn test_schema_defaults_and_options_are_valid() {
+        for field in config_schema() {
+            if let (Some(min), Some(max), Some(value)) = (field.min, field.max, field.default.as_f64()) {
+                assert!((min..=max).contains(&value), "{} 默认值超出范围", field.name);
+            }
+            if let Some(options) = field.options {
+                // 每个选项都能反序列化为对应字段
+                for option in options {
+                    let mut config = serde_json::to_value(UserConfig::default()).unwrap();
+                    config[field.name] = serde_json::json!(option);
+                    assert!(serde_json::from_value::<UserConfig>(config).is_ok(), "{} 选项 {} 无效", field.name, option);
+                }
+            }
+        }
+    }
+}
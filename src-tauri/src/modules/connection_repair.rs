@@ -0,0 +1,457 @@
+// 点对点连接修复（手动重新打洞）
+//
+// 两名玩家之间语音连接卡死时由玩家手动触发，流程为：
+// 1. 重置双方的 WebRTC 信令状态
+// 2. 重新交换发现包，确认对方仍可达
+// 3. 对方没有回应（或直连恢复失败）时请求中继：改用广播发送修复请求，由虚拟网络转发
+// 4. 等待连接恢复（ping 通对方虚拟 IP）
+// 每进入一个阶段都会推送 `connection-repair-progress` 事件。
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{Emitter, State};
+use tokio::sync::Mutex;
+
+use crate::modules::p2p_signaling::P2PSignalingService;
+use crate::modules::error::CommandError;
+use crate::modules::tauri_commands::AppState;
+
+/// 等待对方回应发现包的时间
+const REDISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// 等待连接恢复的时间
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// 轮询对方回应的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 修复流程阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepairStage {
+    /// 重置 WebRTC 信令状态
+    ResettingSignaling,
+    /// 重新交换发现包
+    Rediscovering,
+    /// 请求中继
+    RequestingRelay,
+    /// 等待连接恢复
+    Reconnecting,
+    /// 修复成功
+    Succeeded,
+    /// 修复失败
+    Failed,
+}
+
+/// 推动修复流程的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairEvent {
+    /// 信令状态已重置
+    SignalingReset,
+    /// 对方回应了发现包
+    PeerResponded,
+    /// 等待发现回应超时
+    DiscoveryTimedOut,
+    /// 中继请求已发出
+    RelayReady,
+    /// 无法请求中继
+    RelayUnavailable,
+    /// 连接已恢复
+    PeerConnected,
+    /// 等待连接恢复超时
+    ConnectTimedOut,
+}
+
+/// 修复流程错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RepairError {
+    #[error("修复流程处于 {stage:?} 阶段，不接受事件 {event:?}")]
+    InvalidTransition { stage: RepairStage, event: RepairEvent },
+}
+
+/// 修复流程状态机
+#[derive(Debug)]
+pub struct RepairFlow {
+    stage: RepairStage,
+    /// 是否已改走中继
+    via_relay: bool,
+}
+
+impl Default for RepairFlow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RepairFlow {
+    pub fn new() -> Self {
+        Self {
+            stage: RepairStage::ResettingSignaling,
+            via_relay: false,
+        }
+    }
+
+    /// 当前阶段
+    pub fn stage(&self) -> RepairStage {
+        self.stage
+    }
+
+    /// 是否已改走中继
+    pub fn via_relay(&self) -> bool {
+        self.via_relay
+    }
+
+    /// 流程是否已结束
+    pub fn is_finished(&self) -> bool {
+        matches!(self.stage, RepairStage::Succeeded | RepairStage::Failed)
+    }
+
+    /// 根据事件推进到下一阶段
+    ///
+    /// 直连恢复超时后还会尝试一次中继；中继后仍超时则判定失败。
+    pub fn advance(&mut self, event: RepairEvent) -> Result<RepairStage, RepairError> {
+        use RepairEvent::*;
+        use RepairStage::*;
+
+        let next = match (self.stage, event) {
+            (ResettingSignaling, SignalingReset) => Rediscovering,
+            (Rediscovering, PeerResponded) => Reconnecting,
+            (Rediscovering, DiscoveryTimedOut) => RequestingRelay,
+            (RequestingRelay, RelayReady) => {
+                self.via_relay = true;
+                Reconnecting
+            }
+            (RequestingRelay, RelayUnavailable) => Failed,
+            (Reconnecting, PeerConnected) => Succeeded,
+            (Reconnecting, ConnectTimedOut) if !self.via_relay => RequestingRelay,
+            (Reconnecting, ConnectTimedOut) => Failed,
+            (stage, event) => return Err(RepairError::InvalidTransition { stage, event }),
+        };
+        self.stage = next;
+        Ok(next)
+    }
+}
+
+/// 修复流程中各阶段的具体操作
+pub trait RepairDriver {
+    /// 重置与对方的信令状态（尽力而为）
+    fn reset_signaling(&self, peer_id: &str) -> impl Future<Output = ()> + Send;
+    /// 重新交换发现包，返回对方是否在超时前回应
+    fn rediscover(&self, peer_id: &str) -> impl Future<Output = bool> + Send;
+    /// 请求中继，返回请求是否已发出
+    fn request_relay(&self, peer_id: &str) -> impl Future<Output = bool> + Send;
+    /// 等待连接恢复，返回是否在超时前恢复
+    fn wait_connected(&self, peer_id: &str) -> impl Future<Output = bool> + Send;
+}
+
+/// 执行修复流程直到结束
+///
+/// # 参数
+/// * `peer_id` - 目标玩家 ID
+/// * `driver` - 各阶段的具体操作
+/// * `on_progress` - 每进入一个阶段时回调（阶段, 是否已改走中继）
+///
+/// # 返回
+/// 最终阶段（Succeeded 或 Failed）
+pub async fn run_connection_repair<D: RepairDriver>(
+    peer_id: &str,
+    driver: &D,
+    mut on_progress: impl FnMut(RepairStage, bool),
+) -> RepairStage {
+    let mut flow = RepairFlow::new();
+    on_progress(flow.stage(), flow.via_relay());
+
+    while !flow.is_finished() {
+        let event = match flow.stage() {
+            RepairStage::ResettingSignaling => {
+                driver.reset_signaling(peer_id).await;
+                RepairEvent::SignalingReset
+            }
+            RepairStage::Rediscovering => {
+                if driver.rediscover(peer_id).await {
+                    RepairEvent::PeerResponded
+                } else {
+                    RepairEvent::DiscoveryTimedOut
+                }
+            }
+            RepairStage::RequestingRelay => {
+                if driver.request_relay(peer_id).await {
+                    RepairEvent::RelayReady
+                } else {
+                    RepairEvent::RelayUnavailable
+                }
+            }
+            RepairStage::Reconnecting => {
+                if driver.wait_connected(peer_id).await {
+                    RepairEvent::PeerConnected
+                } else {
+                    RepairEvent::ConnectTimedOut
+                }
+            }
+            RepairStage::Succeeded | RepairStage::Failed => break,
+        };
+        match flow.advance(event) {
+            Ok(stage) => on_progress(stage, flow.via_relay()),
+            Err(e) => {
+                log::error!("❌ [Repair] {}", e);
+                on_progress(RepairStage::Failed, flow.via_relay());
+                return RepairStage::Failed;
+            }
+        }
+    }
+    flow.stage()
+}
+
+/// 基于 P2P 信令服务与前端 WebRTC 的修复操作
+struct AppRepairDriver {
+    app: tauri::AppHandle,
+    p2p_signaling: Arc<Mutex<P2PSignalingService>>,
+}
+
+impl AppRepairDriver {
+    /// 通知本机前端重建与对方的 WebRTC 连接（由本机主动发起协商）
+    fn emit_reset(&self, peer_id: &str) {
+        let _ = self.app.emit("webrtc-repair-peer", serde_json::json!({
+            "peerId": peer_id,
+            "initiate": true,
+        }));
+    }
+}
+
+impl RepairDriver for AppRepairDriver {
+    async fn reset_signaling(&self, peer_id: &str) {
+        self.emit_reset(peer_id);
+        let result = self.p2p_signaling.lock().await.send_repair_request(peer_id, false).await;
+        if let Err(e) = result {
+            log::warn!("⚠️ [Repair] 发送修复请求到 {} 失败: {}", peer_id, e);
+        }
+    }
+
+    async fn rediscover(&self, peer_id: &str) -> bool {
+        let started = Instant::now();
+        let result = self.p2p_signaling.lock().await.send_discovery_to(peer_id).await;
+        if let Err(e) = result {
+            log::warn!("⚠️ [Repair] 发送发现包失败: {}", e);
+            return false;
+        }
+        while started.elapsed() < REDISCOVERY_TIMEOUT {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let peer = self.p2p_signaling.lock().await.get_peer(peer_id).await;
+            if peer.is_some_and(|p| p.last_seen > started) {
+                return true;
+            }
+        }
+        false
+    }
+
+    async fn request_relay(&self, peer_id: &str) -> bool {
+        let result = self.p2p_signaling.lock().await.send_repair_request(peer_id, true).await;
+        match result {
+            Ok(()) => {
+                self.emit_reset(peer_id);
+                true
+            }
+            Err(e) => {
+                log::warn!("⚠️ [Repair] 请求中继失败: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn wait_connected(&self, peer_id: &str) -> bool {
+        let Some(peer) = self.p2p_signaling.lock().await.get_peer(peer_id).await else {
+            return false;
+        };
+        let ip = peer.addr.ip().to_string();
+        let started = Instant::now();
+        while started.elapsed() < RECONNECT_TIMEOUT {
+            if crate::modules::network_diagnostics::ping_virtual_ip(&ip).await.success {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        false
+    }
+}
+
+/// 正在修复的玩家（同一玩家同时只允许一个修复流程）
+fn repairing_peers() -> &'static StdMutex<HashSet<String>> {
+    static PEERS: OnceLock<StdMutex<HashSet<String>>> = OnceLock::new();
+    PEERS.get_or_init(|| StdMutex::new(HashSet::new()))
+}
+
+/// 手动修复与指定玩家的点对点连接
+///
+/// 全过程通过 `connection-repair-progress` 事件推送进度。
+///
+/// # 参数
+/// * `peer_id` - 目标玩家 ID
+///
+/// # 返回
+/// * `Ok(RepairStage)` - 最终阶段（succeeded / failed）
+/// * `Err(CommandError)` - 已有修复流程在进行
+#[tauri::command]
+pub async fn repair_connection(
+    peer_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<RepairStage, CommandError> {
+    log::info!("🔧 [Repair] 开始修复与 {} 的连接", peer_id);
+    if !repairing_peers().lock().unwrap_or_else(|e| e.into_inner()).insert(peer_id.clone()) {
+        return Err(CommandError::validation("正在修复与该玩家的连接"));
+    }
+
+    let p2p_signaling = state.get_p2p_signaling();
+    let driver = AppRepairDriver {
+        app: app.clone(),
+        p2p_signaling,
+    };
+    let result = run_connection_repair(&peer_id, &driver, |stage, via_relay| {
+        log::info!("🔧 [Repair] {} -> {:?}（中继: {}）", peer_id, stage, via_relay);
+        let _ = app.emit("connection-repair-progress", serde_json::json!({
+            "peerId": peer_id,
+            "stage": stage,
+            "viaRelay": via_relay,
+        }));
+    })
+    .await;
+
+    repairing_peers().lock().unwrap_or_else(|e| e.into_inner()).remove(&peer_id);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按预设结果执行各阶段，并记录调用顺序
+    struct ScriptedDriver {
+        rediscover: bool,
+        relay: bool,
+        /// 每次 wait_connected 依次返回的结果
+        connected: StdMutex<Vec<bool>>,
+        calls: StdMutex<Vec<&'static str>>,
+    }
+
+    impl ScriptedDriver {
+        fn new(rediscover: bool, relay: bool, connected: Vec<bool>) -> Self {
+            Self {
+                rediscover,
+                relay,
+                connected: StdMutex::new(connected),
+                calls: StdMutex::new(Vec::new()),
+            }
+        }
+
+        fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl RepairDriver for ScriptedDriver {
+        async fn reset_signaling(&self, _peer_id: &str) {
+            self.calls.lock().unwrap().push("reset");
+        }
+
+        async fn rediscover(&self, _peer_id: &str) -> bool {
+            self.calls.lock().unwrap().push("rediscover");
+            self.rediscover
+        }
+
+        async fn request_relay(&self, _peer_id: &str) -> bool {
+            self.calls.lock().unwrap().push("relay");
+            self.relay
+        }
+
+        async fn wait_connected(&self, _peer_id: &str) -> bool {
+            self.calls.lock().unwrap().push("wait");
+            let mut connected = self.connected.lock().unwrap();
+            if connected.is_empty() {
+                false
+            } else {
+                connected.remove(0)
+            }
+        }
+    }
+
+    async fn run(driver: &ScriptedDriver) -> (RepairStage, Vec<(RepairStage, bool)>) {
+        let mut progress = Vec::new();
+        let result = run_connection_repair("peer-1", driver, |stage, via_relay| progress.push((stage, via_relay))).await;
+        (result, progress)
+    }
+
+    #[test]
+    fn test_flow_transitions() {
+        let mut flow = RepairFlow::new();
+        assert_eq!(flow.advance(RepairEvent::SignalingReset), Ok(RepairStage::Rediscovering));
+        assert_eq!(flow.advance(RepairEvent::DiscoveryTimedOut), Ok(RepairStage::RequestingRelay));
+        assert_eq!(flow.advance(RepairEvent::RelayReady), Ok(RepairStage::Reconnecting));
+        assert!(flow.via_relay());
+        // 已走中继后再次超时直接失败
+        assert_eq!(flow.advance(RepairEvent::ConnectTimedOut), Ok(RepairStage::Failed));
+        assert!(flow.is_finished());
+    }
+
+    #[test]
+    fn test_flow_rejects_invalid_event() {
+        let mut flow = RepairFlow::new();
+        assert_eq!(
+            flow.advance(RepairEvent::PeerConnected),
+            Err(RepairError::InvalidTransition {
+                stage: RepairStage::ResettingSignaling,
+                event: RepairEvent::PeerConnected,
+            })
+        );
+        // 非法事件不改变当前阶段
+        assert_eq!(flow.stage(), RepairStage::ResettingSignaling);
+    }
+
+    #[tokio::test]
+    async fn test_direct_repair_succeeds() {
+        let driver = ScriptedDriver::new(true, true, vec![true]);
+        let (result, progress) = run(&driver).await;
+
+        assert_eq!(result, RepairStage::Succeeded);
+        assert_eq!(driver.calls(), vec!["reset", "rediscover", "wait"]);
+        assert_eq!(
+            progress,
+            vec![
+                (RepairStage::ResettingSignaling, false),
+                (RepairStage::Rediscovering, false),
+                (RepairStage::Reconnecting, false),
+                (RepairStage::Succeeded, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_peer_falls_back_to_relay() {
+        let driver = ScriptedDriver::new(false, true, vec![true]);
+        let (result, progress) = run(&driver).await;
+
+        assert_eq!(result, RepairStage::Succeeded);
+        assert_eq!(driver.calls(), vec!["reset", "rediscover", "relay", "wait"]);
+        assert_eq!(progress.last(), Some(&(RepairStage::Succeeded, true)));
+    }
+
+    #[tokio::test]
+    async fn test_direct_timeout_retries_once_via_relay() {
+        let driver = ScriptedDriver::new(true, true, vec![false, false]);
+        let (result, _) = run(&driver).await;
+
+        assert_eq!(result, RepairStage::Failed);
+        assert_eq!(driver.calls(), vec!["reset", "rediscover", "wait", "relay", "wait"]);
+    }
+
+    #[tokio::test]
+    async fn test_relay_unavailable_fails() {
+        let driver = ScriptedDriver::new(false, false, vec![]);
+        let (result, progress) = run(&driver).await;
+
+        assert_eq!(result, RepairStage::Failed);
+        assert_eq!(driver.calls(), vec!["reset", "rediscover", "relay"]);
+        assert_eq!(progress.last(), Some(&(RepairStage::Failed, false)));
+    }
+}
@@ -0,0 +1,432 @@
+// 远程文件下载任务：暂停 / 恢复 / 崩溃后续传
+//
+// 下载过程中定期把任务进度（已写入字节、ETag）持久化到配置目录下的 downloads.json。
+// 暂停时停止读流并保留已下载的部分文件；恢复时带 `Range: bytes=<已下载>-` 和 `If-Range: <ETag>` 重新请求，
+// 共享端返回 206 则从断点追加写入，返回 200（文件已变化或不支持续传）则从头下载。
+// 应用崩溃或被强制退出后，持久化记录中仍处于"下载中"的任务在下次启动时视为已暂停，可以继续恢复。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+/// 下载进度持久化的最小间隔
+pub const PROGRESS_PERSIST_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 持久化文件名
+const DOWNLOAD_TASKS_FILE_NAME: &str = "downloads.json";
+
+/// 下载任务状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadTaskStatus {
+    /// 下载中
+    Downloading,
+    /// 已暂停（含崩溃后恢复的任务）
+    Paused,
+}
+
+/// 可续传的下载任务记录（不保存共享密码）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DownloadTaskRecord {
+    /// 任务ID（由前端生成）
+    pub task_id: String,
+    /// 共享者虚拟 IP
+    pub peer_ip: String,
+    /// 共享ID
+    pub share_id: String,
+    /// 共享内的文件路径
+    pub file_path: String,
+    /// 本地保存路径
+    pub save_path: String,
+    /// 已写入本地文件的字节数
+    pub downloaded: u64,
+    /// 文件总大小（未知时为 0）
+    pub total: u64,
+    /// 共享端返回的 ETag，用于 If-Range 校验文件是否变化
+    pub etag: Option<String>,
+    /// 任务状态
+    pub status: DownloadTaskStatus,
+    /// 是否为整个共享文件夹的打包下载（此时 `file_path` 为空）
+    #[serde(default)]
+    pub whole_share: bool,
+}
+
+impl DownloadTaskRecord {
+    /// 创建新的下载任务记录
+    pub fn new(task_id: String, peer_ip: String, share_id: String, file_path: String, save_path: String) -> Self {
+        Self {
+            task_id,
+            peer_ip,
+            share_id,
+            file_path,
+            save_path,
+            downloaded: 0,
+            total: 0,
+            etag: None,
+            status: DownloadTaskStatus::Downloading,
+            whole_share: false,
+        }
+    }
+
+    /// 创建整个共享文件夹打包下载的任务记录
+    pub fn whole_share(task_id: String, peer_ip: String, share_id: String, save_path: String) -> Self {
+        Self {
+            whole_share: true,
+            ..Self::new(task_id, peer_ip, share_id, String::new(), save_path)
+        }
+    }
+
+    /// 共享端的下载路径
+    pub fn remote_path(&self) -> String {
+        let share_id = urlencoding::encode(&self.share_id);
+        if self.whole_share {
+            format!("/api/shares/{}/download-all", share_id)
+        } else {
+            format!("/api/shares/{}/download/{}", share_id, urlencoding::encode(&self.file_path))
+        }
+    }
+}
+
+/// 续传请求的写入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumePlan {
+    /// 从头写入（截断本地文件）
+    Fresh,
+    /// 从偏移处追加写入
+    Continue { offset: u64 },
+}
+
+/// 解析 `Content-Range: bytes <start>-<end>/<total>` 的起始偏移
+pub fn parse_content_range_start(value: &str) -> Option<u64> {
+    value
+        .trim()
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// 根据共享端响应决定写入方式
+///
+/// # 参数
+/// * `requested_offset` - 请求的续传偏移（0 表示未请求续传）
+/// * `status` - HTTP 状态码
+/// * `content_range` - 响应的 Content-Range 头
+pub fn plan_resume(requested_offset: u64, status: u16, content_range: Option<&str>) -> Result<ResumePlan, String> {
+    match status {
+        200 => Ok(ResumePlan::Fresh),
+        206 => match content_range.and_then(parse_content_range_start) {
+            Some(start) if start == requested_offset => Ok(ResumePlan::Continue { offset: start }),
+            Some(start) => Err(format!("续传位置不一致：请求 {}，返回 {}", requested_offset, start)),
+            None => Err("续传响应缺少 Content-Range".to_string()),
+        },
+        other => Err(format!("下载失败: HTTP {}", other)),
+    }
+}
+
+/// 写入数据流的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOutcome {
+    /// 数据流读取完毕
+    Completed,
+    /// 收到暂停请求
+    Paused,
+    /// 收到取消请求
+    Cancelled,
+}
+
+/// 下载任务的暂停 / 取消标志
+#[derive(Debug, Default)]
+pub struct DownloadControl {
+    pub pause: AtomicBool,
+    pub cancel: AtomicBool,
+}
+
+/// 把数据流写入文件，每个块写入前检查暂停与取消
+///
+/// # 参数
+/// * `file` - 已定位到写入起点的文件
+/// * `stream` - 数据流
+/// * `control` - 暂停 / 取消标志
+/// * `on_chunk` - 每写入一个块后的回调（参数为该块数据）
+pub async fn write_stream<S, E>(
+    file: &mut tokio::fs::File,
+    mut stream: S,
+    control: &DownloadControl,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<StreamOutcome, String>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    while let Some(chunk) = stream.next().await {
+        if control.cancel.load(Ordering::Relaxed) {
+            return Ok(StreamOutcome::Cancelled);
+        }
+        if control.pause.load(Ordering::Relaxed) {
+            file.flush().await.map_err(|e| format!("刷新文件失败: {}", e))?;
+            return Ok(StreamOutcome::Paused);
+        }
+        let chunk = chunk.map_err(|e| format!("下载中断: {}", e))?;
+        file.write_all(&chunk).await.map_err(|e| format!("写入文件失败: {}", e))?;
+        on_chunk(&chunk);
+    }
+    file.flush().await.map_err(|e| format!("刷新文件失败: {}", e))?;
+    Ok(StreamOutcome::Completed)
+}
+
+/// 下载任务持久化存储
+#[derive(Debug, Default)]
+pub struct DownloadTaskStore {
+    /// 持久化文件路径（None 表示仅内存）
+    path: Option<PathBuf>,
+    /// 任务ID -> 任务记录
+    tasks: HashMap<String, DownloadTaskRecord>,
+}
+
+impl DownloadTaskStore {
+    /// 从持久化文件加载
+    ///
+    /// 上次退出时仍处于下载中的任务（崩溃或强制退出）统一视为已暂停。
+    pub fn load(path: PathBuf) -> Self {
+        let mut tasks: HashMap<String, DownloadTaskRecord> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Vec<DownloadTaskRecord>>(&content).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| (t.task_id.clone(), t))
+            .collect();
+        for task in tasks.values_mut() {
+            if task.status == DownloadTaskStatus::Downloading {
+                log::info!("发现未完成的下载任务，已标记为暂停: {}", task.task_id);
+                task.status = DownloadTaskStatus::Paused;
+            }
+        }
+        Self { path: Some(path), tasks }
+    }
+
+    /// 获取任务记录
+    pub fn get(&self, task_id: &str) -> Option<DownloadTaskRecord> {
+        self.tasks.get(task_id).cloned()
+    }
+
+    /// 列出所有任务记录
+    pub fn list(&self) -> Vec<DownloadTaskRecord> {
+        self.tasks.values().cloned().collect()
+    }
+
+    /// 新增或更新任务记录并持久化
+    pub fn upsert(&mut self, record: DownloadTaskRecord) {
+        self.tasks.insert(record.task_id.clone(), record);
+        self.persist();
+    }
+
+    /// 移除任务记录并持久化
+    pub fn remove(&mut self, task_id: &str) -> Option<DownloadTaskRecord> {
+        let removed = self.tasks.remove(task_id);
+        if removed.is_some() {
+            self.persist();
+        }
+        removed
+    }
+
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let tasks: Vec<&DownloadTaskRecord> = self.tasks.values().collect();
+        let result = serde_json::to_string_pretty(&tasks)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let temp_path = path.with_extension("json.tmp");
+                std::fs::write(&temp_path, json).map_err(|e| e.to_string())?;
+                std::fs::rename(&temp_path, path).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            log::warn!("保存下载任务失败: {}", e);
+        }
+    }
+}
+
+/// 全局下载任务存储（位于配置目录下）
+pub fn download_store() -> &'static parking_lot::Mutex<DownloadTaskStore> {
+    static STORE: OnceLock<parking_lot::Mutex<DownloadTaskStore>> = OnceLock::new();
+    STORE.get_or_init(|| {
+        let store = match dirs::config_dir() {
+            Some(dir) => DownloadTaskStore::load(dir.join("mctier").join(DOWNLOAD_TASKS_FILE_NAME)),
+            None => DownloadTaskStore::default(),
+        };
+        parking_lot::Mutex::new(store)
+    })
+}
+
+/// 正在运行的下载任务的控制标志（task_id -> 标志）
+pub fn active_downloads() -> &'static dashmap::DashMap<String, std::sync::Arc<DownloadControl>> {
+    static ACTIVE: OnceLock<dashmap::DashMap<String, std::sync::Arc<DownloadControl>>> = OnceLock::new();
+    ACTIVE.get_or_init(dashmap::DashMap::new)
+}
+
+/// 计算本地已下载部分的 SHA-256（续传时用于接上完整文件的哈希）
+pub async fn hash_file_prefix(path: &Path, len: u64) -> std::io::Result<sha2::Sha256> {
+    use sha2::Digest;
+    use tokio::io::AsyncReadExt;
+
+    let mut hasher = sha2::Sha256::new();
+    let file = tokio::fs::File::open(path).await?;
+    let mut reader = file.take(len);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(parts: &[&'static [u8]]) -> impl Stream<Item = Result<Bytes, String>> + Unpin {
+        futures_util::stream::iter(parts.iter().map(|p| Ok(Bytes::from_static(p))).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_plan_resume() {
+        assert_eq!(plan_resume(0, 200, None), Ok(ResumePlan::Fresh));
+        assert_eq!(plan_resume(4, 206, Some("bytes 4-9/10")), Ok(ResumePlan::Continue { offset: 4 }));
+        // 文件已变化，共享端忽略 Range 返回完整文件
+        assert_eq!(plan_resume(4, 200, None), Ok(ResumePlan::Fresh));
+        assert!(plan_resume(4, 206, Some("bytes 0-9/10")).is_err());
+        assert!(plan_resume(4, 206, None).is_err());
+        assert!(plan_resume(4, 416, Some("bytes */10")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pause_then_resume_from_breakpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("modpack.zip");
+
+        // 第一段：写入一个块后暂停，已下载部分保留在磁盘上
+        let control = DownloadControl::default();
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let mut downloaded = 0u64;
+        let outcome = write_stream(&mut file, chunks(&[b"0123", b"4567", b"89"]), &control, |chunk| {
+            downloaded += chunk.len() as u64;
+            control.pause.store(true, Ordering::Relaxed);
+        })
+        .await
+        .unwrap();
+        drop(file);
+        assert_eq!(outcome, StreamOutcome::Paused);
+        assert_eq!(downloaded, 4);
+        assert_eq!(std::fs::read(&path).unwrap(), b"0123");
+
+        // 恢复：共享端返回 206，从断点追加剩余部分
+        let plan = plan_resume(downloaded, 206, Some("bytes 4-9/10")).unwrap();
+        assert_eq!(plan, ResumePlan::Continue { offset: 4 });
+        let control = DownloadControl::default();
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await.unwrap();
+        let outcome = write_stream(&mut file, chunks(&[b"4567", b"89"]), &control, |chunk| {
+            downloaded += chunk.len() as u64;
+        })
+        .await
+        .unwrap();
+        drop(file);
+        assert_eq!(outcome, StreamOutcome::Completed);
+        assert_eq!(downloaded, 10);
+        assert_eq!(std::fs::read(&path).unwrap(), b"0123456789");
+
+        // 续传后的整体哈希与一次性下载一致
+        use sha2::Digest;
+        let resumed = hash_file_prefix(&path, downloaded).await.unwrap().finalize();
+        assert_eq!(resumed, sha2::Sha256::digest(b"0123456789"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.bin");
+        let control = DownloadControl::default();
+        control.cancel.store(true, Ordering::Relaxed);
+
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let outcome = write_stream(&mut file, chunks(&[b"data"]), &control, |_| {}).await.unwrap();
+        assert_eq!(outcome, StreamOutcome::Cancelled);
+        assert!(std::fs::read(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_store_persists_and_recovers_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DOWNLOAD_TASKS_FILE_NAME);
+
+        let mut store = DownloadTaskStore::load(path.clone());
+        let mut running = DownloadTaskRecord::new(
+            "task-1".to_string(),
+            "10.126.126.2".to_string(),
+            "share-1".to_string(),
+            "modpack.zip".to_string(),
+            "D:/Downloads/modpack.zip".to_string(),
+        );
+        running.downloaded = 4;
+        running.total = 10;
+        running.etag = Some("\"abc\"".to_string());
+        store.upsert(running.clone());
+        store.upsert(DownloadTaskRecord::new(
+            "task-2".to_string(),
+            "10.126.126.2".to_string(),
+            "share-1".to_string(),
+            "b.zip".to_string(),
+            "D:/Downloads/b.zip".to_string(),
+        ));
+        store.remove("task-2");
+
+        // 模拟崩溃后重新加载：进度保留，下载中的任务变为已暂停
+        let reloaded = DownloadTaskStore::load(path);
+        assert_eq!(reloaded.list().len(), 1);
+        let task = reloaded.get("task-1").unwrap();
+        assert_eq!(task.status, DownloadTaskStatus::Paused);
+        assert_eq!(task.downloaded, 4);
+        assert_eq!(task.etag, running.etag);
+    }
+
+    #[test]
+    fn test_remote_path_for_file_and_whole_share() {
+        let file = DownloadTaskRecord::new(
+            "task-1".to_string(),
+            "10.126.126.2".to_string(),
+            "share-1".to_string(),
+            "mods/模组 A.jar".to_string(),
+            "D:/Downloads/a.jar".to_string(),
+        );
+        assert_eq!(file.remote_path(), "/api/shares/share-1/download/mods%2F%E6%A8%A1%E7%BB%84%20A.jar");
+
+        let all = DownloadTaskRecord::whole_share(
+            "task-2".to_string(),
+            "10.126.126.2".to_string(),
+            "share-1".to_string(),
+            "D:/Downloads/mods.zip".to_string(),
+        );
+        assert_eq!(all.remote_path(), "/api/shares/share-1/download-all");
+
+        // 旧版本持久化的记录没有 whole_share 字段
+        let mut value = serde_json::to_value(&file).unwrap();
+        value.as_object_mut().unwrap().remove("whole_share");
+        let old: DownloadTaskRecord = serde_json::from_value(value).unwrap();
+        assert!(!old.whole_share);
+    }
+}
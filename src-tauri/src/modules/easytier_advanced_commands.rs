@@ -4,6 +4,7 @@
 
 use tauri::State;
 use crate::modules::tauri_commands::AppState;
+use crate::modules::error::{CommandError, ErrorCode};
 
 // ==================== EasyTier 高级配置命令 ====================
 
@@ -14,12 +15,12 @@ use crate::modules::tauri_commands::AppState;
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn save_global_easytier_advanced_config(
     config_json: serde_json::Value,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use crate::modules::config_manager::EasyTierAdvancedConfig;
     
     log::info!("保存全局 EasyTier 高级配置");
@@ -29,8 +30,7 @@ pub async fn save_global_easytier_advanced_config(
     let config: EasyTierAdvancedConfig = serde_json::from_value(config_json)
         .map_err(|e| format!("解析配置失败: {}", e))?;
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     cfg_mgr.update_config(|user_config| {
@@ -45,13 +45,12 @@ pub async fn save_global_easytier_advanced_config(
 /// 
 /// # 返回
 /// * `Ok(serde_json::Value)` - 全局 EasyTier 高级配置
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_global_easytier_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+pub async fn get_global_easytier_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, CommandError> {
     log::info!("获取全局 EasyTier 高级配置");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let cfg_mgr = config_manager.lock().await;
     let config = cfg_mgr.get_config();
     
@@ -60,7 +59,7 @@ pub async fn get_global_easytier_advanced_config(state: State<'_, AppState>) ->
     
     // 序列化为 JSON
     serde_json::to_value(&advanced_config)
-        .map_err(|e| format!("序列化配置失败: {}", e))
+        .map_err(|e| CommandError::new(ErrorCode::Serialization, format!("序列化配置失败: {}", e)))
 }
 
 /// 保存大厅 EasyTier 高级配置
@@ -70,12 +69,12 @@ pub async fn get_global_easytier_advanced_config(state: State<'_, AppState>) ->
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn save_lobby_easytier_advanced_config(
     config_json: serde_json::Value,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use crate::modules::config_manager::EasyTierAdvancedConfig;
     
     log::info!("========================================");
@@ -92,8 +91,7 @@ pub async fn save_lobby_easytier_advanced_config(
     log::info!("  - no_tun: {}", config.no_tun);
     log::info!("  - dhcp: {}", config.dhcp);
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     cfg_mgr.update_config(|user_config| {
@@ -118,13 +116,12 @@ pub async fn save_lobby_easytier_advanced_config(
 /// 
 /// # 返回
 /// * `Ok(serde_json::Value)` - 大厅 EasyTier 高级配置
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_lobby_easytier_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+pub async fn get_lobby_easytier_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, CommandError> {
     log::info!("获取大厅 EasyTier 高级配置");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let cfg_mgr = config_manager.lock().await;
     let config = cfg_mgr.get_config();
     
@@ -133,21 +130,20 @@ pub async fn get_lobby_easytier_advanced_config(state: State<'_, AppState>) -> R
     
     // 序列化为 JSON
     serde_json::to_value(&advanced_config)
-        .map_err(|e| format!("序列化配置失败: {}", e))
+        .map_err(|e| CommandError::new(ErrorCode::Serialization, format!("序列化配置失败: {}", e)))
 }
 
 /// 清除大厅 EasyTier 高级配置（重置为默认）
 /// 
 /// # 返回
 /// * `Ok(())` - 清除成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn clear_lobby_easytier_advanced_config(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn clear_lobby_easytier_advanced_config(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("========================================");
     log::info!("清除大厅 EasyTier 高级配置");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     cfg_mgr.update_config(|user_config| {
@@ -0,0 +1,290 @@
+// EasyTier 连接阶段识别
+//
+// easytier-core 的运行日志只写进本地日志，连接卡住时用户看不到发生了什么。
+// 这里对 stdout / stderr 的每一行做模式匹配，识别出关键阶段（连接节点、握手、打洞、
+// 打洞失败回退中继、直连建立、获得虚拟 IP），通过 `easytier-status` 事件连同原始日志推给前端。
+// 打洞等阶段会反复输出日志，同一阶段在 `PHASE_REPEAT_INTERVAL` 内只推送一次。
+// 同时识别常见失败原因（密码不一致、端口占用、节点不可达、版本不兼容等），
+// 给出用户不看日志也能照着处理的提示。
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// 同一阶段重复推送的最小间隔
+pub const PHASE_REPEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// EasyTier 连接阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionPhase {
+    /// 正在连接服务器节点
+    ConnectingServer,
+    /// 正在与节点 / peer 握手
+    Handshaking,
+    /// 正在 UDP 打洞
+    HolePunching,
+    /// 打洞失败，回退到中继
+    RelayFallback,
+    /// 与 peer 建立了连接
+    PeerConnected,
+    /// 连接 peer 失败
+    PeerConnectFailed,
+    /// 已获得虚拟 IP
+    IpAssigned,
+}
+
+impl ConnectionPhase {
+    /// 阶段说明（用于日志）
+    pub fn label(self) -> &'static str {
+        match self {
+            ConnectionPhase::ConnectingServer => "正在连接服务器节点",
+            ConnectionPhase::Handshaking => "正在握手",
+            ConnectionPhase::HolePunching => "正在打洞",
+            ConnectionPhase::RelayFallback => "P2P 直连失败，已回退中继",
+            ConnectionPhase::PeerConnected => "已与玩家建立连接",
+            ConnectionPhase::PeerConnectFailed => "连接玩家失败",
+            ConnectionPhase::IpAssigned => "已获得虚拟 IP",
+        }
+    }
+}
+
+/// 推送给前端的 `easytier-status` 事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EasyTierStatusEvent {
+    /// 阶段
+    pub phase: ConnectionPhase,
+    /// 触发该阶段的原始日志
+    pub raw: String,
+}
+
+/// 从一行 EasyTier 日志识别连接阶段
+///
+/// 顺序有意义：打洞失败的日志同时含有 "punch"，必须先于打洞本身判断。
+pub fn classify_line(line: &str) -> Option<ConnectionPhase> {
+    let lower = line.to_lowercase();
+    let has = |needle: &str| lower.contains(needle);
+    let failed = has("fail") || has("error") || has("timeout") || has("timed out");
+
+    if has("punch") {
+        return Some(if failed {
+            ConnectionPhase::RelayFallback
+        } else {
+            ConnectionPhase::HolePunching
+        });
+    }
+    if has("relay") && (has("fallback") || has("via relay") || has("use relay")) {
+        return Some(ConnectionPhase::RelayFallback);
+    }
+    if has("connect to peer") && failed {
+        return Some(ConnectionPhase::PeerConnectFailed);
+    }
+    if has("handshake") {
+        return Some(if failed {
+            ConnectionPhase::PeerConnectFailed
+        } else {
+            ConnectionPhase::Handshaking
+        });
+    }
+    if has("new peer connection added") || has("peer conn added") || has("direct connected") {
+        return Some(ConnectionPhase::PeerConnected);
+    }
+    if (has("connecting to") || has("try connect") || has("connect to"))
+        && ["tcp://", "udp://", "ws://", "wss://", "quic://"].iter().any(|scheme| has(scheme))
+    {
+        return Some(ConnectionPhase::ConnectingServer);
+    }
+    None
+}
+
+/// EasyTier 常见失败原因
+///
+/// 变体顺序即优先级：同一段日志里出现多种错误时，越靠前的越可能是根因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EasyTierErrorKind {
+    /// 虚拟网卡（TUN）创建失败
+    TunCreateFailed,
+    /// 端口被占用或绑定被拒绝
+    PortInUse,
+    /// 网络密钥（大厅密码）不一致
+    NetworkSecretMismatch,
+    /// 版本不兼容
+    VersionIncompatible,
+    /// 服务器节点不可达
+    NodeUnreachable,
+}
+
+impl EasyTierErrorKind {
+    /// 给用户看的提示
+    pub fn hint(self) -> &'static str {
+        match self {
+            EasyTierErrorKind::TunCreateFailed => "虚拟网卡创建失败：请右键以管理员身份运行 MCTier，并将本软件加入杀毒软件/防火墙白名单；若仍失败，请重启电脑后重试",
+            EasyTierErrorKind::PortInUse => "端口被占用：通常是上一次的网络进程未完全退出或其他程序占用了端口，请稍等几秒后重试，仍失败请重启电脑",
+            EasyTierErrorKind::NetworkSecretMismatch => "大厅密码错误或与他人不一致：请向房主确认大厅名称和密码后重新加入",
+            EasyTierErrorKind::VersionIncompatible => "与其他玩家的 MCTier 版本不兼容：请所有人更新到最新版本后重试",
+            EasyTierErrorKind::NodeUnreachable => "无法连接服务器节点：请检查网络连接，或在设置中更换服务器节点后重试",
+        }
+    }
+
+    /// 启动阶段遇到该错误是否应直接判定失败
+    ///
+    /// 节点不可达不算：配置了多个节点时 EasyTier 会继续尝试其他节点，单个节点失败不代表无法连接；
+    /// 端口占用会让进程自行退出，交给退出原因分析给出带失败详情的提示。
+    pub fn aborts_startup(self) -> bool {
+        matches!(
+            self,
+            EasyTierErrorKind::TunCreateFailed
+                | EasyTierErrorKind::NetworkSecretMismatch
+                | EasyTierErrorKind::VersionIncompatible
+        )
+    }
+}
+
+/// 从一行 EasyTier 日志识别失败原因
+pub fn classify_error(line: &str) -> Option<EasyTierErrorKind> {
+    let lower = line.to_lowercase();
+    let has = |needle: &str| lower.contains(needle);
+    let mismatch = has("mismatch") || has("not match") || has("wrong") || has("invalid");
+
+    if has("tun device error") || has("failed to create adapter") {
+        return Some(EasyTierErrorKind::TunCreateFailed);
+    }
+    if has("address already in use")
+        || has("addrinuse")
+        || has("os error 10048")
+        || has("os error 10013")
+        || has("os error 98)")
+    {
+        return Some(EasyTierErrorKind::PortInUse);
+    }
+    if (has("secret") || has("network identity") || has("network name")) && mismatch {
+        return Some(EasyTierErrorKind::NetworkSecretMismatch);
+    }
+    if has("version") && (mismatch || has("incompatible") || has("unsupported") || has("not support")) {
+        return Some(EasyTierErrorKind::VersionIncompatible);
+    }
+    let connecting = has("connect") || has("tcp://") || has("udp://") || has("ws://") || has("wss://");
+    if has("network is unreachable")
+        || has("no route to host")
+        || has("failed to lookup address")
+        || (connecting && (has("connection refused") || has("timed out") || has("timeout")))
+    {
+        return Some(EasyTierErrorKind::NodeUnreachable);
+    }
+    None
+}
+
+/// 阶段推送节流
+#[derive(Debug, Default)]
+pub struct PhaseTracker {
+    last: Option<(ConnectionPhase, Instant)>,
+}
+
+impl PhaseTracker {
+    /// 判断该阶段此刻是否应推送，应推送时同时记录
+    ///
+    /// 阶段变化时总是推送；同一阶段在 `PHASE_REPEAT_INTERVAL` 内不重复推送。
+    pub fn should_emit(&mut self, phase: ConnectionPhase, now: Instant) -> bool {
+        if let Some((last_phase, at)) = self.last {
+            if last_phase == phase && now.saturating_duration_since(at) < PHASE_REPEAT_INTERVAL {
+                return false;
+            }
+        }
+        self.last = Some((phase, now));
+        true
+    }
+
+    /// 最近一次推送的阶段
+    pub fn last_phase(&self) -> Option<ConnectionPhase> {
+        self.last.map(|(phase, _)| phase)
+    }
+}
+
+/// 等待虚拟 IP 期间定时推送给前端的 `easytier-connect-progress` 事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectProgressEvent {
+    /// 已等待的秒数
+    pub elapsed_secs: u64,
+    /// 超时秒数
+    pub timeout_secs: u64,
+    /// 当前所处阶段（尚未识别出任何阶段时为 None）
+    pub phase: Option<ConnectionPhase>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_line() {
+        let cases = [
+            ("2025-01-01T00:00:00 INFO try connect to tcp://public.easytier.cn:11010", Some(ConnectionPhase::ConnectingServer)),
+            ("INFO peer_manager: start handshake with peer 1234", Some(ConnectionPhase::Handshaking)),
+            ("DEBUG udp_hole_punch: start punching hole to peer 5678", Some(ConnectionPhase::HolePunching)),
+            ("WARN udp hole punch failed: timeout, peer 5678", Some(ConnectionPhase::RelayFallback)),
+            ("INFO new peer connection added, peer_id: 5678", Some(ConnectionPhase::PeerConnected)),
+            ("WARN connect to peer error: Connection refused", Some(ConnectionPhase::PeerConnectFailed)),
+            ("WARN handshake failed: wrong network secret", Some(ConnectionPhase::PeerConnectFailed)),
+            ("INFO listener started at udp://0.0.0.0:11010", None),
+            ("INFO rpc_portal = 127.0.0.1:15888", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(classify_line(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_classify_error() {
+        use EasyTierErrorKind::*;
+        let cases = [
+            ("ERROR tun device error: Failed to create adapter", Some(TunCreateFailed)),
+            ("0: failed to bind udp listener: Address already in use (os error 98)", Some(PortInUse)),
+            ("Error: 通常每个套接字地址只允许使用一次。 (os error 10048)", Some(PortInUse)),
+            ("1: 以一种访问权限不允许的方式做了一个访问套接字的尝试。 (os error 10013)", Some(PortInUse)),
+            ("WARN handshake failed: wrong network secret", Some(NetworkSecretMismatch)),
+            ("ERROR peer conn error: network secret digest mismatch", Some(NetworkSecretMismatch)),
+            ("WARN network identity not match, peer 1234", Some(NetworkSecretMismatch)),
+            ("ERROR handshake error: packet version mismatch, remote 1, local 2", Some(VersionIncompatible)),
+            ("WARN peer version 1.2.0 is incompatible", Some(VersionIncompatible)),
+            ("WARN connect to tcp://1.2.3.4:11010 failed: Connection refused (os error 111)", Some(NodeUnreachable)),
+            ("WARN try connect to udp://node.example.com:11010 error: timed out", Some(NodeUnreachable)),
+            ("ERROR failed to lookup address information: Name or service not known", Some(NodeUnreachable)),
+            ("WARN udp hole punch failed: timeout, peer 5678", None),
+            ("INFO new peer connection added, peer_id: 5678", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(classify_error(line), expected, "{}", line);
+        }
+        assert!(!NodeUnreachable.aborts_startup());
+        assert!(!PortInUse.aborts_startup());
+        assert!(NetworkSecretMismatch.aborts_startup());
+    }
+
+    #[test]
+    fn test_phase_tracker_throttles_repeats() {
+        let mut tracker = PhaseTracker::default();
+        let t0 = Instant::now();
+
+        assert!(tracker.should_emit(ConnectionPhase::HolePunching, t0));
+        assert!(!tracker.should_emit(ConnectionPhase::HolePunching, t0 + Duration::from_secs(1)));
+        // 阶段变化立即推送
+        assert!(tracker.should_emit(ConnectionPhase::RelayFallback, t0 + Duration::from_secs(2)));
+        assert!(tracker.should_emit(ConnectionPhase::HolePunching, t0 + Duration::from_secs(3)));
+        assert!(tracker.should_emit(ConnectionPhase::HolePunching, t0 + Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn test_phase_tracker_last_phase() {
+        let mut tracker = PhaseTracker::default();
+        let t0 = Instant::now();
+        assert_eq!(tracker.last_phase(), None);
+
+        tracker.should_emit(ConnectionPhase::ConnectingServer, t0);
+        tracker.should_emit(ConnectionPhase::Handshaking, t0 + Duration::from_secs(1));
+        // 被节流的重复阶段不影响最近阶段
+        tracker.should_emit(ConnectionPhase::Handshaking, t0 + Duration::from_secs(2));
+        assert_eq!(tracker.last_phase(), Some(ConnectionPhase::Handshaking));
+    }
+}
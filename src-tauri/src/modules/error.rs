@@ -80,6 +80,177 @@ impl AppError {
     }
 }
 
+/// Tauri 命令错误分类（前端据此做国际化与分支处理）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 输入验证失败
+    Validation,
+    /// 网络错误（虚拟网络、HTTP 请求、信令等）
+    Network,
+    /// 权限不足（需要管理员权限、共享密码错误等）
+    Permission,
+    /// 目标不存在（大厅、共享、文件等）
+    NotFound,
+    /// 音频设备错误
+    Audio,
+    /// 语音服务错误
+    Voice,
+    /// 配置错误
+    Config,
+    /// 进程管理错误
+    Process,
+    /// IO 错误
+    Io,
+    /// 文件操作错误
+    File,
+    /// 序列化/反序列化错误
+    Serialization,
+    /// 未分类的错误
+    Unknown,
+}
+
+/// Tauri 命令统一错误类型
+///
+/// 序列化为 `{ code, message, detail? }` 传给前端；`message` 为可直接展示的中文说明。
+#[derive(Debug, Clone, PartialEq, serde::Serialize, thiserror::Error)]
+#[error("{message}")]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// 补充信息（如底层错误原文），可为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            detail: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Validation, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Network, message)
+    }
+
+    pub fn permission(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Permission, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Io, message)
+    }
+
+    /// 附加补充信息
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// AppError 按变体映射错误分类，消息保持与 `to_string()` 一致
+impl From<AppError> for CommandError {
+    fn from(err: AppError) -> Self {
+        let code = match &err {
+            AppError::ValidationError(_) => ErrorCode::Validation,
+            AppError::NetworkError(_) => ErrorCode::Network,
+            AppError::AudioError(_) => ErrorCode::Audio,
+            AppError::VoiceError(_) => ErrorCode::Voice,
+            AppError::ConfigError(_) => ErrorCode::Config,
+            AppError::ProcessError(_) => ErrorCode::Process,
+            AppError::IoError(_) => ErrorCode::Io,
+            AppError::FileError(_) => ErrorCode::File,
+            AppError::SerializationError(_) => ErrorCode::Serialization,
+            AppError::Unknown(_) => ErrorCode::Unknown,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<crate::modules::input_limits::InputLimitError> for CommandError {
+    fn from(err: crate::modules::input_limits::InputLimitError) -> Self {
+        Self::validation(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        let code = match err.kind() {
+            std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorCode::Permission,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<crate::modules::lobby_manager::LobbyError> for CommandError {
+    fn from(err: crate::modules::lobby_manager::LobbyError) -> Self {
+        use crate::modules::lobby_manager::LobbyError;
+        let code = match &err {
+            LobbyError::InvalidInput(_) | LobbyError::AlreadyInLobby | LobbyError::NotInLobby => ErrorCode::Validation,
+            LobbyError::NetworkError(_) => ErrorCode::Network,
+            LobbyError::PlayerNotFound(_) => ErrorCode::NotFound,
+            LobbyError::PermissionDenied(_) => ErrorCode::Permission,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<crate::modules::voice_service::VoiceError> for CommandError {
+    fn from(err: crate::modules::voice_service::VoiceError) -> Self {
+        use crate::modules::voice_service::VoiceError;
+        let code = match &err {
+            VoiceError::DeviceNotFound => ErrorCode::Audio,
+            VoiceError::PlayerNotFound(_) => ErrorCode::NotFound,
+            _ => ErrorCode::Voice,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<crate::modules::invite_link::InviteLinkError> for CommandError {
+    fn from(err: crate::modules::invite_link::InviteLinkError) -> Self {
+        Self::validation(err.to_string())
+    }
+}
+
+impl From<crate::modules::hotkeys::HotkeyError> for CommandError {
+    fn from(err: crate::modules::hotkeys::HotkeyError) -> Self {
+        Self::validation(err.to_string())
+    }
+}
+
+impl From<crate::modules::signaling_crypto::SignalingCryptoError> for CommandError {
+    fn from(err: crate::modules::signaling_crypto::SignalingCryptoError) -> Self {
+        Self::network(err.to_string())
+    }
+}
+
+/// 尚未分类的字符串错误
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Unknown, message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Unknown, message)
+    }
+}
+
 /// 错误日志记录函数
 pub fn log_error(error: &AppError, context: &str) {
     log::error!("[{}] 错误: {}", context, error);
@@ -245,6 +416,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_command_error_mapping() {
+        let err: CommandError = AppError::NetworkError("连接超时".to_string()).into();
+        assert_eq!(err.code, ErrorCode::Network);
+        assert_eq!(err.message, "网络错误: 连接超时");
+
+        let err: CommandError = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "拒绝访问").into();
+        assert_eq!(err.code, ErrorCode::Permission);
+
+        // 序列化为前端使用的结构，未设置的 detail 不输出
+        let json = serde_json::to_value(CommandError::not_found("共享不存在")).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "not_found", "message": "共享不存在" }));
+        let json = serde_json::to_value(CommandError::from("失败").with_detail("HTTP 500")).unwrap();
+        assert_eq!(json["code"], "unknown");
+        assert_eq!(json["detail"], "HTTP 500");
+    }
+
     #[tokio::test]
     async fn test_with_retry_success_first_attempt() {
         let mut call_count = 0;
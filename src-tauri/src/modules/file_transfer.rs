@@ -4,11 +4,11 @@
  * 使用标准 HTTP 协议，支持断点续传和多线程下载
  */
 
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use axum::{
     body::Body,
@@ -18,16 +18,47 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use base64::Engine as _;
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use tauri::Emitter;
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tower_http::cors::CorsLayer;
 use zip::write::SimpleFileOptions;
 
-const FILE_SERVER_PORT: u16 = 14539; // 固定端口，方便其他节点访问
+use crate::modules::http_auth::AuthPolicy;
+use crate::modules::input_limits::{check_len, MAX_FILE_NAME_CHARS, MAX_SHARE_NAME_CHARS};
+
+pub const FILE_SERVER_PORT: u16 = crate::modules::service_ports::DEFAULT_FILE_SHARE_PORT; // 默认端口，可在端口配置中覆盖
 const CHUNK_SIZE: usize = 1024 * 1024; // 1MB chunks
+/// 共享密码哈希格式：`pbkdf2-sha256$<迭代次数>$<盐hex>$<哈希hex>`
+const PASSWORD_HASH_SCHEME: &str = "pbkdf2-sha256";
+const PASSWORD_HASH_ITERATIONS: u32 = 20_000;
+/// 解析哈希串时接受的最大迭代次数（导入文件可被篡改，防止一次校验耗尽 CPU）
+const MAX_PASSWORD_HASH_ITERATIONS: u32 = 200_000;
+const PASSWORD_SALT_LEN: usize = 16;
+/// 共享列表导出文件格式版本
+const SHARES_EXPORT_VERSION: u32 = 1;
+/// 同时进行的整目录打包下载数上限（超出时返回 503，由下载端稍后重试）
+const MAX_CONCURRENT_ZIP_STREAMS: usize = 2;
+/// 整目录打包时每个数据块的大小
+const ZIP_STREAM_CHUNK_SIZE: usize = 256 * 1024;
+/// 打包线程与响应流之间最多缓冲的数据块数（限制每个打包任务的内存占用）
+const ZIP_STREAM_BUFFERED_CHUNKS: usize = 8;
+/// 后台清理过期共享的间隔（到期时刻另有精确定时器，这里兜底）
+const SHARE_CLEANUP_INTERVAL: Duration = Duration::from_secs(180);
+/// 缩略图最长边（像素）
+const THUMBNAIL_MAX_DIMENSION: u32 = 128;
+/// 缩略图 JPEG 质量
+const THUMBNAIL_JPEG_QUALITY: u8 = 70;
+/// 超过该大小的图片不生成缩略图（解码开销过大）
+const THUMBNAIL_MAX_SOURCE_BYTES: u64 = 20 * 1024 * 1024;
+/// 缩略图缓存条目数上限
+const THUMBNAIL_CACHE_CAPACITY: usize = 256;
+/// 远程文件列表中内联缩略图的最大长度（Base64 字符数），超过的丢弃
+pub const MAX_THUMBNAIL_BASE64_LEN: usize = 64 * 1024;
 
 /// 共享文件夹信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,11 +66,31 @@ pub struct SharedFolder {
     pub id: String,
     pub name: String,
     pub path: String,
+    /// 共享密码：添加共享时替换为加盐哈希，永不在响应中输出
+    #[serde(default, skip_serializing)]
     pub password: Option<String>,
-    pub expire_time: Option<u64>, // Unix timestamp
+    /// 是否设置了密码（供列表展示锁标记）
+    #[serde(default)]
+    pub has_password: bool,
+    /// 过期时间（Unix 时间戳，秒），为空表示永久共享（旧版本字段名为 expire_time）
+    #[serde(alias = "expire_time")]
+    pub expires_at: Option<u64>,
     pub compress_before_send: Option<bool>, // 是否启用"先压后发"策略
+    /// 是否允许其他玩家上传文件到共享目录（旧版本不携带该字段，视为只读）
+    #[serde(default)]
+    pub writable: bool,
     pub owner_id: String,
     pub created_at: u64,
+    /// 共享级备注（来自共享根目录的备注元数据）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl SharedFolder {
+    /// 在 `now`（Unix 时间戳，秒）时是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
 }
 
 /// 文件信息
@@ -50,6 +101,101 @@ pub struct FileInfo {
     pub size: u64,
     pub is_dir: bool,
     pub modified: u64,
+    /// 文件备注
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// 按扩展名识别的 MIME 类型（文件夹与无法识别的类型为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// 图片缩略图（JPEG，Base64）；仅在已生成过缓存时内联返回，否则通过缩略图端点按需获取
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_base64: Option<String>,
+}
+
+/// 按扩展名识别常见文件的 MIME 类型
+pub fn mime_type_for(name: &str) -> Option<&'static str> {
+    let ext = Path::new(name).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "txt" | "log" | "cfg" | "properties" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "json" | "mcmeta" => "application/json",
+        "toml" => "application/toml",
+        "yml" | "yaml" => "application/yaml",
+        "xml" => "application/xml",
+        "zip" | "mcworld" | "mcpack" => "application/zip",
+        "mrpack" => "application/x-modrinth-modpack+zip",
+        "jar" => "application/java-archive",
+        "7z" => "application/x-7z-compressed",
+        "rar" => "application/vnd.rar",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "pdf" => "application/pdf",
+        "exe" | "msi" => "application/vnd.microsoft.portable-executable",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// 是否可以生成缩略图（与内置的图片解码器支持的格式一致）
+fn supports_thumbnail(name: &str) -> bool {
+    matches!(
+        mime_type_for(name),
+        Some("image/png" | "image/jpeg" | "image/gif" | "image/webp")
+    )
+}
+
+/// 生成缩略图：缩放到最长边不超过 128px 后编码为 JPEG，无法解码时返回 `None`
+fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(data).ok()?;
+    let rgb = image.thumbnail(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION).to_rgb8();
+    let mut output = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, THUMBNAIL_JPEG_QUALITY)
+        .encode_image(&rgb)
+        .ok()?;
+    Some(output)
+}
+
+/// 缩略图缓存：按文件路径缓存，文件变化（ETag 不同）后重新生成，超出容量时淘汰最早加入的
+#[derive(Default)]
+struct ThumbnailCache {
+    entries: HashMap<PathBuf, (String, Arc<Vec<u8>>)>,
+    order: VecDeque<PathBuf>,
+}
+
+impl ThumbnailCache {
+    fn get(&self, path: &Path, etag: &str) -> Option<Arc<Vec<u8>>> {
+        self.entries
+            .get(path)
+            .filter(|(cached_etag, _)| cached_etag == etag)
+            .map(|(_, data)| data.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, etag: String, data: Arc<Vec<u8>>) {
+        if self.entries.insert(path.clone(), (etag, data)).is_none() {
+            self.order.push_back(path);
+        }
+        while self.order.len() > THUMBNAIL_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
 }
 
 /// 共享列表响应
@@ -84,7 +230,268 @@ pub struct BatchDownloadRequest {
     pub file_paths: Vec<String>,
 }
 
+/// 上传文件请求参数
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    /// 相对于共享文件夹的目标路径
+    pub path: String,
+}
+
+/// 上传文件响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadResponse {
+    /// 相对于共享文件夹的文件路径
+    pub path: String,
+    /// 写入的字节数
+    pub size: u64,
+}
+
+/// 下载完成回执（下载端完成后上报给共享者，由共享者比对 SHA-256）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadCompleteRequest {
+    /// 相对于共享文件夹的文件路径
+    pub file_path: String,
+    /// 实际下载的字节数
+    pub size: u64,
+    /// 下载端计算的 SHA-256（十六进制）
+    pub sha256: Option<String>,
+    /// 下载的字节数是否与响应声明的长度一致（仅大小检查，不代表内容校验）
+    #[serde(alias = "verified")]
+    pub size_matched: bool,
+}
+
+/// 下载回执的处理结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadCompleteResponse {
+    /// 共享者比对大小与 SHA-256 后确认文件完整
+    pub verified: bool,
+    /// 更新后的下载统计
+    pub stats: ShareDownloadStats,
+}
+
+/// 共享的下载统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShareDownloadStats {
+    /// 校验通过的下载次数
+    pub completed_count: u64,
+    /// 校验失败的下载次数
+    pub failed_count: u64,
+    /// 校验通过的下载累计字节数
+    pub total_bytes: u64,
+    /// 最近一次收到回执的时间（Unix timestamp）
+    pub last_completed_at: Option<u64>,
+}
+
+/// 共享备注元数据文件名（存放在共享根目录，列目录时隐藏）
+pub const SHARE_META_FILE: &str = ".mctier-meta.json";
+/// 上传中的临时文件后缀（上传完成后重命名为目标文件，列目录时隐藏）
+const UPLOAD_PARTIAL_SUFFIX: &str = ".mctier-upload";
+/// 单个上传文件的默认大小上限（2GB）
+pub const DEFAULT_UPLOAD_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// 上传大小上限允许设置的最大值（64GB）
+pub const MAX_UPLOAD_MAX_BYTES: u64 = 64 * 1024 * 1024 * 1024;
+/// 单条备注的最大字符数
+pub const MAX_NOTE_CHARS: usize = 500;
+
+/// 共享备注元数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ShareMeta {
+    /// 共享级备注
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_note: Option<String>,
+    /// 文件级备注（相对共享根目录的路径 -> 备注）
+    #[serde(default)]
+    pub file_notes: HashMap<String, String>,
+}
+
+impl ShareMeta {
+    /// 设置备注：路径为空表示共享级备注，备注为空表示删除
+    pub fn set_note(&mut self, path: &str, note: &str) {
+        let key = normalize_note_path(path);
+        let note = note.trim();
+        let value = (!note.is_empty()).then(|| note.to_string());
+        if key.is_empty() {
+            self.share_note = value;
+        } else if let Some(value) = value {
+            self.file_notes.insert(key, value);
+        } else {
+            self.file_notes.remove(&key);
+        }
+    }
+
+    /// 获取备注：路径为空表示共享级备注
+    pub fn note_for(&self, path: &str) -> Option<&str> {
+        let key = normalize_note_path(path);
+        if key.is_empty() {
+            self.share_note.as_deref()
+        } else {
+            self.file_notes.get(&key).map(String::as_str)
+        }
+    }
+}
+
+/// 统一备注路径格式（正斜杠分隔，去掉首尾斜杠）
+fn normalize_note_path(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_string()
+}
+
+/// 从共享根目录读取备注元数据（文件不存在或损坏时返回空）
+pub fn load_share_meta(share_root: &Path) -> ShareMeta {
+    let meta_path = share_root.join(SHARE_META_FILE);
+    match std::fs::read_to_string(&meta_path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("⚠️ 备注元数据损坏，已忽略: {:?} - {}", meta_path, e);
+            ShareMeta::default()
+        }),
+        Err(_) => ShareMeta::default(),
+    }
+}
+
+/// 将备注元数据写入共享根目录
+pub async fn save_share_meta(share_root: &Path, meta: &ShareMeta) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(meta).map_err(|e| format!("序列化备注失败: {}", e))?;
+    tokio::fs::write(share_root.join(SHARE_META_FILE), content)
+        .await
+        .map_err(|e| format!("写入备注文件失败: {}", e))
+}
+
+/// 生成下载完成回执的上报地址
+pub fn download_complete_url(peer_ip: &str, share_id: &str) -> String {
+    crate::modules::http_tls::peer_url(
+        peer_ip,
+        crate::modules::service_ports::peer_file_port(peer_ip),
+        &format!("/api/shares/{}/download-complete", urlencoding::encode(share_id)),
+    )
+}
+
+/// 导出文件中的单个共享配置
+///
+/// 只保存可复用的配置：不含共享 ID、所有者与过期时间（绝对时间戳，换个大厅后通常已失效），
+/// 密码只以加盐哈希形式导出，从不包含明文。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShareExportEntry {
+    pub name: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_before_send: Option<bool>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub writable: bool,
+}
+
+/// 共享列表导出文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharesExport {
+    pub version: u32,
+    pub exported_at: u64,
+    pub shares: Vec<ShareExportEntry>,
+}
+
+/// 导入时被跳过的共享
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SkippedShare {
+    pub name: String,
+    pub path: String,
+    pub reason: String,
+}
+
+/// 共享列表导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSharesReport {
+    /// 导入成功的共享
+    pub imported: Vec<SharedFolder>,
+    /// 被跳过的共享及原因
+    pub skipped: Vec<SkippedShare>,
+    /// 是否已重新共享（文件服务未运行时共享在服务启动后生效）
+    pub shared: bool,
+}
+
+/// 生成共享列表导出内容
+pub fn build_shares_export(shares: &[SharedFolder], exported_at: u64) -> SharesExport {
+    let shares = shares
+        .iter()
+        .map(|share| {
+            // 内存中的密码已是哈希，原样导出
+            let share = share.clone();
+            ShareExportEntry {
+                name: share.name,
+                path: share.path,
+                password_hash: share.password,
+                compress_before_send: share.compress_before_send,
+                writable: share.writable,
+            }
+        })
+        .collect();
+    SharesExport {
+        version: SHARES_EXPORT_VERSION,
+        exported_at,
+        shares,
+    }
+}
+
+/// 把导出内容转换为待添加的共享，路径失效（不存在或不是文件夹）的条目跳过并记录原因
+///
+/// # 参数
+/// * `export` - 导出文件内容
+/// * `owner_id` - 本机玩家 ID
+/// * `now` - 当前 Unix 时间戳（秒）
+pub fn prepare_imported_shares(
+    export: SharesExport,
+    owner_id: &str,
+    now: u64,
+) -> (Vec<SharedFolder>, Vec<SkippedShare>) {
+    let mut shares = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in export.shares {
+        let path = Path::new(&entry.path);
+        let reason = if !path.exists() {
+            Some("文件夹不存在")
+        } else if !path.is_dir() {
+            Some("路径不是文件夹")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            log::warn!("⚠️ 跳过导入共享 {} ({}): {}", entry.name, entry.path, reason);
+            skipped.push(SkippedShare {
+                name: entry.name,
+                path: entry.path,
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+        // 导入文件只携带密码哈希，格式无效（含迭代次数超限）的条目跳过，不当作明文重新哈希
+        let password = entry.password_hash.filter(|p| !p.is_empty());
+        if password.as_deref().is_some_and(|p| !is_share_password_hash(p)) {
+            log::warn!("⚠️ 跳过导入共享 {} ({}): 密码哈希无效", entry.name, entry.path);
+            skipped.push(SkippedShare {
+                name: entry.name,
+                path: entry.path,
+                reason: "密码哈希无效".to_string(),
+            });
+            continue;
+        }
+        let share = SharedFolder {
+            id: format!("share_{}", uuid::Uuid::new_v4().simple()),
+            name: entry.name,
+            path: entry.path,
+            has_password: password.is_some(),
+            password,
+            expires_at: None,
+            compress_before_send: entry.compress_before_send,
+            writable: entry.writable,
+            owner_id: owner_id.to_string(),
+            created_at: now,
+            note: None,
+        };
+        shares.push(share);
+    }
+    (shares, skipped)
+}
+
 /// 文件传输服务状态
+#[derive(Clone)]
 pub struct FileTransferService {
     /// 本地共享的文件夹
     shared_folders: Arc<DashMap<String, SharedFolder>>,
@@ -92,8 +499,22 @@ pub struct FileTransferService {
     virtual_ip: Arc<RwLock<Option<String>>>,
     /// 服务器句柄
     server_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    /// 服务器实际监听的地址（停止时用于确认端口已释放）
+    bound_addr: Arc<RwLock<Option<std::net::SocketAddr>>>,
     /// 过期定时器句柄
     expiry_timers: Arc<DashMap<String, tokio::task::JoinHandle<()>>>,
+    /// 后台清理过期共享的任务句柄
+    cleanup_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    /// 每个共享的下载统计（由下载端回执更新）
+    download_stats: Arc<DashMap<String, ShareDownloadStats>>,
+    /// 每个共享的备注元数据
+    share_meta: Arc<DashMap<String, ShareMeta>>,
+    /// Tauri 应用句柄（用于向前端发送下载回执事件）
+    app_handle: Arc<RwLock<Option<tauri::AppHandle>>>,
+    /// 游客模式（只能浏览下载他人的共享，不能添加共享）
+    guest_mode: Arc<AtomicBool>,
+    /// 其他成员向可写共享上传单个文件的大小上限（字节）
+    upload_max_bytes: Arc<AtomicU64>,
 }
 
 impl FileTransferService {
@@ -102,10 +523,37 @@ impl FileTransferService {
             shared_folders: Arc::new(DashMap::new()),
             virtual_ip: Arc::new(RwLock::new(None)),
             server_handle: Arc::new(RwLock::new(None)),
+            bound_addr: Arc::new(RwLock::new(None)),
             expiry_timers: Arc::new(DashMap::new()),
+            cleanup_handle: Arc::new(RwLock::new(None)),
+            download_stats: Arc::new(DashMap::new()),
+            share_meta: Arc::new(DashMap::new()),
+            app_handle: Arc::new(RwLock::new(None)),
+            guest_mode: Arc::new(AtomicBool::new(false)),
+            upload_max_bytes: Arc::new(AtomicU64::new(DEFAULT_UPLOAD_MAX_BYTES)),
         }
     }
 
+    /// 设置游客模式
+    pub fn set_guest_mode(&self, guest: bool) {
+        self.guest_mode.store(guest, Ordering::Relaxed);
+    }
+
+    /// 设置上传单个文件的大小上限（字节，运行中立即生效）
+    pub fn set_upload_max_bytes(&self, max_bytes: u64) {
+        self.upload_max_bytes.store(max_bytes, Ordering::Relaxed);
+    }
+
+    /// 是否处于游客模式
+    pub fn is_guest_mode(&self) -> bool {
+        self.guest_mode.load(Ordering::Relaxed)
+    }
+
+    /// 设置 Tauri 应用句柄
+    pub fn set_app_handle(&self, app_handle: tauri::AppHandle) {
+        *self.app_handle.write() = Some(app_handle);
+    }
+
     /// 设置虚拟IP地址
     pub fn set_virtual_ip(&self, ip: String) {
         log::info!("📡 设置虚拟IP: {}", ip);
@@ -152,51 +600,46 @@ impl FileTransferService {
             }
         }
 
-        let addr: SocketAddr = format!("{}:{}", virtual_ip, FILE_SERVER_PORT)
-            .parse()
-            .map_err(|e| {
-                log::error!("❌ 无效的地址格式: {}:{} - {}", virtual_ip, FILE_SERVER_PORT, e);
-                format!("无效的地址: {}", e)
-            })?;
-
-        log::info!("📍 HTTP服务器将仅监听虚拟网卡: {}:{}", virtual_ip, FILE_SERVER_PORT);
+        let port = crate::modules::service_ports::configured_ports().file_share;
+        log::info!("📍 HTTP服务器将仅监听虚拟网卡: {}:{}", virtual_ip, port);
         log::info!("📍 虚拟IP: {}", virtual_ip);
 
         let shared_folders = self.shared_folders.clone();
 
         // 创建路由
-        let app = Router::new()
-            .route("/api/shares", get(list_shares))
-            .route("/api/shares/:share_id/files", get(list_files))
-            .route("/api/shares/:share_id/verify", post(verify_password))
-            .route("/api/shares/:share_id/download/*file_path", get(download_file))
-            .route("/api/shares/:share_id/batch-download", post(batch_download))
-            .layer(CorsLayer::permissive())
-            .with_state(AppState {
-                shared_folders: shared_folders.clone(),
-            });
+        let app = build_router(AppState {
+            shared_folders: shared_folders.clone(),
+            download_stats: self.download_stats.clone(),
+            share_meta: self.share_meta.clone(),
+            app_handle: self.app_handle.clone(),
+            zip_permits: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_ZIP_STREAMS)),
+            thumbnails: Arc::new(parking_lot::Mutex::new(ThumbnailCache::default())),
+            upload_max_bytes: self.upload_max_bytes.clone(),
+        });
 
         log::info!("🚀 正在启动HTTP文件服务器...");
-        log::info!("📍 监听地址: http://{}", addr);
         log::debug!("📂 共享文件夹数量: {}", shared_folders.len());
 
-        // 尝试绑定端口
-        let listener = match tokio::net::TcpListener::bind(addr).await {
-            Ok(l) => {
-                log::info!("✅ 成功绑定端口 {}", FILE_SERVER_PORT);
-                l
+        // 尝试绑定端口（被占用时顺延到后续端口）
+        let (listener, port) = match crate::modules::service_ports::bind_tcp_with_fallback(&virtual_ip, port).await {
+            Ok((l, actual)) => {
+                log::info!("✅ 成功绑定端口 {}", actual);
+                crate::modules::service_ports::record_local_port(crate::modules::service_ports::ServiceKind::FileShare, actual);
+                (l, actual)
             }
             Err(e) => {
-                log::error!("❌ 绑定端口失败: {} - 错误: {}", FILE_SERVER_PORT, e);
+                log::error!("❌ 绑定端口失败: {} - 错误: {}", port, e);
                 log::error!("💡 可能原因: 1) 端口被占用 2) 虚拟网卡未就绪 3) 防火墙阻止");
                 return Err(format!("绑定端口失败: {}", e).into());
             }
         };
 
+        let bound_addr = listener.local_addr()?;
+
         // 启动服务器
         let server_task = tokio::spawn(async move {
             log::info!("🌐 HTTP文件服务器开始监听请求...");
-            if let Err(e) = axum::serve(listener, app).await {
+            if let Err(e) = crate::modules::http_tls::serve(listener, app).await {
                 log::error!("❌ HTTP服务器运行错误: {}", e);
             } else {
                 log::info!("🛑 HTTP服务器已正常停止");
@@ -204,11 +647,25 @@ impl FileTransferService {
         });
 
         *self.server_handle.write() = Some(server_task);
+        *self.bound_addr.write() = Some(bound_addr);
+
+        // 定期清理过期共享
+        let service = self.clone();
+        let cleanup_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SHARE_CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                service.cleanup_expired_shares();
+            }
+        });
+        if let Some(previous) = self.cleanup_handle.write().replace(cleanup_task) {
+            previous.abort();
+        }
 
         log::info!("✅ HTTP文件服务器启动成功！");
-        log::info!("📡 监听地址: {}:{}（仅虚拟网卡）", virtual_ip, FILE_SERVER_PORT);
+        log::info!("📡 监听地址: {}:{}（仅虚拟网卡）", virtual_ip, port);
         log::info!("📡 虚拟IP: {}", virtual_ip);
-        log::debug!("📡 其他玩家可以通过 http://{}:{} 访问您的共享", virtual_ip, FILE_SERVER_PORT);
+        log::debug!("📡 其他玩家可以通过 http://{}:{} 访问您的共享", virtual_ip, port);
         
         // 等待一小段时间，确保服务器完全启动
         tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
@@ -219,10 +676,15 @@ impl FileTransferService {
 
     /// 停止HTTP文件服务器
     pub async fn stop_server(&self) {
-        if let Some(handle) = self.server_handle.write().take() {
-            handle.abort();
+        let handle = self.server_handle.write().take();
+        let bound_addr = self.bound_addr.write().take();
+        if let Some(handle) = handle {
+            crate::modules::service_ports::shutdown_tcp_service(handle, bound_addr).await;
             log::info!("🛑 HTTP文件服务器已停止");
         }
+        if let Some(handle) = self.cleanup_handle.write().take() {
+            handle.abort();
+        }
     }
 
     /// 检查HTTP文件服务器是否正在运行
@@ -230,46 +692,59 @@ impl FileTransferService {
         self.server_handle.read().is_some()
     }
 
+    /// 服务器实际监听的地址（未运行时为 None）
+    pub fn bound_addr(&self) -> Option<std::net::SocketAddr> {
+        *self.bound_addr.read()
+    }
+
     /// 添加共享文件夹
-    pub fn add_share(&self, share: SharedFolder) -> Result<(), String> {
+    ///
+    /// `share.password` 是用户输入的明文密码，一律哈希后保存。
+    pub fn add_share(&self, mut share: SharedFolder) -> Result<(), String> {
+        // 密码只以哈希形式保存在内存中
+        normalize_share_password(&mut share);
+        self.register_share(share)
+    }
+
+    /// 登记密码已是哈希（或无密码）的共享
+    fn register_share(&self, share: SharedFolder) -> Result<(), String> {
+        if self.is_guest_mode() {
+            return Err("游客模式下不能共享文件".to_string());
+        }
+
+        check_len("共享名称", &share.name, MAX_SHARE_NAME_CHARS).map_err(|e| e.to_string())?;
+
         // 检查路径是否存在
         if !Path::new(&share.path).exists() {
             return Err("文件夹不存在".to_string());
         }
 
         let share_id = share.id.clone();
+        self.share_meta.insert(share_id.clone(), load_share_meta(Path::new(&share.path)));
         self.shared_folders.insert(share_id.clone(), share.clone());
         log::debug!("📁 添加共享: {} ({})", share.name, share_id);
         
         // 如果设置了过期时间,创建定时器
-        if let Some(expire_time) = share.expire_time {
+        if let Some(expires_at) = share.expires_at {
             let now = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs();
             
-            if expire_time > now {
-                let delay_secs = expire_time - now;
+            if !share.is_expired(now) {
+                let delay_secs = expires_at - now;
                 log::info!("⏰ 为共享 {} 设置过期定时器: {}秒后过期", share_id, delay_secs);
                 
-                let shared_folders = self.shared_folders.clone();
-                let expiry_timers = self.expiry_timers.clone();
-                let share_id_clone = share_id.clone();
-                
+                let service = self.clone();
                 let timer_handle = tokio::spawn(async move {
                     tokio::time::sleep(tokio::time::Duration::from_secs(delay_secs)).await;
-                    
-                    // 删除过期共享
-                    if shared_folders.remove(&share_id_clone).is_some() {
-                        log::info!("⏰ 共享已过期并自动删除: {}", share_id_clone);
-                    }
-                    
-                    // 清理定时器
-                    expiry_timers.remove(&share_id_clone);
+                    service.cleanup_expired_shares();
                 });
                 
                 self.expiry_timers.insert(share_id.clone(), timer_handle);
             } else {
+                self.shared_folders.remove(&share_id);
+                self.share_meta.remove(&share_id);
                 log::warn!("⚠️ 共享 {} 的过期时间已过,不添加", share_id);
                 return Err("共享已过期".to_string());
             }
@@ -289,6 +764,8 @@ impl FileTransferService {
             timer_handle.abort();
             log::debug!("⏰ 取消共享 {} 的过期定时器", share_id);
         }
+        self.download_stats.remove(share_id);
+        self.share_meta.remove(share_id);
         
         log::debug!("🗑️ 删除共享: {}", share_id);
         Ok(())
@@ -298,12 +775,116 @@ impl FileTransferService {
     pub fn get_shares(&self) -> Vec<SharedFolder> {
         self.shared_folders
             .iter()
-            .map(|entry| entry.value().clone())
+            .map(|entry| with_share_note(&self.share_meta, entry.value()))
             .collect()
     }
 
+    /// 导出当前共享列表到文件
+    /// 
+    /// # 参数
+    /// * `path` - 导出文件路径
+    /// 
+    /// # 返回
+    /// 导出的共享数量
+    pub fn export_shares(&self, path: &Path) -> Result<usize, String> {
+        let shares: Vec<SharedFolder> = self.shared_folders.iter().map(|e| e.value().clone()).collect();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let export = build_shares_export(&shares, now);
+        let json = serde_json::to_string_pretty(&export)
+            .map_err(|e| format!("序列化共享列表失败: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("写入导出文件失败: {}", e))?;
+        log::info!("📤 已导出 {} 个共享到 {}", export.shares.len(), path.display());
+        Ok(export.shares.len())
+    }
+
+    /// 从文件导入共享列表
+    /// 
+    /// 路径失效的条目跳过并在结果中报告。导入的共享直接登记到服务中（密码哈希不会返回给前端，
+    /// 无法由前端重新提交）：文件服务正在运行时立即重新共享，否则在文件服务启动后生效。
+    /// 
+    /// # 参数
+    /// * `path` - 导出文件路径
+    /// * `owner_id` - 本机玩家 ID
+    pub fn import_shares(&self, path: &Path, owner_id: &str) -> Result<ImportSharesReport, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("读取导入文件失败: {}", e))?;
+        let export: SharesExport = serde_json::from_str(&json).map_err(|e| format!("导入文件格式无效: {}", e))?;
+        if export.version > SHARES_EXPORT_VERSION {
+            return Err(format!("不支持的导入文件版本: {}", export.version));
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let (shares, mut skipped) = prepare_imported_shares(export, owner_id, now);
+
+        let mut imported = Vec::with_capacity(shares.len());
+        for share in shares {
+            if let Err(reason) = self.register_share(share.clone()) {
+                skipped.push(SkippedShare {
+                    name: share.name,
+                    path: share.path,
+                    reason,
+                });
+                continue;
+            }
+            imported.push(share);
+        }
+        let shared = self.is_running();
+
+        log::info!("📥 共享列表导入完成：导入 {}，跳过 {}，已重新共享: {}", imported.len(), skipped.len(), shared);
+        Ok(ImportSharesReport { imported, skipped, shared })
+    }
+
+    /// 设置共享或文件的备注
+    /// 
+    /// 备注保存在共享根目录的 `.mctier-meta.json`；目录不可写时仅保存在内存中。
+    /// 
+    /// # 参数
+    /// * `share_id` - 共享ID
+    /// * `path` - 相对共享根目录的路径，为空表示共享级备注
+    /// * `note` - 备注内容，为空表示删除
+    pub async fn set_file_note(&self, share_id: &str, path: &str, note: &str) -> Result<(), String> {
+        let base_path = self
+            .shared_folders
+            .get(share_id)
+            .map(|share| PathBuf::from(&share.path))
+            .ok_or_else(|| "共享不存在".to_string())?;
+
+        if note.trim().chars().count() > MAX_NOTE_CHARS {
+            return Err(format!("备注不能超过 {} 个字符", MAX_NOTE_CHARS));
+        }
+        let key = normalize_note_path(path);
+        if !key.is_empty() {
+            match safe_join(&base_path, &key) {
+                Some(full_path) if full_path.exists() => {}
+                Some(_) => return Err("文件不存在".to_string()),
+                None => return Err("无效的路径".to_string()),
+            }
+        }
+
+        let meta = {
+            let mut meta = self.share_meta.entry(share_id.to_string()).or_default();
+            meta.set_note(path, note);
+            meta.clone()
+        };
+        if let Err(e) = save_share_meta(&base_path, &meta).await {
+            log::warn!("⚠️ {}，备注仅保存在内存中", e);
+        }
+        log::debug!("📝 更新备注: {} / {:?}", share_id, path);
+        Ok(())
+    }
+
+    /// 获取共享的下载统计
+    pub fn get_download_stats(&self, share_id: &str) -> ShareDownloadStats {
+        self.download_stats
+            .get(share_id)
+            .map(|entry| entry.value().clone())
+            .unwrap_or_default()
+    }
+
     /// 清理过期共享
-    pub fn cleanup_expired_shares(&self) {
+    ///
+    /// 过期共享从列表移除后，其文件列表、下载与上传接口都返回 404；
+    /// 有共享被移除时向前端发送 `shares-updated` 事件。返回被移除的共享 ID。
+    pub fn cleanup_expired_shares(&self) -> Vec<String> {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -312,20 +893,30 @@ impl FileTransferService {
         let expired: Vec<String> = self
             .shared_folders
             .iter()
-            .filter(|entry| {
-                if let Some(expire_time) = entry.value().expire_time {
-                    expire_time < now
-                } else {
-                    false
-                }
-            })
+            .filter(|entry| entry.value().is_expired(now))
             .map(|entry| entry.key().clone())
             .collect();
 
-        for share_id in expired {
-            self.shared_folders.remove(&share_id);
-            log::debug!("⏰ 清理过期共享: {}", share_id);
+        for share_id in &expired {
+            let Some((_, share)) = self.shared_folders.remove(share_id) else {
+                continue;
+            };
+            if let Some((_, timer_handle)) = self.expiry_timers.remove(share_id) {
+                timer_handle.abort();
+            }
+            self.download_stats.remove(share_id);
+            self.share_meta.remove(share_id);
+            log::info!("⏰ 共享已过期，已停止提供下载: {} ({})", share.name, share_id);
         }
+
+        if !expired.is_empty() {
+            if let Some(app) = self.app_handle.read().as_ref() {
+                let _ = app.emit("shares-updated", serde_json::json!({
+                    "removedShareIds": expired,
+                }));
+            }
+        }
+        expired
     }
 }
 
@@ -333,19 +924,163 @@ impl FileTransferService {
 #[derive(Clone)]
 struct AppState {
     shared_folders: Arc<DashMap<String, SharedFolder>>,
+    download_stats: Arc<DashMap<String, ShareDownloadStats>>,
+    share_meta: Arc<DashMap<String, ShareMeta>>,
+    app_handle: Arc<RwLock<Option<tauri::AppHandle>>>,
+    /// 整目录打包下载的并发许可
+    zip_permits: Arc<tokio::sync::Semaphore>,
+    /// 图片缩略图缓存
+    thumbnails: Arc<parking_lot::Mutex<ThumbnailCache>>,
+    /// 上传单个文件的大小上限（字节）
+    upload_max_bytes: Arc<AtomicU64>,
+}
+
+/// 复制共享信息并附上共享级备注
+fn with_share_note(share_meta: &DashMap<String, ShareMeta>, share: &SharedFolder) -> SharedFolder {
+    let mut share = share.clone();
+    share.note = share_meta
+        .get(&share.id)
+        .and_then(|meta| meta.share_note.clone());
+    share
+}
+
+/// 根据下载回执更新共享的下载统计，返回更新后的统计
+///
+/// `verified` 为共享者比对大小与哈希后的结果，失败只计入失败次数
+fn record_download_receipt(
+    stats: &DashMap<String, ShareDownloadStats>,
+    share_id: &str,
+    receipt: &DownloadCompleteRequest,
+    verified: bool,
+    now: u64,
+) -> ShareDownloadStats {
+    let mut entry = stats.entry(share_id.to_string()).or_default();
+    if verified {
+        entry.completed_count += 1;
+        entry.total_bytes += receipt.size;
+    } else {
+        entry.failed_count += 1;
+    }
+    entry.last_completed_at = Some(now);
+    entry.clone()
+}
+
+/// 文件共享服务的鉴权策略：大厅签名 + 共享密码（共享不存在时放行，由 handler 返回 404）
+fn share_auth_policy(shared_folders: Arc<DashMap<String, SharedFolder>>) -> AuthPolicy {
+    AuthPolicy::with_share_password(Arc::new(move |share_id: &str, provided: &str| {
+        match shared_folders.get(share_id).and_then(|share| share.password.clone()) {
+            Some(stored) => verify_share_password(&stored, provided),
+            None => true,
+        }
+    }))
+}
+
+/// 计算文件的 SHA-256（十六进制，阻塞 IO，需在 spawn_blocking 中调用）
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::Digest;
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// 比对共享者本地文件与下载端上报的大小和 SHA-256
+fn receipt_matches(receipt: &DownloadCompleteRequest, local_size: u64, local_sha256: &str) -> bool {
+    receipt.size_matched
+        && receipt.size == local_size
+        && receipt
+            .sha256
+            .as_deref()
+            .is_some_and(|sha256| sha256.eq_ignore_ascii_case(local_sha256))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn derive_password_hash(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, iterations, &mut out);
+    out
 }
 
-fn is_share_access_allowed(share: &SharedFolder, headers: &HeaderMap) -> bool {
-    if let Some(expected_password) = &share.password {
-        let provided_password = headers
-            .get("x-share-password")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
+/// 对共享密码做加盐哈希（PBKDF2-HMAC-SHA256，随机盐）
+pub fn hash_share_password(password: &str) -> String {
+    let salt: [u8; PASSWORD_SALT_LEN] = rand::random();
+    let hash = derive_password_hash(password, &salt, PASSWORD_HASH_ITERATIONS);
+    format!(
+        "{}${}${}${}",
+        PASSWORD_HASH_SCHEME,
+        PASSWORD_HASH_ITERATIONS,
+        to_hex(&salt),
+        to_hex(&hash)
+    )
+}
 
-        return ct_eq(provided_password.as_bytes(), expected_password.as_bytes());
+/// 解析哈希串，返回（迭代次数，盐，哈希）；不是哈希格式时返回 None
+fn parse_password_hash(stored: &str) -> Option<(u32, Vec<u8>, Vec<u8>)> {
+    let mut parts = stored.split('$');
+    if parts.next()? != PASSWORD_HASH_SCHEME {
+        return None;
+    }
+    let iterations = parts
+        .next()?
+        .parse::<u32>()
+        .ok()
+        .filter(|n| (1..=MAX_PASSWORD_HASH_ITERATIONS).contains(n))?;
+    let salt = from_hex(parts.next()?)?;
+    let hash = from_hex(parts.next()?)?;
+    if parts.next().is_some() || hash.len() != 32 {
+        return None;
+    }
+    Some((iterations, salt, hash))
+}
+
+/// 存储的密码是否已经是哈希格式
+pub fn is_share_password_hash(stored: &str) -> bool {
+    parse_password_hash(stored).is_some()
+}
+
+/// 校验明文密码与存储值是否匹配
+///
+/// 存储值不是哈希格式时按旧版明文比较，保证迁移前的共享仍可访问。
+/// PBKDF2 计算较重，异步上下文中应放在 `spawn_blocking` 中调用。
+pub fn verify_share_password(stored: &str, provided: &str) -> bool {
+    match parse_password_hash(stored) {
+        Some((iterations, salt, hash)) => {
+            ct_eq(&derive_password_hash(provided, &salt, iterations), &hash)
+        }
+        None => ct_eq(stored.as_bytes(), provided.as_bytes()),
     }
+}
 
-    true
+/// 把用户输入的共享密码规范化为哈希存储：空密码视为无密码，其余一律哈希（即使形如哈希串）
+fn normalize_share_password(share: &mut SharedFolder) {
+    share.password = share
+        .password
+        .take()
+        .filter(|p| !p.is_empty())
+        .map(|p| hash_share_password(&p));
+    share.has_password = share.password.is_some();
 }
 
 /// 常量时间字符串比较，避免密码校验的时间侧信道
@@ -360,36 +1095,203 @@ fn ct_eq(a: &[u8], b: &[u8]) -> bool {
     diff == 0
 }
 
+/// 文件共享 HTTP 路由
+fn build_router(state: AppState) -> Router {
+    Router::new()
+        .route("/api/shares", get(list_shares))
+        .route("/api/shares/:share_id/files", get(list_files))
+        .route("/api/shares/:share_id/verify", post(verify_password))
+        .route("/api/shares/:share_id/download/*file_path", get(download_file))
+        .route("/api/shares/:share_id/thumbnail/*file_path", get(file_thumbnail))
+        .route("/api/shares/:share_id/batch-download", post(batch_download))
+        .route("/api/shares/:share_id/download-all", get(download_all))
+        .route("/api/shares/:share_id/download-complete", post(download_complete))
+        .route("/api/shares/:share_id/upload", post(upload_file))
+        .layer(axum::middleware::from_fn_with_state(
+            share_auth_policy(state.shared_folders.clone()),
+            crate::modules::http_auth::require_auth,
+        ))
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
 /// 安全地把共享内的相对路径拼接到共享根目录，防止路径穿越（`..` 逃逸）。
 ///
-/// 仅允许「正常」路径段，拒绝绝对路径、根、盘符前缀以及任何 `..` 父目录段，
-/// 从而保证最终路径一定位于共享目录内部。返回 `None` 表示路径非法。
+/// `/` 与 `\` 都视为分隔符（Windows 上两者等价，不能只按当前平台拆分），
+/// 拒绝绝对路径、`..` 父目录段、含 `:` 的段（盘符 `C:`、NTFS 备用数据流）和 NUL，
+/// 从而保证拼出的路径在字面上位于共享目录内部。返回 `None` 表示路径非法。
+/// 这里只做字面检查，符号链接逃逸由 `resolve_share_path` 负责。
 fn safe_join(base: &Path, rel: &str) -> Option<PathBuf> {
-    use std::path::Component;
-
-    let rel_path = Path::new(rel);
-    if rel_path.is_absolute() {
+    if rel.starts_with(['/', '\\']) || Path::new(rel).is_absolute() || rel.contains('\0') {
         return None;
     }
 
     let mut result = base.to_path_buf();
-    for comp in rel_path.components() {
-        match comp {
-            Component::Normal(c) => result.push(c),
-            Component::CurDir => {} // 忽略 "."
-            // 拒绝 ".."、根目录、盘符前缀等可能逃出共享目录的段
-            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+    for segment in rel.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            s if s.contains(':') => return None,
+            s => result.push(s),
         }
     }
     Some(result)
 }
 
+/// 解析共享内的相对路径，并确认其真实位置仍在共享根目录内
+///
+/// 先用 `safe_join` 做字面检查，再对共享根目录和目标路径（目标不存在时取最近的已存在上级）
+/// 做 canonicalize，用 `starts_with` 确认没有经由符号链接 / 目录联接逃出共享目录。
+/// 越界返回 403，共享根目录本身不存在返回 404。
+fn resolve_share_path(base: &Path, rel: &str) -> Result<PathBuf, StatusCode> {
+    let joined = safe_join(base, rel).ok_or_else(|| {
+        log::warn!("⚠️ 路径安全检查失败（疑似路径穿越）: {}", rel);
+        StatusCode::FORBIDDEN
+    })?;
+    let real_base = base.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let mut probe = joined.as_path();
+    let real = loop {
+        match probe.canonicalize() {
+            Ok(real) => break real,
+            Err(_) => probe = probe.parent().ok_or(StatusCode::FORBIDDEN)?,
+        }
+    };
+    if !real.starts_with(&real_base) {
+        log::warn!("⚠️ 路径经符号链接逃出共享目录，已拒绝: {} -> {:?}", rel, real);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(joined)
+}
+
+/// 解析上传目标路径
+///
+/// 共享必须标记为可写；路径必须非空、位于共享目录内（`resolve_share_path`），
+/// 不能覆盖备注元数据文件或上传临时文件，文件名不能超长。
+fn resolve_upload_target(share: &SharedFolder, rel: &str) -> Result<PathBuf, StatusCode> {
+    if !share.writable {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let target = resolve_share_path(Path::new(&share.path), rel)?;
+    let file_name = target
+        .strip_prefix(&share.path)
+        .ok()
+        .and_then(|rel| rel.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    if file_name == SHARE_META_FILE || file_name.ends_with(UPLOAD_PARTIAL_SUFFIX) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if file_name.chars().count() > MAX_FILE_NAME_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(target)
+}
+
+/// 上传文件到可写共享（请求体即文件内容）
+///
+/// 先写入同目录的临时文件，完整写完后再重命名为目标文件，中途断开不会留下半个文件；
+/// 目标文件已存在时返回 409，不覆盖共享者的文件。
+async fn upload_file(
+    State(state): State<AppState>,
+    AxumPath(share_id): AxumPath<String>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let share = state
+        .shared_folders
+        .get(&share_id)
+        .map(|entry| entry.value().clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let target = resolve_upload_target(&share, &query.path)?;
+    // 声明的大小已超限时直接拒绝，不创建临时文件
+    let max_bytes = state.upload_max_bytes.load(Ordering::Relaxed);
+    let declared = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > max_bytes) {
+        log::warn!("⚠️ 拒绝超过大小上限的上传: share={} path={} size={:?}", share_id, query.path, declared);
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    if tokio::fs::try_exists(&target).await.unwrap_or(true) {
+        return Err(StatusCode::CONFLICT);
+    }
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let mut partial_name = target.file_name().unwrap_or_default().to_os_string();
+    partial_name.push(UPLOAD_PARTIAL_SUFFIX);
+    let partial = target.with_file_name(partial_name);
+    // create_new：同一路径的并发上传只有一个能进行
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&partial)
+        .await
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    let mut size: u64 = 0;
+    let mut stream = body.into_data_stream();
+    let written: Result<(), StatusCode> = async {
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+            // Content-Length 可能缺失或不实，写入过程中同样按上限截断
+            size += chunk.len() as u64;
+            if size > max_bytes {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            file.write_all(&chunk).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+        file.flush().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        drop(file);
+        // 写入期间目标可能已被共享者创建，此时同样不覆盖
+        if tokio::fs::try_exists(&target).await.unwrap_or(true) {
+            return Err(StatusCode::CONFLICT);
+        }
+        tokio::fs::rename(&partial, &target)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    }
+    .await;
+    if let Err(status) = written {
+        log::warn!("⚠️ 上传失败: share={} path={} status={}", share_id, query.path, status);
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(status);
+    }
+
+    log::info!("📤 收到上传文件: share={} path={} size={}", share_id, query.path, size);
+    if let Some(app) = state.app_handle.read().as_ref() {
+        let _ = app.emit("file-uploaded-by-peer", serde_json::json!({
+            "shareId": share_id,
+            "shareName": share.name,
+            "filePath": query.path,
+            "size": size,
+        }));
+    }
+
+    Ok(Json(UploadResponse { path: query.path, size }))
+}
+
 /// 获取共享列表
 async fn list_shares(State(state): State<AppState>) -> Json<ShareListResponse> {
     let shares: Vec<SharedFolder> = state
         .shared_folders
         .iter()
-        .map(|entry| entry.value().clone())
+        .map(|entry| with_share_note(&state.share_meta, entry.value()))
         .collect();
 
     log::debug!("📋 收到获取共享列表请求，返回 {} 个共享", shares.len());
@@ -402,7 +1304,6 @@ async fn list_files(
     State(state): State<AppState>,
     AxumPath(share_id): AxumPath<String>,
     Query(params): Query<HashMap<String, String>>,
-    headers: HeaderMap,
 ) -> Result<Json<FileListResponse>, StatusCode> {
     // 获取共享信息
     let share = state
@@ -410,18 +1311,17 @@ async fn list_files(
         .get(&share_id)
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    if !is_share_access_allowed(&share, &headers) {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
     let base_path = PathBuf::from(&share.path);
     let sub_path = params.get("path").map(|s| s.as_str()).unwrap_or("");
 
-    // 安全检查：使用 safe_join 防止路径穿越，确保路径在共享目录内
-    let full_path = match safe_join(&base_path, sub_path) {
-        Some(p) => p,
-        None => return Err(StatusCode::FORBIDDEN),
-    };
+    // 安全检查：防止路径穿越，确保路径在共享目录内
+    let full_path = resolve_share_path(&base_path, sub_path)?;
+
+    let meta = state
+        .share_meta
+        .get(&share_id)
+        .map(|entry| entry.value().clone())
+        .unwrap_or_default();
 
     // 读取目录
     let mut files = Vec::new();
@@ -440,6 +1340,9 @@ async fn list_files(
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
         let name = entry.file_name().to_string_lossy().to_string();
+        if name == SHARE_META_FILE || name.ends_with(UPLOAD_PARTIAL_SUFFIX) {
+            continue;
+        }
         let relative_path = if sub_path.is_empty() {
             name.clone()
         } else {
@@ -453,11 +1356,27 @@ async fn list_files(
             .map(|d| d.as_secs())
             .unwrap_or(0);
 
+        let is_dir = metadata.is_dir();
+        let mime_type = if is_dir { None } else { mime_type_for(&name) };
+        // 只内联已缓存的缩略图，未生成的由前端按需请求缩略图端点，避免列表响应过大
+        let thumbnail_base64 = if !is_dir && supports_thumbnail(&name) {
+            state
+                .thumbnails
+                .lock()
+                .get(&full_path.join(&name), &file_etag(&metadata))
+                .map(|data| base64::engine::general_purpose::STANDARD.encode(data.as_slice()))
+        } else {
+            None
+        };
+
         files.push(FileInfo {
+            note: meta.note_for(&relative_path).map(str::to_string),
+            mime_type: mime_type.map(str::to_string),
+            thumbnail_base64,
             name,
             path: relative_path,
             size: metadata.len(),
-            is_dir: metadata.is_dir(),
+            is_dir,
             modified,
         });
     }
@@ -485,8 +1404,8 @@ async fn verify_password(
     AxumPath(share_id): AxumPath<String>,
     Json(req): Json<VerifyPasswordRequest>,
 ) -> Json<VerifyPasswordResponse> {
-    let share = match state.shared_folders.get(&share_id) {
-        Some(s) => s,
+    let stored = match state.shared_folders.get(&share_id) {
+        Some(s) => s.password.clone(),
         None => {
             return Json(VerifyPasswordResponse {
                 success: false,
@@ -495,8 +1414,11 @@ async fn verify_password(
         }
     };
 
-    let success = match &share.password {
-        Some(pwd) => ct_eq(pwd.as_bytes(), req.password.as_bytes()),
+    let success = match stored {
+        // PBKDF2 放到阻塞线程，避免占用异步工作线程
+        Some(stored) => tokio::task::spawn_blocking(move || verify_share_password(&stored, &req.password))
+            .await
+            .unwrap_or(false),
         None => true, // 无密码保护
     };
 
@@ -522,17 +1444,10 @@ async fn download_file(
         .get(&share_id)
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    if !is_share_access_allowed(&share, &headers) {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
     let base_path = PathBuf::from(&share.path);
 
-    // 安全检查：使用 safe_join 防止路径穿越
-    let full_path = match safe_join(&base_path, &file_path) {
-        Some(p) => p,
-        None => return Err(StatusCode::FORBIDDEN),
-    };
+    // 安全检查：防止路径穿越，确保路径在共享目录内
+    let full_path = resolve_share_path(&base_path, &file_path)?;
 
     if !full_path.exists() {
         return Err(StatusCode::NOT_FOUND);
@@ -548,90 +1463,239 @@ async fn download_file(
     }
 
     let file_size = metadata.len();
+    let etag = file_etag(&metadata);
+    let file_name = full_path.file_name().unwrap().to_string_lossy().to_string();
+
+    // If-Range 与当前 ETag 不一致说明文件已变化，忽略 Range 返回完整文件
+    let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let if_range_matches = headers
+        .get(header::IF_RANGE)
+        .map(|v| v.to_str().map(|s| s.trim() == etag).unwrap_or(false))
+        .unwrap_or(true);
+    let range = if if_range_matches {
+        parse_range(range_header, file_size)
+    } else {
+        ByteRange::Full
+    };
 
-    // 解析Range头
-    let range = headers
-        .get(header::RANGE)
-        .and_then(|v| v.to_str().ok())
-        .and_then(parse_range);
+    let (status, start, length) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, file_size),
+        ByteRange::Partial { start, end } => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        ByteRange::Unsatisfiable => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::ETAG, &etag)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+                .body(Body::empty())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
 
-    match range {
-        Some((start, end)) => {
-            // 范围请求
-            let end = end.min(file_size - 1);
-            let length = end - start + 1;
+    let mut file = File::open(&full_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if start > 0 {
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
 
-            let mut file = File::open(&full_path)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let stream = create_file_stream(file, length);
 
-            file.seek(std::io::SeekFrom::Start(start))
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let stream = create_file_stream(file, length);
-
-            Response::builder()
-                .status(StatusCode::PARTIAL_CONTENT)
-                .header(header::CONTENT_TYPE, "application/octet-stream")
-                .header(header::CONTENT_LENGTH, length)
-                .header(
-                    header::CONTENT_RANGE,
-                    format!("bytes {}-{}/{}", start, end, file_size),
-                )
-                .header(
-                    header::CONTENT_DISPOSITION,
-                    format!(
-                        "attachment; filename=\"{}\"",
-                        full_path.file_name().unwrap().to_string_lossy()
-                    ),
-                )
-                .body(Body::from_stream(stream))
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-        }
-        None => {
-            // 完整文件请求
-            let file = File::open(&full_path)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, length)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, start + length - 1, file_size),
+        );
+    }
+    builder
+        .body(Body::from_stream(stream))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
 
-            let stream = create_file_stream(file, file_size);
+/// 接收下载端的下载完成回执，比对本地文件的 SHA-256 后更新统计并通知前端
+async fn download_complete(
+    State(state): State<AppState>,
+    AxumPath(share_id): AxumPath<String>,
+    Json(req): Json<DownloadCompleteRequest>,
+) -> Result<Json<DownloadCompleteResponse>, StatusCode> {
+    let share = state
+        .shared_folders
+        .get(&share_id)
+        .map(|entry| entry.value().clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-            Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/octet-stream")
-                .header(header::CONTENT_LENGTH, file_size)
-                .header(
-                    header::CONTENT_DISPOSITION,
-                    format!(
-                        "attachment; filename=\"{}\"",
-                        full_path.file_name().unwrap().to_string_lossy()
-                    ),
-                )
-                .body(Body::from_stream(stream))
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    // 回执中的路径同样必须位于共享目录内（与其它端点一致，符号链接逃逸也拒绝）
+    let file_path = resolve_share_path(Path::new(&share.path), &req.file_path)?;
+
+    // 在阻塞线程中计算共享文件的哈希，文件缺失或不可读按校验失败处理
+    let local = tokio::task::spawn_blocking(move || {
+        let size = std::fs::metadata(&file_path)?.len();
+        Ok::<_, std::io::Error>((size, sha256_file(&file_path)?))
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let verified = match &local {
+        Ok((size, sha256)) => receipt_matches(&req, *size, sha256),
+        Err(e) => {
+            log::warn!("⚠️ 无法计算共享文件哈希: share={} file={} 错误: {}", share_id, req.file_path, e);
+            false
         }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let stats = record_download_receipt(&state.download_stats, &share_id, &req, verified, now);
+
+    log::info!(
+        "📬 收到下载回执: share={} file={} size={} verified={}",
+        share_id, req.file_path, req.size, verified
+    );
+
+    if let Some(app) = state.app_handle.read().as_ref() {
+        let _ = app.emit("download-completed-by-peer", serde_json::json!({
+            "shareId": share_id,
+            "shareName": share.name,
+            "filePath": req.file_path,
+            "size": req.size,
+            "sha256": req.sha256,
+            "verified": verified,
+            "stats": stats,
+        }));
     }
+
+    Ok(Json(DownloadCompleteResponse { verified, stats }))
 }
 
-/// 解析Range头
-fn parse_range(range_str: &str) -> Option<(u64, u64)> {
-    // 格式: "bytes=start-end"
-    let range_str = range_str.strip_prefix("bytes=")?;
-    let parts: Vec<&str> = range_str.split('-').collect();
+/// Range 请求解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ByteRange {
+    /// 返回完整文件（无 Range 或 Range 无法识别）
+    Full,
+    /// 返回 [start, end] 闭区间
+    Partial { start: u64, end: u64 },
+    /// 范围超出文件大小（416）
+    Unsatisfiable,
+}
 
-    if parts.len() != 2 {
-        return None;
+/// 解析 Range 头（仅支持单个范围：`bytes=start-end`、`bytes=start-`、`bytes=-suffix`）
+///
+/// 多范围或格式错误时按规范忽略 Range，返回完整文件。
+fn parse_range(range_header: Option<&str>, file_size: u64) -> ByteRange {
+    let Some(spec) = range_header.and_then(|r| r.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    if first.is_empty() {
+        // 后缀范围：最后 N 个字节
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if file_size == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial {
+                start: file_size.saturating_sub(n),
+                end: file_size - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
     }
 
-    let start = parts[0].parse::<u64>().ok()?;
-    let end = if parts[1].is_empty() {
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
         u64::MAX
     } else {
-        parts[1].parse::<u64>().ok()?
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
     };
+    if start >= file_size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.min(file_size - 1),
+    }
+}
 
-    Some((start, end))
+/// 基于修改时间和文件大小生成 ETag
+fn file_etag(metadata: &std::fs::Metadata) -> String {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", mtime, metadata.len())
+}
+
+/// 获取图片文件的缩略图（JPEG，首次请求时生成并缓存）
+async fn file_thumbnail(
+    State(state): State<AppState>,
+    AxumPath((share_id, file_path)): AxumPath<(String, String)>,
+) -> Result<Response, StatusCode> {
+    let base_path = state
+        .shared_folders
+        .get(&share_id)
+        .map(|share| PathBuf::from(&share.path))
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // 安全检查：防止路径穿越，确保路径在共享目录内
+    let full_path = resolve_share_path(&base_path, &file_path)?;
+    let metadata = tokio::fs::metadata(&full_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if metadata.is_dir() || !supports_thumbnail(&file_path) {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    if metadata.len() > THUMBNAIL_MAX_SOURCE_BYTES {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let etag = file_etag(&metadata);
+    let cached = state.thumbnails.lock().get(&full_path, &etag);
+    let data = match cached {
+        Some(data) => data,
+        None => {
+            let source = full_path.clone();
+            let data = tokio::task::spawn_blocking(move || std::fs::read(&source).ok().and_then(|raw| make_thumbnail(&raw)))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::UNSUPPORTED_MEDIA_TYPE)?;
+            let data = Arc::new(data);
+            log::debug!("🖼️ 生成缩略图: share={} path={} size={}", share_id, file_path, data.len());
+            state.thumbnails.lock().insert(full_path, etag.clone(), data.clone());
+            data
+        }
+    };
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "image/jpeg")
+        .header(header::CONTENT_LENGTH, data.len())
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, "private, max-age=300")
+        .body(Body::from(data.as_ref().clone()))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 /// 创建文件流
@@ -660,11 +1724,154 @@ fn create_file_stream(
     }
 }
 
+/// 把打包线程写出的字节按块送入响应流
+///
+/// 下载端断开后接收端被丢弃，写入返回 `BrokenPipe`，打包线程随之停止。
+struct ChunkSender {
+    tx: tokio::sync::mpsc::Sender<Result<bytes::Bytes, std::io::Error>>,
+    buf: Vec<u8>,
+}
+
+impl ChunkSender {
+    fn send_buffered(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = bytes::Bytes::from(std::mem::replace(&mut self.buf, Vec::with_capacity(ZIP_STREAM_CHUNK_SIZE)));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "下载端已断开"))
+    }
+}
+
+impl std::io::Write for ChunkSender {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let n = data.len().min(ZIP_STREAM_CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() >= ZIP_STREAM_CHUNK_SIZE {
+            self.send_buffered()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_buffered()
+    }
+}
+
+/// 整个共享文件夹打包下载（流式 ZIP，不落地临时文件，支持 Range 续传）
+async fn download_all(
+    State(state): State<AppState>,
+    AxumPath(share_id): AxumPath<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let (share_name, base_path) = {
+        let share = state
+            .shared_folders
+            .get(&share_id)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        (share.name.clone(), PathBuf::from(&share.path))
+    };
+
+    // 限制并发打包数：每个打包任务占用一个阻塞线程和固定大小的缓冲
+    let permit = state.zip_permits.clone().try_acquire_owned().map_err(|_| {
+        log::warn!("⚠️ 打包下载并发已满，拒绝请求: share_id={}", share_id);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    let plan = tokio::task::spawn_blocking(move || {
+        crate::modules::share_zip::collect_sources(&base_path).map(|mut sources| {
+            sources.retain(|s| s.name != SHARE_META_FILE && !s.name.ends_with(UPLOAD_PARTIAL_SUFFIX));
+            crate::modules::share_zip::ZipPlan::new(sources)
+        })
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|e| {
+        log::error!("❌ 读取共享目录失败: share_id={} - {}", share_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total = plan.total_len();
+    let etag = plan.etag();
+    let range_header = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let if_range_matches = headers
+        .get(header::IF_RANGE)
+        .map(|v| v.to_str().map(|s| s.trim() == etag).unwrap_or(false))
+        .unwrap_or(true);
+    let range = if if_range_matches {
+        parse_range(range_header, total)
+    } else {
+        ByteRange::Full
+    };
+    let (status, start, length) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, total),
+        ByteRange::Partial { start, end } => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        ByteRange::Unsatisfiable => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::ETAG, &etag)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+                .body(Body::empty())
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    log::info!(
+        "📦 开始流式打包共享: share_id={} ({} 个文件, {} 字节, 从 {} 开始)",
+        share_id,
+        plan.entry_count(),
+        total,
+        start
+    );
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(ZIP_STREAM_BUFFERED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let mut sender = ChunkSender {
+            tx: tx.clone(),
+            buf: Vec::with_capacity(ZIP_STREAM_CHUNK_SIZE),
+        };
+        if let Err(e) = plan.write_range(start, length, &mut sender) {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                log::warn!("⚠️ 流式打包中断: share_id={} - {}", share_id, e);
+                let _ = tx.blocking_send(Err(e));
+            }
+        }
+    });
+    let stream = async_stream::stream! {
+        while let Some(chunk) = rx.recv().await {
+            yield chunk;
+        }
+    };
+
+    let zip_filename = format!("{}.zip", share_name);
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_LENGTH, length)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename*=UTF-8''{}", urlencoding::encode(&zip_filename)),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, start + length - 1, total),
+        );
+    }
+    builder
+        .body(Body::from_stream(stream))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 /// 批量打包下载（先压后发）
 async fn batch_download(
     State(state): State<AppState>,
     AxumPath(share_id): AxumPath<String>,
-    headers: HeaderMap,
     Json(req): Json<BatchDownloadRequest>,
 ) -> Result<Response, StatusCode> {
     log::info!("📦 收到批量打包下载请求: share_id={}, files={}", share_id, req.file_paths.len());
@@ -678,10 +1885,6 @@ async fn batch_download(
             StatusCode::NOT_FOUND
         })?;
 
-    if !is_share_access_allowed(&share, &headers) {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-
     // 检查是否启用了"先压后发"
     if !share.compress_before_send.unwrap_or(false) {
         log::warn!("⚠️ 共享未启用先压后发功能");
@@ -711,13 +1914,9 @@ async fn batch_download(
     
     // 添加文件到ZIP
     for file_path in &req.file_paths {
-        // 安全检查：使用 safe_join 防止路径穿越
-        let full_path = match safe_join(&base_path, file_path) {
-            Some(p) => p,
-            None => {
-                log::warn!("⚠️ 路径安全检查失败（疑似路径穿越）: {}", file_path);
-                continue;
-            }
+        // 安全检查：防止路径穿越，越界的条目跳过
+        let Ok(full_path) = resolve_share_path(&base_path, file_path) else {
+            continue;
         };
         
         if !full_path.exists() {
@@ -799,3 +1998,778 @@ async fn batch_download(
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(file_path: &str, size: u64, size_matched: bool) -> DownloadCompleteRequest {
+        DownloadCompleteRequest {
+            file_path: file_path.to_string(),
+            size,
+            sha256: Some("abc123".to_string()),
+            size_matched,
+        }
+    }
+
+    fn test_state(share: SharedFolder) -> AppState {
+        let shared_folders = Arc::new(DashMap::new());
+        shared_folders.insert(share.id.clone(), share);
+        AppState {
+            shared_folders,
+            download_stats: Arc::new(DashMap::new()),
+            share_meta: Arc::new(DashMap::new()),
+            app_handle: Arc::new(RwLock::new(None)),
+            zip_permits: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_ZIP_STREAMS)),
+            thumbnails: Arc::new(parking_lot::Mutex::new(ThumbnailCache::default())),
+            upload_max_bytes: Arc::new(AtomicU64::new(DEFAULT_UPLOAD_MAX_BYTES)),
+        }
+    }
+
+    fn test_share(password: Option<&str>) -> SharedFolder {
+        SharedFolder {
+            id: "share-1".to_string(),
+            name: "存档".to_string(),
+            path: std::env::temp_dir().to_string_lossy().to_string(),
+            password: password.map(|p| p.to_string()),
+            has_password: password.is_some(),
+            expires_at: None,
+            compress_before_send: None,
+            writable: false,
+            owner_id: "owner".to_string(),
+            created_at: 0,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_record_download_receipt_updates_stats() {
+        let stats = DashMap::new();
+
+        let s = record_download_receipt(&stats, "share-1", &receipt("a.zip", 100, true), true, 10);
+        assert_eq!(s.completed_count, 1);
+        assert_eq!(s.total_bytes, 100);
+
+        let s = record_download_receipt(&stats, "share-1", &receipt("b.zip", 50, true), true, 20);
+        assert_eq!(s.completed_count, 2);
+        assert_eq!(s.total_bytes, 150);
+        assert_eq!(s.last_completed_at, Some(20));
+
+        // 校验失败只计入失败次数，不累计字节数
+        let s = record_download_receipt(&stats, "share-1", &receipt("c.zip", 70, false), false, 30);
+        assert_eq!(s.completed_count, 2);
+        assert_eq!(s.failed_count, 1);
+        assert_eq!(s.total_bytes, 150);
+
+        // 不同共享的统计互不影响
+        let other = record_download_receipt(&stats, "share-2", &receipt("a.zip", 1, true), true, 40);
+        assert_eq!(other.completed_count, 1);
+        assert_eq!(other.total_bytes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_complete_handler_records_receipt() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("world")).unwrap();
+        std::fs::write(dir.path().join("world/level.dat"), vec![7u8; 2048]).unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        let state = test_state(share);
+        let sha256 = sha256_file(&dir.path().join("world/level.dat")).unwrap();
+
+        let mut matching = receipt("world/level.dat", 2048, true);
+        matching.sha256 = Some(sha256.to_uppercase());
+        let Json(response) = download_complete(
+            State(state.clone()),
+            AxumPath("share-1".to_string()),
+            Json(matching),
+        )
+        .await
+        .expect("回执应被接受");
+        assert!(response.verified);
+        assert_eq!(response.stats.completed_count, 1);
+        assert_eq!(response.stats.total_bytes, 2048);
+        assert_eq!(state.download_stats.get("share-1").unwrap().completed_count, 1);
+
+        // 大小一致但哈希不符：不算校验通过，只计入失败次数
+        let Json(response) = download_complete(
+            State(state.clone()),
+            AxumPath("share-1".to_string()),
+            Json(receipt("world/level.dat", 2048, true)),
+        )
+        .await
+        .expect("回执应被接受");
+        assert!(!response.verified);
+        assert_eq!(response.stats.completed_count, 1);
+        assert_eq!(response.stats.failed_count, 1);
+
+        // 旧版本回执的 verified 字段按大小检查解析
+        let legacy: DownloadCompleteRequest = serde_json::from_value(serde_json::json!({
+            "file_path": "world/level.dat",
+            "size": 2048,
+            "sha256": sha256,
+            "verified": true,
+        }))
+        .unwrap();
+        assert!(legacy.size_matched);
+        assert!(receipt_matches(&legacy, 2048, &sha256));
+        assert!(!receipt_matches(&legacy, 2047, &sha256));
+    }
+
+    #[tokio::test]
+    async fn test_download_complete_handler_rejects_invalid_requests() {
+        let state = test_state(test_share(Some("secret1")));
+
+        // 共享不存在
+        let result = download_complete(
+            State(state.clone()),
+            AxumPath("missing".to_string()),
+            Json(receipt("a.zip", 1, true)),
+        )
+        .await;
+        assert_eq!(result.err(), Some(StatusCode::NOT_FOUND));
+
+        // 路径穿越（共享密码由鉴权中间件统一校验，见 test_share_auth_policy）
+        let result = download_complete(
+            State(state.clone()),
+            AxumPath("share-1".to_string()),
+            Json(receipt("../outside.txt", 1, true)),
+        )
+        .await;
+        assert_eq!(result.err(), Some(StatusCode::FORBIDDEN));
+
+        assert!(state.download_stats.is_empty(), "被拒绝的回执不应更新统计");
+    }
+
+    #[test]
+    fn test_share_auth_policy() {
+        use crate::modules::http_auth::{AuthRequest, HttpAuthError, SHARE_PASSWORD_HEADER};
+
+        let mut share = test_share(Some("secret1"));
+        normalize_share_password(&mut share);
+        let state = test_state(share);
+        let policy = share_auth_policy(state.shared_folders.clone());
+        let check = |path: &str, password: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(password) = password {
+                headers.insert(SHARE_PASSWORD_HEADER, password.parse().unwrap());
+            }
+            policy.authorize(None, &AuthRequest { method: "GET", path, query: None, headers: &headers, is_local: false }, 0)
+        };
+
+        assert_eq!(check("/api/shares/share-1/files", None), Err(HttpAuthError::SharePassword));
+        assert_eq!(check("/api/shares/share-1/download/a.zip", Some("wrong")), Err(HttpAuthError::SharePassword));
+        assert_eq!(check("/api/shares/share-1/download-complete", Some("secret1")), Ok(()));
+        // 不存在的共享交给 handler 返回 404
+        assert_eq!(check("/api/shares/missing/files", None), Ok(()));
+    }
+
+    #[test]
+    fn test_remove_share_clears_download_stats() {
+        let service = FileTransferService::new();
+        service.add_share(test_share(None)).unwrap();
+        record_download_receipt(&service.download_stats, "share-1", &receipt("a.zip", 10, true), true, 1);
+        assert_eq!(service.get_download_stats("share-1").completed_count, 1);
+
+        service.remove_share("share-1").unwrap();
+        assert_eq!(service.get_download_stats("share-1"), ShareDownloadStats::default());
+    }
+
+    #[test]
+    fn test_cleanup_expired_shares() {
+        let service = FileTransferService::new();
+        service.add_share(test_share(None)).unwrap();
+        let mut expired = test_share(None);
+        expired.id = "share-2".to_string();
+        expired.expires_at = Some(1);
+        assert!(expired.is_expired(1));
+        assert!(!expired.is_expired(0));
+        // add_share 拒绝已过期的共享，这里直接登记模拟到期
+        assert!(service.add_share(expired.clone()).is_err());
+        assert!(service.shared_folders.get("share-2").is_none());
+        service.shared_folders.insert(expired.id.clone(), expired);
+        record_download_receipt(&service.download_stats, "share-2", &receipt("a.zip", 10, true), true, 1);
+
+        assert_eq!(service.cleanup_expired_shares(), vec!["share-2".to_string()]);
+        assert!(service.shared_folders.get("share-2").is_none());
+        assert!(service.shared_folders.get("share-1").is_some());
+        assert_eq!(service.get_download_stats("share-2"), ShareDownloadStats::default());
+        assert!(service.cleanup_expired_shares().is_empty());
+
+        // 旧版本的 expire_time 字段仍可解析
+        let mut json = serde_json::to_value(test_share(None)).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("expires_at");
+        fields.insert("expire_time".to_string(), serde_json::json!(42));
+        let legacy: SharedFolder = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.expires_at, Some(42));
+    }
+
+    #[test]
+    fn test_download_complete_url() {
+        assert_eq!(
+            download_complete_url("10.126.126.2", "share-1"),
+            "http://10.126.126.2:14539/api/shares/share-1/download-complete"
+        );
+    }
+
+    #[test]
+    fn test_share_meta_set_and_remove_notes() {
+        let mut meta = ShareMeta::default();
+        meta.set_note("", "  这是优化版，先装这个  ");
+        meta.set_note("mods\\optifine.jar", "必装");
+        meta.set_note("/config/", "配置文件");
+
+        assert_eq!(meta.note_for(""), Some("这是优化版，先装这个"));
+        assert_eq!(meta.note_for("mods/optifine.jar"), Some("必装"));
+        assert_eq!(meta.note_for("config"), Some("配置文件"));
+        assert_eq!(meta.note_for("other.txt"), None);
+
+        // 空备注表示删除
+        meta.set_note("mods/optifine.jar", "");
+        meta.set_note("", " ");
+        assert_eq!(meta.note_for("mods/optifine.jar"), None);
+        assert_eq!(meta.note_for(""), None);
+    }
+
+    #[test]
+    fn test_resolve_share_path_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("saves")).unwrap();
+        std::fs::write(dir.path().join("saves/world.zip"), b"zip").unwrap();
+        let base = dir.path();
+
+        assert_eq!(resolve_share_path(base, "saves/world.zip"), Ok(base.join("saves").join("world.zip")));
+        assert_eq!(resolve_share_path(base, "./saves\\world.zip"), Ok(base.join("saves").join("world.zip")));
+        assert_eq!(resolve_share_path(base, ""), Ok(base.to_path_buf()));
+        // 目标尚不存在时按最近的已存在上级判断
+        assert_eq!(resolve_share_path(base, "saves/new/a.zip"), Ok(base.join("saves").join("new").join("a.zip")));
+
+        for rel in [
+            "../../../../Windows/System32",
+            "saves/../../etc/passwd",
+            "..\\..\\Windows\\System32",
+            "saves\\..\\..\\secret.txt",
+            "/etc/passwd",
+            "\\Windows\\System32",
+            "C:\\Windows\\System32",
+            "C:/Windows/System32",
+            "saves/C:evil.txt",
+            "saves/world.zip:hidden",
+            "\\\\server\\share\\file",
+            "saves/world.zip\0.txt",
+        ] {
+            assert_eq!(resolve_share_path(base, rel), Err(StatusCode::FORBIDDEN), "{}", rel);
+        }
+
+        // 共享根目录不存在
+        assert_eq!(resolve_share_path(&base.join("missing"), "a.zip"), Err(StatusCode::NOT_FOUND));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_share_path_rejects_symlink_escape() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), b"secret").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("saves")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), dir.path().join("secret.txt")).unwrap();
+        // 指向共享内部的链接是允许的
+        std::os::unix::fs::symlink(dir.path().join("saves"), dir.path().join("inner")).unwrap();
+        let base = dir.path();
+
+        assert_eq!(resolve_share_path(base, "link"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_share_path(base, "link/secret.txt"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_share_path(base, "secret.txt"), Err(StatusCode::FORBIDDEN));
+        // 经由链接目录上传新文件同样拒绝
+        assert_eq!(resolve_share_path(base, "link/new.txt"), Err(StatusCode::FORBIDDEN));
+        assert!(resolve_share_path(base, "inner").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_router_rejects_url_encoded_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("modpack.zip"), b"zip").unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        // 以本机连接访问，跳过请求签名
+        let app = build_router(test_state(share))
+            .layer(axum::Extension(crate::modules::http_auth::ConnectionOrigin { is_local: true }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = reqwest::Client::new();
+        let status = |path: &str| {
+            let request = client.get(format!("http://{}/api/shares/share-1{}", addr, path));
+            async move { request.send().await.unwrap().status().as_u16() }
+        };
+
+        assert_eq!(status("/download/modpack.zip").await, 200);
+        assert_eq!(status("/files?path=").await, 200);
+        // 服务端解码后才出现 `..`、`\`、盘符
+        for path in [
+            "/download/..%2f..%2f..%2fetc%2fpasswd",
+            "/download/%2e%2e%2f%2e%2e%2fetc%2fpasswd",
+            "/download/..%5c..%5cWindows%5cwin.ini",
+            "/download/C%3a%5cWindows%5cwin.ini",
+            "/files?path=..%2f..%2f..%2f..%2fWindows%2fSystem32",
+            "/files?path=%2e%2e%5c%2e%2e",
+            "/files?path=C%3A%2FWindows",
+        ] {
+            assert_eq!(status(path).await, 403, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_all_streams_zip_with_range() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("mods")).unwrap();
+        std::fs::write(dir.path().join("mods/a.jar"), vec![1u8; 100_000]).unwrap();
+        std::fs::write(dir.path().join("readme.txt"), b"hi").unwrap();
+        std::fs::write(dir.path().join(SHARE_META_FILE), b"{}").unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        let app = build_router(test_state(share))
+            .layer(axum::Extension(crate::modules::http_auth::ConnectionOrigin { is_local: true }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let url = format!("http://{}/api/shares/share-1/download-all", addr);
+        let client = reqwest::Client::new();
+        let resp = client.get(&url).send().await.unwrap();
+        assert_eq!(resp.status().as_u16(), 200);
+        let etag = resp.headers()[reqwest::header::ETAG].to_str().unwrap().to_string();
+        let full = resp.bytes().await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(full.to_vec())).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, vec!["mods/a.jar", "readme.txt"]);
+        let mut readme = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("readme.txt").unwrap(), &mut readme).unwrap();
+        assert_eq!(readme, "hi");
+
+        // 续传：同一 ETag 下从中间继续，拼起来与完整下载一致
+        let resp = client
+            .get(&url)
+            .header(reqwest::header::RANGE, "bytes=5000-")
+            .header(reqwest::header::IF_RANGE, &etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 206);
+        assert_eq!(
+            resp.headers()[reqwest::header::CONTENT_RANGE].to_str().unwrap(),
+            format!("bytes 5000-{}/{}", full.len() - 1, full.len())
+        );
+        assert_eq!(resp.bytes().await.unwrap(), full.slice(5000..));
+
+        // 目录内容变化后 ETag 不再匹配，返回完整 ZIP
+        std::fs::write(dir.path().join("new.txt"), b"new").unwrap();
+        let resp = client
+            .get(&url)
+            .header(reqwest::header::RANGE, "bytes=5000-")
+            .header(reqwest::header::IF_RANGE, &etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 200);
+    }
+
+    #[test]
+    fn test_resolve_upload_target_rules() {
+        let mut share = test_share(None);
+        // 只读共享拒绝上传
+        assert_eq!(resolve_upload_target(&share, "a.zip"), Err(StatusCode::FORBIDDEN));
+
+        share.writable = true;
+        let target = resolve_upload_target(&share, "saves/world.zip").unwrap();
+        assert_eq!(target, Path::new(&share.path).join("saves").join("world.zip"));
+
+        // 路径穿越、元数据文件、空路径与超长文件名都被拒绝
+        assert_eq!(resolve_upload_target(&share, "../evil.exe"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_upload_target(&share, "saves/../../evil.exe"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_upload_target(&share, SHARE_META_FILE), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_upload_target(&share, "a.zip.mctier-upload"), Err(StatusCode::FORBIDDEN));
+        assert_eq!(resolve_upload_target(&share, ""), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            resolve_upload_target(&share, &"a".repeat(MAX_FILE_NAME_CHARS + 1)),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_writes_into_writable_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        share.writable = true;
+        let state = test_state(share);
+
+        let upload = |path: &str, content: &'static [u8]| {
+            upload_file(
+                State(state.clone()),
+                AxumPath("share-1".to_string()),
+                Query(UploadQuery { path: path.to_string() }),
+                HeaderMap::new(),
+                Body::from(content),
+            )
+        };
+
+        let Json(response) = upload("saves/world.zip", b"level data").await.expect("上传应成功");
+        assert_eq!(response.size, 10);
+        assert_eq!(std::fs::read(dir.path().join("saves/world.zip")).unwrap(), b"level data");
+        // 不留下临时文件，也不覆盖已有文件
+        assert!(!dir.path().join("saves/world.zip.mctier-upload").exists());
+        assert_eq!(upload("saves/world.zip", b"other").await.unwrap_err(), StatusCode::CONFLICT);
+        assert_eq!(std::fs::read(dir.path().join("saves/world.zip")).unwrap(), b"level data");
+        assert_eq!(upload("../outside.zip", b"x").await.unwrap_err(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_enforces_size_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        share.writable = true;
+        let state = test_state(share);
+        state.upload_max_bytes.store(4, Ordering::Relaxed);
+
+        let upload = |path: &str, headers: HeaderMap, content: &'static [u8]| {
+            upload_file(
+                State(state.clone()),
+                AxumPath("share-1".to_string()),
+                Query(UploadQuery { path: path.to_string() }),
+                headers,
+                Body::from(content),
+            )
+        };
+
+        // 声明的大小超限：直接拒绝
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_LENGTH, "10".parse().unwrap());
+        assert_eq!(upload("big.zip", headers, b"level data").await.unwrap_err(), StatusCode::PAYLOAD_TOO_LARGE);
+        // 未声明大小：写入时超限同样拒绝，并删除临时文件
+        assert_eq!(upload("big.zip", HeaderMap::new(), b"level data").await.unwrap_err(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!dir.path().join("big.zip").exists());
+        assert!(!dir.path().join("big.zip.mctier-upload").exists());
+
+        let Json(response) = upload("ok.txt", HeaderMap::new(), b"tiny").await.expect("未超限的上传应成功");
+        assert_eq!(response.size, 4);
+    }
+
+    #[tokio::test]
+    async fn test_set_file_note_persists_and_lists_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("modpack.zip"), b"zip").unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+
+        let service = FileTransferService::new();
+        service.add_share(share.clone()).unwrap();
+        service.set_file_note("share-1", "", "整合包").await.unwrap();
+        service.set_file_note("share-1", "modpack.zip", "先装这个").await.unwrap();
+
+        // 不存在的文件、路径穿越与超长备注都被拒绝
+        assert!(service.set_file_note("share-1", "missing.zip", "x").await.is_err());
+        assert!(service.set_file_note("share-1", "../outside", "x").await.is_err());
+        assert!(service.set_file_note("share-1", "", &"长".repeat(MAX_NOTE_CHARS + 1)).await.is_err());
+        assert!(service.set_file_note("missing", "", "x").await.is_err());
+
+        // 备注写入共享根目录，重新添加共享后仍可读取
+        let saved = load_share_meta(dir.path());
+        assert_eq!(saved.note_for("modpack.zip"), Some("先装这个"));
+        let service = FileTransferService::new();
+        service.add_share(share.clone()).unwrap();
+        assert_eq!(service.get_shares()[0].note.as_deref(), Some("整合包"));
+
+        // 列目录时返回备注并隐藏元数据文件
+        let state = test_state(share);
+        state.share_meta.insert("share-1".to_string(), saved);
+        let Json(list) = list_files(
+            State(state),
+            AxumPath("share-1".to_string()),
+            Query(HashMap::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(list.files.len(), 1);
+        assert_eq!(list.files[0].name, "modpack.zip");
+        assert_eq!(list.files[0].note.as_deref(), Some("先装这个"));
+    }
+
+    #[tokio::test]
+    async fn test_list_files_mime_types_and_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(400, 200, image::Rgb([30, 160, 90]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        std::fs::write(dir.path().join("screenshot.PNG"), &png).unwrap();
+        std::fs::write(dir.path().join("modpack.mrpack"), b"zip").unwrap();
+        std::fs::write(dir.path().join("notes.unknown"), b"?").unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        let state = test_state(share);
+
+        let list = || async {
+            let Json(list) = list_files(State(state.clone()), AxumPath("share-1".to_string()), Query(HashMap::new()))
+                .await
+                .unwrap();
+            list.files
+        };
+        let files = list().await;
+        let mime: Vec<_> = files.iter().map(|f| f.mime_type.as_deref()).collect();
+        assert_eq!(mime, vec![Some("application/x-modrinth-modpack+zip"), None, Some("image/png")]);
+        assert!(files.iter().all(|f| f.thumbnail_base64.is_none()));
+
+        // 缩略图按需生成，之后的列表内联缓存结果
+        let response = file_thumbnail(State(state.clone()), AxumPath(("share-1".to_string(), "screenshot.PNG".to_string())))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let thumbnail = image::load_from_memory(&body).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION / 2));
+        assert!(list().await[2].thumbnail_base64.is_some());
+
+        // 非图片与路径穿越被拒绝
+        let status = file_thumbnail(State(state.clone()), AxumPath(("share-1".to_string(), "modpack.mrpack".to_string())))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let status = file_thumbnail(State(state), AxumPath(("share-1".to_string(), "../x.png".to_string())))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), ByteRange::Partial { start: 0, end: 9 });
+        assert_eq!(parse_range(Some("bytes=90-"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), ByteRange::Partial { start: 50, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-10"), 100), ByteRange::Partial { start: 90, end: 99 });
+        assert_eq!(parse_range(Some("bytes=-500"), 100), ByteRange::Partial { start: 0, end: 99 });
+
+        // 超出文件大小
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+
+        // 无法识别的 Range 被忽略
+        assert_eq!(parse_range(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=abc"), 100), ByteRange::Full);
+    }
+
+    #[tokio::test]
+    async fn test_download_file_supports_range_and_if_range() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("modpack.zip"), b"0123456789").unwrap();
+        let mut share = test_share(None);
+        share.path = dir.path().to_string_lossy().to_string();
+        let state = test_state(share);
+
+        let request = |headers: HeaderMap| {
+            download_file(
+                State(state.clone()),
+                AxumPath(("share-1".to_string(), "modpack.zip".to_string())),
+                headers,
+            )
+        };
+        let body = |response: Response| async move {
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()
+        };
+
+        // 完整下载带 Accept-Ranges 与 ETag
+        let response = request(HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(&body(response).await[..], b"0123456789");
+
+        // 续传
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=4-".parse().unwrap());
+        headers.insert(header::IF_RANGE, etag.parse().unwrap());
+        let response = request(headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 4-9/10");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "6");
+        assert_eq!(&body(response).await[..], b"456789");
+
+        // ETag 不匹配（文件已变化）时返回完整文件
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=4-".parse().unwrap());
+        headers.insert(header::IF_RANGE, "\"stale\"".parse().unwrap());
+        let response = request(headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&body(response).await[..], b"0123456789");
+
+        // 超出范围
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, "bytes=10-".parse().unwrap());
+        let response = request(headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+    }
+
+    #[test]
+    fn test_shares_export_import_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("mctier-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let export_path = dir.join("shares.json");
+
+        let service = FileTransferService::new();
+        let mut share = test_share(Some("secret1"));
+        share.compress_before_send = Some(true);
+        service.add_share(share).unwrap();
+        assert_eq!(service.export_shares(&export_path).unwrap(), 1);
+
+        // 导出文件中不含密码明文与共享 ID
+        let json = std::fs::read_to_string(&export_path).unwrap();
+        assert!(!json.contains("secret1"));
+        assert!(!json.contains("share-1"));
+
+        // 文件服务未运行：共享已登记，服务启动后生效
+        let target = FileTransferService::new();
+        let report = target.import_shares(&export_path, "new-owner").unwrap();
+        assert!(!report.shared);
+        assert!(report.skipped.is_empty());
+        assert_eq!(target.get_shares().len(), 1);
+
+        let imported = &report.imported[0];
+        assert_eq!(imported.name, "存档");
+        assert_eq!(imported.path, std::env::temp_dir().to_string_lossy());
+        assert_eq!(imported.owner_id, "new-owner");
+        assert_eq!(imported.compress_before_send, Some(true));
+        assert_ne!(imported.id, "share-1");
+        // 密码哈希原样导入，原密码仍可用
+        assert!(imported.has_password);
+        assert!(verify_share_password(imported.password.as_deref().unwrap(), "secret1"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_skips_invalid_paths() {
+        let file = std::env::temp_dir().join(format!("mctier-not-a-dir-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, b"x").unwrap();
+        let export = SharesExport {
+            version: SHARES_EXPORT_VERSION,
+            exported_at: 0,
+            shares: vec![
+                ShareExportEntry {
+                    name: "存档".to_string(),
+                    path: std::env::temp_dir().to_string_lossy().to_string(),
+                    password_hash: None,
+                    compress_before_send: None,
+                    writable: false,
+                },
+                ShareExportEntry {
+                    name: "已删除".to_string(),
+                    path: "/mctier/definitely/missing".to_string(),
+                    password_hash: None,
+                    compress_before_send: None,
+                    writable: false,
+                },
+                ShareExportEntry {
+                    name: "文件".to_string(),
+                    path: file.to_string_lossy().to_string(),
+                    password_hash: None,
+                    compress_before_send: None,
+                    writable: false,
+                },
+                ShareExportEntry {
+                    name: "迭代过多".to_string(),
+                    path: std::env::temp_dir().to_string_lossy().to_string(),
+                    password_hash: Some(format!("{}$4000000000$00${}", PASSWORD_HASH_SCHEME, "00".repeat(32))),
+                    compress_before_send: None,
+                    writable: false,
+                },
+            ],
+        };
+
+        let (shares, skipped) = prepare_imported_shares(export, "owner", 42);
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].created_at, 42);
+        assert!(!shares[0].has_password);
+        assert_eq!(
+            skipped.iter().map(|s| (s.name.as_str(), s.reason.as_str())).collect::<Vec<_>>(),
+            vec![("已删除", "文件夹不存在"), ("文件", "路径不是文件夹"), ("迭代过多", "密码哈希无效")]
+        );
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_share_password_hash_roundtrip() {
+        let stored = hash_share_password("secret1");
+        assert!(is_share_password_hash(&stored));
+        assert!(!stored.contains("secret1"));
+        assert!(verify_share_password(&stored, "secret1"));
+        assert!(!verify_share_password(&stored, "secret2"));
+        assert!(!verify_share_password(&stored, ""));
+
+        // 随机盐：同一密码两次哈希结果不同
+        assert_ne!(stored, hash_share_password("secret1"));
+
+        // 迭代次数超出上限的哈希串不被接受
+        let parts: Vec<&str> = stored.split('$').collect();
+        let huge = format!("{}${}${}${}", parts[0], u32::MAX, parts[2], parts[3]);
+        assert!(!is_share_password_hash(&huge));
+        let max = format!("{}${}${}${}", parts[0], MAX_PASSWORD_HASH_ITERATIONS, parts[2], parts[3]);
+        assert!(is_share_password_hash(&max));
+
+        // 旧版明文存储仍按明文比对
+        assert!(!is_share_password_hash("secret1"));
+        assert!(verify_share_password("secret1", "secret1"));
+        assert!(!verify_share_password("secret1", "secret2"));
+    }
+
+    #[test]
+    fn test_add_share_hashes_password_and_never_serializes_it() {
+        let service = FileTransferService::new();
+        service.add_share(test_share(Some("secret1"))).unwrap();
+
+        let stored = service.shared_folders.get("share-1").unwrap().clone();
+        let hash = stored.password.clone().unwrap();
+        assert!(is_share_password_hash(&hash));
+        assert!(stored.has_password);
+
+        let json = serde_json::to_value(&stored).unwrap();
+        assert!(json.get("password").is_none());
+        assert_eq!(json["has_password"], true);
+
+        // 用户输入的密码即使形如哈希串也照样哈希，哈希串本身才是密码
+        let mut again = test_share(None);
+        again.password = Some(hash.clone());
+        service.add_share(again).unwrap();
+        let stored = service.shared_folders.get("share-1").unwrap().password.clone().unwrap();
+        assert_ne!(stored, hash);
+        assert!(verify_share_password(&stored, &hash));
+        assert!(!verify_share_password(&stored, "secret1"));
+
+        // 空密码视为无密码
+        service.add_share(test_share(Some(""))).unwrap();
+        let share = service.shared_folders.get("share-1").unwrap().clone();
+        assert!(share.password.is_none());
+        assert!(!share.has_password);
+    }
+
+    #[test]
+    fn test_guest_mode_rejects_new_shares() {
+        let service = FileTransferService::new();
+        service.set_guest_mode(true);
+        assert!(service.add_share(test_share(None)).is_err());
+        assert!(service.get_shares().is_empty());
+
+        service.set_guest_mode(false);
+        assert!(service.add_share(test_share(None)).is_ok());
+    }
+}
@@ -3,16 +3,210 @@
 
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use crate::modules::error::AppError;
 
+/// MCTier 记录块开始标记前缀（后跟会话 ID）
+const BLOCK_BEGIN_PREFIX: &str = "# MCTIER-BEGIN";
+/// MCTier 记录块结束标记
+const BLOCK_END: &str = "# MCTIER-END";
+/// 旧版本使用的记录块开始标记前缀（后跟大厅名称）
+const LEGACY_BEGIN_PREFIX: &str = "# MCTier Magic DNS";
+/// 旧版本使用的记录块结束标记
+const LEGACY_END: &str = "# MCTier Magic DNS End";
+/// UTF-8 BOM
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
 /// 进程级 hosts 文件操作锁：串行化所有「读-改-写」，防止并发交错导致 hosts 文件损坏
 fn hosts_lock() -> &'static Mutex<()> {
     static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
     LOCK.get_or_init(|| Mutex::new(()))
 }
 
+/// 系统 hosts 文件路径
+fn system_hosts_path() -> PathBuf {
+    #[cfg(windows)]
+    let hosts_path = PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts");
+    
+    #[cfg(not(windows))]
+    let hosts_path = PathBuf::from("/etc/hosts");
+    
+    hosts_path
+}
+
+/// hosts 文件的原始格式，写回时保持一致
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct HostsFormat {
+    /// 文件以 UTF-8 BOM 开头
+    bom: bool,
+    /// 文件不是合法 UTF-8（如 Windows 本地代码页 GBK），按字节原样保留
+    legacy_encoding: bool,
+    /// 使用 CRLF 换行
+    crlf: bool,
+}
+
+/// 解码 hosts 文件内容，统一为 LF 换行的字符串
+/// 
+/// 非 UTF-8 内容按字节逐个映射为字符（Latin-1），保证写回时字节不变，
+/// 避免用户在 hosts 中用本地代码页写的注释被破坏
+fn decode_hosts(bytes: &[u8]) -> (String, HostsFormat) {
+    let mut format = HostsFormat::default();
+    let bytes = match bytes.strip_prefix(UTF8_BOM) {
+        Some(rest) => {
+            format.bom = true;
+            rest
+        }
+        None => bytes,
+    };
+    let content = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => {
+            format.legacy_encoding = true;
+            bytes.iter().map(|&b| b as char).collect()
+        }
+    };
+    format.crlf = content.contains("\r\n");
+    (content.replace("\r\n", "\n"), format)
+}
+
+/// 按原始格式编码 hosts 文件内容
+fn encode_hosts(content: &str, format: HostsFormat) -> Vec<u8> {
+    let content = if format.crlf {
+        content.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else {
+        content.to_string()
+    };
+    let mut bytes = Vec::with_capacity(content.len() + UTF8_BOM.len());
+    if format.bom {
+        bytes.extend_from_slice(UTF8_BOM);
+    }
+    if format.legacy_encoding {
+        for ch in content.chars() {
+            match u8::try_from(ch as u32) {
+                Ok(b) => bytes.push(b),
+                Err(_) => {
+                    let mut buf = [0u8; 4];
+                    bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+    } else {
+        bytes.extend_from_slice(content.as_bytes());
+    }
+    bytes
+}
+
+/// 判断是否为 MCTier 记录块开始标记（包含旧版本格式）
+fn is_block_begin(line: &str) -> bool {
+    line.starts_with(BLOCK_BEGIN_PREFIX) || (line.starts_with(LEGACY_BEGIN_PREFIX) && line != LEGACY_END)
+}
+
+/// 判断是否为 MCTier 记录块结束标记（包含旧版本格式）
+fn is_block_end(line: &str) -> bool {
+    line == BLOCK_END || line == LEGACY_END
+}
+
+/// 移除内容中所有 MCTier 记录块（无论属于哪个会话）
+/// 
+/// 缺少结束标记的残缺块只移除其中的 `.mct.net` 映射，其余行原样保留，
+/// 防止误删用户自己写在后面的记录
+/// 
+/// # 返回
+/// (移除后的内容, 移除的记录块数量)
+fn strip_mctier_blocks(content: &str) -> (String, usize) {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut pending: Option<Vec<&str>> = None;
+    let mut removed = 0;
+    
+    for line in content.lines() {
+        let trimmed = line.trim_end();
+        if is_block_begin(trimmed) {
+            if let Some(block) = pending.take() {
+                // 上一个块没有结束标记
+                kept.extend(block.into_iter().filter(|l| !l.trim_end().ends_with(".mct.net")));
+            }
+            removed += 1;
+            pending = Some(Vec::new());
+        } else if is_block_end(trimmed) {
+            if pending.take().is_none() {
+                // 孤立的结束标记，同样视为残留
+                continue;
+            }
+        } else if let Some(block) = pending.as_mut() {
+            block.push(line);
+        } else {
+            kept.push(line);
+        }
+    }
+    if let Some(block) = pending.take() {
+        kept.extend(block.into_iter().filter(|l| !l.trim_end().ends_with(".mct.net")));
+    }
+    
+    let mut result = kept.join("\n");
+    if !result.is_empty() {
+        // 确保文件以换行符结尾
+        result.push('\n');
+    }
+    (result, removed)
+}
+
+/// 读取 hosts 文件并解码
+fn read_hosts_file(path: &Path) -> Result<(String, HostsFormat), AppError> {
+    let mut file = File::open(path)
+        .map_err(|e| AppError::FileError(format!("无法打开hosts文件: {}", e)))?;
+    
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| AppError::FileError(format!("无法读取hosts文件: {}", e)))?;
+    
+    Ok(decode_hosts(&bytes))
+}
+
+/// 按原始格式写回 hosts 文件
+/// 
+/// 部分安全软件会把 hosts 设为只读，此时临时去掉只读属性，写入后再恢复
+fn write_hosts_file(path: &Path, content: &str, format: HostsFormat) -> Result<(), AppError> {
+    let bytes = encode_hosts(content, format);
+    
+    let readonly = std::fs::metadata(path)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(false);
+    if readonly {
+        log::info!("📝 [HostsManager] hosts文件为只读，临时取消只读属性");
+        set_readonly(path, false)?;
+    }
+    
+    let result = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| AppError::FileError(format!("无法打开hosts文件进行写入: {}. 请确保以管理员权限运行", e)))
+        .and_then(|mut file| {
+            file.write_all(&bytes)
+                .map_err(|e| AppError::FileError(format!("无法写入hosts文件: {}", e)))
+        });
+    
+    if readonly {
+        if let Err(e) = set_readonly(path, true) {
+            log::warn!("⚠️ [HostsManager] 恢复hosts文件只读属性失败: {}", e);
+        }
+    }
+    result
+}
+
+/// 设置文件只读属性
+fn set_readonly(path: &Path, readonly: bool) -> Result<(), AppError> {
+    let mut permissions = std::fs::metadata(path)
+        .map_err(|e| AppError::FileError(format!("无法读取hosts文件属性: {}", e)))?
+        .permissions();
+    // Windows 下只切换只读属性位，不涉及 Unix 权限位
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(readonly);
+    std::fs::set_permissions(path, permissions)
+        .map_err(|e| AppError::FileError(format!("无法修改hosts文件只读属性: {}. 请确保以管理员权限运行", e)))
+}
+
 /// Hosts文件管理器
 pub struct HostsManager {
     hosts_path: PathBuf,
@@ -22,106 +216,50 @@ pub struct HostsManager {
 
 impl HostsManager {
     /// 创建新的Hosts管理器实例
+    /// 
+    /// 每个实例生成唯一的会话 ID，写入的记录块以 `# MCTIER-BEGIN <会话ID>` 开始、`# MCTIER-END` 结束。
+    /// 标记只使用 ASCII 字符，避免与本地代码页编码的 hosts 文件冲突
     pub fn new(lobby_name: &str) -> Self {
-        #[cfg(windows)]
-        let hosts_path = PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts");
-        
-        #[cfg(not(windows))]
-        let hosts_path = PathBuf::from("/etc/hosts");
+        let session_id = uuid::Uuid::new_v4().simple().to_string();
+        log::info!("📝 [HostsManager] 大厅 {} 的hosts会话ID: {}", lobby_name, session_id);
         
         Self {
-            hosts_path,
-            marker_start: format!("# MCTier Magic DNS - {}", lobby_name),
-            marker_end: "# MCTier Magic DNS End".to_string(),
+            hosts_path: system_hosts_path(),
+            marker_start: format!("{} {}", BLOCK_BEGIN_PREFIX, session_id),
+            marker_end: BLOCK_END.to_string(),
         }
     }
     
     /// 清理所有MCTier相关的hosts记录（静态方法）
     /// 
-    /// 此方法会清理所有以"# MCTier Magic DNS"开头的记录块，
-    /// 无论大厅名称是什么，确保彻底清理
+    /// 此方法会清理所有 `# MCTIER-BEGIN` 记录块（以及旧版本的 `# MCTier Magic DNS` 块），
+    /// 无论属于哪个会话，用于启动时清理上次崩溃遗留的记录
     /// 
     /// # 返回
-    /// * `Ok(())` - 清理成功
+    /// * `Ok(usize)` - 清理的记录块数量
     /// * `Err(AppError)` - 清理失败
-    pub fn cleanup_all_mctier_entries() -> Result<(), AppError> {
+    pub fn cleanup_all_mctier_entries() -> Result<usize, AppError> {
         log::info!("🧹 开始清理所有MCTier hosts记录...");
         // 串行化 hosts 读-改-写
         let _guard = hosts_lock().lock().unwrap_or_else(|e| e.into_inner());
         
-        #[cfg(windows)]
-        let hosts_path = PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts");
+        let hosts_path = system_hosts_path();
+        let (content, format) = read_hosts_file(&hosts_path)?;
+        let (new_content, removed_count) = strip_mctier_blocks(&content);
         
-        #[cfg(not(windows))]
-        let hosts_path = PathBuf::from("/etc/hosts");
-        
-        // 读取hosts文件
-        let mut file = File::open(&hosts_path)
-            .map_err(|e| AppError::FileError(format!("无法打开hosts文件: {}", e)))?;
-        
-        let mut content = String::new();
-        file.read_to_string(&mut content)
-            .map_err(|e| AppError::FileError(format!("无法读取hosts文件: {}", e)))?;
-        
-        // 分析并移除所有MCTier相关的记录块
-        let lines: Vec<&str> = content.lines().collect();
-        let mut new_lines = Vec::new();
-        let mut in_mctier_section = false;
-        let mut removed_count = 0;
-        
-        for line in lines {
-            if line.starts_with("# MCTier Magic DNS") {
-                in_mctier_section = true;
-                removed_count += 1;
-                continue;
-            } else if line == "# MCTier Magic DNS End" {
-                in_mctier_section = false;
-                continue;
-            } else if in_mctier_section {
-                // 跳过MCTier区域内的所有行
-                continue;
-            } else {
-                new_lines.push(line);
-            }
+        if removed_count == 0 {
+            // 没有残留时不写文件，避免非管理员运行时无谓报错
+            log::info!("✅ 没有发现MCTier hosts记录，无需清理");
+            return Ok(0);
         }
         
-        // 重新组合内容
-        let new_content = new_lines.join("\n");
-        if !new_content.is_empty() && !new_content.ends_with('\n') {
-            // 确保文件以换行符结尾
-            let new_content = format!("{}\n", new_content);
-            
-            // 写回hosts文件
-            let mut file = OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .open(&hosts_path)
-                .map_err(|e| AppError::FileError(format!("无法打开hosts文件进行写入: {}. 请确保以管理员权限运行", e)))?;
-            
-            file.write_all(new_content.as_bytes())
-                .map_err(|e| AppError::FileError(format!("无法写入hosts文件: {}", e)))?;
-        } else {
-            // 写回hosts文件
-            let mut file = OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .open(&hosts_path)
-                .map_err(|e| AppError::FileError(format!("无法打开hosts文件进行写入: {}. 请确保以管理员权限运行", e)))?;
-            
-            file.write_all(new_content.as_bytes())
-                .map_err(|e| AppError::FileError(format!("无法写入hosts文件: {}", e)))?;
-        }
+        write_hosts_file(&hosts_path, &new_content, format)?;
         
         // 刷新DNS缓存
         Self::flush_dns_cache_static()?;
         
-        if removed_count > 0 {
-            log::info!("✅ 已清理 {} 个MCTier hosts记录块", removed_count);
-        } else {
-            log::info!("✅ 没有发现MCTier hosts记录，无需清理");
-        }
-        
-        Ok(())
+        log::info!("✅ 已清理 {} 个MCTier hosts记录块", removed_count);
+        Ok(removed_count)
     }
     
     /// 刷新DNS缓存（静态方法）
@@ -182,7 +320,7 @@ impl HostsManager {
         
         // 读取现有hosts文件内容
         log::info!("📝 [HostsManager] 正在读取hosts文件...");
-        let mut content = self.read_hosts()?;
+        let (mut content, format) = self.read_hosts()?;
         log::info!("📝 [HostsManager] Hosts文件读取成功，大小: {} 字节", content.len());
         
         // 查找MCTier标记区域
@@ -225,7 +363,7 @@ impl HostsManager {
         
         // 写回hosts文件
         log::info!("📝 [HostsManager] 正在写入hosts文件...");
-        self.write_hosts(&content)?;
+        self.write_hosts(&content, format)?;
         
         log::info!("✅ [HostsManager] hosts记录添加成功");
         Ok(())
@@ -244,7 +382,7 @@ impl HostsManager {
         let _guard = hosts_lock().lock().unwrap_or_else(|e| e.into_inner());
         
         // 读取现有hosts文件内容
-        let content = self.read_hosts()?;
+        let (content, format) = self.read_hosts()?;
         
         // 查找MCTier标记区域
         let (before_marker, mctier_section, after_marker) = self.split_content(&content);
@@ -264,7 +402,7 @@ impl HostsManager {
         let new_content = format!("{}{}{}", before_marker, new_section, after_marker);
         
         // 写回hosts文件
-        self.write_hosts(&new_content)?;
+        self.write_hosts(&new_content, format)?;
         
         log::info!("✅ hosts记录删除成功");
         Ok(())
@@ -280,7 +418,7 @@ impl HostsManager {
         let _guard = hosts_lock().lock().unwrap_or_else(|e| e.into_inner());
         
         // 读取现有hosts文件内容
-        let content = self.read_hosts()?;
+        let (content, format) = self.read_hosts()?;
         
         // 查找MCTier标记区域
         let (before_marker, _, after_marker) = self.split_content(&content);
@@ -289,7 +427,7 @@ impl HostsManager {
         let new_content = format!("{}{}", before_marker, after_marker);
         
         // 写回hosts文件
-        self.write_hosts(&new_content)?;
+        self.write_hosts(&new_content, format)?;
         
         log::info!("✅ 所有MCTier hosts记录已清理");
         Ok(())
@@ -314,27 +452,13 @@ impl HostsManager {
     }
     
     /// 读取hosts文件内容
-    fn read_hosts(&self) -> Result<String, AppError> {
-        let mut file = File::open(&self.hosts_path)
-            .map_err(|e| AppError::FileError(format!("无法打开hosts文件: {}", e)))?;
-        
-        let mut content = String::new();
-        file.read_to_string(&mut content)
-            .map_err(|e| AppError::FileError(format!("无法读取hosts文件: {}", e)))?;
-        
-        Ok(content)
+    fn read_hosts(&self) -> Result<(String, HostsFormat), AppError> {
+        read_hosts_file(&self.hosts_path)
     }
     
     /// 写入hosts文件内容
-    fn write_hosts(&self, content: &str) -> Result<(), AppError> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.hosts_path)
-            .map_err(|e| AppError::FileError(format!("无法打开hosts文件进行写入: {}. 请确保以管理员权限运行", e)))?;
-        
-        file.write_all(content.as_bytes())
-            .map_err(|e| AppError::FileError(format!("无法写入hosts文件: {}", e)))?;
+    fn write_hosts(&self, content: &str, format: HostsFormat) -> Result<(), AppError> {
+        write_hosts_file(&self.hosts_path, content, format)?;
         
         // 刷新DNS缓存
         self.flush_dns_cache()?;
@@ -353,10 +477,10 @@ impl HostsManager {
         let mut in_mctier_section = false;
         
         for line in lines {
-            if line.starts_with(&self.marker_start) {
+            if line.trim_end() == self.marker_start {
                 in_mctier_section = true;
                 mctier.push(line);
-            } else if line == self.marker_end {
+            } else if in_mctier_section && line.trim_end() == self.marker_end {
                 mctier.push(line);
                 in_mctier_section = false;
             } else if in_mctier_section {
@@ -478,17 +602,92 @@ mod tests {
     fn test_split_content() {
         let manager = HostsManager::new("测试大厅");
         
-        let content = r#"127.0.0.1 localhost
-# MCTier Magic DNS - 测试大厅
-10.126.126.1 test.mct.net
-# MCTier Magic DNS End
-192.168.1.1 router
-"#;
+        let content = format!(
+            "127.0.0.1 localhost\n{}\n10.126.126.1 test.mct.net\n{}\n192.168.1.1 router\n",
+            manager.marker_start, manager.marker_end
+        );
         
-        let (before, mctier, after) = manager.split_content(content);
+        let (before, mctier, after) = manager.split_content(&content);
         
         assert!(before.contains("127.0.0.1 localhost"));
         assert!(mctier.contains("10.126.126.1 test.mct.net"));
         assert!(after.contains("192.168.1.1 router"));
     }
+    
+    #[test]
+    fn test_strip_blocks_from_any_session() {
+        let content = "127.0.0.1 localhost
+# MCTIER-BEGIN 0a1b2c
+10.126.126.1 a.mct.net
+# MCTIER-END
+192.168.1.1 router
+# MCTIER-BEGIN 3d4e5f
+10.126.126.2 b.mct.net
+# MCTIER-END
+# MCTier Magic DNS - 旧大厅
+10.126.126.3 c.mct.net
+# MCTier Magic DNS End
+";
+        let (stripped, removed) = strip_mctier_blocks(content);
+        
+        assert_eq!(removed, 3);
+        assert_eq!(stripped, "127.0.0.1 localhost\n192.168.1.1 router\n");
+    }
+    
+    #[test]
+    fn test_strip_unterminated_block_keeps_user_entries() {
+        let content = "127.0.0.1 localhost
+# MCTIER-BEGIN 0a1b2c
+10.126.126.1 a.mct.net
+192.168.1.1 router
+";
+        let (stripped, removed) = strip_mctier_blocks(content);
+        
+        assert_eq!(removed, 1);
+        assert_eq!(stripped, "127.0.0.1 localhost\n192.168.1.1 router\n");
+    }
+    
+    #[test]
+    fn test_bom_and_crlf_round_trip() {
+        let mut bytes = UTF8_BOM.to_vec();
+        bytes.extend_from_slice(b"127.0.0.1 localhost\r\n# MCTIER-BEGIN 0a1b2c\r\n10.126.126.1 a.mct.net\r\n# MCTIER-END\r\n");
+        
+        let (content, format) = decode_hosts(&bytes);
+        assert!(format.bom && format.crlf && !format.legacy_encoding);
+        assert!(!content.starts_with('\u{feff}'));
+        
+        let (stripped, _) = strip_mctier_blocks(&content);
+        let mut expected = UTF8_BOM.to_vec();
+        expected.extend_from_slice(b"127.0.0.1 localhost\r\n");
+        assert_eq!(encode_hosts(&stripped, format), expected);
+    }
+    
+    #[test]
+    fn test_non_utf8_bytes_are_preserved() {
+        // GBK 编码的「本地」注释
+        let bytes = b"# \xb1\xbe\xb5\xd8\n127.0.0.1 localhost\n".to_vec();
+        
+        let (content, format) = decode_hosts(&bytes);
+        assert!(format.legacy_encoding);
+        
+        let appended = format!("{}10.126.126.1 a.mct.net\n", content);
+        let mut expected = bytes.clone();
+        expected.extend_from_slice(b"10.126.126.1 a.mct.net\n");
+        assert_eq!(encode_hosts(&appended, format), expected);
+    }
+    
+    #[test]
+    fn test_write_restores_readonly_attribute() {
+        let path = std::env::temp_dir().join(format!("mctier-hosts-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"127.0.0.1 localhost\n").unwrap();
+        set_readonly(&path, true).unwrap();
+        
+        let (content, format) = read_hosts_file(&path).unwrap();
+        write_hosts_file(&path, &format!("{}10.126.126.1 a.mct.net\n", content), format).unwrap();
+        
+        assert!(std::fs::metadata(&path).unwrap().permissions().readonly());
+        assert_eq!(std::fs::read(&path).unwrap(), b"127.0.0.1 localhost\n10.126.126.1 a.mct.net\n");
+        set_readonly(&path, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,243 @@
+// 全局快捷键绑定
+//
+// 麦克风开关与全局听筒静音两个快捷键可由用户自定义：启动时读取配置中的绑定，
+// 运行中修改时先注销旧快捷键再注册新快捷键，新快捷键被其它程序占用时恢复旧绑定并返回明确错误。
+// 临时开麦（F2）与唤出窗口（Ctrl+Alt+M）仍为固定快捷键，自定义绑定不能与其冲突。
+
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use log::{error, info};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tokio::sync::Mutex;
+
+use crate::modules::voice_service::VoiceService;
+use crate::modules::voice_feedback::{announce, VoiceCue};
+use crate::modules::voice_service::MicMode;
+
+/// 默认麦克风快捷键
+pub const DEFAULT_MIC_HOTKEY: &str = "CommandOrControl+M";
+
+/// 默认全局听筒静音快捷键
+pub const DEFAULT_GLOBAL_MUTE_HOTKEY: &str = "CommandOrControl+T";
+
+/// 临时开麦快捷键（固定）
+pub const PUSH_TO_TALK_HOTKEY: &str = "F2";
+
+/// 唤出窗口快捷键（固定）
+pub const SUMMON_HOTKEY: &str = "CommandOrControl+Alt+M";
+
+/// 开关类快捷键的防抖间隔
+const HOTKEY_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// 快捷键错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum HotkeyError {
+    #[error("快捷键不能为空")]
+    Empty,
+
+    #[error("快捷键格式无效: {0}（示例: Ctrl+M、Ctrl+Shift+K、F4）")]
+    Invalid(String),
+
+    #[error("麦克风与全局静音不能使用同一个快捷键: {0}")]
+    Duplicate(String),
+
+    #[error("快捷键 {0} 已被程序内置功能占用，请换一个")]
+    Reserved(String),
+
+    #[error("快捷键 {key} 注册失败（可能已被其它程序占用），请换一个: {reason}")]
+    RegisterFailed { key: String, reason: String },
+}
+
+/// 解析快捷键字符串（tauri 格式，如 `CommandOrControl+M`、`Ctrl+Shift+K`）
+pub fn parse_hotkey(key: &str) -> Result<Shortcut, HotkeyError> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(HotkeyError::Empty);
+    }
+    key.parse::<Shortcut>().map_err(|_| HotkeyError::Invalid(key.to_string()))
+}
+
+/// 校验麦克风与全局静音快捷键，返回解析后的 (麦克风, 全局静音)
+pub fn validate_hotkeys(mic_key: &str, mute_key: &str) -> Result<(Shortcut, Shortcut), HotkeyError> {
+    let mic = parse_hotkey(mic_key)?;
+    let mute = parse_hotkey(mute_key)?;
+    if mic == mute {
+        return Err(HotkeyError::Duplicate(mute_key.trim().to_string()));
+    }
+    for (shortcut, key) in [(mic, mic_key), (mute, mute_key)] {
+        let reserved = [PUSH_TO_TALK_HOTKEY, SUMMON_HOTKEY]
+            .iter()
+            .any(|r| r.parse::<Shortcut>().ok() == Some(shortcut));
+        if reserved {
+            return Err(HotkeyError::Reserved(key.trim().to_string()));
+        }
+    }
+    Ok((mic, mute))
+}
+
+/// 当前生效的 (麦克风, 全局静音) 快捷键
+fn active_slot() -> &'static parking_lot::Mutex<Option<(Shortcut, Shortcut)>> {
+    static ACTIVE: OnceLock<parking_lot::Mutex<Option<(Shortcut, Shortcut)>>> = OnceLock::new();
+    ACTIVE.get_or_init(|| parking_lot::Mutex::new(None))
+}
+
+/// 返回一个防抖判断器：距上次触发不足 `HOTKEY_DEBOUNCE` 时返回 false
+fn debouncer() -> impl Fn() -> bool + Send + Sync + 'static {
+    let last = parking_lot::Mutex::new(Instant::now() - Duration::from_millis(500));
+    move || {
+        let mut last = match last.try_lock() {
+            Some(g) => g,
+            None => return false,
+        };
+        let now = Instant::now();
+        if now.duration_since(*last) < HOTKEY_DEBOUNCE {
+            return false;
+        }
+        *last = now;
+        true
+    }
+}
+
+/// 注册麦克风开关快捷键
+fn register_mic_hotkey(app: &AppHandle, voice_service: Arc<Mutex<VoiceService>>, shortcut: Shortcut) -> Result<(), HotkeyError> {
+    let ready = debouncer();
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _, ev| {
+            if ev.state == ShortcutState::Released || !ready() {
+                return;
+            }
+            let c = Arc::clone(&voice_service);
+            let h = app.clone();
+            tauri::async_runtime::spawn(async move {
+                // 按键说话模式下只能按住 F2 发声，忽略开关快捷键
+                let mode = c.lock().await.get_mic_mode().await;
+                if mode == MicMode::PushToTalk {
+                    info!("按键说话模式下忽略麦克风开关快捷键");
+                    return;
+                }
+                match c.lock().await.toggle_mic().await {
+                    Ok(s) => {
+                        let _ = h.emit("mic-toggled", s);
+                        announce(&h, VoiceCue::mic(s));
+                    }
+                    Err(e) => { error!("切换麦克风失败: {}", e); }
+                }
+            });
+        })
+        .map_err(|e| HotkeyError::RegisterFailed { key: shortcut.to_string(), reason: e.to_string() })
+}
+
+/// 注册全局听筒静音快捷键
+fn register_global_mute_hotkey(app: &AppHandle, voice_service: Arc<Mutex<VoiceService>>, shortcut: Shortcut) -> Result<(), HotkeyError> {
+    let ready = debouncer();
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _, ev| {
+            if ev.state == ShortcutState::Released || !ready() {
+                return;
+            }
+            let c = Arc::clone(&voice_service);
+            let h = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let v = c.lock().await;
+                let ns = !v.is_global_muted();
+                match v.mute_all(ns).await {
+                    Ok(_) => {
+                        let _ = h.emit("global-mute-toggled", ns);
+                        announce(&h, VoiceCue::speaker(ns));
+                    }
+                    Err(e) => { error!("切换静音失败: {}", e); }
+                }
+            });
+        })
+        .map_err(|e| HotkeyError::RegisterFailed { key: shortcut.to_string(), reason: e.to_string() })
+}
+
+/// 注册一组快捷键，任一失败时撤销本组已注册的部分
+fn register_pair(app: &AppHandle, voice_service: &Arc<Mutex<VoiceService>>, (mic, mute): (Shortcut, Shortcut)) -> Result<(), HotkeyError> {
+    register_mic_hotkey(app, Arc::clone(voice_service), mic)?;
+    if let Err(e) = register_global_mute_hotkey(app, Arc::clone(voice_service), mute) {
+        let _ = app.global_shortcut().unregister(mic);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// 启动时按配置注册快捷键；配置无效时回退到默认绑定
+pub fn install_hotkeys(app: &AppHandle, voice_service: Arc<Mutex<VoiceService>>, mic_key: Option<&str>, mute_key: Option<&str>) {
+    let pair = validate_hotkeys(
+        mic_key.unwrap_or(DEFAULT_MIC_HOTKEY),
+        mute_key.unwrap_or(DEFAULT_GLOBAL_MUTE_HOTKEY),
+    )
+    .unwrap_or_else(|e| {
+        log::warn!("配置中的快捷键无效，使用默认绑定: {}", e);
+        validate_hotkeys(DEFAULT_MIC_HOTKEY, DEFAULT_GLOBAL_MUTE_HOTKEY).expect("默认快捷键有效")
+    });
+
+    // 两个快捷键互相独立，其中一个被占用时另一个仍然注册
+    let mic_ok = register_mic_hotkey(app, Arc::clone(&voice_service), pair.0)
+        .map_err(|e| error!("麦克风快捷键注册失败: {}", e))
+        .is_ok();
+    let mute_ok = register_global_mute_hotkey(app, voice_service, pair.1)
+        .map_err(|e| error!("全局静音快捷键注册失败: {}", e))
+        .is_ok();
+    if mic_ok && mute_ok {
+        info!("快捷键注册成功: 麦克风={}, 全局静音={}", pair.0, pair.1);
+    }
+    *active_slot().lock() = Some(pair);
+}
+
+/// 运行中更换快捷键：先注销旧绑定再注册新绑定，失败时恢复旧绑定
+pub fn update_hotkeys(app: &AppHandle, voice_service: Arc<Mutex<VoiceService>>, mic_key: &str, mute_key: &str) -> Result<(), HotkeyError> {
+    let new_pair = validate_hotkeys(mic_key, mute_key)?;
+    let mut active = active_slot().lock();
+    let old_pair = *active;
+
+    if let Some((mic, mute)) = old_pair {
+        let _ = app.global_shortcut().unregister(mic);
+        let _ = app.global_shortcut().unregister(mute);
+    }
+
+    if let Err(e) = register_pair(app, &voice_service, new_pair) {
+        if let Some(old) = old_pair {
+            if let Err(restore) = register_pair(app, &voice_service, old) {
+                error!("恢复原快捷键失败: {}", restore);
+            }
+        }
+        return Err(e);
+    }
+
+    *active = Some(new_pair);
+    info!("快捷键已更新: 麦克风={}, 全局静音={}", new_pair.0, new_pair.1);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        assert_eq!(parse_hotkey("Ctrl+M").unwrap(), parse_hotkey(DEFAULT_MIC_HOTKEY).unwrap());
+        assert!(parse_hotkey(" Ctrl+Shift+K ").is_ok());
+        assert!(parse_hotkey("F4").is_ok());
+        assert_eq!(parse_hotkey("  "), Err(HotkeyError::Empty));
+        assert_eq!(parse_hotkey("Ctrl+"), Err(HotkeyError::Invalid("Ctrl+".to_string())));
+        assert_eq!(parse_hotkey("Ctrl+Foo"), Err(HotkeyError::Invalid("Ctrl+Foo".to_string())));
+    }
+
+    #[test]
+    fn test_validate_hotkeys() {
+        assert!(validate_hotkeys("Ctrl+M", "Ctrl+T").is_ok());
+        assert_eq!(
+            validate_hotkeys("Ctrl+M", "CommandOrControl+M"),
+            Err(HotkeyError::Duplicate("CommandOrControl+M".to_string()))
+        );
+        assert_eq!(validate_hotkeys("F2", "Ctrl+T"), Err(HotkeyError::Reserved("F2".to_string())));
+        assert_eq!(
+            validate_hotkeys("Ctrl+M", "Ctrl+Alt+M"),
+            Err(HotkeyError::Reserved("Ctrl+Alt+M".to_string()))
+        );
+    }
+}
@@ -0,0 +1,577 @@
+// 文件共享 / 聊天 HTTP 服务的统一鉴权
+//
+// 两个服务的所有端点都挂同一个中间件（`require_auth`），不再在各个 handler 里分散校验：
+// - 大厅签名：远程请求必须携带 `x-mctier-ts`、`x-mctier-content-sha256` 与 `x-mctier-sig`，签名是用大厅密钥对
+//   「方法 + 路径 + 规范化查询串 + 请求体 SHA-256 + 时间戳」计算的 HMAC-SHA256。密钥由大厅密码（network_key）
+//   经 HKDF 派生，同一大厅的成员无需交换任何数据就能互相验证；时间戳与本机相差超过 `SIGNATURE_MAX_SKEW` 视为重放。
+//   请求体在交给 handler 时边读边计算哈希，与签名中的哈希不符时读取请求体出错，请求随之失败。
+// - 共享密码：访问带密码共享的文件列表、下载等端点时还要携带正确的 `x-share-password`。
+// 任一项不通过返回 401。本机前端直接订阅自己的聊天流，来自本机的连接不要求签名。
+// 新增端点挂在同一个 Router 下即自动受保护。
+
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use hkdf::hmac::{Hmac, Mac};
+use hkdf::Hkdf;
+use parking_lot::RwLock;
+use sha2::Sha256;
+
+/// 签名时间戳请求头（Unix 秒）
+pub const TIMESTAMP_HEADER: &str = "x-mctier-ts";
+/// 签名请求头（小写十六进制）
+pub const SIGNATURE_HEADER: &str = "x-mctier-sig";
+/// 请求体 SHA-256 请求头（小写十六进制，参与签名）
+pub const CONTENT_SHA256_HEADER: &str = "x-mctier-content-sha256";
+/// 共享密码请求头
+pub const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+/// 签名时间戳允许的最大偏差
+pub const SIGNATURE_MAX_SKEW: Duration = Duration::from_secs(120);
+
+/// HKDF 盐值
+const KEY_DERIVATION_SALT: &[u8] = b"mctier-http-auth";
+/// HKDF info（随签名格式版本变化）
+const KEY_DERIVATION_INFO: &[u8] = b"mctier-http-auth-hmac-sha256-v2";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 鉴权错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum HttpAuthError {
+    #[error("缺少大厅签名")]
+    MissingSignature,
+
+    #[error("大厅签名无效")]
+    InvalidSignature,
+
+    #[error("签名已过期（时间戳相差 {0} 秒）")]
+    Expired(u64),
+
+    #[error("共享密码错误")]
+    SharePassword,
+}
+
+/// 由大厅密码派生的请求签名密钥
+#[derive(Clone)]
+pub struct HttpAuthKey([u8; 32]);
+
+impl HttpAuthKey {
+    /// 由大厅 network_key 派生密钥
+    pub fn from_network_key(network_key: &str) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(Some(KEY_DERIVATION_SALT), network_key.as_bytes());
+        let mut key = [0u8; 32];
+        hkdf.expand(KEY_DERIVATION_INFO, &mut key)
+            .expect("32 字节在 HKDF-SHA256 的输出长度范围内");
+        Self(key)
+    }
+
+    fn mac(&self, method: &str, path: &str, query: Option<&str>, content_sha256: &str, timestamp: u64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC 接受任意长度密钥");
+        mac.update(
+            format!(
+                "{}\n{}\n{}\n{}\n{}",
+                method.to_ascii_uppercase(),
+                path,
+                canonical_query(query),
+                content_sha256.to_ascii_lowercase(),
+                timestamp
+            )
+            .as_bytes(),
+        );
+        mac
+    }
+
+    /// 计算请求签名
+    ///
+    /// # 参数
+    /// * `method` - 请求方法
+    /// * `path` - 请求路径（保持百分号编码原样）
+    /// * `query` - 查询串（不含 `?`，保持百分号编码原样）
+    /// * `content_sha256` - 请求体的 SHA-256（十六进制）
+    /// * `timestamp` - Unix 秒
+    pub fn sign(&self, method: &str, path: &str, query: Option<&str>, content_sha256: &str, timestamp: u64) -> String {
+        encode_hex(&self.mac(method, path, query, content_sha256, timestamp).finalize().into_bytes())
+    }
+
+    /// 校验请求签名（常量时间比较）
+    pub fn verify(
+        &self,
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        content_sha256: &str,
+        timestamp: u64,
+        signature: &str,
+    ) -> bool {
+        let Some(bytes) = decode_hex(signature) else {
+            return false;
+        };
+        self.mac(method, path, query, content_sha256, timestamp)
+            .verify_slice(&bytes)
+            .is_ok()
+    }
+}
+
+/// 规范化查询串：去掉空参数后按字典序排列，参数顺序不影响签名
+fn canonical_query(query: Option<&str>) -> String {
+    let mut pairs: Vec<&str> = query
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .collect();
+    pairs.sort_unstable();
+    pairs.join("&")
+}
+
+/// 计算请求体的 SHA-256（小写十六进制）
+pub fn content_sha256(body: &[u8]) -> String {
+    use sha2::Digest;
+    encode_hex(&Sha256::digest(body))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn key_slot() -> &'static RwLock<Option<HttpAuthKey>> {
+    static SLOT: OnceLock<RwLock<Option<HttpAuthKey>>> = OnceLock::new();
+    SLOT.get_or_init(|| RwLock::new(None))
+}
+
+/// 进入大厅时启用请求签名
+///
+/// 需要在启动文件/聊天服务器之前调用。
+pub fn configure_for_lobby(network_key: &str) {
+    *key_slot().write() = Some(HttpAuthKey::from_network_key(network_key));
+}
+
+/// 关闭请求签名（离开大厅时调用）
+pub fn deactivate() {
+    key_slot().write().take();
+}
+
+fn active_key() -> Option<HttpAuthKey> {
+    key_slot().read().clone()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 为发往其他玩家 HTTP 服务的请求附加大厅签名
+///
+/// 内存中的请求体直接计算哈希；流式请求体（如上传文件）需要调用方预先设置 `CONTENT_SHA256_HEADER`。
+pub fn sign_request(request: &mut reqwest::Request) {
+    let Some(key) = active_key() else {
+        return;
+    };
+    let body_sha256 = match request.body() {
+        None => content_sha256(&[]),
+        Some(body) => match body.as_bytes() {
+            Some(bytes) => content_sha256(bytes),
+            None => match request
+                .headers()
+                .get(CONTENT_SHA256_HEADER)
+                .and_then(|v| v.to_str().ok())
+            {
+                Some(hash) => hash.to_string(),
+                None => {
+                    log::error!("❌ [HttpAuth] 流式请求体缺少 {}，对方将拒绝请求: {}", CONTENT_SHA256_HEADER, request.url().path());
+                    return;
+                }
+            },
+        },
+    };
+    let timestamp = unix_now();
+    let signature = key.sign(
+        request.method().as_str(),
+        request.url().path(),
+        request.url().query(),
+        &body_sha256,
+        timestamp,
+    );
+    let headers = request.headers_mut();
+    if let Ok(value) = timestamp.to_string().parse() {
+        headers.insert(TIMESTAMP_HEADER, value);
+    }
+    if let Ok(value) = body_sha256.parse() {
+        headers.insert(CONTENT_SHA256_HEADER, value);
+    }
+    if let Ok(value) = signature.parse() {
+        headers.insert(SIGNATURE_HEADER, value);
+    }
+}
+
+/// 附加大厅签名后发送请求（替代 `RequestBuilder::send`）
+pub async fn send_signed(builder: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+    let (client, request) = builder.build_split();
+    let mut request = request?;
+    sign_request(&mut request);
+    client.execute(request).await
+}
+
+/// 连接来源（由 `http_tls` 在接受连接时写入请求扩展）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionOrigin {
+    /// 是否来自本机
+    pub is_local: bool,
+}
+
+/// 共享密码校验：(共享 ID, 请求携带的密码) -> 是否允许访问
+///
+/// 共享不存在时应返回 true，交给 handler 返回 404。
+pub type SharePasswordCheck = Arc<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+/// 待鉴权的请求
+pub struct AuthRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub query: Option<&'a str>,
+    pub headers: &'a HeaderMap,
+    pub is_local: bool,
+}
+
+/// 服务的鉴权策略
+#[derive(Clone, Default)]
+pub struct AuthPolicy {
+    share_password: Option<SharePasswordCheck>,
+}
+
+impl AuthPolicy {
+    /// 只校验大厅签名（聊天服务）
+    pub fn lobby_only() -> Self {
+        Self::default()
+    }
+
+    /// 校验大厅签名与共享密码（文件共享服务）
+    pub fn with_share_password(check: SharePasswordCheck) -> Self {
+        Self { share_password: Some(check) }
+    }
+
+    /// 判断请求是否放行
+    ///
+    /// # 参数
+    /// * `key` - 当前大厅的签名密钥（未进入大厅时为 None，不校验签名）
+    /// * `request` - 待鉴权的请求
+    /// * `now` - 当前 Unix 秒
+    pub fn authorize(&self, key: Option<&HttpAuthKey>, request: &AuthRequest<'_>, now: u64) -> Result<(), HttpAuthError> {
+        if let (Some(key), false) = (key, request.is_local) {
+            let header = |name: &str| request.headers.get(name).and_then(|v| v.to_str().ok());
+            let (Some(timestamp), Some(body_sha256), Some(signature)) =
+                (header(TIMESTAMP_HEADER), header(CONTENT_SHA256_HEADER), header(SIGNATURE_HEADER))
+            else {
+                return Err(HttpAuthError::MissingSignature);
+            };
+            let timestamp: u64 = timestamp.trim().parse().map_err(|_| HttpAuthError::InvalidSignature)?;
+            let skew = now.abs_diff(timestamp);
+            if skew > SIGNATURE_MAX_SKEW.as_secs() {
+                return Err(HttpAuthError::Expired(skew));
+            }
+            if !key.verify(
+                request.method,
+                request.path,
+                request.query,
+                body_sha256.trim(),
+                timestamp,
+                signature.trim(),
+            ) {
+                return Err(HttpAuthError::InvalidSignature);
+            }
+        }
+
+        if let (Some(check), Some(share_id)) = (&self.share_password, protected_share_id(request.path)) {
+            let provided = request
+                .headers
+                .get(SHARE_PASSWORD_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            if !check(&share_id, provided) {
+                return Err(HttpAuthError::SharePassword);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 需要共享密码的路径中的共享 ID
+///
+/// `/api/shares/{id}/...` 下除密码验证端点（`/verify`，由 handler 自行比对请求体中的密码）外都需要共享密码；
+/// 共享列表 `/api/shares` 不需要。
+fn protected_share_id(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/api/shares/")?;
+    let (share_id, endpoint) = rest.split_once('/')?;
+    if share_id.is_empty() || endpoint.is_empty() || endpoint == "verify" {
+        return None;
+    }
+    urlencoding::decode(share_id).ok().map(|id| id.into_owned())
+}
+
+/// 包装请求体：边读边计算 SHA-256，读完时与签名中的哈希不符则返回错误而不是正常结束
+fn verify_body(body: Body, expected: Vec<u8>) -> Body {
+    use futures_util::StreamExt;
+    use sha2::Digest;
+
+    let stream = futures_util::stream::unfold(
+        (body.into_data_stream(), Some(Sha256::new())),
+        move |(mut inner, hasher)| {
+            let expected = expected.clone();
+            async move {
+                let mut hasher = hasher?;
+                match inner.next().await {
+                    Some(Ok(chunk)) => {
+                        hasher.update(&chunk);
+                        Some((Ok(chunk), (inner, Some(hasher))))
+                    }
+                    Some(Err(e)) => Some((Err(std::io::Error::other(e)), (inner, None))),
+                    None if hasher.finalize().as_slice() == expected.as_slice() => None,
+                    None => Some((
+                        Err(std::io::Error::other("请求体与签名中的 SHA-256 不符")),
+                        (inner, None),
+                    )),
+                }
+            }
+        },
+    );
+    Body::from_stream(stream)
+}
+
+/// 统一鉴权中间件，未通过返回 401
+pub async fn require_auth(
+    State(policy): State<AuthPolicy>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let is_local = request
+        .extensions()
+        .get::<ConnectionOrigin>()
+        .is_some_and(|origin| origin.is_local);
+    let key = active_key();
+    let result = if policy.share_password.is_some() {
+        // 共享密码校验要跑 PBKDF2，放到阻塞线程中执行
+        let (method, uri, headers) = (request.method().clone(), request.uri().clone(), request.headers().clone());
+        let key = key.clone();
+        tokio::task::spawn_blocking(move || {
+            let auth_request = AuthRequest {
+                method: method.as_str(),
+                path: uri.path(),
+                query: uri.query(),
+                headers: &headers,
+                is_local,
+            };
+            policy.authorize(key.as_ref(), &auth_request, unix_now())
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    } else {
+        let auth_request = AuthRequest {
+            method: request.method().as_str(),
+            path: request.uri().path(),
+            query: request.uri().query(),
+            headers: request.headers(),
+            is_local,
+        };
+        policy.authorize(key.as_ref(), &auth_request, unix_now())
+    };
+    if let Err(e) = result {
+        log::warn!("⚠️ [HttpAuth] 拒绝 {} {}: {}", request.method(), request.uri().path(), e);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // 签名已覆盖请求体哈希，这里保证实际收到的请求体与之一致
+    if key.is_none() || is_local {
+        return Ok(next.run(request).await);
+    }
+    let expected = request
+        .headers()
+        .get(CONTENT_SHA256_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| decode_hex(v.trim()))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let request = request.map(|body| verify_body(body, expected));
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_headers(key: &HttpAuthKey, method: &str, path: &str, timestamp: u64) -> HeaderMap {
+        signed_headers_with(key, method, path, None, b"", timestamp)
+    }
+
+    fn signed_headers_with(
+        key: &HttpAuthKey,
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        body: &[u8],
+        timestamp: u64,
+    ) -> HeaderMap {
+        let body_sha256 = content_sha256(body);
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(CONTENT_SHA256_HEADER, body_sha256.parse().unwrap());
+        headers.insert(
+            SIGNATURE_HEADER,
+            key.sign(method, path, query, &body_sha256, timestamp).parse().unwrap(),
+        );
+        headers
+    }
+
+    fn remote<'a>(method: &'a str, path: &'a str, headers: &'a HeaderMap) -> AuthRequest<'a> {
+        AuthRequest { method, path, query: None, headers, is_local: false }
+    }
+
+    #[test]
+    fn test_lobby_signature() {
+        let key = HttpAuthKey::from_network_key("lobby-pass");
+        let policy = AuthPolicy::lobby_only();
+        let now = 1_700_000_000;
+
+        let headers = signed_headers(&key, "POST", "/api/chat/send", now - 30);
+        assert_eq!(policy.authorize(Some(&key), &remote("POST", "/api/chat/send", &headers), now), Ok(()));
+
+        // 缺少签名、其它大厅的密钥、签名挪用到其它路径、过期
+        let empty = HeaderMap::new();
+        assert_eq!(
+            policy.authorize(Some(&key), &remote("GET", "/api/chat/messages", &empty), now),
+            Err(HttpAuthError::MissingSignature)
+        );
+        let other = signed_headers(&HttpAuthKey::from_network_key("other"), "POST", "/api/chat/send", now);
+        assert_eq!(
+            policy.authorize(Some(&key), &remote("POST", "/api/chat/send", &other), now),
+            Err(HttpAuthError::InvalidSignature)
+        );
+        assert_eq!(
+            policy.authorize(Some(&key), &remote("GET", "/api/chat/messages", &headers), now),
+            Err(HttpAuthError::InvalidSignature)
+        );
+        let stale = signed_headers(&key, "GET", "/api/chat/messages", now - 600);
+        assert_eq!(
+            policy.authorize(Some(&key), &remote("GET", "/api/chat/messages", &stale), now),
+            Err(HttpAuthError::Expired(600))
+        );
+
+        // 本机连接与未进入大厅时不要求签名
+        let local = AuthRequest { is_local: true, ..remote("GET", "/api/chat/stream", &empty) };
+        assert_eq!(policy.authorize(Some(&key), &local, now), Ok(()));
+        assert_eq!(policy.authorize(None, &remote("GET", "/api/chat/stream", &empty), now), Ok(()));
+    }
+
+    #[test]
+    fn test_signature_covers_query_and_body() {
+        let key = HttpAuthKey::from_network_key("lobby-pass");
+        let policy = AuthPolicy::lobby_only();
+        let now = 1_700_000_000;
+        let path = "/api/shares/s1/upload";
+        let headers = signed_headers_with(&key, "POST", path, Some("path=a.zip&x=1"), b"level data", now);
+        let request = |query: Option<&'static str>, headers: &HeaderMap| {
+            policy.authorize(Some(&key), &AuthRequest { query, ..remote("POST", path, headers) }, now)
+        };
+
+        assert_eq!(request(Some("path=a.zip&x=1"), &headers), Ok(()));
+        // 参数顺序不同视为同一查询串
+        assert_eq!(request(Some("x=1&path=a.zip"), &headers), Ok(()));
+        // 篡改或去掉查询参数
+        assert_eq!(request(Some("path=..%2Fevil.exe&x=1"), &headers), Err(HttpAuthError::InvalidSignature));
+        assert_eq!(request(None, &headers), Err(HttpAuthError::InvalidSignature));
+
+        // 篡改请求体哈希
+        let mut tampered = headers.clone();
+        tampered.insert(CONTENT_SHA256_HEADER, content_sha256(b"evil data").parse().unwrap());
+        assert_eq!(request(Some("path=a.zip&x=1"), &tampered), Err(HttpAuthError::InvalidSignature));
+        tampered.remove(CONTENT_SHA256_HEADER);
+        assert_eq!(request(Some("path=a.zip&x=1"), &tampered), Err(HttpAuthError::MissingSignature));
+    }
+
+    #[test]
+    fn test_share_password() {
+        let key = HttpAuthKey::from_network_key("lobby-pass");
+        let policy = AuthPolicy::with_share_password(Arc::new(|share_id: &str, provided: &str| {
+            share_id != "locked share" || provided == "secret1"
+        }));
+        let now = 1_700_000_000;
+        let path = "/api/shares/locked%20share/download/a.zip";
+
+        let mut headers = signed_headers(&key, "GET", path, now);
+        assert_eq!(
+            policy.authorize(Some(&key), &remote("GET", path, &headers), now),
+            Err(HttpAuthError::SharePassword)
+        );
+        headers.insert(SHARE_PASSWORD_HEADER, "secret1".parse().unwrap());
+        assert_eq!(policy.authorize(Some(&key), &remote("GET", path, &headers), now), Ok(()));
+
+        // 共享列表与密码验证端点不需要共享密码，但仍需要大厅签名
+        for path in ["/api/shares", "/api/shares/locked%20share/verify"] {
+            let headers = signed_headers(&key, "POST", path, now);
+            assert_eq!(policy.authorize(Some(&key), &remote("POST", path, &headers), now), Ok(()));
+            assert_eq!(
+                policy.authorize(Some(&key), &remote("POST", path, &HeaderMap::new()), now),
+                Err(HttpAuthError::MissingSignature)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_returns_401() {
+        use axum::{routing::{get, post}, Router};
+
+        async fn spawn(app: Router) -> String {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            format!("http://{}/api/chat/messages?since=1", addr)
+        }
+
+        configure_for_lobby("lobby-pass");
+        let app = Router::new()
+            .route("/api/chat/messages", get(|| async { "ok" }))
+            .route("/api/chat/send", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn_with_state(AuthPolicy::lobby_only(), require_auth));
+        let url = spawn(app.clone()).await;
+        let client = reqwest::Client::new();
+
+        // 签名覆盖查询参数
+        let signed = send_signed(client.get(&url)).await.unwrap();
+        assert_eq!(signed.status().as_u16(), 200);
+        let unsigned = client.get(&url).send().await.unwrap();
+        assert_eq!(unsigned.status().as_u16(), 401);
+        let mut request = client.get(&url).build().unwrap();
+        sign_request(&mut request);
+        *request.url_mut() = format!("{}0", url).parse().unwrap();
+        assert_eq!(client.execute(request).await.unwrap().status().as_u16(), 401);
+
+        // 签名覆盖请求体：签名后替换请求体，handler 读取请求体失败
+        let send_url = url.replace("/api/chat/messages?since=1", "/api/chat/send");
+        let signed = send_signed(client.post(&send_url).body("hello")).await.unwrap();
+        assert_eq!(signed.text().await.unwrap(), "hello");
+        let mut request = client.post(&send_url).body("hello").build().unwrap();
+        sign_request(&mut request);
+        *request.body_mut() = Some("evil!".into());
+        assert!(!client.execute(request).await.unwrap().status().is_success());
+
+        // 本机连接不要求签名
+        let local_url = spawn(app.layer(axum::Extension(ConnectionOrigin { is_local: true }))).await;
+        assert_eq!(client.get(&local_url).send().await.unwrap().status().as_u16(), 200);
+
+        deactivate();
+    }
+}
@@ -0,0 +1,444 @@
+// 虚拟网内 HTTP 服务（文件共享 / 聊天）的可选 TLS（默认关闭）
+//
+// 证书在运行时生成：Ed25519 私钥由"大厅名称 + 大厅密码"派生，证书参数固定，
+// 因此同一大厅的成员无需交换任何数据就能在本地算出同一张证书及其 SHA-256 指纹。
+// 客户端只接受指纹匹配的证书（握手签名仍照常校验），不知道大厅密码的同网段节点
+// 既无法伪装成服务端，也无法窥探明文。
+//
+// 开启后服务端仍接受来自本机的明文连接（前端用 EventSource 订阅自己的聊天流），
+// 来自其它地址的明文连接会被直接关闭。
+
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder as ConnectionBuilder;
+use hyper_util::service::TowerToHyperService;
+use parking_lot::RwLock;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+
+/// 派生私钥时使用的上下文前缀
+const KEY_DERIVATION_CONTEXT: &str = "mctier-http-tls-v1";
+/// Ed25519 PKCS#8 v1 私钥 DER 的固定前缀（后接 32 字节种子）
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+/// 证书中的主机名（客户端只校验指纹，不校验主机名）
+const CERT_SERVER_NAME: &str = "mctier.lobby";
+/// TLS 记录层握手消息的首字节
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+/// 接受连接失败后的首次重试间隔
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(5);
+/// 接受连接失败后的最长重试间隔
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+/// TLS 错误类型
+#[derive(Debug, thiserror::Error)]
+pub enum HttpTlsError {
+    #[error("生成证书失败: {0}")]
+    Certificate(String),
+
+    #[error("TLS 配置失败: {0}")]
+    Config(String),
+}
+
+/// 大厅 TLS 身份（证书、私钥与证书指纹）
+#[derive(Clone)]
+pub struct LobbyTlsIdentity {
+    cert_der: Vec<u8>,
+    key_der: Vec<u8>,
+    fingerprint: String,
+}
+
+impl LobbyTlsIdentity {
+    /// 证书的 SHA-256 指纹（小写十六进制）
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// 证书 DER
+    pub fn cert_der(&self) -> &[u8] {
+        &self.cert_der
+    }
+}
+
+/// 计算证书的 SHA-256 指纹（小写十六进制）
+pub fn cert_fingerprint(cert_der: &[u8]) -> String {
+    Sha256::digest(cert_der).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 由大厅名称和密码派生 TLS 身份
+///
+/// 相同的输入总是得到相同的证书，同一大厅的成员据此得到一致的期望指纹。
+///
+/// # 参数
+/// * `lobby_name` - 大厅名称
+/// * `password` - 大厅密码
+pub fn derive_lobby_identity(lobby_name: &str, password: &str) -> Result<LobbyTlsIdentity, HttpTlsError> {
+    let mut hasher = Sha256::new();
+    hasher.update(KEY_DERIVATION_CONTEXT.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(lobby_name.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(password.as_bytes());
+    let seed = hasher.finalize();
+
+    let mut pkcs8 = ED25519_PKCS8_PREFIX.to_vec();
+    pkcs8.extend_from_slice(&seed);
+    let key_pair = rcgen::KeyPair::from_der_and_sign_algo(&pkcs8, &rcgen::PKCS_ED25519)
+        .map_err(|e| HttpTlsError::Certificate(e.to_string()))?;
+
+    // 有效期沿用 rcgen 的固定默认值，序列号取自种子，保证证书逐字节可复现
+    let mut params = rcgen::CertificateParams::new(vec![CERT_SERVER_NAME.to_string()]);
+    params.alg = &rcgen::PKCS_ED25519;
+    params.key_pair = Some(key_pair);
+    params.serial_number = Some(rcgen::SerialNumber::from_slice(&seed[..8]));
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params.distinguished_name.push(rcgen::DnType::CommonName, "MCTier Lobby");
+
+    let cert = rcgen::Certificate::from_params(params).map_err(|e| HttpTlsError::Certificate(e.to_string()))?;
+    let cert_der = cert.serialize_der().map_err(|e| HttpTlsError::Certificate(e.to_string()))?;
+    let fingerprint = cert_fingerprint(&cert_der);
+
+    Ok(LobbyTlsIdentity {
+        key_der: cert.serialize_private_key_der(),
+        cert_der,
+        fingerprint,
+    })
+}
+
+/// 构建服务端 TLS 配置
+pub fn server_config(identity: &LobbyTlsIdentity) -> Result<Arc<rustls::ServerConfig>, HttpTlsError> {
+    let mut config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(identity.cert_der.clone())],
+            rustls::PrivateKey(identity.key_der.clone()),
+        )
+        .map_err(|e| HttpTlsError::Config(e.to_string()))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// 只信任指定指纹证书的校验器
+struct FingerprintVerifier {
+    expected: String,
+}
+
+impl rustls::client::ServerCertVerifier for FingerprintVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        let actual = cert_fingerprint(&end_entity.0);
+        if actual == self.expected {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        } else {
+            log::warn!("⚠️ [HttpTls] 证书指纹不匹配: 期望 {}，实际 {}", self.expected, actual);
+            Err(rustls::Error::InvalidCertificate(rustls::CertificateError::ApplicationVerificationFailure))
+        }
+    }
+}
+
+/// 构建客户端 TLS 配置：只接受指纹为 `expected_fingerprint` 的证书
+pub fn client_config(expected_fingerprint: &str) -> rustls::ClientConfig {
+    let mut config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(FingerprintVerifier {
+            expected: expected_fingerprint.to_lowercase(),
+        }))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    config
+}
+
+static ACTIVE_IDENTITY: OnceLock<RwLock<Option<LobbyTlsIdentity>>> = OnceLock::new();
+
+fn active_slot() -> &'static RwLock<Option<LobbyTlsIdentity>> {
+    ACTIVE_IDENTITY.get_or_init(|| RwLock::new(None))
+}
+
+/// 按配置为当前大厅启用或关闭 TLS
+///
+/// 需要在启动文件/聊天服务器之前调用；所有大厅成员的开关需保持一致。
+///
+/// # 参数
+/// * `enabled` - 是否启用 TLS
+/// * `lobby_name` - 大厅名称
+/// * `password` - 大厅密码
+pub fn configure_for_lobby(enabled: bool, lobby_name: &str, password: &str) -> Result<(), HttpTlsError> {
+    if !enabled {
+        deactivate();
+        return Ok(());
+    }
+    let identity = derive_lobby_identity(lobby_name, password)?;
+    log::info!("🔒 [HttpTls] 已启用 HTTPS，证书指纹: {}", identity.fingerprint);
+    *active_slot().write() = Some(identity);
+    Ok(())
+}
+
+/// 关闭 TLS（离开大厅时调用）
+pub fn deactivate() {
+    if active_slot().write().take().is_some() {
+        log::info!("🔓 [HttpTls] 已关闭 HTTPS");
+    }
+}
+
+/// 当前是否启用了 TLS
+pub fn is_enabled() -> bool {
+    active_slot().read().is_some()
+}
+
+/// 当前大厅证书指纹（未启用时为 None）
+pub fn active_fingerprint() -> Option<String> {
+    active_slot().read().as_ref().map(|i| i.fingerprint.clone())
+}
+
+/// 拼接访问其他玩家 HTTP 服务的 URL（启用 TLS 时使用 https）
+///
+/// # 参数
+/// * `peer_ip` - 对方虚拟 IP
+/// * `port` - 服务端口
+/// * `path` - 以 `/` 开头的路径（可带查询参数）
+pub fn peer_url(peer_ip: &str, port: u16, path: &str) -> String {
+    let scheme = if is_enabled() { "https" } else { "http" };
+    format!("{}://{}:{}{}", scheme, peer_ip, port, path)
+}
+
+/// 创建访问其他玩家 HTTP 服务的客户端构建器（启用 TLS 时固定信任大厅证书指纹）
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    match active_fingerprint() {
+        Some(fingerprint) => builder.use_preconfigured_tls(client_config(&fingerprint)),
+        None => builder,
+    }
+}
+
+/// 在监听器上运行 axum 应用；启用 TLS 时对远程连接强制 TLS
+///
+/// 两种模式下都只接受本机与当前大厅成员的连接（见 `peer_access`）。
+pub async fn serve(listener: TcpListener, app: Router) -> std::io::Result<()> {
+    let identity = active_slot().read().clone();
+    let Some(identity) = identity else {
+        return serve_plain(listener, app).await;
+    };
+    let config = server_config(&identity).map_err(|e| std::io::Error::other(e.to_string()))?;
+    serve_tls(listener, app, config).await
+}
+
+/// 接收下一个连接，同时回收已结束的连接任务
+///
+/// 连接任务放在 `JoinSet` 里：服务停止时接收循环被中止，`JoinSet` 随之销毁并中止所有连接
+/// （包括长时间保持的 SSE 聊天流），端口不会在退出大厅后仍被旧连接占用。
+/// 接受失败（如文件描述符耗尽）时按指数退避等待后重试，避免空转占满 CPU；等待期间继续回收结束的连接。
+async fn accept_next(listener: &TcpListener, connections: &mut JoinSet<()>) -> (TcpStream, SocketAddr) {
+    let mut backoff = ACCEPT_BACKOFF_MIN;
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(conn) => return conn,
+                Err(e) => {
+                    log::warn!("⚠️ [HttpTls] 接受连接失败，{:?} 后重试: {}", backoff, e);
+                    let retry = tokio::time::sleep(backoff);
+                    tokio::pin!(retry);
+                    loop {
+                        tokio::select! {
+                            _ = &mut retry => break,
+                            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                        }
+                    }
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                }
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+/// 明文接收循环
+async fn serve_plain(listener: TcpListener, app: Router) -> std::io::Result<()> {
+    let local_addr = listener.local_addr()?;
+    let mut connections = JoinSet::new();
+
+    loop {
+        let (stream, peer_addr) = accept_next(&listener, &mut connections).await;
+        let app = app.clone();
+
+        connections.spawn(async move {
+            if crate::modules::peer_access::is_allowed(peer_addr.ip()).await {
+                serve_connection(stream, app, is_local_peer(&local_addr, &peer_addr)).await;
+            }
+        });
+    }
+}
+
+/// TLS 接收循环
+///
+/// 根据首字节区分 TLS 与明文：TLS 连接完成握手后交给 axum；明文连接仅允许来自本机。
+pub async fn serve_tls(listener: TcpListener, app: Router, config: Arc<rustls::ServerConfig>) -> std::io::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+    let local_addr = listener.local_addr()?;
+    let mut connections = JoinSet::new();
+
+    loop {
+        let (stream, peer_addr) = accept_next(&listener, &mut connections).await;
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+
+        connections.spawn(async move {
+            if !crate::modules::peer_access::is_allowed(peer_addr.ip()).await {
+                return;
+            }
+
+            let mut first = [0u8; 1];
+            match stream.peek(&mut first).await {
+                Ok(1) => {}
+                _ => return,
+            }
+
+            if first[0] == TLS_HANDSHAKE_RECORD {
+                match acceptor.accept(stream).await {
+                    Ok(tls_stream) => serve_connection(tls_stream, app, is_local_peer(&local_addr, &peer_addr)).await,
+                    Err(e) => log::debug!("[HttpTls] TLS 握手失败 ({}): {}", peer_addr, e),
+                }
+            } else if is_local_peer(&local_addr, &peer_addr) {
+                serve_connection(stream, app, true).await;
+            } else {
+                log::warn!("⚠️ [HttpTls] 拒绝来自 {} 的明文连接", peer_addr);
+            }
+        });
+    }
+}
+
+/// 明文连接是否来自本机（回环地址或与监听地址相同）
+fn is_local_peer(local_addr: &SocketAddr, peer_addr: &SocketAddr) -> bool {
+    peer_addr.ip().is_loopback() || peer_addr.ip() == local_addr.ip()
+}
+
+/// 处理单个连接，并把连接来源写入请求扩展供鉴权中间件使用（见 `http_auth`）
+async fn serve_connection<S>(io: S, app: Router, is_local: bool)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let app = app.layer(axum::Extension(crate::modules::http_auth::ConnectionOrigin { is_local }));
+    let service = TowerToHyperService::new(app);
+    if let Err(e) = ConnectionBuilder::new(TokioExecutor::new())
+        .serve_connection_with_upgrades(TokioIo::new(io), service)
+        .await
+    {
+        log::debug!("[HttpTls] 连接结束: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+
+    async fn spawn_tls_server(identity: &LobbyTlsIdentity) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/ping", get(|| async { "pong" }));
+        let config = server_config(identity).unwrap();
+        tokio::spawn(serve_tls(listener, app, config));
+        addr
+    }
+
+    async fn tls_get(addr: SocketAddr, fingerprint: &str) -> std::io::Result<String> {
+        let connector = TlsConnector::from(Arc::new(client_config(fingerprint)));
+        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let server_name = rustls::ServerName::try_from(CERT_SERVER_NAME).unwrap();
+        let mut tls = connector.connect(server_name, stream).await?;
+        tls.write_all(b"GET /ping HTTP/1.1\r\nHost: mctier\r\nConnection: close\r\n\r\n").await?;
+        let mut response = String::new();
+        tls.read_to_string(&mut response).await?;
+        Ok(response)
+    }
+
+    #[test]
+    fn test_identity_is_deterministic_per_lobby() {
+        let a = derive_lobby_identity("大厅", "secret").unwrap();
+        let b = derive_lobby_identity("大厅", "secret").unwrap();
+        assert_eq!(a.cert_der(), b.cert_der());
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_eq!(a.fingerprint().len(), 64);
+
+        assert_ne!(a.fingerprint(), derive_lobby_identity("大厅", "other").unwrap().fingerprint());
+        assert_ne!(a.fingerprint(), derive_lobby_identity("大厅2", "secret").unwrap().fingerprint());
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake_with_matching_fingerprint() {
+        let identity = derive_lobby_identity("lobby", "pwd").unwrap();
+        let addr = spawn_tls_server(&identity).await;
+
+        // 另一位成员用同样的大厅信息算出期望指纹
+        let expected = derive_lobby_identity("lobby", "pwd").unwrap();
+        let response = tls_get(addr, expected.fingerprint()).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("pong"));
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake_rejects_wrong_fingerprint() {
+        let identity = derive_lobby_identity("lobby", "pwd").unwrap();
+        let addr = spawn_tls_server(&identity).await;
+
+        let wrong = derive_lobby_identity("lobby", "guess").unwrap();
+        assert!(tls_get(addr, wrong.fingerprint()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_client_pins_fingerprint() {
+        let identity = derive_lobby_identity("lobby", "pwd").unwrap();
+        let addr = spawn_tls_server(&identity).await;
+        let url = format!("https://{}/ping", addr);
+
+        let client = reqwest::Client::builder()
+            .use_preconfigured_tls(client_config(identity.fingerprint()))
+            .build()
+            .unwrap();
+        assert_eq!(client.get(&url).send().await.unwrap().text().await.unwrap(), "pong");
+
+        let wrong = derive_lobby_identity("other", "pwd").unwrap();
+        let client = reqwest::Client::builder()
+            .use_preconfigured_tls(client_config(wrong.fingerprint()))
+            .build()
+            .unwrap();
+        assert!(client.get(&url).send().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_allowed_only_from_local() {
+        let identity = derive_lobby_identity("lobby", "pwd").unwrap();
+        let addr = spawn_tls_server(&identity).await;
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /ping HTTP/1.1\r\nHost: mctier\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with("pong"));
+
+        let local: SocketAddr = "10.126.126.2:14540".parse().unwrap();
+        assert!(is_local_peer(&local, &"10.126.126.2:50000".parse().unwrap()));
+        assert!(!is_local_peer(&local, &"10.126.126.3:50000".parse().unwrap()));
+    }
+}
@@ -0,0 +1,140 @@
+// 输入长度上限
+//
+// 玩家名、大厅名、聊天消息、共享名、文件名等字符串会原样显示在前端，超长内容可能撑爆布局，
+// 也可能被恶意 peer 用来占用内存。这里统一定义各字段的长度上限：
+// 本机输入超长直接拒绝并提示；来自其他 peer 的内容无法拒绝其本身，按字符边界安全截断并记录日志。
+
+use std::borrow::Cow;
+
+/// 玩家名称最大字符数
+pub const MAX_PLAYER_NAME_CHARS: usize = 32;
+/// 大厅名称最大字符数
+pub const MAX_LOBBY_NAME_CHARS: usize = 32;
+/// 文本聊天消息最大字符数
+pub const MAX_CHAT_MESSAGE_CHARS: usize = 2000;
+/// 控制类聊天消息（待办、白板等 JSON）最大字节数，超长无法截断，只能拒绝
+pub const MAX_CONTROL_MESSAGE_BYTES: usize = 64 * 1024;
+/// 共享名称最大字符数
+pub const MAX_SHARE_NAME_CHARS: usize = 64;
+/// 文件名最大字符数（与常见文件系统的单个文件名上限一致）
+pub const MAX_FILE_NAME_CHARS: usize = 255;
+/// Minecraft 局域网世界描述（MOTD）最大字符数
+pub const MAX_LAN_MOTD_CHARS: usize = 128;
+
+/// 截断时追加的省略号
+const ELLIPSIS: char = '…';
+
+/// 输入长度错误
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum InputLimitError {
+    #[error("{field}不能超过 {max} 个字符")]
+    TooLong { field: &'static str, max: usize },
+}
+
+/// 校验本机输入的长度（按字符计数，忽略首尾空白）
+pub fn check_len(field: &'static str, value: &str, max: usize) -> Result<(), InputLimitError> {
+    if value.trim().chars().count() > max {
+        return Err(InputLimitError::TooLong { field, max });
+    }
+    Ok(())
+}
+
+/// 将字符串截断到最多 `max` 个字符（含末尾省略号），未超长时不分配
+pub fn truncate_chars(value: &str, max: usize) -> Cow<'_, str> {
+    match value.char_indices().nth(max) {
+        None => Cow::Borrowed(value),
+        Some(_) if max == 0 => Cow::Borrowed(""),
+        Some(_) => {
+            let end = value.char_indices().nth(max - 1).map(|(i, _)| i).unwrap_or(value.len());
+            let mut truncated = String::with_capacity(end + ELLIPSIS.len_utf8());
+            truncated.push_str(&value[..end]);
+            truncated.push(ELLIPSIS);
+            Cow::Owned(truncated)
+        }
+    }
+}
+
+/// 截断来自其他 peer 的字段并记录日志
+pub fn clamp_remote(field: &str, value: String, max: usize) -> String {
+    let Cow::Owned(truncated) = truncate_chars(&value, max) else {
+        return value;
+    };
+    log::warn!("⚠️ 收到超长{}（{} 个字符），已截断到 {} 个字符", field, value.chars().count(), max);
+    truncated
+}
+
+/// 将文件名截断到 `MAX_FILE_NAME_CHARS` 个字符，尽量保留扩展名
+pub fn clamp_file_name(name: &str) -> Cow<'_, str> {
+    if name.chars().count() <= MAX_FILE_NAME_CHARS {
+        return Cow::Borrowed(name);
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        // 扩展名本身过长时视为没有扩展名
+        Some((stem, ext)) if !stem.is_empty() && ext.chars().count() < 16 => (stem, Some(ext)),
+        _ => (name, None),
+    };
+    let ext_chars = ext.map(|e| e.chars().count() + 1).unwrap_or(0);
+    let mut clamped: String = stem.chars().take(MAX_FILE_NAME_CHARS - ext_chars).collect();
+    if let Some(ext) = ext {
+        clamped.push('.');
+        clamped.push_str(ext);
+    }
+    log::warn!("⚠️ 文件名过长（{} 个字符），已截断为: {}", name.chars().count(), clamped);
+    Cow::Owned(clamped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_len_per_field() {
+        let cases = [
+            ("玩家名称", MAX_PLAYER_NAME_CHARS),
+            ("大厅名称", MAX_LOBBY_NAME_CHARS),
+            ("消息", MAX_CHAT_MESSAGE_CHARS),
+            ("共享名称", MAX_SHARE_NAME_CHARS),
+            ("文件名", MAX_FILE_NAME_CHARS),
+        ];
+        for (field, max) in cases {
+            // 按字符而不是字节计数，中文恰好到上限也通过
+            assert!(check_len(field, &"中".repeat(max), max).is_ok(), "{}", field);
+            // 首尾空白不计入
+            assert!(check_len(field, &format!("  {}  ", "a".repeat(max)), max).is_ok(), "{}", field);
+            assert_eq!(
+                check_len(field, &"中".repeat(max + 1), max),
+                Err(InputLimitError::TooLong { field, max })
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_char_boundaries() {
+        assert!(matches!(truncate_chars("Steve", 5), Cow::Borrowed("Steve")));
+        assert_eq!(truncate_chars("Alexander", 5), "Alex…");
+        // 多字节字符不会被截成半个
+        assert_eq!(truncate_chars("玩家名字很长很长", 4), "玩家名…");
+        assert_eq!(truncate_chars("😀😀😀", 2), "😀…");
+        assert_eq!(truncate_chars("abc", 0), "");
+
+        let name = clamp_remote("玩家名称", "x".repeat(1000), MAX_PLAYER_NAME_CHARS);
+        assert_eq!(name.chars().count(), MAX_PLAYER_NAME_CHARS);
+        assert_eq!(clamp_remote("玩家名称", "Steve".to_string(), MAX_PLAYER_NAME_CHARS), "Steve");
+    }
+
+    #[test]
+    fn test_clamp_file_name_keeps_extension() {
+        assert!(matches!(clamp_file_name("world.zip"), Cow::Borrowed("world.zip")));
+
+        let long = format!("{}.zip", "存档".repeat(200));
+        let clamped = clamp_file_name(&long);
+        assert_eq!(clamped.chars().count(), MAX_FILE_NAME_CHARS);
+        assert!(clamped.ends_with(".zip"));
+
+        // 没有扩展名或扩展名异常长时直接截断
+        let no_ext = "a".repeat(300);
+        assert_eq!(clamp_file_name(&no_ext).chars().count(), MAX_FILE_NAME_CHARS);
+        let weird = format!("a.{}", "b".repeat(300));
+        assert_eq!(clamp_file_name(&weird).chars().count(), MAX_FILE_NAME_CHARS);
+    }
+}
@@ -0,0 +1,141 @@
+// 大厅邀请链接
+//
+// 把大厅名、服务器节点、是否使用虚拟域名打包成 `mctier://join?...` 链接（也用作二维码内容），
+// 好友点击链接由 deep link 拉起应用并预填加入表单。密码默认不放进链接，需要时由调用方显式要求；
+// 链接会经过聊天软件、截图等渠道传播，带密码的链接等同于把密码交给所有看到它的人。
+// 参数名与旧版前端生成的 `mctier://join?name=&pwd=` 保持一致，新旧版本的链接可以互相解析。
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// 邀请链接前缀
+pub const INVITE_LINK_PREFIX: &str = "mctier://join";
+
+/// 邀请链接解析错误
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InviteLinkError {
+    #[error("不是 MCTier 邀请链接")]
+    NotInviteLink,
+    #[error("邀请链接缺少大厅名称")]
+    MissingName,
+    #[error("邀请链接参数 {0} 无效")]
+    InvalidParam(String),
+}
+
+/// 通过邀请链接加入大厅所需的参数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LobbyJoinParams {
+    /// 大厅名称
+    pub name: String,
+    /// 服务器节点地址（旧版链接没有该参数）
+    pub server_node: Option<String>,
+    /// 是否使用虚拟域名
+    pub use_domain: bool,
+    /// 大厅密码（仅在生成时显式要求才包含）
+    pub password: Option<String>,
+}
+
+/// 生成邀请链接
+pub fn build_invite_link(params: &LobbyJoinParams) -> String {
+    let mut link = format!("{}?name={}", INVITE_LINK_PREFIX, urlencoding::encode(&params.name));
+    if let Some(node) = params.server_node.as_deref().filter(|n| !n.is_empty()) {
+        link.push_str(&format!("&server={}", urlencoding::encode(node)));
+    }
+    if params.use_domain {
+        link.push_str("&domain=1");
+    }
+    if let Some(password) = params.password.as_deref().filter(|p| !p.is_empty()) {
+        link.push_str(&format!("&pwd={}", urlencoding::encode(password)));
+    }
+    link
+}
+
+/// 解析邀请链接
+///
+/// 接受 `mctier://join?...` 与 `mctier://join/?...` 两种形式，前后空白与未知参数忽略。
+pub fn parse_invite_link(link: &str) -> Result<LobbyJoinParams, InviteLinkError> {
+    let link = link.trim();
+    let rest = link
+        .get(..INVITE_LINK_PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(INVITE_LINK_PREFIX))
+        .map(|_| &link[INVITE_LINK_PREFIX.len()..])
+        .ok_or(InviteLinkError::NotInviteLink)?;
+    let query = rest
+        .strip_prefix('/')
+        .unwrap_or(rest)
+        .strip_prefix('?')
+        .ok_or(InviteLinkError::MissingName)?;
+
+    let mut params = LobbyJoinParams {
+        name: String::new(),
+        server_node: None,
+        use_domain: false,
+        password: None,
+    };
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = urlencoding::decode(&value.replace('+', " "))
+            .map_err(|_| InviteLinkError::InvalidParam(key.to_string()))?
+            .trim()
+            .to_string();
+        match key {
+            "name" => params.name = value,
+            "server" => params.server_node = Some(value).filter(|v| !v.is_empty()),
+            "domain" => {
+                params.use_domain = match value.as_str() {
+                    "1" | "true" => true,
+                    "0" | "false" | "" => false,
+                    _ => return Err(InviteLinkError::InvalidParam(key.to_string())),
+                }
+            }
+            "pwd" => params.password = Some(value).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+    }
+
+    if params.name.is_empty() {
+        return Err(InviteLinkError::MissingName);
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invite_link_round_trip() {
+        let params = LobbyJoinParams {
+            name: "周末 生存服&1".to_string(),
+            server_node: Some("tcp://node.example.com:11010".to_string()),
+            use_domain: true,
+            password: None,
+        };
+        let link = build_invite_link(&params);
+        assert!(link.starts_with("mctier://join?name="));
+        assert!(!link.contains("pwd="));
+        assert_eq!(parse_invite_link(&link).unwrap(), params);
+
+        let with_password = LobbyJoinParams { password: Some("abc 123".to_string()), ..params };
+        assert_eq!(parse_invite_link(&build_invite_link(&with_password)).unwrap(), with_password);
+    }
+
+    #[test]
+    fn test_parse_legacy_and_invalid_links() {
+        // 旧版前端生成的链接
+        let legacy = parse_invite_link(" mctier://join/?name=%E5%A4%A7%E5%8E%85&pwd=secret1 ").unwrap();
+        assert_eq!(legacy.name, "大厅");
+        assert_eq!(legacy.password.as_deref(), Some("secret1"));
+        assert_eq!(legacy.server_node, None);
+        assert!(!legacy.use_domain);
+
+        assert_eq!(parse_invite_link("https://example.com/join?name=a"), Err(InviteLinkError::NotInviteLink));
+        assert_eq!(parse_invite_link("mctier://join"), Err(InviteLinkError::MissingName));
+        assert_eq!(parse_invite_link("mctier://join?pwd=x"), Err(InviteLinkError::MissingName));
+        assert_eq!(
+            parse_invite_link("mctier://join?name=a&domain=maybe"),
+            Err(InviteLinkError::InvalidParam("domain".to_string()))
+        );
+    }
+}
@@ -0,0 +1,67 @@
+// 虚拟 IP 冲突检测
+//
+// EasyTier DHCP 模式下，网络分区合并后多人可能拿到相同的虚拟 IP，导致信令和文件传输错乱。
+// 拿到虚拟 IP 后通过 P2P 信令定期广播声明，收到其他玩家声明相同 IP 时判定冲突：
+// player_id 较大者让出，重启 EasyTier 重新分配；双方按同一规则仲裁，只有一方会重新分配。
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// 检测到虚拟 IP 冲突时推送的事件
+pub const IP_CONFLICT_EVENT: &str = "ip-conflict";
+/// 声明检查间隔
+pub const IP_CLAIM_INTERVAL: Duration = Duration::from_secs(3);
+/// 拿到虚拟 IP 后每次检查都广播声明的时长
+pub const IP_CLAIM_FAST_WINDOW: Duration = Duration::from_secs(30);
+/// 之后每隔多少次检查广播一次声明（兼顾网络分区合并的情况）
+pub const IP_CLAIM_EVERY: u32 = 10;
+
+/// `ip-conflict` 事件内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpConflict {
+    /// 冲突的虚拟 IP
+    pub virtual_ip: String,
+    /// 声明相同 IP 的玩家 ID
+    pub other_player_id: String,
+    /// 本机是否让出该 IP 并重新分配
+    pub yielding: bool,
+}
+
+/// 冲突仲裁：player_id 较大者让出
+pub fn should_yield(local_player_id: &str, other_player_id: &str) -> bool {
+    local_player_id > other_player_id
+}
+
+/// 检查其他玩家的 IP 声明是否与本机冲突
+///
+/// # 参数
+/// * `local_player_id` / `local_ip` - 本机玩家 ID 与虚拟 IP
+/// * `other_player_id` / `claimed_ip` - 声明方玩家 ID 与其声明的虚拟 IP
+pub fn detect(local_player_id: &str, local_ip: &str, other_player_id: &str, claimed_ip: &str) -> Option<IpConflict> {
+    (other_player_id != local_player_id && claimed_ip == local_ip).then(|| IpConflict {
+        virtual_ip: claimed_ip.to_string(),
+        other_player_id: other_player_id.to_string(),
+        yielding: should_yield(local_player_id, other_player_id),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_conflict_and_arbitrate() {
+        // 自己的声明与不同 IP 不算冲突
+        assert!(detect("player-b", "10.126.126.2", "player-b", "10.126.126.2").is_none());
+        assert!(detect("player-b", "10.126.126.2", "player-a", "10.126.126.3").is_none());
+
+        // 双方各自检测到冲突，只有 player_id 较大者让出
+        let b_view = detect("player-b", "10.126.126.2", "player-a", "10.126.126.2").unwrap();
+        let a_view = detect("player-a", "10.126.126.2", "player-b", "10.126.126.2").unwrap();
+        assert!(b_view.yielding);
+        assert!(!a_view.yielding);
+        assert_eq!(a_view.other_player_id, "player-b");
+    }
+}
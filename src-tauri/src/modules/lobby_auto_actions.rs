@@ -0,0 +1,263 @@
+// 进入大厅后的自动动作序列
+//
+// 玩家在配置中声明一组动作（开麦、共享文件夹、发送消息），进入大厅成功后按顺序执行。
+// 单个动作失败只记录日志与结果，不会中断后续动作。
+
+use std::future::Future;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{Emitter, Manager, State};
+
+use crate::modules::config_manager::LobbyAutoAction;
+use crate::modules::error::CommandError;
+use crate::modules::file_transfer::SharedFolder;
+use crate::modules::tauri_commands::{add_shared_folder, send_p2p_chat_message, AppState};
+
+/// 单个动作的执行结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoActionOutcome {
+    /// 动作在序列中的位置（从 0 开始）
+    pub index: usize,
+    /// 执行的动作
+    pub action: LobbyAutoAction,
+    /// 失败原因，成功时为 None
+    pub error: Option<String>,
+}
+
+/// 动作执行器
+pub trait AutoActionExecutor {
+    /// 执行单个动作
+    fn execute(&self, action: &LobbyAutoAction) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+/// 按顺序执行动作序列，某个动作失败不影响后续动作
+///
+/// # 参数
+/// * `actions` - 动作序列
+/// * `executor` - 动作执行器
+///
+/// # 返回
+/// 每个动作的执行结果（与输入顺序一致）
+pub async fn run_auto_actions<E: AutoActionExecutor>(
+    actions: &[LobbyAutoAction],
+    executor: &E,
+) -> Vec<AutoActionOutcome> {
+    let mut outcomes = Vec::with_capacity(actions.len());
+    for (index, action) in actions.iter().enumerate() {
+        let error = match executor.execute(action).await {
+            Ok(()) => {
+                log::info!("✅ [AutoActions] 第 {} 个动作执行成功: {:?}", index + 1, action);
+                None
+            }
+            Err(e) => {
+                log::warn!("⚠️ [AutoActions] 第 {} 个动作执行失败（继续执行后续动作）: {:?} - {}", index + 1, action, e);
+                Some(e)
+            }
+        };
+        outcomes.push(AutoActionOutcome {
+            index,
+            action: action.clone(),
+            error,
+        });
+    }
+    outcomes
+}
+
+/// 通过应用内各服务执行动作
+struct AppActionExecutor {
+    app: tauri::AppHandle,
+    player_id: String,
+    player_name: String,
+}
+
+impl AutoActionExecutor for AppActionExecutor {
+    async fn execute(&self, action: &LobbyAutoAction) -> Result<(), String> {
+        let state = self.app.state::<AppState>();
+        match action {
+            LobbyAutoAction::EnableMic => {
+                let enabled = state
+                    .core
+                    .lock()
+                    .await
+                    .set_mic_enabled(true)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _ = self.app.emit("mic-toggled", enabled);
+                Ok(())
+            }
+            LobbyAutoAction::ShareFolder { path, name, password } => {
+                let name = name.clone().filter(|n| !n.trim().is_empty()).unwrap_or_else(|| {
+                    std::path::Path::new(path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| path.clone())
+                });
+                let share = SharedFolder {
+                    id: format!("auto-{}", uuid::Uuid::new_v4()),
+                    name,
+                    path: path.clone(),
+                    password: password.clone(),
+                    has_password: false,
+                    expires_at: None,
+                    compress_before_send: None,
+                    writable: false,
+                    owner_id: self.player_id.clone(),
+                    created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+                    note: None,
+                };
+                add_shared_folder(share, state).await.map_err(|e| e.to_string())
+            }
+            LobbyAutoAction::SendMessage { content } => {
+                if content.trim().is_empty() {
+                    return Err("消息内容为空".to_string());
+                }
+                let p2p_signaling = state.get_p2p_signaling();
+                let peer_ips = p2p_signaling
+                    .lock()
+                    .await
+                    .get_peers()
+                    .await
+                    .into_iter()
+                    .map(|peer| peer.addr.ip().to_string())
+                    .collect();
+                send_p2p_chat_message(
+                    self.player_id.clone(),
+                    self.player_name.clone(),
+                    content.clone(),
+                    "text".to_string(),
+                    None,
+                    peer_ips,
+                    None,
+                    None,
+                    state,
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// 进入大厅成功后在后台执行配置的动作序列，完成后推送 `lobby-auto-actions-finished` 事件
+///
+/// # 参数
+/// * `app` - Tauri 应用句柄
+/// * `player_id` - 本机玩家 ID
+/// * `player_name` - 本机玩家名称
+pub fn spawn_lobby_auto_actions(app: tauri::AppHandle, player_id: String, player_name: String) {
+    tauri::async_runtime::spawn(async move {
+        let actions = {
+            let state = app.state::<AppState>();
+            let config_manager = state.get_config_manager();
+            let actions = config_manager.lock().await.get_config().lobby_auto_actions.clone().unwrap_or_default();
+            actions
+        };
+        if actions.is_empty() {
+            return;
+        }
+
+        log::info!("🤖 [AutoActions] 开始执行进入大厅自动动作，共 {} 个", actions.len());
+        let executor = AppActionExecutor {
+            app: app.clone(),
+            player_id,
+            player_name,
+        };
+        let outcomes = run_auto_actions(&actions, &executor).await;
+        let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+        log::info!("🤖 [AutoActions] 自动动作执行完成：成功 {}，失败 {}", outcomes.len() - failed, failed);
+        let _ = app.emit("lobby-auto-actions-finished", serde_json::json!({ "outcomes": outcomes }));
+    });
+}
+
+/// 设置进入大厅后自动执行的动作序列
+///
+/// # 参数
+/// * `actions` - 动作序列（按顺序执行）
+#[tauri::command]
+pub async fn set_lobby_auto_actions(
+    actions: Vec<LobbyAutoAction>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置进入大厅自动动作命令: {} 个", actions.len());
+    let config_manager = state.get_config_manager();
+    let result = config_manager.lock().await.set_lobby_auto_actions(actions).await;
+    result.map_err(CommandError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// 记录执行顺序，并让指定位置的动作失败
+    struct RecordingExecutor {
+        executed: Mutex<Vec<LobbyAutoAction>>,
+        fail_on_mic: bool,
+    }
+
+    impl AutoActionExecutor for RecordingExecutor {
+        async fn execute(&self, action: &LobbyAutoAction) -> Result<(), String> {
+            self.executed.lock().unwrap().push(action.clone());
+            match action {
+                LobbyAutoAction::EnableMic if self.fail_on_mic => Err("没有可用的麦克风".to_string()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn sample_actions() -> Vec<LobbyAutoAction> {
+        vec![
+            LobbyAutoAction::EnableMic,
+            LobbyAutoAction::ShareFolder {
+                path: "C:/saves".to_string(),
+                name: None,
+                password: None,
+            },
+            LobbyAutoAction::SendMessage {
+                content: "大家好".to_string(),
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_actions_run_in_order() {
+        let executor = RecordingExecutor {
+            executed: Mutex::new(Vec::new()),
+            fail_on_mic: false,
+        };
+        let outcomes = run_auto_actions(&sample_actions(), &executor).await;
+
+        assert_eq!(*executor.executed.lock().unwrap(), sample_actions());
+        assert_eq!(outcomes.iter().map(|o| o.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(outcomes.iter().all(|o| o.error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_failed_action_does_not_stop_sequence() {
+        let executor = RecordingExecutor {
+            executed: Mutex::new(Vec::new()),
+            fail_on_mic: true,
+        };
+        let outcomes = run_auto_actions(&sample_actions(), &executor).await;
+
+        // 第一个动作失败后，后续动作仍全部执行
+        assert_eq!(executor.executed.lock().unwrap().len(), 3);
+        assert_eq!(outcomes[0].error.as_deref(), Some("没有可用的麦克风"));
+        assert!(outcomes[1].error.is_none());
+        assert!(outcomes[2].error.is_none());
+    }
+
+    #[test]
+    fn test_action_json_format() {
+        let json = r#"[
+            {"type": "enable_mic"},
+            {"type": "share_folder", "path": "C:/saves"},
+            {"type": "send_message", "content": "大家好"}
+        ]"#;
+        let actions: Vec<LobbyAutoAction> = serde_json::from_str(json).unwrap();
+        assert_eq!(actions, sample_actions());
+    }
+}
@@ -1,8 +1,44 @@
+use crate::modules::config_manager::PlayerSortOrder;
 use crate::modules::error::AppError;
 use crate::modules::hosts_manager::HostsManager;
+use crate::modules::input_limits::{check_len, MAX_LOBBY_NAME_CHARS, MAX_PLAYER_NAME_CHARS};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 大厅人数上限的最大可设置值
+pub const MAX_LOBBY_PLAYERS: u32 = 64;
+
+/// 大厅密码最大字符数
+pub const MAX_LOBBY_PASSWORD_CHARS: usize = 32;
+
+/// 约定的大厅创建者虚拟 IP（EasyTier DHCP 模式下第一个加入网络的节点通常获得该地址）
+pub const CREATOR_VIRTUAL_IP: &str = "10.126.126.1";
+
+/// 大厅密码强度策略（创建大厅时生效）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordPolicy {
+    /// 8-32 个字符，必须同时包含字母和数字
+    #[default]
+    Strict,
+    /// 4-32 个字符，不限字符种类（可用纯中文密码）
+    Relaxed,
+    /// 不校验强度，只要求非空且不超过 32 个字符
+    None,
+}
+
+impl PasswordPolicy {
+    /// 最少字符数
+    fn min_chars(self) -> usize {
+        match self {
+            PasswordPolicy::Strict => 8,
+            PasswordPolicy::Relaxed => 4,
+            PasswordPolicy::None => 1,
+        }
+    }
+}
 
 /// 大厅信息
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -30,6 +66,12 @@ pub struct Lobby {
     /// 信令服务器地址
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signaling_server: Option<String>,
+    /// 人数上限（含创建者），None 表示不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<u32>,
+    /// 本机是否以游客身份加入（只读参与：不开麦、不共享文件、不占用名额）
+    #[serde(default)]
+    pub is_guest: bool,
 }
 
 impl Lobby {
@@ -65,6 +107,8 @@ impl Lobby {
             virtual_domain,
             use_domain,
             signaling_server,
+            max_players: None,
+            is_guest: false,
         }
     }
 }
@@ -83,8 +127,23 @@ pub struct Player {
     pub mic_enabled: bool,
     /// 是否被静音
     pub is_muted: bool,
+    /// 是否已准备
+    #[serde(default)]
+    pub is_ready: bool,
+    /// 当前是否正在说话（语音活动检测），仅在返回玩家列表时填充
+    #[serde(default)]
+    pub is_speaking: bool,
     /// 加入时间
     pub joined_at: DateTime<Utc>,
+    /// 本次会话累计说话时长（秒），仅在有发言统计时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaking_seconds: Option<u64>,
+    /// 本次会话发言次数，仅在有发言统计时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_count: Option<u32>,
+    /// 与其他玩家重名时的显示后缀（如 "#2"），不重名时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_suffix: Option<String>,
 }
 
 impl Player {
@@ -103,11 +162,137 @@ impl Player {
             virtual_ip,
             mic_enabled: false,
             is_muted: false,
+            is_ready: false,
+            is_speaking: false,
             joined_at: Utc::now(),
+            speaking_seconds: None,
+            message_count: None,
+            display_suffix: None,
         }
     }
 }
 
+/// 同一会话内的显示名去重表
+///
+/// 第一个使用某昵称的玩家不带后缀，之后重名的玩家按加入顺序取最小的空闲序号，显示为 "#2"、"#3"……
+/// 昵称比较忽略首尾空白和大小写。玩家离开后序号释放，已分配的玩家在会话内保持原后缀不变。
+#[derive(Debug, Default, Clone)]
+pub struct DisplayNameRegistry {
+    /// 玩家 ID -> (规范化昵称, 序号)
+    assigned: HashMap<String, (String, u32)>,
+}
+
+/// 大厅管理器与 P2P 信令共用的显示名去重表
+pub type SharedDisplayNames = Arc<parking_lot::Mutex<DisplayNameRegistry>>;
+
+impl DisplayNameRegistry {
+    fn normalize(name: &str) -> String {
+        name.trim().to_lowercase()
+    }
+
+    fn suffix(index: u32) -> Option<String> {
+        (index > 1).then(|| format!("#{}", index))
+    }
+
+    /// 为玩家分配显示后缀，返回 None 表示无需后缀
+    pub fn assign(&mut self, player_id: &str, name: &str) -> Option<String> {
+        let key = Self::normalize(name);
+        if let Some((assigned_name, index)) = self.assigned.get(player_id) {
+            if *assigned_name == key {
+                return Self::suffix(*index);
+            }
+        }
+        let index = (1..)
+            .find(|i| {
+                !self
+                    .assigned
+                    .iter()
+                    .any(|(id, (n, idx))| id != player_id && *n == key && idx == i)
+            })
+            .unwrap_or(1);
+        self.assigned.insert(player_id.to_string(), (key, index));
+        Self::suffix(index)
+    }
+
+    /// 玩家当前的显示后缀
+    pub fn suffix_of(&self, player_id: &str) -> Option<String> {
+        self.assigned.get(player_id).and_then(|(_, index)| Self::suffix(*index))
+    }
+
+    /// 玩家离开，释放其序号
+    pub fn remove(&mut self, player_id: &str) {
+        self.assigned.remove(player_id);
+    }
+
+    /// 清空（退出大厅时）
+    pub fn clear(&mut self) {
+        self.assigned.clear();
+    }
+}
+
+/// 大厅子服务的启动状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ServiceStatus {
+    /// 已启动
+    Running,
+    /// 按需启动（例如文件服务器在第一次添加共享时启动）
+    OnDemand,
+    /// 可用但已降级（例如 TLS 初始化失败回退为明文 HTTP）
+    Degraded { reason: String },
+    /// 启动失败
+    Failed { reason: String },
+}
+
+/// 创建大厅的结果：大厅本身已创建成功，附带各子服务（网络/文件/聊天/信令）的启动状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LobbyCreateResult {
+    /// 创建的大厅
+    pub lobby: Lobby,
+    /// 子服务名称 -> 启动状态
+    pub services: HashMap<String, ServiceStatus>,
+}
+
+impl LobbyCreateResult {
+    /// 子服务名称：虚拟网络
+    pub const NETWORK: &'static str = "network";
+    /// 子服务名称：文件共享
+    pub const FILE: &'static str = "file";
+    /// 子服务名称：聊天
+    pub const CHAT: &'static str = "chat";
+    /// 子服务名称：P2P 信令
+    pub const SIGNALING: &'static str = "signaling";
+
+    /// 创建结果（虚拟网络已启动，其余子服务待记录）
+    pub fn new(lobby: Lobby) -> Self {
+        let mut services = HashMap::new();
+        services.insert(Self::NETWORK.to_string(), ServiceStatus::Running);
+        Self { lobby, services }
+    }
+
+    /// 记录子服务状态
+    pub fn set_service(&mut self, name: &str, status: ServiceStatus) {
+        self.services.insert(name.to_string(), status);
+    }
+
+    /// 未正常运行（失败或降级）的子服务及原因，按名称排序
+    pub fn problems(&self) -> Vec<(String, String)> {
+        let mut problems: Vec<(String, String)> = self
+            .services
+            .iter()
+            .filter_map(|(name, status)| match status {
+                ServiceStatus::Failed { reason } | ServiceStatus::Degraded { reason } => {
+                    Some((name.clone(), reason.clone()))
+                }
+                ServiceStatus::Running | ServiceStatus::OnDemand => None,
+            })
+            .collect();
+        problems.sort();
+        problems
+    }
+}
+
 /// 大厅错误类型
 #[derive(Debug, thiserror::Error)]
 pub enum LobbyError {
@@ -130,6 +315,10 @@ pub enum LobbyError {
     /// 玩家不存在
     #[error("玩家不存在: {0}")]
     PlayerNotFound(String),
+    
+    /// 权限不足
+    #[error("权限不足: {0}")]
+    PermissionDenied(String),
 }
 
 /// 将 LobbyError 转换为 AppError
@@ -147,10 +336,46 @@ impl From<LobbyError> for AppError {
             LobbyError::PlayerNotFound(id) => {
                 AppError::ValidationError(format!("玩家不存在: {}", id))
             }
+            LobbyError::PermissionDenied(msg) => {
+                AppError::ValidationError(format!("权限不足: {}", msg))
+            }
         }
     }
 }
 
+/// EasyTier 网络名前缀
+const NETWORK_NAME_PREFIX: &str = "MCTier-";
+
+/// 把任意文本编码为 ASCII 标识（UTF-8 字节的小写十六进制）
+///
+/// 中文、空格和特殊字符直接作为 EasyTier 命令行参数时可能被错误解析，
+/// 编码后只含 `0-9a-f`，同一输入在任何系统上得到相同结果。
+pub fn encode_network_component(value: &str) -> String {
+    value.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 解码 `encode_network_component` 的结果，格式不正确时返回 None
+pub fn decode_network_component(encoded: &str) -> Option<String> {
+    if !encoded.len().is_multiple_of(2) || !encoded.is_ascii() {
+        return None;
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// 由大厅名称与密码生成 EasyTier 网络名与网络密钥
+///
+/// 网络名为 "MCTier-" + 编码后的大厅名称，实现大厅隔离；密码同样编码后作为网络密钥。
+pub fn network_credentials(name: &str, password: &str) -> (String, String) {
+    (
+        format!("{}{}", NETWORK_NAME_PREFIX, encode_network_component(name)),
+        encode_network_component(password),
+    )
+}
+
 /// 大厅管理器
 /// 
 /// 负责管理大厅的创建、加入、退出以及玩家管理
@@ -161,6 +386,10 @@ pub struct LobbyManager {
     players: HashMap<String, Player>,
     /// Hosts文件管理器（用于Magic DNS）
     hosts_manager: Option<HostsManager>,
+    /// 创建大厅时的密码强度策略
+    password_policy: PasswordPolicy,
+    /// 重名玩家的显示名去重表（与 P2P 信令共用）
+    display_names: SharedDisplayNames,
 }
 
 impl LobbyManager {
@@ -173,9 +402,16 @@ impl LobbyManager {
             current_lobby: None,
             players: HashMap::new(),
             hosts_manager: None,
+            password_policy: PasswordPolicy::default(),
+            display_names: SharedDisplayNames::default(),
         }
     }
 
+    /// 设置创建大厅时的密码强度策略
+    pub fn set_password_policy(&mut self, policy: PasswordPolicy) {
+        self.password_policy = policy;
+    }
+
     /// 验证输入字符串
     /// 
     /// # 参数
@@ -201,6 +437,17 @@ impl LobbyManager {
         Ok(())
     }
 
+    /// 验证玩家名称
+    ///
+    /// # 规则
+    /// - 不能为空或仅包含空白字符
+    /// - 最多 `MAX_PLAYER_NAME_CHARS` 个字符
+    pub fn validate_player_name(player_name: &str) -> Result<(), LobbyError> {
+        Self::validate_input(player_name, "玩家名称")?;
+        check_len("玩家名称", player_name, MAX_PLAYER_NAME_CHARS)
+            .map_err(|e| LobbyError::InvalidInput(e.to_string()))
+    }
+
     /// 标准化服务器节点地址
     ///
     /// 将历史官方地址统一迁移到 EasyTier 官方域名节点
@@ -245,10 +492,11 @@ impl LobbyManager {
                 "大厅名称至少需要 4 个字符".to_string()
             ));
         }
-        if char_count > 32 {
-            return Err(LobbyError::InvalidInput(
-                "大厅名称最多 32 个字符".to_string()
-            ));
+        if char_count > MAX_LOBBY_NAME_CHARS {
+            return Err(LobbyError::InvalidInput(format!(
+                "大厅名称最多 {} 个字符",
+                MAX_LOBBY_NAME_CHARS
+            )));
         }
 
         // 检查是否包含字母或数字
@@ -273,33 +521,57 @@ impl LobbyManager {
         Ok(())
     }
 
+    /// 验证大厅人数上限
+    /// 
+    /// # 规则
+    /// - 未设置表示不限制
+    /// - 设置时范围为 2-`MAX_LOBBY_PLAYERS`（含创建者）
+    pub fn validate_max_players(max_players: Option<u32>) -> Result<(), LobbyError> {
+        match max_players {
+            Some(n) if !(2..=MAX_LOBBY_PLAYERS).contains(&n) => Err(LobbyError::InvalidInput(format!(
+                "人数上限需在 2-{} 之间",
+                MAX_LOBBY_PLAYERS
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     /// 验证密码
     /// 
     /// # 参数
     /// * `password` - 密码
+    /// * `policy` - 强度策略
     /// 
     /// # 返回
     /// * `Ok(())` - 验证通过
     /// * `Err(LobbyError)` - 验证失败
     /// 
     /// # 规则
-    /// - 长度：8-32 个字符
-    /// - 必须包含字母和数字
-    /// - 不能仅包含空白字符
-    pub fn validate_password(password: &str) -> Result<(), LobbyError> {
+    /// - 长度按字符计数（中文算 1 个字符），忽略首尾空白
+    /// - Strict：8-32 个字符，必须包含字母和数字
+    /// - Relaxed：4-32 个字符
+    /// - None：非空且不超过 32 个字符
+    pub fn validate_password(password: &str, policy: PasswordPolicy) -> Result<(), LobbyError> {
         let trimmed = password.trim();
+        let chars = trimmed.chars().count();
         
         // 检查长度
-        if trimmed.len() < 8 {
+        if chars == 0 {
+            return Err(LobbyError::InvalidInput("密码不能为空".to_string()));
+        }
+        if chars < policy.min_chars() {
             return Err(LobbyError::InvalidInput(
-                "密码至少需要 8 个字符".to_string()
+                format!("密码至少需要 {} 个字符", policy.min_chars())
             ));
         }
-        if trimmed.len() > 32 {
+        if chars > MAX_LOBBY_PASSWORD_CHARS {
             return Err(LobbyError::InvalidInput(
-                "密码最多 32 个字符".to_string()
+                format!("密码最多 {} 个字符", MAX_LOBBY_PASSWORD_CHARS)
             ));
         }
+        if policy != PasswordPolicy::Strict {
+            return Ok(());
+        }
 
         // 检查是否包含字母
         let has_letter = trimmed.chars().any(|c| c.is_alphabetic());
@@ -348,6 +620,7 @@ impl LobbyManager {
     /// * `app_handle` - Tauri 应用句柄
     /// * `global_config` - 全局 EasyTier 高级配置
     /// * `lobby_config` - 大厅 EasyTier 高级配置
+    /// * `max_players` - 人数上限（含创建者），None 表示不限制
     /// 
     /// # 返回
     /// * `Ok(Lobby)` - 成功创建的大厅信息
@@ -365,6 +638,7 @@ impl LobbyManager {
         app_handle: &tauri::AppHandle,
         global_config: Option<crate::modules::config_manager::EasyTierAdvancedConfig>,
         lobby_config: Option<crate::modules::config_manager::EasyTierAdvancedConfig>,
+        max_players: Option<u32>,
     ) -> Result<Lobby, LobbyError> {
         // 检查是否已经在大厅中
         if self.current_lobby.is_some() {
@@ -373,16 +647,15 @@ impl LobbyManager {
 
         // 验证输入
         Self::validate_lobby_name(&name)?;
-        Self::validate_password(&password)?;
-        Self::validate_input(&player_name, "玩家名称")?;
+        Self::validate_password(&password, self.password_policy)?;
+        Self::validate_max_players(max_players)?;
+        Self::validate_player_name(&player_name)?;
         Self::validate_input(&server_node, "服务器节点")?;
 
         log::info!("正在创建大厅: {}, 使用域名: {}, 虚拟域名: {:?}", name, use_domain, virtual_domain);
 
-        // 构建 EasyTier 网络凭证
-        // 使用 "MCTier-" + 大厅名称作为网络号，实现大厅隔离
-        let network_name = format!("MCTier-{}", name);
-        let network_key = password.clone();
+        // 构建 EasyTier 网络凭证（大厅名称与密码编码为 ASCII）
+        let (network_name, network_key) = network_credentials(&name, &password);
 
         log::info!("EasyTier 网络号: {}", network_name);
 
@@ -438,9 +711,9 @@ impl LobbyManager {
         // 创建大厅实例
         // 约定：所有节点都连接到 10.126.126.1:8445
         // 在 EasyTier DHCP 模式下，第一个加入网络的节点通常会获得 10.126.126.1
-        let creator_virtual_ip = "10.126.126.1".to_string();
+        let creator_virtual_ip = CREATOR_VIRTUAL_IP.to_string();
         log::info!("约定的信令服务器地址: {}:8445", creator_virtual_ip);
-        let lobby = Lobby::new(
+        let mut lobby = Lobby::new(
             name, 
             Some(password), 
             virtual_ip.clone(), 
@@ -449,13 +722,14 @@ impl LobbyManager {
             Some(use_domain),
             Some(signaling_server),
         );
+        lobby.max_players = max_players;
 
         // 创建当前玩家
         let player = Player::new(player_name, virtual_ip.clone());
 
         // 保存大厅和玩家信息
         self.current_lobby = Some(lobby.clone());
-        self.players.insert(player.id.clone(), player);
+        self.insert_player(player);
 
         log::info!("大厅创建成功: {}", lobby.name);
 
@@ -497,16 +771,14 @@ impl LobbyManager {
 
         // 验证输入
         Self::validate_lobby_name(&name)?;
-        Self::validate_password(&password)?;
-        Self::validate_input(&player_name, "玩家名称")?;
+        Self::validate_password(&password, self.password_policy)?;
+        Self::validate_player_name(&player_name)?;
         Self::validate_input(&server_node, "服务器节点")?;
 
         log::info!("正在创建大厅: {}, 使用域名: {}, 虚拟域名: {:?}", name, use_domain, virtual_domain);
 
-        // 构建 EasyTier 网络凭证
-        // 使用 "MCTier-" + 大厅名称作为网络号，实现大厅隔离
-        let network_name = format!("MCTier-{}", name);
-        let network_key = password.clone();
+        // 构建 EasyTier 网络凭证（大厅名称与密码编码为 ASCII）
+        let (network_name, network_key) = network_credentials(&name, &password);
 
         log::info!("EasyTier 网络号: {}", network_name);
 
@@ -554,7 +826,7 @@ impl LobbyManager {
         // 创建大厅实例
         // 约定：所有节点都连接到 10.126.126.1:8445
         // 在 EasyTier DHCP 模式下，第一个加入网络的节点通常会获得 10.126.126.1
-        let creator_virtual_ip = "10.126.126.1".to_string();
+        let creator_virtual_ip = CREATOR_VIRTUAL_IP.to_string();
         log::info!("约定的信令服务器地址: {}:8445", creator_virtual_ip);
         let lobby = Lobby::new(
             name, 
@@ -571,7 +843,7 @@ impl LobbyManager {
 
         // 保存大厅和玩家信息
         self.current_lobby = Some(lobby.clone());
-        self.players.insert(player.id.clone(), player);
+        self.insert_player(player);
 
         log::info!("大厅创建成功: {}", lobby.name);
 
@@ -631,15 +903,15 @@ impl LobbyManager {
 
         // 验证输入
         Self::validate_lobby_name(&name)?;
-        Self::validate_password(&password)?;
-        Self::validate_input(&player_name, "玩家名称")?;
+        // 密码强度由创建者的策略决定，加入时只做基本检查
+        Self::validate_password(&password, PasswordPolicy::None)?;
+        Self::validate_player_name(&player_name)?;
         Self::validate_input(&server_node, "服务器节点")?;
 
         log::info!("正在加入大厅: {}, 使用域名: {}, 虚拟域名: {:?}", name, use_domain, virtual_domain);
 
-        // 构建 EasyTier 网络凭证
-        let network_name = format!("MCTier-{}", name);
-        let network_key = password.clone();
+        // 构建 EasyTier 网络凭证（大厅名称与密码编码为 ASCII）
+        let (network_name, network_key) = network_credentials(&name, &password);
 
         log::info!("EasyTier 网络号: {}", network_name);
 
@@ -693,7 +965,7 @@ impl LobbyManager {
         }
 
         // 创建大厅实例
-        let creator_virtual_ip = "10.126.126.1".to_string();
+        let creator_virtual_ip = CREATOR_VIRTUAL_IP.to_string();
         log::info!("约定的信令服务器地址: {}:8445", creator_virtual_ip);
         let lobby = Lobby::new(
             name, 
@@ -710,7 +982,7 @@ impl LobbyManager {
 
         // 保存大厅和玩家信息
         self.current_lobby = Some(lobby.clone());
-        self.players.insert(player.id.clone(), player);
+        self.insert_player(player);
 
         log::info!("成功加入大厅: {}", lobby.name);
 
@@ -750,16 +1022,15 @@ impl LobbyManager {
 
         // 验证输入
         Self::validate_lobby_name(&name)?;
-        Self::validate_password(&password)?;
-        Self::validate_input(&player_name, "玩家名称")?;
+        // 密码强度由创建者的策略决定，加入时只做基本检查
+        Self::validate_password(&password, PasswordPolicy::None)?;
+        Self::validate_player_name(&player_name)?;
         Self::validate_input(&server_node, "服务器节点")?;
 
         log::info!("正在加入大厅: {}, 使用域名: {}, 虚拟域名: {:?}", name, use_domain, virtual_domain);
 
-        // 构建 EasyTier 网络凭证
-        // 使用 "MCTier-" + 大厅名称作为网络号，实现大厅隔离
-        let network_name = format!("MCTier-{}", name);
-        let network_key = password.clone();
+        // 构建 EasyTier 网络凭证（大厅名称与密码编码为 ASCII）
+        let (network_name, network_key) = network_credentials(&name, &password);
 
         log::info!("EasyTier 网络号: {}", network_name);
 
@@ -808,8 +1079,8 @@ impl LobbyManager {
 
         // 约定：所有节点都尝试连接到虚拟IP为 10.126.126.1 的节点
         // 在 EasyTier DHCP 模式下，第一个加入网络的节点通常会获得 10.126.126.1
-        // 如果第一个节点离开，需要有重新选举机制（TODO）
-        let creator_virtual_ip = "10.126.126.1".to_string();
+        // 如果第一个节点离开，由 CreatorElection 在剩余成员中重新选举
+        let creator_virtual_ip = CREATOR_VIRTUAL_IP.to_string();
         
         log::info!("将连接到信令服务器: {}:8445", creator_virtual_ip);
 
@@ -829,7 +1100,7 @@ impl LobbyManager {
 
         // 保存大厅和玩家信息
         self.current_lobby = Some(lobby.clone());
-        self.players.insert(player.id.clone(), player);
+        self.insert_player(player);
 
         log::info!("成功加入大厅: {}", lobby.name);
 
@@ -878,12 +1149,87 @@ impl LobbyManager {
         // 清理大厅和玩家信息
         self.current_lobby = None;
         self.players.clear();
+        self.display_names.lock().clear();
 
         log::info!("已成功退出大厅");
 
         Ok(())
     }
 
+    /// 当前玩家是否为大厅创建者（本地虚拟 IP 等于创建者虚拟 IP）
+    pub fn is_creator(&self) -> bool {
+        self.current_lobby
+            .as_ref()
+            .map(|lobby| lobby.virtual_ip == lobby.creator_virtual_ip)
+            .unwrap_or(false)
+    }
+
+    /// 检查是否可以踢出指定玩家
+    /// 
+    /// # 参数
+    /// * `player_id` - 要踢出的玩家 ID
+    /// * `local_player_id` - 本地玩家 ID
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 可以踢出
+    /// * `Err(LobbyError)` - 不在大厅、非创建者或试图踢出自己
+    pub fn ensure_can_kick(&self, player_id: &str, local_player_id: Option<&str>) -> Result<(), LobbyError> {
+        if self.current_lobby.is_none() {
+            return Err(LobbyError::NotInLobby);
+        }
+        if !self.is_creator() {
+            return Err(LobbyError::PermissionDenied("只有大厅创建者可以踢出玩家".to_string()));
+        }
+        Self::validate_input(player_id, "玩家ID")?;
+        if local_player_id == Some(player_id) {
+            return Err(LobbyError::InvalidInput("不能踢出自己".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 踢出玩家
+    /// 
+    /// 仅大厅创建者可调用。通过 P2P 信令广播 `Kicked` 消息，被踢玩家收到后会自动退出大厅。
+    /// 
+    /// # 参数
+    /// * `player_id` - 要踢出的玩家 ID
+    /// * `reason` - 踢出原因
+    /// * `p2p_signaling` - P2P 信令服务
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 已发送踢出消息
+    /// * `Err(LobbyError)` - 权限不足、玩家不存在或发送失败
+    pub async fn kick_player(
+        &mut self,
+        player_id: &str,
+        reason: String,
+        p2p_signaling: &crate::modules::p2p_signaling::P2PSignalingService,
+    ) -> Result<(), LobbyError> {
+        let local_player_id = p2p_signaling.get_local_player_id().await;
+        self.ensure_can_kick(player_id, local_player_id.as_deref())?;
+        
+        let known_peer = p2p_signaling.has_peer(player_id).await;
+        if !known_peer && !self.players.contains_key(player_id) {
+            return Err(LobbyError::PlayerNotFound(player_id.to_string()));
+        }
+        
+        log::info!("踢出玩家: {} (原因: {})", player_id, reason);
+        
+        p2p_signaling
+            .broadcast_to_all(crate::modules::p2p_signaling::P2PMessage::Kicked {
+                player_id: player_id.to_string(),
+                reason,
+            })
+            .await
+            .map_err(|e| LobbyError::NetworkError(e.to_string()))?;
+        
+        p2p_signaling.remove_peer(player_id).await;
+        self.display_names.lock().remove(player_id);
+        self.players.remove(player_id);
+        
+        Ok(())
+    }
+
     /// 添加玩家
     /// 
     /// # 参数
@@ -893,9 +1239,48 @@ impl LobbyManager {
     /// 此方法用于添加其他玩家到玩家列表（通过网络同步）
     pub fn add_player(&mut self, player: Player) {
         log::info!("添加玩家: {} ({})", player.name, player.id);
+        self.insert_player(player);
+    }
+
+    /// 记录通过 P2P 发现的其他玩家
+    /// 
+    /// 已存在时只更新名称和虚拟 IP，保留加入时间、准备 / 麦克风等状态。
+    /// 不在大厅中时忽略（退出大厅后仍可能收到迟到的发现消息）。
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家 ID（与 P2P 信令中的 ID 一致）
+    /// * `name` - 玩家名称
+    /// * `virtual_ip` - 玩家虚拟 IP
+    /// 
+    /// # 返回
+    /// 是否新增了玩家
+    pub fn upsert_discovered_player(&mut self, player_id: &str, name: &str, virtual_ip: &str) -> bool {
+        if self.current_lobby.is_none() {
+            return false;
+        }
+        if let Some(player) = self.players.get_mut(player_id) {
+            player.name = name.to_string();
+            player.virtual_ip = virtual_ip.to_string();
+            return false;
+        }
+        let mut player = Player::new(name.to_string(), virtual_ip.to_string());
+        player.id = player_id.to_string();
+        log::info!("同步 P2P 发现的玩家: {} ({})", player.name, player.id);
+        self.insert_player(player);
+        true
+    }
+
+    /// 记录玩家并按去重表补上显示后缀，保证同一会话内显示名唯一
+    fn insert_player(&mut self, mut player: Player) {
+        player.display_suffix = self.display_names.lock().assign(&player.id, &player.name);
         self.players.insert(player.id.clone(), player);
     }
 
+    /// 重名玩家的显示名去重表（交给 P2P 信令在发现新玩家时使用）
+    pub fn display_names(&self) -> SharedDisplayNames {
+        self.display_names.clone()
+    }
+
     /// 移除玩家
     /// 
     /// # 参数
@@ -906,6 +1291,7 @@ impl LobbyManager {
     /// * `None` - 玩家不存在
     pub fn remove_player(&mut self, player_id: &str) -> Option<Player> {
         log::info!("移除玩家: {}", player_id);
+        self.display_names.lock().remove(player_id);
         self.players.remove(player_id)
     }
 
@@ -922,6 +1308,21 @@ impl LobbyManager {
         players
     }
 
+    /// 按排序偏好获取玩家列表（仅用于展示）
+    /// 
+    /// # 参数
+    /// * `order` - 排序偏好，`None` 时按加入时间排序
+    /// 
+    /// # 返回
+    /// 排序后的玩家列表
+    pub fn get_players_sorted(&self, order: Option<PlayerSortOrder>) -> Vec<Player> {
+        let mut players = self.get_players();
+        if let Some(order) = order {
+            sort_players(&mut players, order);
+        }
+        players
+    }
+
     /// 获取玩家数量
     /// 
     /// # 返回
@@ -1012,6 +1413,35 @@ impl LobbyManager {
         Ok(())
     }
 
+    /// 更新玩家准备状态
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家 ID
+    /// * `is_ready` - 是否已准备
+    /// 
+    /// # 返回
+    /// * `Ok(())` - 更新成功
+    /// * `Err(LobbyError)` - 玩家不存在
+    pub fn update_player_ready_status(
+        &mut self,
+        player_id: &str,
+        is_ready: bool,
+    ) -> Result<(), LobbyError> {
+        let player = self
+            .get_player_mut(player_id)
+            .ok_or_else(|| LobbyError::PlayerNotFound(player_id.to_string()))?;
+
+        player.is_ready = is_ready;
+
+        log::debug!(
+            "更新玩家 {} 准备状态: {}",
+            player_id,
+            is_ready
+        );
+
+        Ok(())
+    }
+
     /// 获取当前大厅信息
     /// 
     /// # 返回
@@ -1021,6 +1451,41 @@ impl LobbyManager {
         self.current_lobby.as_ref()
     }
 
+    /// 标记本机在当前大厅中是否为游客
+    /// 
+    /// # 返回
+    /// 更新后的大厅信息（不在大厅中时为 None）
+    pub fn set_guest(&mut self, guest: bool) -> Option<Lobby> {
+        let lobby = self.current_lobby.as_mut()?;
+        lobby.is_guest = guest;
+        Some(lobby.clone())
+    }
+
+    /// 更新当前大厅的创建者虚拟 IP（重新选举后调用）
+    /// 
+    /// # 返回
+    /// 更新后的大厅信息（不在大厅中时为 None）
+    pub fn set_creator_virtual_ip(&mut self, creator_virtual_ip: String) -> Option<Lobby> {
+        let lobby = self.current_lobby.as_mut()?;
+        log::info!("大厅创建者变更: {} -> {}", lobby.creator_virtual_ip, creator_virtual_ip);
+        lobby.creator_virtual_ip = creator_virtual_ip;
+        Some(lobby.clone())
+    }
+
+    /// 更新本机在当前大厅中的虚拟 IP（冲突后重新分配时调用），本机为创建者时同步更新创建者 IP
+    /// 
+    /// # 返回
+    /// 更新后的大厅信息（不在大厅中时为 None）
+    pub fn set_virtual_ip(&mut self, virtual_ip: String) -> Option<Lobby> {
+        let lobby = self.current_lobby.as_mut()?;
+        log::info!("本机虚拟 IP 变更: {} -> {}", lobby.virtual_ip, virtual_ip);
+        if lobby.creator_virtual_ip == lobby.virtual_ip {
+            lobby.creator_virtual_ip = virtual_ip.clone();
+        }
+        lobby.virtual_ip = virtual_ip;
+        Some(lobby.clone())
+    }
+
     /// 检查是否在大厅中
     /// 
     /// # 返回
@@ -1063,6 +1528,111 @@ impl Default for LobbyManager {
     }
 }
 
+/// 加入大厅后等待约定创建者出现的时间，超过仍未发现则重新选举
+pub const CREATOR_DISCOVERY_GRACE: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 确定性选举：在候选虚拟 IP 中取最小者作为创建者
+///
+/// 无法解析为 IPv4 的地址会被忽略。
+pub fn elect_creator<'a>(candidates: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    candidates
+        .into_iter()
+        .filter_map(|ip| ip.parse::<std::net::Ipv4Addr>().ok())
+        .min()
+        .map(|ip| ip.to_string())
+}
+
+/// 创建者重新选举
+///
+/// - 当前创建者从成员中消失（曾经出现过，或加入后超过宽限期仍未出现）时，在剩余成员中重新选举
+/// - 脑裂：收到其他节点宣告的创建者与本地结果不一致时，两者中虚拟 IP 较小的一方胜出，
+///   双方按同一规则收敛到同一个创建者
+#[derive(Debug)]
+pub struct CreatorElection {
+    /// 当前创建者是否曾出现在成员中
+    creator_seen: bool,
+    /// 开始观察的时间（加入大厅的时间）
+    started_at: std::time::Instant,
+}
+
+impl CreatorElection {
+    pub fn new(now: std::time::Instant) -> Self {
+        Self {
+            creator_seen: false,
+            started_at: now,
+        }
+    }
+
+    /// 根据当前成员重新计算创建者
+    ///
+    /// # 参数
+    /// * `current_creator` - 当前认定的创建者虚拟 IP
+    /// * `members` - 当前成员的虚拟 IP（含本机，不含游客）
+    /// * `announced` - 最近收到的其他节点宣告的创建者虚拟 IP
+    /// * `now` - 当前时间
+    ///
+    /// # 返回
+    /// * `Some(ip)` - 创建者发生变化，新的创建者虚拟 IP
+    /// * `None` - 创建者不变
+    pub fn evaluate(
+        &mut self,
+        current_creator: &str,
+        members: &[String],
+        announced: Option<&str>,
+        now: std::time::Instant,
+    ) -> Option<String> {
+        let is_member = |ip: &str| members.iter().any(|m| m == ip);
+        if is_member(current_creator) {
+            self.creator_seen = true;
+        }
+
+        let creator_gone = !is_member(current_creator)
+            && (self.creator_seen || now.saturating_duration_since(self.started_at) >= CREATOR_DISCOVERY_GRACE);
+        let mut creator = if creator_gone {
+            elect_creator(members.iter().map(String::as_str))?
+        } else {
+            current_creator.to_string()
+        };
+
+        if let Some(announced) = announced.filter(|ip| is_member(ip)) {
+            if let Some(winner) = elect_creator([creator.as_str(), announced]) {
+                creator = winner;
+            }
+        }
+
+        if creator == current_creator {
+            return None;
+        }
+        self.creator_seen = is_member(&creator);
+        Some(creator)
+    }
+}
+
+/// 按排序偏好对玩家列表排序（稳定排序，相同条件下保持加入顺序）
+/// 
+/// # 参数
+/// * `players` - 玩家列表
+/// * `order` - 排序偏好
+pub fn sort_players(players: &mut [Player], order: PlayerSortOrder) {
+    match order {
+        PlayerSortOrder::Name => players.sort_by(|a, b| {
+            a.name.to_lowercase().cmp(&b.name.to_lowercase())
+                .then_with(|| a.joined_at.cmp(&b.joined_at))
+        }),
+        PlayerSortOrder::JoinOrder => players.sort_by_key(|p| p.joined_at),
+        // 正在说话的在前，其余按本次会话累计说话时长降序
+        PlayerSortOrder::Speaking => players.sort_by(|a, b| {
+            b.is_speaking.cmp(&a.is_speaking)
+                .then_with(|| b.speaking_seconds.unwrap_or(0).cmp(&a.speaking_seconds.unwrap_or(0)))
+                .then_with(|| a.joined_at.cmp(&b.joined_at))
+        }),
+        PlayerSortOrder::Ready => players.sort_by(|a, b| {
+            b.is_ready.cmp(&a.is_ready)
+                .then_with(|| a.joined_at.cmp(&b.joined_at))
+        }),
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -1079,6 +1649,48 @@ mod tests {
         assert!(!lobby.id.is_empty());
     }
 
+    #[test]
+    fn test_lobby_create_result_reports_problems() {
+        let lobby = Lobby::new("测试大厅".to_string(), None, "10.126.126.1".to_string(), "10.126.126.1".to_string(), None, Some(false), None);
+        let mut result = LobbyCreateResult::new(lobby);
+        result.set_service(LobbyCreateResult::SIGNALING, ServiceStatus::Running);
+        result.set_service(LobbyCreateResult::FILE, ServiceStatus::OnDemand);
+        assert!(result.problems().is_empty());
+
+        result.set_service(LobbyCreateResult::CHAT, ServiceStatus::Failed { reason: "端口被占用".to_string() });
+        result.set_service(LobbyCreateResult::FILE, ServiceStatus::Degraded { reason: "TLS 不可用".to_string() });
+        assert_eq!(
+            result.problems(),
+            vec![
+                ("chat".to_string(), "端口被占用".to_string()),
+                ("file".to_string(), "TLS 不可用".to_string()),
+            ]
+        );
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["services"]["network"], serde_json::json!({ "status": "running" }));
+        assert_eq!(json["services"]["chat"], serde_json::json!({ "status": "failed", "reason": "端口被占用" }));
+    }
+
+    #[test]
+    fn test_network_credentials_are_ascii_and_round_trip() {
+        let (name, key) = network_credentials("我的 大厅-1", "密码 p@ss");
+        assert!(name.starts_with(NETWORK_NAME_PREFIX));
+        assert!(name.is_ascii() && key.is_ascii());
+        assert!(!name.contains(' ') && !key.contains(' '));
+        assert_eq!(decode_network_component(&name[NETWORK_NAME_PREFIX.len()..]).as_deref(), Some("我的 大厅-1"));
+        assert_eq!(decode_network_component(&key).as_deref(), Some("密码 p@ss"));
+
+        // 编码是确定性的，不同大厅名称不会冲突
+        assert_eq!(network_credentials("我的 大厅-1", "密码 p@ss"), (name.clone(), key));
+        assert_ne!(network_credentials("我的 大厅-2", "密码 p@ss").0, name);
+        assert_eq!(encode_network_component("Ab"), "4162");
+
+        assert_eq!(decode_network_component("e6"), None);
+        assert_eq!(decode_network_component("abc"), None);
+        assert_eq!(decode_network_component("zz"), None);
+    }
+
     #[test]
     fn test_player_creation() {
         let player = Player::new("测试玩家".to_string(), "10.126.126.1".to_string());
@@ -1141,6 +1753,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_max_players() {
+        assert!(LobbyManager::validate_max_players(None).is_ok());
+        assert!(LobbyManager::validate_max_players(Some(2)).is_ok());
+        assert!(LobbyManager::validate_max_players(Some(MAX_LOBBY_PLAYERS)).is_ok());
+        assert!(LobbyManager::validate_max_players(Some(0)).is_err());
+        assert!(LobbyManager::validate_max_players(Some(1)).is_err());
+        assert!(LobbyManager::validate_max_players(Some(MAX_LOBBY_PLAYERS + 1)).is_err());
+    }
+
     #[test]
     fn test_lobby_manager_creation() {
         let manager = LobbyManager::new();
@@ -1180,6 +1802,30 @@ mod tests {
         assert!(manager.get_player(&player2_id).is_some());
     }
 
+    #[test]
+    fn test_upsert_discovered_player() {
+        let mut manager = LobbyManager::new();
+        // 不在大厅中时忽略迟到的发现消息
+        assert!(!manager.upsert_discovered_player("peer-1", "玩家A", "10.126.126.2"));
+        assert_eq!(manager.get_player_count(), 0);
+
+        manager.current_lobby = Some(Lobby::new("测试大厅".to_string(), None, "10.126.126.1".to_string(), "10.126.126.1".to_string(), None, Some(false), None));
+        assert!(manager.upsert_discovered_player("peer-1", "玩家A", "10.126.126.2"));
+        manager.get_player_mut("peer-1").unwrap().is_ready = true;
+        let joined_at = manager.get_player("peer-1").unwrap().joined_at;
+
+        // 重复发现只更新名称 / IP，保留加入时间与状态
+        assert!(!manager.upsert_discovered_player("peer-1", "玩家A2", "10.126.126.3"));
+        let player = manager.get_player("peer-1").unwrap();
+        assert_eq!(player.name, "玩家A2");
+        assert_eq!(player.virtual_ip, "10.126.126.3");
+        assert_eq!(player.joined_at, joined_at);
+        assert!(player.is_ready);
+
+        assert!(manager.remove_player("peer-1").is_some());
+        assert_eq!(manager.get_player_count(), 0);
+    }
+
     #[test]
     fn test_get_players_sorted() {
         let mut manager = LobbyManager::new();
@@ -1347,6 +1993,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_validate_player_name_length() {
+        assert!(LobbyManager::validate_player_name(&"史蒂夫".repeat(10)).is_ok());
+        assert!(LobbyManager::validate_player_name(&"a".repeat(MAX_PLAYER_NAME_CHARS)).is_ok());
+
+        let result = LobbyManager::validate_player_name(&"a".repeat(MAX_PLAYER_NAME_CHARS + 1));
+        assert!(matches!(result, Err(LobbyError::InvalidInput(ref msg)) if msg.contains("玩家名称")));
+        assert!(LobbyManager::validate_player_name("   ").is_err());
+        assert!(LobbyManager::validate_lobby_name(&"房间".repeat(MAX_LOBBY_NAME_CHARS / 2 + 1)).is_err());
+    }
+
     #[test]
     fn test_validate_password_empty() {
         let result = LobbyManager::validate_input("", "密码");
@@ -1362,6 +2019,48 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_password_strict() {
+        let strict = PasswordPolicy::Strict;
+        assert!(LobbyManager::validate_password("abc12345", strict).is_ok());
+        assert!(LobbyManager::validate_password("abc1234", strict).is_err());
+        assert!(LobbyManager::validate_password("abcdefgh", strict).is_err());
+        assert!(LobbyManager::validate_password("12345678", strict).is_err());
+        assert!(LobbyManager::validate_password(&"a1".repeat(16), strict).is_ok());
+        assert!(LobbyManager::validate_password(&"a1".repeat(17), strict).is_err());
+        // 中文按字符计数：7 个汉字 + 1 个数字共 8 个字符（24+ 字节）
+        assert!(LobbyManager::validate_password("我们的秘密基地1", strict).is_ok());
+        assert!(LobbyManager::validate_password("秘密基地1", strict).is_err());
+        // 纯中文没有数字，严格模式下不通过
+        assert!(LobbyManager::validate_password("我们的秘密基地啊", strict).is_err());
+    }
+
+    #[test]
+    fn test_validate_password_relaxed_and_none_allow_chinese() {
+        for policy in [PasswordPolicy::Relaxed, PasswordPolicy::None] {
+            assert!(LobbyManager::validate_password("我们的秘密基地", policy).is_ok());
+            assert!(LobbyManager::validate_password("一起挖矿", policy).is_ok());
+            // 32 个汉字（96 字节）按字符计数仍在上限内
+            assert!(LobbyManager::validate_password(&"矿".repeat(32), policy).is_ok());
+            assert!(LobbyManager::validate_password(&"矿".repeat(33), policy).is_err());
+            assert!(LobbyManager::validate_password("   ", policy).is_err());
+        }
+        assert!(LobbyManager::validate_password("挖矿", PasswordPolicy::Relaxed).is_err());
+        assert!(LobbyManager::validate_password("挖", PasswordPolicy::None).is_ok());
+    }
+
+    #[test]
+    fn test_password_policy_serde_names() {
+        for (policy, name) in [
+            (PasswordPolicy::Strict, "\"strict\""),
+            (PasswordPolicy::Relaxed, "\"relaxed\""),
+            (PasswordPolicy::None, "\"none\""),
+        ] {
+            assert_eq!(serde_json::to_string(&policy).unwrap(), name);
+            assert_eq!(serde_json::from_str::<PasswordPolicy>(name).unwrap(), policy);
+        }
+    }
+
     #[test]
     fn test_validate_player_name_empty() {
         let result = LobbyManager::validate_input("", "玩家名称");
@@ -1643,6 +2342,206 @@ mod tests {
         assert_eq!(manager1.is_in_lobby(), manager2.is_in_lobby());
         assert_eq!(manager1.get_player_count(), manager2.get_player_count());
     }
-}
 
+    /// 构造按固定间隔加入的玩家，便于验证排序
+    fn players_for_sorting() -> Vec<Player> {
+        let base = Utc::now();
+        let mut players = vec![
+            Player::new("charlie".to_string(), "10.126.126.3".to_string()),
+            Player::new("Alice".to_string(), "10.126.126.4".to_string()),
+            Player::new("bob".to_string(), "10.126.126.5".to_string()),
+        ];
+        for (i, player) in players.iter_mut().enumerate() {
+            player.joined_at = base + chrono::Duration::seconds(i as i64);
+        }
+        players
+    }
+
+    fn names(players: &[Player]) -> Vec<&str> {
+        players.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_players_by_name() {
+        let mut players = players_for_sorting();
+        sort_players(&mut players, PlayerSortOrder::Name);
+        assert_eq!(names(&players), vec!["Alice", "bob", "charlie"]);
+    }
+
+    #[test]
+    fn test_sort_players_by_join_order() {
+        let mut players = players_for_sorting();
+        players.reverse();
+        sort_players(&mut players, PlayerSortOrder::JoinOrder);
+        assert_eq!(names(&players), vec!["charlie", "Alice", "bob"]);
+    }
+
+    #[test]
+    fn test_sort_players_by_speaking() {
+        let mut players = players_for_sorting();
+        // 只开麦不说话不算活跃
+        players[0].mic_enabled = true;
+        players[1].speaking_seconds = Some(30);
+        players[2].is_speaking = true;
+        sort_players(&mut players, PlayerSortOrder::Speaking);
+        // 正在说话的在前，其次按累计说话时长，其余保持加入顺序
+        assert_eq!(names(&players), vec!["bob", "Alice", "charlie"]);
+    }
+
+    #[test]
+    fn test_sort_players_by_ready() {
+        let mut players = players_for_sorting();
+        players[1].is_ready = true;
+        players[2].is_ready = true;
+        sort_players(&mut players, PlayerSortOrder::Ready);
+        assert_eq!(names(&players), vec!["Alice", "bob", "charlie"]);
+    }
+
+    #[test]
+    fn test_get_players_sorted_with_preference() {
+        let mut manager = LobbyManager::new();
+        for player in players_for_sorting() {
+            manager.add_player(player);
+        }
+
+        // 无偏好时按加入顺序
+        assert_eq!(names(&manager.get_players_sorted(None)), vec!["charlie", "Alice", "bob"]);
+        assert_eq!(
+            names(&manager.get_players_sorted(Some(PlayerSortOrder::Name))),
+            vec!["Alice", "bob", "charlie"]
+        );
+    }
+
+    fn lobby_with_ips(virtual_ip: &str, creator_virtual_ip: &str) -> Lobby {
+        Lobby::new("测试大厅".to_string(), Some("test1234".to_string()), virtual_ip.to_string(), creator_virtual_ip.to_string(), None, None, None)
+    }
+
+    #[test]
+    fn test_ensure_can_kick_requires_creator() {
+        let mut manager = LobbyManager::new();
+        assert!(matches!(manager.ensure_can_kick("player-2", Some("player-1")), Err(LobbyError::NotInLobby)));
+
+        // 普通成员无权踢人
+        manager.current_lobby = Some(lobby_with_ips("10.126.126.5", "10.126.126.1"));
+        assert!(!manager.is_creator());
+        assert!(matches!(
+            manager.ensure_can_kick("player-2", Some("player-1")),
+            Err(LobbyError::PermissionDenied(_))
+        ));
+
+        // 创建者可以踢出他人，但不能踢出自己
+        manager.current_lobby = Some(lobby_with_ips("10.126.126.1", "10.126.126.1"));
+        assert!(manager.is_creator());
+        assert!(manager.ensure_can_kick("player-2", Some("player-1")).is_ok());
+        assert!(matches!(
+            manager.ensure_can_kick("player-1", Some("player-1")),
+            Err(LobbyError::InvalidInput(_))
+        ));
+        assert!(matches!(manager.ensure_can_kick("  ", Some("player-1")), Err(LobbyError::InvalidInput(_))));
+    }
+
+    fn ips(list: &[&str]) -> Vec<String> {
+        list.iter().map(|ip| ip.to_string()).collect()
+    }
+
+    #[test]
+    fn test_elect_creator_picks_lowest_ip() {
+        // 按数值而不是字符串比较
+        assert_eq!(elect_creator(["10.126.126.10", "10.126.126.9", "10.126.126.100"]), Some("10.126.126.9".to_string()));
+        assert_eq!(elect_creator(["invalid", "10.126.126.3"]), Some("10.126.126.3".to_string()));
+        assert_eq!(elect_creator([]), None);
+    }
+
+    #[test]
+    fn test_reelect_when_creator_leaves() {
+        let now = std::time::Instant::now();
+        let mut election = CreatorElection::new(now);
+        let members = ips(&["10.126.126.1", "10.126.126.3", "10.126.126.2"]);
+
+        // 创建者仍在，不变
+        assert_eq!(election.evaluate("10.126.126.1", &members, None, now), None);
+        // 创建者离开，剩余成员中虚拟 IP 最小者当选
+        let remaining = ips(&["10.126.126.3", "10.126.126.2"]);
+        assert_eq!(election.evaluate("10.126.126.1", &remaining, None, now), Some("10.126.126.2".to_string()));
+        assert_eq!(election.evaluate("10.126.126.2", &remaining, None, now), None);
+    }
+
+    #[test]
+    fn test_creator_not_reelected_before_discovery_grace() {
+        let now = std::time::Instant::now();
+        let mut election = CreatorElection::new(now);
+        let members = ips(&["10.126.126.5"]);
+
+        // 刚加入时还没发现约定的创建者，先等待
+        assert_eq!(election.evaluate("10.126.126.1", &members, None, now), None);
+        // 宽限期后仍未出现则重新选举
+        assert_eq!(
+            election.evaluate("10.126.126.1", &members, None, now + CREATOR_DISCOVERY_GRACE),
+            Some("10.126.126.5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_brain_converges_to_lowest_ip() {
+        let now = std::time::Instant::now();
+        let members = ips(&["10.126.126.2", "10.126.126.4"]);
+
+        // 两个节点各自认为自己是创建者，交换宣告后都收敛到 10.126.126.2
+        let mut a = CreatorElection::new(now);
+        let mut b = CreatorElection::new(now);
+        assert_eq!(a.evaluate("10.126.126.2", &members, Some("10.126.126.4"), now), None);
+        assert_eq!(b.evaluate("10.126.126.4", &members, Some("10.126.126.2"), now), Some("10.126.126.2".to_string()));
+
+        // 宣告的创建者不在成员中（已离开）时忽略
+        assert_eq!(a.evaluate("10.126.126.2", &members, Some("10.126.126.1"), now), None);
+    }
+
+    #[test]
+    fn test_set_creator_virtual_ip() {
+        let mut manager = LobbyManager::new();
+        assert!(manager.set_creator_virtual_ip("10.126.126.2".to_string()).is_none());
+
+        manager.current_lobby = Some(lobby_with_ips("10.126.126.2", "10.126.126.1"));
+        assert!(!manager.is_creator());
+        let lobby = manager.set_creator_virtual_ip("10.126.126.2".to_string()).unwrap();
+        assert_eq!(lobby.creator_virtual_ip, "10.126.126.2");
+        assert!(manager.is_creator());
+    }
+
+    #[test]
+    fn test_display_name_registry_dedups_in_join_order() {
+        let mut names = DisplayNameRegistry::default();
+        assert_eq!(names.assign("a", "Steve"), None);
+        assert_eq!(names.assign("b", " steve "), Some("#2".to_string()));
+        assert_eq!(names.assign("c", "Steve"), Some("#3".to_string()));
+        assert_eq!(names.assign("d", "Alex"), None);
+        // 重复发现同一玩家保持原后缀
+        assert_eq!(names.assign("b", "Steve"), Some("#2".to_string()));
+
+        // 离开后序号释放，新来的重名玩家补上空位
+        names.remove("b");
+        assert_eq!(names.assign("e", "Steve"), Some("#2".to_string()));
+        assert_eq!(names.suffix_of("c"), Some("#3".to_string()));
+
+        // 改名后按新昵称重新分配
+        assert_eq!(names.assign("c", "Alex"), Some("#2".to_string()));
+        names.clear();
+        assert_eq!(names.assign("e", "Steve"), None);
+    }
 
+    #[test]
+    fn test_add_player_assigns_display_suffix() {
+        let mut manager = LobbyManager::new();
+        let first = Player::new("玩家".to_string(), "10.126.126.2".to_string());
+        let second = Player::new("玩家".to_string(), "10.126.126.3".to_string());
+        let second_id = second.id.clone();
+        manager.add_player(first);
+        manager.add_player(second);
+
+        let suffixes: Vec<_> = manager.get_players().into_iter().map(|p| p.display_suffix).collect();
+        assert!(suffixes.contains(&None));
+        assert!(suffixes.contains(&Some("#2".to_string())));
+        // P2P 信令共用同一张表
+        assert_eq!(manager.display_names().lock().suffix_of(&second_id), Some("#2".to_string()));
+    }
+}
@@ -0,0 +1,198 @@
+// 应用日志文件
+//
+// 应用日志（含 EasyTier 的 stdout / stderr 转发）写入用户数据目录下的 mctier.log，
+// 单个文件超过上限后依次轮转为 mctier.1.log ~ mctier.N.log，最旧的直接删除，总大小有上限。
+// 日志级别可在运行时调整，并保存到配置中，下次启动时恢复。
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// 当前日志文件名
+pub const LOG_FILE_NAME: &str = "mctier.log";
+/// 单个日志文件大小上限（字节）
+pub const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// 保留的历史日志文件数，日志总大小不超过 (MAX_LOG_BACKUPS + 1) * MAX_LOG_FILE_BYTES
+pub const MAX_LOG_BACKUPS: usize = 4;
+
+/// 第三方库的日志即使调到 debug / trace 也只保留 info 及以上，避免刷屏
+const NOISY_MODULES: &[&str] = &["hyper", "hyper_util", "h2", "reqwest", "rustls", "tungstenite", "tokio_tungstenite", "mio", "tao", "wry"];
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// 日志目录：%LOCALAPPDATA%/MCTier（无法获取时为当前目录）
+pub fn log_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .map(|d| d.join("MCTier"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// 当前日志文件路径
+pub fn log_file_path() -> PathBuf {
+    log_dir().join(LOG_FILE_NAME)
+}
+
+/// 第 `index` 个历史日志文件（1 为最新）
+fn backup_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("mctier.{}.log", index))
+}
+
+/// 按大小轮转的日志文件写入器
+pub struct RotatingFileWriter {
+    dir: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    backups: usize,
+}
+
+impl RotatingFileWriter {
+    /// 打开（追加）日志目录下的当前日志文件
+    ///
+    /// # 参数
+    /// * `dir` - 日志目录
+    /// * `max_bytes` - 单个文件大小上限
+    /// * `backups` - 保留的历史文件数
+    pub fn open(dir: &Path, max_bytes: u64, backups: usize) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = Self::open_current(dir)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            written,
+            max_bytes,
+            backups,
+        })
+    }
+
+    fn open_current(dir: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE_NAME))
+    }
+
+    /// 当前文件依次后移为历史文件，超出保留数的删除
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let current = self.dir.join(LOG_FILE_NAME);
+        if self.backups == 0 {
+            std::fs::remove_file(&current)?;
+        } else {
+            let _ = std::fs::remove_file(backup_path(&self.dir, self.backups));
+            for index in (1..self.backups).rev() {
+                let from = backup_path(&self.dir, index);
+                if from.exists() {
+                    std::fs::rename(&from, backup_path(&self.dir, index + 1))?;
+                }
+            }
+            std::fs::rename(&current, backup_path(&self.dir, 1))?;
+        }
+        self.file = Self::open_current(&self.dir)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            // 轮转失败时继续写当前文件，不丢日志
+            if let Err(e) = self.rotate() {
+                eprintln!("日志文件轮转失败: {}", e);
+            }
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 初始化日志：写入轮转日志文件，默认 info 级别
+///
+/// 底层过滤器放开到 trace，实际级别由 `log::set_max_level` 控制，以便运行时调整。
+///
+/// # 返回
+/// 当前日志文件路径
+pub fn init_logger() -> PathBuf {
+    let dir = log_dir();
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.filter_level(log::LevelFilter::Trace).format_timestamp_millis();
+    for module in NOISY_MODULES {
+        builder.filter_module(module, log::LevelFilter::Info);
+    }
+    match RotatingFileWriter::open(&dir, MAX_LOG_FILE_BYTES, MAX_LOG_BACKUPS) {
+        Ok(writer) => {
+            builder.target(env_logger::Target::Pipe(Box::new(writer)));
+        }
+        Err(e) => eprintln!("无法创建日志文件，日志仅输出到控制台: {}", e),
+    }
+    builder.init();
+    set_log_level(LogLevel::default());
+    dir.join(LOG_FILE_NAME)
+}
+
+/// 运行时调整日志级别
+pub fn set_log_level(level: LogLevel) {
+    log::set_max_level(level.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotating_writer_limits_total_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let mut writer = RotatingFileWriter::open(dir, 100, 2).unwrap();
+        for i in 0..20 {
+            writer.write_all(format!("第 {:02} 行日志内容，用于测试轮转。\n", i).as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert!(dir.join(LOG_FILE_NAME).exists());
+        assert!(backup_path(dir, 1).exists());
+        assert!(backup_path(dir, 2).exists());
+        assert!(!backup_path(dir, 3).exists());
+        for entry in std::fs::read_dir(dir).unwrap() {
+            assert!(entry.unwrap().metadata().unwrap().len() <= 100);
+        }
+        // 最新的内容在当前文件，较旧的在 .1
+        let current = std::fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap();
+        assert!(current.contains("第 19 行"));
+        let previous = std::fs::read_to_string(backup_path(dir, 1)).unwrap();
+        assert!(!previous.contains("第 19 行"));
+
+        // 重新打开时接着已有大小计数
+        let reopened = RotatingFileWriter::open(dir, 100, 2).unwrap();
+        assert_eq!(reopened.written, current.len() as u64);
+    }
+}
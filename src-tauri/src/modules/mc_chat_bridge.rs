@@ -0,0 +1,486 @@
+// Minecraft 聊天互通（默认关闭）
+//
+// 1. MCTier → 游戏：订阅聊天服务的消息广播，把文本消息通过 RCON 的 say 命令发到 MC 服务器。
+// 2. 游戏 → MCTier：轮询 MC 服务器日志（logs/latest.log），解析 "<玩家> 消息" 形式的聊天行，
+//    作为本地消息加入聊天服务，其他玩家拉取消息时即可看到。
+//
+// 从游戏导入的消息使用固定的 player_id，转发时会跳过，避免消息在两边来回回环。
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tauri::State;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::modules::chat_service::{ChatMessage, ChatService, MessageType};
+use crate::modules::error::CommandError;
+use crate::modules::tauri_commands::AppState;
+
+/// 未配置 RCON 地址时使用的默认地址
+pub const DEFAULT_RCON_ADDRESS: &str = "127.0.0.1:25575";
+/// 从游戏导入的消息使用的发送者 ID
+pub const MC_BRIDGE_PLAYER_ID: &str = "minecraft";
+/// say 命令中消息内容的最大字符数（MC 聊天命令总长度上限为 256）
+const MAX_SAY_CONTENT_CHARS: usize = 200;
+/// 日志轮询间隔
+const LOG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// RCON 连接与读写超时
+const RCON_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RCON_TYPE_AUTH: i32 = 3;
+const RCON_TYPE_AUTH_RESPONSE: i32 = 2;
+const RCON_TYPE_COMMAND: i32 = 2;
+
+/// 最小化的 Source RCON 客户端（Minecraft 服务器使用同一协议）
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    /// 连接并认证
+    ///
+    /// # 参数
+    /// * `address` - RCON 地址（host:port）
+    /// * `password` - RCON 密码
+    pub async fn connect(address: &str, password: &str) -> Result<Self, String> {
+        let stream = tokio::time::timeout(RCON_TIMEOUT, TcpStream::connect(address))
+            .await
+            .map_err(|_| format!("连接 RCON 超时: {}", address))?
+            .map_err(|e| format!("连接 RCON 失败 ({}): {}", address, e))?;
+
+        let mut client = Self { stream, next_id: 1 };
+        let id = client.send_packet(RCON_TYPE_AUTH, password).await?;
+
+        // 部分服务端会先回一个空的 RESPONSE_VALUE，再回 AUTH_RESPONSE
+        loop {
+            let (resp_id, resp_type, _) = client.read_packet().await?;
+            if resp_type != RCON_TYPE_AUTH_RESPONSE {
+                continue;
+            }
+            if resp_id == -1 || resp_id != id {
+                return Err("RCON 认证失败，请检查密码".to_string());
+            }
+            return Ok(client);
+        }
+    }
+
+    /// 执行一条命令并返回服务端响应
+    pub async fn command(&mut self, command: &str) -> Result<String, String> {
+        let id = self.send_packet(RCON_TYPE_COMMAND, command).await?;
+        loop {
+            let (resp_id, _, body) = self.read_packet().await?;
+            if resp_id == id {
+                return Ok(body);
+            }
+        }
+    }
+
+    async fn send_packet(&mut self, packet_type: i32, body: &str) -> Result<i32, String> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+
+        let payload = encode_packet(id, packet_type, body);
+        tokio::time::timeout(RCON_TIMEOUT, self.stream.write_all(&payload))
+            .await
+            .map_err(|_| "RCON 写入超时".to_string())?
+            .map_err(|e| format!("RCON 写入失败: {}", e))?;
+        Ok(id)
+    }
+
+    async fn read_packet(&mut self) -> Result<(i32, i32, String), String> {
+        tokio::time::timeout(RCON_TIMEOUT, read_packet(&mut self.stream))
+            .await
+            .map_err(|_| "RCON 读取超时".to_string())?
+    }
+}
+
+/// 编码 RCON 数据包：长度 + ID + 类型 + 内容 + 两个 0 字节（均为小端）
+fn encode_packet(id: i32, packet_type: i32, body: &str) -> Vec<u8> {
+    let body = body.as_bytes();
+    let len = (4 + 4 + body.len() + 2) as i32;
+    let mut buf = Vec::with_capacity(len as usize + 4);
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&packet_type.to_le_bytes());
+    buf.extend_from_slice(body);
+    buf.extend_from_slice(&[0, 0]);
+    buf
+}
+
+/// 读取一个 RCON 数据包，返回 (ID, 类型, 内容)
+async fn read_packet<R: AsyncReadExt + Unpin>(reader: &mut R) -> Result<(i32, i32, String), String> {
+    let len = reader
+        .read_i32_le()
+        .await
+        .map_err(|e| format!("RCON 读取失败: {}", e))?;
+    if !(10..=4096 + 10).contains(&len) {
+        return Err(format!("RCON 数据包长度异常: {}", len));
+    }
+
+    let mut buf = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut buf)
+        .await
+        .map_err(|e| format!("RCON 读取失败: {}", e))?;
+
+    let id = i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let packet_type = i32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    let body = String::from_utf8_lossy(&buf[8..buf.len() - 2]).to_string();
+    Ok((id, packet_type, body))
+}
+
+/// 清理将要发到游戏内的文本：去掉换行和 § 颜色代码
+fn sanitize_for_game(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // § 后紧跟一个格式代码字符，一并去掉
+            '§' => {
+                chars.next();
+            }
+            '\r' | '\n' | '\t' => out.push(' '),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out.trim().to_string()
+}
+
+/// 把 MCTier 聊天消息格式化为游戏内的 say 命令
+///
+/// 内容为空时返回 None；过长的内容会被截断并追加省略号
+pub fn format_say_command(player_name: &str, content: &str) -> Option<String> {
+    let content = sanitize_for_game(content);
+    if content.is_empty() {
+        return None;
+    }
+    let name = sanitize_for_game(player_name);
+    let name = if name.is_empty() { "玩家".to_string() } else { name };
+
+    let content = if content.chars().count() > MAX_SAY_CONTENT_CHARS {
+        let truncated: String = content.chars().take(MAX_SAY_CONTENT_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        content
+    };
+
+    Some(format!("say [MCTier] {}: {}", name, content))
+}
+
+/// 判断聊天消息是否需要转发到游戏内
+///
+/// 只转发文本消息，并跳过从游戏导入的消息以避免回环
+pub fn should_forward_to_game(message: &ChatMessage) -> bool {
+    message.message_type == MessageType::Text && message.player_id != MC_BRIDGE_PLAYER_ID
+}
+
+/// 解析 MC 服务器日志中的聊天行，返回 (玩家名, 消息)
+///
+/// 兼容以下常见格式：
+/// * `[12:34:56] [Server thread/INFO]: <Steve> hello`
+/// * `[12:34:56 INFO]: <Steve> hello`（Paper/Spigot）
+/// * `[12:34:56] [Server thread/INFO]: [Not Secure] <Steve> hello`（1.19+ 未签名消息）
+pub fn parse_log_chat_line(line: &str) -> Option<(String, String)> {
+    let (_, rest) = line.split_once("]: ")?;
+    let rest = rest.strip_prefix("[Not Secure] ").unwrap_or(rest);
+    let rest = rest.strip_prefix('<')?;
+    let (name, message) = rest.split_once("> ")?;
+
+    let valid_name = !name.is_empty()
+        && name.chars().count() <= 16
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let message = message.trim();
+    if !valid_name || message.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), message.to_string()))
+}
+
+/// 把游戏内聊天构造成 MCTier 聊天消息
+fn game_chat_message(name: &str, content: &str) -> ChatMessage {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    ChatMessage {
+        id: ChatMessage::new_id(&format!("{}-{}", MC_BRIDGE_PLAYER_ID, name)),
+        player_id: MC_BRIDGE_PLAYER_ID.to_string(),
+        player_name: format!("[MC] {}", name),
+        content: content.to_string(),
+        message_type: MessageType::Text,
+        timestamp: now.as_secs(),
+        image_data: None,
+        seq: 0,
+        reply_to: None,
+        mentions: Vec::new(),
+    }
+}
+
+/// 转发任务：把聊天广播中的消息通过 RCON 发到游戏内
+async fn forward_loop(chat_service: Arc<tokio::sync::Mutex<ChatService>>, address: String, password: String) {
+    let mut rx = chat_service.lock().await.subscribe();
+    let mut client: Option<RconClient> = None;
+
+    loop {
+        let message = match rx.recv().await {
+            Ok(m) => m,
+            Err(RecvError::Lagged(n)) => {
+                log::warn!("⚠️ [McChatBridge] 消息积压，跳过 {} 条", n);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if !should_forward_to_game(&message) {
+            continue;
+        }
+        let Some(command) = format_say_command(&message.player_name, &message.content) else {
+            continue;
+        };
+
+        // 连接断开后在下一条消息时重连，失败只记录日志，不影响聊天本身
+        for attempt in 0..2 {
+            if client.is_none() {
+                match RconClient::connect(&address, &password).await {
+                    Ok(c) => client = Some(c),
+                    Err(e) => {
+                        log::warn!("⚠️ [McChatBridge] {}", e);
+                        break;
+                    }
+                }
+            }
+            if let Some(c) = client.as_mut() {
+                match c.command(&command).await {
+                    Ok(_) => break,
+                    Err(e) => {
+                        log::warn!("⚠️ [McChatBridge] 转发到游戏失败 (第{}次): {}", attempt + 1, e);
+                        client = None;
+                    }
+                }
+            }
+        }
+    }
+    log::info!("🛑 [McChatBridge] 转发任务已结束");
+}
+
+/// 导入任务：轮询 MC 日志，把新增的聊天行加入 MCTier 聊天
+async fn import_loop(chat_service: Arc<tokio::sync::Mutex<ChatService>>, log_path: String) {
+    // 从当前文件末尾开始，不导入历史聊天
+    let mut offset = tokio::fs::metadata(&log_path).await.map(|m| m.len()).unwrap_or(0);
+    let mut pending = String::new();
+
+    loop {
+        tokio::time::sleep(LOG_POLL_INTERVAL).await;
+
+        let len = match tokio::fs::metadata(&log_path).await {
+            Ok(m) => m.len(),
+            Err(_) => continue,
+        };
+        // 服务器重启时 latest.log 会被轮转，重新从头读取
+        if len < offset {
+            offset = 0;
+            pending.clear();
+        }
+        if len == offset {
+            continue;
+        }
+
+        let mut file = match tokio::fs::File::open(&log_path).await {
+            Ok(f) => f,
+            Err(e) => {
+                log::warn!("⚠️ [McChatBridge] 打开日志失败: {}", e);
+                continue;
+            }
+        };
+        if file.seek(std::io::SeekFrom::Start(offset)).await.is_err() {
+            continue;
+        }
+        let mut buf = Vec::new();
+        if file.read_to_end(&mut buf).await.is_err() {
+            continue;
+        }
+        offset += buf.len() as u64;
+        pending.push_str(&String::from_utf8_lossy(&buf));
+
+        // 最后一行可能还没写完，留到下一轮
+        let complete = match pending.rfind('\n') {
+            Some(pos) => pending.drain(..=pos).collect::<String>(),
+            None => continue,
+        };
+        for line in complete.lines() {
+            if let Some((name, content)) = parse_log_chat_line(line) {
+                log::info!("🎮 [McChatBridge] 导入游戏内聊天: {} - {}", name, content);
+                chat_service.lock().await.add_local_message(game_chat_message(&name, &content));
+            }
+        }
+    }
+}
+
+static BRIDGE_TASKS: OnceLock<Mutex<Vec<JoinHandle<()>>>> = OnceLock::new();
+
+fn bridge_tasks() -> &'static Mutex<Vec<JoinHandle<()>>> {
+    BRIDGE_TASKS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// 按配置启动 Minecraft 聊天互通
+#[tauri::command]
+pub async fn start_mc_chat_bridge(state: State<'_, AppState>) -> Result<(), CommandError> {
+    let core = state.core.lock().await;
+    let config = core
+        .get_config_manager()
+        .lock()
+        .await
+        .get_config()
+        .mc_chat_bridge
+        .clone()
+        .unwrap_or_default();
+    let chat_service = core.get_chat_service();
+    drop(core);
+
+    if !config.enabled {
+        return Err(CommandError::validation("未启用 Minecraft 聊天互通"));
+    }
+    let password = config.rcon_password.unwrap_or_default();
+    if password.is_empty() {
+        return Err(CommandError::validation("请先设置 RCON 密码"));
+    }
+    let address = config
+        .rcon_address
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_RCON_ADDRESS.to_string());
+
+    // 先验证一次 RCON，配置错误时直接返回给前端
+    RconClient::connect(&address, &password).await.map_err(CommandError::network)?;
+
+    stop_mc_chat_bridge()?;
+    let mut tasks = bridge_tasks().lock().map_err(|_| "锁失败".to_string())?;
+    log::info!("🎮 [McChatBridge] 启动聊天互通，RCON: {}", address);
+    tasks.push(tokio::spawn(forward_loop(chat_service.clone(), address, password)));
+    match config.log_path.filter(|p| !p.trim().is_empty()) {
+        Some(log_path) => {
+            log::info!("🎮 [McChatBridge] 监听服务器日志: {}", log_path);
+            tasks.push(tokio::spawn(import_loop(chat_service, log_path)));
+        }
+        None => log::info!("📝 [McChatBridge] 未设置日志路径，不导入游戏内聊天"),
+    }
+    Ok(())
+}
+
+/// 停止 Minecraft 聊天互通
+#[tauri::command]
+pub fn stop_mc_chat_bridge() -> Result<(), CommandError> {
+    let mut tasks = bridge_tasks().lock().map_err(|_| "锁失败".to_string())?;
+    if !tasks.is_empty() {
+        log::info!("🛑 [McChatBridge] 停止聊天互通");
+    }
+    for task in tasks.drain(..) {
+        task.abort();
+    }
+    Ok(())
+}
+
+/// 测试 RCON 连接，成功时返回 list 命令的结果
+#[tauri::command]
+pub async fn test_mc_rcon(address: String, password: String) -> Result<String, CommandError> {
+    let mut client = RconClient::connect(&address, &password).await.map_err(CommandError::network)?;
+    client.command("list").await.map_err(CommandError::network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 启动一个只处理一次连接的模拟 RCON 服务器，返回地址和收到的命令
+    async fn spawn_mock_rcon(password: &'static str) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut commands = Vec::new();
+            while let Ok((id, packet_type, body)) = read_packet(&mut socket).await {
+                if packet_type == RCON_TYPE_AUTH {
+                    let resp_id = if body == password { id } else { -1 };
+                    socket.write_all(&encode_packet(id, 0, "")).await.unwrap();
+                    socket.write_all(&encode_packet(resp_id, RCON_TYPE_AUTH_RESPONSE, "")).await.unwrap();
+                } else {
+                    commands.push(body);
+                    socket.write_all(&encode_packet(id, 0, "ok")).await.unwrap();
+                }
+            }
+            commands
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_format_say_command() {
+        assert_eq!(
+            format_say_command("Alice", "大家好"),
+            Some("say [MCTier] Alice: 大家好".to_string())
+        );
+        // 换行和颜色代码被清理
+        assert_eq!(
+            format_say_command("Bob", "§c红色\n第二行"),
+            Some("say [MCTier] Bob: 红色 第二行".to_string())
+        );
+        assert_eq!(format_say_command("Bob", "  \n "), None);
+
+        let long = "a".repeat(MAX_SAY_CONTENT_CHARS + 50);
+        let command = format_say_command("Carol", &long).unwrap();
+        assert!(command.ends_with('…'));
+        assert_eq!(command.chars().count(), "say [MCTier] Carol: ".chars().count() + MAX_SAY_CONTENT_CHARS + 1);
+    }
+
+    #[test]
+    fn test_should_forward_to_game() {
+        let mut message = game_chat_message("Steve", "hi");
+        assert!(!should_forward_to_game(&message));
+
+        message.player_id = "player-1".to_string();
+        assert!(should_forward_to_game(&message));
+
+        message.message_type = MessageType::Image;
+        assert!(!should_forward_to_game(&message));
+    }
+
+    #[test]
+    fn test_parse_log_chat_line() {
+        assert_eq!(
+            parse_log_chat_line("[12:34:56] [Server thread/INFO]: <Steve> hello world"),
+            Some(("Steve".to_string(), "hello world".to_string()))
+        );
+        assert_eq!(
+            parse_log_chat_line("[12:34:56 INFO]: <Alex_01> 你好"),
+            Some(("Alex_01".to_string(), "你好".to_string()))
+        );
+        assert_eq!(
+            parse_log_chat_line("[12:34:56] [Server thread/INFO]: [Not Secure] <Steve> hi"),
+            Some(("Steve".to_string(), "hi".to_string()))
+        );
+        // 非聊天行与 say 命令回显不导入，避免回环
+        assert_eq!(parse_log_chat_line("[12:34:56] [Server thread/INFO]: Steve joined the game"), None);
+        assert_eq!(parse_log_chat_line("[12:34:56] [Server thread/INFO]: [Server] [MCTier] Alice: hi"), None);
+    }
+
+    #[tokio::test]
+    async fn test_rcon_forwards_formatted_say_command() {
+        let (address, server) = spawn_mock_rcon("secret").await;
+
+        let mut client = RconClient::connect(&address, "secret").await.unwrap();
+        let command = format_say_command("Alice", "一起挖矿\n吗").unwrap();
+        assert_eq!(client.command(&command).await.unwrap(), "ok");
+        drop(client);
+
+        let received = server.await.unwrap();
+        assert_eq!(received, vec!["say [MCTier] Alice: 一起挖矿 吗".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rcon_wrong_password() {
+        let (address, _server) = spawn_mock_rcon("secret").await;
+        let result = RconClient::connect(&address, "wrong").await;
+        assert!(result.is_err());
+    }
+}
@@ -9,6 +9,9 @@
 // 2. 周期性向组播组 224.0.2.60:4445 发送 Minecraft LAN 公告 "[MOTD]描述[/MOTD][AD]代理端口[/AD]"，
 //    发送套接字绑定 127.0.0.1，使本机 Minecraft 读取到的服务器地址为 127.0.0.1:代理端口。
 // 3. 本机 Minecraft 在「局域网」列表看到该世界，点击即连到 127.0.0.1:代理端口 → 代理转发到房主。
+//
+// 待广播的世界有两个来源：前端扫描（SLP）得到的列表，以及其他成员通过 P2P 信令宣告的
+// 局域网世界（见 mc_lan_detect）。两者合并后统一维护代理，互不覆盖。
 
 use std::collections::HashMap;
 use std::io::{Read, Write};
@@ -18,6 +21,8 @@ use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
 use std::time::Duration;
 
+use crate::modules::error::CommandError;
+
 /// 前端传入的待广播服务器
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct McServer {
@@ -37,6 +42,10 @@ struct Bridge {
     running: bool,
     /// key = "ip:port"
     proxies: HashMap<String, ProxyEntry>,
+    /// 前端扫描得到的服务器，key = "ip:port"
+    scanned: HashMap<String, McServer>,
+    /// 其他成员通过信令宣告的局域网世界，key = 玩家 ID
+    announced: HashMap<String, McServer>,
     /// 公告线程代次，stop 或重置时自增以让旧线程退出
     emit_gen: u64,
 }
@@ -50,6 +59,8 @@ fn bridge() -> &'static Mutex<Bridge> {
         Mutex::new(Bridge {
             running: false,
             proxies: HashMap::new(),
+            scanned: HashMap::new(),
+            announced: HashMap::new(),
             emit_gen: 0,
         })
     })
@@ -178,19 +189,11 @@ fn ensure_emit_thread() {
     });
 }
 
-/// 设置/更新要在本机 Minecraft 局域网列表中显示的服务器集合
-#[tauri::command]
-pub fn start_mc_lan_broadcast(servers: Vec<McServer>) -> Result<(), String> {
-    let mut b = bridge().lock().map_err(|_| "锁失败".to_string())?;
-    b.running = true;
-
-    // 期望的 key 集合
-    let mut wanted: HashMap<String, McServer> = HashMap::new();
-    for s in servers {
-        if s.ip.trim().is_empty() || s.port == 0 {
-            continue;
-        }
-        wanted.insert(format!("{}:{}", s.ip, s.port), s);
+/// 按两个来源的并集增删代理，并更新已有代理的 motd
+fn sync_proxies(b: &mut Bridge) {
+    let mut wanted: HashMap<String, McServer> = b.scanned.clone();
+    for s in b.announced.values() {
+        wanted.insert(format!("{}:{}", s.ip, s.port), s.clone());
     }
 
     // 移除不再需要的代理
@@ -224,21 +227,82 @@ pub fn start_mc_lan_broadcast(servers: Vec<McServer>) -> Result<(), String> {
             );
         }
     }
+    b.running = !b.proxies.is_empty();
+}
+
+/// 端口是否为本桥的本地代理端口（本机公告的世界，检测本机局域网世界时需排除）
+pub fn is_proxy_port(port: u16) -> bool {
+    bridge()
+        .lock()
+        .map(|b| b.proxies.values().any(|p| p.proxy_port == port))
+        .unwrap_or(false)
+}
+
+/// 设置 / 移除某个成员宣告的局域网世界（server 为 None 表示该成员已关闭世界）
+pub fn set_announced_world(player_id: &str, server: Option<McServer>) {
+    let Ok(mut b) = bridge().lock() else {
+        return;
+    };
+    let changed = match server {
+        Some(s) if s.ip.trim().is_empty() || s.port == 0 => false,
+        Some(s) => {
+            let changed = b
+                .announced
+                .get(player_id)
+                .is_none_or(|old| old.ip != s.ip || old.port != s.port || old.motd != s.motd);
+            b.announced.insert(player_id.to_string(), s);
+            changed
+        }
+        None => b.announced.remove(player_id).is_some(),
+    };
+    if !changed {
+        return;
+    }
+    sync_proxies(&mut b);
+    drop(b);
+    ensure_emit_thread();
+}
+
+/// 清空所有成员宣告的局域网世界（退出大厅时调用）
+pub fn clear_announced_worlds() {
+    let Ok(mut b) = bridge().lock() else {
+        return;
+    };
+    if b.announced.is_empty() {
+        return;
+    }
+    b.announced.clear();
+    sync_proxies(&mut b);
+}
+
+/// 设置/更新要在本机 Minecraft 局域网列表中显示的服务器集合
+#[tauri::command]
+pub fn start_mc_lan_broadcast(servers: Vec<McServer>) -> Result<(), CommandError> {
+    let mut b = bridge().lock().map_err(|_| "锁失败".to_string())?;
+
+    b.scanned.clear();
+    for s in servers {
+        if s.ip.trim().is_empty() || s.port == 0 {
+            continue;
+        }
+        b.scanned.insert(format!("{}:{}", s.ip, s.port), s);
+    }
+    sync_proxies(&mut b);
 
     drop(b);
     ensure_emit_thread();
     Ok(())
 }
 
-/// 停止局域网中继：关闭所有代理与公告
+/// 停止前端扫描结果的局域网中继（成员宣告的世界仍继续显示）
 #[tauri::command]
-pub fn stop_mc_lan_broadcast() -> Result<(), String> {
+pub fn stop_mc_lan_broadcast() -> Result<(), CommandError> {
     let mut b = bridge().lock().map_err(|_| "锁失败".to_string())?;
-    b.running = false;
-    for (_, p) in b.proxies.drain() {
-        p.alive.store(false, Ordering::Relaxed);
+    b.scanned.clear();
+    sync_proxies(&mut b);
+    if !b.running {
+        // 让公告线程退出
+        EMIT_GEN.fetch_add(1, Ordering::SeqCst);
     }
-    // 让公告线程退出
-    EMIT_GEN.fetch_add(1, Ordering::SeqCst);
     Ok(())
 }
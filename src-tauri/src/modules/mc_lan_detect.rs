@@ -0,0 +1,187 @@
+// Minecraft 局域网世界检测
+//
+// 本机 Minecraft "对局域网开放"后端口是随机的，并约每 1.5 秒向组播组 224.0.2.60:4445 发送
+// "[MOTD]世界名[/MOTD][AD]端口[/AD]" 公告。这里监听该组播，识别出本机开放的世界后
+// 通过 P2P 信令宣告给大厅其他成员，对方由 mc_lan_bridge 在本地重播公告，
+// 让 Minecraft 客户端直接在多人游戏列表里看到这个世界，无需手动输入端口。
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::{Duration, Instant};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// Minecraft 局域网公告组播地址
+pub const MC_LAN_MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 2, 60);
+/// Minecraft 局域网公告端口
+pub const MC_LAN_PORT: u16 = 4445;
+/// 超过该时间没有收到公告，视为本机世界已关闭
+pub const LAN_WORLD_EXPIRE: Duration = Duration::from_secs(10);
+/// 世界开放期间重复宣告的间隔（信令走 UDP，可能丢包；后加入的成员也需要收到）
+pub const LAN_WORLD_REANNOUNCE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 一条 Minecraft 局域网公告
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanAnnouncement {
+    /// 世界描述（MOTD）
+    pub motd: String,
+    /// 世界监听端口
+    pub port: u16,
+}
+
+/// 取出 `[tag]...[/tag]` 之间的内容
+fn tagged<'a>(text: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("[{}]", tag);
+    let close = format!("[/{}]", tag);
+    let start = text.find(&open)? + open.len();
+    let end = start + text[start..].find(&close)?;
+    Some(&text[start..end])
+}
+
+/// 解析 Minecraft 局域网公告 `[MOTD]描述[/MOTD][AD]端口[/AD]`
+///
+/// 部分模组会在 AD 中写 `主机:端口`，这里只取端口部分。
+pub fn parse_lan_announcement(data: &[u8]) -> Option<LanAnnouncement> {
+    let text = String::from_utf8_lossy(data);
+    let motd = tagged(&text, "MOTD")?.trim().to_string();
+    let ad = tagged(&text, "AD")?.trim();
+    let port_text = ad.rsplit(':').next().unwrap_or(ad);
+    let port = port_text.trim().parse::<u16>().ok().filter(|p| *p != 0)?;
+    Some(LanAnnouncement { motd, port })
+}
+
+/// 本机局域网世界的状态变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LanWorldChange {
+    /// 世界开放（或需要重复宣告）
+    Opened(LanAnnouncement),
+    /// 世界已关闭
+    Closed,
+}
+
+/// 跟踪本机开放的局域网世界，决定何时宣告
+#[derive(Debug, Default)]
+pub struct LanWorldTracker {
+    current: Option<LanAnnouncement>,
+    last_seen: Option<Instant>,
+    last_announced: Option<Instant>,
+}
+
+impl LanWorldTracker {
+    /// 创建空的跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前开放的世界
+    pub fn current(&self) -> Option<&LanAnnouncement> {
+        self.current.as_ref()
+    }
+
+    /// 记录一条已确认属于本机的公告，世界首次出现或端口 / 描述变化时返回需要宣告的变化
+    pub fn observe(&mut self, announcement: LanAnnouncement, now: Instant) -> Option<LanWorldChange> {
+        self.last_seen = Some(now);
+        if self.current.as_ref() == Some(&announcement) {
+            return None;
+        }
+        self.current = Some(announcement.clone());
+        self.last_announced = Some(now);
+        Some(LanWorldChange::Opened(announcement))
+    }
+
+    /// 定期检查：公告超时视为世界关闭，开放期间按间隔重复宣告
+    pub fn tick(&mut self, now: Instant) -> Option<LanWorldChange> {
+        let current = self.current.clone()?;
+        if self.last_seen.is_some_and(|t| now.duration_since(t) >= LAN_WORLD_EXPIRE) {
+            self.current = None;
+            self.last_seen = None;
+            self.last_announced = None;
+            return Some(LanWorldChange::Closed);
+        }
+        if self.last_announced.is_some_and(|t| now.duration_since(t) >= LAN_WORLD_REANNOUNCE_INTERVAL) {
+            self.last_announced = Some(now);
+            return Some(LanWorldChange::Opened(current));
+        }
+        None
+    }
+}
+
+/// 绑定 4445 端口并加入 Minecraft 局域网组播组
+///
+/// 开启地址复用，与本机 Minecraft 客户端（同样监听 4445）共存。
+pub fn bind_lan_socket() -> std::io::Result<std::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MC_LAN_PORT).into())?;
+    socket.join_multicast_v4(&MC_LAN_MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+/// 公告中的端口是否为本机开放的世界
+///
+/// 物理局域网里其他电脑的公告同样会收到，这里以"本机回环地址上该端口可连接"为准；
+/// mc_lan_bridge 为其他成员世界开的本地代理端口也会被公告，需要排除，否则会互相转播形成回环。
+pub async fn is_local_world(port: u16) -> bool {
+    if crate::modules::mc_lan_bridge::is_proxy_port(port) {
+        return false;
+    }
+    matches!(
+        tokio::time::timeout(
+            Duration::from_millis(500),
+            tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, port)),
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lan_announcement() {
+        assert_eq!(
+            parse_lan_announcement(b"[MOTD]Steve - New World[/MOTD][AD]51234[/AD]"),
+            Some(LanAnnouncement { motd: "Steve - New World".to_string(), port: 51234 })
+        );
+        assert_eq!(
+            parse_lan_announcement("[MOTD]我的世界[/MOTD][AD]192.168.1.5:25565[/AD]".as_bytes()).map(|a| a.port),
+            Some(25565)
+        );
+        assert_eq!(parse_lan_announcement(b"[MOTD]x[/MOTD][AD]0[/AD]"), None);
+        assert_eq!(parse_lan_announcement(b"[MOTD]x[/MOTD][AD]abc[/AD]"), None);
+        assert_eq!(parse_lan_announcement(b"[AD]25565[/AD]"), None);
+    }
+
+    #[test]
+    fn test_lan_world_tracker() {
+        let world = LanAnnouncement { motd: "w".to_string(), port: 51234 };
+        let t0 = Instant::now();
+        let mut tracker = LanWorldTracker::new();
+        assert_eq!(tracker.tick(t0), None);
+
+        assert_eq!(tracker.observe(world.clone(), t0), Some(LanWorldChange::Opened(world.clone())));
+        // 重复公告不重复宣告，直到重复宣告间隔到达
+        assert_eq!(tracker.observe(world.clone(), t0 + Duration::from_secs(2)), None);
+        assert_eq!(tracker.tick(t0 + Duration::from_secs(5)), None);
+        assert_eq!(tracker.observe(world.clone(), t0 + Duration::from_secs(14)), None);
+        assert_eq!(
+            tracker.tick(t0 + Duration::from_secs(15)),
+            Some(LanWorldChange::Opened(world.clone()))
+        );
+
+        // 换了端口立即宣告
+        let reopened = LanAnnouncement { motd: "w".to_string(), port: 40000 };
+        assert_eq!(
+            tracker.observe(reopened.clone(), t0 + Duration::from_secs(16)),
+            Some(LanWorldChange::Opened(reopened))
+        );
+
+        // 超时未收到公告视为关闭，只通知一次
+        assert_eq!(tracker.tick(t0 + Duration::from_secs(26)), Some(LanWorldChange::Closed));
+        assert_eq!(tracker.tick(t0 + Duration::from_secs(40)), None);
+        assert!(tracker.current().is_none());
+    }
+}
@@ -246,10 +246,10 @@ pub struct PeerLatency {
     pub loss_rate: u8,
 }
 
-/// 测量到某个虚拟 IP 的延迟（通过 TCP 连接其聊天端口 14540 估算 RTT）
+/// 测量到某个虚拟 IP 的延迟（通过 TCP 连接其聊天端口估算 RTT）
 async fn measure_one(ip: &str) -> Option<u64> {
     let start = std::time::Instant::now();
-    let connect = TcpStream::connect((ip, 14540u16));
+    let connect = TcpStream::connect((ip, crate::modules::service_ports::peer_chat_port(ip)));
     match tokio::time::timeout(Duration::from_millis(800), connect).await {
         Ok(Ok(_stream)) => Some(start.elapsed().as_millis() as u64),
         // 连接被拒绝也说明主机可达（端口可能未开），仍记录 RTT
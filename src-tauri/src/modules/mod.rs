@@ -7,12 +7,27 @@ pub mod error;
 // 配置管理模块
 pub mod config_manager;
 
+// 配置文件加密模块（Windows DPAPI）
+pub mod config_crypto;
+
+// 配置 schema 导出模块（前端自动生成设置界面）
+pub mod config_schema;
+
 // 资源管理模块
 pub mod resource_manager;
 
 // 网络服务模块
 pub mod network_service;
 
+// 虚拟网络自检模块
+pub mod network_self_check;
+
+// 网络诊断模块（外部命令超时与结构化结果）
+pub mod network_diagnostics;
+
+// 虚拟网流量统计模块（easytier-cli 收发字节 + 本次大厅累计）
+pub mod traffic_stats;
+
 // 大厅管理模块
 pub mod lobby_manager;
 
@@ -25,6 +40,12 @@ pub mod voice_service;
 // P2P信令服务模块
 pub mod p2p_signaling;
 
+// P2P信令加密模块（大厅密码派生密钥）
+pub mod signaling_crypto;
+
+// P2P信令端口 UPnP 自动映射模块
+pub mod upnp_mapping;
+
 // WebSocket信令服务模块
 pub mod websocket_signaling;
 
@@ -43,14 +64,92 @@ pub mod tauri_events;
 // 高性能文件传输模块
 pub mod file_transfer;
 
+// 远程文件下载任务（暂停 / 恢复 / 续传）
+pub mod download_tasks;
+
+// 整个共享文件夹流式打包（不落地临时文件，支持 Range 续传）
+pub mod share_zip;
+
 // P2P聊天服务模块
 pub mod chat_service;
 
+// 虚拟网 HTTP 服务可选 TLS 模块（大厅密码派生证书 + 指纹校验）
+pub mod http_tls;
+
+// 虚拟网 HTTP 服务请求来源校验模块（仅同大厅 peer）
+pub mod peer_access;
+
 // Minecraft 局域网世界自动发现模块
 pub mod minecraft_discovery;
 
 // Minecraft 局域网中继桥（让虚拟网内的 MC 世界出现在本机 Minecraft 局域网列表）
 pub mod mc_lan_bridge;
 
+// Minecraft 局域网世界检测（识别本机开放的世界并通过信令宣告）
+pub mod mc_lan_detect;
+
+// Minecraft 聊天互通模块（RCON 转发 + 服务器日志导入）
+pub mod mc_chat_bridge;
+
+// 进入大厅后的自动动作序列模块
+pub mod lobby_auto_actions;
+
+// 点对点连接修复模块（手动重新打洞）
+pub mod connection_repair;
+
+// 后端语音连接（SDP offer/answer 协商，WebRTC 音频管道迁移的第一阶段）
+pub mod voice_session;
+
 // 远程控制（输入注入）模块
 pub mod remote_control;
+
+// 全局快捷键绑定模块（麦克风 / 全局静音可自定义）
+pub mod hotkeys;
+
+// 共享浏览状态模块（"某某正在浏览你的共享"）
+pub mod share_presence;
+
+// 房间健康分模块（综合延迟 / 丢包 / 连接类型）
+pub mod room_health;
+
+// 文件共享 / 聊天 HTTP 服务的统一鉴权中间件
+pub mod http_auth;
+
+// EasyTier 连接阶段识别（打洞 / 回退中继等，推送给前端）
+pub mod easytier_phase;
+
+// 玩家名 / 消息 / 共享名 / 文件名等输入的长度上限
+pub mod input_limits;
+
+// 联机会话一键巡检与自动修复（自动医生）
+pub mod auto_doctor;
+
+// 文件命令的路径白名单（限制前端可读写删除的目录）
+pub mod path_guard;
+
+// 麦克风 / 听筒静音切换后的提示音与托盘状态
+pub mod voice_feedback;
+
+// 大厅邀请链接（mctier://join?...）的生成与解析
+pub mod invite_link;
+
+// 窗口透明度（macOS / Linux 系统透明度，其它环境降级为 CSS）
+pub mod window_opacity;
+
+// P2P 信令 / 文件共享 / 聊天服务端口的统一配置与实际端口记录
+pub mod service_ports;
+
+// 玩家在线状态（在线 / 离开 / 游戏中 / 忙碌）的手动设置与自动检测
+pub mod presence;
+
+// 应用日志文件的轮转与运行时日志级别
+pub mod log_file;
+
+// P2P 打洞失败回退中继时的提示
+pub mod relay_watch;
+
+// 虚拟 IP 冲突检测与仲裁
+pub mod ip_conflict;
+
+// 系统托盘菜单与关闭时最小化到托盘
+pub mod tray;
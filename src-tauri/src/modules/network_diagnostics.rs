@@ -0,0 +1,616 @@
+// 网络诊断模块
+//
+// 诊断项需要调用 ipconfig / netsh / ping 等外部程序，在规则多或网络异常的机器上可能卡住数秒甚至更久。
+// 这里统一用 tokio 异步进程 + 超时包裹，每个诊断项返回结构化的 `DiagnosticResult`，
+// 超时时返回失败并注明「诊断超时」，同时结束仍在运行的子进程。
+
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// 虚拟网卡检查超时
+pub const ADAPTER_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// 防火墙规则检查超时（netsh show rule name=all 在规则多时较慢）
+pub const FIREWALL_CHECK_TIMEOUT: Duration = Duration::from_secs(8);
+/// ping 超时（ping 本身发 2 个包，每个最多等 1 秒）
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+/// 延迟测量发送的 ping 包数
+pub const PING_DETAILED_COUNT: u32 = 4;
+/// 延迟测量超时（4 个包，每个最多等 1 秒）
+pub const PING_DETAILED_TIMEOUT: Duration = Duration::from_secs(8);
+/// 单个端口探测超时
+pub const PORT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// P2P 信令默认 UDP 端口（端口被占用时对方可能顺延到后续端口）
+pub const DEFAULT_SIGNALING_PORT: u16 = crate::modules::service_ports::DEFAULT_SIGNALING_PORT;
+
+/// 单个诊断项的结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DiagnosticResult {
+    /// 诊断项名称
+    pub name: String,
+    /// 是否通过
+    pub success: bool,
+    /// 结果说明
+    pub detail: String,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 在超时限制内执行诊断
+///
+/// # 参数
+/// * `name` - 诊断项名称
+/// * `timeout` - 超时时间
+/// * `check` - 诊断逻辑，返回 (是否通过, 说明)；执行失败时返回错误说明
+pub async fn run_diagnostic<F>(name: &str, timeout: Duration, check: F) -> DiagnosticResult
+where
+    F: Future<Output = Result<(bool, String), String>>,
+{
+    let start = Instant::now();
+    let (success, detail) = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => (false, e),
+        Err(_) => (false, format!("诊断超时（超过 {} 秒）", timeout.as_secs())),
+    };
+    let duration_ms = start.elapsed().as_millis() as u64;
+    if success {
+        log::info!("✅ [Diagnostics] {}: {} ({}ms)", name, detail, duration_ms);
+    } else {
+        log::warn!("⚠️ [Diagnostics] {}: {} ({}ms)", name, detail, duration_ms);
+    }
+    DiagnosticResult {
+        name: name.to_string(),
+        success,
+        detail,
+        duration_ms,
+    }
+}
+
+/// 异步执行外部命令（隐藏窗口），诊断超时被取消时子进程随之结束
+async fn command_output(program: &str, args: &[&str]) -> Result<Output, String> {
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args).kill_on_drop(true);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    cmd.output()
+        .await
+        .map_err(|e| format!("执行 {} 失败: {}", program, e))
+}
+
+/// 检查虚拟网卡是否存在
+pub async fn check_virtual_adapter() -> DiagnosticResult {
+    run_diagnostic("虚拟网卡", ADAPTER_CHECK_TIMEOUT, async {
+        if !cfg!(windows) {
+            // 非 Windows 平台暂不支持
+            return Ok((true, "非 Windows 平台，跳过检查".to_string()));
+        }
+
+        // 使用 ipconfig 命令查找 EasyTier 创建的虚拟网卡
+        let output = command_output("ipconfig", &["/all"]).await?;
+        let output_str = String::from_utf8_lossy(&output.stdout);
+
+        // 查找包含 "EasyTier" 或 "WinTun" 的网卡
+        let has_adapter = output_str.contains("EasyTier")
+            || output_str.contains("WinTun")
+            || output_str.contains("wintun");
+        Ok(if has_adapter {
+            (true, "已找到 EasyTier 虚拟网卡".to_string())
+        } else {
+            (false, "未找到 EasyTier 虚拟网卡".to_string())
+        })
+    })
+    .await
+}
+
+/// 检查防火墙是否已存在 MCTier 的放行规则
+pub async fn check_firewall_rules() -> DiagnosticResult {
+    run_diagnostic("防火墙规则", FIREWALL_CHECK_TIMEOUT, async {
+        if !cfg!(windows) {
+            return Ok((true, "非 Windows 平台，跳过检查".to_string()));
+        }
+
+        // 注意：必须与 add_firewall_rules 中添加的规则名保持一致
+        let output = command_output("netsh", &["advfirewall", "firewall", "show", "rule", "name=all"]).await?;
+        let output_str = String::from_utf8_lossy(&output.stdout);
+
+        // add_firewall_rules 添加的规则名为：MCTier-in/-out、MCTier-EasyTier-in/-out
+        Ok(if output_str.contains("MCTier") {
+            (true, "已存在 MCTier 防火墙放行规则".to_string())
+        } else {
+            (false, "未找到 MCTier 防火墙放行规则".to_string())
+        })
+    })
+    .await
+}
+
+/// ping 指定虚拟 IP
+pub async fn ping_virtual_ip(ip: &str) -> DiagnosticResult {
+    run_diagnostic("Ping 虚拟 IP", PING_TIMEOUT, async {
+        let output = if cfg!(windows) {
+            command_output("ping", &["-n", "2", "-w", "1000", ip]).await?
+        } else {
+            command_output("ping", &["-c", "2", "-W", "1", ip]).await?
+        };
+        Ok(if output.status.success() {
+            (true, format!("{} 可以 ping 通", ip))
+        } else {
+            (false, format!("无法 ping 通 {}", ip))
+        })
+    })
+    .await
+}
+
+/// ping 延迟测量结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PingResult {
+    /// 是否收到至少一个回复
+    pub success: bool,
+    /// 平均延迟（毫秒），没有回复时为空
+    pub avg_latency_ms: Option<f64>,
+    /// 丢包率（百分比 0–100）
+    pub packet_loss: u8,
+    /// 最小延迟（毫秒）
+    pub min_ms: Option<f64>,
+    /// 最大延迟（毫秒）
+    pub max_ms: Option<f64>,
+}
+
+/// 从单行 ping 输出中解析回复延迟（毫秒）
+///
+/// 只解析带 TTL 的回复行，避免把 Windows 末尾的「最短 = 1ms，最长 = 2ms」统计行算进去。
+/// 不依赖「time」「时间」「Zeit」等本地化字段名，而是找 `=` 或 `<` 之后紧跟 `ms` 的数字：
+/// 中文 Windows 的输出是 GBK 编码，按 UTF-8 解码后汉字会变成乱码，但 `=`、数字和 `ms` 不受影响。
+/// `time<1ms` 按 1 毫秒计。
+fn parse_reply_latency(line: &str) -> Option<f64> {
+    if !line.to_ascii_lowercase().contains("ttl=") {
+        return None;
+    }
+    let bytes = line.as_bytes();
+    let mut search_from = 0;
+    while let Some(pos) = line[search_from..].find("ms") {
+        let ms_at = search_from + pos;
+        search_from = ms_at + 2;
+
+        // 向前跳过空格，取出数字
+        let mut end = ms_at;
+        while end > 0 && bytes[end - 1] == b' ' {
+            end -= 1;
+        }
+        let mut start = end;
+        while start > 0 && (bytes[start - 1].is_ascii_digit() || bytes[start - 1] == b'.') {
+            start -= 1;
+        }
+        if start == end {
+            continue;
+        }
+        // 数字前必须是 `=` 或 `<`（允许中间有空格）
+        let mut before = start;
+        while before > 0 && bytes[before - 1] == b' ' {
+            before -= 1;
+        }
+        if before > 0 && matches!(bytes[before - 1], b'=' | b'<') {
+            if let Ok(value) = line[start..end].parse::<f64>() {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+/// 根据 ping 输出统计延迟与丢包
+///
+/// # 参数
+/// * `output` - ping 命令的标准输出
+/// * `sent` - 发送的包数
+pub fn evaluate_ping_output(output: &str, sent: u32) -> PingResult {
+    let times: Vec<f64> = output.lines().filter_map(parse_reply_latency).collect();
+    let received = (times.len() as u32).min(sent);
+    let packet_loss = ((sent - received) * 100).checked_div(sent).unwrap_or(100) as u8;
+    let avg = (!times.is_empty()).then(|| times.iter().sum::<f64>() / times.len() as f64);
+    PingResult {
+        success: received > 0,
+        avg_latency_ms: avg.map(|v| (v * 10.0).round() / 10.0),
+        packet_loss,
+        min_ms: times.iter().copied().reduce(f64::min),
+        max_ms: times.iter().copied().reduce(f64::max),
+    }
+}
+
+/// ping 指定虚拟 IP 并返回延迟与丢包
+pub async fn ping_virtual_ip_detailed(ip: &str) -> PingResult {
+    let count = PING_DETAILED_COUNT.to_string();
+    let ping = async {
+        if cfg!(windows) {
+            command_output("ping", &["-n", &count, "-w", "1000", ip]).await
+        } else {
+            command_output("ping", &["-c", &count, "-W", "1", ip]).await
+        }
+    };
+    let output = match tokio::time::timeout(PING_DETAILED_TIMEOUT, ping).await {
+        Ok(Ok(output)) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Ok(Err(e)) => {
+            log::warn!("⚠️ [Diagnostics] 测量 {} 延迟失败: {}", ip, e);
+            String::new()
+        }
+        Err(_) => {
+            log::warn!("⚠️ [Diagnostics] 测量 {} 延迟超时", ip);
+            String::new()
+        }
+    };
+    evaluate_ping_output(&output, PING_DETAILED_COUNT)
+}
+
+/// 端口协议
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    Tcp,
+    Udp,
+}
+
+/// 玩家之间需要互通的服务
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerService {
+    /// 文件共享 HTTP 服务（TCP 14539）
+    FileShare,
+    /// 聊天服务（TCP 14540）
+    Chat,
+    /// P2P 信令（UDP 47777，语音与玩家发现依赖它）
+    Signaling,
+}
+
+impl PeerService {
+    /// 服务名称
+    pub fn label(self) -> &'static str {
+        match self {
+            PeerService::FileShare => "文件共享服务",
+            PeerService::Chat => "聊天服务",
+            PeerService::Signaling => "P2P 信令",
+        }
+    }
+
+    /// 服务使用的协议
+    pub fn protocol(self) -> PortProtocol {
+        match self {
+            PeerService::FileShare | PeerService::Chat => PortProtocol::Tcp,
+            PeerService::Signaling => PortProtocol::Udp,
+        }
+    }
+}
+
+/// 端口探测结果
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortStatus {
+    /// TCP 连接成功 / UDP 收到回应
+    Open,
+    /// 对方明确拒绝（端口上没有服务在监听）
+    Closed,
+    /// 超时无响应（可能被防火墙拦截；UDP 服务本身也可能不回应探测包）
+    NoResponse,
+}
+
+/// 单个端口的检查结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortCheckResult {
+    /// 服务
+    pub service: PeerService,
+    /// 端口
+    pub port: u16,
+    /// 协议
+    pub protocol: PortProtocol,
+    /// 探测结果
+    pub status: PortStatus,
+    /// 综合判断是否可达
+    pub reachable: bool,
+    /// 结果说明
+    pub detail: String,
+    /// 针对性建议（可达时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+/// TCP connect 探测
+pub async fn probe_tcp_port(ip: IpAddr, port: u16, timeout: Duration) -> PortStatus {
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect((ip, port))).await {
+        Ok(Ok(_)) => PortStatus::Open,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => PortStatus::Closed,
+        Ok(Err(e)) => {
+            log::debug!("TCP 探测 {}:{} 失败: {}", ip, port, e);
+            PortStatus::NoResponse
+        }
+        Err(_) => PortStatus::NoResponse,
+    }
+}
+
+/// UDP 探测：向端口发送一个空数据报，收到 ICMP 端口不可达即为关闭
+///
+/// 信令服务会直接丢弃无法解码的数据报，不会回应，因此"无响应"本身不代表不通，
+/// 需要结合是否收到过对方的信令来判断。
+pub async fn probe_udp_port(ip: IpAddr, port: u16, timeout: Duration) -> PortStatus {
+    let bind_addr: SocketAddr = if ip.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { (std::net::Ipv6Addr::UNSPECIFIED, 0).into() };
+    let socket = match tokio::net::UdpSocket::bind(bind_addr).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::debug!("UDP 探测绑定失败: {}", e);
+            return PortStatus::NoResponse;
+        }
+    };
+    if socket.connect((ip, port)).await.is_err() || socket.send(&[]).await.is_err() {
+        return PortStatus::NoResponse;
+    }
+    let mut buf = [0u8; 64];
+    match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+        Ok(Ok(_)) => PortStatus::Open,
+        // Linux 报告 ConnectionRefused，Windows 报告 ConnectionReset（WSAECONNRESET）
+        Ok(Err(e)) if matches!(e.kind(), std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset) => {
+            PortStatus::Closed
+        }
+        Ok(Err(_)) | Err(_) => PortStatus::NoResponse,
+    }
+}
+
+/// 根据探测结果生成端口检查结论与建议
+///
+/// # 参数
+/// * `service` - 服务
+/// * `port` - 端口
+/// * `status` - 探测结果
+/// * `signaling_seen` - 是否收到过对方的 P2P 信令（UDP 无响应时据此判断可达）
+/// * `duration_ms` - 探测耗时
+pub fn evaluate_port(service: PeerService, port: u16, status: PortStatus, signaling_seen: bool, duration_ms: u64) -> PortCheckResult {
+    let protocol = service.protocol();
+    let reachable = match status {
+        PortStatus::Open => true,
+        PortStatus::Closed => false,
+        PortStatus::NoResponse => protocol == PortProtocol::Udp && signaling_seen,
+    };
+    let label = service.label();
+    let detail = match (status, reachable) {
+        (PortStatus::Open, _) => format!("{} 端口 {} 可连接", label, port),
+        (PortStatus::NoResponse, true) => format!("{} 端口 {} 未回应探测，但已收到对方的信令", label, port),
+        (PortStatus::Closed, _) => format!("{} 端口 {} 被拒绝连接", label, port),
+        (PortStatus::NoResponse, false) => format!("{} 端口 {} 无响应", label, port),
+    };
+    let suggestion = if reachable {
+        None
+    } else {
+        Some(match (service, status) {
+            (PeerService::FileShare, PortStatus::Closed) => {
+                format!("{} 不通，对方文件共享服务未启动，请让对方重新进入大厅", port)
+            }
+            (PeerService::Chat, PortStatus::Closed) => format!("{} 不通，对方聊天服务未启动，请让对方重新进入大厅", port),
+            (PeerService::Signaling, PortStatus::Closed) => {
+                format!("{} 不通，对方 P2P 信令未启动，玩家列表与语音将不可用，请让对方重新进入大厅", port)
+            }
+            (PeerService::Signaling, _) => {
+                format!("{} 无响应且未收到对方的信令，UDP 可能被防火墙拦截，语音可能无法建立，请双方允许 MCTier 通过防火墙", port)
+            }
+            _ => format!("{} 无响应，可能被对方防火墙拦截，请对方允许 MCTier 通过防火墙", port),
+        })
+    };
+    PortCheckResult {
+        service,
+        port,
+        protocol,
+        status,
+        reachable,
+        detail,
+        suggestion,
+        duration_ms,
+    }
+}
+
+/// 探测对方某个服务的端口
+pub async fn check_service_port(ip: IpAddr, service: PeerService, port: u16, signaling_seen: bool) -> PortCheckResult {
+    let start = Instant::now();
+    let status = match service.protocol() {
+        PortProtocol::Tcp => probe_tcp_port(ip, port, PORT_PROBE_TIMEOUT).await,
+        PortProtocol::Udp => probe_udp_port(ip, port, PORT_PROBE_TIMEOUT).await,
+    };
+    let result = evaluate_port(service, port, status, signaling_seen, start.elapsed().as_millis() as u64);
+    if result.reachable {
+        log::info!("✅ [Diagnostics] {}", result.detail);
+    } else {
+        log::warn!("⚠️ [Diagnostics] {}", result.detail);
+    }
+    result
+}
+
+/// EasyTier 节点与 P2P 玩家的对齐状态
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerAlignmentStatus {
+    /// EasyTier 可见且已完成玩家握手
+    Player,
+    /// EasyTier 可见（网络层在线），但尚未通过 P2P 信令发现玩家（"能 ping 通但看不到玩家"）
+    HandshakePending,
+    /// 已发现玩家，但 EasyTier 当前路由里没有该节点（可能刚掉线）
+    SignalingOnly,
+}
+
+/// 合并视图中的一个节点
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerAlignment {
+    /// 虚拟 IP
+    pub virtual_ip: String,
+    /// 玩家 ID（已完成握手时）
+    pub player_id: Option<String>,
+    /// 玩家名称（已完成握手时）
+    pub player_name: Option<String>,
+    /// 对齐状态
+    pub status: PeerAlignmentStatus,
+}
+
+/// 按虚拟 IP 对齐 EasyTier 看到的节点与 P2P 发现的玩家，结果按虚拟 IP 排序
+///
+/// # 参数
+/// * `network_peers` - EasyTier 路由中的节点虚拟 IP
+/// * `players` - P2P 信令发现的玩家 (玩家 ID, 玩家名称, 虚拟 IP)
+pub fn align_peers(network_peers: &[String], players: &[(String, String, String)]) -> Vec<PeerAlignment> {
+    let mut result: Vec<PeerAlignment> = Vec::new();
+    for ip in network_peers {
+        if result.iter().any(|p| &p.virtual_ip == ip) {
+            continue;
+        }
+        let player = players.iter().find(|(_, _, player_ip)| player_ip == ip);
+        result.push(PeerAlignment {
+            virtual_ip: ip.clone(),
+            player_id: player.map(|p| p.0.clone()),
+            player_name: player.map(|p| p.1.clone()),
+            status: if player.is_some() {
+                PeerAlignmentStatus::Player
+            } else {
+                PeerAlignmentStatus::HandshakePending
+            },
+        });
+    }
+    for (player_id, player_name, ip) in players {
+        if !network_peers.contains(ip) {
+            result.push(PeerAlignment {
+                virtual_ip: ip.clone(),
+                player_id: Some(player_id.clone()),
+                player_name: Some(player_name.clone()),
+                status: PeerAlignmentStatus::SignalingOnly,
+            });
+        }
+    }
+    result.sort_by_key(|p| (p.virtual_ip.parse::<std::net::Ipv4Addr>().ok(), p.virtual_ip.clone()));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_peers() {
+        let network_peers = vec!["10.126.126.3".to_string(), "10.126.126.2".to_string(), "10.126.126.2".to_string()];
+        let players = vec![
+            ("p2".to_string(), "Alex".to_string(), "10.126.126.2".to_string()),
+            ("p9".to_string(), "Steve".to_string(), "10.126.126.9".to_string()),
+        ];
+
+        let aligned = align_peers(&network_peers, &players);
+        let summary: Vec<(&str, Option<&str>, PeerAlignmentStatus)> = aligned
+            .iter()
+            .map(|p| (p.virtual_ip.as_str(), p.player_name.as_deref(), p.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("10.126.126.2", Some("Alex"), PeerAlignmentStatus::Player),
+                ("10.126.126.3", None, PeerAlignmentStatus::HandshakePending),
+                ("10.126.126.9", Some("Steve"), PeerAlignmentStatus::SignalingOnly),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_ping_output() {
+        // 英文 Windows（含 time<1ms 与末尾统计行）
+        let english = "Pinging 10.126.126.2 with 32 bytes of data:\r\n\
+            Reply from 10.126.126.2: bytes=32 time=12ms TTL=64\r\n\
+            Reply from 10.126.126.2: bytes=32 time<1ms TTL=64\r\n\
+            Request timed out.\r\n\
+            Reply from 10.126.126.2: bytes=32 time=20ms TTL=64\r\n\
+            Approximate round trip times in milli-seconds:\r\n\
+            \x20   Minimum = 1ms, Maximum = 20ms, Average = 11ms\r\n";
+        let result = evaluate_ping_output(english, 4);
+        assert!(result.success);
+        assert_eq!(result.packet_loss, 25);
+        assert_eq!((result.min_ms, result.max_ms), (Some(1.0), Some(20.0)));
+        assert_eq!(result.avg_latency_ms, Some(11.0));
+
+        // 中文 Windows，以及 GBK 按 UTF-8 解码后的乱码字段名
+        let chinese = "来自 10.126.126.2 的回复: 字节=32 时间=8ms TTL=64\n\
+            \u{FFFD}\u{FFFD} 10.126.126.2 \u{FFFD}Ļظ\u{FFFD}: \u{FFFD}ֽ\u{FFFD}=32 ʱ\u{FFFD}\u{FFFD}=10ms TTL=64\n";
+        assert_eq!(evaluate_ping_output(chinese, 2).avg_latency_ms, Some(9.0));
+
+        // Linux / 法语 Windows（数字与 ms 之间有空格）
+        let linux = "64 bytes from 10.126.126.2: icmp_seq=1 ttl=64 time=0.45 ms\n\
+            Réponse de 10.126.126.2 : octets=32 temps=3 ms TTL=64\n";
+        let result = evaluate_ping_output(linux, 2);
+        assert_eq!((result.min_ms, result.max_ms, result.packet_loss), (Some(0.45), Some(3.0), 0));
+
+        // 全部超时
+        let lost = evaluate_ping_output("Request timed out.\nDestination host unreachable.\n", 4);
+        assert_eq!((lost.success, lost.packet_loss, lost.avg_latency_ms), (false, 100, None));
+    }
+
+    #[test]
+    fn test_evaluate_port() {
+        let open = evaluate_port(PeerService::FileShare, 14539, PortStatus::Open, false, 3);
+        assert!(open.reachable);
+        assert_eq!(open.protocol, PortProtocol::Tcp);
+        assert!(open.suggestion.is_none());
+
+        let chat = evaluate_port(PeerService::Chat, 14540, PortStatus::Closed, true, 3);
+        assert!(!chat.reachable);
+        assert!(chat.suggestion.unwrap().contains("14540 不通，对方聊天服务未启动"));
+
+        // UDP 无响应时以是否收到过对方信令为准
+        assert!(evaluate_port(PeerService::Signaling, 47777, PortStatus::NoResponse, true, 3).reachable);
+        let blocked = evaluate_port(PeerService::Signaling, 47777, PortStatus::NoResponse, false, 3);
+        assert!(!blocked.reachable);
+        assert!(blocked.suggestion.unwrap().contains("防火墙"));
+
+        // TCP 无响应不会因为收到信令而视为可达
+        assert!(!evaluate_port(PeerService::FileShare, 14539, PortStatus::NoResponse, true, 3).reachable);
+    }
+
+    #[tokio::test]
+    async fn test_probe_local_ports() {
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+
+        let listener = tokio::net::TcpListener::bind((localhost, 0)).await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        assert_eq!(probe_tcp_port(localhost, open_port, Duration::from_secs(1)).await, PortStatus::Open);
+        drop(listener);
+        assert_eq!(probe_tcp_port(localhost, open_port, Duration::from_secs(1)).await, PortStatus::Closed);
+
+        // 绑定但不回应的 UDP 端口无响应，关闭的 UDP 端口返回端口不可达
+        let silent = std::net::UdpSocket::bind((localhost, 0)).unwrap();
+        let udp_port = silent.local_addr().unwrap().port();
+        assert_eq!(probe_udp_port(localhost, udp_port, Duration::from_millis(200)).await, PortStatus::NoResponse);
+        drop(silent);
+        assert_eq!(probe_udp_port(localhost, udp_port, Duration::from_secs(1)).await, PortStatus::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic_result() {
+        let result = run_diagnostic("测试", Duration::from_secs(1), async { Ok((true, "正常".to_string())) }).await;
+        assert_eq!(result.name, "测试");
+        assert!(result.success);
+        assert_eq!(result.detail, "正常");
+
+        let result = run_diagnostic("测试", Duration::from_secs(1), async { Err("执行失败".to_string()) }).await;
+        assert!(!result.success);
+        assert_eq!(result.detail, "执行失败");
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic_timeout() {
+        let result = run_diagnostic("测试", Duration::from_millis(50), async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok((true, "不应到达".to_string()))
+        })
+        .await;
+
+        assert!(!result.success);
+        assert!(result.detail.contains("诊断超时"));
+        assert!(result.duration_ms < 5000);
+    }
+}
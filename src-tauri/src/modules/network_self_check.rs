@@ -0,0 +1,475 @@
+// 虚拟网络自检模块
+//
+// EasyTier 拿到虚拟 IP 后，若系统没有为虚拟网卡配好地址/路由，ping peer 会失败，
+// 但界面上看起来一切正常。这里在启动成功后做一次自检：
+// 1. 虚拟网卡是否存在，网卡上是否配置了分配到的虚拟 IP
+// 2. 路由表中是否有经过虚拟网卡、覆盖虚拟网段的路由
+// 3. 能否通过 easytier-cli 解析到至少一个 peer
+// 发现异常时向前端发送 `network-config-warning` 事件并附带处理建议。
+
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::Emitter;
+
+/// 未配置 dev_name 时 EasyTier 虚拟网卡的名称
+pub const DEFAULT_TUN_DEVICE_NAME: &str = "MCTier_Net";
+/// 拿到虚拟 IP 后等待系统完成网卡配置的时间
+const SELF_CHECK_DELAY: Duration = Duration::from_secs(5);
+/// 未解析到 peer 时的重试次数（房主刚建好大厅时可能暂时没有 peer）
+const PEER_CHECK_RETRIES: u32 = 3;
+/// peer 检查重试间隔
+const PEER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 自检发现的问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum NetworkConfigIssue {
+    /// 系统中找不到虚拟网卡
+    AdapterNotFound { adapter: String },
+    /// 虚拟网卡上没有配置分配到的虚拟 IP
+    AddressMismatch { expected: String, actual: Vec<String> },
+    /// 没有覆盖虚拟网段的路由
+    RouteMissing { subnet: String },
+    /// 解析不到任何 peer
+    NoPeerResolved,
+}
+
+impl NetworkConfigIssue {
+    /// 面向用户的问题描述
+    pub fn message(&self) -> String {
+        match self {
+            NetworkConfigIssue::AdapterNotFound { adapter } => {
+                format!("未找到虚拟网卡 {}", adapter)
+            }
+            NetworkConfigIssue::AddressMismatch { expected, actual } => {
+                if actual.is_empty() {
+                    format!("虚拟网卡上没有 IPv4 地址，期望为 {}", expected)
+                } else {
+                    format!("虚拟网卡地址为 {}，与分配的虚拟 IP {} 不一致", actual.join(", "), expected)
+                }
+            }
+            NetworkConfigIssue::RouteMissing { subnet } => {
+                format!("路由表中缺少虚拟网段 {} 的路由", subnet)
+            }
+            NetworkConfigIssue::NoPeerResolved => "暂未发现大厅中的其他玩家".to_string(),
+        }
+    }
+
+    /// 处理建议
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            NetworkConfigIssue::AdapterNotFound { .. } => {
+                "请确认以管理员身份运行，并检查杀毒软件是否拦截了 WinTun 驱动"
+            }
+            NetworkConfigIssue::AddressMismatch { .. } => {
+                "虚拟网卡地址未生效，请尝试退出大厅后重新加入，或在网络设置中禁用再启用 MCTier_Net 网卡"
+            }
+            NetworkConfigIssue::RouteMissing { .. } => {
+                "系统未为虚拟网卡添加路由，请检查是否有其他 VPN/加速器占用路由，必要时重启电脑后重试"
+            }
+            NetworkConfigIssue::NoPeerResolved => {
+                "如果你是房主且还没有人加入可忽略；否则请检查大厅名称/密码是否一致，或尝试更换节点服务器"
+            }
+        }
+    }
+}
+
+/// 自检所需的系统查询（抽象出来便于测试时 mock）
+pub trait NetworkSystemQuery {
+    /// 虚拟网卡上的 IPv4 地址（"ip/前缀长度"），网卡不存在时返回 None
+    fn adapter_addresses(&self, adapter: &str) -> Option<Vec<String>>;
+    /// 经过虚拟网卡的 IPv4 路由目标网段（"网段/前缀长度"）
+    fn adapter_routes(&self, adapter: &str) -> Vec<String>;
+    /// 当前能解析到的 peer 虚拟 IP
+    fn peer_ips(&self) -> Vec<String>;
+}
+
+/// 解析 "ip/前缀长度" 形式的 CIDR，缺省前缀视为 /32
+fn parse_cidr(cidr: &str) -> Option<(Ipv4Addr, u8)> {
+    let cidr = cidr.trim();
+    let (ip, prefix) = match cidr.split_once('/') {
+        Some((ip, prefix)) => (ip, prefix.trim().parse::<u8>().ok()?),
+        None => (cidr, 32),
+    };
+    if prefix > 32 {
+        return None;
+    }
+    Some((ip.trim().parse().ok()?, prefix))
+}
+
+/// 计算网段地址
+fn network_of(ip: Ipv4Addr, prefix: u8) -> Ipv4Addr {
+    let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+    Ipv4Addr::from(u32::from(ip) & mask)
+}
+
+/// 执行自检判定
+///
+/// # 参数
+/// * `query` - 系统查询
+/// * `adapter` - 虚拟网卡名称
+/// * `virtual_ip` - 分配到的虚拟 IP
+///
+/// # 返回
+/// 发现的问题列表，为空表示自检通过
+pub fn evaluate_network_self_check(
+    query: &dyn NetworkSystemQuery,
+    adapter: &str,
+    virtual_ip: &str,
+) -> Vec<NetworkConfigIssue> {
+    let Some((ip, _)) = parse_cidr(virtual_ip) else {
+        return Vec::new();
+    };
+
+    let Some(addresses) = query.adapter_addresses(adapter) else {
+        return vec![NetworkConfigIssue::AdapterNotFound { adapter: adapter.to_string() }];
+    };
+
+    let mut issues = Vec::new();
+
+    // 网卡地址：优先使用网卡上配置的前缀，找不到时按常见的 /24 推断网段
+    let prefix = match addresses
+        .iter()
+        .filter_map(|a| parse_cidr(a))
+        .find(|(addr, _)| *addr == ip)
+    {
+        Some((_, prefix)) => prefix,
+        None => {
+            issues.push(NetworkConfigIssue::AddressMismatch {
+                expected: ip.to_string(),
+                actual: addresses.clone(),
+            });
+            24
+        }
+    };
+
+    // 路由：需要一条覆盖虚拟 IP 的网段路由（排除默认路由和 /32 主机路由）
+    let has_subnet_route = query
+        .adapter_routes(adapter)
+        .iter()
+        .filter_map(|r| parse_cidr(r))
+        .any(|(net, p)| p > 0 && p < 32 && network_of(ip, p) == network_of(net, p));
+    if !has_subnet_route {
+        issues.push(NetworkConfigIssue::RouteMissing {
+            subnet: format!("{}/{}", network_of(ip, prefix), prefix),
+        });
+    }
+
+    let has_peer = query
+        .peer_ips()
+        .iter()
+        .filter_map(|p| parse_cidr(p))
+        .any(|(peer, _)| peer != ip);
+    if !has_peer {
+        issues.push(NetworkConfigIssue::NoPeerResolved);
+    }
+
+    issues
+}
+
+/// 基于系统命令的查询实现（仅 Windows）
+pub struct SystemNetworkQuery {
+    /// easytier-cli 路径
+    cli_path: Option<PathBuf>,
+    /// EasyTier RPC 端口
+    rpc_port: Option<u16>,
+}
+
+impl SystemNetworkQuery {
+    pub fn new(cli_path: Option<PathBuf>, rpc_port: Option<u16>) -> Self {
+        Self { cli_path, rpc_port }
+    }
+
+    /// 运行一条 PowerShell 命令并按行返回输出
+    #[cfg(target_os = "windows")]
+    fn powershell_lines(script: &str) -> Option<Vec<String>> {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        let output = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect(),
+        )
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn powershell_lines(_script: &str) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// 把任意字符串转成 PowerShell 单引号字面量
+///
+/// 单引号字符串中只有引号本身需要转义（写两次）；PowerShell 把弯引号也当作单引号，一并处理。
+fn powershell_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// 从 easytier-cli 的 JSON 输出中收集非本机 peer 的虚拟 IP
+pub fn collect_peer_ips(value: &serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Array(arr) => arr.iter().for_each(|v| collect_peer_ips(v, out)),
+        serde_json::Value::Object(map) => {
+            let ip = map.get("ipv4").and_then(|v| v.as_str()).unwrap_or("");
+            let cost = map.get("cost").and_then(|v| v.as_str()).unwrap_or("");
+            if !ip.is_empty() && !cost.is_empty() && !cost.eq_ignore_ascii_case("local") {
+                out.push(ip.to_string());
+            }
+            map.values().for_each(|v| collect_peer_ips(v, out));
+        }
+        _ => {}
+    }
+}
+
+impl NetworkSystemQuery for SystemNetworkQuery {
+    fn adapter_addresses(&self, adapter: &str) -> Option<Vec<String>> {
+        // 网卡不存在时 Get-NetIPAddress 报错返回非 0，对应 None
+        Self::powershell_lines(&format!(
+            "Get-NetIPAddress -InterfaceAlias {} -AddressFamily IPv4 -ErrorAction Stop | ForEach-Object {{ \"$($_.IPAddress)/$($_.PrefixLength)\" }}",
+            powershell_quote(adapter)
+        ))
+    }
+
+    fn adapter_routes(&self, adapter: &str) -> Vec<String> {
+        Self::powershell_lines(&format!(
+            "Get-NetRoute -InterfaceAlias {} -AddressFamily IPv4 -ErrorAction SilentlyContinue | ForEach-Object {{ $_.DestinationPrefix }}",
+            powershell_quote(adapter)
+        ))
+        .unwrap_or_default()
+    }
+
+    fn peer_ips(&self) -> Vec<String> {
+        let (Some(cli_path), Some(port)) = (self.cli_path.as_ref(), self.rpc_port) else {
+            return Vec::new();
+        };
+
+        let mut cmd = std::process::Command::new(cli_path);
+        cmd.args(["-p", &format!("127.0.0.1:{}", port), "-o", "json", "peer"]);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let Ok(output) = cmd.output() else {
+            return Vec::new();
+        };
+        let parsed: serde_json::Value =
+            serde_json::from_slice(&output.stdout).unwrap_or(serde_json::Value::Null);
+        let mut ips = Vec::new();
+        collect_peer_ips(&parsed, &mut ips);
+        ips
+    }
+}
+
+/// 启动成功后在后台执行网络自检，异常时通知前端
+///
+/// # 参数
+/// * `app_handle` - Tauri 应用句柄
+/// * `virtual_ip` - 分配到的虚拟 IP
+/// * `adapter` - 虚拟网卡名称
+/// * `rpc_port` - EasyTier RPC 端口
+pub async fn run_network_self_check(
+    app_handle: tauri::AppHandle,
+    virtual_ip: String,
+    adapter: String,
+    rpc_port: Option<u16>,
+) {
+    if !cfg!(target_os = "windows") {
+        return;
+    }
+    tokio::time::sleep(SELF_CHECK_DELAY).await;
+    log::info!("🩺 [SelfCheck] 开始虚拟网络自检: ip={}, adapter={}", virtual_ip, adapter);
+
+    let cli_path = crate::modules::resource_manager::ResourceManager::get_easytier_cli_path(&app_handle).ok();
+    let query = std::sync::Arc::new(SystemNetworkQuery::new(cli_path, rpc_port));
+
+    let mut issues = Vec::new();
+    for attempt in 0..PEER_CHECK_RETRIES {
+        let (q, a, ip) = (query.clone(), adapter.clone(), virtual_ip.clone());
+        issues = match tokio::task::spawn_blocking(move || evaluate_network_self_check(&*q, &a, &ip)).await {
+            Ok(issues) => issues,
+            Err(e) => {
+                log::warn!("⚠️ [SelfCheck] 自检任务异常: {}", e);
+                return;
+            }
+        };
+        // 只有 peer 未解析到时才重试，网卡/路由问题重试也不会自愈
+        if issues != [NetworkConfigIssue::NoPeerResolved] || attempt + 1 == PEER_CHECK_RETRIES {
+            break;
+        }
+        tokio::time::sleep(PEER_CHECK_INTERVAL).await;
+    }
+
+    if issues.is_empty() {
+        log::info!("✅ [SelfCheck] 虚拟网络自检通过");
+        return;
+    }
+
+    for issue in &issues {
+        log::warn!("⚠️ [SelfCheck] {}，建议：{}", issue.message(), issue.suggestion());
+    }
+    let payload: Vec<serde_json::Value> = issues
+        .iter()
+        .map(|issue| {
+            let mut value = serde_json::to_value(issue).unwrap_or(serde_json::Value::Null);
+            if let Some(map) = value.as_object_mut() {
+                map.insert("message".to_string(), issue.message().into());
+                map.insert("suggestion".to_string(), issue.suggestion().into());
+            }
+            value
+        })
+        .collect();
+    let _ = app_handle.emit(
+        "network-config-warning",
+        serde_json::json!({
+            "virtualIp": virtual_ip,
+            "adapter": adapter,
+            "issues": payload,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟的系统查询结果
+    struct MockQuery {
+        addresses: Option<Vec<String>>,
+        routes: Vec<String>,
+        peers: Vec<String>,
+    }
+
+    impl MockQuery {
+        /// 配置正常的虚拟网卡
+        fn healthy() -> Self {
+            Self {
+                addresses: Some(vec!["10.126.126.5/24".to_string()]),
+                routes: vec![
+                    "10.126.126.0/24".to_string(),
+                    "10.126.126.5/32".to_string(),
+                    "10.126.126.255/32".to_string(),
+                ],
+                peers: vec!["10.126.126.1".to_string()],
+            }
+        }
+    }
+
+    impl NetworkSystemQuery for MockQuery {
+        fn adapter_addresses(&self, _adapter: &str) -> Option<Vec<String>> {
+            self.addresses.clone()
+        }
+
+        fn adapter_routes(&self, _adapter: &str) -> Vec<String> {
+            self.routes.clone()
+        }
+
+        fn peer_ips(&self) -> Vec<String> {
+            self.peers.clone()
+        }
+    }
+
+    #[test]
+    fn test_self_check_healthy() {
+        let issues = evaluate_network_self_check(&MockQuery::healthy(), DEFAULT_TUN_DEVICE_NAME, "10.126.126.5");
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_powershell_quote() {
+        assert_eq!(powershell_quote("MCTier"), "'MCTier'");
+        // 自定义网卡名中的引号不能提前结束字符串
+        assert_eq!(powershell_quote("a'; Remove-Item x; '"), "'a''; Remove-Item x; '''");
+        assert_eq!(powershell_quote("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+        assert_eq!(powershell_quote("$env:PATH"), "'$env:PATH'");
+    }
+
+    #[test]
+    fn test_self_check_adapter_not_found() {
+        let query = MockQuery { addresses: None, ..MockQuery::healthy() };
+        let issues = evaluate_network_self_check(&query, DEFAULT_TUN_DEVICE_NAME, "10.126.126.5");
+        assert_eq!(
+            issues,
+            vec![NetworkConfigIssue::AdapterNotFound { adapter: DEFAULT_TUN_DEVICE_NAME.to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_self_check_address_mismatch() {
+        // 网卡仍是 APIPA 地址，说明虚拟 IP 没有生效
+        let query = MockQuery {
+            addresses: Some(vec!["169.254.10.20/16".to_string()]),
+            ..MockQuery::healthy()
+        };
+        let issues = evaluate_network_self_check(&query, DEFAULT_TUN_DEVICE_NAME, "10.126.126.5");
+        assert_eq!(
+            issues,
+            vec![NetworkConfigIssue::AddressMismatch {
+                expected: "10.126.126.5".to_string(),
+                actual: vec!["169.254.10.20/16".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_self_check_route_missing() {
+        // 只有主机路由和默认路由，不算覆盖虚拟网段
+        let query = MockQuery {
+            routes: vec!["10.126.126.5/32".to_string(), "0.0.0.0/0".to_string()],
+            ..MockQuery::healthy()
+        };
+        let issues = evaluate_network_self_check(&query, DEFAULT_TUN_DEVICE_NAME, "10.126.126.5");
+        assert_eq!(
+            issues,
+            vec![NetworkConfigIssue::RouteMissing { subnet: "10.126.126.0/24".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_self_check_no_peer() {
+        // 只解析到自己不算
+        let query = MockQuery {
+            peers: vec!["10.126.126.5/24".to_string()],
+            ..MockQuery::healthy()
+        };
+        let issues = evaluate_network_self_check(&query, DEFAULT_TUN_DEVICE_NAME, "10.126.126.5");
+        assert_eq!(issues, vec![NetworkConfigIssue::NoPeerResolved]);
+    }
+
+    #[test]
+    fn test_collect_peer_ips() {
+        let json = serde_json::json!([
+            { "ipv4": "10.126.126.5/24", "cost": "Local" },
+            { "ipv4": "10.126.126.1/24", "cost": "p2p" },
+            { "ipv4": "10.126.126.2", "cost": "relay(2)" },
+        ]);
+        let mut ips = Vec::new();
+        collect_peer_ips(&json, &mut ips);
+        assert_eq!(ips, vec!["10.126.126.1/24".to_string(), "10.126.126.2".to_string()]);
+    }
+}
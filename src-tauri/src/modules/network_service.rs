@@ -1,12 +1,15 @@
+use crate::modules::easytier_phase::{classify_error, classify_line, ConnectProgressEvent, ConnectionPhase, EasyTierErrorKind, EasyTierStatusEvent, PhaseTracker};
 use crate::modules::error::AppError;
+use crate::modules::network_self_check::{run_network_self_check, DEFAULT_TUN_DEVICE_NAME};
 use crate::modules::resource_manager::ResourceManager;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex, Notify};
 use tokio::time::{sleep, Duration};
 
 #[cfg(windows)]
@@ -19,7 +22,7 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 /// 检查是否以管理员权限运行（仅 Windows）
 #[cfg(windows)]
-fn is_elevated() -> bool {
+pub fn is_elevated() -> bool {
     use windows::Win32::Foundation::HANDLE;
     use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
     use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
@@ -50,10 +53,97 @@ fn is_elevated() -> bool {
 
 /// 非 Windows 平台始终返回 true（不需要管理员权限）
 #[cfg(not(windows))]
-fn is_elevated() -> bool {
+pub fn is_elevated() -> bool {
     true
 }
 
+/// 以管理员身份重启时传给新进程的参数前缀，值为旧进程 PID
+pub const ELEVATED_RESTART_ARG: &str = "--elevated-restart=";
+
+/// 构造以管理员身份重启的新进程命令行参数
+///
+/// 保留原有参数（去掉上一次重启留下的标记），追加当前进程 PID，
+/// 新进程据此等待旧进程退出后再启动，避免被单实例检测拦下。
+pub fn elevated_restart_params(args: &[String], pid: u32) -> String {
+    args.iter()
+        .filter(|a| !a.starts_with(ELEVATED_RESTART_ARG))
+        .cloned()
+        .chain(std::iter::once(format!("{}{}", ELEVATED_RESTART_ARG, pid)))
+        .map(|a| {
+            if a.is_empty() || a.contains([' ', '\t', '"']) {
+                format!("\"{}\"", a.replace('"', "\\\""))
+            } else {
+                a
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 从命令行参数中取出以管理员身份重启前的旧进程 PID
+pub fn parse_elevated_restart_pid<I: IntoIterator<Item = String>>(args: I) -> Option<u32> {
+    args.into_iter()
+        .find_map(|a| a.strip_prefix(ELEVATED_RESTART_ARG).and_then(|pid| pid.parse().ok()))
+}
+
+/// 以管理员身份（UAC runas）重新启动当前程序
+#[cfg(windows)]
+pub fn relaunch_elevated() -> Result<(), String> {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let exe = std::env::current_exe().map_err(|e| format!("获取程序路径失败: {}", e))?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let params = elevated_restart_params(&args, std::process::id());
+    let to_wide = |s: &str| s.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    let exe_w = to_wide(&exe.to_string_lossy());
+    let params_w = to_wide(&params);
+
+    log::info!("以管理员身份重启: {:?} {}", exe, params);
+    let result = unsafe {
+        ShellExecuteW(
+            HWND::default(),
+            w!("runas"),
+            PCWSTR(exe_w.as_ptr()),
+            PCWSTR(params_w.as_ptr()),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // 返回值不大于 32 表示失败；用户在 UAC 弹窗中点"否"时为 SE_ERR_ACCESSDENIED(5)
+    match result.0 as isize {
+        code if code > 32 => Ok(()),
+        5 => Err("已取消管理员授权".to_string()),
+        code => Err(format!("以管理员身份启动失败（错误码 {}）", code)),
+    }
+}
+
+/// 非 Windows 平台不支持以管理员身份重启
+#[cfg(not(windows))]
+pub fn relaunch_elevated() -> Result<(), String> {
+    Err("当前平台不支持以管理员身份重启".to_string())
+}
+
+/// 等待指定进程退出（最多 `timeout`），进程不存在时立即返回
+#[cfg(windows)]
+pub fn wait_for_process_exit(pid: u32, timeout: std::time::Duration) {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE};
+
+    unsafe {
+        if let Ok(handle) = OpenProcess(PROCESS_SYNCHRONIZE, false, pid) {
+            let _ = WaitForSingleObject(handle, timeout.as_millis() as u32);
+            let _ = CloseHandle(handle);
+        }
+    }
+}
+
+/// 非 Windows 平台不会以管理员身份重启，无需等待
+#[cfg(not(windows))]
+pub fn wait_for_process_exit(_pid: u32, _timeout: std::time::Duration) {}
+
 /// 连接状态枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", content = "data")]
@@ -66,6 +156,8 @@ pub enum ConnectionStatus {
     Connecting,
     /// 错误状态（包含错误信息）
     Error(String),
+    /// 连接意外丢失，处于重连宽限期（大厅状态保持不变）
+    Reconnecting,
 }
 
 /// 网络配置
@@ -75,6 +167,24 @@ pub struct NetworkConfig {
     pub easytier_path: PathBuf,
     /// 配置目录
     pub config_dir: PathBuf,
+    /// 监听器协议
+    pub listener_protocol: ListenerProtocol,
+    /// 使用内置公共节点时是否同时连接其它内置节点作为冗余
+    pub redundant_public_peers: bool,
+    /// 追加到命令行末尾的自定义 EasyTier 参数
+    pub extra_args: Vec<String>,
+    /// 数据压缩算法
+    pub compression: CompressionAlgorithm,
+    /// 加密选项
+    pub encryption: EncryptionMode,
+    /// 组网模式（虚拟网卡 / 纯中继）
+    pub mode: NetworkMode,
+    /// 纯中继模式下 Minecraft 端口转发的目标虚拟 IP（None 表示本机即为房主，无需转发）
+    pub relay_forward_target: Option<String>,
+    /// 偏好的中继节点（P2P 打洞失败时优先经由该节点中继）
+    pub preferred_relay: Option<String>,
+    /// 启动后等待获取虚拟 IP 的超时时间
+    pub connect_timeout: Duration,
 }
 
 impl Default for NetworkConfig {
@@ -82,8 +192,567 @@ impl Default for NetworkConfig {
         Self {
             easytier_path: PathBuf::from("easytier-core.exe"),
             config_dir: PathBuf::from("./config"),
+            listener_protocol: ListenerProtocol::Auto,
+            redundant_public_peers: true,
+            extra_args: Vec::new(),
+            compression: CompressionAlgorithm::None,
+            encryption: EncryptionMode::Default,
+            mode: NetworkMode::Tun,
+            relay_forward_target: None,
+            preferred_relay: None,
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// 组网模式
+///
+/// - `Tun`：创建虚拟网卡，所有端口互通，需要管理员权限；
+/// - `Relay`：纯中继模式（EasyTier `--no-tun`），不创建虚拟网卡、无需管理员权限，
+///   只通过端口转发连通 Minecraft 所需端口。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkMode {
+    /// 虚拟网卡模式（默认）
+    #[default]
+    Tun,
+    /// 纯中继模式
+    Relay,
+}
+
+/// Minecraft 服务器默认端口
+pub const MINECRAFT_SERVER_PORT: u16 = 25565;
+
+/// 按组网模式调整最终使用的高级配置
+///
+/// 纯中继模式强制启用 `--no-tun`，并在有转发目标时追加 Minecraft 端口转发：
+/// 本机 `127.0.0.1:25565`（TCP）转发到房主的 25565。局域网世界广播是房主向外发送的组播，
+/// 端口转发无法送达，纯中继模式下不支持自动发现，因此不转发 UDP 4445。
+/// 高级配置里已绑定同一本地地址的规则保持不变，避免 EasyTier 重复绑定端口。
+pub fn apply_network_mode(
+    mut advanced: crate::modules::config_manager::EasyTierAdvancedConfig,
+    mode: NetworkMode,
+    forward_target: Option<&str>,
+) -> crate::modules::config_manager::EasyTierAdvancedConfig {
+    use crate::modules::config_manager::PortForwardRule;
+
+    if mode == NetworkMode::Tun {
+        return advanced;
+    }
+    advanced.no_tun = true;
+    let Some(target) = forward_target.map(str::trim).filter(|t| !t.is_empty()) else {
+        return advanced;
+    };
+    let bind_addr = format!("127.0.0.1:{}", MINECRAFT_SERVER_PORT);
+    let exists = advanced
+        .port_forward_rules
+        .iter()
+        .any(|r| r.protocol.eq_ignore_ascii_case("tcp") && r.bind_addr == bind_addr);
+    if !exists {
+        advanced.port_forward_rules.push(PortForwardRule {
+            protocol: "tcp".to_string(),
+            bind_addr,
+            dst_addr: format!("{}:{}", target, MINECRAFT_SERVER_PORT),
+        });
+    }
+    advanced
+}
+
+/// EasyTier 数据压缩算法（对应 `--compression`）
+///
+/// 高延迟、带宽紧张的链路开启压缩可以节省流量，代价是少量 CPU。EasyTier 目前只提供 zstd。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    /// 不压缩（EasyTier 默认）
+    #[default]
+    None,
+    /// zstd 压缩
+    Zstd,
+}
+
+/// EasyTier 加密选项（对应 `--encryption-algorithm` / `--disable-encryption`）
+///
+/// 关闭加密可以降低低端机的 CPU 占用。加密设置需要大厅内所有成员一致，否则彼此无法通信。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EncryptionMode {
+    /// 使用 EasyTier 默认算法（aes-gcm）
+    #[default]
+    Default,
+    AesGcm,
+    Aes256Gcm,
+    Chacha20,
+    Xor,
+    /// 不加密
+    Disabled,
+}
+
+/// 生成压缩与加密的命令行参数
+///
+/// 高级配置或自定义参数中已指定同类选项时以它们为准，这里不再重复添加（EasyTier 不接受重复选项）。
+///
+/// # 参数
+/// * `compression` - 压缩算法
+/// * `encryption` - 加密选项
+/// * `advanced` - 最终使用的高级配置
+/// * `extra_args` - 自定义参数
+pub fn performance_args(
+    compression: CompressionAlgorithm,
+    encryption: EncryptionMode,
+    advanced: &crate::modules::config_manager::EasyTierAdvancedConfig,
+    extra_args: &[String],
+) -> Vec<String> {
+    let has_extra = |option: &str| {
+        extra_args
+            .iter()
+            .any(|a| a == option || a.starts_with(&format!("{}=", option)))
+    };
+    let mut args = Vec::new();
+
+    let compression_overridden = advanced.compression.as_deref().is_some_and(|c| !c.is_empty()) || has_extra("--compression");
+    if compression == CompressionAlgorithm::Zstd && !compression_overridden {
+        args.extend(["--compression".to_string(), "zstd".to_string()]);
+    }
+
+    let encryption_overridden = advanced.disable_encryption
+        || advanced.encryption_algorithm.as_deref().is_some_and(|a| !a.is_empty())
+        || has_extra("--disable-encryption")
+        || has_extra("--encryption-algorithm");
+    if !encryption_overridden {
+        let algorithm = match encryption {
+            EncryptionMode::Default => None,
+            EncryptionMode::AesGcm => Some("aes-gcm"),
+            EncryptionMode::Aes256Gcm => Some("aes-256-gcm"),
+            EncryptionMode::Chacha20 => Some("chacha20"),
+            EncryptionMode::Xor => Some("xor"),
+            EncryptionMode::Disabled => {
+                args.push("--disable-encryption".to_string());
+                None
+            }
+        };
+        if let Some(algorithm) = algorithm {
+            args.extend(["--encryption-algorithm".to_string(), algorithm.to_string()]);
+        }
+    }
+    args
+}
+
+/// EasyTier 监听器协议
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerProtocol {
+    /// 按服务器节点协议自动选择（ws/wss 节点用 ws，其余用 udp）
+    #[default]
+    Auto,
+    Udp,
+    Tcp,
+    Ws,
+}
+
+impl ListenerProtocol {
+    /// 生成监听地址和对应的 `--default-protocol`
+    ///
+    /// # 参数
+    /// * `is_ws_peer` - 服务器节点是否为 ws/wss 协议
+    /// * `port` - 监听端口
+    pub fn listener(self, is_ws_peer: bool, port: u16) -> (String, &'static str) {
+        let protocol = match self {
+            ListenerProtocol::Auto if is_ws_peer => ListenerProtocol::Ws,
+            ListenerProtocol::Auto => ListenerProtocol::Udp,
+            other => other,
+        };
+        match protocol {
+            ListenerProtocol::Ws => (format!("ws://0.0.0.0:{}/", port), "ws"),
+            ListenerProtocol::Tcp => (format!("tcp://0.0.0.0:{}", port), "tcp"),
+            _ => (format!("udp://0.0.0.0:{}", port), "udp"),
+        }
+    }
+}
+
+/// 由 MCTier 自行管理、不允许通过自定义参数覆盖的 EasyTier 选项
+///
+/// 包括大厅身份（网络名/密钥）、节点、实例与 RPC 端口，以及会整体替换命令行配置的配置文件/配置服务器。
+const RESERVED_EASYTIER_ARGS: &[&str] = &[
+    "--network-name",
+    "--network-secret",
+    "--peers",
+    "-p",
+    "--hostname",
+    "--instance-name",
+    "-m",
+    "--config-dir",
+    "--rpc-portal",
+    "-r",
+    "--listeners",
+    "-l",
+    "--default-protocol",
+    "--config-file",
+    "-c",
+    "--config-server",
+];
+
+/// 自定义参数中不允许出现的字符（控制字符另行检查）
+const FORBIDDEN_ARG_CHARS: &[char] = &['&', '|', ';', '<', '>', '`', '$', '^', '"'];
+
+/// 校验自定义 EasyTier 参数
+///
+/// 拒绝空参数、含控制字符或 shell 元字符的参数，以及覆盖 MCTier 自行管理的选项（含 `--opt=value` 与短选项连写）。
+pub fn validate_extra_args(args: &[String]) -> Result<(), AppError> {
+    for arg in args {
+        if arg.trim().is_empty() {
+            return Err(AppError::ValidationError("自定义参数不能为空".to_string()));
+        }
+        if arg.chars().any(|c| c.is_control() || FORBIDDEN_ARG_CHARS.contains(&c)) {
+            return Err(AppError::ValidationError(format!("自定义参数包含非法字符: {}", arg)));
+        }
+        // 短选项可以直接连写值（如 `-pudp://...`），只取前两个字符比较
+        let option = if arg.starts_with('-') && !arg.starts_with("--") {
+            arg.get(..2).unwrap_or(arg)
+        } else {
+            arg.split('=').next().unwrap_or(arg)
+        };
+        if RESERVED_EASYTIER_ARGS.contains(&option) {
+            return Err(AppError::ValidationError(format!("参数 {} 由 MCTier 管理，不能自定义", option)));
+        }
+    }
+    Ok(())
+}
+
+/// 偏好中继节点支持的协议
+const RELAY_NODE_SCHEMES: &[&str] = &["tcp", "udp", "ws", "wss", "quic", "wg"];
+
+/// 校验偏好中继节点地址（`协议://主机[:端口]`）
+pub fn validate_relay_node(node: &str) -> Result<(), AppError> {
+    let invalid = || AppError::ValidationError(format!("中继节点地址无效: {}", node));
+    let (scheme, rest) = node.split_once("://").ok_or_else(invalid)?;
+    if !RELAY_NODE_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+        return Err(AppError::ValidationError(format!("不支持的中继节点协议: {}", scheme)));
+    }
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() || host.starts_with(':') || node.chars().any(|c| c.is_whitespace() || c.is_control() || FORBIDDEN_ARG_CHARS.contains(&c)) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// 把偏好中继节点排在主节点之后加入 peer 列表（已在列表中时只调整顺序）
+///
+/// 中继需经由双方都连接的节点，房间成员设置同一个偏好中继节点效果最好。
+pub fn insert_preferred_relay(peer_nodes: &mut Vec<String>, preferred_relay: Option<&str>) {
+    let Some(relay) = preferred_relay.map(|r| r.trim().trim_end_matches('/')).filter(|r| !r.is_empty()) else {
+        return;
+    };
+    let same = |p: &String| p.trim_end_matches('/').eq_ignore_ascii_case(relay);
+    if peer_nodes.first().is_some_and(same) {
+        return;
+    }
+    peer_nodes.retain(|p| !same(p));
+    peer_nodes.insert(peer_nodes.len().min(1), relay.to_string());
+}
+
+/// 启动前检测到其它 easytier-core 实例时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceConflictAction {
+    /// 未发现其它实例，直接启动
+    Proceed,
+    /// 自动清理旧实例后再启动
+    Cleanup,
+    /// 中止启动，交由用户确认后再清理
+    Abort,
+}
+
+/// 解析 `tasklist /FO CSV /NH` 的输出，提取 easytier-core.exe 进程的 PID
+///
+/// 没有匹配进程时 tasklist 会输出一行提示信息（"INFO: ..." / "信息: ..."），会被忽略。
+pub fn parse_tasklist_output(output: &str) -> Vec<u32> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().split("\",\"").map(|f| f.trim_matches('"'));
+            let image = fields.next()?;
+            if !image.eq_ignore_ascii_case("easytier-core.exe") {
+                return None;
+            }
+            fields.next()?.trim().parse::<u32>().ok()
+        })
+        .collect()
+}
+
+/// 根据已存在的 easytier-core 进程列表决定启动前的处理方式
+///
+/// # 参数
+/// * `existing_pids` - 系统中已在运行的 easytier-core 进程 PID
+/// * `auto_cleanup` - 是否允许自动清理旧实例
+pub fn decide_instance_conflict(existing_pids: &[u32], auto_cleanup: bool) -> InstanceConflictAction {
+    if existing_pids.is_empty() {
+        InstanceConflictAction::Proceed
+    } else if auto_cleanup {
+        InstanceConflictAction::Cleanup
+    } else {
+        InstanceConflictAction::Abort
+    }
+}
+
+/// EasyTier 版本号
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EasyTierVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// `--version` 的原始输出（去掉首尾空白）
+    pub raw: String,
+}
+
+impl std::fmt::Display for EasyTierVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// 已知与当前大厅协议不兼容的 EasyTier 版本：(主版本, 次版本上限（不含）, 原因)
+///
+/// 主版本相同且次版本低于上限的都视为不兼容。
+const INCOMPATIBLE_EASYTIER_VERSIONS: &[(u32, u32, &str)] = &[
+    (0, u32::MAX, "EasyTier 0.x 为早期测试版本，与 2.x 协议不兼容"),
+    (1, u32::MAX, "EasyTier 1.x 与 2.x 的握手协议不兼容"),
+    (2, 4, "EasyTier 2.4 之前的版本不支持大厅使用的 WebSocket 节点与 DHCP 分配"),
+];
+
+/// 执行 `easytier-core --version` 的超时时间
+const EASYTIER_VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 解析 `easytier-core --version` 的输出
+///
+/// 输出形如 `easytier-core 2.5.0-8f3ac4b1`，取第一个形如 `x.y.z` 的版本号。
+pub fn parse_easytier_version(output: &str) -> Option<EasyTierVersion> {
+    output.split_whitespace().find_map(|token| {
+        let token = token.trim_start_matches(['v', 'V']);
+        let core = token.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u32>().ok());
+        let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+        Some(EasyTierVersion { major, minor, patch, raw: output.trim().to_string() })
+    })
+}
+
+/// 检查版本是否在已知不兼容列表中，返回不兼容原因
+pub fn easytier_compatibility_warning(version: &EasyTierVersion) -> Option<&'static str> {
+    INCOMPATIBLE_EASYTIER_VERSIONS
+        .iter()
+        .find(|(major, below_minor, _)| version.major == *major && version.minor < *below_minor)
+        .map(|(_, _, reason)| *reason)
+}
+
+/// 已检测到的本机 EasyTier 版本（同一二进制只需检测一次）
+fn easytier_version_slot() -> &'static parking_lot::Mutex<Option<EasyTierVersion>> {
+    static VERSION: std::sync::OnceLock<parking_lot::Mutex<Option<EasyTierVersion>>> = std::sync::OnceLock::new();
+    VERSION.get_or_init(|| parking_lot::Mutex::new(None))
+}
+
+/// 执行 `easytier-core --version` 获取版本号，结果会被缓存
+///
+/// 检测到已知不兼容版本时向前端发送 `easytier-version-warning` 事件。
+pub async fn detect_easytier_version(
+    easytier_path: &std::path::Path,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<EasyTierVersion, AppError> {
+    if let Some(version) = easytier_version_slot().lock().clone() {
+        return Ok(version);
+    }
+
+    let mut cmd = Command::new(easytier_path);
+    cmd.arg("--version").stdin(Stdio::null()).kill_on_drop(true);
+    #[cfg(target_os = "windows")]
+    {
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = tokio::time::timeout(EASYTIER_VERSION_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| AppError::ProcessError("获取 EasyTier 版本超时".to_string()))?
+        .map_err(|e| AppError::ProcessError(format!("执行 easytier-core --version 失败: {}", e)))?;
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let version = parse_easytier_version(&text).ok_or_else(|| {
+        AppError::ProcessError(format!("无法解析 EasyTier 版本号: {}", text.trim()))
+    })?;
+
+    log::info!("EasyTier 版本: {} ({})", version, version.raw);
+    if let Some(reason) = easytier_compatibility_warning(&version) {
+        log::warn!("⚠️ 检测到不兼容的 EasyTier 版本 {}: {}", version, reason);
+        if let Some(app_handle) = app_handle {
+            use tauri::Emitter;
+            let _ = app_handle.emit(
+                "easytier-version-warning",
+                serde_json::json!({ "version": version.to_string(), "reason": reason }),
+            );
         }
     }
+    *easytier_version_slot().lock() = Some(version.clone());
+    Ok(version)
+}
+
+/// 断线重连宽限期默认时长（秒）
+pub const DEFAULT_RECONNECT_GRACE_SECS: u64 = 30;
+/// 断线重连宽限期允许的最大时长（秒）
+pub const MAX_RECONNECT_GRACE_SECS: u64 = 600;
+
+/// 重连宽限期检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraceState {
+    /// 连接正常（未处于宽限期）
+    Online,
+    /// 宽限期内，附带剩余时长
+    Waiting(Duration),
+    /// 宽限期已耗尽，需要真正退出大厅
+    Expired,
+}
+
+/// 断线重连宽限期
+///
+/// 连接丢失后先进入宽限期，期间恢复则视为短暂抖动继续使用，超时才判定为长断线。
+#[derive(Debug, Clone)]
+pub struct ReconnectGrace {
+    grace: Duration,
+    lost_at: Option<Instant>,
+}
+
+impl ReconnectGrace {
+    /// 创建宽限期（`grace` 为 0 表示断线后立即退出）
+    pub fn new(grace: Duration) -> Self {
+        Self { grace, lost_at: None }
+    }
+
+    /// 记录连接丢失（宽限期内重复丢失不会重新计时）
+    pub fn connection_lost(&mut self, now: Instant) {
+        if self.lost_at.is_none() {
+            self.lost_at = Some(now);
+        }
+    }
+
+    /// 记录连接恢复，返回本次断线持续的时长（未处于宽限期时返回 None）
+    pub fn connection_restored(&mut self, now: Instant) -> Option<Duration> {
+        self.lost_at.take().map(|lost_at| now.saturating_duration_since(lost_at))
+    }
+
+    /// 检查当前所处的阶段
+    pub fn check(&self, now: Instant) -> GraceState {
+        match self.lost_at {
+            None => GraceState::Online,
+            Some(lost_at) => {
+                let elapsed = now.saturating_duration_since(lost_at);
+                if elapsed >= self.grace {
+                    GraceState::Expired
+                } else {
+                    GraceState::Waiting(self.grace - elapsed)
+                }
+            }
+        }
+    }
+}
+
+/// 连接超时默认时长（秒）
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 60;
+/// 连接超时允许的最短时长（秒）
+pub const MIN_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// 连接超时允许的最长时长（秒）
+pub const MAX_CONNECT_TIMEOUT_SECS: u64 = 600;
+/// 连接进度事件的推送间隔
+const CONNECT_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 用户请求取消正在进行的连接
+///
+/// create_lobby / join_lobby 等待虚拟 IP 期间一直持有 network_service 锁，
+/// 取消命令拿不到锁，只能通过这个标志通知等待循环自行停止进程并返回。
+static CONNECT_CANCEL: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// 请求取消正在进行的连接（不在连接中时无效果，下次创建 / 加入大厅会重置）
+pub fn request_connect_cancel() {
+    CONNECT_CANCEL.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// 清除上一次遗留的取消请求
+///
+/// 在 create_lobby / join_lobby（以及自动重连）一开始调用，而不是在启动前检查之后，
+/// 这样前置检查期间点击的取消不会被吞掉。
+pub fn reset_connect_cancel() {
+    CONNECT_CANCEL.store(false, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// 重连时使用缓存 IP 乐观恢复前，等待新进程稳定运行的时间
+const OPTIMISTIC_RESTORE_SETTLE: Duration = Duration::from_millis(1500);
+/// 乐观恢复后后台确认实际分配 IP 的最长等待时间
+const VIRTUAL_IP_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 上次成功分配的虚拟 IP 缓存（按网络名称）
+#[derive(Debug, Default, Clone)]
+pub struct VirtualIpCache {
+    entries: std::collections::HashMap<String, String>,
+}
+
+impl VirtualIpCache {
+    /// 记录某个网络最近一次成功分配的虚拟 IP
+    pub fn remember(&mut self, network_name: &str, ip: &str) {
+        self.entries.insert(network_name.to_string(), ip.to_string());
+    }
+
+    /// 查询某个网络缓存的虚拟 IP
+    pub fn get(&self, network_name: &str) -> Option<String> {
+        self.entries.get(network_name).cloned()
+    }
+
+    /// 丢弃某个网络缓存的虚拟 IP（该 IP 与其他成员冲突时）
+    pub fn forget(&mut self, network_name: &str) {
+        self.entries.remove(network_name);
+    }
+}
+
+/// 比较乐观使用的缓存 IP 与实际分配的 IP，不一致时返回需要校正为的新 IP
+pub fn reconcile_virtual_ip(optimistic: &str, actual: &str) -> Option<String> {
+    (optimistic != actual).then(|| actual.to_string())
+}
+
+/// 等待 EasyTier 输出实际分配的虚拟 IP
+///
+/// 进程退出或超时时返回 None。
+async fn await_confirmed_ip(
+    virtual_ip: &Mutex<Option<String>>,
+    is_running: &Mutex<bool>,
+    timeout: Duration,
+) -> Option<String> {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if let Some(ip) = virtual_ip.lock().await.clone() {
+            return Some(ip);
+        }
+        if !*is_running.lock().await {
+            return None;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    None
+}
+
+/// 断线重连结果
+pub struct RelaunchOutcome {
+    /// 当前使用的虚拟 IP（乐观恢复时为缓存 IP）
+    pub virtual_ip: String,
+    /// 乐观恢复时，后台确认到的实际 IP；非乐观恢复时为 None
+    pub confirmation: Option<oneshot::Receiver<String>>,
+}
+
+/// 最近一次启动 EasyTier 使用的参数（断线重连时原样复用）
+#[derive(Clone)]
+struct LaunchParams {
+    network_name: String,
+    network_key: String,
+    server_node: String,
+    player_name: String,
+    global_config: Option<Option<crate::modules::config_manager::EasyTierAdvancedConfig>>,
+    lobby_config: Option<Option<crate::modules::config_manager::EasyTierAdvancedConfig>>,
 }
 
 /// 网络服务
@@ -108,6 +777,18 @@ pub struct NetworkService {
     rpc_port: Arc<Mutex<Option<u16>>>,
     /// 最近的标准错误输出（用于在进程意外退出时定位原因，仅保留最近若干行）
     last_stderr: Arc<Mutex<std::collections::VecDeque<String>>>,
+    /// 启动前发现其它 easytier-core 实例时是否自动清理
+    auto_cleanup_instances: Arc<Mutex<bool>>,
+    /// 最近一次启动参数（用于断线重连）
+    last_launch: Arc<Mutex<Option<LaunchParams>>>,
+    /// 已连接状态下进程意外退出时触发
+    connection_lost: Arc<Notify>,
+    /// 上次成功分配的虚拟 IP（按网络名称）
+    ip_cache: Arc<Mutex<VirtualIpCache>>,
+    /// 本次启动允许乐观使用的缓存 IP（仅断线重连时设置）
+    optimistic_ip: Arc<Mutex<Option<String>>>,
+    /// 乐观恢复后等待实际 IP 确认的接收端
+    pending_confirmation: Arc<Mutex<Option<oneshot::Receiver<String>>>>,
 }
 
 impl NetworkService {
@@ -129,6 +810,12 @@ impl NetworkService {
             instance_config_dir: Arc::new(Mutex::new(None)),
             rpc_port: Arc::new(Mutex::new(None)),
             last_stderr: Arc::new(Mutex::new(std::collections::VecDeque::new())),
+            auto_cleanup_instances: Arc::new(Mutex::new(true)),
+            last_launch: Arc::new(Mutex::new(None)),
+            connection_lost: Arc::new(Notify::new()),
+            ip_cache: Arc::new(Mutex::new(VirtualIpCache::default())),
+            optimistic_ip: Arc::new(Mutex::new(None)),
+            pending_confirmation: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -144,6 +831,26 @@ impl NetworkService {
     pub fn set_app_handle(&mut self, app_handle: tauri::AppHandle) {
         self.app_handle = Some(app_handle);
     }
+
+    /// 设置启动前发现其它 easytier-core 实例时是否自动清理
+    ///
+    /// 关闭后遇到已有实例会中止启动并返回错误，由前端提示用户确认
+    pub async fn set_auto_cleanup_instances(&self, enabled: bool) {
+        *self.auto_cleanup_instances.lock().await = enabled;
+    }
+
+    /// 获取"连接意外丢失"通知
+    ///
+    /// 已连接状态下 EasyTier 进程意外退出时触发（主动停止不会触发），
+    /// 由上层决定在宽限期内重连还是退出大厅。
+    pub fn connection_lost_signal(&self) -> Arc<Notify> {
+        Arc::clone(&self.connection_lost)
+    }
+
+    /// 是否自动清理已存在的 easytier-core 实例
+    pub async fn is_auto_cleanup_instances(&self) -> bool {
+        *self.auto_cleanup_instances.lock().await
+    }
     
     /// 获取 EasyTier 可执行文件路径
     /// 
@@ -159,6 +866,65 @@ impl NetworkService {
         }
     }
 
+    /// 设置 EasyTier 启动选项（下次启动时生效）
+    pub fn set_launch_options(&mut self, options: crate::modules::config_manager::EasyTierLaunchOptions) {
+        self.config.listener_protocol = options.listener_protocol;
+        self.config.redundant_public_peers = options.redundant_public_peers;
+        self.config.extra_args = options.extra_args;
+        self.config.compression = options.compression;
+        self.config.encryption = options.encryption;
+    }
+
+    /// 设置组网模式（下次启动时生效）
+    ///
+    /// # 参数
+    /// * `mode` - 组网模式
+    /// * `relay_forward_target` - 纯中继模式下 Minecraft 端口转发的目标虚拟 IP
+    pub fn set_network_mode(&mut self, mode: NetworkMode, relay_forward_target: Option<String>) {
+        self.config.mode = mode;
+        self.config.relay_forward_target = relay_forward_target;
+    }
+
+    /// 设置偏好的中继节点（下次启动时生效）
+    pub fn set_preferred_relay(&mut self, node: Option<String>) {
+        self.config.preferred_relay = node;
+    }
+
+    /// 设置连接超时（秒，下次启动时生效）
+    pub fn set_connect_timeout_secs(&mut self, secs: u64) {
+        let secs = secs.clamp(MIN_CONNECT_TIMEOUT_SECS, MAX_CONNECT_TIMEOUT_SECS);
+        self.config.connect_timeout = Duration::from_secs(secs);
+    }
+
+    /// 当前偏好的中继节点
+    pub fn preferred_relay(&self) -> Option<String> {
+        self.config.preferred_relay.clone()
+    }
+
+    /// 按顺序拼接 EasyTier 命令行：基础参数、高级配置，最后是自定义参数
+    ///
+    /// 自定义参数放在最后，未被 MCTier 管理的选项可以覆盖高级配置中的同名选项。
+    /// 自定义参数校验失败时整体忽略（配置文件可能被手动改过）。
+    fn apply_launch_args(
+        cmd: &mut tokio::process::Command,
+        base_args: &[String],
+        advanced: &crate::modules::config_manager::EasyTierAdvancedConfig,
+        extra_args: &[String],
+    ) {
+        cmd.args(base_args);
+        Self::apply_advanced_config(cmd, advanced);
+        if extra_args.is_empty() {
+            return;
+        }
+        match validate_extra_args(extra_args) {
+            Ok(()) => {
+                cmd.args(extra_args);
+                log::info!("  ✅ 追加自定义参数: {:?}", extra_args);
+            }
+            Err(e) => log::warn!("⚠️ 自定义 EasyTier 参数无效，已忽略: {}", e),
+        }
+    }
+
     /// 应用 EasyTier 高级配置到命令行
     /// 
     /// # 参数
@@ -528,9 +1294,9 @@ impl NetworkService {
         global_config_param: Option<Option<crate::modules::config_manager::EasyTierAdvancedConfig>>,
         lobby_config_param: Option<Option<crate::modules::config_manager::EasyTierAdvancedConfig>>,
     ) -> Result<String, AppError> {
-        // 检查管理员权限（Windows 平台需要）
+        // 检查管理员权限（Windows 平台需要；纯中继模式不创建虚拟网卡，无需管理员权限）
         #[cfg(windows)]
-        {
+        if self.config.mode == NetworkMode::Tun {
             if !is_elevated() {
                 log::error!("权限不足，无法创建虚拟网卡");
                 return Err(AppError::NetworkError(
@@ -548,6 +1314,15 @@ impl NetworkService {
             ));
         }
 
+        *self.last_launch.lock().await = Some(LaunchParams {
+            network_name: network_name.clone(),
+            network_key: network_key.clone(),
+            server_node: server_node.clone(),
+            player_name: player_name.clone(),
+            global_config: global_config_param.clone(),
+            lobby_config: lobby_config_param.clone(),
+        });
+
         log::info!("========================================");
         log::info!("正在启动 EasyTier 服务");
         log::info!("  网络名称: {}", network_name);
@@ -557,9 +1332,29 @@ impl NetworkService {
         // 更新状态为连接中
         *self.status.lock().await = ConnectionStatus::Connecting;
 
-        // 【关键修复】启动前清理可能残留的孤儿 easytier-core.exe 进程，
+        // 【关键修复】启动前检测已在运行的 easytier-core.exe 进程（残留孤儿或另一个 MCTier/EasyTier 实例），
         // 避免它占用固定虚拟网卡名 MCTier_Net / RPC 端口，导致新进程"意外终止"
-        Self::cleanup_orphan_processes().await;
+        let existing_pids = Self::list_easytier_processes().await;
+        let auto_cleanup = *self.auto_cleanup_instances.lock().await;
+        match decide_instance_conflict(&existing_pids, auto_cleanup) {
+            InstanceConflictAction::Proceed => {
+                log::info!("✅ [PreStart] 未发现其它 easytier-core 实例，环境干净");
+            }
+            InstanceConflictAction::Cleanup => {
+                log::warn!("⚠️ [PreStart] 发现 {} 个已在运行的 easytier-core 实例 {:?}，自动清理", existing_pids.len(), existing_pids);
+                Self::emit_instance_conflict(app_handle, &existing_pids, true);
+                Self::cleanup_orphan_processes().await;
+            }
+            InstanceConflictAction::Abort => {
+                log::warn!("⚠️ [PreStart] 发现 {} 个已在运行的 easytier-core 实例 {:?}，等待用户确认", existing_pids.len(), existing_pids);
+                Self::emit_instance_conflict(app_handle, &existing_pids, false);
+                *self.status.lock().await = ConnectionStatus::Disconnected;
+                return Err(AppError::NetworkError(format!(
+                    "检测到 {} 个已在运行的 EasyTier 实例（可能是另一个 MCTier 或 EasyTier），请确认清理后重试",
+                    existing_pids.len()
+                )));
+            }
+        }
 
         // 清空上一次的 stderr 缓存
         self.last_stderr.lock().await.clear();
@@ -569,6 +1364,11 @@ impl NetworkService {
         
         log::info!("使用 EasyTier 路径: {:?}", easytier_path);
 
+        // 检测 EasyTier 版本：不兼容时提醒用户，检测失败不阻塞启动
+        if let Err(e) = detect_easytier_version(&easytier_path, Some(app_handle)).await {
+            log::warn!("⚠️ 检测 EasyTier 版本失败（忽略）: {}", e);
+        }
+
         // 获取 EasyTier 所在目录作为工作目录
         let working_dir = easytier_path
             .parent()
@@ -686,13 +1486,8 @@ impl NetworkService {
         // 改为预先探测一个可用的显式端口给监听器使用，确保稳定。
         let listener_port = Self::find_available_rpc_port_randomized().await
             .unwrap_or(0); // 兜底：万一找不到则退回端口 0 让系统分配
-        let listener = if is_ws_peer {
-            format!("ws://0.0.0.0:{}/", listener_port)
-        } else {
-            format!("udp://0.0.0.0:{}", listener_port)
-        };
+        let (listener, default_protocol) = self.config.listener_protocol.listener(is_ws_peer, listener_port);
         log::info!("✅ 监听器使用显式端口: {} -> {}", listener_port, listener);
-        let default_protocol = if is_ws_peer { "ws" } else { "udp" };
 
         // 读取高级功能配置
         use tauri::Manager;
@@ -789,6 +1584,15 @@ impl NetworkService {
             }
         };
         
+        let final_config = apply_network_mode(
+            final_config,
+            self.config.mode,
+            self.config.relay_forward_target.as_deref(),
+        );
+        if self.config.mode == NetworkMode::Relay {
+            log::info!("✅ 使用纯中继模式（--no-tun），转发目标: {:?}", self.config.relay_forward_target);
+        }
+
         log::info!("========================================");
         log::info!("最终使用的高级配置:");
         log::info!("  - 使用全局配置标志: {}", final_config.use_global_config);
@@ -817,7 +1621,7 @@ impl NetworkService {
         let is_builtin_public = BUILTIN_PUBLIC_NODES
             .iter()
             .any(|n| n.eq_ignore_ascii_case(primary_node.trim_end_matches('/')));
-        if is_builtin_public {
+        if is_builtin_public && self.config.redundant_public_peers {
             for n in BUILTIN_PUBLIC_NODES {
                 if !n.eq_ignore_ascii_case(primary_node.trim_end_matches('/'))
                     && !peer_nodes.iter().any(|p| p.eq_ignore_ascii_case(n))
@@ -829,32 +1633,48 @@ impl NetworkService {
         } else {
             log::info!("使用私有/自定义节点，按隔离策略仅连接该节点: {}", primary_node);
         }
+        if let Some(relay) = self.config.preferred_relay.as_deref() {
+            insert_preferred_relay(&mut peer_nodes, Some(relay));
+            log::info!("✅ 已加入偏好中继节点: {}", relay);
+        }
 
         // 构建命令行参数
-        let mut cmd = Command::new(&easytier_path);
-        cmd.arg("--network-name")
-            .arg(&network_name)
-            .arg("--network-secret")
-            .arg(&network_key);
+        let mut base_args = vec![
+            "--network-name".to_string(),
+            network_name.clone(),
+            "--network-secret".to_string(),
+            network_key.clone(),
+        ];
         // 依次加入所有 peer（主节点优先，其余为冗余备用）
         for peer in &peer_nodes {
-            cmd.arg("--peers").arg(peer);
-        }
-        cmd.arg("--hostname")
-            .arg(&sanitized_hostname) // 设置主机名用于Magic DNS
-            .arg("--instance-name")
-            .arg(&instance_name)
-            .arg("--config-dir")
-            .arg(&config_dir)
-            .arg("--rpc-portal")
-            .arg(format!("127.0.0.1:{}", rpc_port)) // 显式绑定回环地址：避免裸端口被绑到 0.0.0.0 而撞上 Windows(Hyper-V/Docker) 保留端口段导致 os error 10013
-            .arg("--listeners")
-            .arg(listener)
-            .arg("--default-protocol")
-            .arg(default_protocol);
-        
-        // 应用高级配置
-        Self::apply_advanced_config(&mut cmd, &final_config);
+            base_args.push("--peers".to_string());
+            base_args.push(peer.clone());
+        }
+        base_args.extend([
+            "--hostname".to_string(),
+            sanitized_hostname.clone(), // 设置主机名用于Magic DNS
+            "--instance-name".to_string(),
+            instance_name.clone(),
+            "--config-dir".to_string(),
+            config_dir.to_string_lossy().to_string(),
+            "--rpc-portal".to_string(),
+            format!("127.0.0.1:{}", rpc_port), // 显式绑定回环地址：避免裸端口被绑到 0.0.0.0 而撞上 Windows(Hyper-V/Docker) 保留端口段导致 os error 10013
+            "--listeners".to_string(),
+            listener,
+            "--default-protocol".to_string(),
+            default_protocol.to_string(),
+        ]);
+        // 压缩与加密（修改后需重新进入大厅才会生效）
+        base_args.extend(performance_args(
+            self.config.compression,
+            self.config.encryption,
+            &final_config,
+            &self.config.extra_args,
+        ));
+        
+        // 依次应用基础参数、高级配置和自定义参数
+        let mut cmd = Command::new(&easytier_path);
+        Self::apply_launch_args(&mut cmd, &base_args, &final_config, &self.config.extra_args);
         
         // 【重要】输出完整的 EasyTier 命令行，用于验证配置是否生效
         let cmd_args: Vec<String> = cmd.as_std()
@@ -934,16 +1754,31 @@ impl NetworkService {
         let status_clone = Arc::clone(&self.status);
         let is_running_stdout = Arc::clone(&self.is_running);
         let stderr_buf_stdout = Arc::clone(&self.last_stderr);
+        // 两路输出共用一个阶段节流器，避免同一阶段在 stdout / stderr 各推一次
+        let phase_tracker = Arc::new(parking_lot::Mutex::new(PhaseTracker::default()));
+        let app_handle_stdout = app_handle.clone();
+        let phase_tracker_stdout = Arc::clone(&phase_tracker);
 
         tokio::spawn(async move {
-            Self::monitor_stdout(stdout, virtual_ip_clone, status_clone, is_running_stdout, stderr_buf_stdout).await;
+            Self::monitor_stdout(
+                stdout,
+                virtual_ip_clone,
+                status_clone,
+                is_running_stdout,
+                stderr_buf_stdout,
+                app_handle_stdout,
+                phase_tracker_stdout,
+            )
+            .await;
         });
 
         let is_running_clone = Arc::clone(&self.is_running);
         let status_clone2 = Arc::clone(&self.status);
         let stderr_buf_clone = Arc::clone(&self.last_stderr);
+        let app_handle_stderr = app_handle.clone();
+        let phase_tracker_stderr = Arc::clone(&phase_tracker);
         tokio::spawn(async move {
-            Self::monitor_stderr(stderr, is_running_clone, status_clone2, stderr_buf_clone).await;
+            Self::monitor_stderr(stderr, is_running_clone, status_clone2, stderr_buf_clone, app_handle_stderr, phase_tracker_stderr).await;
         });
 
         // 启动进程监控任务
@@ -952,6 +1787,7 @@ impl NetworkService {
         let is_running_clone = Arc::clone(&self.is_running);
         let virtual_ip_clone = Arc::clone(&self.virtual_ip);
         let stderr_buf_clone2 = Arc::clone(&self.last_stderr);
+        let connection_lost = Arc::clone(&self.connection_lost);
 
         tokio::spawn(async move {
             Self::monitor_process(
@@ -960,19 +1796,29 @@ impl NetworkService {
                 is_running_clone,
                 virtual_ip_clone,
                 stderr_buf_clone2,
+                connection_lost,
             )
             .await;
         });
 
-        // 等待获取虚拟 IP（最多等待 60 秒）
-        let timeout_duration = Duration::from_secs(60);
+        // 等待获取虚拟 IP（超时时间可配置）
+        let timeout_duration = self.config.connect_timeout;
+        let timeout_secs = timeout_duration.as_secs();
         let start_time = std::time::Instant::now();
         let mut last_log_time = std::time::Instant::now();
+        let mut last_progress_time: Option<std::time::Instant> = None;
 
         loop {
+            // 检查用户是否取消了连接
+            if CONNECT_CANCEL.swap(false, std::sync::atomic::Ordering::SeqCst) {
+                log::info!("🛑 用户取消连接，停止 EasyTier 进程");
+                self.stop_easytier().await?;
+                return Err(AppError::NetworkError("已取消连接".to_string()));
+            }
+
             // 检查是否超时
             if start_time.elapsed() > timeout_duration {
-                log::error!("❌ 获取虚拟 IP 超时（等待了60秒）");
+                log::error!("❌ 获取虚拟 IP 超时（等待了{}秒）", timeout_secs);
                 log::error!("可能的原因：");
                 log::error!("  1. EasyTier进程启动失败");
                 log::error!("  2. 网络连接问题，无法连接到信令服务器");
@@ -987,9 +1833,23 @@ impl NetworkService {
             // 每5秒输出一次等待日志
             if last_log_time.elapsed().as_secs() >= 5 {
                 let elapsed = start_time.elapsed().as_secs();
-                log::info!("⏳ 等待获取虚拟 IP... 已等待 {} 秒 / 60 秒", elapsed);
+                log::info!("⏳ 等待获取虚拟 IP... 已等待 {} 秒 / {} 秒", elapsed, timeout_secs);
                 last_log_time = std::time::Instant::now();
             }
+
+            // 定时向前端推送等待进度与当前阶段
+            if last_progress_time.is_none_or(|t| t.elapsed() >= CONNECT_PROGRESS_INTERVAL) {
+                use tauri::Emitter;
+                let _ = app_handle.emit(
+                    "easytier-connect-progress",
+                    ConnectProgressEvent {
+                        elapsed_secs: start_time.elapsed().as_secs(),
+                        timeout_secs,
+                        phase: phase_tracker.lock().last_phase(),
+                    },
+                );
+                last_progress_time = Some(std::time::Instant::now());
+            }
             
             // 检查是否有错误状态
             let current_status = self.status.lock().await.clone();
@@ -1004,9 +1864,38 @@ impl NetworkService {
             if let Some(ip_addr) = ip {
                 log::info!("✅ 从输出中成功获取虚拟 IP: {}", ip_addr);
                 *self.status.lock().await = ConnectionStatus::Connected(ip_addr.clone());
+                if let Some(params) = self.last_launch.lock().await.as_ref() {
+                    self.ip_cache.lock().await.remember(&params.network_name, &ip_addr);
+                }
+
+                // 后台自检虚拟网卡地址/路由与 peer 解析情况（no-tun 模式没有虚拟网卡，跳过）
+                if !final_config.no_tun {
+                    let adapter = final_config
+                        .dev_name
+                        .clone()
+                        .filter(|n| !n.is_empty())
+                        .unwrap_or_else(|| DEFAULT_TUN_DEVICE_NAME.to_string());
+                    tokio::spawn(run_network_self_check(
+                        app_handle.clone(),
+                        ip_addr.clone(),
+                        adapter,
+                        Some(rpc_port),
+                    ));
+                }
                 return Ok(ip_addr);
             }
             
+            // 断线重连：进程稳定运行后先用缓存 IP 乐观恢复，后台再确认实际分配的 IP
+            let optimistic = self.optimistic_ip.lock().await.clone();
+            if let Some(cached_ip) = optimistic {
+                if start_time.elapsed() >= OPTIMISTIC_RESTORE_SETTLE && *self.is_running.lock().await {
+                    log::info!("⚡ 使用缓存虚拟 IP 乐观恢复: {}（后台确认实际 IP）", cached_ip);
+                    *self.status.lock().await = ConnectionStatus::Connected(cached_ip.clone());
+                    *self.pending_confirmation.lock().await = Some(self.spawn_ip_confirmation(cached_ip.clone()));
+                    return Ok(cached_ip);
+                }
+            }
+
             // 【已废弃】不再使用 CLI 工具查询虚拟IP
             // easytier-cli已移除，完全依赖从标准输出解析虚拟IP
             // 如果超时仍未获取到IP，将在下面的超时检查中返回错误
@@ -1130,13 +2019,52 @@ impl NetworkService {
         Self::find_available_rpc_port(15889, 20).await
     }
 
+    /// 列出系统中正在运行的 easytier-core.exe 进程 PID（仅 Windows）
+    ///
+    /// 查询失败时返回空列表，不阻塞启动流程
+    #[cfg(target_os = "windows")]
+    pub async fn list_easytier_processes() -> Vec<u32> {
+        let output = tokio::process::Command::new("tasklist")
+            .args(["/FI", "IMAGENAME eq easytier-core.exe", "/FO", "CSV", "/NH"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .await;
+
+        match output {
+            Ok(o) => parse_tasklist_output(&String::from_utf8_lossy(&o.stdout)),
+            Err(e) => {
+                log::warn!("⚠️ [PreStart] 查询 easytier-core 进程列表失败（忽略）: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub async fn list_easytier_processes() -> Vec<u32> {
+        // 非 Windows 平台不做处理
+        Vec::new()
+    }
+
+    /// 通知前端检测到其它 easytier-core 实例
+    fn emit_instance_conflict(app_handle: &tauri::AppHandle, pids: &[u32], cleaned: bool) {
+        use tauri::Emitter;
+        let _ = app_handle.emit(
+            "easytier-instance-conflict",
+            serde_json::json!({
+                "count": pids.len(),
+                "pids": pids,
+                "cleaned": cleaned,
+            }),
+        );
+    }
+
     /// 启动前清理孤儿 EasyTier 进程（仅 Windows）
     ///
     /// 上一次 App 异常退出时可能残留 easytier-core.exe 进程，
     /// 它会占用固定虚拟网卡名 MCTier_Net 和 RPC 端口，
     /// 导致新进程创建网卡失败而"意外终止"。这里在启动前先强制清理。
     #[cfg(target_os = "windows")]
-    async fn cleanup_orphan_processes() {
+    pub async fn cleanup_orphan_processes() {
         log::info!("🧹 [PreStart] 检查并清理可能残留的孤儿 easytier-core.exe 进程...");
         let output = tokio::process::Command::new("taskkill")
             .args(&["/F", "/IM", "easytier-core.exe"])
@@ -1163,7 +2091,7 @@ impl NetworkService {
     }
 
     #[cfg(not(target_os = "windows"))]
-    async fn cleanup_orphan_processes() {
+    pub async fn cleanup_orphan_processes() {
         // 非 Windows 平台不做处理
     }
 
@@ -1181,10 +2109,9 @@ impl NetworkService {
         };
 
         // 先在最近日志里找"虚拟网卡创建失败"这类最关键的具体原因
-        if recent_stderr.iter().any(|l| {
-            l.contains("tun device error") || l.contains("Failed to create adapter")
-        }) {
-            return "虚拟网卡创建失败：请右键以管理员身份运行 MCTier，并将本软件加入杀毒软件/防火墙白名单；若仍失败，请重启电脑后重试".to_string();
+        let error_kind = recent_stderr.iter().filter_map(|l| classify_error(l)).min();
+        if error_kind == Some(EasyTierErrorKind::TunCreateFailed) {
+            return EasyTierErrorKind::TunCreateFailed.hint().to_string();
         }
 
         // 端口绑定被拒绝（os error 10013 / WSAEACCES）——常见于二次使用时上一个
@@ -1213,6 +2140,11 @@ impl NetworkService {
             return base.to_string();
         }
 
+        // 密码不一致、端口占用、版本不兼容等已知原因直接给出对应提示
+        if let Some(kind) = error_kind {
+            return kind.hint().to_string();
+        }
+
         // 优先使用 stderr/stdout 中的具体错误信息（跳过通用汇总行）
         let stderr_hint = recent_stderr
             .iter()
@@ -1263,7 +2195,50 @@ impl NetworkService {
         "EasyTier 进程意外终止：可能被安全软件拦截、虚拟网卡创建失败或缺少运行库，请尝试以管理员身份运行并将本软件加入杀毒软件白名单".to_string()
     }
 
-    /// 监控标准输出，解析虚拟 IP
+    /// 把识别出的失败原因写入连接状态
+    ///
+    /// 虚拟网卡创建失败无论何时都是致命的；其余错误只在尚未连上时作为启动失败原因，
+    /// 已连接后（例如某个后加入的玩家密码填错）只记日志，不影响自己的连接。
+    async fn report_error(
+        kind: EasyTierErrorKind,
+        line: &str,
+        status: &Mutex<ConnectionStatus>,
+        is_running: &Mutex<bool>,
+    ) {
+        log::error!("EasyTier 发生错误（{:?}）: {}", kind, line);
+        if kind == EasyTierErrorKind::TunCreateFailed {
+            *is_running.lock().await = false;
+            *status.lock().await = ConnectionStatus::Error(kind.hint().to_string());
+            return;
+        }
+        if !kind.aborts_startup() {
+            return;
+        }
+        let mut status = status.lock().await;
+        if !matches!(*status, ConnectionStatus::Connected(_) | ConnectionStatus::Error(_)) {
+            *status = ConnectionStatus::Error(kind.hint().to_string());
+        }
+    }
+
+    /// 识别出连接阶段时向前端推送 `easytier-status` 事件（同一阶段节流）
+    fn emit_phase(
+        app_handle: &tauri::AppHandle,
+        tracker: &parking_lot::Mutex<PhaseTracker>,
+        phase: ConnectionPhase,
+        raw: &str,
+    ) {
+        use tauri::Emitter;
+        if !tracker.lock().should_emit(phase, std::time::Instant::now()) {
+            return;
+        }
+        log::info!("EasyTier 连接阶段: {}", phase.label());
+        let _ = app_handle.emit(
+            "easytier-status",
+            EasyTierStatusEvent { phase, raw: raw.to_string() },
+        );
+    }
+
+    /// 监控标准输出，解析虚拟 IP 与连接阶段
     ///
     /// 注意：easytier-core 2.5.0 将运行日志（包括 `tun device error`、
     /// `Failed to create adapter` 等致命错误）输出到 stdout 而非 stderr，
@@ -1274,6 +2249,8 @@ impl NetworkService {
         status: Arc<Mutex<ConnectionStatus>>,
         is_running: Arc<Mutex<bool>>,
         last_stderr: Arc<Mutex<std::collections::VecDeque<String>>>,
+        app_handle: tauri::AppHandle,
+        phase_tracker: Arc<parking_lot::Mutex<PhaseTracker>>,
     ) {
         let reader = BufReader::new(stdout);
         let mut lines = reader.lines();
@@ -1282,6 +2259,10 @@ impl NetworkService {
             // 打印所有输出用于调试
             log::info!("EasyTier stdout: {}", line);
 
+            if let Some(phase) = classify_line(&line) {
+                Self::emit_phase(&app_handle, &phase_tracker, phase, &line);
+            }
+
             // 将含关键信息的行缓存进 last_stderr（统一作为"最近日志"缓冲区），
             // 供进程意外退出时 describe_exit_failure 定位真正原因。
             // easytier 的致命错误以 anyhow 错误链形式输出（形如 "0: xxx" / "1: yyy" / "2: ..."），
@@ -1313,14 +2294,13 @@ impl NetworkService {
             }
 
             // 虚拟网卡（TUN）创建失败——这是 Windows 上最高频的致命错误，
-            // 在 2.5.0 中通过 stdout 输出，必须在此处捕获并给出可操作的提示
-            if line.contains("tun device error") || line.contains("Failed to create adapter") {
-                log::error!("检测到虚拟网卡创建失败: {}", line);
-                *is_running.lock().await = false;
-                *status.lock().await = ConnectionStatus::Error(
-                    "虚拟网卡创建失败：请右键以管理员身份运行 MCTier，并将本软件加入杀毒软件/防火墙白名单；若仍失败，请重启电脑后重试".to_string(),
-                );
-                continue;
+            // 在 2.5.0 中通过 stdout 输出，必须在此处捕获并给出可操作的提示；
+            // 密码不一致、端口占用等其他已知错误同样转换为具体提示
+            if let Some(kind) = classify_error(&line) {
+                Self::report_error(kind, &line, &status, &is_running).await;
+                if kind == EasyTierErrorKind::TunCreateFailed {
+                    continue;
+                }
             }
 
             // WebSocket 节点升级失败（通常是反向代理/上游配置问题）
@@ -1366,7 +2346,10 @@ impl NetworkService {
                             // 只接受 1-254 的主机地址
                             if last_octet >= 1 && last_octet <= 254 {
                                 log::info!("✅ 从输出中提取到有效的虚拟 IP: {}", ip);
-                                *virtual_ip.lock().await = Some(ip.clone());
+                                let previous = virtual_ip.lock().await.replace(ip.clone());
+                                if previous.as_deref() != Some(ip.as_str()) {
+                                    Self::emit_phase(&app_handle, &phase_tracker, ConnectionPhase::IpAssigned, &line);
+                                }
                                 *status.lock().await = ConnectionStatus::Connected(ip);
                             } else {
                                 log::debug!("跳过无效的主机地址: {} (最后一位: {})", ip, last_octet);
@@ -1386,6 +2369,8 @@ impl NetworkService {
         is_running: Arc<Mutex<bool>>,
         status: Arc<Mutex<ConnectionStatus>>,
         last_stderr: Arc<Mutex<std::collections::VecDeque<String>>>,
+        app_handle: tauri::AppHandle,
+        phase_tracker: Arc<parking_lot::Mutex<PhaseTracker>>,
     ) {
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
@@ -1393,6 +2378,10 @@ impl NetworkService {
         while let Ok(Some(line)) = lines.next_line().await {
             log::warn!("EasyTier stderr: {}", line);
 
+            if let Some(phase) = classify_line(&line) {
+                Self::emit_phase(&app_handle, &phase_tracker, phase, &line);
+            }
+
             // 缓存最近的 stderr 输出（最多保留 30 行），用于进程意外退出时定位原因
             {
                 let mut buf = last_stderr.lock().await;
@@ -1402,18 +2391,11 @@ impl NetworkService {
                 }
             }
 
-            // 检查是否有致命错误
-            if line.contains("error") || line.contains("Error") || line.contains("ERROR") {
+            // 识别常见失败原因并设置具体的错误提示
+            if let Some(kind) = classify_error(&line) {
+                Self::report_error(kind, &line, &status, &is_running).await;
+            } else if line.contains("error") || line.contains("Error") || line.contains("ERROR") {
                 log::error!("EasyTier 发生错误: {}", line);
-                
-                // 检查是否是 TUN 设备创建失败
-                if line.contains("tun device error") || line.contains("Failed to create adapter") {
-                    log::error!("TUN 设备创建失败，可能是缺少 WinTun 驱动或权限不足");
-                    *is_running.lock().await = false;
-                    *status.lock().await = ConnectionStatus::Error(
-                        "虚拟网卡创建失败：请以管理员身份运行，并确认 WinTun 驱动正常、未被安全软件拦截".to_string()
-                    );
-                }
             }
         }
 
@@ -1427,6 +2409,7 @@ impl NetworkService {
         is_running: Arc<Mutex<bool>>,
         virtual_ip: Arc<Mutex<Option<String>>>,
         last_stderr: Arc<Mutex<std::collections::VecDeque<String>>>,
+        connection_lost: Arc<Notify>,
     ) {
         loop {
             sleep(Duration::from_secs(1)).await;
@@ -1446,8 +2429,9 @@ impl NetworkService {
                         let already_error = matches!(current, ConnectionStatus::Error(_));
 
                         if was_connected {
-                            // 连接成功后进程退出，视为正常断开
-                            *status.lock().await = ConnectionStatus::Disconnected;
+                            // 连接成功后进程意外退出：进入重连宽限期，由上层决定重连或退出大厅
+                            log::warn!("⚠️ 已连接状态下 EasyTier 进程退出，进入重连宽限期");
+                            *status.lock().await = ConnectionStatus::Reconnecting;
                         } else if !already_error {
                             // 连接建立前异常退出：根据退出码 + stderr 生成可读原因
                             let recent: Vec<String> =
@@ -1460,6 +2444,9 @@ impl NetworkService {
                         *is_running.lock().await = false;
                         *virtual_ip.lock().await = None;
                         *process_guard = None;
+                        if was_connected {
+                            connection_lost.notify_one();
+                        }
                         break;
                     }
                     Ok(None) => {
@@ -1941,6 +2928,112 @@ impl NetworkService {
         *self.is_running.lock().await
     }
 
+    /// EasyTier 进程是否仍在运行（进程句柄存在且尚未退出）
+    pub async fn is_process_alive(&self) -> bool {
+        match self.easytier_process.lock().await.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
+    /// 获取最近一次启动使用的服务器节点地址
+    pub async fn current_server_node(&self) -> Option<String> {
+        self.last_launch.lock().await.as_ref().map(|params| params.server_node.clone())
+    }
+
+    /// 使用最近一次的启动参数重新拉起 EasyTier（断线重连）
+    /// 
+    /// # 参数
+    /// * `app_handle` - Tauri应用句柄
+    /// 
+    /// # 返回
+    /// * `Ok(String)` - 重连成功，返回虚拟 IP
+    /// * `Err(AppError)` - 没有可复用的启动参数或启动失败
+    pub async fn relaunch(&self, app_handle: &tauri::AppHandle) -> Result<RelaunchOutcome, AppError> {
+        let params = self
+            .last_launch
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| AppError::NetworkError("没有可用于重连的启动参数".to_string()))?;
+
+        log::info!("🔄 正在重新连接 EasyTier 网络: {}", params.network_name);
+        *self.optimistic_ip.lock().await = self.ip_cache.lock().await.get(&params.network_name);
+        *self.pending_confirmation.lock().await = None;
+        let result = self
+            .start_easytier_with_config(
+                params.network_name,
+                params.network_key,
+                params.server_node,
+                params.player_name,
+                app_handle,
+                params.global_config,
+                params.lobby_config,
+            )
+            .await;
+        *self.optimistic_ip.lock().await = None;
+
+        Ok(RelaunchOutcome {
+            virtual_ip: result?,
+            confirmation: self.pending_confirmation.lock().await.take(),
+        })
+    }
+
+    /// 放弃当前虚拟 IP 并重新分配（与其他成员的虚拟 IP 冲突且仲裁需要让出时调用）
+    ///
+    /// 丢弃缓存 IP 后停止 EasyTier，再用最近一次的启动参数重新启动，等待 DHCP 分配新的地址。
+    ///
+    /// # 返回
+    /// * `Ok(String)` - 新分配的虚拟 IP
+    /// * `Err(AppError)` - 没有可复用的启动参数或启动失败
+    pub async fn renew_virtual_ip(&self, app_handle: &tauri::AppHandle) -> Result<String, AppError> {
+        let network_name = self
+            .last_launch
+            .lock()
+            .await
+            .as_ref()
+            .map(|params| params.network_name.clone())
+            .ok_or_else(|| AppError::NetworkError("没有可用于重新分配的启动参数".to_string()))?;
+
+        log::warn!("🔁 放弃当前虚拟 IP，重新启动 EasyTier 以重新分配: {}", network_name);
+        self.ip_cache.lock().await.forget(&network_name);
+        self.stop_easytier().await?;
+        Ok(self.relaunch(app_handle).await?.virtual_ip)
+    }
+
+    /// 后台等待实际分配的虚拟 IP，与乐观使用的缓存 IP 不一致时校正连接状态
+    fn spawn_ip_confirmation(&self, optimistic_ip: String) -> oneshot::Receiver<String> {
+        let (tx, rx) = oneshot::channel();
+        let virtual_ip = Arc::clone(&self.virtual_ip);
+        let is_running = Arc::clone(&self.is_running);
+        let status = Arc::clone(&self.status);
+        let ip_cache = Arc::clone(&self.ip_cache);
+        let last_launch = Arc::clone(&self.last_launch);
+
+        tokio::spawn(async move {
+            let Some(actual) = await_confirmed_ip(&virtual_ip, &is_running, VIRTUAL_IP_CONFIRM_TIMEOUT).await else {
+                log::warn!("⚠️ 未能确认实际分配的虚拟 IP，继续使用缓存 IP: {}", optimistic_ip);
+                return;
+            };
+            if let Some(params) = last_launch.lock().await.as_ref() {
+                ip_cache.lock().await.remember(&params.network_name, &actual);
+            }
+            match reconcile_virtual_ip(&optimistic_ip, &actual) {
+                Some(corrected) => {
+                    log::warn!("⚠️ 实际分配的虚拟 IP 与缓存不一致: {} -> {}", optimistic_ip, corrected);
+                    let mut current = status.lock().await;
+                    if matches!(*current, ConnectionStatus::Connected(_)) {
+                        *current = ConnectionStatus::Connected(corrected);
+                    }
+                }
+                None => log::info!("✅ 已确认虚拟 IP 与缓存一致: {}", actual),
+            }
+            let _ = tx.send(actual);
+        });
+
+        rx
+    }
+
     /// 重启服务
     /// 
     /// # 参数
@@ -1962,6 +3055,7 @@ impl NetworkService {
         app_handle: &tauri::AppHandle,
     ) -> Result<String, AppError> {
         log::info!("正在重启 EasyTier 服务...");
+        reset_connect_cancel();
 
         // 先停止服务
         self.stop_easytier().await?;
@@ -2198,6 +3292,7 @@ mod tests {
         let config = NetworkConfig {
             easytier_path: PathBuf::from("custom/path/easytier.exe"),
             config_dir: PathBuf::from("custom/config"),
+            ..Default::default()
         };
         
         assert_eq!(config.easytier_path, PathBuf::from("custom/path/easytier.exe"));
@@ -2209,6 +3304,7 @@ mod tests {
         let config = NetworkConfig {
             easytier_path: PathBuf::from("test/easytier.exe"),
             config_dir: PathBuf::from("test/config"),
+            ..Default::default()
         };
         
         let _service = NetworkService::new(config);
@@ -2320,4 +3416,335 @@ mod tests {
             assert_eq!(service.get_virtual_ip().await, None);
         }
     }
+
+    #[test]
+    fn test_preferred_relay() {
+        assert!(validate_relay_node("tcp://relay.example.com:11010").is_ok());
+        assert!(validate_relay_node("wss://relay.example.com/path").is_ok());
+        assert!(validate_relay_node("relay.example.com:11010").is_err());
+        assert!(validate_relay_node("http://relay.example.com").is_err());
+        assert!(validate_relay_node("tcp://:11010").is_err());
+        assert!(validate_relay_node("tcp://a.com:1 & calc").is_err());
+
+        let mut peers = vec!["udp://primary:11010".to_string(), "tcp://backup:11010".to_string()];
+        insert_preferred_relay(&mut peers, Some("tcp://backup:11010/"));
+        insert_preferred_relay(&mut peers, Some("tcp://relay:11010"));
+        assert_eq!(peers, vec!["udp://primary:11010", "tcp://relay:11010", "tcp://backup:11010"]);
+        // 与主节点相同时不重复添加
+        insert_preferred_relay(&mut peers, Some("UDP://primary:11010"));
+        assert_eq!(peers.len(), 3);
+    }
+
+    #[test]
+    fn test_validate_extra_args() {
+        let args = |v: &[&str]| v.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_extra_args(&args(&["--disable-ipv6", "--mtu", "1300", "--compression=zstd"])).is_ok());
+        // 覆盖 MCTier 管理的选项
+        assert!(validate_extra_args(&args(&["--network-secret", "x"])).is_err());
+        assert!(validate_extra_args(&args(&["--rpc-portal=0.0.0.0:15888"])).is_err());
+        assert!(validate_extra_args(&args(&["-ptcp://evil.example:11010"])).is_err());
+        assert!(validate_extra_args(&args(&["-c", "other.toml"])).is_err());
+        // 注入字符与空参数
+        assert!(validate_extra_args(&args(&["--mtu", "1300 & calc"])).is_err());
+        assert!(validate_extra_args(&args(&["--mtu\n--peers"])).is_err());
+        assert!(validate_extra_args(&args(&[" "])).is_err());
+    }
+
+    #[test]
+    fn test_launch_args_order() {
+        use crate::modules::config_manager::EasyTierAdvancedConfig;
+
+        let base = vec!["--network-name".to_string(), "lobby".to_string()];
+        let advanced = EasyTierAdvancedConfig {
+            no_tun: true,
+            ..Default::default()
+        };
+        let extra = vec!["--disable-ipv6".to_string()];
+        let collect = |cmd: &Command| {
+            cmd.as_std().get_args().map(|a| a.to_string_lossy().to_string()).collect::<Vec<_>>()
+        };
+
+        let mut cmd = Command::new("easytier-core.exe");
+        NetworkService::apply_launch_args(&mut cmd, &base, &advanced, &extra);
+        let args = collect(&cmd);
+        let pos = |a: &str| args.iter().position(|x| x == a).unwrap();
+        assert_eq!(&args[..2], &base[..]);
+        assert!(pos("--no-tun") < pos("--disable-ipv6"));
+        assert_eq!(args.last().map(String::as_str), Some("--disable-ipv6"));
+
+        // 非法的自定义参数整体忽略，基础参数和高级配置不受影响
+        let mut cmd = Command::new("easytier-core.exe");
+        NetworkService::apply_launch_args(&mut cmd, &base, &advanced, &["--peers".to_string(), "tcp://x:1".to_string()]);
+        let args = collect(&cmd);
+        assert!(args.contains(&"--no-tun".to_string()));
+        assert!(!args.contains(&"--peers".to_string()));
+    }
+
+    #[test]
+    fn test_apply_network_mode() {
+        use crate::modules::config_manager::{EasyTierAdvancedConfig, PortForwardRule};
+
+        // 虚拟网卡模式保持原样
+        let advanced = EasyTierAdvancedConfig { dhcp: true, ..Default::default() };
+        assert_eq!(apply_network_mode(advanced.clone(), NetworkMode::Tun, Some("10.126.126.1")), advanced);
+
+        // 房主使用纯中继模式：只关闭 TUN，不添加转发
+        let relay = apply_network_mode(advanced.clone(), NetworkMode::Relay, None);
+        assert!(relay.no_tun);
+        assert!(relay.port_forward_rules.is_empty());
+
+        // 加入者：只转发 Minecraft TCP 端口到房主
+        let relay = apply_network_mode(EasyTierAdvancedConfig::default(), NetworkMode::Relay, Some("10.126.126.1"));
+        assert_eq!(
+            relay.port_forward_rules,
+            vec![PortForwardRule {
+                protocol: "tcp".to_string(),
+                bind_addr: "127.0.0.1:25565".to_string(),
+                dst_addr: "10.126.126.1:25565".to_string(),
+            }]
+        );
+
+        // 已有同绑定地址的规则不重复添加
+        let advanced = EasyTierAdvancedConfig {
+            port_forward_rules: vec![PortForwardRule {
+                protocol: "tcp".to_string(),
+                bind_addr: "127.0.0.1:25565".to_string(),
+                dst_addr: "10.126.126.9:25566".to_string(),
+            }],
+            ..Default::default()
+        };
+        let relay = apply_network_mode(advanced, NetworkMode::Relay, Some("10.126.126.1"));
+        let rules: Vec<String> = relay
+            .port_forward_rules
+            .iter()
+            .map(|r| format!("{}://{}/{}", r.protocol, r.bind_addr, r.dst_addr))
+            .collect();
+        assert_eq!(rules, vec!["tcp://127.0.0.1:25565/10.126.126.9:25566"]);
+    }
+
+    #[test]
+    fn test_performance_args() {
+        use crate::modules::config_manager::EasyTierAdvancedConfig;
+
+        let advanced = EasyTierAdvancedConfig::default();
+        // 默认不追加任何参数，保持 EasyTier 自身默认
+        let defaults = NetworkConfig::default();
+        assert!(performance_args(defaults.compression, defaults.encryption, &advanced, &[]).is_empty());
+
+        assert_eq!(
+            performance_args(CompressionAlgorithm::Zstd, EncryptionMode::Chacha20, &advanced, &[]),
+            vec!["--compression", "zstd", "--encryption-algorithm", "chacha20"]
+        );
+        assert_eq!(
+            performance_args(CompressionAlgorithm::None, EncryptionMode::Disabled, &advanced, &[]),
+            vec!["--disable-encryption"]
+        );
+
+        // 高级配置或自定义参数已指定时不重复添加
+        let advanced = EasyTierAdvancedConfig {
+            compression: Some("zstd".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            performance_args(CompressionAlgorithm::Zstd, EncryptionMode::Xor, &advanced, &["--disable-encryption".to_string()]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_listener_protocol() {
+        assert_eq!(ListenerProtocol::Auto.listener(false, 11010), ("udp://0.0.0.0:11010".to_string(), "udp"));
+        assert_eq!(ListenerProtocol::Auto.listener(true, 11010), ("ws://0.0.0.0:11010/".to_string(), "ws"));
+        assert_eq!(ListenerProtocol::Tcp.listener(true, 11010), ("tcp://0.0.0.0:11010".to_string(), "tcp"));
+    }
+
+    #[test]
+    fn test_parse_tasklist_output() {
+        let output = "\"easytier-core.exe\",\"1234\",\"Console\",\"1\",\"12,345 K\"\r\n\"EasyTier-Core.exe\",\"5678\",\"Console\",\"1\",\"8,000 K\"\r\n";
+        assert_eq!(parse_tasklist_output(output), vec![1234, 5678]);
+
+        assert!(parse_tasklist_output("INFO: No tasks are running which match the specified criteria.").is_empty());
+        assert!(parse_tasklist_output("信息: 没有运行的任务匹配指定标准。").is_empty());
+        assert!(parse_tasklist_output("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_easytier_version() {
+        let v = parse_easytier_version("easytier-core 2.5.0-8f3ac4b1\n").unwrap();
+        assert_eq!((v.major, v.minor, v.patch), (2, 5, 0));
+        assert_eq!(v.raw, "easytier-core 2.5.0-8f3ac4b1");
+        assert_eq!(v.to_string(), "2.5.0");
+        assert_eq!(parse_easytier_version("easytier-core v1.2.3").map(|v| v.minor), Some(2));
+        assert_eq!(parse_easytier_version("easytier-core 2.4.5+dirty").map(|v| v.patch), Some(5));
+        assert!(parse_easytier_version("easytier-core unknown").is_none());
+        assert!(parse_easytier_version("").is_none());
+
+        let compat = |text: &str| easytier_compatibility_warning(&parse_easytier_version(text).unwrap());
+        assert!(compat("easytier-core 1.2.3").is_some());
+        assert!(compat("easytier-core 2.3.9").is_some());
+        assert!(compat("easytier-core 2.4.0").is_none());
+        assert!(compat("easytier-core 2.5.0").is_none());
+        assert!(compat("easytier-core 3.0.0").is_none());
+    }
+
+    #[test]
+    fn test_decide_instance_conflict_with_existing_instances() {
+        // 模拟进程列表中已有另一个 MCTier/EasyTier 的 easytier-core
+        let mock_output = "\"easytier-core.exe\",\"4242\",\"Console\",\"1\",\"20,480 K\"";
+        let pids = parse_tasklist_output(mock_output);
+        assert_eq!(pids, vec![4242]);
+
+        assert_eq!(decide_instance_conflict(&pids, true), InstanceConflictAction::Cleanup);
+        assert_eq!(decide_instance_conflict(&pids, false), InstanceConflictAction::Abort);
+    }
+
+    #[test]
+    fn test_decide_instance_conflict_without_instances() {
+        assert_eq!(decide_instance_conflict(&[], true), InstanceConflictAction::Proceed);
+        assert_eq!(decide_instance_conflict(&[], false), InstanceConflictAction::Proceed);
+    }
+
+    #[tokio::test]
+    async fn test_auto_cleanup_instances_default() {
+        let service = NetworkService::new_with_defaults();
+        assert!(service.is_auto_cleanup_instances().await);
+
+        service.set_auto_cleanup_instances(false).await;
+        assert!(!service.is_auto_cleanup_instances().await);
+    }
+
+    #[test]
+    fn test_reconnect_grace_short_drop_recovers() {
+        let start = Instant::now();
+        let mut grace = ReconnectGrace::new(Duration::from_secs(30));
+        assert_eq!(grace.check(start), GraceState::Online);
+
+        grace.connection_lost(start);
+        assert_eq!(grace.check(start + Duration::from_secs(5)), GraceState::Waiting(Duration::from_secs(25)));
+
+        // 宽限期内再次丢失不会重新计时
+        grace.connection_lost(start + Duration::from_secs(5));
+        assert_eq!(grace.check(start + Duration::from_secs(10)), GraceState::Waiting(Duration::from_secs(20)));
+
+        // 几秒后恢复：无缝继续
+        let downtime = grace.connection_restored(start + Duration::from_secs(10));
+        assert_eq!(downtime, Some(Duration::from_secs(10)));
+        assert_eq!(grace.check(start + Duration::from_secs(60)), GraceState::Online);
+        assert_eq!(grace.connection_restored(start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_reconnect_grace_long_drop_expires() {
+        let start = Instant::now();
+        let mut grace = ReconnectGrace::new(Duration::from_secs(30));
+        grace.connection_lost(start);
+
+        assert!(matches!(grace.check(start + Duration::from_secs(29)), GraceState::Waiting(_)));
+        assert_eq!(grace.check(start + Duration::from_secs(30)), GraceState::Expired);
+        assert_eq!(grace.check(start + Duration::from_secs(120)), GraceState::Expired);
+
+        // 宽限期为 0 时断线立即退出
+        let mut immediate = ReconnectGrace::new(Duration::ZERO);
+        immediate.connection_lost(start);
+        assert_eq!(immediate.check(start), GraceState::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_exit_while_connected_enters_grace() {
+        #[cfg(windows)]
+        let child = Command::new("cmd").args(["/C", "exit", "1"]).spawn().unwrap();
+        #[cfg(not(windows))]
+        let child = Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+
+        let service = NetworkService::new_with_defaults();
+        *service.easytier_process.lock().await = Some(child);
+        *service.is_running.lock().await = true;
+        *service.status.lock().await = ConnectionStatus::Connected("10.126.126.2".to_string());
+        *service.virtual_ip.lock().await = Some("10.126.126.2".to_string());
+        let lost = service.connection_lost_signal();
+
+        NetworkService::monitor_process(
+            Arc::clone(&service.easytier_process),
+            Arc::clone(&service.status),
+            Arc::clone(&service.is_running),
+            Arc::clone(&service.virtual_ip),
+            Arc::clone(&service.last_stderr),
+            service.connection_lost_signal(),
+        )
+        .await;
+
+        assert_eq!(service.check_connection().await, ConnectionStatus::Reconnecting);
+        assert!(!service.is_running().await);
+        tokio::time::timeout(Duration::from_secs(1), lost.notified())
+            .await
+            .expect("连接丢失通知应已发出");
+    }
+
+    #[test]
+    fn test_virtual_ip_cache_per_network() {
+        let mut cache = VirtualIpCache::default();
+        assert_eq!(cache.get("lobby-a"), None);
+
+        cache.remember("lobby-a", "10.126.126.5");
+        cache.remember("lobby-b", "10.126.126.9");
+        assert_eq!(cache.get("lobby-a").as_deref(), Some("10.126.126.5"));
+        assert_eq!(cache.get("lobby-b").as_deref(), Some("10.126.126.9"));
+
+        cache.remember("lobby-a", "10.126.126.6");
+        assert_eq!(cache.get("lobby-a").as_deref(), Some("10.126.126.6"));
+
+        // 冲突让出后丢弃缓存，不影响其他网络
+        cache.forget("lobby-a");
+        assert_eq!(cache.get("lobby-a"), None);
+        assert_eq!(cache.get("lobby-b").as_deref(), Some("10.126.126.9"));
+    }
+
+    #[tokio::test]
+    async fn test_optimistic_ip_corrected_after_confirmation() {
+        let virtual_ip = Arc::new(Mutex::new(None::<String>));
+        let is_running = Arc::new(Mutex::new(true));
+
+        // 乐观恢复使用缓存 IP，稍后 EasyTier 输出了不同的实际 IP
+        let optimistic = "10.126.126.5".to_string();
+        let writer = Arc::clone(&virtual_ip);
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(150)).await;
+            *writer.lock().await = Some("10.126.126.7".to_string());
+        });
+
+        let actual = await_confirmed_ip(&virtual_ip, &is_running, Duration::from_secs(2)).await;
+        assert_eq!(actual.as_deref(), Some("10.126.126.7"));
+        assert_eq!(reconcile_virtual_ip(&optimistic, "10.126.126.7").as_deref(), Some("10.126.126.7"));
+
+        // 实际 IP 与缓存一致时无需校正
+        assert_eq!(reconcile_virtual_ip(&optimistic, &optimistic), None);
+    }
+
+    #[tokio::test]
+    async fn test_ip_confirmation_gives_up_when_process_exits() {
+        let virtual_ip = Mutex::new(None::<String>);
+        let is_running = Mutex::new(false);
+        assert_eq!(await_confirmed_ip(&virtual_ip, &is_running, Duration::from_secs(2)).await, None);
+    }
+
+    #[test]
+    fn test_elevated_restart_params_roundtrip() {
+        let args = vec![
+            "mctier://join?name=a b".to_string(),
+            format!("{}123", ELEVATED_RESTART_ARG),
+            "--minimized".to_string(),
+        ];
+        let params = elevated_restart_params(&args, 4242);
+        assert_eq!(params, "\"mctier://join?name=a b\" --minimized --elevated-restart=4242");
+
+        let relaunched = vec![
+            "mctier.exe".to_string(),
+            "--minimized".to_string(),
+            format!("{}4242", ELEVATED_RESTART_ARG),
+        ];
+        assert_eq!(parse_elevated_restart_pid(relaunched), Some(4242));
+        assert_eq!(parse_elevated_restart_pid(vec!["mctier.exe".to_string()]), None);
+        assert_eq!(parse_elevated_restart_pid(vec![format!("{}abc", ELEVATED_RESTART_ARG)]), None);
+    }
 }
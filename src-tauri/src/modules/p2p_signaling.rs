@@ -1,4 +1,4 @@
-use std::net::{UdpSocket, SocketAddr};
+use std::net::{Ipv4Addr, UdpSocket, SocketAddr};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::RwLock;
@@ -6,6 +6,13 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tauri::Emitter;
 use crate::modules::error::AppError;
+use crate::modules::input_limits::{clamp_remote, MAX_LAN_MOTD_CHARS, MAX_PLAYER_NAME_CHARS};
+use crate::modules::ip_conflict::IpConflict;
+use crate::modules::lobby_manager::SharedDisplayNames;
+use crate::modules::presence::Presence;
+use crate::modules::signaling_crypto::{is_plaintext_packet, is_sealed_field, SignalingCipher, SignalingCryptoError};
+use crate::modules::share_presence::ShareActivity;
+use crate::modules::upnp_mapping::{self, IgdDiscovery, UpnpPortMapping};
 
 /// P2P 信令消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +25,18 @@ pub enum P2PMessage {
         #[serde(rename = "playerName")]
         player_name: String,
         port: u16,
+        /// 是否以游客身份加入（旧版本不携带该字段，视为非游客）
+        #[serde(rename = "isGuest", default)]
+        is_guest: bool,
+        /// 文件共享端口（旧版本不携带，使用默认端口）
+        #[serde(rename = "filePort", default, skip_serializing_if = "Option::is_none")]
+        file_port: Option<u16>,
+        /// 聊天端口（旧版本不携带，使用默认端口）
+        #[serde(rename = "chatPort", default, skip_serializing_if = "Option::is_none")]
+        chat_port: Option<u16>,
+        /// 在线状态（旧版本不携带，视为在线）
+        #[serde(default)]
+        presence: Presence,
     },
     /// 玩家发现响应
     PlayerDiscoveryResponse {
@@ -26,6 +45,18 @@ pub enum P2PMessage {
         #[serde(rename = "playerName")]
         player_name: String,
         port: u16,
+        /// 是否以游客身份加入（旧版本不携带该字段，视为非游客）
+        #[serde(rename = "isGuest", default)]
+        is_guest: bool,
+        /// 文件共享端口（旧版本不携带，使用默认端口）
+        #[serde(rename = "filePort", default, skip_serializing_if = "Option::is_none")]
+        file_port: Option<u16>,
+        /// 聊天端口（旧版本不携带，使用默认端口）
+        #[serde(rename = "chatPort", default, skip_serializing_if = "Option::is_none")]
+        chat_port: Option<u16>,
+        /// 在线状态（旧版本不携带，视为在线）
+        #[serde(default)]
+        presence: Presence,
     },
     /// WebRTC Offer
     Offer {
@@ -49,6 +80,12 @@ pub enum P2PMessage {
         #[serde(rename = "micEnabled")]
         mic_enabled: bool,
     },
+    /// 在线状态变化
+    PresenceUpdate {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        presence: Presence,
+    },
     /// 心跳
     Heartbeat {
         #[serde(rename = "playerId")]
@@ -60,6 +97,102 @@ pub enum P2PMessage {
         #[serde(rename = "playerId")]
         player_id: String,
     },
+    /// 大厅已满（创建者拒绝新玩家加入）
+    LobbyFull {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "maxPlayers")]
+        max_players: u32,
+    },
+    /// 踢出玩家（仅大厅创建者发送）
+    Kicked {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        reason: String,
+    },
+    /// 说话状态（语音活动检测）
+    SpeakingState {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "isSpeaking")]
+        is_speaking: bool,
+    },
+    /// 准备状态（玩家列表按准备状态排序时使用）
+    ReadyState {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "isReady")]
+        is_ready: bool,
+    },
+    /// 麦克风输入电平（量化等级，已限流）
+    InputLevel {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        level: u8,
+    },
+    /// 连接修复请求：请求目标玩家重置与发起方的语音连接并重新响应发现包
+    RepairRequest {
+        from: String,
+        to: String,
+    },
+    /// 后端语音连接 offer（SDP，见 voice_session 模块）
+    VoiceOffer {
+        from: String,
+        to: String,
+        sdp: String,
+    },
+    /// 后端语音连接 answer
+    VoiceAnswer {
+        from: String,
+        to: String,
+        sdp: String,
+    },
+    /// 关闭后端语音连接
+    VoiceClose {
+        from: String,
+        to: String,
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+    /// 创建者宣告：发送方认为自己是大厅创建者（用于创建者离开后的重新选举与脑裂收敛）
+    CreatorAnnounce {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "creatorVirtualIp")]
+        creator_virtual_ip: String,
+    },
+    /// 聊天消息表情回应（added 为 false 表示取消回应）
+    MessageReaction {
+        #[serde(rename = "messageId")]
+        message_id: String,
+        emoji: String,
+        #[serde(rename = "playerId")]
+        player_id: String,
+        added: bool,
+    },
+    /// 正在浏览 / 下载某个共享（由共享者提示"某某正在浏览你的共享"）
+    BrowsingShare {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "shareId")]
+        share_id: String,
+        activity: ShareActivity,
+    },
+    /// 本机开放的 Minecraft 局域网世界（port 为 None 表示世界已关闭）
+    McLanWorld {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        port: Option<u16>,
+        #[serde(default)]
+        motd: String,
+    },
+    /// 虚拟 IP 声明（用于检测 DHCP 分配冲突）
+    IpClaim {
+        #[serde(rename = "playerId")]
+        player_id: String,
+        #[serde(rename = "virtualIp")]
+        virtual_ip: String,
+    },
 }
 
 /// 对等节点信息
@@ -69,6 +202,244 @@ pub struct PeerInfo {
     pub player_name: String,
     pub addr: SocketAddr,
     pub last_seen: std::time::Instant,
+    /// 是否为游客（只读参与，不占用大厅名额）
+    pub is_guest: bool,
+    /// 在线状态
+    pub presence: Presence,
+}
+
+/// 判断新玩家加入时大厅是否已满
+///
+/// 人数按本机 + 已发现的非游客 peers 计算；已在 peers 中的玩家（重复发现/心跳）不受限制。
+/// 游客不占用名额，游客自身加入也不受上限限制（由调用方跳过检查）。
+///
+/// # 参数
+/// * `peers` - 当前已发现的对等节点
+/// * `player_id` - 请求加入的玩家 ID
+/// * `max_players` - 人数上限（含本机），None 表示不限制
+pub fn is_lobby_full(
+    peers: &HashMap<String, PeerInfo>,
+    player_id: &str,
+    max_players: Option<u32>,
+) -> bool {
+    match max_players {
+        Some(max) if !peers.contains_key(player_id) => {
+            let members = peers.values().filter(|p| !p.is_guest).count() as u64;
+            members + 1 >= max as u64
+        }
+        _ => false,
+    }
+}
+
+/// 发现广播、心跳与其它局域网广播统一使用的目标地址
+///
+/// 使用受限广播地址 255.255.255.255，而不是虚拟 IP 或某个固定网段的定向广播地址：
+/// no-tun 模式下虚拟 IP 不存在于系统网卡中，而硬编码某个网段的定向广播地址在其它子网下根本发不出去，
+/// 心跳发不出去会导致 90 秒后所有 peer 被误判超时。
+pub fn broadcast_target(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::BROADCAST, port))
+}
+
+/// 截断其他 peer 发来的超长显示字段（玩家名称）
+fn clamp_remote_fields(message: P2PMessage) -> P2PMessage {
+    match message {
+        P2PMessage::PlayerDiscovery { player_id, player_name, port, is_guest, file_port, chat_port, presence } => {
+            P2PMessage::PlayerDiscovery {
+                player_id,
+                player_name: clamp_remote("玩家名称", player_name, MAX_PLAYER_NAME_CHARS),
+                port,
+                is_guest,
+                file_port,
+                chat_port,
+                presence,
+            }
+        }
+        P2PMessage::PlayerDiscoveryResponse { player_id, player_name, port, is_guest, file_port, chat_port, presence } => {
+            P2PMessage::PlayerDiscoveryResponse {
+                player_id,
+                player_name: clamp_remote("玩家名称", player_name, MAX_PLAYER_NAME_CHARS),
+                port,
+                is_guest,
+                file_port,
+                chat_port,
+                presence,
+            }
+        }
+        P2PMessage::McLanWorld { player_id, port, motd } => P2PMessage::McLanWorld {
+            player_id,
+            port,
+            motd: clamp_remote("世界描述", motd, MAX_LAN_MOTD_CHARS),
+        },
+        other => other,
+    }
+}
+
+/// 同一 peer 重复发现时的最小响应间隔
+const DISCOVERY_RESPONSE_MIN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 发现响应频率控制
+///
+/// 记录对每个 peer 最近一次回应 `PlayerDiscoveryResponse` 的时间，
+/// 在最小间隔内收到同一 peer 的重复发现广播时不再回应，避免多人大厅的广播风暴。
+/// 从未回应过的新 peer 总是立即回应。
+#[derive(Debug)]
+pub struct DiscoveryResponseThrottle {
+    /// 最小响应间隔
+    min_interval: std::time::Duration,
+    /// 每个 peer 最近一次回应时间
+    last_responded: HashMap<String, std::time::Instant>,
+}
+
+impl DiscoveryResponseThrottle {
+    /// 创建新的频率控制器
+    pub fn new(min_interval: std::time::Duration) -> Self {
+        Self {
+            min_interval,
+            last_responded: HashMap::new(),
+        }
+    }
+
+    /// 判断此刻是否应回应该 peer 的发现广播，若应回应则同时记录回应时间
+    ///
+    /// # 参数
+    /// * `player_id` - 发起发现的玩家ID
+    /// * `now` - 当前时间
+    ///
+    /// # 返回
+    /// * `true` - 新 peer 或距上次回应已超过最小间隔
+    /// * `false` - 最小间隔内的重复发现
+    pub fn should_respond(&mut self, player_id: &str, now: std::time::Instant) -> bool {
+        if let Some(last) = self.last_responded.get(player_id) {
+            if now.saturating_duration_since(*last) < self.min_interval {
+                return false;
+            }
+        }
+        self.last_responded.insert(player_id.to_string(), now);
+        true
+    }
+
+    /// 移除某个 peer 的回应记录（玩家离开或超时后，再次出现时应立即回应）
+    pub fn forget(&mut self, player_id: &str) {
+        self.last_responded.remove(player_id);
+    }
+
+    /// 清空所有回应记录
+    pub fn clear(&mut self) {
+        self.last_responded.clear();
+    }
+}
+
+impl Default for DiscoveryResponseThrottle {
+    fn default() -> Self {
+        Self::new(DISCOVERY_RESPONSE_MIN_INTERVAL)
+    }
+}
+
+/// 信令消息编解码
+///
+/// 设置了大厅 network_key 时所有消息都加密发送，收到的加密包解密失败即丢弃。
+/// 开启明文兼容后，额外接受旧版本客户端的明文发现阶段消息（发现、发现响应、心跳、离开），
+/// 并向其回应明文发现响应；SDP、ICE 等其它消息始终只接受加密包。
+#[derive(Clone, Default)]
+pub struct SignalingCodec {
+    cipher: Option<SignalingCipher>,
+    plaintext_compat: bool,
+}
+
+impl SignalingCodec {
+    /// 创建编解码器
+    ///
+    /// # 参数
+    /// * `network_key` - 大厅 network_key，None 表示不加密（全部明文）
+    /// * `plaintext_compat` - 是否兼容旧版本的明文发现
+    pub fn new(network_key: Option<&str>, plaintext_compat: bool) -> Self {
+        Self {
+            cipher: network_key.map(SignalingCipher::from_network_key),
+            plaintext_compat,
+        }
+    }
+
+    /// 是否兼容旧版本的明文发现
+    pub fn plaintext_compat(&self) -> bool {
+        self.cipher.is_some() && self.plaintext_compat
+    }
+
+    /// 编码消息（设置了密钥时加密）
+    pub fn encode(&self, message: &P2PMessage) -> Result<Vec<u8>, AppError> {
+        let json = Self::encode_plaintext(message)?;
+        Ok(match &self.cipher {
+            Some(cipher) => cipher.seal(&json),
+            None => json,
+        })
+    }
+
+    /// 按对方使用的格式编码回复（旧版本客户端的明文发现只能收到明文回复）
+    pub fn encode_reply(&self, message: &P2PMessage, plaintext: bool) -> Result<Vec<u8>, AppError> {
+        if plaintext {
+            Self::encode_plaintext(message)
+        } else {
+            self.encode(message)
+        }
+    }
+
+    /// 编码为明文 JSON（仅用于兼容旧版本的发现阶段）
+    pub fn encode_plaintext(message: &P2PMessage) -> Result<Vec<u8>, AppError> {
+        serde_json::to_vec(message)
+            .map_err(|e| AppError::NetworkError(format!("序列化消息失败: {}", e)))
+    }
+
+    /// 解码数据包
+    ///
+    /// # 返回
+    /// * `Some((消息, 是否为明文包))` - 解码成功
+    /// * `None` - 解密失败、格式错误或不被接受的明文包，应直接丢弃
+    pub fn decode(&self, packet: &[u8]) -> Option<(P2PMessage, bool)> {
+        if is_plaintext_packet(packet) {
+            let message = clamp_remote_fields(serde_json::from_slice::<P2PMessage>(packet).ok()?);
+            let accepted = self.cipher.is_none()
+                || (self.plaintext_compat && Self::is_discovery_phase(&message));
+            return accepted.then_some((message, true));
+        }
+
+        let cipher = self.cipher.as_ref()?;
+        match cipher.open(packet) {
+            Ok(json) => serde_json::from_slice::<P2PMessage>(&json).ok().map(|message| (clamp_remote_fields(message), false)),
+            Err(e) => {
+                log::debug!("丢弃无法解密的信令包: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 加密语音信令字段（未设置密钥时原样返回）
+    pub fn seal_field(&self, value: &str, aad: &[u8]) -> String {
+        match &self.cipher {
+            Some(cipher) => cipher.seal_field(value, aad),
+            None => value.to_string(),
+        }
+    }
+
+    /// 解密语音信令字段
+    ///
+    /// 与 UDP 信令一致：设置了密钥时 SDP / ICE 只接受加密字段，明文兼容模式也不例外。
+    pub fn open_field(&self, value: &str, aad: &[u8]) -> Result<String, SignalingCryptoError> {
+        match &self.cipher {
+            Some(cipher) => cipher.open_field(value, aad),
+            None if is_sealed_field(value) => Err(SignalingCryptoError::DecryptFailed),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// 是否为发现阶段的消息（明文兼容模式下允许明文收发）
+    fn is_discovery_phase(message: &P2PMessage) -> bool {
+        matches!(
+            message,
+            P2PMessage::PlayerDiscovery { .. }
+                | P2PMessage::PlayerDiscoveryResponse { .. }
+                | P2PMessage::Heartbeat { .. }
+                | P2PMessage::PlayerLeft { .. }
+        )
+    }
 }
 
 /// P2P 信令服务
@@ -102,6 +473,110 @@ pub struct P2PSignalingService {
 
     /// 后台任务句柄（接收/发现广播/心跳），停止时统一 abort
     task_handles: Arc<RwLock<Vec<tokio::task::JoinHandle<()>>>>,
+
+    /// 发现响应频率控制（同一 peer 短时间内的重复发现只回应一次）
+    response_throttle: Arc<RwLock<DiscoveryResponseThrottle>>,
+
+    /// 大厅人数上限（仅创建者设置，含本机）
+    max_players: Arc<RwLock<Option<u32>>>,
+
+    /// 是否暂停（虚拟网络重连期间不发心跳、不判定玩家超时）
+    paused: Arc<AtomicBool>,
+
+    /// 本机是否以游客身份加入
+    local_is_guest: Arc<AtomicBool>,
+
+    /// 信令消息编解码（大厅密码派生的加密密钥）
+    codec: Arc<RwLock<SignalingCodec>>,
+
+    /// 最近收到的创建者宣告中虚拟 IP 最小者（取走后清空）
+    announced_creator: Arc<RwLock<Option<String>>>,
+
+    /// 当前大厅创建者的虚拟 IP（踢人等只有创建者能发出的指令据此校验来源）
+    creator_virtual_ip: Arc<RwLock<Option<String>>>,
+
+    /// 最近检测到的虚拟 IP 冲突（取走后清空）
+    ip_conflict: Arc<RwLock<Option<IpConflict>>>,
+
+    /// 是否在网关上通过 UPnP 映射信令端口
+    upnp_enabled: Arc<AtomicBool>,
+
+    /// 当前的 UPnP 端口映射
+    upnp_mapping: Arc<RwLock<Option<Arc<UpnpPortMapping>>>>,
+
+    /// 重名玩家的显示名去重表（与大厅管理器共用）
+    display_names: SharedDisplayNames,
+}
+
+/// 消息来源是否为当前大厅创建者（创建者未知时一律视为否）
+///
+/// 踢人、大厅已满只能由创建者发出，持有大厅密钥的其他成员也能构造这些消息，必须按来源 IP 校验。
+fn is_from_creator(src_ip: std::net::IpAddr, creator_virtual_ip: Option<&str>) -> bool {
+    creator_virtual_ip
+        .and_then(|ip| ip.parse::<std::net::IpAddr>().ok())
+        .is_some_and(|ip| ip == src_ip)
+}
+
+/// 大厅管理器玩家列表的同步操作
+#[derive(Debug)]
+enum PlayerSync {
+    /// 发现玩家（新增或更新名称 / IP）
+    Upsert { player_id: String, player_name: String, virtual_ip: String },
+    /// 玩家离开 / 超时 / 被踢出
+    Remove { player_id: String },
+    /// 准备状态变化
+    Ready { player_id: String, is_ready: bool },
+}
+
+/// 玩家列表同步队列
+///
+/// 由单个后台任务按入队顺序依次应用，接收循环不等待大厅锁（创建 / 加入大厅期间会长时间持有），
+/// 同时保证离开之后不会被更早的发现覆盖、留下幽灵玩家。
+fn player_sync_queue(app: &tauri::AppHandle) -> &'static tokio::sync::mpsc::UnboundedSender<PlayerSync> {
+    static QUEUE: std::sync::OnceLock<tokio::sync::mpsc::UnboundedSender<PlayerSync>> = std::sync::OnceLock::new();
+    QUEUE.get_or_init(|| {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<PlayerSync>();
+        let app = app.clone();
+        tokio::spawn(async move {
+            use tauri::Manager;
+            while let Some(op) = rx.recv().await {
+                let Some(state) = app.try_state::<crate::modules::tauri_commands::AppState>() else {
+                    continue;
+                };
+                let lobby_manager = state.get_lobby_manager();
+                let mut lobby_manager = lobby_manager.lock().await;
+                match op {
+                    PlayerSync::Upsert { player_id, player_name, virtual_ip } => {
+                        lobby_manager.upsert_discovered_player(&player_id, &player_name, &virtual_ip);
+                    }
+                    PlayerSync::Remove { player_id } => {
+                        lobby_manager.remove_player(&player_id);
+                    }
+                    PlayerSync::Ready { player_id, is_ready } => {
+                        if let Err(e) = lobby_manager.update_player_ready_status(&player_id, is_ready) {
+                            log::debug!("同步准备状态失败: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+        tx
+    })
+}
+
+/// 将 P2P 发现的玩家同步到大厅管理器，使 get_players 返回完整的在线玩家
+fn sync_discovered_player(app: &tauri::AppHandle, player_id: &str, player_name: &str, virtual_ip: String) {
+    let _ = player_sync_queue(app).send(PlayerSync::Upsert {
+        player_id: player_id.to_string(),
+        player_name: player_name.to_string(),
+        virtual_ip,
+    });
+}
+
+/// 玩家离开 / 超时 / 被踢出时从大厅管理器的玩家列表中移除
+fn sync_removed_player(app: &tauri::AppHandle, player_id: &str) {
+    crate::modules::voice_session::handle_remote_close(app, player_id, None);
+    let _ = player_sync_queue(app).send(PlayerSync::Remove { player_id: player_id.to_string() });
 }
 
 impl P2PSignalingService {
@@ -120,8 +595,44 @@ impl P2PSignalingService {
             app_handle: Arc::new(RwLock::new(None)),
             running: Arc::new(AtomicBool::new(false)),
             task_handles: Arc::new(RwLock::new(Vec::new())),
+            response_throttle: Arc::new(RwLock::new(DiscoveryResponseThrottle::default())),
+            max_players: Arc::new(RwLock::new(None)),
+            paused: Arc::new(AtomicBool::new(false)),
+            local_is_guest: Arc::new(AtomicBool::new(false)),
+            codec: Arc::new(RwLock::new(SignalingCodec::default())),
+            announced_creator: Arc::new(RwLock::new(None)),
+            creator_virtual_ip: Arc::new(RwLock::new(None)),
+            ip_conflict: Arc::new(RwLock::new(None)),
+            upnp_enabled: Arc::new(AtomicBool::new(false)),
+            upnp_mapping: Arc::new(RwLock::new(None)),
+            display_names: SharedDisplayNames::default(),
         }
     }
+
+    /// 设置与大厅管理器共用的显示名去重表
+    pub fn set_display_names(&mut self, display_names: SharedDisplayNames) {
+        self.display_names = display_names;
+    }
+
+    /// 加密语音信令字段（SDP / ICE candidate），未设置大厅密钥时原样返回
+    pub async fn seal_signaling_field(&self, value: &str, aad: &[u8]) -> String {
+        self.codec.read().await.seal_field(value, aad)
+    }
+
+    /// 解密语音信令字段，密钥不匹配、上下文不符或设置了密钥却收到明文时返回错误
+    pub async fn open_signaling_field(&self, value: &str, aad: &[u8]) -> Result<String, SignalingCryptoError> {
+        self.codec.read().await.open_field(value, aad)
+    }
+
+    /// 设置信令监听端口（下次启动服务时生效，被占用时仍会顺延）
+    pub fn set_listen_port(&mut self, listen_port: u16) {
+        self.listen_port = listen_port;
+    }
+
+    /// 设置是否通过 UPnP 映射信令端口（下次启动服务时生效）
+    pub fn set_upnp_enabled(&self, enabled: bool) {
+        self.upnp_enabled.store(enabled, Ordering::Relaxed);
+    }
     
     /// 设置 Tauri 应用句柄
     pub async fn set_app_handle(&self, app_handle: tauri::AppHandle) {
@@ -130,6 +641,52 @@ impl P2PSignalingService {
         log::info!("P2P信令服务已设置应用句柄");
     }
     
+    /// 设置大厅人数上限（仅大厅创建者需要设置）
+    pub async fn set_max_players(&self, max_players: Option<u32>) {
+        *self.max_players.write().await = max_players;
+    }
+    
+    /// 设置当前大厅创建者的虚拟 IP（进入大厅与创建者变更时调用）
+    pub async fn set_creator_virtual_ip(&self, creator_virtual_ip: Option<String>) {
+        *self.creator_virtual_ip.write().await = creator_virtual_ip;
+    }
+    
+    /// 设置本机是否以游客身份加入（需在 start 之前调用，会随发现广播告知其他玩家）
+    pub fn set_guest(&self, guest: bool) {
+        self.local_is_guest.store(guest, Ordering::Relaxed);
+    }
+
+    /// 设置信令加密密钥（需在 start 之前调用）
+    ///
+    /// # 参数
+    /// * `network_key` - 大厅 network_key（大厅密码），None 表示明文收发
+    /// * `plaintext_compat` - 是否兼容旧版本客户端的明文发现
+    pub async fn set_network_key(&self, network_key: Option<&str>, plaintext_compat: bool) {
+        *self.codec.write().await = SignalingCodec::new(network_key, plaintext_compat);
+        log::info!(
+            "P2P信令加密: {}{}",
+            if network_key.is_some() { "已启用" } else { "未启用" },
+            if network_key.is_some() && plaintext_compat { "（兼容明文发现）" } else { "" }
+        );
+    }
+
+    /// 暂停或恢复信令服务
+    ///
+    /// 虚拟网络重连期间暂停，避免把所有玩家判定为超时；恢复时刷新玩家的最后活跃时间，
+    /// 让他们在恢复后重新获得完整的超时窗口。
+    pub async fn set_paused(&self, paused: bool) {
+        let was_paused = self.paused.swap(paused, Ordering::Relaxed);
+        if was_paused && !paused {
+            let now = std::time::Instant::now();
+            for peer in self.peers.write().await.values_mut() {
+                peer.last_seen = now;
+            }
+            log::info!("P2P信令服务已恢复");
+        } else if !was_paused && paused {
+            log::info!("P2P信令服务已暂停（等待虚拟网络重连）");
+        }
+    }
+
     /// 启动P2P信令服务
     pub async fn start(&self, player_id: String, player_name: String, virtual_ip: String) -> Result<(), AppError> {
         log::info!("启动P2P信令服务: player={}, virtual_ip={}", player_name, virtual_ip);
@@ -182,6 +739,7 @@ impl P2PSignalingService {
         
         // 保存实际使用的端口
         *self.actual_port.write().await = actual_port;
+        crate::modules::service_ports::record_local_port(crate::modules::service_ports::ServiceKind::Signaling, actual_port);
         
         *self.socket.write().await = Some(socket);
         
@@ -198,9 +756,42 @@ impl P2PSignalingService {
         // 启动心跳任务
         self.start_heartbeat().await;
         
+        // 可选：在网关上映射信令端口（失败静默降级）
+        if self.upnp_enabled.load(Ordering::Relaxed) {
+            self.start_upnp_mapping(actual_port).await;
+        }
+        
         Ok(())
     }
     
+    /// 启动 UPnP 端口映射任务：建立映射后按租期定期续约，任务随服务停止一起 abort
+    async fn start_upnp_mapping(&self, port: u16) {
+        let upnp_mapping = Arc::clone(&self.upnp_mapping);
+        let running = Arc::clone(&self.running);
+        
+        let handle = tokio::spawn(async move {
+            // 网关搜索与 SOAP 请求是阻塞调用，放到阻塞线程池执行
+            let mapping = tokio::task::spawn_blocking(move || upnp_mapping::try_map_port(&IgdDiscovery, port))
+                .await
+                .ok()
+                .flatten();
+            let Some(mapping) = mapping.map(Arc::new) else {
+                return;
+            };
+            *upnp_mapping.write().await = Some(Arc::clone(&mapping));
+            
+            while running.load(Ordering::SeqCst) {
+                tokio::time::sleep(upnp_mapping::UPNP_RENEW_INTERVAL).await;
+                let mapping = Arc::clone(&mapping);
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || mapping.renew()).await {
+                    log::warn!("[UPnP] 端口映射续约失败: {}", e);
+                }
+            }
+        });
+        
+        self.task_handles.write().await.push(handle);
+    }
+    
     /// 启动接收线程
     async fn start_receiver(&self) -> Result<(), AppError> {
         let socket = self.socket.read().await;
@@ -222,6 +813,15 @@ impl P2PSignalingService {
         let local_player_name = Arc::clone(&self.local_player_name);
         let actual_port = Arc::clone(&self.actual_port);
         let running = Arc::clone(&self.running);
+        let response_throttle = Arc::clone(&self.response_throttle);
+        let display_names = Arc::clone(&self.display_names);
+        let max_players = Arc::clone(&self.max_players);
+        let local_is_guest = Arc::clone(&self.local_is_guest);
+        let codec = Arc::clone(&self.codec);
+        let announced_creator = Arc::clone(&self.announced_creator);
+        let creator_virtual_ip = Arc::clone(&self.creator_virtual_ip);
+        let ip_conflict = Arc::clone(&self.ip_conflict);
+        let local_virtual_ip = Arc::clone(&self.virtual_ip);
         
         let handle = tokio::spawn(async move {
             let mut buf = [0u8; 65536];
@@ -229,50 +829,122 @@ impl P2PSignalingService {
             while running.load(Ordering::Relaxed) {
                 match socket_clone.recv_from(&mut buf) {
                     Ok((len, src_addr)) => {
-                        if let Ok(msg_str) = std::str::from_utf8(&buf[..len]) {
-                            if let Ok(message) = serde_json::from_str::<P2PMessage>(msg_str) {
-                                // 如果是PlayerDiscovery消息，立即发送响应
-                                if let P2PMessage::PlayerDiscovery { ref player_id, ref player_name, port } = message {
-                                    // 检查是否是自己的广播
-                                    let is_self = {
-                                        let local_id = local_player_id.read().await;
-                                        local_id.as_ref() == Some(player_id)
-                                    };
-                                    
-                                    if !is_self {
-                                        // 立即发送响应
-                                        if let (Some(my_id), Some(my_name)) = (
-                                            local_player_id.read().await.as_ref(),
-                                            local_player_name.read().await.as_ref(),
-                                        ) {
-                                            let response = P2PMessage::PlayerDiscoveryResponse {
-                                                player_id: my_id.clone(),
-                                                player_name: my_name.clone(),
-                                                port: *actual_port.read().await,
-                                            };
+                        let codec = codec.read().await.clone();
+                        if let Some((message, from_plaintext)) = codec.decode(&buf[..len]) {
+                            // 人数上限检查：新玩家通过发现广播或发现响应加入时，若已满则回复 LobbyFull 并丢弃该消息。
+                            // 接收循环串行处理所有消息，peers 的新增也只发生在本循环内，
+                            // 因此"检查是否已满"与"加入 peers"之间不会穿插其它玩家的加入，同时加入也不会超员。
+                            let joining = match message {
+                                P2PMessage::PlayerDiscovery { ref player_id, port, is_guest: false, .. }
+                                | P2PMessage::PlayerDiscoveryResponse { ref player_id, port, is_guest: false, .. } => Some((player_id.clone(), port)),
+                                _ => None,
+                            };
+                            if let Some((joining_id, port)) = joining {
+                                let max = *max_players.read().await;
+                                let is_self = local_player_id.read().await.as_ref() == Some(&joining_id);
+                                if !is_self && is_lobby_full(&*peers.read().await, &joining_id, max) {
+                                    let max = max.unwrap_or_default();
+                                    log::warn!("⚠️ 大厅已满（上限 {} 人），拒绝玩家 {} 加入", max, joining_id);
+                                    let reply = P2PMessage::LobbyFull { player_id: joining_id, max_players: max };
+                                    if let Ok(reply_packet) = codec.encode_reply(&reply, from_plaintext) {
+                                        let mut reply_addr = src_addr;
+                                        reply_addr.set_port(port);
+                                        let _ = socket_for_response.send_to(&reply_packet, reply_addr);
+                                    }
+                                    continue;
+                                }
+                            }
+                            
+                            // 如果是PlayerDiscovery消息，立即发送响应（同一 peer 短时间内只回应一次）
+                            if let P2PMessage::PlayerDiscovery { ref player_id, ref player_name, port, .. } = message {
+                                // 检查是否是自己的广播
+                                let is_self = {
+                                    let local_id = local_player_id.read().await;
+                                    local_id.as_ref() == Some(player_id)
+                                };
+                                
+                                let should_respond = !is_self && response_throttle
+                                    .write()
+                                    .await
+                                    .should_respond(player_id, std::time::Instant::now());
+                                
+                                if !is_self && !should_respond {
+                                    log::debug!("短时间内已回应过 {} ({})，跳过重复发现响应", player_name, player_id);
+                                }
+
+                                if should_respond {
+                                    // 立即发送响应
+                                    if let (Some(my_id), Some(my_name)) = (
+                                        local_player_id.read().await.as_ref(),
+                                        local_player_name.read().await.as_ref(),
+                                    ) {
+                                        let service_ports = crate::modules::service_ports::local_ports();
+                                        let response = P2PMessage::PlayerDiscoveryResponse {
+                                            player_id: my_id.clone(),
+                                            player_name: my_name.clone(),
+                                            port: *actual_port.read().await,
+                                            is_guest: local_is_guest.load(Ordering::Relaxed),
+                                            file_port: Some(service_ports.file_share),
+                                            chat_port: Some(service_ports.chat),
+                                            presence: crate::modules::presence::current(),
+                                        };
+                                        
+                                        if let Ok(response_packet) = codec.encode_reply(&response, from_plaintext) {
+                                            let mut response_addr = src_addr;
+                                            response_addr.set_port(port);
                                             
-                                            if let Ok(response_json) = serde_json::to_string(&response) {
-                                                let mut response_addr = src_addr;
-                                                response_addr.set_port(port);
-                                                
-                                                if let Err(e) = socket_for_response.send_to(response_json.as_bytes(), response_addr) {
-                                                    log::warn!("发送发现响应失败: {}", e);
-                                                } else {
-                                                    log::info!("✅ 已发送发现响应给 {} ({})", player_name, player_id);
-                                                }
+                                            if let Err(e) = socket_for_response.send_to(&response_packet, response_addr) {
+                                                log::warn!("发送发现响应失败: {}", e);
+                                            } else {
+                                                log::info!("✅ 已发送发现响应给 {} ({})", player_name, player_id);
                                             }
                                         }
                                     }
                                 }
-                                
-                                Self::handle_message_static(
-                                    message,
-                                    src_addr,
-                                    &peers,
-                                    &app_handle,
-                                    &local_player_id,
-                                ).await;
                             }
+                            
+                            // 玩家离开后清除回应记录，重新加入时应立即回应
+                            if let P2PMessage::PlayerLeft { ref player_id } = message {
+                                response_throttle.write().await.forget(player_id);
+                            }
+                            
+                            // 收到修复请求后清除对发起方的回应记录，确保随后的发现包立即得到回应
+                            if let P2PMessage::RepairRequest { ref from, ref to } = message {
+                                if local_player_id.read().await.as_ref() == Some(to) {
+                                    response_throttle.write().await.forget(from);
+                                }
+                            }
+                            
+                            // 记录其他节点宣告的创建者，同时收到多个宣告时保留虚拟 IP 最小者
+                            if let P2PMessage::CreatorAnnounce { ref creator_virtual_ip, .. } = message {
+                                let mut announced = announced_creator.write().await;
+                                let current = announced.as_deref().unwrap_or(creator_virtual_ip);
+                                *announced = crate::modules::lobby_manager::elect_creator([current, creator_virtual_ip.as_str()]);
+                            }
+                            
+                            // 其他玩家声明了与本机相同的虚拟 IP，记录冲突交给冲突处理任务；需要让出的冲突优先保留
+                            if let P2PMessage::IpClaim { ref player_id, ref virtual_ip } = message {
+                                let local_id = local_player_id.read().await.clone();
+                                let local_ip = local_virtual_ip.read().await.clone();
+                                if let (Some(local_id), Some(local_ip)) = (local_id, local_ip) {
+                                    if let Some(conflict) = crate::modules::ip_conflict::detect(&local_id, &local_ip, player_id, virtual_ip) {
+                                        let mut current = ip_conflict.write().await;
+                                        if !current.as_ref().is_some_and(|c| c.yielding) {
+                                            *current = Some(conflict);
+                                        }
+                                    }
+                                }
+                            }
+                            
+                            Self::handle_message_static(
+                                message,
+                                src_addr,
+                                &peers,
+                                &app_handle,
+                                &local_player_id,
+                                &display_names,
+                                &creator_virtual_ip,
+                            ).await;
                         }
                     }
                     Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -292,7 +964,17 @@ impl P2PSignalingService {
         log::info!("UDP接收线程已启动");
         Ok(())
     }
-    
+
+    /// 通知前端玩家在线状态变化
+    async fn emit_presence(app_handle: &Arc<RwLock<Option<tauri::AppHandle>>>, player_id: &str, presence: Presence) {
+        if let Some(app) = app_handle.read().await.as_ref() {
+            let _ = app.emit("player-presence-update", serde_json::json!({
+                "playerId": player_id,
+                "presence": presence,
+            }));
+        }
+    }
+
     /// 处理接收到的消息（静态方法）
     async fn handle_message_static(
         message: P2PMessage,
@@ -300,9 +982,11 @@ impl P2PSignalingService {
         peers: &Arc<RwLock<HashMap<String, PeerInfo>>>,
         app_handle: &Arc<RwLock<Option<tauri::AppHandle>>>,
         local_player_id: &Arc<RwLock<Option<String>>>,
+        display_names: &SharedDisplayNames,
+        creator_virtual_ip: &Arc<RwLock<Option<String>>>,
     ) {
         match message {
-            P2PMessage::PlayerDiscovery { player_id, player_name, port } => {
+            P2PMessage::PlayerDiscovery { player_id, player_name, port, is_guest, file_port, chat_port, presence } => {
                 log::info!("📡 收到玩家发现广播: {} ({})", player_name, player_id);
                 
                 // 忽略自己的广播
@@ -313,12 +997,13 @@ impl P2PSignalingService {
                 }
                 drop(local_id);
                 
-                // 检查是否已经存在
-                let already_exists = {
-                    let peers_read = peers.read().await;
-                    peers_read.contains_key(&player_id)
-                };
+                // 检查是否已经存在（记录原有在线状态，用于补发丢失的状态变化）
+                let previous_presence = peers.read().await.get(&player_id).map(|peer| peer.presence);
+                let already_exists = previous_presence.is_some();
                 
+                // 记录对方宣告的文件 / 聊天端口
+                crate::modules::service_ports::record_peer_ports(&src_addr.ip().to_string(), file_port, chat_port);
+
                 // 添加到对等节点列表（必须在发送事件之前完成）
                 let mut addr = src_addr;
                 addr.set_port(port);
@@ -328,12 +1013,17 @@ impl P2PSignalingService {
                     player_name: player_name.clone(),
                     addr,
                     last_seen: std::time::Instant::now(),
+                    is_guest,
+                    presence,
                 };
                 
                 {
                     let mut peers_write = peers.write().await;
                     peers_write.insert(player_id.clone(), peer_info);
                 }
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    sync_discovered_player(app, &player_id, &player_name, src_addr.ip().to_string());
+                }
                 
                 // 只有新玩家才发送 player-joined 事件
                 if !already_exists {
@@ -343,19 +1033,33 @@ impl P2PSignalingService {
                     // 等待200ms确保peers列表已完全更新
                     tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
                     
+                    // 与已有玩家重名时附加显示后缀（如 "#2"）
+                    let display_suffix = display_names.lock().assign(&player_id, &player_name);
+                    if let Some(suffix) = &display_suffix {
+                        log::info!("   昵称与其他玩家重复，显示为: {} {}", player_name, suffix);
+                    }
+
                     // 发送事件到前端
                     if let Some(app) = app_handle.read().await.as_ref() {
                         let _ = app.emit("player-joined", serde_json::json!({
                             "playerId": player_id,
                             "playerName": player_name,
+                            "isGuest": is_guest,
+                            "displaySuffix": display_suffix,
+                            "presence": presence,
                         }));
                         log::info!("   已发送 player-joined 事件到前端");
+                        let shown_name = format!("{}{}", player_name, display_suffix.as_deref().unwrap_or(""));
+                        crate::modules::tauri_commands::post_system_message(app, format!("{} 加入了大厅", shown_name));
                     }
                 } else {
                     log::debug!("更新已存在玩家的心跳: {}", player_id);
+                    if previous_presence != Some(presence) {
+                        Self::emit_presence(app_handle, &player_id, presence).await;
+                    }
                 }
             }
-            P2PMessage::PlayerDiscoveryResponse { player_id, player_name, port } => {
+            P2PMessage::PlayerDiscoveryResponse { player_id, player_name, port, is_guest, file_port, chat_port, presence } => {
                 log::info!("📡 收到玩家发现响应: {} ({})", player_name, player_id);
                 
                 // 忽略自己的响应
@@ -366,12 +1070,13 @@ impl P2PSignalingService {
                 }
                 drop(local_id);
                 
-                // 检查是否已经存在
-                let already_exists = {
-                    let peers_read = peers.read().await;
-                    peers_read.contains_key(&player_id)
-                };
+                // 检查是否已经存在（记录原有在线状态，用于补发丢失的状态变化）
+                let previous_presence = peers.read().await.get(&player_id).map(|peer| peer.presence);
+                let already_exists = previous_presence.is_some();
                 
+                // 记录对方宣告的文件 / 聊天端口
+                crate::modules::service_ports::record_peer_ports(&src_addr.ip().to_string(), file_port, chat_port);
+
                 // 添加到对等节点列表（必须在发送事件之前完成）
                 let mut addr = src_addr;
                 addr.set_port(port);
@@ -381,12 +1086,17 @@ impl P2PSignalingService {
                     player_name: player_name.clone(),
                     addr,
                     last_seen: std::time::Instant::now(),
+                    is_guest,
+                    presence,
                 };
                 
                 {
                     let mut peers_write = peers.write().await;
                     peers_write.insert(player_id.clone(), peer_info);
                 }
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    sync_discovered_player(app, &player_id, &player_name, src_addr.ip().to_string());
+                }
                 
                 // 只有新玩家才发送 player-joined 事件
                 if !already_exists {
@@ -396,16 +1106,30 @@ impl P2PSignalingService {
                     // 等待200ms确保peers列表已更新
                     tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
                     
+                    // 与已有玩家重名时附加显示后缀（如 "#2"）
+                    let display_suffix = display_names.lock().assign(&player_id, &player_name);
+                    if let Some(suffix) = &display_suffix {
+                        log::info!("   昵称与其他玩家重复，显示为: {} {}", player_name, suffix);
+                    }
+
                     // 发送事件到前端
                     if let Some(app) = app_handle.read().await.as_ref() {
                         let _ = app.emit("player-joined", serde_json::json!({
                             "playerId": player_id,
                             "playerName": player_name,
+                            "isGuest": is_guest,
+                            "displaySuffix": display_suffix,
+                            "presence": presence,
                         }));
                         log::info!("   已发送 player-joined 事件到前端");
+                        let shown_name = format!("{}{}", player_name, display_suffix.as_deref().unwrap_or(""));
+                        crate::modules::tauri_commands::post_system_message(app, format!("{} 加入了大厅", shown_name));
                     }
                 } else {
                     log::debug!("更新已存在玩家的心跳（通过响应）: {}", player_id);
+                    if previous_presence != Some(presence) {
+                        Self::emit_presence(app_handle, &player_id, presence).await;
+                    }
                 }
             }
             P2PMessage::Offer { from, sdp } => {
@@ -447,6 +1171,17 @@ impl P2PSignalingService {
                     }));
                 }
             }
+            P2PMessage::PresenceUpdate { player_id, presence } => {
+                // 本机的状态变化已由发送方直接通知前端
+                if local_player_id.read().await.as_deref() == Some(player_id.as_str()) {
+                    return;
+                }
+                log::info!("收到在线状态: {} {}", player_id, presence.label());
+                if let Some(peer) = peers.write().await.get_mut(&player_id) {
+                    peer.presence = presence;
+                }
+                Self::emit_presence(app_handle, &player_id, presence).await;
+            }
             P2PMessage::Heartbeat { player_id, .. } => {
                 // 更新最后见到时间
                 if let Some(peer) = peers.write().await.get_mut(&player_id) {
@@ -455,14 +1190,259 @@ impl P2PSignalingService {
             }
             P2PMessage::PlayerLeft { player_id } => {
                 log::info!("玩家离开: {}", player_id);
-                peers.write().await.remove(&player_id);
+                let removed = peers.write().await.remove(&player_id);
+                display_names.lock().remove(&player_id);
+                crate::modules::mc_lan_bridge::set_announced_world(&player_id, None);
                 
                 if let Some(app) = app_handle.read().await.as_ref() {
+                    sync_removed_player(app, &player_id);
                     let _ = app.emit("player-left", serde_json::json!({
                         "playerId": player_id,
                     }));
+                    if let Some(peer) = removed {
+                        crate::modules::tauri_commands::post_system_message(app, format!("{} 离开了大厅", peer.player_name));
+                    }
                 }
             }
+            P2PMessage::LobbyFull { player_id, max_players } => {
+                if local_player_id.read().await.as_ref() != Some(&player_id) {
+                    return;
+                }
+                // 人数上限只由创建者检查，其他成员伪造的已满消息直接丢弃
+                if !is_from_creator(src_addr.ip(), creator_virtual_ip.read().await.as_deref()) {
+                    log::warn!("⚠️ 忽略来自非创建者 {} 的大厅已满消息", src_addr.ip());
+                    return;
+                }
+                log::warn!("⚠️ 大厅已满（上限 {} 人），自动退出", max_players);
+                if let Some(app) = app_handle.read().await.clone() {
+                    let _ = app.emit("lobby-full", serde_json::json!({
+                        "maxPlayers": max_players,
+                    }));
+                    
+                    // 退出大厅会停止本服务并 abort 接收任务，必须放到独立任务中执行
+                    tokio::spawn(async move {
+                        use tauri::Manager;
+                        let state = app.state::<crate::modules::tauri_commands::AppState>();
+                        if let Err(e) = crate::modules::tauri_commands::leave_lobby(state).await {
+                            log::error!("大厅已满，退出大厅失败: {}", e);
+                        }
+                    });
+                }
+            }
+            P2PMessage::Kicked { player_id, reason } => {
+                // 只有创建者能踢人，其他成员伪造的踢人消息直接丢弃
+                if !is_from_creator(src_addr.ip(), creator_virtual_ip.read().await.as_deref()) {
+                    log::warn!("⚠️ 忽略来自非创建者 {} 的踢人消息（目标 {}）", src_addr.ip(), player_id);
+                    return;
+                }
+                let is_self = local_player_id.read().await.as_ref() == Some(&player_id);
+                let Some(app) = app_handle.read().await.clone() else {
+                    return;
+                };
+                
+                if is_self {
+                    log::warn!("⚠️ 已被大厅创建者踢出: {}", reason);
+                    let _ = app.emit("kicked-from-lobby", serde_json::json!({
+                        "reason": reason,
+                    }));
+                    
+                    // 退出大厅会停止本服务并 abort 接收任务，必须放到独立任务中执行
+                    tokio::spawn(async move {
+                        use tauri::Manager;
+                        let state = app.state::<crate::modules::tauri_commands::AppState>();
+                        if let Err(e) = crate::modules::tauri_commands::leave_lobby(state).await {
+                            log::error!("被踢出后退出大厅失败: {}", e);
+                        }
+                    });
+                } else {
+                    log::info!("玩家 {} 被踢出大厅: {}", player_id, reason);
+                    let removed = peers.write().await.remove(&player_id);
+                    display_names.lock().remove(&player_id);
+                    crate::modules::mc_lan_bridge::set_announced_world(&player_id, None);
+                    sync_removed_player(&app, &player_id);
+                    let _ = app.emit("player-left", serde_json::json!({
+                        "playerId": player_id,
+                    }));
+                    // 踢人的创建者已在本地移除该玩家并生成过系统消息，这里只处理仍在列表中的
+                    if let Some(peer) = removed {
+                        crate::modules::tauri_commands::post_system_message(&app, format!("{} 被踢出大厅", peer.player_name));
+                    }
+                }
+            }
+            P2PMessage::SpeakingState { player_id, is_speaking } => {
+                log::debug!("收到说话状态: {} speaking={}", player_id, is_speaking);
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    let _ = app.emit("player-speaking", serde_json::json!({
+                        "playerId": player_id,
+                        "isSpeaking": is_speaking,
+                    }));
+                    
+                    // 同步到语音服务的说话集合（异步执行，避免在接收循环中等待全局锁）
+                    let app = app.clone();
+                    tokio::spawn(async move {
+                        use tauri::Manager;
+                        let state = app.state::<crate::modules::tauri_commands::AppState>();
+                        let voice_service = state.get_voice_service();
+                        voice_service.lock().await.set_player_speaking(&player_id, is_speaking).await;
+                    });
+                }
+            }
+            P2PMessage::ReadyState { player_id, is_ready } => {
+                if local_player_id.read().await.as_ref() == Some(&player_id) {
+                    return;
+                }
+                log::info!("收到准备状态: {} ready={}", player_id, is_ready);
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    let _ = app.emit("player-ready", serde_json::json!({
+                        "playerId": player_id,
+                        "isReady": is_ready,
+                    }));
+                    let _ = player_sync_queue(app).send(PlayerSync::Ready { player_id, is_ready });
+                }
+            }
+            P2PMessage::InputLevel { player_id, level } => {
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    let _ = app.emit("peer-input-level", serde_json::json!({
+                        "playerId": player_id,
+                        "level": level,
+                    }));
+                    
+                    // 记录长期平均电平用于跨玩家响度均衡，静音样本不参与计算
+                    if level > 0 {
+                        let app = app.clone();
+                        tokio::spawn(async move {
+                            use tauri::Manager;
+                            let state = app.state::<crate::modules::tauri_commands::AppState>();
+                            let voice_service = state.get_voice_service();
+                            let gains = voice_service.lock().await.record_peer_input_level(&player_id, level).await;
+                            if let Some(gains) = gains {
+                                let _ = app.emit("voice-normalization-gains", serde_json::json!({
+                                    "gains": gains,
+                                }));
+                            }
+                        });
+                    }
+                }
+            }
+            P2PMessage::RepairRequest { from, to } => {
+                if local_player_id.read().await.as_ref() != Some(&to) {
+                    return;
+                }
+                log::info!("🔧 收到 {} 的连接修复请求，重置语音连接", from);
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    // 由发起方主动重新协商，本机只清理旧连接等待 Offer，避免双方同时发起
+                    let _ = app.emit("webrtc-repair-peer", serde_json::json!({
+                        "peerId": from,
+                        "initiate": false,
+                    }));
+                }
+            }
+            P2PMessage::VoiceOffer { from, to, sdp } => {
+                if local_player_id.read().await.as_ref() != Some(&to) {
+                    return;
+                }
+                log::info!("🎙️ 收到 {} 的语音连接 offer", from);
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    // 应答需要信令服务锁，放到后台任务中执行
+                    let app = app.clone();
+                    let peer_ip = src_addr.ip().to_string();
+                    tokio::spawn(crate::modules::voice_session::handle_remote_offer(app, from, peer_ip, sdp));
+                }
+            }
+            P2PMessage::VoiceAnswer { from, to, sdp } => {
+                if local_player_id.read().await.as_ref() != Some(&to) {
+                    return;
+                }
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    crate::modules::voice_session::handle_remote_answer(app, &from, &sdp);
+                }
+            }
+            P2PMessage::VoiceClose { from, to, session_id } => {
+                if local_player_id.read().await.as_ref() != Some(&to) {
+                    return;
+                }
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    crate::modules::voice_session::handle_remote_close(app, &from, Some(&session_id));
+                }
+            }
+            P2PMessage::MessageReaction { message_id, emoji, player_id, added } => {
+                log::debug!("收到表情回应: {} {} {} added={}", player_id, message_id, emoji, added);
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    // 聚合到聊天服务（异步执行，避免在接收循环中等待全局锁）
+                    let app = app.clone();
+                    tokio::spawn(async move {
+                        use tauri::Manager;
+                        let state = app.state::<crate::modules::tauri_commands::AppState>();
+                        let chat_service = state.get_chat_service();
+                        let update = chat_service.lock().await.apply_reaction(&message_id, &emoji, &player_id, added);
+                        if let Some(reactions) = update {
+                            let _ = app.emit("message-reaction-update", serde_json::json!({
+                                "messageId": message_id,
+                                "reactions": reactions,
+                            }));
+                        }
+                    });
+                }
+            }
+            P2PMessage::McLanWorld { player_id, port, motd } => {
+                if local_player_id.read().await.as_ref() == Some(&player_id) {
+                    return;
+                }
+                // 优先使用已发现的 peer 地址，未发现时退回消息来源地址
+                let ip = peers
+                    .read()
+                    .await
+                    .get(&player_id)
+                    .map(|p| p.addr.ip())
+                    .unwrap_or_else(|| src_addr.ip());
+                let server = port.map(|port| crate::modules::mc_lan_bridge::McServer {
+                    ip: ip.to_string(),
+                    port,
+                    motd,
+                });
+                log::info!("收到玩家 {} 的局域网世界宣告: {:?}", player_id, server);
+                crate::modules::mc_lan_bridge::set_announced_world(&player_id, server);
+            }
+            P2PMessage::BrowsingShare { player_id, share_id, activity } => {
+                if local_player_id.read().await.as_ref() == Some(&player_id) {
+                    return;
+                }
+                if let Some(app) = app_handle.read().await.as_ref() {
+                    // 只提示本机的共享（异步执行，避免在接收循环中等待全局锁）
+                    let app = app.clone();
+                    let player_name = peers.read().await.get(&player_id).map(|p| p.player_name.clone());
+                    tokio::spawn(async move {
+                        use tauri::Manager;
+                        let state = app.state::<crate::modules::tauri_commands::AppState>();
+                        let file_transfer = state.get_file_transfer();
+                        let share_name = file_transfer
+                            .lock()
+                            .await
+                            .get_shares()
+                            .into_iter()
+                            .find(|s| s.id == share_id)
+                            .map(|s| s.name);
+                        if let Some(share_name) = share_name {
+                            log::info!("玩家 {} 正在访问共享 {}（{:?}）", player_id, share_name, activity);
+                            let _ = app.emit("peer-browsing", serde_json::json!({
+                                "playerId": player_id,
+                                "playerName": player_name,
+                                "shareId": share_id,
+                                "shareName": share_name,
+                                "activity": activity,
+                            }));
+                        }
+                    });
+                }
+            }
+            P2PMessage::CreatorAnnounce { player_id, creator_virtual_ip } => {
+                // 已在接收循环中记录，由创建者选举任务处理
+                log::debug!("收到创建者宣告: {} ({})", creator_virtual_ip, player_id);
+            }
+            P2PMessage::IpClaim { player_id, virtual_ip } => {
+                // 已在接收循环中检查冲突，由冲突处理任务处理
+                log::debug!("收到虚拟 IP 声明: {} ({})", virtual_ip, player_id);
+            }
         }
     }
     
@@ -473,6 +1453,8 @@ impl P2PSignalingService {
         let socket = Arc::clone(&self.socket);
         let actual_port = Arc::clone(&self.actual_port);
         let running = Arc::clone(&self.running);
+        let local_is_guest = Arc::clone(&self.local_is_guest);
+        let codec = Arc::clone(&self.codec);
         
         let handle = tokio::spawn(async move {
             let mut count = 0;
@@ -493,24 +1475,34 @@ impl P2PSignalingService {
                     local_player_id.read().await.as_ref(),
                     local_player_name.read().await.as_ref(),
                 ) {
+                    let service_ports = crate::modules::service_ports::local_ports();
                     let message = P2PMessage::PlayerDiscovery {
                         player_id: player_id.clone(),
                         player_name: player_name.clone(),
                         port: *actual_port.read().await,
+                        is_guest: local_is_guest.load(Ordering::Relaxed),
+                        file_port: Some(service_ports.file_share),
+                        chat_port: Some(service_ports.chat),
+                        presence: crate::modules::presence::current(),
                     };
                     
                     if let Some(sock) = socket.read().await.as_ref() {
-                        if let Ok(msg_json) = serde_json::to_string(&message) {
-                            let port = *actual_port.read().await;
-                            // 使用真实的局域网广播地址，而不是虚拟IP的广播地址
-                            // 因为在 no-tun 模式下，虚拟IP不存在于系统网卡中
-                            let broadcast_addr = format!("255.255.255.255:{}", port);
+                        let codec = codec.read().await.clone();
+                        if let Ok(packet) = codec.encode(&message) {
+                            let broadcast_addr = broadcast_target(*actual_port.read().await);
                             
-                            if let Err(e) = sock.send_to(msg_json.as_bytes(), &broadcast_addr) {
+                            if let Err(e) = sock.send_to(&packet, broadcast_addr) {
                                 log::warn!("发送玩家发现广播失败: {}", e);
                             } else {
                                 log::debug!("已发送玩家发现广播到 {} (第{}次)", broadcast_addr, count);
                             }
+                            
+                            // 兼容旧版本：额外发送一份明文发现广播
+                            if codec.plaintext_compat() {
+                                if let Ok(plain) = SignalingCodec::encode_plaintext(&message) {
+                                    let _ = sock.send_to(&plain, broadcast_addr);
+                                }
+                            }
                         }
                     }
                 }
@@ -529,18 +1521,13 @@ impl P2PSignalingService {
         let socket_ref = socket.as_ref()
             .ok_or_else(|| AppError::NetworkError("套接字未初始化".to_string()))?;
         
-        let msg_json = serde_json::to_string(&message)
-            .map_err(|e| AppError::NetworkError(format!("序列化消息失败: {}", e)))?;
-        
-        // 获取实际端口
-        let actual_port = *self.actual_port.read().await;
+        let packet = self.codec.read().await.encode(&message)?;
         
-        // 使用真实的局域网广播地址
-        let broadcast_addr = format!("255.255.255.255:{}", actual_port);
+        let broadcast_addr = broadcast_target(*self.actual_port.read().await);
         
         log::debug!("广播消息到: {}", broadcast_addr);
         
-        socket_ref.send_to(msg_json.as_bytes(), &broadcast_addr)
+        socket_ref.send_to(&packet, broadcast_addr)
             .map_err(|e| AppError::NetworkError(format!("发送广播失败: {}", e)))?;
         
         Ok(())
@@ -559,10 +1546,9 @@ impl P2PSignalingService {
         let socket_ref = socket.as_ref()
             .ok_or_else(|| AppError::NetworkError("套接字未初始化".to_string()))?;
         
-        let msg_json = serde_json::to_string(&message)
-            .map_err(|e| AppError::NetworkError(format!("序列化消息失败: {}", e)))?;
+        let packet = self.codec.read().await.encode(&message)?;
         
-        socket_ref.send_to(msg_json.as_bytes(), addr)
+        socket_ref.send_to(&packet, addr)
             .map_err(|e| AppError::NetworkError(format!("发送消息失败: {}", e)))?;
         
         Ok(())
@@ -581,6 +1567,10 @@ impl P2PSignalingService {
         let peers = Arc::clone(&self.peers);
         let app_handle = Arc::clone(&self.app_handle);
         let running = Arc::clone(&self.running);
+        let response_throttle = Arc::clone(&self.response_throttle);
+        let display_names = Arc::clone(&self.display_names);
+        let paused = Arc::clone(&self.paused);
+        let codec = Arc::clone(&self.codec);
         
         let handle = tokio::spawn(async move {
             while running.load(Ordering::Relaxed) {
@@ -588,6 +1578,9 @@ impl P2PSignalingService {
                 if !running.load(Ordering::Relaxed) {
                     break;
                 }
+                if paused.load(Ordering::Relaxed) {
+                    continue;
+                }
                 
                 // 发送心跳
                 if let Some(player_id) = local_player_id.read().await.as_ref() {
@@ -597,12 +1590,19 @@ impl P2PSignalingService {
                     };
                     
                     if let Some(sock) = socket.read().await.as_ref() {
-                        if let Ok(msg_json) = serde_json::to_string(&message) {
-                            let port = *actual_port.read().await;
-                            // 使用全局广播地址，与发现广播保持一致（修复此前硬编码 192.168.0.255
-                            // 导致非该网段子网收不到心跳的问题）
-                            let broadcast_addr = format!("255.255.255.255:{}", port);
-                            let _ = sock.send_to(msg_json.as_bytes(), broadcast_addr);
+                        let codec = codec.read().await.clone();
+                        if let Ok(packet) = codec.encode(&message) {
+                            // 与发现广播使用同一目标地址
+                            let broadcast_addr = broadcast_target(*actual_port.read().await);
+                            if let Err(e) = sock.send_to(&packet, broadcast_addr) {
+                                log::warn!("发送心跳失败: {}", e);
+                            }
+                            // 兼容旧版本：额外发送一份明文心跳，避免被旧客户端判定超时
+                            if codec.plaintext_compat() {
+                                if let Ok(plain) = SignalingCodec::encode_plaintext(&message) {
+                                    let _ = sock.send_to(&plain, broadcast_addr);
+                                }
+                            }
                         }
                     }
                 }
@@ -626,13 +1626,20 @@ impl P2PSignalingService {
                     let mut peers_write = peers.write().await;
                     for player_id in timeout_players {
                         log::warn!("玩家超时: {}", player_id);
-                        peers_write.remove(&player_id);
+                        let removed = peers_write.remove(&player_id);
+                        response_throttle.write().await.forget(&player_id);
+                        display_names.lock().remove(&player_id);
+                        crate::modules::mc_lan_bridge::set_announced_world(&player_id, None);
                         
                         // 通知前端玩家离开
                         if let Some(app) = app_handle.read().await.as_ref() {
+                            sync_removed_player(app, &player_id);
                             let _ = app.emit("player-left", serde_json::json!({
                                 "playerId": player_id,
                             }));
+                            if let Some(peer) = removed {
+                                crate::modules::tauri_commands::post_system_message(app, format!("{} 连接超时，已离开大厅", peer.player_name));
+                            }
                         }
                     }
                 }
@@ -658,12 +1665,22 @@ impl P2PSignalingService {
         // 标记停止，让后台 loop 任务自行退出
         self.running.store(false, Ordering::SeqCst);
         
-        // 强制 abort 所有后台任务（接收/发现广播/心跳），彻底回收任务与克隆的套接字句柄
-        {
-            let mut handles = self.task_handles.write().await;
-            for handle in handles.drain(..) {
-                handle.abort();
-            }
+        // 强制 abort 所有后台任务（接收/发现广播/心跳），并等待任务结束，彻底回收克隆的套接字句柄，
+        // 保证退出后立即重进时 UDP 端口已释放
+        let handles: Vec<_> = self.task_handles.write().await.drain(..).collect();
+        for handle in &handles {
+            handle.abort();
+        }
+        for handle in handles {
+            let _ = handle.await;
+        }
+        
+        // 撤销 UPnP 端口映射（后台执行，不阻塞退出大厅）
+        if let Some(mapping) = self.upnp_mapping.write().await.take() {
+            tokio::task::spawn_blocking(move || match mapping.remove() {
+                Ok(()) => log::info!("[UPnP] 已撤销信令端口映射"),
+                Err(e) => log::warn!("[UPnP] 撤销信令端口映射失败: {}", e),
+            });
         }
         
         // 关闭套接字
@@ -671,15 +1688,158 @@ impl P2PSignalingService {
         
         // 清理对等节点
         self.peers.write().await.clear();
+        self.response_throttle.write().await.clear();
+        crate::modules::mc_lan_bridge::clear_announced_worlds();
+        crate::modules::voice_session::voice_sessions().lock().clear();
+        *self.max_players.write().await = None;
+        *self.announced_creator.write().await = None;
+        *self.creator_virtual_ip.write().await = None;
+        *self.ip_conflict.write().await = None;
+        self.paused.store(false, Ordering::Relaxed);
+        self.local_is_guest.store(false, Ordering::Relaxed);
         
         log::info!("✅ P2P信令服务已停止，后台任务已回收");
         Ok(())
     }
     
+    /// UDP 套接字活跃时返回实际监听端口
+    pub async fn active_port(&self) -> Option<u16> {
+        if !self.running.load(Ordering::SeqCst) || self.socket.read().await.is_none() {
+            return None;
+        }
+        Some(*self.actual_port.read().await)
+    }
+
     /// 获取所有对等节点
     pub async fn get_peers(&self) -> Vec<PeerInfo> {
         self.peers.read().await.values().cloned().collect()
     }
+    
+    /// 是否已发现指定玩家
+    pub async fn has_peer(&self, player_id: &str) -> bool {
+        self.peers.read().await.contains_key(player_id)
+    }
+    
+    /// 移除对等节点
+    pub async fn remove_peer(&self, player_id: &str) -> Option<PeerInfo> {
+        self.response_throttle.write().await.forget(player_id);
+        self.display_names.lock().remove(player_id);
+        crate::modules::mc_lan_bridge::set_announced_world(player_id, None);
+        self.peers.write().await.remove(player_id)
+    }
+    
+    /// 获取本地玩家 ID
+    pub async fn get_local_player_id(&self) -> Option<String> {
+        self.local_player_id.read().await.clone()
+    }
+    
+    /// 获取指定玩家的对等节点信息
+    pub async fn get_peer(&self, player_id: &str) -> Option<PeerInfo> {
+        self.peers.read().await.get(player_id).cloned()
+    }
+    
+    /// peer 列表句柄（供 HTTP 服务校验请求来源）
+    pub fn peers_handle(&self) -> Arc<RwLock<HashMap<String, PeerInfo>>> {
+        Arc::clone(&self.peers)
+    }
+
+    /// 当前非游客成员的虚拟 IP（不含本机）
+    pub async fn member_virtual_ips(&self) -> Vec<String> {
+        self.peers
+            .read()
+            .await
+            .values()
+            .filter(|p| !p.is_guest)
+            .map(|p| p.addr.ip().to_string())
+            .collect()
+    }
+
+    /// 取走最近收到的创建者宣告
+    pub async fn take_announced_creator(&self) -> Option<String> {
+        self.announced_creator.write().await.take()
+    }
+
+    /// 广播本机为大厅创建者
+    pub async fn announce_creator(&self, creator_virtual_ip: &str) -> Result<(), AppError> {
+        let player_id = self.local_player_id.read().await.clone()
+            .ok_or_else(|| AppError::NetworkError("P2P信令服务未启动".to_string()))?;
+        self.broadcast(P2PMessage::CreatorAnnounce {
+            player_id,
+            creator_virtual_ip: creator_virtual_ip.to_string(),
+        })
+        .await
+    }
+
+    /// 本机当前的虚拟 IP
+    pub async fn local_virtual_ip(&self) -> Option<String> {
+        self.virtual_ip.read().await.clone()
+    }
+
+    /// 更新本机虚拟 IP（重新分配后调用），之前检测到的冲突作废
+    pub async fn set_virtual_ip(&self, virtual_ip: String) {
+        *self.virtual_ip.write().await = Some(virtual_ip);
+        *self.ip_conflict.write().await = None;
+    }
+
+    /// 广播本机的虚拟 IP 声明
+    pub async fn claim_virtual_ip(&self) -> Result<(), AppError> {
+        let player_id = self.local_player_id.read().await.clone()
+            .ok_or_else(|| AppError::NetworkError("P2P信令服务未启动".to_string()))?;
+        let virtual_ip = self.virtual_ip.read().await.clone()
+            .ok_or_else(|| AppError::NetworkError("尚未分配虚拟 IP".to_string()))?;
+        self.broadcast(P2PMessage::IpClaim { player_id, virtual_ip }).await
+    }
+
+    /// 取走最近检测到的虚拟 IP 冲突
+    pub async fn take_ip_conflict(&self) -> Option<IpConflict> {
+        self.ip_conflict.write().await.take()
+    }
+
+    /// 向指定玩家发送连接修复请求
+    /// 
+    /// # 参数
+    /// * `player_id` - 目标玩家 ID
+    /// * `via_broadcast` - 是否改用广播发送（已知地址失效时由虚拟网络转发）
+    pub async fn send_repair_request(&self, player_id: &str, via_broadcast: bool) -> Result<(), AppError> {
+        let from = self.get_local_player_id().await
+            .ok_or_else(|| AppError::NetworkError("信令服务未启动".to_string()))?;
+        let message = P2PMessage::RepairRequest {
+            from,
+            to: player_id.to_string(),
+        };
+        if via_broadcast {
+            self.broadcast(message).await
+        } else {
+            self.send_to_player(player_id, message).await
+        }
+    }
+    
+    /// 重新向指定玩家发送发现包（已知地址单播 + 广播），对方回应后会刷新其最后活跃时间
+    pub async fn send_discovery_to(&self, player_id: &str) -> Result<(), AppError> {
+        self.response_throttle.write().await.forget(player_id);
+        let (Some(local_id), Some(local_name)) = (
+            self.local_player_id.read().await.clone(),
+            self.local_player_name.read().await.clone(),
+        ) else {
+            return Err(AppError::NetworkError("信令服务未启动".to_string()));
+        };
+        let service_ports = crate::modules::service_ports::local_ports();
+        let message = P2PMessage::PlayerDiscovery {
+            player_id: local_id,
+            player_name: local_name,
+            port: *self.actual_port.read().await,
+            is_guest: self.local_is_guest.load(Ordering::Relaxed),
+            file_port: Some(service_ports.file_share),
+            chat_port: Some(service_ports.chat),
+            presence: crate::modules::presence::current(),
+        };
+        if self.has_peer(player_id).await {
+            if let Err(e) = self.send_to_player(player_id, message.clone()).await {
+                log::warn!("单播发现包到 {} 失败: {}", player_id, e);
+            }
+        }
+        self.broadcast(message).await
+    }
 }
 
 impl Default for P2PSignalingService {
@@ -687,3 +1847,291 @@ impl Default for P2PSignalingService {
         Self::new(47777) // 默认端口
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_broadcast_target_is_limited_broadcast() {
+        let addr = broadcast_target(47777);
+        assert_eq!(addr.to_string(), "255.255.255.255:47777");
+        assert_eq!(addr.ip(), std::net::IpAddr::V4(Ipv4Addr::BROADCAST));
+    }
+
+    #[test]
+    fn test_broadcast_tasks_share_target_logic() {
+        // 发现广播、心跳与 broadcast() 都只通过 broadcast_target 计算目标地址，不再有各自硬编码的地址
+        let source = include_str!("p2p_signaling.rs");
+        let code = &source[..source.find("#[cfg(test)]").unwrap()];
+        assert_eq!(code.matches("broadcast_target(*").count(), 3);
+        assert!(!code.contains("192.168.0.255"));
+        assert!(!code.contains("\"255.255.255.255"));
+    }
+
+    #[test]
+    fn test_kick_from_non_creator_ignored() {
+        let creator = Some("10.126.126.1");
+        let kick_from = |ip: &str| is_from_creator(ip.parse().unwrap(), creator);
+
+        assert!(kick_from("10.126.126.1"));
+        // 其他成员持有大厅密钥也能发出踢人消息，来源不是创建者时必须忽略
+        assert!(!kick_from("10.126.126.7"));
+        assert!(!is_from_creator("10.126.126.1".parse().unwrap(), None));
+    }
+
+    fn discovery() -> P2PMessage {
+        P2PMessage::PlayerDiscovery {
+            player_id: "peer-a".to_string(),
+            player_name: "玩家A".to_string(),
+            port: 47777,
+            is_guest: false,
+            file_port: None,
+            chat_port: None,
+            presence: Presence::Online,
+        }
+    }
+
+    #[test]
+    fn test_codec_encrypts_and_drops_foreign_packets() {
+        let codec = SignalingCodec::new(Some("secret"), false);
+        let offer = P2PMessage::Offer { from: "peer-a".to_string(), sdp: "v=0 sdp".to_string() };
+        let packet = codec.encode(&offer).unwrap();
+
+        // 密文中不出现明文内容
+        assert!(!String::from_utf8_lossy(&packet).contains("sdp"));
+        let (decoded, plaintext) = SignalingCodec::new(Some("secret"), false).decode(&packet).unwrap();
+        assert!(matches!(decoded, P2PMessage::Offer { ref sdp, .. } if sdp == "v=0 sdp"));
+        assert!(!plaintext);
+
+        // 其它大厅的广播与明文包直接丢弃
+        assert!(SignalingCodec::new(Some("other"), false).decode(&packet).is_none());
+        assert!(codec.decode(&SignalingCodec::encode_plaintext(&discovery()).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_codec_signaling_field_roundtrip_and_rejection() {
+        use crate::modules::signaling_crypto::signaling_field_aad;
+
+        let aad = signaling_field_aad("ice-candidate", "peer-a", "peer-b");
+        let codec = SignalingCodec::new(Some("secret"), true);
+        let sealed = codec.seal_field("candidate:1 1 udp 2122260223 10.126.126.2 50000 typ host", &aad);
+        assert!(!sealed.contains("10.126.126.2"));
+        assert_eq!(
+            SignalingCodec::new(Some("secret"), false).open_field(&sealed, &aad).unwrap(),
+            "candidate:1 1 udp 2122260223 10.126.126.2 50000 typ host"
+        );
+
+        // 错误的大厅密钥
+        assert_eq!(
+            SignalingCodec::new(Some("other"), false).open_field(&sealed, &aad),
+            Err(SignalingCryptoError::DecryptFailed)
+        );
+        // 设置了密钥时即使开启明文兼容也不接受明文 SDP / ICE
+        assert_eq!(codec.open_field("v=0", &aad), Err(SignalingCryptoError::NotSealed));
+        // 未设置密钥时明文收发，无法解密收到的密文
+        let plain = SignalingCodec::new(None, false);
+        assert_eq!(plain.seal_field("v=0", &aad), "v=0");
+        assert_eq!(plain.open_field("v=0", &aad).unwrap(), "v=0");
+        assert_eq!(plain.open_field(&sealed, &aad), Err(SignalingCryptoError::DecryptFailed));
+    }
+
+    #[test]
+    fn test_codec_plaintext_compat_accepts_discovery_only() {
+        let codec = SignalingCodec::new(Some("secret"), true);
+        let plain_discovery = SignalingCodec::encode_plaintext(&discovery()).unwrap();
+        let plain_offer = SignalingCodec::encode_plaintext(&P2PMessage::Offer {
+            from: "peer-a".to_string(),
+            sdp: "v=0".to_string(),
+        })
+        .unwrap();
+
+        assert!(matches!(codec.decode(&plain_discovery), Some((P2PMessage::PlayerDiscovery { .. }, true))));
+        assert!(codec.decode(&plain_offer).is_none());
+        // 明文发现按明文回复，加密发现按加密回复
+        assert_eq!(codec.encode_reply(&discovery(), true).unwrap()[0], b'{');
+        assert_ne!(codec.encode_reply(&discovery(), false).unwrap()[0], b'{');
+
+        // 未设置密钥时收发明文
+        let plain = SignalingCodec::default();
+        assert!(!plain.plaintext_compat());
+        assert_eq!(plain.encode(&discovery()).unwrap(), plain_discovery);
+        assert!(plain.decode(&plain_offer).is_some());
+    }
+
+    #[test]
+    fn test_codec_clamps_long_player_name() {
+        let codec = SignalingCodec::new(Some("secret"), false);
+        let long = P2PMessage::PlayerDiscovery {
+            player_id: "peer-a".to_string(),
+            player_name: "很".repeat(10_000),
+            port: 47777,
+            is_guest: false,
+            file_port: None,
+            chat_port: None,
+            presence: Presence::Online,
+        };
+        let (decoded, _) = codec.decode(&codec.encode(&long).unwrap()).unwrap();
+        assert!(matches!(
+            decoded,
+            P2PMessage::PlayerDiscovery { ref player_name, .. } if player_name.chars().count() == MAX_PLAYER_NAME_CHARS
+        ));
+        let (decoded, _) = codec.decode(&codec.encode(&discovery()).unwrap()).unwrap();
+        assert!(matches!(decoded, P2PMessage::PlayerDiscovery { ref player_name, .. } if player_name == "玩家A"));
+    }
+
+    #[test]
+    fn test_new_peer_is_responded_immediately() {
+        let mut throttle = DiscoveryResponseThrottle::new(Duration::from_secs(3));
+        let now = Instant::now();
+
+        assert!(throttle.should_respond("peer-a", now), "新 peer 的首次发现应立即回应");
+        assert!(throttle.should_respond("peer-b", now), "其它新 peer 不应受已回应 peer 影响");
+    }
+
+    #[test]
+    fn test_duplicate_discovery_within_interval_is_suppressed() {
+        let mut throttle = DiscoveryResponseThrottle::new(Duration::from_secs(3));
+        let now = Instant::now();
+
+        assert!(throttle.should_respond("peer-a", now));
+        assert!(!throttle.should_respond("peer-a", now + Duration::from_millis(500)));
+        assert!(!throttle.should_respond("peer-a", now + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_discovery_after_interval_is_responded_again() {
+        let mut throttle = DiscoveryResponseThrottle::new(Duration::from_secs(3));
+        let now = Instant::now();
+
+        assert!(throttle.should_respond("peer-a", now));
+        assert!(throttle.should_respond("peer-a", now + Duration::from_secs(3)));
+        // 间隔从最近一次回应重新计时
+        assert!(!throttle.should_respond("peer-a", now + Duration::from_secs(4)));
+    }
+
+    #[test]
+    fn test_forget_and_clear_allow_immediate_response() {
+        let mut throttle = DiscoveryResponseThrottle::new(Duration::from_secs(3));
+        let now = Instant::now();
+
+        assert!(throttle.should_respond("peer-a", now));
+        assert!(throttle.should_respond("peer-b", now));
+
+        throttle.forget("peer-a");
+        assert!(throttle.should_respond("peer-a", now + Duration::from_millis(100)));
+        assert!(!throttle.should_respond("peer-b", now + Duration::from_millis(100)));
+
+        throttle.clear();
+        assert!(throttle.should_respond("peer-b", now + Duration::from_millis(200)));
+    }
+
+    fn peer(player_id: &str) -> PeerInfo {
+        PeerInfo {
+            player_id: player_id.to_string(),
+            player_name: player_id.to_string(),
+            addr: "10.126.126.2:9999".parse().unwrap(),
+            last_seen: Instant::now(),
+            is_guest: false,
+            presence: Presence::Online,
+        }
+    }
+
+    #[test]
+    fn test_lobby_full_counts_local_player_and_peers() {
+        let mut peers = HashMap::new();
+        assert!(!is_lobby_full(&peers, "p1", None));
+        assert!(!is_lobby_full(&peers, "p1", Some(2)));
+
+        peers.insert("p1".to_string(), peer("p1"));
+        // 本机 + p1 已达 2 人上限
+        assert!(is_lobby_full(&peers, "p2", Some(2)));
+        // 已加入的玩家再次发现不受限制
+        assert!(!is_lobby_full(&peers, "p1", Some(2)));
+        assert!(!is_lobby_full(&peers, "p2", None));
+    }
+
+    #[test]
+    fn test_simultaneous_joins_never_exceed_limit() {
+        // 接收循环串行处理：每个加入请求先检查再插入
+        let mut peers = HashMap::new();
+        let mut rejected = Vec::new();
+        for id in ["p1", "p2", "p3", "p4", "p5"] {
+            if is_lobby_full(&peers, id, Some(3)) {
+                rejected.push(id);
+            } else {
+                peers.insert(id.to_string(), peer(id));
+            }
+        }
+        assert_eq!(peers.len() + 1, 3);
+        assert_eq!(rejected, vec!["p3", "p4", "p5"]);
+    }
+
+    #[test]
+    fn test_guests_do_not_take_lobby_slots() {
+        let mut peers = HashMap::new();
+        let mut guest = peer("g1");
+        guest.is_guest = true;
+        peers.insert("g1".to_string(), guest);
+
+        // 只有游客在场时，上限 2 的大厅仍可加入一名正式玩家
+        assert!(!is_lobby_full(&peers, "p1", Some(2)));
+        peers.insert("p1".to_string(), peer("p1"));
+        assert!(is_lobby_full(&peers, "p2", Some(2)));
+    }
+
+    #[test]
+    fn test_discovery_guest_flag_defaults_to_false() {
+        let legacy = r#"{"type":"player-discovery","playerId":"p1","playerName":"Steve","port":8888}"#;
+        match serde_json::from_str::<P2PMessage>(legacy).unwrap() {
+            P2PMessage::PlayerDiscovery { is_guest, .. } => assert!(!is_guest),
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        let json = serde_json::to_string(&P2PMessage::PlayerDiscoveryResponse {
+            player_id: "p1".to_string(),
+            player_name: "Steve".to_string(),
+            port: 8888,
+            is_guest: true,
+            file_port: None,
+            chat_port: None,
+            presence: Presence::Online,
+        })
+        .unwrap();
+        assert!(json.contains("\"isGuest\":true"));
+    }
+
+    #[test]
+    fn test_discovery_carries_service_ports() {
+        // 旧版本不宣告文件 / 聊天端口
+        let legacy = r#"{"type":"player-discovery","playerId":"p1","playerName":"Steve","port":8888}"#;
+        match serde_json::from_str::<P2PMessage>(legacy).unwrap() {
+            P2PMessage::PlayerDiscovery { file_port, chat_port, presence, .. } => {
+                assert_eq!((file_port, chat_port), (None, None));
+                assert_eq!(presence, Presence::Online);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        let json = serde_json::to_string(&P2PMessage::PlayerDiscovery {
+            player_id: "p1".to_string(),
+            player_name: "Steve".to_string(),
+            port: 8888,
+            is_guest: false,
+            file_port: Some(15539),
+            chat_port: Some(15540),
+            presence: Presence::InGame,
+        })
+        .unwrap();
+        match serde_json::from_str::<P2PMessage>(&json).unwrap() {
+            P2PMessage::PlayerDiscovery { file_port, chat_port, presence, .. } => {
+                assert_eq!((file_port, chat_port), (Some(15539), Some(15540)));
+                assert_eq!(presence, Presence::InGame);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(json.contains("\"filePort\":15539"));
+        assert!(json.contains("\"presence\":\"in_game\""));
+    }
+}
@@ -0,0 +1,188 @@
+// 文件命令路径白名单
+//
+// save_file / read_file / delete_file / write_file_bytes 等命令由前端传入路径，
+// 不加限制时前端（或被注入的脚本）可以读写删除系统任意文件。这里维护一份允许目录白名单：
+// 默认包含应用配置目录、日志目录、系统下载目录和临时目录，用户通过选择对话框选中的目录或文件在运行时动态加入。
+// 所有文件命令（以及添加共享文件夹）的路径都必须落在白名单内，否则返回权限错误。
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use parking_lot::RwLock;
+use thiserror::Error;
+
+use crate::modules::error::CommandError;
+
+/// 路径校验错误
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PathGuardError {
+    #[error("权限错误：只允许访问绝对路径")]
+    NotAbsolute,
+    #[error("权限错误：路径中不允许包含 ..")]
+    ParentTraversal,
+    #[error("权限错误：路径不在允许访问的目录内: {0}")]
+    Denied(String),
+}
+
+/// 允许访问的目录白名单
+#[derive(Debug, Default)]
+pub struct PathAllowlist {
+    roots: Vec<PathBuf>,
+}
+
+/// 解析符号链接后的路径
+///
+/// Windows 上规范化结果带 `\\?\` 前缀，白名单目录与待校验路径都经过同样的规范化，前缀一致。
+/// 路径可能尚不存在（例如即将保存的文件），此时规范化最近一个存在的祖先目录，再拼回剩余部分。
+fn resolve(path: &Path) -> Result<PathBuf, PathGuardError> {
+    if !path.is_absolute() {
+        return Err(PathGuardError::NotAbsolute);
+    }
+    if path.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(PathGuardError::ParentTraversal);
+    }
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = std::fs::canonicalize(existing) {
+            let mut resolved = canonical;
+            resolved.extend(rest.iter().rev());
+            return Ok(resolved);
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return Ok(path.to_path_buf()),
+        }
+    }
+}
+
+impl PathAllowlist {
+    /// 创建空白名单
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 把目录（或单个文件）加入白名单，已被覆盖的路径不重复添加
+    pub fn allow(&mut self, path: &Path) {
+        let Ok(dir) = resolve(path) else {
+            log::warn!("⚠️ 忽略无效的白名单路径: {}", path.display());
+            return;
+        };
+        if self.roots.iter().any(|root| dir.starts_with(root)) {
+            return;
+        }
+        log::info!("已允许文件命令访问: {}", dir.display());
+        self.roots.retain(|root| !root.starts_with(&dir));
+        self.roots.push(dir);
+    }
+
+    /// 校验路径是否在白名单内，返回解析后的路径
+    pub fn check(&self, path: &Path) -> Result<PathBuf, PathGuardError> {
+        let resolved = resolve(path)?;
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(PathGuardError::Denied(path.display().to_string()))
+        }
+    }
+}
+
+/// 全局路径白名单
+///
+/// 首次使用时加入默认目录，以及已持久化的下载任务所在目录（重启后仍需续传 / 清理残留文件）。
+pub fn allowlist() -> &'static RwLock<PathAllowlist> {
+    static ALLOWLIST: OnceLock<RwLock<PathAllowlist>> = OnceLock::new();
+    ALLOWLIST.get_or_init(|| {
+        let mut allowlist = PathAllowlist::new();
+        let defaults = [
+            dirs::config_dir().map(|d| d.join("mctier")),
+            Some(crate::modules::log_file::log_dir()),
+            dirs::download_dir(),
+            Some(std::env::temp_dir()),
+        ];
+        for dir in defaults.into_iter().flatten() {
+            allowlist.allow(&dir);
+        }
+        for record in crate::modules::download_tasks::download_store().lock().list() {
+            if let Some(parent) = Path::new(&record.save_path).parent() {
+                allowlist.allow(parent);
+            }
+        }
+        RwLock::new(allowlist)
+    })
+}
+
+/// 把用户通过对话框选中的目录或文件加入白名单
+pub fn allow(path: &Path) {
+    allowlist().write().allow(path);
+}
+
+/// 校验文件命令的路径，失败时返回权限错误
+pub fn check_path(path: &str) -> Result<PathBuf, CommandError> {
+    allowlist().read().check(Path::new(path)).map_err(|e| {
+        log::warn!("⚠️ 拒绝访问白名单外的路径: {}", path);
+        CommandError::permission(e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_check() {
+        let base = std::env::temp_dir().join(format!("mctier-path-guard-{}", std::process::id()));
+        let allowed = base.join("allowed");
+        let other = base.join("other");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+
+        let mut allowlist = PathAllowlist::new();
+        allowlist.allow(&allowed);
+
+        // 已存在的文件与尚未创建的深层文件都允许
+        assert!(allowlist.check(&allowed.join("a.txt")).is_ok());
+        assert!(allowlist.check(&allowed.join("new").join("b.txt")).is_ok());
+        // 白名单外、相对路径、.. 逃逸都拒绝
+        assert!(matches!(allowlist.check(&other.join("a.txt")), Err(PathGuardError::Denied(_))));
+        assert_eq!(allowlist.check(Path::new("a.txt")), Err(PathGuardError::NotAbsolute));
+        assert_eq!(
+            allowlist.check(&allowed.join("..").join("other").join("a.txt")),
+            Err(PathGuardError::ParentTraversal)
+        );
+        // 前缀相同但不是子目录
+        assert!(allowlist.check(&base.join("allowed-evil").join("a.txt")).is_err());
+
+        // 单个文件只放行该文件
+        allowlist.allow(&other.join("picked.json"));
+        assert!(allowlist.check(&other.join("picked.json")).is_ok());
+        assert!(allowlist.check(&other.join("a.txt")).is_err());
+
+        // 加入父目录后覆盖原有子目录
+        allowlist.allow(&base);
+        assert_eq!(allowlist.roots.len(), 1);
+        assert!(allowlist.check(&other.join("a.txt")).is_ok());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_allowlist_rejects_symlink_escape() {
+        let base = std::env::temp_dir().join(format!("mctier-path-guard-link-{}", std::process::id()));
+        let allowed = base.join("allowed");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, allowed.join("link")).unwrap();
+
+        let mut allowlist = PathAllowlist::new();
+        allowlist.allow(&allowed);
+        assert!(allowlist.check(&allowed.join("link").join("a.txt")).is_err());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+// 虚拟网 HTTP 服务（文件共享 / 聊天）的请求来源校验
+//
+// EasyTier 网络按大厅隔离，但同一网络的多个实例之间仍可能互访。文件/聊天服务只应对当前大厅的成员开放：
+// 连接来源 IP 必须是本机，或在 P2P 信令已发现的 peer 列表内，其余连接在接受后直接关闭。
+// 刚进入大厅时 peer 列表尚未建立，宽限期内暂不拒绝与本机同一虚拟网段的未知来源，避免成员在发现完成前的正常请求失败；
+// 虚拟网段之外的来源（如同一物理局域网里的设备经路由访问虚拟 IP）任何时候都直接拒绝。
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+
+use crate::modules::p2p_signaling::PeerInfo;
+
+/// 进入大厅后 peer 列表建立的宽限期
+pub const PEER_LIST_GRACE: Duration = Duration::from_secs(20);
+/// 虚拟网段前缀长度（EasyTier 默认分配 /24）
+const VIRTUAL_SUBNET_PREFIX_LEN: u32 = 24;
+
+/// P2P 信令维护的 peer 列表
+pub type PeerTable = Arc<tokio::sync::RwLock<HashMap<String, PeerInfo>>>;
+
+/// 当前大厅的访问控制
+#[derive(Clone)]
+struct LobbyAccess {
+    /// 本机虚拟 IP
+    local_ip: Option<IpAddr>,
+    /// 已发现的 peer 列表
+    peers: PeerTable,
+    /// 开始校验的时间（进入大厅的时间）
+    since: Instant,
+}
+
+fn access_slot() -> &'static RwLock<Option<LobbyAccess>> {
+    static SLOT: OnceLock<RwLock<Option<LobbyAccess>>> = OnceLock::new();
+    SLOT.get_or_init(|| RwLock::new(None))
+}
+
+/// 进入大厅时启用来源校验
+///
+/// # 参数
+/// * `local_virtual_ip` - 本机虚拟 IP
+/// * `peers` - P2P 信令的 peer 列表
+pub fn configure_for_lobby(local_virtual_ip: &str, peers: PeerTable) {
+    *access_slot().write() = Some(LobbyAccess {
+        local_ip: local_virtual_ip.parse().ok(),
+        peers,
+        since: Instant::now(),
+    });
+    log::info!("🔒 [PeerAccess] 文件/聊天服务仅对本大厅成员开放");
+}
+
+/// 断线重连后虚拟 IP 变化时更新本机地址
+pub fn update_local_ip(local_virtual_ip: &str) {
+    if let Some(access) = access_slot().write().as_mut() {
+        access.local_ip = local_virtual_ip.parse().ok();
+    }
+}
+
+/// 退出大厅时关闭来源校验
+pub fn deactivate() {
+    *access_slot().write() = None;
+}
+
+/// 来源 IP 是否与本机虚拟 IP 处于同一虚拟网段
+fn in_virtual_subnet(remote: IpAddr, local_ip: Option<IpAddr>) -> bool {
+    let (IpAddr::V4(remote), Some(IpAddr::V4(local))) = (remote, local_ip) else {
+        return false;
+    };
+    let mask = u32::MAX << (32 - VIRTUAL_SUBNET_PREFIX_LEN);
+    u32::from(remote) & mask == u32::from(local) & mask
+}
+
+/// 判断来源 IP 是否允许访问
+///
+/// # 参数
+/// * `remote` - 连接来源 IP
+/// * `local_ip` - 本机虚拟 IP
+/// * `peer_ips` - 已发现的 peer 虚拟 IP
+/// * `elapsed` - 进入大厅以来经过的时间
+pub fn is_request_allowed(remote: IpAddr, local_ip: Option<IpAddr>, peer_ips: &[IpAddr], elapsed: Duration) -> bool {
+    remote.is_loopback()
+        || Some(remote) == local_ip
+        || peer_ips.contains(&remote)
+        || (elapsed < PEER_LIST_GRACE && in_virtual_subnet(remote, local_ip))
+}
+
+/// 检查连接来源是否为当前大厅成员（未进入大厅时不做限制）
+pub async fn is_allowed(remote: IpAddr) -> bool {
+    let access = access_slot().read().clone();
+    let Some(access) = access else {
+        return true;
+    };
+    let peer_ips: Vec<IpAddr> = access.peers.read().await.values().map(|p| p.addr.ip()).collect();
+    let allowed = is_request_allowed(remote, access.local_ip, &peer_ips, access.since.elapsed());
+    if !allowed {
+        log::warn!("⚠️ [PeerAccess] 拒绝非大厅成员 {} 的请求", remote);
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_non_member_request_rejected() {
+        let local = Some(ip("10.126.126.2"));
+        let peers = [ip("10.126.126.1"), ip("10.126.126.3")];
+        let settled = PEER_LIST_GRACE + Duration::from_secs(1);
+
+        assert!(!is_request_allowed(ip("10.126.126.9"), local, &peers, settled));
+        // peer 列表为空（其他成员都已离开）时同样拒绝
+        assert!(!is_request_allowed(ip("10.126.126.9"), local, &[], settled));
+    }
+
+    #[test]
+    fn test_members_and_local_requests_allowed() {
+        let local = Some(ip("10.126.126.2"));
+        let peers = [ip("10.126.126.1")];
+        let settled = PEER_LIST_GRACE + Duration::from_secs(1);
+
+        assert!(is_request_allowed(ip("10.126.126.1"), local, &peers, settled));
+        assert!(is_request_allowed(ip("10.126.126.2"), local, &peers, settled));
+        assert!(is_request_allowed(ip("127.0.0.1"), local, &peers, settled));
+    }
+
+    #[test]
+    fn test_unknown_source_allowed_during_grace() {
+        let local = Some(ip("10.126.126.2"));
+        assert!(is_request_allowed(ip("10.126.126.9"), local, &[], Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_physical_lan_source_rejected_during_grace() {
+        let local = Some(ip("10.126.126.2"));
+        assert!(!is_request_allowed(ip("192.168.1.20"), local, &[], Duration::from_secs(1)));
+        assert!(!is_request_allowed(ip("10.0.0.5"), local, &[], Duration::from_secs(1)));
+        // 本机虚拟 IP 未知时无法判断网段，宽限期内同样拒绝
+        assert!(!is_request_allowed(ip("10.126.126.9"), None, &[], Duration::from_secs(1)));
+    }
+}
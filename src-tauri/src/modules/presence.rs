@@ -0,0 +1,256 @@
+// 玩家在线状态（在线 / 离开 / 游戏中 / 忙碌）
+//
+// 用户可以手动设置状态；设为"在线"时由后台自动检测：检测到 Minecraft 进程运行时为"游戏中"，
+// 系统长时间没有键鼠输入或主窗口长时间最小化时为"离开"。手动设置的其它状态优先于自动检测。
+// 状态变化通过 P2P 信令广播，并随发现广播携带给新加入的成员。
+
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// 无输入 / 最小化超过该时长视为离开
+pub const AWAY_IDLE_THRESHOLD: Duration = Duration::from_secs(5 * 60);
+/// 自动检测间隔
+pub const PRESENCE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 在线状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    /// 在线
+    #[default]
+    Online,
+    /// 离开
+    Away,
+    /// 游戏中
+    InGame,
+    /// 忙碌
+    Busy,
+}
+
+impl Presence {
+    /// 中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Presence::Online => "在线",
+            Presence::Away => "离开",
+            Presence::InGame => "游戏中",
+            Presence::Busy => "忙碌",
+        }
+    }
+}
+
+/// 本机状态跟踪：手动状态优先，否则按自动检测结果
+#[derive(Debug, Default)]
+pub struct PresenceTracker {
+    /// 手动设置的状态（None 表示自动检测）
+    manual: Option<Presence>,
+    game_running: bool,
+    idle: bool,
+    /// 主窗口开始最小化的时间
+    minimized_since: Option<Instant>,
+    current: Presence,
+}
+
+impl PresenceTracker {
+    /// 当前生效的状态
+    pub fn current(&self) -> Presence {
+        self.current
+    }
+
+    /// 手动设置状态，设为"在线"表示恢复自动检测
+    ///
+    /// # 返回
+    /// 生效状态发生变化时返回新状态
+    pub fn set_manual(&mut self, presence: Presence) -> Option<Presence> {
+        self.manual = (presence != Presence::Online).then_some(presence);
+        self.refresh()
+    }
+
+    /// 更新自动检测结果
+    ///
+    /// # 参数
+    /// * `game_running` - 是否检测到 Minecraft 进程
+    /// * `system_idle` - 系统无键鼠输入的时长（平台不支持时为 None）
+    /// * `minimized` - 主窗口是否处于最小化
+    /// * `now` - 当前时间
+    ///
+    /// # 返回
+    /// 生效状态发生变化时返回新状态
+    pub fn observe(&mut self, game_running: bool, system_idle: Option<Duration>, minimized: bool, now: Instant) -> Option<Presence> {
+        self.game_running = game_running;
+        self.minimized_since = minimized.then(|| self.minimized_since.unwrap_or(now));
+        let minimized_for = self.minimized_since.map(|since| now.duration_since(since));
+        self.idle = [system_idle, minimized_for].into_iter().flatten().any(|d| d >= AWAY_IDLE_THRESHOLD);
+        self.refresh()
+    }
+
+    fn resolve(&self) -> Presence {
+        if let Some(manual) = self.manual {
+            manual
+        } else if self.game_running {
+            // 游戏窗口在前台时本应用收不到输入，游戏中优先于离开
+            Presence::InGame
+        } else if self.idle {
+            Presence::Away
+        } else {
+            Presence::Online
+        }
+    }
+
+    fn refresh(&mut self) -> Option<Presence> {
+        let next = self.resolve();
+        if next == self.current {
+            return None;
+        }
+        self.current = next;
+        Some(next)
+    }
+}
+
+/// 本机状态跟踪器
+pub fn tracker() -> &'static Mutex<PresenceTracker> {
+    static TRACKER: OnceLock<Mutex<PresenceTracker>> = OnceLock::new();
+    TRACKER.get_or_init(|| Mutex::new(PresenceTracker::default()))
+}
+
+/// 本机当前状态
+pub fn current() -> Presence {
+    tracker().lock().current()
+}
+
+/// 解析 `tasklist /V /FO CSV /NH` 的输出，判断是否有 Minecraft 在运行
+///
+/// 基岩版按进程名识别；Java 版进程名是通用的 java(w).exe，按窗口标题识别（排除官方启动器）。
+pub fn tasklist_has_minecraft(output: &str) -> bool {
+    output.lines().any(|line| {
+        let fields: Vec<&str> = line.trim().trim_matches('"').split("\",\"").collect();
+        let image = fields.first().map(|s| s.to_ascii_lowercase()).unwrap_or_default();
+        let title = fields.last().map(|s| s.trim().to_ascii_lowercase()).unwrap_or_default();
+        if image == "minecraft.windows.exe" {
+            return true;
+        }
+        (image == "javaw.exe" || image == "java.exe")
+            && title.starts_with("minecraft")
+            && !title.starts_with("minecraft launcher")
+    })
+}
+
+/// 解析 `ps -A -o command=` 的输出，判断是否有 Minecraft Java 版在运行
+///
+/// 原版与各类模组加载器启动的游戏进程都会带 `net.minecraft` 主类、启动器标识或 `--assetsDir` 参数，
+/// 单独运行的启动器（HMCL、PCL 等）没有这些参数。
+pub fn ps_has_minecraft(output: &str) -> bool {
+    output.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.contains("java")
+            && (line.contains("net.minecraft") || line.contains("minecraft.launcher.brand") || line.contains("--assetsdir"))
+    })
+}
+
+/// 检测本机是否有 Minecraft 在运行（查询失败视为未运行）
+#[cfg(target_os = "windows")]
+pub async fn is_minecraft_running() -> bool {
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    match tokio::process::Command::new("tasklist")
+        .args(["/V", "/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .await
+    {
+        // tasklist 输出可能是 GBK，进程名与 Minecraft 窗口标题前缀是 ASCII，lossy 不影响匹配
+        Ok(o) => tasklist_has_minecraft(&String::from_utf8_lossy(&o.stdout)),
+        Err(e) => {
+            log::debug!("查询进程列表失败: {}", e);
+            false
+        }
+    }
+}
+
+/// 检测本机是否有 Minecraft 在运行（查询失败视为未运行）
+#[cfg(not(target_os = "windows"))]
+pub async fn is_minecraft_running() -> bool {
+    match tokio::process::Command::new("ps").args(["-A", "-o", "command="]).output().await {
+        Ok(o) => ps_has_minecraft(&String::from_utf8_lossy(&o.stdout)),
+        Err(e) => {
+            log::debug!("查询进程列表失败: {}", e);
+            false
+        }
+    }
+}
+
+/// 系统无键鼠输入的时长（仅 Windows，其它平台只按窗口最小化判断离开）
+#[cfg(target_os = "windows")]
+pub fn system_idle_time() -> Option<Duration> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    // SAFETY: info 为有效的 LASTINPUTINFO 且 cbSize 已正确设置
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return None;
+    }
+    // GetTickCount 约 49.7 天回绕一次，wrapping_sub 保证回绕前后的差值正确
+    let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Some(Duration::from_millis(idle_ms as u64))
+}
+
+/// 系统无键鼠输入的时长（仅 Windows，其它平台只按窗口最小化判断离开）
+#[cfg(not(target_os = "windows"))]
+pub fn system_idle_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence_tracker_priority() {
+        let start = Instant::now();
+        let mut tracker = PresenceTracker::default();
+        assert_eq!(tracker.observe(false, Some(Duration::from_secs(10)), false, start), None);
+        assert_eq!(tracker.observe(false, Some(AWAY_IDLE_THRESHOLD), false, start), Some(Presence::Away));
+        // 游戏中优先于离开
+        assert_eq!(tracker.observe(true, Some(AWAY_IDLE_THRESHOLD), false, start), Some(Presence::InGame));
+
+        // 手动状态优先于自动检测，设回在线后恢复自动检测
+        assert_eq!(tracker.set_manual(Presence::Busy), Some(Presence::Busy));
+        assert_eq!(tracker.observe(false, None, false, start), None);
+        assert_eq!(tracker.set_manual(Presence::Online), Some(Presence::Online));
+
+        // 最小化需持续超过阈值才算离开，恢复窗口后重新计时
+        assert_eq!(tracker.observe(false, None, true, start), None);
+        assert_eq!(tracker.observe(false, None, true, start + AWAY_IDLE_THRESHOLD), Some(Presence::Away));
+        assert_eq!(tracker.observe(false, None, false, start + AWAY_IDLE_THRESHOLD), Some(Presence::Online));
+        assert_eq!(tracker.observe(false, None, true, start + AWAY_IDLE_THRESHOLD * 2), None);
+
+        assert_eq!(serde_json::to_string(&Presence::InGame).unwrap(), "\"in_game\"");
+    }
+
+    #[test]
+    fn test_detect_minecraft_process() {
+        let tasklist = concat!(
+            "\"javaw.exe\",\"1200\",\"Console\",\"1\",\"1,024,000 K\",\"Running\",\"PC\\\\user\",\"0:05:00\",\"Minecraft* 1.20.1 - 多人游戏（第三方服务器）\"\r\n",
+            "\"chrome.exe\",\"800\",\"Console\",\"1\",\"200,000 K\",\"Running\",\"PC\\\\user\",\"0:01:00\",\"Minecraft Wiki - Chrome\"\r\n",
+        );
+        assert!(tasklist_has_minecraft(tasklist));
+        assert!(!tasklist_has_minecraft(
+            "\"javaw.exe\",\"1200\",\"Console\",\"1\",\"300,000 K\",\"Running\",\"PC\\\\user\",\"0:00:10\",\"Minecraft Launcher\"\r\n"
+        ));
+        assert!(tasklist_has_minecraft(
+            "\"Minecraft.Windows.exe\",\"900\",\"Console\",\"1\",\"500,000 K\",\"Running\",\"PC\\\\user\",\"0:02:00\",\"Minecraft\"\r\n"
+        ));
+
+        assert!(ps_has_minecraft(
+            "/usr/bin/java -Xmx4G -Dminecraft.launcher.brand=HMCL net.minecraft.client.main.Main --assetsDir /home/u/.minecraft/assets\n"
+        ));
+        assert!(!ps_has_minecraft("/usr/bin/java -jar HMCL-3.5.jar\n/usr/bin/bash\n"));
+    }
+}
@@ -0,0 +1,75 @@
+// 中继连接提示
+//
+// P2P 打洞失败时 EasyTier 会自动改走中继，延迟变高但用户无感知。每次查询连接类型后与上次结果比较，
+// 新变为中继的 peer 通过 `connection-relayed` 事件提示用户；跨国联机可指定偏好中继节点改善延迟。
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// 连接变为中继时推送的事件
+pub const CONNECTION_RELAYED_EVENT: &str = "connection-relayed";
+
+/// `connection-relayed` 事件内容
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayedPeer {
+    /// 虚拟 IP
+    pub ip: String,
+    /// 玩家名称（已完成握手时）
+    pub player_name: Option<String>,
+    /// 当前设置的偏好中继节点
+    pub preferred_relay: Option<String>,
+}
+
+/// 记录上一次查询时处于中继连接的 peer
+#[derive(Debug, Default)]
+pub struct RelayWatch {
+    relayed: HashSet<String>,
+}
+
+impl RelayWatch {
+    /// 用最新的连接类型更新记录
+    ///
+    /// # 参数
+    /// * `peers` - (虚拟 IP, 是否中继) 列表，未出现的 peer 视为已离开
+    ///
+    /// # 返回
+    /// 本次新变为中继连接的 peer 虚拟 IP
+    pub fn update<'a>(&mut self, peers: impl IntoIterator<Item = (&'a str, bool)>) -> Vec<String> {
+        let relayed: HashSet<String> = peers
+            .into_iter()
+            .filter(|(_, relay)| *relay)
+            .map(|(ip, _)| ip.to_string())
+            .collect();
+        let mut newly: Vec<String> = relayed.difference(&self.relayed).cloned().collect();
+        newly.sort();
+        self.relayed = relayed;
+        newly
+    }
+}
+
+/// 全局中继记录
+pub fn relay_watch() -> &'static Mutex<RelayWatch> {
+    static WATCH: OnceLock<Mutex<RelayWatch>> = OnceLock::new();
+    WATCH.get_or_init(|| Mutex::new(RelayWatch::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_watch_reports_new_relays_once() {
+        let mut watch = RelayWatch::default();
+        assert!(watch.update([("10.0.0.2", false)]).is_empty());
+        assert_eq!(watch.update([("10.0.0.2", true), ("10.0.0.3", true)]), vec!["10.0.0.2", "10.0.0.3"]);
+        // 持续中继不重复提示
+        assert!(watch.update([("10.0.0.2", true), ("10.0.0.3", true)]).is_empty());
+        // 恢复直连后再次回退中继时重新提示；离开的 peer 不保留记录
+        assert!(watch.update([("10.0.0.2", false)]).is_empty());
+        assert_eq!(watch.update([("10.0.0.2", true), ("10.0.0.3", true)]), vec!["10.0.0.2", "10.0.0.3"]);
+    }
+}
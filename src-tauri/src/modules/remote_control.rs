@@ -4,6 +4,8 @@
 
 use serde::Deserialize;
 
+use crate::modules::error::CommandError;
+
 /// 单个远程输入事件（与前端协议一致）
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "kind")]
@@ -36,15 +38,15 @@ pub enum RemoteInputEvent {
 
 /// 注入一批输入事件
 #[tauri::command]
-pub fn remote_inject_input(events: Vec<RemoteInputEvent>) -> Result<(), String> {
+pub fn remote_inject_input(events: Vec<RemoteInputEvent>) -> Result<(), CommandError> {
     #[cfg(target_os = "windows")]
     {
-        platform::inject(&events)
+        platform::inject(&events).map_err(CommandError::from)
     }
     #[cfg(not(target_os = "windows"))]
     {
         let _ = events;
-        Err("远程控制注入仅支持 Windows".to_string())
+        Err(CommandError::validation("远程控制注入仅支持 Windows"))
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::modules::error::AppError;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::Write;
 use tauri::Manager;
@@ -116,6 +116,12 @@ impl ResourceManager {
         file.write_all(bytes).map_err(|e| {
             AppError::ConfigError(format!("无法写入文件 {}: {}", filename, e))
         })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(&target_path, fs::Permissions::from_mode(0o755));
+        }
         
         log::info!("成功提取文件到: {:?}", target_path);
         Ok(target_path)
@@ -128,8 +134,48 @@ impl ResourceManager {
     /// 
     /// # 返回
     /// * `Ok(PathBuf)` - EasyTier 可执行文件的完整路径
-    /// * `Err(AppError)` - 获取路径失败
+    /// * `Err(AppError)` - 获取路径失败，或文件不存在 / 不可执行
     pub fn get_easytier_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+        let path = Self::locate_easytier_binary(app_handle)?;
+        Self::verify_executable(&path)?;
+        Ok(path)
+    }
+
+    /// 校验二进制文件存在且可执行
+    ///
+    /// 杀毒软件隔离、提取中断都可能留下缺失或被截断的文件，这里提前给出明确错误，
+    /// 避免启动时只得到含糊的"进程意外终止"。Windows 上检查 PE 文件头（`MZ`），其它平台检查可执行权限位。
+    pub fn verify_executable(path: &Path) -> Result<(), AppError> {
+        let metadata = fs::metadata(path).map_err(|e| {
+            AppError::ProcessError(format!("EasyTier 程序不存在（可能被杀毒软件隔离）: {:?} - {}", path, e))
+        })?;
+        if !metadata.is_file() || metadata.len() < 2 {
+            return Err(AppError::ProcessError(format!("EasyTier 程序文件已损坏: {:?}", path)));
+        }
+
+        #[cfg(windows)]
+        {
+            use std::io::Read;
+            let mut header = [0u8; 2];
+            fs::File::open(path)
+                .and_then(|mut file| file.read_exact(&mut header))
+                .map_err(|e| AppError::ProcessError(format!("无法读取 EasyTier 程序: {:?} - {}", path, e)))?;
+            if &header != b"MZ" {
+                return Err(AppError::ProcessError(format!("EasyTier 程序不是有效的可执行文件: {:?}", path)));
+            }
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o111 == 0 {
+                return Err(AppError::ProcessError(format!("EasyTier 程序没有可执行权限: {:?}", path)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 定位 easytier-core 二进制（开发模式优先外部文件，否则从内嵌资源提取）
+    fn locate_easytier_binary(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
         // 在开发模式下，优先使用 target 目录中的 binaries；不存在时退回到嵌入提取
         #[cfg(debug_assertions)]
         {
@@ -283,10 +329,38 @@ impl ResourceManager {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_resource_manager_exists() {
         // 这个测试只是确保模块可以编译
         // 实际的路径测试需要在集成测试中进行
         assert!(true);
     }
+
+    #[test]
+    fn test_verify_executable() {
+        let dir = std::env::temp_dir().join(format!("mctier-verify-exe-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert!(ResourceManager::verify_executable(&dir.join("missing.exe")).is_err());
+        assert!(ResourceManager::verify_executable(&dir).is_err());
+
+        let empty = dir.join("empty.exe");
+        fs::write(&empty, b"").unwrap();
+        assert!(ResourceManager::verify_executable(&empty).is_err());
+
+        let binary = dir.join("easytier-core.exe");
+        fs::write(&binary, b"MZ\x90\x00").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&binary, fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(ResourceManager::verify_executable(&binary).is_err());
+            fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        assert!(ResourceManager::verify_executable(&binary).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,209 @@
+// 房间健康分
+//
+// 综合所有 peer 的延迟、丢包与连接类型（直连 / 中继）给出 0–100 的房间健康分，
+// 让房主一眼看出整个房间的连接好不好。每个 peer 先单独评分，房间分取平均分与最低分的加权，
+// 避免一个很差的 peer 被其他人的好成绩平均掉。分数偏低时列出主要拖后腿的 peer 及原因。
+
+use serde::Serialize;
+
+/// 低于该分数的房间给出拖后腿的 peer
+pub const ROOM_HEALTH_GOOD: u8 = 80;
+
+/// 最多列出的拖后腿 peer 数量
+const MAX_LAGGARDS: usize = 3;
+
+/// 延迟不扣分的上限（毫秒）
+const LATENCY_FREE_MS: u64 = 50;
+/// 延迟扣满分的阈值（毫秒）
+const LATENCY_WORST_MS: u64 = 300;
+/// 延迟最多扣分
+const LATENCY_MAX_PENALTY: f64 = 40.0;
+/// 每 1% 丢包扣分
+const LOSS_PENALTY_PER_PERCENT: f64 = 2.0;
+/// 丢包最多扣分
+const LOSS_MAX_PENALTY: f64 = 40.0;
+/// 中继连接扣分
+const RELAY_PENALTY: f64 = 15.0;
+/// 房间分中平均分的权重（其余为最低分）
+const MEAN_WEIGHT: f64 = 0.7;
+
+/// 单个 peer 的网络质量
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerQuality {
+    /// 虚拟 IP
+    pub ip: String,
+    /// 玩家名称（已完成握手时）
+    pub player_name: Option<String>,
+    /// 是否为中继连接
+    pub relay: bool,
+    /// 延迟（毫秒），None 表示测不到
+    pub latency_ms: Option<u64>,
+    /// 丢包率（百分比 0–100）
+    pub loss_rate: u8,
+}
+
+/// 扣分项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssue {
+    /// 延迟偏高
+    HighLatency,
+    /// 测不到延迟（可能不可达）
+    Unreachable,
+    /// 丢包
+    PacketLoss,
+    /// 中继连接
+    Relay,
+}
+
+/// 单个 peer 的评分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerHealth {
+    /// 虚拟 IP
+    pub ip: String,
+    /// 玩家名称
+    pub player_name: Option<String>,
+    /// 分数（0–100）
+    pub score: u8,
+    /// 扣分最多的一项（满分时为空）
+    pub main_issue: Option<HealthIssue>,
+    /// 延迟（毫秒）
+    pub latency_ms: Option<u64>,
+    /// 丢包率（百分比）
+    pub loss_rate: u8,
+    /// 是否为中继连接
+    pub relay: bool,
+}
+
+/// 房间健康分
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomHealth {
+    /// 房间分（0–100），没有其他 peer 时为 100
+    pub score: u8,
+    /// 各 peer 的评分（按分数升序）
+    pub peers: Vec<PeerHealth>,
+    /// 拖后腿的 peer（房间分低于 `ROOM_HEALTH_GOOD` 时给出，按分数升序）
+    pub laggards: Vec<PeerHealth>,
+    /// 走中继连接的 peer 数量
+    pub relayed_count: usize,
+}
+
+/// 计算单个 peer 的分数与主要扣分项
+pub fn score_peer(peer: &PeerQuality) -> PeerHealth {
+    let (latency_issue, latency_penalty) = match peer.latency_ms {
+        None => (HealthIssue::Unreachable, LATENCY_MAX_PENALTY),
+        Some(ms) => {
+            let over = ms.saturating_sub(LATENCY_FREE_MS) as f64;
+            let range = (LATENCY_WORST_MS - LATENCY_FREE_MS) as f64;
+            (HealthIssue::HighLatency, (over / range).min(1.0) * LATENCY_MAX_PENALTY)
+        }
+    };
+    let loss_penalty = (peer.loss_rate.min(100) as f64 * LOSS_PENALTY_PER_PERCENT).min(LOSS_MAX_PENALTY);
+    let relay_penalty = if peer.relay { RELAY_PENALTY } else { 0.0 };
+
+    let penalties = [
+        (latency_issue, latency_penalty),
+        (HealthIssue::PacketLoss, loss_penalty),
+        (HealthIssue::Relay, relay_penalty),
+    ];
+    let total: f64 = penalties.iter().map(|(_, p)| p).sum();
+    // 扣分并列时取靠前的一项（延迟 > 丢包 > 中继）
+    let main_issue = penalties
+        .iter()
+        .rev()
+        .filter(|(_, p)| *p > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(issue, _)| *issue);
+
+    PeerHealth {
+        ip: peer.ip.clone(),
+        player_name: peer.player_name.clone(),
+        score: (100.0 - total).clamp(0.0, 100.0).round() as u8,
+        main_issue,
+        latency_ms: peer.latency_ms,
+        loss_rate: peer.loss_rate,
+        relay: peer.relay,
+    }
+}
+
+/// 计算房间健康分
+pub fn room_health(peers: &[PeerQuality]) -> RoomHealth {
+    let mut scored: Vec<PeerHealth> = peers.iter().map(score_peer).collect();
+    scored.sort_by_key(|p| p.score);
+
+    let Some(min) = scored.first().map(|p| p.score as f64) else {
+        return RoomHealth { score: 100, peers: Vec::new(), laggards: Vec::new(), relayed_count: 0 };
+    };
+    let mean = scored.iter().map(|p| p.score as f64).sum::<f64>() / scored.len() as f64;
+    let score = (MEAN_WEIGHT * mean + (1.0 - MEAN_WEIGHT) * min).round() as u8;
+
+    let laggards = if score < ROOM_HEALTH_GOOD {
+        scored
+            .iter()
+            .filter(|p| p.score < ROOM_HEALTH_GOOD)
+            .take(MAX_LAGGARDS)
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let relayed_count = scored.iter().filter(|p| p.relay).count();
+    RoomHealth { score, peers: scored, laggards, relayed_count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(ip: &str, relay: bool, latency_ms: Option<u64>, loss_rate: u8) -> PeerQuality {
+        PeerQuality { ip: ip.to_string(), player_name: None, relay, latency_ms, loss_rate }
+    }
+
+    #[test]
+    fn test_score_peer_penalties() {
+        // 低延迟直连不扣分
+        let good = score_peer(&peer("a", false, Some(30), 0));
+        assert_eq!((good.score, good.main_issue), (100, None));
+
+        // 延迟在 50–300ms 之间线性扣分，超过 300ms 扣满 40
+        assert_eq!(score_peer(&peer("a", false, Some(175), 0)).score, 80);
+        assert_eq!(score_peer(&peer("a", false, Some(900), 0)).score, 60);
+
+        // 丢包每 1% 扣 2 分、最多 40；中继扣 15
+        assert_eq!(score_peer(&peer("a", false, Some(30), 5)).score, 90);
+        assert_eq!(score_peer(&peer("a", false, Some(30), 50)).score, 60);
+        let relay = score_peer(&peer("a", true, Some(30), 0));
+        assert_eq!((relay.score, relay.main_issue), (85, Some(HealthIssue::Relay)));
+
+        // 测不到延迟按不可达扣满延迟分，与其它扣分叠加
+        let worst = score_peer(&peer("a", true, None, 100));
+        assert_eq!((worst.score, worst.main_issue), (5, Some(HealthIssue::Unreachable)));
+    }
+
+    #[test]
+    fn test_room_health_weights_worst_peer() {
+        assert_eq!(room_health(&[]).score, 100);
+
+        let healthy = room_health(&[peer("a", false, Some(20), 0), peer("b", false, Some(60), 0)]);
+        assert!(healthy.score >= ROOM_HEALTH_GOOD);
+        assert!(healthy.laggards.is_empty());
+
+        // 一个很差的 peer 明显拉低房间分（平均分 81，最低分 5 → 0.7*81 + 0.3*5 = 58.2）
+        let room = room_health(&[
+            peer("a", false, Some(20), 0),
+            peer("b", false, Some(30), 0),
+            peer("c", false, Some(40), 0),
+            peer("d", true, None, 100),
+            peer("e", false, Some(50), 0),
+        ]);
+        assert_eq!(room.score, 58);
+        assert_eq!(room.peers.first().unwrap().ip, "d");
+        assert_eq!(room.laggards.len(), 1);
+        assert_eq!(room.laggards[0].ip, "d");
+        assert_eq!(room.laggards[0].main_issue, Some(HealthIssue::Unreachable));
+        assert_eq!(room.relayed_count, 1);
+    }
+}
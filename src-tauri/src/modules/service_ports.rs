@@ -0,0 +1,340 @@
+// 大厅服务端口统一管理
+//
+// P2P 信令（UDP）、文件共享（HTTP）、聊天（HTTP）的端口集中在 `PortConfig`，可由用户配置覆盖，
+// 适配封禁固定端口的企业 / 学校网络。服务启动时从这里取端口，绑定失败自动递增寻找可用端口，
+// 实际端口记录后通过 `service-port-changed` 事件告知前端，并随 P2P 发现广播告知其他成员；
+// 访问其他成员的服务时按对方宣告的端口拼接地址，旧版本不宣告时使用配置端口。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use thiserror::Error;
+
+/// 默认 P2P 信令端口
+pub const DEFAULT_SIGNALING_PORT: u16 = 47777;
+/// 默认文件共享端口
+pub const DEFAULT_FILE_SHARE_PORT: u16 = 14539;
+/// 默认聊天端口
+pub const DEFAULT_CHAT_PORT: u16 = 14540;
+/// HTTP 服务绑定失败时最多尝试的端口数（含配置端口本身）
+pub const PORT_FALLBACK_ATTEMPTS: u16 = 20;
+
+/// 端口配置错误
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PortConfigError {
+    #[error("{0}端口不能为 0")]
+    Zero(&'static str),
+    #[error("{0}端口与{1}端口相同")]
+    Duplicate(&'static str, &'static str),
+}
+
+/// 大厅服务
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    /// P2P 信令（UDP）
+    Signaling,
+    /// 文件共享（HTTP）
+    FileShare,
+    /// 聊天（HTTP）
+    Chat,
+}
+
+impl ServiceKind {
+    /// 中文名称
+    pub fn label(self) -> &'static str {
+        match self {
+            ServiceKind::Signaling => "P2P 信令",
+            ServiceKind::FileShare => "文件共享",
+            ServiceKind::Chat => "聊天",
+        }
+    }
+}
+
+/// 大厅服务端口
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PortConfig {
+    /// P2P 信令端口
+    #[serde(default = "default_signaling_port")]
+    pub signaling: u16,
+    /// 文件共享端口
+    #[serde(default = "default_file_share_port")]
+    pub file_share: u16,
+    /// 聊天端口
+    #[serde(default = "default_chat_port")]
+    pub chat: u16,
+}
+
+fn default_signaling_port() -> u16 {
+    DEFAULT_SIGNALING_PORT
+}
+
+fn default_file_share_port() -> u16 {
+    DEFAULT_FILE_SHARE_PORT
+}
+
+fn default_chat_port() -> u16 {
+    DEFAULT_CHAT_PORT
+}
+
+impl Default for PortConfig {
+    fn default() -> Self {
+        Self {
+            signaling: DEFAULT_SIGNALING_PORT,
+            file_share: DEFAULT_FILE_SHARE_PORT,
+            chat: DEFAULT_CHAT_PORT,
+        }
+    }
+}
+
+impl PortConfig {
+    /// 某个服务的端口
+    pub fn get(&self, kind: ServiceKind) -> u16 {
+        match kind {
+            ServiceKind::Signaling => self.signaling,
+            ServiceKind::FileShare => self.file_share,
+            ServiceKind::Chat => self.chat,
+        }
+    }
+
+    /// 设置某个服务的端口
+    pub fn set(&mut self, kind: ServiceKind, port: u16) {
+        match kind {
+            ServiceKind::Signaling => self.signaling = port,
+            ServiceKind::FileShare => self.file_share = port,
+            ServiceKind::Chat => self.chat = port,
+        }
+    }
+
+    /// 校验端口：不能为 0；文件与聊天同为 TCP，不能相同（信令走 UDP，可与它们相同）
+    pub fn validate(&self) -> Result<(), PortConfigError> {
+        for kind in [ServiceKind::Signaling, ServiceKind::FileShare, ServiceKind::Chat] {
+            if self.get(kind) == 0 {
+                return Err(PortConfigError::Zero(kind.label()));
+            }
+        }
+        if self.file_share == self.chat {
+            return Err(PortConfigError::Duplicate(ServiceKind::FileShare.label(), ServiceKind::Chat.label()));
+        }
+        Ok(())
+    }
+}
+
+/// 从 `base` 开始依次尝试的端口（不回绕到 0）
+pub fn fallback_ports(base: u16) -> impl Iterator<Item = u16> {
+    (0..PORT_FALLBACK_ATTEMPTS).map_while(move |offset| base.checked_add(offset))
+}
+
+/// 在 `ip` 上绑定 TCP 端口，配置端口被占用时递增尝试
+///
+/// # 返回
+/// 监听器与实际绑定的端口，全部失败时返回最后一次的错误
+pub async fn bind_tcp_with_fallback(ip: &str, base: u16) -> std::io::Result<(tokio::net::TcpListener, u16)> {
+    let mut last_error = None;
+    for port in fallback_ports(base) {
+        match tokio::net::TcpListener::bind((ip, port)).await {
+            Ok(listener) => return Ok((listener, port)),
+            Err(e) => {
+                log::warn!("端口 {}:{} 绑定失败，尝试下一个端口: {}", ip, port, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::AddrInUse, "没有可用端口")))
+}
+
+/// 停止服务后等待端口释放的最长时间
+pub const PORT_RELEASE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 等待 TCP 端口可以重新绑定
+///
+/// 用与服务启动相同的方式试绑定：端口仍被占用时轮询，其它错误（如退出大厅后虚拟网卡已移除）
+/// 说明没有套接字占用该端口，直接视为已释放。
+///
+/// # 返回
+/// 超时前端口已释放返回 true
+pub async fn wait_tcp_port_released(addr: std::net::SocketAddr, timeout: std::time::Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(_) => return true,
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                if tokio::time::Instant::now() >= deadline {
+                    log::warn!("⚠️ 等待端口 {} 释放超时", addr);
+                    return false;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Err(_) => return true,
+        }
+    }
+}
+
+/// 停止 HTTP 服务：中止服务任务并等待其结束（监听套接字与所有连接随之关闭），再确认端口已释放
+///
+/// # 参数
+/// * `handle` - 服务任务句柄
+/// * `addr` - 服务实际监听的地址
+pub async fn shutdown_tcp_service(handle: tokio::task::JoinHandle<()>, addr: Option<std::net::SocketAddr>) {
+    handle.abort();
+    // 被中止的任务在下一次调度时才真正销毁，等它结束后监听套接字才会关闭
+    let _ = handle.await;
+    if let Some(addr) = addr {
+        wait_tcp_port_released(addr, PORT_RELEASE_TIMEOUT).await;
+    }
+}
+
+/// 端口状态：配置端口、本机实际端口、其他成员宣告的端口
+#[derive(Debug, Default)]
+struct PortRegistry {
+    configured: PortConfig,
+    local: PortConfig,
+    /// peer IP -> 对方宣告的文件 / 聊天端口
+    peers: HashMap<String, (Option<u16>, Option<u16>)>,
+}
+
+impl PortRegistry {
+    fn peer_port(&self, peer_ip: &str, kind: ServiceKind) -> u16 {
+        let advertised = self.peers.get(peer_ip).and_then(|(file_share, chat)| match kind {
+            ServiceKind::FileShare => *file_share,
+            ServiceKind::Chat => *chat,
+            ServiceKind::Signaling => None,
+        });
+        advertised.unwrap_or_else(|| self.configured.get(kind))
+    }
+}
+
+fn registry() -> &'static RwLock<PortRegistry> {
+    static REGISTRY: OnceLock<RwLock<PortRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(PortRegistry::default()))
+}
+
+fn app_handle_slot() -> &'static OnceLock<tauri::AppHandle> {
+    static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+    &APP_HANDLE
+}
+
+/// 设置用于推送端口变化事件的应用句柄
+pub fn set_app_handle(app: tauri::AppHandle) {
+    let _ = app_handle_slot().set(app);
+}
+
+/// 应用端口配置（下次启动服务时生效）
+pub fn configure(config: PortConfig) {
+    let mut registry = registry().write();
+    registry.configured = config;
+    registry.local = config;
+}
+
+/// 配置的端口
+pub fn configured_ports() -> PortConfig {
+    registry().read().configured
+}
+
+/// 本机服务实际使用的端口
+pub fn local_ports() -> PortConfig {
+    registry().read().local
+}
+
+/// 记录服务实际绑定的端口，与配置端口不同时通知前端
+pub fn record_local_port(kind: ServiceKind, port: u16) {
+    let configured = {
+        let mut registry = registry().write();
+        registry.local.set(kind, port);
+        registry.configured.get(kind)
+    };
+    if port == configured {
+        return;
+    }
+    log::warn!("⚠️ {}端口 {} 不可用，已改用 {}", kind.label(), configured, port);
+    if let Some(app) = app_handle_slot().get() {
+        let _ = app.emit("service-port-changed", serde_json::json!({
+            "service": kind,
+            "configuredPort": configured,
+            "actualPort": port,
+        }));
+    }
+}
+
+/// 记录其他成员在发现广播中宣告的端口
+pub fn record_peer_ports(peer_ip: &str, file_share: Option<u16>, chat: Option<u16>) {
+    if file_share.is_none() && chat.is_none() {
+        return;
+    }
+    registry().write().peers.insert(peer_ip.to_string(), (file_share, chat));
+}
+
+/// 访问某个成员的服务时使用的端口（对方未宣告时使用配置端口）
+pub fn peer_port(peer_ip: &str, kind: ServiceKind) -> u16 {
+    registry().read().peer_port(peer_ip, kind)
+}
+
+/// 某个成员的文件共享端口
+pub fn peer_file_port(peer_ip: &str) -> u16 {
+    peer_port(peer_ip, ServiceKind::FileShare)
+}
+
+/// 某个成员的聊天端口
+pub fn peer_chat_port(peer_ip: &str) -> u16 {
+    peer_port(peer_ip, ServiceKind::Chat)
+}
+
+/// 清空其他成员宣告的端口（离开大厅时调用）
+pub fn clear_peer_ports() {
+    registry().write().peers.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_config_validate() {
+        assert!(PortConfig::default().validate().is_ok());
+        let zero = PortConfig { chat: 0, ..PortConfig::default() };
+        assert_eq!(zero.validate(), Err(PortConfigError::Zero("聊天")));
+        let clash = PortConfig { chat: DEFAULT_FILE_SHARE_PORT, ..PortConfig::default() };
+        assert!(matches!(clash.validate(), Err(PortConfigError::Duplicate(..))));
+        // 信令走 UDP，可与 HTTP 端口相同
+        let shared = PortConfig { signaling: DEFAULT_CHAT_PORT, ..PortConfig::default() };
+        assert!(shared.validate().is_ok());
+
+        // 旧配置缺少的字段使用默认端口
+        let parsed: PortConfig = serde_json::from_str(r#"{"chat": 20000}"#).unwrap();
+        assert_eq!(parsed, PortConfig { chat: 20000, ..PortConfig::default() });
+    }
+
+    #[test]
+    fn test_fallback_ports_do_not_wrap() {
+        let ports: Vec<u16> = fallback_ports(14539).collect();
+        assert_eq!(ports.len(), PORT_FALLBACK_ATTEMPTS as usize);
+        assert_eq!(ports[0], 14539);
+        assert_eq!(fallback_ports(u16::MAX - 1).collect::<Vec<_>>(), vec![u16::MAX - 1, u16::MAX]);
+    }
+
+    #[tokio::test]
+    async fn test_bind_tcp_falls_back_to_next_port() {
+        let (taken, port) = bind_tcp_with_fallback("127.0.0.1", 0).await.unwrap();
+        assert_eq!(port, 0);
+        let occupied = taken.local_addr().unwrap().port();
+        let (_listener, actual) = bind_tcp_with_fallback("127.0.0.1", occupied).await.unwrap();
+        assert_ne!(actual, occupied);
+        assert!(actual > occupied);
+    }
+
+    #[test]
+    fn test_peer_ports_fall_back_to_configured() {
+        let mut registry = PortRegistry {
+            configured: PortConfig { file_share: 15000, chat: 15001, ..PortConfig::default() },
+            ..PortRegistry::default()
+        };
+        registry.peers.insert("10.126.126.9".to_string(), (Some(16000), None));
+        assert_eq!(registry.peer_port("10.126.126.9", ServiceKind::FileShare), 16000);
+        // 对方未宣告的端口与未宣告过的成员都使用配置端口
+        assert_eq!(registry.peer_port("10.126.126.9", ServiceKind::Chat), 15001);
+        assert_eq!(registry.peer_port("10.126.126.10", ServiceKind::FileShare), 15000);
+    }
+}
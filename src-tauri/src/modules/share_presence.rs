@@ -0,0 +1,152 @@
+// 共享浏览状态
+//
+// 浏览或下载他人的共享时，通过 P2P 信令广播一条轻量的 `BrowsingShare` 状态，
+// 共享者据此提示"某某正在浏览你的共享"。同一共享的同类行为在最小间隔内只广播一次，
+// 避免翻目录、批量下载时刷屏。出于隐私考虑可以关闭，关闭后本机不再广播自己的浏览行为。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// 同一共享同类行为的最小广播间隔
+pub const SHARE_PRESENCE_MIN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 对共享的访问行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareActivity {
+    /// 浏览文件列表
+    Browsing,
+    /// 下载文件
+    Downloading,
+}
+
+/// 本机浏览状态的广播控制
+#[derive(Debug)]
+pub struct SharePresence {
+    /// 是否广播本机的浏览行为
+    enabled: bool,
+    /// 最小广播间隔
+    min_interval: Duration,
+    /// 每个 (共享, 行为) 最近一次广播时间
+    last_announced: HashMap<(String, ShareActivity), Instant>,
+}
+
+impl SharePresence {
+    /// 创建新的广播控制
+    pub fn new(enabled: bool, min_interval: Duration) -> Self {
+        Self {
+            enabled,
+            min_interval,
+            last_announced: HashMap::new(),
+        }
+    }
+
+    /// 设置是否广播本机的浏览行为
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.last_announced.clear();
+        }
+    }
+
+    /// 是否广播本机的浏览行为
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 判断此刻是否应广播该行为，若应广播则同时记录广播时间
+    ///
+    /// # 返回
+    /// * `true` - 已开启且距上次同类广播已超过最小间隔
+    /// * `false` - 已关闭，或最小间隔内的重复行为
+    pub fn should_announce(&mut self, share_id: &str, activity: ShareActivity, now: Instant) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let key = (share_id.to_string(), activity);
+        if let Some(last) = self.last_announced.get(&key) {
+            if now.saturating_duration_since(*last) < self.min_interval {
+                return false;
+            }
+        }
+        self.last_announced.insert(key, now);
+        true
+    }
+}
+
+impl Default for SharePresence {
+    fn default() -> Self {
+        Self::new(true, SHARE_PRESENCE_MIN_INTERVAL)
+    }
+}
+
+/// 全局浏览状态广播控制
+pub fn presence_slot() -> &'static Mutex<SharePresence> {
+    static PRESENCE: OnceLock<Mutex<SharePresence>> = OnceLock::new();
+    PRESENCE.get_or_init(|| Mutex::new(SharePresence::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::p2p_signaling::P2PMessage;
+
+    #[test]
+    fn test_browsing_share_message_roundtrip() {
+        let message = P2PMessage::BrowsingShare {
+            player_id: "peer-a".to_string(),
+            share_id: "share-1".to_string(),
+            activity: ShareActivity::Downloading,
+        };
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "browsing-share",
+                "playerId": "peer-a",
+                "shareId": "share-1",
+                "activity": "downloading",
+            })
+        );
+        let decoded: P2PMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            decoded,
+            P2PMessage::BrowsingShare { ref share_id, activity: ShareActivity::Downloading, .. } if share_id == "share-1"
+        ));
+    }
+
+    #[test]
+    fn test_announce_is_throttled_per_share_and_activity() {
+        let mut presence = SharePresence::new(true, Duration::from_secs(30));
+        let t0 = Instant::now();
+
+        assert!(presence.should_announce("share-1", ShareActivity::Browsing, t0));
+        // 翻目录不重复广播
+        assert!(!presence.should_announce("share-1", ShareActivity::Browsing, t0 + Duration::from_secs(5)));
+        // 不同行为、不同共享各自计时
+        assert!(presence.should_announce("share-1", ShareActivity::Downloading, t0 + Duration::from_secs(5)));
+        assert!(presence.should_announce("share-2", ShareActivity::Browsing, t0 + Duration::from_secs(5)));
+        // 超过间隔后再次广播
+        assert!(presence.should_announce("share-1", ShareActivity::Browsing, t0 + Duration::from_secs(31)));
+    }
+
+    #[test]
+    fn test_disabled_presence_never_announces() {
+        let mut presence = SharePresence::new(true, Duration::from_secs(30));
+        let t0 = Instant::now();
+        assert!(presence.should_announce("share-1", ShareActivity::Browsing, t0));
+
+        presence.set_enabled(false);
+        assert!(!presence.is_enabled());
+        assert!(!presence.should_announce("share-2", ShareActivity::Browsing, t0));
+        assert!(!presence.should_announce("share-1", ShareActivity::Downloading, t0 + Duration::from_secs(60)));
+
+        // 重新开启后从头计时
+        presence.set_enabled(true);
+        assert!(presence.should_announce("share-1", ShareActivity::Browsing, t0 + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,505 @@
+// 共享文件夹流式打包
+//
+// 把整个共享目录按条目名排序后打包为不压缩（stored）的 ZIP，边读边写，不落地临时文件。
+// 条目使用数据描述符（先写文件内容、后写 CRC），因此不需要回头改写文件头，输出端不必可 Seek；
+// 又因为不压缩，ZIP 的布局只取决于文件名和大小，打包前就能算出总长度（Content-Length），
+// 同一组文件每次生成的字节完全一致，续传时重新生成并跳过已下载的部分即可支持 Range。
+// 超过 4GB 的文件、超过 4GB 的偏移或 65535 个以上的条目自动使用 ZIP64 扩展。
+
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const ZIP64_END_SIG: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x0706_4b50;
+const END_SIG: u32 = 0x0605_4b50;
+/// 通用标志：bit3 使用数据描述符，bit11 文件名为 UTF-8
+const GENERAL_FLAGS: u16 = 0x0808;
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+const U16_LIMIT: u64 = 0xFFFF;
+const U32_LIMIT: u64 = 0xFFFF_FFFF;
+/// 读取文件的缓冲大小
+const READ_BUF_SIZE: usize = 256 * 1024;
+
+/// 待打包的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipSource {
+    /// ZIP 内的条目名（以 `/` 分隔的相对路径）
+    pub name: String,
+    /// 本地文件路径
+    pub path: PathBuf,
+    /// 文件大小（打包时按此长度读取）
+    pub size: u64,
+    /// 修改时间
+    pub modified: SystemTime,
+}
+
+/// 收集目录下的所有常规文件，按条目名排序
+///
+/// 不跟随符号链接（既防止借链接越出共享目录，也避免链接成环）。
+pub fn collect_sources(root: &Path) -> io::Result<Vec<ZipSource>> {
+    let mut sources = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if file_type.is_dir() {
+                pending.push((entry.path(), format!("{}/", name)));
+            } else if file_type.is_file() {
+                let metadata = entry.metadata()?;
+                sources.push(ZipSource {
+                    name,
+                    path: entry.path(),
+                    size: metadata.len(),
+                    modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                });
+            }
+        }
+    }
+    sources.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sources)
+}
+
+/// 已确定位置的条目
+#[derive(Debug, Clone)]
+struct PlannedEntry {
+    source: ZipSource,
+    /// 本地文件头在 ZIP 中的偏移
+    offset: u64,
+    /// 文件大小是否需要 ZIP64
+    zip64_size: bool,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl PlannedEntry {
+    fn zip64_offset(&self) -> bool {
+        self.offset >= U32_LIMIT
+    }
+
+    fn version_needed(&self) -> u16 {
+        if self.zip64_size || self.zip64_offset() {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFAULT
+        }
+    }
+
+    fn local_header_len(&self) -> u64 {
+        30 + self.source.name.len() as u64 + if self.zip64_size { 20 } else { 0 }
+    }
+
+    fn descriptor_len(&self) -> u64 {
+        if self.zip64_size {
+            24
+        } else {
+            16
+        }
+    }
+
+    fn central_extra_len(&self) -> u64 {
+        let fields = if self.zip64_size { 16 } else { 0 } + if self.zip64_offset() { 8 } else { 0 };
+        if fields == 0 {
+            0
+        } else {
+            4 + fields
+        }
+    }
+
+    fn central_header_len(&self) -> u64 {
+        46 + self.source.name.len() as u64 + self.central_extra_len()
+    }
+
+    fn local_header(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.local_header_len() as usize);
+        put_u32(&mut buf, LOCAL_HEADER_SIG);
+        put_u16(&mut buf, self.version_needed());
+        put_u16(&mut buf, GENERAL_FLAGS);
+        put_u16(&mut buf, 0); // stored
+        put_u16(&mut buf, self.dos_time);
+        put_u16(&mut buf, self.dos_date);
+        // CRC 与大小写在数据描述符里
+        put_u32(&mut buf, 0);
+        let size_field = if self.zip64_size { U32_LIMIT as u32 } else { 0 };
+        put_u32(&mut buf, size_field);
+        put_u32(&mut buf, size_field);
+        put_u16(&mut buf, self.source.name.len() as u16);
+        put_u16(&mut buf, if self.zip64_size { 20 } else { 0 });
+        buf.extend_from_slice(self.source.name.as_bytes());
+        if self.zip64_size {
+            put_u16(&mut buf, ZIP64_EXTRA_ID);
+            put_u16(&mut buf, 16);
+            put_u64(&mut buf, 0);
+            put_u64(&mut buf, 0);
+        }
+        buf
+    }
+
+    fn data_descriptor(&self, crc: u32) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.descriptor_len() as usize);
+        put_u32(&mut buf, DATA_DESCRIPTOR_SIG);
+        put_u32(&mut buf, crc);
+        if self.zip64_size {
+            put_u64(&mut buf, self.source.size);
+            put_u64(&mut buf, self.source.size);
+        } else {
+            put_u32(&mut buf, self.source.size as u32);
+            put_u32(&mut buf, self.source.size as u32);
+        }
+        buf
+    }
+
+    fn central_header(&self, crc: u32) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.central_header_len() as usize);
+        put_u32(&mut buf, CENTRAL_HEADER_SIG);
+        put_u16(&mut buf, VERSION_ZIP64); // version made by
+        put_u16(&mut buf, self.version_needed());
+        put_u16(&mut buf, GENERAL_FLAGS);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, self.dos_time);
+        put_u16(&mut buf, self.dos_date);
+        put_u32(&mut buf, crc);
+        let size_field = self.source.size.min(U32_LIMIT) as u32;
+        put_u32(&mut buf, size_field);
+        put_u32(&mut buf, size_field);
+        put_u16(&mut buf, self.source.name.len() as u16);
+        put_u16(&mut buf, self.central_extra_len() as u16);
+        put_u16(&mut buf, 0); // comment
+        put_u16(&mut buf, 0); // disk number
+        put_u16(&mut buf, 0); // internal attributes
+        put_u32(&mut buf, 0); // external attributes
+        put_u32(&mut buf, self.offset.min(U32_LIMIT) as u32);
+        buf.extend_from_slice(self.source.name.as_bytes());
+        if self.central_extra_len() > 0 {
+            put_u16(&mut buf, ZIP64_EXTRA_ID);
+            put_u16(&mut buf, (self.central_extra_len() - 4) as u16);
+            if self.zip64_size {
+                put_u64(&mut buf, self.source.size);
+                put_u64(&mut buf, self.source.size);
+            }
+            if self.zip64_offset() {
+                put_u64(&mut buf, self.offset);
+            }
+        }
+        buf
+    }
+}
+
+/// 打包计划：确定每个条目的位置与 ZIP 总长度
+#[derive(Debug, Clone)]
+pub struct ZipPlan {
+    entries: Vec<PlannedEntry>,
+    central_offset: u64,
+    central_size: u64,
+    total_len: u64,
+}
+
+impl ZipPlan {
+    /// 根据文件列表生成打包计划（不读取文件内容）
+    pub fn new(sources: Vec<ZipSource>) -> Self {
+        let mut offset = 0u64;
+        let mut entries = Vec::with_capacity(sources.len());
+        for source in sources {
+            let (dos_time, dos_date) = dos_date_time(source.modified);
+            let entry = PlannedEntry {
+                zip64_size: source.size >= U32_LIMIT,
+                source,
+                offset,
+                dos_time,
+                dos_date,
+            };
+            offset += entry.local_header_len() + entry.source.size + entry.descriptor_len();
+            entries.push(entry);
+        }
+        let central_offset = offset;
+        let central_size = entries.iter().map(PlannedEntry::central_header_len).sum::<u64>();
+        let mut plan = Self {
+            entries,
+            central_offset,
+            central_size,
+            total_len: 0,
+        };
+        plan.total_len = central_offset + central_size + plan.end_records_len();
+        plan
+    }
+
+    /// ZIP 总字节数
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    /// 条目数
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 文件列表的指纹（条目名、大小、修改时间任一变化都会改变），用作 ETag
+    pub fn etag(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        for entry in &self.entries {
+            let mtime = entry
+                .source
+                .modified
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0);
+            hasher.update(entry.source.name.as_bytes());
+            hasher.update([0]);
+            hasher.update(entry.source.size.to_le_bytes());
+            hasher.update(mtime.to_le_bytes());
+        }
+        let digest = hasher.finalize();
+        let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("\"zip-{}\"", hex)
+    }
+
+    fn needs_zip64_end(&self) -> bool {
+        self.entries.len() as u64 >= U16_LIMIT || self.central_offset >= U32_LIMIT || self.central_size >= U32_LIMIT
+    }
+
+    fn end_records_len(&self) -> u64 {
+        22 + if self.needs_zip64_end() { 56 + 20 } else { 0 }
+    }
+
+    fn end_records(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.end_records_len() as usize);
+        let count = self.entries.len() as u64;
+        if self.needs_zip64_end() {
+            let zip64_end_offset = self.central_offset + self.central_size;
+            put_u32(&mut buf, ZIP64_END_SIG);
+            put_u64(&mut buf, 44);
+            put_u16(&mut buf, VERSION_ZIP64);
+            put_u16(&mut buf, VERSION_ZIP64);
+            put_u32(&mut buf, 0);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, count);
+            put_u64(&mut buf, self.central_size);
+            put_u64(&mut buf, self.central_offset);
+
+            put_u32(&mut buf, ZIP64_LOCATOR_SIG);
+            put_u32(&mut buf, 0);
+            put_u64(&mut buf, zip64_end_offset);
+            put_u32(&mut buf, 1);
+        }
+        put_u32(&mut buf, END_SIG);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, 0);
+        put_u16(&mut buf, count.min(U16_LIMIT) as u16);
+        put_u16(&mut buf, count.min(U16_LIMIT) as u16);
+        put_u32(&mut buf, self.central_size.min(U32_LIMIT) as u32);
+        put_u32(&mut buf, self.central_offset.min(U32_LIMIT) as u32);
+        put_u16(&mut buf, 0);
+        buf
+    }
+
+    /// 生成 ZIP 中 `[start, start + length)` 范围的字节写入 `out`
+    ///
+    /// 范围之前的文件仍需读取以计算 CRC，但不会写出；写满范围后立即停止。
+    /// 文件在打包过程中变短会返回 `UnexpectedEof`，变长则只读取计划中的长度。
+    pub fn write_range<W: Write>(&self, start: u64, length: u64, out: &mut W) -> io::Result<()> {
+        let mut writer = RangeWriter {
+            out,
+            pos: 0,
+            start,
+            end: start.saturating_add(length).min(self.total_len),
+        };
+        let mut crcs = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            if writer.finished() {
+                return writer.out.flush();
+            }
+            writer.put(&entry.local_header())?;
+            let crc = copy_entry(entry, &mut writer)?;
+            writer.put(&entry.data_descriptor(crc))?;
+            crcs.push(crc);
+        }
+        for (entry, crc) in self.entries.iter().zip(crcs) {
+            if writer.finished() {
+                return writer.out.flush();
+            }
+            writer.put(&entry.central_header(crc))?;
+        }
+        writer.put(&self.end_records())?;
+        writer.out.flush()
+    }
+}
+
+/// 只写出落在 `[start, end)` 内的字节
+struct RangeWriter<'a, W: Write> {
+    out: &'a mut W,
+    pos: u64,
+    start: u64,
+    end: u64,
+}
+
+impl<W: Write> RangeWriter<'_, W> {
+    fn finished(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        let data_end = self.pos + data.len() as u64;
+        let lo = self.start.max(self.pos);
+        let hi = self.end.min(data_end);
+        if lo < hi {
+            self.out
+                .write_all(&data[(lo - self.pos) as usize..(hi - self.pos) as usize])?;
+        }
+        self.pos = data_end;
+        Ok(())
+    }
+}
+
+/// 读取条目文件内容写入 ZIP，返回 CRC32
+fn copy_entry<W: Write>(entry: &PlannedEntry, writer: &mut RangeWriter<'_, W>) -> io::Result<u32> {
+    let file = std::fs::File::open(&entry.source.path)?;
+    let mut reader = file.take(entry.source.size);
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; READ_BUF_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        writer.put(&buf[..n])?;
+        copied += n as u64;
+    }
+    if copied != entry.source.size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("文件在打包过程中被修改: {}", entry.source.name),
+        ));
+    }
+    Ok(hasher.finalize())
+}
+
+/// 转换为 ZIP 使用的 DOS 时间与日期（本地时区，范围 1980-2107）
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let dt: chrono::DateTime<chrono::Local> = time.into();
+    if dt.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let year = (dt.year().min(2107) - 1980) as u32;
+    let time = (dt.hour() << 11) | (dt.minute() << 5) | (dt.second() / 2);
+    let date = (year << 9) | (dt.month() << 5) | dt.day();
+    (time as u16, date as u16)
+}
+
+fn put_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("mods/lib")).unwrap();
+        std::fs::write(dir.path().join("mods/a.jar"), vec![7u8; 300_000]).unwrap();
+        std::fs::write(dir.path().join("mods/lib/模组.jar"), b"hello").unwrap();
+        std::fs::write(dir.path().join("empty.txt"), b"").unwrap();
+        dir
+    }
+
+    fn build_all(plan: &ZipPlan) -> Vec<u8> {
+        let mut out = Vec::new();
+        plan.write_range(0, plan.total_len(), &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_stream_zip_roundtrip() {
+        let dir = sample_dir();
+        let sources = collect_sources(dir.path()).unwrap();
+        let names: Vec<_> = sources.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["empty.txt", "mods/a.jar", "mods/lib/模组.jar"]);
+
+        let plan = ZipPlan::new(sources);
+        let data = build_all(&plan);
+        assert_eq!(data.len() as u64, plan.total_len());
+
+        let mut archive = zip::ZipArchive::new(io::Cursor::new(data)).unwrap();
+        assert_eq!(archive.len(), 3);
+        let mut content = Vec::new();
+        archive.by_name("mods/lib/模组.jar").unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"hello");
+        content.clear();
+        archive.by_name("mods/a.jar").unwrap().read_to_end(&mut content).unwrap();
+        assert_eq!(content, vec![7u8; 300_000]);
+    }
+
+    #[test]
+    fn test_stream_zip_range_matches_full_output() {
+        let dir = sample_dir();
+        let plan = ZipPlan::new(collect_sources(dir.path()).unwrap());
+        let full = build_all(&plan);
+        let total = plan.total_len();
+        for (start, length) in [(0, 10), (25, 200_000), (100_000, total), (total - 30, 30), (total - 1, 100)] {
+            let mut part = Vec::new();
+            plan.write_range(start, length, &mut part).unwrap();
+            let end = (start + length).min(total) as usize;
+            assert_eq!(part, full[start as usize..end], "range {}+{}", start, length);
+        }
+        // 同一组文件两次打包结果一致，ETag 相同
+        assert_eq!(build_all(&plan), full);
+        assert_eq!(plan.etag(), ZipPlan::new(collect_sources(dir.path()).unwrap()).etag());
+    }
+
+    #[test]
+    fn test_stream_zip_detects_modified_file() {
+        let dir = sample_dir();
+        let plan = ZipPlan::new(collect_sources(dir.path()).unwrap());
+        std::fs::write(dir.path().join("mods/a.jar"), b"short").unwrap();
+        let err = plan.write_range(0, plan.total_len(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_ne!(plan.etag(), ZipPlan::new(collect_sources(dir.path()).unwrap()).etag());
+    }
+
+    #[test]
+    fn test_zip64_layout_for_large_file() {
+        let big = 5 * 1024 * 1024 * 1024u64;
+        let plan = ZipPlan::new(vec![ZipSource {
+            name: "world.tar".to_string(),
+            path: PathBuf::from("world.tar"),
+            size: big,
+            modified: UNIX_EPOCH,
+        }]);
+        // 本地头(30+9+20) + 数据 + ZIP64 描述符(24) + 中央目录(46+9+20) + ZIP64 结尾(56+20) + 结尾(22)
+        assert_eq!(plan.total_len(), 59 + big + 24 + 75 + 76 + 22);
+        assert!(plan.needs_zip64_end());
+        assert_eq!(plan.entry_count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_sources_skips_symlinks() {
+        let dir = sample_dir();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), b"secret").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), dir.path().join("link.txt")).unwrap();
+        let sources = collect_sources(dir.path()).unwrap();
+        assert!(sources.iter().all(|s| !s.name.starts_with("escape") && s.name != "link.txt"));
+    }
+}
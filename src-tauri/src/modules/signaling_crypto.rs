@@ -0,0 +1,229 @@
+// P2P 信令加密
+//
+// P2P 信令以 UDP 广播发送到整个局域网，明文 JSON 会把玩家名、虚拟 IP、SDP 暴露给同网段的任何人。
+// 这里用 HKDF-SHA256 从大厅的 network_key（即大厅密码）派生 AES-256-GCM 密钥，对整个 UDP 负载加密。
+// 同一大厅的成员无需交换任何数据就能得到同一把密钥；解密失败的包直接丢弃，
+// 这也顺带过滤掉了同网段其它大厅的广播。
+//
+// 加密包格式：[版本字节][12 字节随机 nonce][密文 + 16 字节认证标签]
+// 明文 JSON 总是以 `{` 开头，与版本字节不会冲突，接收端据此区分新旧格式。
+//
+// 语音信令经 WebSocket 信令服务器转发，服务器需要按消息结构转发，因此不能整包加密，
+// 而是只加密其中的 SDP / ICE candidate 字段：`mctier-sealed1:` 前缀 + base64(加密包)。
+// 字段加密把消息类型、发送方、接收方作为附加认证数据（AAD），
+// 截获的密文无法被改投给其他玩家或冒充另一种消息。
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// 当前加密包格式版本
+pub const SIGNALING_PACKET_VERSION: u8 = 1;
+/// HKDF 盐值
+const KEY_DERIVATION_SALT: &[u8] = b"mctier-p2p-signaling";
+/// HKDF info（随包格式版本变化）
+const KEY_DERIVATION_INFO: &[u8] = b"mctier-p2p-signaling-aes-256-gcm-v1";
+/// AES-GCM nonce 长度
+const NONCE_LEN: usize = 12;
+/// 加密字段的前缀（含格式版本）
+pub const SEALED_FIELD_PREFIX: &str = "mctier-sealed1:";
+
+/// 信令加密错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum SignalingCryptoError {
+    #[error("不支持的信令包版本: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("信令包长度不足")]
+    Truncated,
+
+    #[error("信令包解密失败（密钥不匹配或数据被篡改）")]
+    DecryptFailed,
+
+    #[error("信令字段未加密")]
+    NotSealed,
+
+    #[error("加密信令字段格式错误")]
+    MalformedField,
+}
+
+/// 判断数据包是否为明文 JSON 信令
+pub fn is_plaintext_packet(packet: &[u8]) -> bool {
+    packet.first() == Some(&b'{')
+}
+
+/// 判断信令字段是否已加密
+pub fn is_sealed_field(value: &str) -> bool {
+    value.starts_with(SEALED_FIELD_PREFIX)
+}
+
+/// 语音信令字段的附加认证数据
+///
+/// # 参数
+/// * `kind` - 消息类型（offer / answer / ice-candidate）
+/// * `from` - 发送方玩家 ID
+/// * `to` - 接收方玩家 ID
+pub fn signaling_field_aad(kind: &str, from: &str, to: &str) -> Vec<u8> {
+    format!("{}\n{}\n{}", kind, from, to).into_bytes()
+}
+
+/// 由大厅 network_key 派生的信令加密器
+#[derive(Clone)]
+pub struct SignalingCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SignalingCipher {
+    /// 由大厅 network_key 派生密钥
+    ///
+    /// 相同的 network_key 总是得到相同的密钥。
+    pub fn from_network_key(network_key: &str) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(Some(KEY_DERIVATION_SALT), network_key.as_bytes());
+        let mut key = [0u8; 32];
+        hkdf.expand(KEY_DERIVATION_INFO, &mut key)
+            .expect("32 字节在 HKDF-SHA256 的输出长度范围内");
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        }
+    }
+
+    /// 加密信令负载，返回带版本字节和 nonce 的完整数据包
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        self.seal_with_aad(plaintext, &[])
+    }
+
+    /// 解密数据包，返回信令负载
+    pub fn open(&self, packet: &[u8]) -> Result<Vec<u8>, SignalingCryptoError> {
+        self.open_with_aad(packet, &[])
+    }
+
+    /// 带附加认证数据加密
+    fn seal_with_aad(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .expect("AES-GCM 加密内存数据不会失败");
+
+        let mut packet = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        packet.push(SIGNALING_PACKET_VERSION);
+        packet.extend_from_slice(&nonce);
+        packet.extend_from_slice(&ciphertext);
+        packet
+    }
+
+    /// 带附加认证数据解密
+    fn open_with_aad(&self, packet: &[u8], aad: &[u8]) -> Result<Vec<u8>, SignalingCryptoError> {
+        let (&version, rest) = packet.split_first().ok_or(SignalingCryptoError::Truncated)?;
+        if version != SIGNALING_PACKET_VERSION {
+            return Err(SignalingCryptoError::UnsupportedVersion(version));
+        }
+        if rest.len() < NONCE_LEN {
+            return Err(SignalingCryptoError::Truncated);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| SignalingCryptoError::DecryptFailed)
+    }
+
+    /// 加密单个信令字段（SDP / ICE candidate）
+    ///
+    /// # 参数
+    /// * `value` - 字段明文
+    /// * `aad` - 附加认证数据，见 `signaling_field_aad`
+    pub fn seal_field(&self, value: &str, aad: &[u8]) -> String {
+        format!("{}{}", SEALED_FIELD_PREFIX, STANDARD.encode(self.seal_with_aad(value.as_bytes(), aad)))
+    }
+
+    /// 解密单个信令字段
+    pub fn open_field(&self, value: &str, aad: &[u8]) -> Result<String, SignalingCryptoError> {
+        let encoded = value.strip_prefix(SEALED_FIELD_PREFIX).ok_or(SignalingCryptoError::NotSealed)?;
+        let packet = STANDARD.decode(encoded.trim()).map_err(|_| SignalingCryptoError::MalformedField)?;
+        let plain = self.open_with_aad(&packet, aad)?;
+        String::from_utf8(plain).map_err(|_| SignalingCryptoError::MalformedField)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_roundtrip() {
+        let cipher = SignalingCipher::from_network_key("secret");
+        let packet = cipher.seal(br#"{"type":"heartbeat"}"#);
+
+        assert_eq!(packet[0], SIGNALING_PACKET_VERSION);
+        assert!(!is_plaintext_packet(&packet));
+        // 每次加密使用新的 nonce
+        assert_ne!(packet, cipher.seal(br#"{"type":"heartbeat"}"#));
+        // 同一密码在另一台机器上派生出的密钥可以解密
+        let other = SignalingCipher::from_network_key("secret");
+        assert_eq!(other.open(&packet).unwrap(), br#"{"type":"heartbeat"}"#);
+    }
+
+    #[test]
+    fn test_open_rejects_foreign_or_malformed_packets() {
+        let cipher = SignalingCipher::from_network_key("secret");
+        let mut packet = cipher.seal(b"payload");
+
+        // 其它大厅的密钥
+        assert_eq!(
+            SignalingCipher::from_network_key("other").open(&packet),
+            Err(SignalingCryptoError::DecryptFailed)
+        );
+        // 被篡改
+        let last = packet.len() - 1;
+        packet[last] ^= 0x01;
+        assert_eq!(cipher.open(&packet), Err(SignalingCryptoError::DecryptFailed));
+        // 未来版本与截断的包
+        assert_eq!(cipher.open(&[2, 0, 0]), Err(SignalingCryptoError::UnsupportedVersion(2)));
+        assert_eq!(cipher.open(&[SIGNALING_PACKET_VERSION, 0, 0]), Err(SignalingCryptoError::Truncated));
+        assert_eq!(cipher.open(&[]), Err(SignalingCryptoError::Truncated));
+    }
+
+    #[test]
+    fn test_sealed_field_roundtrip() {
+        let aad = signaling_field_aad("offer", "alice", "bob");
+        let sealed = SignalingCipher::from_network_key("secret").seal_field("v=0\r\nc=IN IP4 10.126.126.2", &aad);
+
+        assert!(is_sealed_field(&sealed));
+        assert!(!sealed.contains("10.126.126.2"));
+        assert_eq!(
+            SignalingCipher::from_network_key("secret").open_field(&sealed, &aad).unwrap(),
+            "v=0\r\nc=IN IP4 10.126.126.2"
+        );
+    }
+
+    #[test]
+    fn test_open_field_rejects_wrong_key_or_context() {
+        let cipher = SignalingCipher::from_network_key("secret");
+        let aad = signaling_field_aad("offer", "alice", "bob");
+        let sealed = cipher.seal_field("sdp", &aad);
+
+        // 错误的大厅密钥
+        assert_eq!(
+            SignalingCipher::from_network_key("other").open_field(&sealed, &aad),
+            Err(SignalingCryptoError::DecryptFailed)
+        );
+        // 改投给其他玩家、冒充其他发送方或其它消息类型
+        for aad in [
+            signaling_field_aad("offer", "alice", "carol"),
+            signaling_field_aad("offer", "mallory", "bob"),
+            signaling_field_aad("answer", "alice", "bob"),
+        ] {
+            assert_eq!(cipher.open_field(&sealed, &aad), Err(SignalingCryptoError::DecryptFailed));
+        }
+        // 整包加密的 UDP 信令不能当作字段解密
+        let packet = format!("{}{}", SEALED_FIELD_PREFIX, STANDARD.encode(cipher.seal(b"sdp")));
+        assert_eq!(cipher.open_field(&packet, &aad), Err(SignalingCryptoError::DecryptFailed));
+        // 明文与格式错误的字段
+        assert_eq!(cipher.open_field("v=0", &aad), Err(SignalingCryptoError::NotSealed));
+        assert_eq!(
+            cipher.open_field(&format!("{}!!!", SEALED_FIELD_PREFIX), &aad),
+            Err(SignalingCryptoError::MalformedField)
+        );
+    }
+}
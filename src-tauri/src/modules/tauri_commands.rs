@@ -5,9 +5,18 @@ use tauri::State;
 use tauri::Emitter;
 use tauri::Manager;
 use crate::modules::app_core::{AppCore, AppState as CoreAppState};
-use crate::modules::lobby_manager::{Lobby, Player};
-use crate::modules::voice_service::AudioDevice;
-use crate::modules::config_manager::UserConfig;
+use crate::modules::lobby_manager::{Lobby, LobbyCreateResult, Player, ServiceStatus};
+use crate::modules::voice_service::{AudioDevice, MicMode, SpeakingActivity};
+use crate::modules::voice_feedback::VoiceCue;
+use crate::modules::network_service::NetworkService;
+use crate::modules::config_manager::{PlayerSortOrder, RecentLobby, RoomCode, UserConfig};
+use crate::modules::config_schema::ConfigFieldSchema;
+use crate::modules::invite_link::{build_invite_link, LobbyJoinParams};
+use crate::modules::window_opacity::{apply_window_opacity, clamp_window_opacity, WindowOpacityMode};
+use crate::modules::service_ports::PortConfig;
+use crate::modules::network_diagnostics::{self, DiagnosticResult, PeerAlignment};
+use crate::modules::traffic_stats::TrafficStats;
+use crate::modules::error::{CommandError, ErrorCode};
 use std::sync::Arc;
 use std::sync::OnceLock;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -20,8 +29,72 @@ fn download_cancels() -> &'static dashmap::DashMap<String, Arc<AtomicBool>> {
 }
 
 /// 应用状态包装器（用于 Tauri State）
+///
+/// 各子服务有独立的锁，且在 AppCore 生命周期内不会被替换，初始化时复制一份句柄：
+/// 只访问单个子服务（或取得句柄后立即释放 core）的命令直接通过这些句柄加锁，
+/// 不必排队等待 AppCore 大锁，避免一个慢命令阻塞切麦克风、发消息等其它命令。
+/// 需要同时持有多个子服务锁或改变应用状态的命令（创建 / 加入 / 退出大厅等）仍先持有 core 锁，
+/// 保证这些命令之间的加锁顺序一致、不会互相死锁。
 pub struct AppState {
     pub core: Arc<Mutex<AppCore>>,
+    lobby_manager: Arc<Mutex<crate::modules::lobby_manager::LobbyManager>>,
+    network_service: Arc<Mutex<NetworkService>>,
+    voice_service: Arc<Mutex<crate::modules::voice_service::VoiceService>>,
+    p2p_signaling: Arc<Mutex<crate::modules::p2p_signaling::P2PSignalingService>>,
+    file_transfer: Arc<Mutex<crate::modules::file_transfer::FileTransferService>>,
+    chat_service: Arc<Mutex<crate::modules::chat_service::ChatService>>,
+    config_manager: Arc<Mutex<crate::modules::config_manager::ConfigManager>>,
+}
+
+impl AppState {
+    /// 包装应用核心，并复制各子服务句柄
+    pub fn new(core: AppCore) -> Self {
+        Self {
+            lobby_manager: core.get_lobby_manager(),
+            network_service: core.get_network_service(),
+            voice_service: core.get_voice_service(),
+            p2p_signaling: core.get_p2p_signaling(),
+            file_transfer: core.get_file_transfer(),
+            chat_service: core.get_chat_service(),
+            config_manager: core.get_config_manager(),
+            core: Arc::new(Mutex::new(core)),
+        }
+    }
+
+    /// 获取大厅管理器的引用（无需 core 锁）
+    pub fn get_lobby_manager(&self) -> Arc<Mutex<crate::modules::lobby_manager::LobbyManager>> {
+        Arc::clone(&self.lobby_manager)
+    }
+
+    /// 获取网络服务的引用（无需 core 锁）
+    pub fn get_network_service(&self) -> Arc<Mutex<NetworkService>> {
+        Arc::clone(&self.network_service)
+    }
+
+    /// 获取语音服务的引用（无需 core 锁）
+    pub fn get_voice_service(&self) -> Arc<Mutex<crate::modules::voice_service::VoiceService>> {
+        Arc::clone(&self.voice_service)
+    }
+
+    /// 获取P2P信令服务的引用（无需 core 锁）
+    pub fn get_p2p_signaling(&self) -> Arc<Mutex<crate::modules::p2p_signaling::P2PSignalingService>> {
+        Arc::clone(&self.p2p_signaling)
+    }
+
+    /// 获取文件传输服务的引用（无需 core 锁）
+    pub fn get_file_transfer(&self) -> Arc<Mutex<crate::modules::file_transfer::FileTransferService>> {
+        Arc::clone(&self.file_transfer)
+    }
+
+    /// 获取P2P聊天服务的引用（无需 core 锁）
+    pub fn get_chat_service(&self) -> Arc<Mutex<crate::modules::chat_service::ChatService>> {
+        Arc::clone(&self.chat_service)
+    }
+
+    /// 获取配置管理器的引用（无需 core 锁）
+    pub fn get_config_manager(&self) -> Arc<Mutex<crate::modules::config_manager::ConfigManager>> {
+        Arc::clone(&self.config_manager)
+    }
 }
 
 // ==================== 大厅操作命令 ====================
@@ -35,10 +108,11 @@ pub struct AppState {
 /// * `player_id` - 玩家ID（由前端生成）
 /// * `server_node` - 服务器节点地址
 /// * `signaling_server` - 信令服务器地址
+/// * `max_players` - 大厅人数上限（含创建者），None 表示不限制
 /// 
 /// # 返回
-/// * `Ok(Lobby)` - 成功创建的大厅信息
-/// * `Err(String)` - 错误信息
+/// * `Ok(LobbyCreateResult)` - 成功创建的大厅信息及各子服务（网络/文件/聊天/信令）的启动状态
+/// * `Err(CommandError)` - 错误信息（虚拟网络启动失败时整个创建回滚）
 #[tauri::command]
 pub async fn create_lobby(
     name: String,
@@ -49,10 +123,13 @@ pub async fn create_lobby(
     signaling_server: String,
     use_domain: Option<bool>,
     virtual_domain: Option<String>,
+    max_players: Option<u32>,
+    network_mode: Option<crate::modules::network_service::NetworkMode>,
     app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Lobby, String> {
+) -> Result<LobbyCreateResult, CommandError> {
     log::info!("收到创建大厅命令: name={}, player={}, player_id={}, signaling_server={}, use_domain={:?}, virtual_domain={:?}", name, player_name, player_id, signaling_server, use_domain, virtual_domain);
+    crate::modules::network_service::reset_connect_cancel();
     
     let core = state.core.lock().await;
     
@@ -60,7 +137,7 @@ pub async fn create_lobby(
     core.set_state(CoreAppState::Connecting).await;
     
     // 【关键修复】在这里读取配置，避免在 start_easytier 中再次获取 core 的锁
-    let (global_config, lobby_config) = {
+    let (global_config, lobby_config, enable_http_tls, signaling_plaintext_compat) = {
         let config_manager = core.get_config_manager();
         let cfg_mgr = config_manager.lock().await;
         let user_config = cfg_mgr.get_config();
@@ -68,7 +145,12 @@ pub async fn create_lobby(
         let global_cfg = user_config.global_easytier_advanced_config.clone();
         let lobby_cfg = user_config.lobby_easytier_advanced_config.clone();
         
-        (global_cfg, lobby_cfg)
+        (
+            global_cfg,
+            lobby_cfg,
+            user_config.enable_http_tls.unwrap_or(false),
+            user_config.p2p_signaling_plaintext_compat.unwrap_or(false),
+        )
     };
     
     // 获取各个服务的引用
@@ -76,13 +158,23 @@ pub async fn create_lobby(
     let network_service = core.get_network_service();
     let file_transfer = core.get_file_transfer();
     let chat_service = core.get_chat_service();
+    let p2p_signaling = core.get_p2p_signaling();
     
     // 释放 core 的锁，避免死锁
     drop(core);
     
+    // HTTP 服务的 TLS 证书由大厅名称和密码派生
+    let tls_seed = (name.clone(), password.clone());
+    // P2P 信令密钥由大厅密码（即 EasyTier network_key）派生
+    let signaling_key = password.clone();
+    let recent_server_node = server_node.clone();
+    let auto_player = (player_id.clone(), player_name.clone());
+    
     // 创建大厅
     let mut lobby_mgr = lobby_manager.lock().await;
-    let network_svc = network_service.lock().await;
+    let mut network_svc = network_service.lock().await;
+    // 房主本机运行 Minecraft 服务器，纯中继模式下无需端口转发
+    network_svc.set_network_mode(network_mode.unwrap_or_default(), None);
     
     match lobby_mgr.create_lobby_with_config(
         name,
@@ -96,6 +188,7 @@ pub async fn create_lobby(
         &app_handle,
         global_config,
         lobby_config,
+        max_players,
     ).await {
         Ok(lobby) => {
             log::info!("大厅创建成功: {}", lobby.name);
@@ -115,23 +208,68 @@ pub async fn create_lobby(
             // 所有客户端都连接到官方 WebSockets 信令服务器 (wss://mctier.pmhs.top/signaling)
             log::info!("客户端将连接到官方 WebSockets 信令服务器: wss://mctier.pmhs.top/signaling");
             
-            // 不再在创建大厅时自动启动HTTP文件服务器
-            // HTTP服务器将在第一次添加共享时按需启动
+            // 本次大厅流量从零开始统计
+            crate::modules::traffic_stats::reset_session();
+            
+            // 虚拟网络已就绪，其余子服务失败只降级，不回滚大厅
+            let mut result = LobbyCreateResult::new(lobby.clone());
+            
+            // 启动P2P信令服务（创建者负责人数上限检查与踢人）
+            log::info!("正在启动P2P信令服务（创建大厅）...");
+            let p2p_svc = p2p_signaling.lock().await;
+            p2p_svc.set_max_players(lobby.max_players).await;
+            p2p_svc.set_creator_virtual_ip(Some(lobby.creator_virtual_ip.clone())).await;
+            p2p_svc.set_network_key(Some(&signaling_key), signaling_plaintext_compat).await;
+            match p2p_svc.start(player_id.clone(), player_name, virtual_ip.clone()).await {
+                Ok(_) => {
+                    log::info!("✅ P2P信令服务启动成功（创建大厅）");
+                    // 文件/聊天服务仅对 P2P 信令发现的本大厅成员开放
+                    crate::modules::peer_access::configure_for_lobby(&virtual_ip, p2p_svc.peers_handle());
+                    result.set_service(LobbyCreateResult::SIGNALING, ServiceStatus::Running);
+                }
+                Err(e) => {
+                    // 创建者的信令服务失败不影响大厅本身，只是人数上限与踢人不可用
+                    log::warn!("⚠️ 启动P2P信令服务失败（创建大厅）: {}", e);
+                    result.set_service(LobbyCreateResult::SIGNALING, ServiceStatus::Failed { reason: e.to_string() });
+                }
+            }
+            drop(p2p_svc);
+            
+            // 必须在启动文件/聊天服务器之前确定是否启用 TLS
+            // 不再在创建大厅时自动启动HTTP文件服务器，HTTP服务器将在第一次添加共享时按需启动
+            let mut http_status = ServiceStatus::OnDemand;
+            // 文件/聊天服务的请求签名同样由大厅密码派生
+            crate::modules::http_auth::configure_for_lobby(&signaling_key);
+            if let Err(e) = crate::modules::http_tls::configure_for_lobby(enable_http_tls, &tls_seed.0, &tls_seed.1) {
+                log::error!("❌ 初始化 HTTP TLS 失败，将使用明文 HTTP: {}", e);
+                crate::modules::http_tls::deactivate();
+                http_status = ServiceStatus::Degraded { reason: format!("TLS 不可用，已回退为明文 HTTP: {}", e) };
+            }
+            
             log::info!("📝 HTTP文件服务器将在添加共享时按需启动");
             let ft_service = file_transfer.lock().await;
             ft_service.set_virtual_ip(virtual_ip.clone());
+            ft_service.set_guest_mode(false);
             drop(ft_service);
+            result.set_service(LobbyCreateResult::FILE, http_status.clone());
             
             // 启动P2P聊天服务器
             log::info!("正在启动P2P聊天服务器...");
             let chat_svc = chat_service.lock().await;
             chat_svc.set_virtual_ip(virtual_ip.clone());
+            chat_svc.set_local_player_id(player_id);
             match chat_svc.start_server().await {
                 Ok(_) => {
                     log::info!("✅ P2P聊天服务器启动成功");
+                    let chat_status = match http_status {
+                        ServiceStatus::Degraded { reason } => ServiceStatus::Degraded { reason },
+                        _ => ServiceStatus::Running,
+                    };
+                    result.set_service(LobbyCreateResult::CHAT, chat_status);
                 }
                 Err(e) => {
                     log::error!("❌ P2P聊天服务器启动失败: {}", e);
+                    result.set_service(LobbyCreateResult::CHAT, ServiceStatus::Failed { reason: e.to_string() });
                 }
             }
             drop(chat_svc);
@@ -139,9 +277,25 @@ pub async fn create_lobby(
             // 更新应用状态为在大厅中
             let core = state.core.lock().await;
             core.set_state(CoreAppState::InLobby).await;
+            let config_manager = core.get_config_manager();
             drop(core);
             
-            Ok(lobby)
+            // 记录到最近大厅（不保存密码），失败不影响进入大厅
+            if let Err(e) = config_manager
+                .lock()
+                .await
+                .record_recent_lobby(lobby.name.clone(), recent_server_node, lobby.use_domain.unwrap_or(false))
+                .await
+            {
+                log::warn!("记录最近大厅失败: {}", e);
+            }
+            
+            crate::modules::lobby_auto_actions::spawn_lobby_auto_actions(app_handle.clone(), auto_player.0, auto_player.1);
+            
+            for (service, reason) in result.problems() {
+                log::warn!("⚠️ 大厅已创建，但子服务 {} 未正常运行: {}", service, reason);
+            }
+            Ok(result)
         }
         Err(e) => {
             log::error!("创建大厅失败: {}", e);
@@ -151,7 +305,7 @@ pub async fn create_lobby(
             core.set_state(CoreAppState::Error(e.to_string())).await;
             drop(core);
             
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -165,10 +319,11 @@ pub async fn create_lobby(
 /// * `player_id` - 玩家ID（由前端生成）
 /// * `server_node` - 服务器节点地址
 /// * `signaling_server` - 信令服务器地址
+/// * `as_guest` - 是否以游客身份加入（不开麦、不共享文件、不占用大厅名额，仍可看聊天和收听语音）
 /// 
 /// # 返回
 /// * `Ok(Lobby)` - 成功加入的大厅信息
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn join_lobby(
     name: String,
@@ -179,10 +334,14 @@ pub async fn join_lobby(
     signaling_server: String,
     use_domain: Option<bool>,
     virtual_domain: Option<String>,
+    as_guest: Option<bool>,
+    network_mode: Option<crate::modules::network_service::NetworkMode>,
     app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Lobby, String> {
-    log::info!("收到加入大厅命令: name={}, player={}, player_id={}, signaling_server={}, use_domain={:?}, virtual_domain={:?}", name, player_name, player_id, signaling_server, use_domain, virtual_domain);
+) -> Result<Lobby, CommandError> {
+    log::info!("收到加入大厅命令: name={}, player={}, player_id={}, signaling_server={}, use_domain={:?}, virtual_domain={:?}, as_guest={:?}", name, player_name, player_id, signaling_server, use_domain, virtual_domain, as_guest);
+    crate::modules::network_service::reset_connect_cancel();
+    let as_guest = as_guest.unwrap_or(false);
     
     let core = state.core.lock().await;
     
@@ -190,7 +349,7 @@ pub async fn join_lobby(
     core.set_state(CoreAppState::Connecting).await;
     
     // 【关键修复】在这里读取配置，避免在 start_easytier 中再次获取 core 的锁
-    let (global_config, lobby_config) = {
+    let (global_config, lobby_config, enable_http_tls, signaling_plaintext_compat) = {
         let config_manager = core.get_config_manager();
         let cfg_mgr = config_manager.lock().await;
         let user_config = cfg_mgr.get_config();
@@ -198,7 +357,12 @@ pub async fn join_lobby(
         let global_cfg = user_config.global_easytier_advanced_config.clone();
         let lobby_cfg = user_config.lobby_easytier_advanced_config.clone();
         
-        (global_cfg, lobby_cfg)
+        (
+            global_cfg,
+            lobby_cfg,
+            user_config.enable_http_tls.unwrap_or(false),
+            user_config.p2p_signaling_plaintext_compat.unwrap_or(false),
+        )
     };
     
     // 获取各个服务的引用
@@ -212,9 +376,21 @@ pub async fn join_lobby(
     // 释放 core 的锁，避免死锁
     drop(core);
     
+    // HTTP 服务的 TLS 证书由大厅名称和密码派生
+    let tls_seed = (name.clone(), password.clone());
+    // P2P 信令密钥由大厅密码（即 EasyTier network_key）派生
+    let signaling_key = password.clone();
+    let recent_server_node = server_node.clone();
+    let auto_player = (player_id.clone(), player_name.clone());
+    
     // 加入大厅
     let mut lobby_mgr = lobby_manager.lock().await;
-    let network_svc = network_service.lock().await;
+    let mut network_svc = network_service.lock().await;
+    // 纯中继模式下把本机 Minecraft 端口转发到房主（房主按约定获得 CREATOR_VIRTUAL_IP）
+    network_svc.set_network_mode(
+        network_mode.unwrap_or_default(),
+        Some(crate::modules::lobby_manager::CREATOR_VIRTUAL_IP.to_string()),
+    );
     
     match lobby_mgr.join_lobby_with_config(
         name,
@@ -230,14 +406,16 @@ pub async fn join_lobby(
         lobby_config,
     ).await {
         Ok(lobby) => {
-            log::info!("成功加入大厅: {}", lobby.name);
+            log::info!("成功加入大厅: {}{}", lobby.name, if as_guest { "（游客）" } else { "" });
+            let lobby = lobby_mgr.set_guest(as_guest).unwrap_or(lobby);
             
-            // 初始化语音服务
+            // 初始化语音服务（游客仍需要扬声器接收语音，只是不允许开麦）
             let voice_svc = voice_service.lock().await;
             if let Err(e) = voice_svc.initialize().await {
                 log::warn!("语音服务初始化失败: {}", e);
                 // 语音服务失败不应该阻止加入大厅
             }
+            voice_svc.set_guest_mode(as_guest);
             drop(voice_svc);
             
             // 获取虚拟IP（用于P2P信令服务和HTTP文件服务器）
@@ -250,12 +428,20 @@ pub async fn join_lobby(
             // 所有客户端都连接到官方 WebSockets 信令服务器 (wss://mctier.pmhs.top/signaling)
             log::info!("客户端将连接到官方 WebSockets 信令服务器: wss://mctier.pmhs.top/signaling");
             
+            // 本次大厅流量从零开始统计
+            crate::modules::traffic_stats::reset_session();
+            
             // 启动P2P信令服务
             log::info!("正在启动P2P信令服务（加入大厅）...");
             let p2p_svc = p2p_signaling.lock().await;
-            match p2p_svc.start(player_id, player_name, virtual_ip.clone()).await {
+            p2p_svc.set_guest(as_guest);
+            p2p_svc.set_creator_virtual_ip(Some(lobby.creator_virtual_ip.clone())).await;
+            p2p_svc.set_network_key(Some(&signaling_key), signaling_plaintext_compat).await;
+            match p2p_svc.start(player_id.clone(), player_name, virtual_ip.clone()).await {
                 Ok(_) => {
                     log::info!("✅ P2P信令服务启动成功（加入大厅）");
+                    // 文件/聊天服务仅对 P2P 信令发现的本大厅成员开放
+                    crate::modules::peer_access::configure_for_lobby(&virtual_ip, p2p_svc.peers_handle());
                 }
                 Err(e) => {
                     log::error!("❌ 启动P2P信令服务失败（加入大厅）: {}", e);
@@ -264,22 +450,32 @@ pub async fn join_lobby(
                     let core = state.core.lock().await;
                     core.set_state(CoreAppState::Error(format!("P2P信令服务启动失败: {}", e))).await;
                     drop(core);
-                    return Err(format!("P2P信令服务启动失败: {}", e));
+                    return Err(CommandError::network(format!("P2P信令服务启动失败: {}", e)));
                 }
             }
             drop(p2p_svc);
             
+            // 必须在启动文件/聊天服务器之前确定是否启用 TLS
+            // 文件/聊天服务的请求签名同样由大厅密码派生
+            crate::modules::http_auth::configure_for_lobby(&signaling_key);
+            if let Err(e) = crate::modules::http_tls::configure_for_lobby(enable_http_tls, &tls_seed.0, &tls_seed.1) {
+                log::error!("❌ 初始化 HTTP TLS 失败，将使用明文 HTTP: {}", e);
+                crate::modules::http_tls::deactivate();
+            }
+            
             // 不再在加入大厅时自动启动HTTP文件服务器
             // HTTP服务器将在第一次添加共享时按需启动
             log::info!("📝 HTTP文件服务器将在添加共享时按需启动");
             let ft_service = file_transfer.lock().await;
             ft_service.set_virtual_ip(virtual_ip.clone());
+            ft_service.set_guest_mode(as_guest);
             drop(ft_service);
             
             // 启动P2P聊天服务器
             log::info!("正在启动P2P聊天服务器...");
             let chat_svc = chat_service.lock().await;
             chat_svc.set_virtual_ip(virtual_ip.clone());
+            chat_svc.set_local_player_id(player_id);
             match chat_svc.start_server().await {
                 Ok(_) => {
                     log::info!("✅ P2P聊天服务器启动成功");
@@ -293,8 +489,21 @@ pub async fn join_lobby(
             // 更新应用状态为在大厅中
             let core = state.core.lock().await;
             core.set_state(CoreAppState::InLobby).await;
+            let config_manager = core.get_config_manager();
             drop(core);
             
+            // 记录到最近大厅（不保存密码），失败不影响进入大厅
+            if let Err(e) = config_manager
+                .lock()
+                .await
+                .record_recent_lobby(lobby.name.clone(), recent_server_node, lobby.use_domain.unwrap_or(false))
+                .await
+            {
+                log::warn!("记录最近大厅失败: {}", e);
+            }
+            
+            crate::modules::lobby_auto_actions::spawn_lobby_auto_actions(app_handle.clone(), auto_player.0, auto_player.1);
+            
             Ok(lobby)
         }
         Err(e) => {
@@ -305,7 +514,7 @@ pub async fn join_lobby(
             core.set_state(CoreAppState::Error(e.to_string())).await;
             drop(core);
             
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -314,9 +523,9 @@ pub async fn join_lobby(
 /// 
 /// # 返回
 /// * `Ok(())` - 成功退出
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn leave_lobby(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn leave_lobby(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("收到退出大厅命令");
     
     let core = state.core.lock().await;
@@ -327,15 +536,25 @@ pub async fn leave_lobby(state: State<'_, AppState>) -> Result<(), String> {
     let voice_service = core.get_voice_service();
     let p2p_signaling = core.get_p2p_signaling();
     let file_transfer = core.get_file_transfer();
+    let chat_service = core.get_chat_service();
     
     // 【修复】尽早释放 core 锁，避免在数秒级的 stop_easytier（netsh/pnputil/PowerShell）
     // 期间一直占用 core 锁，导致其它命令阻塞、界面卡死
     drop(core);
     
-    // 停止HTTP文件服务器
+    // 依次停止HTTP文件服务器与聊天服务器，每个服务都等到端口释放后才继续，
+    // 保证退出后立即重新创建 / 加入大厅时能绑定同一端口
     let ft_service = file_transfer.lock().await;
     ft_service.stop_server().await;
     drop(ft_service);
+    let chat_svc = chat_service.lock().await;
+    chat_svc.stop_server().await;
+    drop(chat_svc);
+    crate::modules::http_tls::deactivate();
+    crate::modules::http_auth::deactivate();
+    crate::modules::peer_access::deactivate();
+    crate::modules::traffic_stats::reset_session();
+    crate::modules::service_ports::clear_peer_ports();
     
     // 停止P2P信令服务
     let p2p_svc = p2p_signaling.lock().await;
@@ -370,146 +589,1735 @@ pub async fn leave_lobby(state: State<'_, AppState>) -> Result<(), String> {
         }
         Err(e) => {
             log::error!("退出大厅失败: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
-// ==================== 语音控制命令 ====================
+/// 音频设备热插拔检测守护任务（应用启动时调用一次）
+///
+/// 定期重新枚举音频设备并与当前列表对比：列表变化时推送 `audio-devices-updated`，
+/// 当前选中的设备被拔出时自动回退到默认设备并推送 `audio-device-changed`。
+pub async fn run_audio_device_watcher(app: tauri::AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let voice_service = state.get_voice_service();
 
-/// 切换麦克风状态
-/// 
-/// # 返回
-/// * `Ok(bool)` - 新的麦克风状态（true=开启，false=关闭）
-/// * `Err(String)` - 错误信息
-#[tauri::command]
-pub async fn toggle_mic(
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-) -> Result<bool, String> {
-    log::info!("收到切换麦克风命令");
-    
-    let core = state.core.lock().await;
-    
-    // 使用 AppCore 的 toggle_mic 方法，它会正确处理状态切换
-    match core.toggle_mic().await {
-        Ok(new_state) => {
-            log::info!("麦克风状态已切换: {}", new_state);
-            
-            // 发送事件到前端更新UI
-            if let Err(e) = app.emit("mic-toggled", new_state) {
-                log::error!("发送麦克风状态事件失败: {}", e);
+    loop {
+        tokio::time::sleep(crate::modules::voice_service::AUDIO_DEVICE_POLL_INTERVAL).await;
+
+        // 枚举在锁外进行，避免阻塞其它语音命令；枚举失败时跳过，保留当前选择
+        let Some(devices) = crate::modules::voice_service::scan_audio_devices().await else {
+            continue;
+        };
+        let refresh = voice_service.lock().await.apply_device_list(devices.clone()).await;
+        if !refresh.devices_changed {
+            continue;
+        }
+
+        log::info!("🎧 音频设备列表已变化，共 {} 个设备", devices.len());
+        let _ = app.emit("audio-devices-updated", serde_json::json!({
+            "devices": devices,
+        }));
+        for fallback in refresh.fallbacks {
+            let _ = app.emit("audio-device-changed", serde_json::json!({
+                "deviceType": fallback.device_type,
+                "previousDeviceId": fallback.previous,
+                "deviceId": fallback.current,
+            }));
+        }
+    }
+}
+
+/// 麦克风实时电平推送任务（应用启动时调用一次）
+///
+/// 麦克风开启（按键说话模式下即按键按住）期间打开一个只读输入流，
+/// 以约 20Hz 推送 `mic-level` 事件（0.0-1.0）；麦克风关闭时关闭输入流并推送一次 0 让电平条归零。
+/// 没有麦克风或没有麦克风权限时推送一次 `mic-level-unavailable`，本次开启期间不再重试。
+pub async fn run_mic_level_monitor(app: tauri::AppHandle) {
+    use crate::modules::voice_service::{MicLevelMeter, MIC_LEVEL_INTERVAL};
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let voice_service = state.get_voice_service();
+
+    let mut meter: Option<MicLevelMeter> = None;
+    // 打开失败的设备，避免每个周期重复尝试
+    let mut unavailable: Option<Option<String>> = None;
+    let mut ticker = tokio::time::interval(MIC_LEVEL_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+        let (active, device) = {
+            let vs = voice_service.lock().await;
+            (vs.is_mic_enabled(), vs.get_selected_microphone().await)
+        };
+
+        if !active {
+            unavailable = None;
+            if meter.take().is_some() {
+                let _ = app.emit("mic-level", 0.0f32);
             }
-            
-            Ok(new_state)
+            continue;
         }
-        Err(e) => {
-            log::error!("切换麦克风失败: {}", e);
-            Err(e.to_string())
+
+        // 切换了麦克风或输入流出错时重新打开
+        if meter.as_ref().is_some_and(|m| m.is_failed() || m.device() != device.as_deref()) {
+            meter = None;
+        }
+        if meter.is_none() && unavailable.as_ref() != Some(&device) {
+            let wanted = device.clone();
+            match tokio::task::spawn_blocking(move || MicLevelMeter::start(wanted)).await {
+                Ok(Ok(m)) => {
+                    log::info!("🎙️ 麦克风电平采集已启动: {:?}", device);
+                    meter = Some(m);
+                }
+                Ok(Err(e)) => {
+                    log::warn!("⚠️ 无法采集麦克风电平（可能没有麦克风权限）: {}", e);
+                    let _ = app.emit("mic-level-unavailable", serde_json::json!({ "reason": e.to_string() }));
+                    unavailable = Some(device);
+                }
+                Err(e) => log::warn!("⚠️ 麦克风电平采集任务异常: {}", e),
+            }
+        }
+        if let Some(m) = &meter {
+            let _ = app.emit("mic-level", m.level());
         }
     }
 }
 
-/// 静音或取消静音指定玩家
-/// 
-/// # 参数
-/// * `player_id` - 玩家 ID
-/// * `muted` - true=静音，false=取消静音
-/// 
-/// # 返回
-/// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
-#[tauri::command]
-pub async fn mute_player(
-    player_id: String,
-    muted: bool,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    log::info!("收到静音玩家命令: player_id={}, muted={}", player_id, muted);
-    
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
-    let voice_svc = voice_service.lock().await;
-    
-    match voice_svc.mute_player(&player_id, muted).await {
-        Ok(_) => {
-            log::info!("玩家 {} 静音状态已更新: {}", player_id, muted);
-            Ok(())
+/// 房间健康分推送间隔
+const ROOM_HEALTH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// 房间健康分推送任务（应用启动时调用一次）
+///
+/// 在大厅中时定期计算房间健康分并推送 `room-health` 事件。
+pub async fn run_room_health_monitor(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(ROOM_HEALTH_INTERVAL).await;
+
+        let state = app.state::<AppState>();
+        if !matches!(state.core.lock().await.get_state().await, CoreAppState::InLobby) {
+            continue;
         }
-        Err(e) => {
-            log::error!("更新玩家静音状态失败: {}", e);
-            Err(e.to_string())
+        match collect_room_health(app.clone(), state).await {
+            Ok(health) => {
+                if !health.laggards.is_empty() {
+                    log::info!("房间健康分 {}，拖后腿的节点: {:?}", health.score, health.laggards.iter().map(|p| &p.ip).collect::<Vec<_>>());
+                }
+                let _ = app.emit("room-health", &health);
+            }
+            Err(e) => log::debug!("计算房间健康分失败: {}", e),
         }
     }
 }
 
-/// 全局静音或取消静音所有玩家
-/// 
-/// # 参数
-/// * `muted` - true=静音所有玩家，false=取消静音所有玩家
-/// 
-/// # 返回
-/// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
-#[tauri::command]
-pub async fn mute_all(muted: bool, state: State<'_, AppState>) -> Result<(), String> {
-    log::info!("收到全局静音命令: muted={}", muted);
-    
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
-    let voice_svc = voice_service.lock().await;
-    
-    match voice_svc.mute_all(muted).await {
-        Ok(_) => {
-            log::info!("全局静音状态已更新: {}", muted);
-            Ok(())
+/// 聊天 @ 提及通知任务（应用启动时调用一次）
+///
+/// 其他成员的消息 @ 了本机玩家时推送 `chat-mention` 事件，前端据此提示音与高亮。
+pub async fn run_chat_mention_notifier(app: tauri::AppHandle) {
+    use crate::modules::chat_service::CHAT_MENTION_EVENT;
+
+    let chat_service = app.state::<AppState>().get_chat_service();
+    let mut rx = chat_service.lock().await.subscribe_mentions();
+    drop(chat_service);
+    loop {
+        match rx.recv().await {
+            Ok(message) => {
+                let _ = app.emit(CHAT_MENTION_EVENT, message);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("⚠️ @ 提及通知积压，跳过 {} 条", skipped);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// 配置文件热重载任务（应用启动时调用一次）
+///
+/// 定期检查配置文件是否被外部修改（手动编辑或其他实例保存），重新加载后推送 `config-reloaded` 事件；
+/// 本实例自己的保存不会触发。新内容解析失败时保留当前配置。
+pub async fn run_config_file_watcher(app: tauri::AppHandle) {
+    use crate::modules::config_manager::CONFIG_WATCH_INTERVAL;
+
+    loop {
+        tokio::time::sleep(CONFIG_WATCH_INTERVAL).await;
+
+        let config_manager = app.state::<AppState>().get_config_manager();
+        let mut cfg_mgr = config_manager.lock().await;
+        match cfg_mgr.reload_if_changed().await {
+            Ok(true) => {
+                let _ = app.emit("config-reloaded", cfg_mgr.get_config_clone());
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("⚠️ 配置文件被外部修改但无法解析，保留当前配置: {}", e),
         }
+    }
+}
+
+/// 本机 Minecraft 局域网世界检测任务（应用启动时调用一次）
+///
+/// 监听 224.0.2.60:4445 上的 Minecraft 局域网公告，识别出本机开放的世界后，
+/// 在大厅中通过 P2P 信令向其他成员宣告端口，世界关闭后宣告撤销。
+pub async fn run_mc_lan_detector(app: tauri::AppHandle) {
+    use crate::modules::mc_lan_detect::{self, LanWorldChange, LanWorldTracker};
+
+    let socket = match mc_lan_detect::bind_lan_socket().and_then(tokio::net::UdpSocket::from_std) {
+        Ok(socket) => socket,
         Err(e) => {
-            log::error!("更新全局静音状态失败: {}", e);
-            Err(e.to_string())
+            log::warn!("⚠️ 无法监听 Minecraft 局域网公告，局域网世界自动宣告不可用: {}", e);
+            return;
+        }
+    };
+    let mut tracker = LanWorldTracker::new();
+    // 已确认不属于本机的公告端口（物理局域网里其他电脑的世界），避免每条公告都探测一次
+    let mut foreign_ports = std::collections::HashSet::new();
+    let mut buf = [0u8; 1024];
+
+    loop {
+        let change = match tokio::time::timeout(std::time::Duration::from_secs(2), socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, _))) => match mc_lan_detect::parse_lan_announcement(&buf[..len]) {
+                Some(announcement) if tracker.current().is_some_and(|c| c.port == announcement.port) => {
+                    tracker.observe(announcement, std::time::Instant::now())
+                }
+                Some(announcement) if !foreign_ports.contains(&announcement.port) => {
+                    if mc_lan_detect::is_local_world(announcement.port).await {
+                        log::info!("🎮 检测到本机开放的局域网世界: {} 端口 {}", announcement.motd, announcement.port);
+                        tracker.observe(announcement, std::time::Instant::now())
+                    } else {
+                        foreign_ports.insert(announcement.port);
+                        None
+                    }
+                }
+                _ => None,
+            },
+            Ok(Err(e)) => {
+                log::debug!("接收 Minecraft 局域网公告失败: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                None
+            }
+            Err(_) => None,
+        };
+        let Some(change) = change.or_else(|| tracker.tick(std::time::Instant::now())) else {
+            continue;
+        };
+        if change == LanWorldChange::Closed {
+            log::info!("🎮 本机局域网世界已关闭");
+            foreign_ports.clear();
+        }
+
+        let p2p_signaling = app.state::<AppState>().get_p2p_signaling();
+        let p2p = p2p_signaling.lock().await;
+        let Some(player_id) = p2p.get_local_player_id().await else {
+            continue;
+        };
+        let (port, motd) = match change {
+            LanWorldChange::Opened(world) => (Some(world.port), world.motd),
+            LanWorldChange::Closed => (None, String::new()),
+        };
+        let message = crate::modules::p2p_signaling::P2PMessage::McLanWorld { player_id, port, motd };
+        if let Err(e) = p2p.broadcast_to_all(message).await {
+            log::debug!("广播局域网世界失败: {}", e);
         }
     }
 }
 
-// ==================== 配置管理命令 ====================
+/// 在线状态自动检测任务（应用启动时调用一次）
+///
+/// 定期检测 Minecraft 进程、系统无输入时长与主窗口最小化状态，生效状态变化时广播给大厅成员。
+/// 手动设置了非"在线"状态时检测结果不影响生效状态。
+pub async fn run_presence_monitor(app: tauri::AppHandle) {
+    use crate::modules::presence::{self, PRESENCE_CHECK_INTERVAL};
+
+    loop {
+        tokio::time::sleep(PRESENCE_CHECK_INTERVAL).await;
+
+        let game_running = presence::is_minecraft_running().await;
+        let minimized = app
+            .get_webview_window("main")
+            .and_then(|w| w.is_minimized().ok())
+            .unwrap_or(false);
+        let changed = presence::tracker().lock().observe(
+            game_running,
+            presence::system_idle_time(),
+            minimized,
+            std::time::Instant::now(),
+        );
+        if let Some(presence) = changed {
+            log::info!("在线状态自动切换为: {}", presence.label());
+            broadcast_presence(&app, presence).await;
+        }
+    }
+}
 
-/// 获取用户配置
-/// 
-/// # 返回
-/// * `Ok(UserConfig)` - 用户配置
-/// * `Err(String)` - 错误信息
-#[tauri::command]
-pub async fn get_config(state: State<'_, AppState>) -> Result<UserConfig, String> {
-    log::info!("收到获取配置命令");
-    
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
-    let config_mgr = config_manager.lock().await;
-    
-    let config = config_mgr.get_config_clone();
-    
-    log::debug!("返回配置: {:?}", config);
-    
-    Ok(config)
+/// 托盘状态刷新任务（应用启动时调用一次）
+///
+/// 定期读取所在大厅、麦克风与听筒静音状态，变化时更新托盘菜单、悬停提示与图标角标。
+pub async fn run_tray_status_updater(app: tauri::AppHandle) {
+    use crate::modules::tray::{self, TrayStatus, TRAY_STATUS_INTERVAL};
+
+    loop {
+        tokio::time::sleep(TRAY_STATUS_INTERVAL).await;
+
+        let state = app.state::<AppState>();
+        let (lobby_manager, voice_service) = (state.get_lobby_manager(), state.get_voice_service());
+        let lobby_name = lobby_manager.lock().await.get_current_lobby().map(|lobby| lobby.name.clone());
+        let (mic_enabled, speaker_muted) = {
+            let voice_svc = voice_service.lock().await;
+            (lobby_name.is_some() && voice_svc.is_mic_enabled(), voice_svc.is_global_muted())
+        };
+        if let Err(e) = tray::update_status(&app, TrayStatus { lobby_name, mic_enabled, speaker_muted }) {
+            log::warn!("更新托盘状态失败: {}", e);
+        }
+    }
 }
 
-/// 更新用户配置
-/// 
+/// 广播本机在线状态，并通知本地前端（不在大厅中时只更新本地状态，加入后随发现广播携带）
+async fn broadcast_presence(app: &tauri::AppHandle, presence: crate::modules::presence::Presence) {
+    let p2p_signaling = app.state::<AppState>().get_p2p_signaling();
+    let p2p = p2p_signaling.lock().await;
+    let Some(player_id) = p2p.get_local_player_id().await else {
+        return;
+    };
+    let message = crate::modules::p2p_signaling::P2PMessage::PresenceUpdate {
+        player_id: player_id.clone(),
+        presence,
+    };
+    if let Err(e) = p2p.broadcast_to_all(message).await {
+        log::warn!("广播在线状态失败: {}", e);
+    }
+    let _ = app.emit("player-presence-update", serde_json::json!({
+        "playerId": player_id,
+        "presence": presence,
+    }));
+}
+
+/// 手动设置本机在线状态
+///
+/// 设为"在线"表示恢复自动检测（游戏中 / 离开），其它状态会一直保持到再次设置。
+///
 /// # 参数
-/// * `config` - 新的用户配置
-/// 
+/// * `status` - 在线状态
+///
 /// # 返回
-/// * `Ok(())` - 更新成功
-/// * `Err(String)` - 错误信息
+/// * `Ok(Presence)` - 实际生效的状态
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn update_config(
-    config: UserConfig,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    log::info!("收到更新配置命令");
-    
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+pub async fn set_presence(
+    status: crate::modules::presence::Presence,
+    app: tauri::AppHandle,
+) -> Result<crate::modules::presence::Presence, CommandError> {
+    use crate::modules::presence;
+
+    log::info!("设置在线状态: {}", status.label());
+    let changed = presence::tracker().lock().set_manual(status);
+    if let Some(current) = changed {
+        broadcast_presence(&app, current).await;
+    }
+    Ok(presence::current())
+}
+
+/// 创建者选举检查间隔
+const CREATOR_ELECTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// 本机为创建者时定期宣告的间隔（以检查次数计）
+const CREATOR_ANNOUNCE_EVERY: u32 = 6;
+
+/// 大厅创建者重新选举守护任务（应用启动时调用一次）
+///
+/// 定期用 P2P 信令发现的成员列表检查创建者是否仍在大厅中，创建者离开后在剩余成员中
+/// 按虚拟 IP 最小者重新选举，更新 `Lobby.creator_virtual_ip` 并推送 `creator-changed` 事件。
+/// 本机为创建者时定期广播宣告，收到冲突宣告时按同一规则收敛，处理两个节点同时自认为创建者的情况。
+pub async fn run_creator_election_supervisor(app: tauri::AppHandle) {
+    use crate::modules::lobby_manager::CreatorElection;
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    // (大厅 ID, 选举状态)，换大厅后重新开始
+    let mut election: Option<(String, CreatorElection)> = None;
+    let mut ticks = 0u32;
+
+    loop {
+        tokio::time::sleep(CREATOR_ELECTION_INTERVAL).await;
+
+        let core = state.core.lock().await;
+        if !matches!(core.get_state().await, CoreAppState::InLobby) {
+            election = None;
+            continue;
+        }
+        let lobby_manager = core.get_lobby_manager();
+        let p2p_signaling = core.get_p2p_signaling();
+        drop(core);
+
+        let Some(lobby) = lobby_manager.lock().await.get_current_lobby().cloned() else {
+            election = None;
+            continue;
+        };
+        let (members, announced) = {
+            let p2p = p2p_signaling.lock().await;
+            let mut members = p2p.member_virtual_ips().await;
+            if !lobby.is_guest {
+                members.push(lobby.virtual_ip.clone());
+            }
+            (members, p2p.take_announced_creator().await)
+        };
+
+        let now = std::time::Instant::now();
+        if election.as_ref().map(|(id, _)| id != &lobby.id).unwrap_or(true) {
+            election = Some((lobby.id.clone(), CreatorElection::new(now)));
+            ticks = 0;
+        }
+        let Some((_, election_state)) = election.as_mut() else {
+            continue;
+        };
+
+        let changed = election_state.evaluate(&lobby.creator_virtual_ip, &members, announced.as_deref(), now);
+        let creator = changed.clone().unwrap_or_else(|| lobby.creator_virtual_ip.clone());
+        let is_creator = creator == lobby.virtual_ip;
+
+        if let Some(new_creator) = changed {
+            let updated = lobby_manager.lock().await.set_creator_virtual_ip(new_creator.clone());
+            let Some(updated) = updated else {
+                continue;
+            };
+            log::warn!("👑 大厅创建者变更: {} -> {}", lobby.creator_virtual_ip, new_creator);
+            p2p_signaling.lock().await.set_creator_virtual_ip(Some(new_creator.clone())).await;
+            // 新创建者接管人数上限检查
+            if is_creator {
+                p2p_signaling.lock().await.set_max_players(updated.max_players).await;
+            }
+            let _ = app.emit("creator-changed", serde_json::json!({
+                "previousCreatorVirtualIp": lobby.creator_virtual_ip,
+                "creatorVirtualIp": new_creator,
+                "isCreator": is_creator,
+            }));
+            let creator_name = if is_creator {
+                "你".to_string()
+            } else {
+                p2p_signaling
+                    .lock()
+                    .await
+                    .get_peers()
+                    .await
+                    .into_iter()
+                    .find(|p| p.addr.ip().to_string() == new_creator)
+                    .map(|p| p.player_name)
+                    .unwrap_or_else(|| new_creator.clone())
+            };
+            post_system_message(&app, format!("大厅创建者变更为 {}", creator_name));
+            ticks = 0;
+        }
+
+        // 本机为创建者：当选后立即宣告，之后定期宣告；收到与自己不同的宣告时也立即宣告让对方收敛
+        let conflicting = announced.as_deref().is_some_and(|ip| ip != creator);
+        if is_creator && (ticks.is_multiple_of(CREATOR_ANNOUNCE_EVERY) || conflicting) {
+            if let Err(e) = p2p_signaling.lock().await.announce_creator(&creator).await {
+                log::debug!("广播创建者宣告失败: {}", e);
+            }
+        }
+        ticks = ticks.wrapping_add(1);
+    }
+}
+
+/// 虚拟 IP 冲突检测任务（应用启动时调用一次）
+///
+/// 在大厅中时通过 P2P 信令广播本机虚拟 IP 声明：拿到新 IP 后的一段时间内每次检查都广播，之后低频广播，
+/// 以覆盖网络分区合并的情况。收到其他玩家声明相同 IP 时推送 `ip-conflict` 事件，
+/// 按仲裁需要让出的一方重启 EasyTier 重新分配，并把新 IP 同步给依赖它的服务。
+pub async fn run_ip_conflict_supervisor(app: tauri::AppHandle) {
+    use crate::modules::ip_conflict::{IP_CLAIM_EVERY, IP_CLAIM_FAST_WINDOW, IP_CLAIM_INTERVAL, IP_CONFLICT_EVENT};
+    use crate::modules::network_service::ConnectionStatus;
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    // 最近声明的虚拟 IP 及开始声明的时间，IP 变化后重新开始
+    let mut claimed: Option<(String, std::time::Instant)> = None;
+    let mut ticks = 0u32;
+
+    loop {
+        tokio::time::sleep(IP_CLAIM_INTERVAL).await;
+
+        let core = state.core.lock().await;
+        if !matches!(core.get_state().await, CoreAppState::InLobby) {
+            claimed = None;
+            continue;
+        }
+        let p2p_signaling = core.get_p2p_signaling();
+        let network_service = core.get_network_service();
+        let lobby_manager = core.get_lobby_manager();
+        let file_transfer = core.get_file_transfer();
+        let chat_service = core.get_chat_service();
+        drop(core);
+
+        let p2p = p2p_signaling.lock().await;
+        let Some(ip) = p2p.local_virtual_ip().await else {
+            claimed = None;
+            continue;
+        };
+        if claimed.as_ref().is_none_or(|(claimed_ip, _)| claimed_ip != &ip) {
+            claimed = Some((ip.clone(), std::time::Instant::now()));
+            ticks = 0;
+        }
+        let fast = claimed.as_ref().is_some_and(|(_, since)| since.elapsed() < IP_CLAIM_FAST_WINDOW);
+        if fast || ticks.is_multiple_of(IP_CLAIM_EVERY) {
+            if let Err(e) = p2p.claim_virtual_ip().await {
+                log::debug!("广播虚拟 IP 声明失败: {}", e);
+            }
+        }
+        ticks = ticks.wrapping_add(1);
+        let conflict = p2p.take_ip_conflict().await;
+        drop(p2p);
+
+        let Some(conflict) = conflict else {
+            continue;
+        };
+        log::warn!(
+            "⚠️ 检测到虚拟 IP 冲突: {} 与玩家 {} 相同（{}）",
+            conflict.virtual_ip,
+            conflict.other_player_id,
+            if conflict.yielding { "本机让出" } else { "对方让出" }
+        );
+        let _ = app.emit(IP_CONFLICT_EVENT, &conflict);
+        if !conflict.yielding {
+            continue;
+        }
+
+        post_system_message(&app, format!("虚拟 IP {} 与其他玩家冲突，正在重新分配", conflict.virtual_ip));
+        let renewed = network_service.lock().await.renew_virtual_ip(&app).await;
+        let new_ip = match renewed {
+            Ok(new_ip) => new_ip,
+            Err(e) => {
+                log::error!("❌ 重新分配虚拟 IP 失败: {}", e);
+                post_system_message(&app, format!("重新分配虚拟 IP 失败: {}", e));
+                continue;
+            }
+        };
+
+        log::info!("✅ 虚拟 IP 已重新分配: {} -> {}", conflict.virtual_ip, new_ip);
+        lobby_manager.lock().await.set_virtual_ip(new_ip.clone());
+        crate::modules::peer_access::update_local_ip(&new_ip);
+        // 文件 / 聊天服务绑定在虚拟 IP 上，运行中的按新 IP 重新监听
+        let ft_service = file_transfer.lock().await;
+        ft_service.set_virtual_ip(new_ip.clone());
+        if ft_service.is_running() {
+            ft_service.stop_server().await;
+            if let Err(e) = ft_service.start_server().await {
+                log::error!("❌ 文件共享服务按新虚拟 IP 重启失败: {}", e);
+            }
+        }
+        drop(ft_service);
+        let chat_svc = chat_service.lock().await;
+        chat_svc.set_virtual_ip(new_ip.clone());
+        if chat_svc.is_running() {
+            if let Err(e) = chat_svc.start_server().await {
+                log::error!("❌ 聊天服务按新虚拟 IP 重启失败: {}", e);
+            }
+        }
+        drop(chat_svc);
+        p2p_signaling.lock().await.set_virtual_ip(new_ip.clone()).await;
+        let _ = crate::modules::tauri_events::emit_network_status_change(&app, ConnectionStatus::Connected(new_ip.clone()));
+        let _ = app.emit("virtual-ip-corrected", serde_json::json!({
+            "previousIp": conflict.virtual_ip,
+            "virtualIp": new_ip,
+        }));
+    }
+}
+
+/// 断线重连宽限期内两次重连尝试之间的间隔
+const RECONNECT_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 虚拟网络断线重连守护任务（应用启动时调用一次）
+///
+/// EasyTier 在已连接状态下意外退出后进入重连宽限期：保持大厅状态、暂停 P2P 信令的心跳与超时判定，
+/// 并反复尝试用原参数重新拉起网络。宽限期内恢复则无缝继续，超时才真正退出大厅并清理。
+pub async fn run_network_reconnect_supervisor(app: tauri::AppHandle) {
+    use crate::modules::network_service::{ConnectionStatus, GraceState, ReconnectGrace, DEFAULT_RECONNECT_GRACE_SECS};
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let network_service = state.get_network_service();
+    let lost_signal = network_service.lock().await.connection_lost_signal();
+    drop(network_service);
+
+    loop {
+        lost_signal.notified().await;
+
+        let core = state.core.lock().await;
+        if !matches!(core.get_state().await, CoreAppState::InLobby) {
+            continue;
+        }
+        let network_service = core.get_network_service();
+        let p2p_signaling = core.get_p2p_signaling();
+        let file_transfer = core.get_file_transfer();
+        let chat_service = core.get_chat_service();
+        let grace_secs = core
+            .get_config_manager()
+            .lock()
+            .await
+            .get_config()
+            .reconnect_grace_secs
+            .unwrap_or(DEFAULT_RECONNECT_GRACE_SECS);
+        drop(core);
+
+        let mut grace = ReconnectGrace::new(std::time::Duration::from_secs(grace_secs));
+        grace.connection_lost(std::time::Instant::now());
+        log::warn!("⚠️ 虚拟网络连接丢失，进入 {} 秒重连宽限期", grace_secs);
+        let _ = crate::modules::tauri_events::emit_network_status_change(&app, ConnectionStatus::Reconnecting);
+        let _ = app.emit("network-reconnecting", serde_json::json!({ "graceSecs": grace_secs }));
+        p2p_signaling.lock().await.set_paused(true).await;
+
+        let mut recovered = None;
+        let mut attempt = 0u32;
+        while let GraceState::Waiting(remaining) = grace.check(std::time::Instant::now()) {
+            // 用户在宽限期内主动退出了大厅
+            if !matches!(state.core.lock().await.get_state().await, CoreAppState::InLobby) {
+                break;
+            }
+
+            attempt += 1;
+            log::info!("🔄 断线重连第 {} 次尝试（剩余 {} 秒）", attempt, remaining.as_secs());
+            let result = tokio::time::timeout(remaining, async {
+                network_service.lock().await.relaunch(&app).await
+            })
+            .await;
+
+            match result {
+                Ok(Ok(outcome)) => {
+                    recovered = Some(outcome);
+                    break;
+                }
+                Ok(Err(e)) => {
+                    log::warn!("断线重连失败: {}", e);
+                    tokio::time::sleep(RECONNECT_RETRY_INTERVAL.min(remaining)).await;
+                }
+                Err(_) => break,
+            }
+        }
+
+        if !matches!(state.core.lock().await.get_state().await, CoreAppState::InLobby) {
+            log::info!("重连期间已退出大厅，停止重连");
+            continue;
+        }
+
+        match recovered {
+            Some(outcome) => {
+                let ip = outcome.virtual_ip;
+                let downtime = grace.connection_restored(std::time::Instant::now()).unwrap_or_default();
+                log::info!("✅ 虚拟网络已恢复: {}（断线 {} 秒）", ip, downtime.as_secs());
+
+                // 重连后虚拟 IP 可能变化，同步给依赖它的服务
+                file_transfer.lock().await.set_virtual_ip(ip.clone());
+                chat_service.lock().await.set_virtual_ip(ip.clone());
+                crate::modules::peer_access::update_local_ip(&ip);
+                p2p_signaling.lock().await.set_paused(false).await;
+
+                let _ = crate::modules::tauri_events::emit_network_status_change(&app, ConnectionStatus::Connected(ip.clone()));
+                let _ = app.emit("network-reconnected", serde_json::json!({
+                    "virtualIp": ip,
+                    "downtimeSecs": downtime.as_secs(),
+                    "optimistic": outcome.confirmation.is_some(),
+                }));
+
+                // 使用缓存 IP 乐观恢复时，实际分配的 IP 确认后若不一致再更新各服务
+                if let Some(confirmation) = outcome.confirmation {
+                    let app = app.clone();
+                    tokio::spawn(async move {
+                        let Ok(actual) = confirmation.await else {
+                            return;
+                        };
+                        let Some(corrected) = crate::modules::network_service::reconcile_virtual_ip(&ip, &actual) else {
+                            return;
+                        };
+                        file_transfer.lock().await.set_virtual_ip(corrected.clone());
+                        chat_service.lock().await.set_virtual_ip(corrected.clone());
+                        let _ = crate::modules::tauri_events::emit_network_status_change(&app, ConnectionStatus::Connected(corrected.clone()));
+                        let _ = app.emit("virtual-ip-corrected", serde_json::json!({
+                            "previousIp": ip,
+                            "virtualIp": corrected,
+                        }));
+                    });
+                }
+            }
+            None => {
+                log::error!("❌ 重连宽限期（{} 秒）已耗尽，退出大厅", grace_secs);
+                let _ = app.emit("network-reconnect-failed", serde_json::json!({ "graceSecs": grace_secs }));
+                if let Err(e) = leave_lobby(app.state::<AppState>()).await {
+                    log::error!("断线超时后退出大厅失败: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 踢出玩家
+/// 
+/// 仅大厅创建者可调用，被踢玩家会自动退出大厅并收到 `kicked-from-lobby` 事件
+/// 
+/// # 参数
+/// * `player_id` - 要踢出的玩家ID
+/// * `reason` - 踢出原因（可选）
+/// 
+/// # 返回
+/// * `Ok(())` - 踢出成功
+/// * `Err(CommandError)` - 错误信息（如权限不足）
+#[tauri::command]
+pub async fn kick_player(
+    player_id: String,
+    reason: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("收到踢出玩家命令: player_id={}, reason={:?}", player_id, reason);
+    
+    let lobby_manager = state.get_lobby_manager();
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    let reason = reason
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .unwrap_or_else(|| "被大厅创建者移出大厅".to_string());
+    
+    let mut lobby_mgr = lobby_manager.lock().await;
+    let p2p_svc = p2p_signaling.lock().await;
+    let player_name = p2p_svc.get_peer(&player_id).await.map(|p| p.player_name);
+    lobby_mgr.kick_player(&player_id, reason, &p2p_svc).await.map_err(|e| {
+        log::error!("踢出玩家失败: {}", e);
+        CommandError::from(e)
+    })?;
+    drop(p2p_svc);
+    drop(lobby_mgr);
+    
+    post_system_message(&app, format!("{} 被踢出大厅", player_name.unwrap_or(player_id.clone())));
+    
+    let _ = app.emit("player-left", serde_json::json!({
+        "playerId": player_id,
+    }));
+    
+    Ok(())
+}
+
+// ==================== 语音控制命令 ====================
+
+/// 切换麦克风状态
+/// 
+/// # 返回
+/// * `Ok(bool)` - 新的麦克风状态（true=开启，false=关闭）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn toggle_mic(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<bool, CommandError> {
+    log::info!("收到切换麦克风命令");
+    
+    // 只锁语音服务，不等待 AppCore 大锁（避免被创建大厅等慢命令阻塞）
+    let voice_service = state.get_voice_service();
+    let result = voice_service.lock().await.toggle_mic().await;
+    match result {
+        Ok(new_state) => {
+            log::info!("麦克风状态已切换: {}", new_state);
+            
+            // 发送事件到前端更新UI
+            if let Err(e) = app.emit("mic-toggled", new_state) {
+                log::error!("发送麦克风状态事件失败: {}", e);
+            }
+            crate::modules::voice_feedback::announce(&app, VoiceCue::mic(new_state));
+            
+            Ok(new_state)
+        }
+        Err(e) => {
+            log::error!("切换麦克风失败: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 设置麦克风模式
+/// 
+/// # 参数
+/// * `mode` - 麦克风模式（toggle / push_to_talk / voice_activated）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_mic_mode(
+    mode: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("收到设置麦克风模式命令: {}", mode);
+    
+    let mic_mode: MicMode = mode.parse().map_err(|e: crate::modules::voice_service::VoiceError| e.to_string())?;
+    
+    let voice_service = state.get_voice_service();
+    let mic_closed = voice_service.lock().await.set_mic_mode(mic_mode).await;
+    
+    // 切换到按键说话模式时麦克风会被关闭：与快捷键开关麦相同，
+    // 通知前端更新 UI 并由前端的 mic-toggled 监听广播状态给其他玩家
+    if mic_closed {
+        let _ = app.emit("mic-toggled", false);
+        crate::modules::voice_feedback::announce(&app, VoiceCue::mic(false));
+    }
+    
+    let config_manager = state.get_config_manager();
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_mic_mode(mic_mode).await.map_err(|e| {
+        log::error!("保存麦克风模式失败: {}", e);
+        CommandError::from(e)
+    })?;
+    
+    let _ = app.emit("mic-mode-changed", mic_mode.as_str());
+    
+    Ok(())
+}
+
+/// 获取麦克风模式
+/// 
+/// # 返回
+/// * `Ok(String)` - 当前麦克风模式（toggle / push_to_talk / voice_activated）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_mic_mode(state: State<'_, AppState>) -> Result<String, CommandError> {
+    let voice_service = state.get_voice_service();
+    let voice_svc = voice_service.lock().await;
+    
+    Ok(voice_svc.get_mic_mode().await.as_str().to_string())
+}
+
+/// 检测系统中已在运行的 easytier-core 进程
+/// 
+/// # 返回
+/// * `Ok(Vec<u32>)` - 已在运行的进程 PID 列表，为空表示没有其它实例
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn detect_easytier_instances() -> Result<Vec<u32>, CommandError> {
+    let pids = NetworkService::list_easytier_processes().await;
+    log::info!("检测到 {} 个 easytier-core 进程: {:?}", pids.len(), pids);
+    Ok(pids)
+}
+
+/// 清理系统中已在运行的 easytier-core 进程
+/// 
+/// 在用户确认后调用，当前 MCTier 的 EasyTier 服务运行中时拒绝清理
+/// 
+/// # 返回
+/// * `Ok(usize)` - 清理前检测到的进程数量
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn cleanup_easytier_instances(state: State<'_, AppState>) -> Result<usize, CommandError> {
+    log::info!("收到清理 EasyTier 实例命令");
+    
+    let network_service = state.get_network_service();
+    
+    if network_service.lock().await.is_running().await {
+        return Err(CommandError::validation("EasyTier 服务正在运行，请先退出大厅"));
+    }
+    
+    let pids = NetworkService::list_easytier_processes().await;
+    if !pids.is_empty() {
+        NetworkService::cleanup_orphan_processes().await;
+    }
+    
+    Ok(pids.len())
+}
+
+/// 设置启动前发现其它 EasyTier 实例时是否自动清理
+/// 
+/// # 参数
+/// * `enabled` - 是否自动清理
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_auto_cleanup_easytier_instances(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置自动清理 EasyTier 实例命令: {}", enabled);
+    
+    state.get_network_service().lock().await.set_auto_cleanup_instances(enabled).await;
+    
+    let config_manager = state.get_config_manager();
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_auto_cleanup_easytier_instances(enabled).await.map_err(|e| {
+        log::error!("保存自动清理设置失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 获取最近创建/加入的大厅（按时间倒序，不含密码）
+/// 
+/// # 返回
+/// * `Ok(Vec<RecentLobby>)` - 最近大厅列表
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_recent_lobbies(state: State<'_, AppState>) -> Result<Vec<RecentLobby>, CommandError> {
+    let config_manager = state.get_config_manager();
+    
+    let config_mgr = config_manager.lock().await;
+    Ok(config_mgr.get_config().recent_lobbies.clone().unwrap_or_default())
+}
+
+/// 清空最近大厅记录
+/// 
+/// # 返回
+/// * `Ok(())` - 清空成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn clear_recent_lobbies(state: State<'_, AppState>) -> Result<(), CommandError> {
+    log::info!("收到清空最近大厅命令");
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.clear_recent_lobbies().await.map_err(|e| {
+        log::error!("清空最近大厅失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 为大厅生成短房间码（同一大厅总是返回同一个码）
+/// 
+/// # 参数
+/// * `name` - 大厅名称
+/// * `server_node` - 服务器节点地址
+/// * `use_domain` - 是否使用虚拟域名
+/// 
+/// # 返回
+/// * `Ok(String)` - 短房间码
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn create_room_code(
+    name: String,
+    server_node: String,
+    use_domain: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, CommandError> {
+    log::info!("收到生成房间码命令: name={}, server={}", name, server_node);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.create_room_code(name, server_node, use_domain.unwrap_or(false)).await.map_err(|e| {
+        log::error!("保存房间码失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 将短房间码还原为大厅参数（不含密码）
+/// 
+/// # 参数
+/// * `code` - 短房间码（忽略大小写、空白和 `-`）
+/// 
+/// # 返回
+/// * `Ok(Some(RoomCode))` - 对应的大厅参数
+/// * `Ok(None)` - 本地没有该房间码
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn resolve_room_code(code: String, state: State<'_, AppState>) -> Result<Option<RoomCode>, CommandError> {
+    let config_manager = state.get_config_manager();
+    
+    let config_mgr = config_manager.lock().await;
+    let list = config_mgr.get_config().room_codes.clone().unwrap_or_default();
+    Ok(crate::modules::config_manager::resolve_room_code(&list, &code).cloned())
+}
+
+/// 生成当前大厅的邀请链接（也可作为二维码内容）
+/// 
+/// # 参数
+/// * `include_password` - 是否把大厅密码放进链接（默认不包含，对方需另外输入密码）
+/// 
+/// # 返回
+/// * `Ok(String)` - `mctier://join?...` 邀请链接
+/// * `Err(CommandError)` - 当前不在大厅中
+#[tauri::command]
+pub async fn generate_invite_link(
+    include_password: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, CommandError> {
+    let lobby_manager = state.get_lobby_manager();
+    let network_service = state.get_network_service();
+    
+    let lobby = lobby_manager
+        .lock()
+        .await
+        .get_current_lobby()
+        .cloned()
+        .ok_or_else(|| CommandError::validation("当前不在大厅中"))?;
+    let server_node = network_service.lock().await.current_server_node().await;
+    
+    let params = LobbyJoinParams {
+        name: lobby.name,
+        server_node,
+        use_domain: lobby.use_domain.unwrap_or(false),
+        password: lobby.password.filter(|_| include_password.unwrap_or(false)),
+    };
+    log::info!("生成邀请链接: name={}, 含密码={}", params.name, params.password.is_some());
+    Ok(build_invite_link(&params))
+}
+
+/// 解析邀请链接，用于预填加入表单
+/// 
+/// # 参数
+/// * `link` - `mctier://join?...` 邀请链接（兼容旧版只含 name / pwd 的链接）
+/// 
+/// # 返回
+/// * `Ok(LobbyJoinParams)` - 加入大厅的参数
+/// * `Err(CommandError)` - 链接无效
+#[tauri::command]
+pub async fn parse_invite_link(link: String) -> Result<LobbyJoinParams, CommandError> {
+    crate::modules::invite_link::parse_invite_link(&link).map_err(|e| {
+        log::warn!("⚠️ 解析邀请链接失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置文件/聊天 HTTP 服务是否启用 TLS（下次创建或加入大厅时生效）
+/// 
+/// # 参数
+/// * `enabled` - 是否启用 TLS
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_http_tls_enabled(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置 HTTP TLS 命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_enable_http_tls(enabled).await.map_err(|e| {
+        log::error!("保存 HTTP TLS 设置失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置 P2P 信令加密后是否兼容旧版本的明文发现
+/// 
+/// # 参数
+/// * `enabled` - 是否兼容（下次进入大厅时生效）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_p2p_signaling_plaintext_compat(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置信令明文兼容命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_p2p_signaling_plaintext_compat(enabled).await.map_err(|e| {
+        log::error!("保存信令明文兼容设置失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置虚拟网络断线后的重连宽限期
+/// 
+/// # 参数
+/// * `secs` - 宽限期秒数（0 表示断线立即退出大厅）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_reconnect_grace_secs(
+    secs: u64,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置重连宽限期命令: {} 秒", secs);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_reconnect_grace_secs(secs).await.map_err(|e| {
+        log::error!("保存重连宽限期失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置启动组网后等待获取虚拟 IP 的超时时间
+/// 
+/// # 参数
+/// * `secs` - 超时秒数
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_connect_timeout_secs(
+    secs: u64,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置连接超时命令: {} 秒", secs);
+    
+    let config_manager = state.get_config_manager();
+    let network_service = state.get_network_service();
+    
+    config_manager.lock().await.set_connect_timeout_secs(secs).await.map_err(|e| {
+        log::error!("保存连接超时失败: {}", e);
+        CommandError::from(e)
+    })?;
+    network_service.lock().await.set_connect_timeout_secs(secs);
+    Ok(())
+}
+
+/// 设置聊天图片大小上限
+/// 
+/// # 参数
+/// * `max_bytes` - 图片原始数据大小上限（字节）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_chat_image_max_bytes(
+    max_bytes: u64,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置聊天图片大小上限命令: {} 字节", max_bytes);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_chat_image_max_bytes(max_bytes).await.map_err(|e| {
+        log::error!("保存聊天图片大小上限失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置其他成员向可写共享上传单个文件的大小上限
+/// 
+/// # 参数
+/// * `max_bytes` - 大小上限（字节）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_upload_max_bytes(
+    max_bytes: u64,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置上传大小上限命令: {} 字节", max_bytes);
+    
+    let config_manager = state.get_config_manager();
+    let file_transfer = state.get_file_transfer();
+    
+    config_manager.lock().await.set_upload_max_bytes(max_bytes).await.map_err(|e| {
+        log::error!("保存上传大小上限失败: {}", e);
+        CommandError::from(e)
+    })?;
+    file_transfer.lock().await.set_upload_max_bytes(max_bytes);
+    Ok(())
+}
+
+/// 设置是否用 Windows DPAPI 加密配置文件
+/// 
+/// # 参数
+/// * `enabled` - 是否加密
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_encrypt_config_file(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置配置文件加密命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_encrypt_config_file(enabled).await.map_err(|e| {
+        log::error!("设置配置文件加密失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置是否通过 UPnP 在路由器上映射 P2P 信令端口（下次进入大厅时生效）
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_upnp_port_mapping(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置 UPnP 端口映射命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    config_manager.lock().await.set_upnp_port_mapping(enabled).await.map_err(|e| {
+        log::error!("保存 UPnP 端口映射设置失败: {}", e);
+        CommandError::from(e)
+    })?;
+    p2p_signaling.lock().await.set_upnp_enabled(enabled);
+    Ok(())
+}
+
+/// 设置 P2P 信令 / 文件共享 / 聊天服务端口（下次进入大厅时生效）
+/// 
+/// 端口被占用时服务会自动顺延到后续端口，实际端口通过 `service-port-changed` 事件推送。
+/// 
+/// # 参数
+/// * `ports` - 端口配置
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 端口不合法或保存失败
+#[tauri::command]
+pub async fn set_port_config(
+    ports: PortConfig,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置服务端口命令: {:?}", ports);
+    
+    let config_manager = state.get_config_manager();
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    config_manager.lock().await.set_port_config(ports).await.map_err(|e| {
+        log::error!("保存服务端口失败: {}", e);
+        CommandError::from(e)
+    })?;
+    crate::modules::service_ports::configure(ports);
+    p2p_signaling.lock().await.set_listen_port(ports.signaling);
+    Ok(())
+}
+
+/// 获取本机服务实际使用的端口（绑定时可能顺延，与配置端口不同）
+#[tauri::command]
+pub async fn get_service_ports() -> Result<PortConfig, CommandError> {
+    Ok(crate::modules::service_ports::local_ports())
+}
+
+/// 设置浏览 / 下载他人共享时是否告知共享者
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_share_activity_presence(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置共享浏览状态广播命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    config_manager.lock().await.set_share_activity_presence(enabled).await.map_err(|e| {
+        log::error!("保存共享浏览状态广播设置失败: {}", e);
+        CommandError::from(e)
+    })?;
+    crate::modules::share_presence::presence_slot().lock().set_enabled(enabled);
+    Ok(())
+}
+
+/// 设置切换麦克风 / 听筒静音时是否播放提示音
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_voice_feedback_sound(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置开关麦提示音命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    config_manager.lock().await.set_voice_feedback_sound(enabled).await.map_err(|e| {
+        log::error!("保存开关麦提示音设置失败: {}", e);
+        CommandError::from(e)
+    })?;
+    crate::modules::voice_feedback::set_sound_enabled(enabled);
+    Ok(())
+}
+
+/// 设置创建大厅时的密码强度策略
+/// 
+/// # 参数
+/// * `policy` - 密码强度策略（strict / relaxed / none）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_lobby_password_policy(
+    policy: crate::modules::lobby_manager::PasswordPolicy,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置大厅密码强度策略命令: {:?}", policy);
+    
+    let config_manager = state.get_config_manager();
+    let lobby_manager = state.get_lobby_manager();
+    
+    config_manager.lock().await.set_lobby_password_policy(policy).await.map_err(|e| {
+        log::error!("保存大厅密码强度策略失败: {}", e);
+        CommandError::from(e)
+    })?;
+    lobby_manager.lock().await.set_password_policy(policy);
+    Ok(())
+}
+
+/// 设置说话检测阈值（麦克风灵敏度）
+/// 
+/// 立即作用于语音活动检测，并保存到配置。
+/// 
+/// # 参数
+/// * `value` - 麦克风电平阈值（0.0-1.0，超出范围时限制到 0.0-0.9）
+/// 
+/// # 返回
+/// * `Ok(f32)` - 实际生效的阈值
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_vad_threshold(value: f32, state: State<'_, AppState>) -> Result<f32, CommandError> {
+    log::info!("收到设置说话阈值命令: {}", value);
+    if !value.is_finite() {
+        return Err(CommandError::validation("说话阈值必须是有效数字"));
+    }
+    
+    let config_manager = state.get_config_manager();
+    let voice_service = state.get_voice_service();
+    
+    let threshold = voice_service.lock().await.set_vad_threshold(value);
+    config_manager.lock().await.set_vad_threshold(threshold).await.map_err(|e| {
+        log::error!("保存说话阈值失败: {}", e);
+        CommandError::from(e)
+    })?;
+    Ok(threshold)
+}
+
+/// 获取当前说话检测阈值
+#[tauri::command]
+pub async fn get_vad_threshold(state: State<'_, AppState>) -> Result<f32, CommandError> {
+    let voice_service = state.get_voice_service();
+    let threshold = voice_service.lock().await.get_vad_threshold();
+    Ok(threshold)
+}
+
+/// 校准说话检测阈值
+/// 
+/// 在当前选择的麦克风上采集一段环境音，按底噪的线性 RMS 推荐阈值。只返回推荐值，
+/// 由前端确认后再调用 `set_vad_threshold` 应用。校准期间请保持安静。
+/// 
+/// # 参数
+/// * `duration_secs` - 采集时长（秒），默认 3 秒，最长 10 秒
+/// 
+/// # 返回
+/// * `Ok(VadCalibration)` - 底噪电平与推荐阈值
+/// * `Err(CommandError)` - 无法打开麦克风
+#[tauri::command]
+pub async fn calibrate_vad_threshold(
+    duration_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<crate::modules::voice_service::VadCalibration, CommandError> {
+    use crate::modules::voice_service::{
+        recommend_vad_threshold, MicLevelMeter, DEFAULT_VAD_CALIBRATION_SECS, MAX_VAD_CALIBRATION_SECS, MIC_LEVEL_INTERVAL,
+    };
+    
+    let secs = duration_secs.unwrap_or(DEFAULT_VAD_CALIBRATION_SECS).clamp(1, MAX_VAD_CALIBRATION_SECS);
+    let voice_service = state.get_voice_service();
+    let device = voice_service.lock().await.get_selected_microphone().await;
+    log::info!("🎙️ 开始校准说话阈值: 设备={:?}, {} 秒", device, secs);
+    
+    let meter = tokio::task::spawn_blocking(move || MicLevelMeter::start(device))
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Audio, format!("麦克风采集任务异常: {}", e)))??;
+    
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(secs);
+    let mut ticker = tokio::time::interval(MIC_LEVEL_INTERVAL);
+    let mut samples = Vec::new();
+    while tokio::time::Instant::now() < deadline {
+        ticker.tick().await;
+        if meter.is_failed() {
+            return Err(CommandError::new(ErrorCode::Audio, "麦克风在校准过程中出错"));
+        }
+        samples.push(meter.rms());
+    }
+    drop(meter);
+    
+    let calibration = recommend_vad_threshold(&samples);
+    log::info!("🎙️ 说话阈值校准完成: 底噪 {:.3}，推荐 {:.3}", calibration.noise_level, calibration.recommended);
+    Ok(calibration)
+}
+
+/// 设置是否启用跨玩家语音响度均衡
+/// 
+/// 关闭时下发空的增益表，前端恢复各玩家的原始音量。
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_voice_loudness_normalization(
+    enabled: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("收到设置语音响度均衡命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    let voice_service = state.get_voice_service();
+    
+    config_manager.lock().await.set_voice_loudness_normalization(enabled).await.map_err(|e| {
+        log::error!("保存语音响度均衡设置失败: {}", e);
+        CommandError::from(e)
+    })?;
+    voice_service.lock().await.set_loudness_normalization(enabled).await;
+    if !enabled {
+        let _ = app.emit("voice-normalization-gains", serde_json::json!({
+            "gains": {},
+        }));
+    }
+    Ok(())
+}
+
+/// 设置 EasyTier 启动选项（下次创建/加入大厅时生效）
+/// 
+/// # 参数
+/// * `options` - 启动选项
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_easytier_launch_options(
+    options: crate::modules::config_manager::EasyTierLaunchOptions,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置 EasyTier 启动选项命令: {:?}", options);
+    
+    let config_manager = state.get_config_manager();
+    let network_service = state.get_network_service();
+    
+    config_manager.lock().await.set_easytier_launch_options(options.clone()).await.map_err(|e| {
+        log::error!("保存 EasyTier 启动选项失败: {}", e);
+        CommandError::from(e)
+    })?;
+    network_service.lock().await.set_launch_options(options);
+    Ok(())
+}
+
+/// 设置偏好的中继节点（下次创建/加入大厅时生效）
+/// 
+/// P2P 打洞失败时 EasyTier 会经由双方都连接的节点中继，跨国联机时指定一个两地延迟都低的节点可明显改善延迟。
+/// 
+/// # 参数
+/// * `node` - 中继节点地址（如 `tcp://relay.example.com:11010`），空或 None 表示不指定
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 地址无效或保存失败
+#[tauri::command]
+pub async fn set_preferred_relay(
+    node: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let node = node.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    log::info!("收到设置偏好中继节点命令: {:?}", node);
+    
+    let config_manager = state.get_config_manager();
+    let network_service = state.get_network_service();
+    
+    config_manager.lock().await.set_preferred_relay(node.clone()).await.map_err(|e| {
+        log::error!("保存偏好中继节点失败: {}", e);
+        CommandError::from(e)
+    })?;
+    network_service.lock().await.set_preferred_relay(node);
+    Ok(())
+}
+
+/// 获取当前大厅 HTTP 服务的 TLS 证书指纹（未启用 TLS 时返回 None）
+#[tauri::command]
+pub async fn get_http_tls_fingerprint() -> Result<Option<String>, CommandError> {
+    Ok(crate::modules::http_tls::active_fingerprint())
+}
+
+/// 静音或取消静音指定玩家，并记住静音状态供之后的会话恢复
+/// 
+/// # 参数
+/// * `player_id` - 玩家 ID
+/// * `muted` - true=静音，false=取消静音
+/// * `player_name` - 玩家昵称（按昵称记住静音时使用）
+/// 
+/// # 返回
+/// * `Ok(())` - 操作成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn mute_player(
+    player_id: String,
+    muted: bool,
+    player_name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到静音玩家命令: player_id={}, muted={}", player_id, muted);
+    
+    let voice_service = state.get_voice_service();
+    let config_manager = state.get_config_manager();
+    
+    let result = voice_service.lock().await.mute_player(&player_id, muted).await;
+    if let Err(e) = result {
+        log::error!("更新玩家静音状态失败: {}", e);
+        return Err(e.into());
+    }
+    log::info!("玩家 {} 静音状态已更新: {}", player_id, muted);
+    
+    // 记录失败不影响本次静音
+    let result = config_manager
+        .lock()
+        .await
+        .record_player_mute(&player_id, player_name.as_deref(), muted)
+        .await;
+    if let Err(e) = result {
+        log::warn!("保存玩家静音记录失败: {}", e);
+    }
+    Ok(())
+}
+
+/// 获取跨会话记住的静音玩家
+/// 
+/// # 返回
+/// * `Ok(PersistedMutes)` - 记住的玩家 ID / 昵称及是否按昵称记住
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_persisted_mutes(
+    state: State<'_, AppState>,
+) -> Result<crate::modules::config_manager::PersistedMutes, CommandError> {
+    let config_manager = state.get_config_manager();
+    
+    let config_mgr = config_manager.lock().await;
+    Ok(config_mgr.get_config().persisted_mutes.clone().unwrap_or_default())
+}
+
+/// 清空跨会话记住的静音玩家
+/// 
+/// # 返回
+/// * `Ok(())` - 清空成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn clear_persisted_mutes(state: State<'_, AppState>) -> Result<(), CommandError> {
+    log::info!("收到清空静音记录命令");
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.clear_persisted_mutes().await.map_err(|e| {
+        log::error!("清空静音记录失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置是否按昵称记住静音（玩家 ID 每次启动都会变化）
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_remember_mutes_by_name(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置按昵称记住静音命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_remember_mutes_by_name(enabled).await.map_err(|e| {
+        log::error!("保存按昵称记住静音设置失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置指定玩家的音量增益
+/// 
+/// # 参数
+/// * `player_id` - 玩家 ID
+/// * `volume` - 音量增益（0.0-2.0，即 0%-200%，0 等价于静音）
+/// 
+/// # 返回
+/// * `Ok(f32)` - 截断后实际生效的音量增益
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_player_volume(
+    player_id: String,
+    volume: f32,
+    state: State<'_, AppState>,
+) -> Result<f32, CommandError> {
+    log::info!("收到设置玩家音量命令: player_id={}, volume={}", player_id, volume);
+    
+    let voice_service = state.get_voice_service();
+    let voice_svc = voice_service.lock().await;
+    
+    voice_svc.set_player_volume(&player_id, volume).await.map_err(|e| {
+        log::error!("设置玩家音量失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 获取指定玩家的音量增益
+/// 
+/// # 参数
+/// * `player_id` - 玩家 ID
+/// 
+/// # 返回
+/// * `Ok(f32)` - 音量增益（未设置时为 1.0）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_player_volume(
+    player_id: String,
+    state: State<'_, AppState>,
+) -> Result<f32, CommandError> {
+    let voice_service = state.get_voice_service();
+    let voice_svc = voice_service.lock().await;
+    
+    Ok(voice_svc.get_player_volume(&player_id).await)
+}
+
+/// 全局静音或取消静音所有玩家
+/// 
+/// # 参数
+/// * `muted` - true=静音所有玩家，false=取消静音所有玩家
+/// 
+/// # 返回
+/// * `Ok(())` - 操作成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn mute_all(
+    muted: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("收到全局静音命令: muted={}", muted);
+    
+    let voice_service = state.get_voice_service();
+    let voice_svc = voice_service.lock().await;
+    
+    match voice_svc.mute_all(muted).await {
+        Ok(_) => {
+            log::info!("全局静音状态已更新: {}", muted);
+            crate::modules::voice_feedback::announce(&app, VoiceCue::speaker(muted));
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("更新全局静音状态失败: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// ==================== 配置管理命令 ====================
+
+/// 获取用户配置
+/// 
+/// # 返回
+/// * `Ok(UserConfig)` - 用户配置
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_config(state: State<'_, AppState>) -> Result<UserConfig, CommandError> {
+    log::info!("收到获取配置命令");
+    
+    let config_manager = state.get_config_manager();
+    let config_mgr = config_manager.lock().await;
+    
+    let config = config_mgr.get_config_clone();
+    
+    log::debug!("返回配置: {:?}", config);
+    
+    Ok(config)
+}
+
+/// 获取用户配置的 schema（各字段的名称、类型、默认值、范围/选项与分组），供前端自动渲染设置界面
+/// 
+/// # 返回
+/// * `Ok(Vec<ConfigFieldSchema>)` - 字段元数据列表（按展示顺序）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_config_schema() -> Result<Vec<ConfigFieldSchema>, CommandError> {
+    Ok(crate::modules::config_schema::config_schema())
+}
+
+/// 更换麦克风与全局静音快捷键
+/// 
+/// 先注销旧快捷键再注册新快捷键，新快捷键被其它程序占用时恢复旧绑定并返回错误，成功后保存到配置
+/// 
+/// # 参数
+/// * `mic_key` - 麦克风开关快捷键（tauri 格式，如 `Ctrl+M`）
+/// * `mute_key` - 全局静音快捷键
+/// 
+/// # 返回
+/// * `Ok(())` - 更换成功
+/// * `Err(CommandError)` - 快捷键无效或注册冲突
+#[tauri::command]
+pub async fn update_hotkeys(
+    mic_key: String,
+    mute_key: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到更换快捷键请求: 麦克风={}, 全局静音={}", mic_key, mute_key);
+
+    crate::modules::hotkeys::update_hotkeys(&app_handle, state.get_voice_service(), &mic_key, &mute_key)
+        .map_err(CommandError::from)?;
+
+    let config_manager = state.get_config_manager();
+    let mut config_manager = config_manager.lock().await;
+    config_manager.set_mic_hotkey(mic_key.trim().to_string()).await.map_err(CommandError::from)?;
+    config_manager.set_global_mute_hotkey(mute_key.trim().to_string()).await.map_err(CommandError::from)?;
+    Ok(())
+}
+
+/// 更新用户配置
+/// 
+/// # 参数
+/// * `config` - 新的用户配置
+/// 
+/// # 返回
+/// * `Ok(())` - 更新成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn update_config(
+    config: UserConfig,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到更新配置命令");
+    
+    let config_manager = state.get_config_manager();
     let mut config_mgr = config_manager.lock().await;
     
     match config_mgr.update_config(|cfg| {
@@ -521,7 +2329,7 @@ pub async fn update_config(
         }
         Err(e) => {
             log::error!("更新配置失败: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -533,16 +2341,15 @@ pub async fn update_config(
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn save_opacity(
     opacity: f64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("收到保存透明度命令: {}", opacity);
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut config_mgr = config_manager.lock().await;
     
     match config_mgr.set_opacity(opacity).await {
@@ -552,7 +2359,7 @@ pub async fn save_opacity(
         }
         Err(e) => {
             log::error!("保存透明度失败: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
@@ -563,13 +2370,12 @@ pub async fn save_opacity(
 /// 
 /// # 返回
 /// * `Ok(Vec<AudioDevice>)` - 音频设备列表
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_audio_devices(state: State<'_, AppState>) -> Result<Vec<AudioDevice>, String> {
+pub async fn get_audio_devices(state: State<'_, AppState>) -> Result<Vec<AudioDevice>, CommandError> {
     log::info!("收到获取音频设备命令");
     
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
+    let voice_service = state.get_voice_service();
     let voice_svc = voice_service.lock().await;
     
     let devices = voice_svc.get_audio_devices().await;
@@ -579,13 +2385,63 @@ pub async fn get_audio_devices(state: State<'_, AppState>) -> Result<Vec<AudioDe
     Ok(devices)
 }
 
+/// 选择麦克风设备（保存到配置，下次启动时恢复）
+/// 
+/// # 参数
+/// * `device_id` - 设备 ID（来自 get_audio_devices）
+/// 
+/// # 返回
+/// * `Ok(())` - 切换成功
+/// * `Err(CommandError)` - 设备不存在或保存失败
+#[tauri::command]
+pub async fn select_microphone(
+    device_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到选择麦克风命令: {}", device_id);
+    
+    state.get_voice_service().lock().await.select_microphone(&device_id).await?;
+    
+    let config_manager = state.get_config_manager();
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_audio_device_id(device_id).await.map_err(|e| {
+        log::error!("保存麦克风设备失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 选择扬声器设备（保存到配置，下次启动时恢复）
+/// 
+/// # 参数
+/// * `device_id` - 设备 ID（来自 get_audio_devices）
+/// 
+/// # 返回
+/// * `Ok(())` - 切换成功
+/// * `Err(CommandError)` - 设备不存在或保存失败
+#[tauri::command]
+pub async fn select_speaker(
+    device_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到选择扬声器命令: {}", device_id);
+    
+    state.get_voice_service().lock().await.select_speaker(&device_id).await?;
+    
+    let config_manager = state.get_config_manager();
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_speaker_device_id(device_id).await.map_err(|e| {
+        log::error!("保存扬声器设备失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
 /// 获取当前应用状态
 /// 
 /// # 返回
 /// * `Ok(String)` - 应用状态的字符串表示
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_app_state(state: State<'_, AppState>) -> Result<String, String> {
+pub async fn get_app_state(state: State<'_, AppState>) -> Result<String, CommandError> {
     let core = state.core.lock().await;
     let app_state = core.get_state().await;
     Ok(format!("{:?}", app_state))
@@ -595,13 +2451,12 @@ pub async fn get_app_state(state: State<'_, AppState>) -> Result<String, String>
 /// 
 /// # 返回
 /// * `Ok(Option<Lobby>)` - 当前大厅信息，如果未加入大厅则返回 None
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_current_lobby(state: State<'_, AppState>) -> Result<Option<Lobby>, String> {
+pub async fn get_current_lobby(state: State<'_, AppState>) -> Result<Option<Lobby>, CommandError> {
     log::info!("收到获取当前大厅命令");
     
-    let core = state.core.lock().await;
-    let lobby_manager = core.get_lobby_manager();
+    let lobby_manager = state.get_lobby_manager();
     let lobby_mgr = lobby_manager.lock().await;
     
     let lobby = lobby_mgr.get_current_lobby().cloned();
@@ -613,31 +2468,69 @@ pub async fn get_current_lobby(state: State<'_, AppState>) -> Result<Option<Lobb
 /// 
 /// # 返回
 /// * `Ok(Vec<Player>)` - 玩家列表
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_players(state: State<'_, AppState>) -> Result<Vec<Player>, String> {
+pub async fn get_players(state: State<'_, AppState>) -> Result<Vec<Player>, CommandError> {
     log::info!("收到获取玩家列表命令");
     
-    let core = state.core.lock().await;
-    let lobby_manager = core.get_lobby_manager();
-    let lobby_mgr = lobby_manager.lock().await;
+    // 读取玩家列表排序偏好（仅影响展示顺序）
+    let sort_order = state.get_config_manager().lock().await.get_config().player_sort_order;
     
-    let players = lobby_mgr.get_players();
+    let lobby_manager = state.get_lobby_manager();
+    let voice_service = state.get_voice_service();
+    
+    let mut players = lobby_manager.lock().await.get_players();
+    
+    // 附加当前说话状态与本次会话的说话时长、发言次数（按说话排序时依赖这些字段）
+    let voice_svc = voice_service.lock().await;
+    let activity = voice_svc.get_speaking_activity().await;
+    let speaking = voice_svc.get_speaking_players().await;
+    drop(voice_svc);
+    for player in players.iter_mut() {
+        player.is_speaking = speaking.contains(&player.id);
+        if let Some(stats) = activity.get(&player.id) {
+            player.speaking_seconds = Some(stats.speaking_seconds);
+            player.message_count = Some(stats.message_count);
+        }
+    }
+    if let Some(order) = sort_order {
+        crate::modules::lobby_manager::sort_players(&mut players, order);
+    }
     
     log::info!("返回 {} 个玩家", players.len());
     
     Ok(players)
 }
 
+/// 设置玩家列表排序偏好
+/// 
+/// # 参数
+/// * `order` - 排序偏好（name/join_order/speaking/ready），`None` 表示恢复默认
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_player_sort_order(
+    order: Option<PlayerSortOrder>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("设置玩家列表排序偏好: {:?}", order);
+    
+    let config_manager = state.get_config_manager();
+    let mut config_mgr = config_manager.lock().await;
+    
+    config_mgr.set_player_sort_order(order).await.map_err(CommandError::from)
+}
+
 /// 获取麦克风状态
 /// 
 /// # 返回
 /// * `Ok(bool)` - 麦克风状态（true=开启，false=关闭）
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_mic_status(state: State<'_, AppState>) -> Result<bool, String> {
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
+pub async fn get_mic_status(state: State<'_, AppState>) -> Result<bool, CommandError> {
+    let voice_service = state.get_voice_service();
     let voice_svc = voice_service.lock().await;
     
     let status = voice_svc.is_mic_enabled();
@@ -649,11 +2542,10 @@ pub async fn get_mic_status(state: State<'_, AppState>) -> Result<bool, String>
 /// 
 /// # 返回
 /// * `Ok(bool)` - 全局静音状态（true=静音，false=未静音）
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_global_mute_status(state: State<'_, AppState>) -> Result<bool, String> {
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
+pub async fn get_global_mute_status(state: State<'_, AppState>) -> Result<bool, CommandError> {
+    let voice_service = state.get_voice_service();
     let voice_svc = voice_service.lock().await;
     
     let status = voice_svc.is_global_muted();
@@ -668,14 +2560,13 @@ pub async fn get_global_mute_status(state: State<'_, AppState>) -> Result<bool,
 /// 
 /// # 返回
 /// * `Ok(bool)` - 是否被静音（true=静音，false=未静音）
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn is_player_muted(
     player_id: String,
     state: State<'_, AppState>,
-) -> Result<bool, String> {
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
+) -> Result<bool, CommandError> {
+    let voice_service = state.get_voice_service();
     let voice_svc = voice_service.lock().await;
     
     let is_muted = voice_svc.is_player_muted(&player_id).await;
@@ -693,7 +2584,7 @@ pub async fn is_player_muted(
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn save_window_position(
     x: i32,
@@ -701,22 +2592,24 @@ pub async fn save_window_position(
     width: u32,
     height: u32,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+    window: tauri::Window,
+) -> Result<(), CommandError> {
     use crate::modules::config_manager::WindowPosition;
     
-    log::info!("保存窗口位置: x={}, y={}, width={}, height={}", x, y, width, height);
+    // 记录窗口所在显示器，多屏时下次启动可校验该显示器是否仍在
+    let monitor = window.current_monitor().ok().flatten().and_then(|m| m.name().cloned());
+    log::info!("保存窗口位置: x={}, y={}, width={}, height={}, monitor={:?}", x, y, width, height, monitor);
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     // 检查是否启用了记住窗口位置
     let remember = cfg_mgr.get_config().remember_window_position.unwrap_or(false);
     
     if remember {
-        let position = WindowPosition { x, y, width, height };
+        let position = WindowPosition { x, y, width, height, monitor };
         cfg_mgr.set_window_position(position).await
-            .map_err(|e| format!("保存窗口位置失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::Config, format!("保存窗口位置失败: {}", e)))?;
         log::info!("窗口位置已保存");
     } else {
         log::debug!("未启用记住窗口位置，跳过保存");
@@ -729,12 +2622,12 @@ pub async fn save_window_position(
 /// 
 /// # 返回
 /// * `Ok(())` - 退出成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn exit_app(
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("收到退出应用命令");
     
     // 先清理资源
@@ -768,6 +2661,7 @@ pub async fn exit_app(
     log::info!("资源清理完成，正在退出应用...");
     
     // 退出应用
+    crate::modules::tray::mark_quitting();
     app.exit(0);
     
     Ok(())
@@ -777,18 +2671,17 @@ pub async fn exit_app(
 /// 
 /// # 返回
 /// * `Ok(String)` - 连接状态的 JSON 字符串
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_network_status(state: State<'_, AppState>) -> Result<String, String> {
-    let core = state.core.lock().await;
-    let network_service = core.get_network_service();
+pub async fn get_network_status(state: State<'_, AppState>) -> Result<String, CommandError> {
+    let network_service = state.get_network_service();
     let network_svc = network_service.lock().await;
     
     let status = network_svc.check_connection().await;
     
     match serde_json::to_string(&status) {
         Ok(json) => Ok(json),
-        Err(e) => Err(format!("序列化连接状态失败: {}", e)),
+        Err(e) => Err(CommandError::new(ErrorCode::Serialization, format!("序列化连接状态失败: {}", e))),
     }
 }
 
@@ -796,11 +2689,10 @@ pub async fn get_network_status(state: State<'_, AppState>) -> Result<String, St
 /// 
 /// # 返回
 /// * `Ok(Option<String>)` - 虚拟 IP 地址，如果未连接则返回 None
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_virtual_ip(state: State<'_, AppState>) -> Result<Option<String>, String> {
-    let core = state.core.lock().await;
-    let network_service = core.get_network_service();
+pub async fn get_virtual_ip(state: State<'_, AppState>) -> Result<Option<String>, CommandError> {
+    let network_service = state.get_network_service();
     let network_svc = network_service.lock().await;
     
     let ip = network_svc.get_virtual_ip().await;
@@ -828,27 +2720,25 @@ pub struct PeerConnType {
     pub loss_rate: Option<u8>,
 }
 
-/// 查询大厅内各对等节点的连接类型（P2P 直连 / 中继）。
-/// 通过 easytier-cli 连接 easytier-core 的 RPC 端口获取 peer 路由，cost==1 即 P2P 直连。
-#[tauri::command]
-pub async fn get_peer_connection_types(
-    app_handle: tauri::AppHandle,
-    state: State<'_, AppState>,
-) -> Result<Vec<PeerConnType>, String> {
+/// 通过 easytier-cli 连接 easytier-core 的 RPC 端口查询 peer 列表（JSON）。
+/// 未连接或 CLI 执行失败时返回 `Value::Null`。
+async fn query_easytier_peers(
+    app_handle: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<serde_json::Value, CommandError> {
     // 取当前 RPC 端口
     let rpc_port = {
-        let core = state.core.lock().await;
-        let ns = core.get_network_service();
+        let ns = state.get_network_service();
         let svc = ns.lock().await;
         svc.get_rpc_port().await
     };
     let port = match rpc_port {
         Some(p) => p,
-        None => return Ok(vec![]),
+        None => return Ok(serde_json::Value::Null),
     };
 
-    let cli_path = crate::modules::resource_manager::ResourceManager::get_easytier_cli_path(&app_handle)
-        .map_err(|e| format!("获取 easytier-cli 失败: {}", e))?;
+    let cli_path = crate::modules::resource_manager::ResourceManager::get_easytier_cli_path(app_handle)
+        .map_err(|e| CommandError::new(ErrorCode::Process, format!("获取 easytier-cli 失败: {}", e)))?;
 
     let mut cmd = tokio::process::Command::new(&cli_path);
     cmd.args(["-p", &format!("127.0.0.1:{}", port), "-o", "json", "peer"]);
@@ -861,13 +2751,23 @@ pub async fn get_peer_connection_types(
 
     let output = tokio::time::timeout(std::time::Duration::from_secs(5), cmd.output())
         .await
-        .map_err(|_| "easytier-cli 查询超时".to_string())?
-        .map_err(|e| format!("运行 easytier-cli 失败: {}", e))?;
+        .map_err(|_| CommandError::new(ErrorCode::Process, "easytier-cli 查询超时"))?
+        .map_err(|e| CommandError::new(ErrorCode::Process, format!("运行 easytier-cli 失败: {}", e)))?;
     if !output.status.success() {
-        return Ok(vec![]);
+        return Ok(serde_json::Value::Null);
     }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap_or(serde_json::Value::Null);
+    Ok(serde_json::from_str(stdout.trim()).unwrap_or(serde_json::Value::Null))
+}
+
+/// 查询大厅内各对等节点的连接类型（P2P 直连 / 中继）。
+/// 通过 easytier-cli 连接 easytier-core 的 RPC 端口获取 peer 路由，cost==1 即 P2P 直连。
+#[tauri::command]
+pub async fn get_peer_connection_types(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<PeerConnType>, CommandError> {
+    let parsed = query_easytier_peers(&app_handle, &state).await?;
 
     // 递归收集所有含 ipv4 + cost 的对象（兼容单/多实例的 JSON 结构）
     let mut result: Vec<PeerConnType> = Vec::new();
@@ -912,9 +2812,130 @@ pub async fn get_peer_connection_types(
     // 去重（同一 IP 保留首个）
     let mut seen = std::collections::HashSet::new();
     result.retain(|e| seen.insert(e.ip.clone()));
+
+    let newly_relayed = crate::modules::relay_watch::relay_watch()
+        .lock()
+        .update(result.iter().map(|c| (c.ip.as_str(), c.conn_type == "relay")));
+    if !newly_relayed.is_empty() {
+        notify_connection_relayed(&app_handle, &state, newly_relayed).await;
+    }
     Ok(result)
 }
 
+/// 提示与这些 peer 的连接已回退为中继（P2P 打洞失败）
+async fn notify_connection_relayed(app_handle: &tauri::AppHandle, state: &State<'_, AppState>, ips: Vec<String>) {
+    use tauri::Emitter;
+    use crate::modules::relay_watch::{RelayedPeer, CONNECTION_RELAYED_EVENT};
+
+    let p2p_signaling = state.get_p2p_signaling();
+    let network_service = state.get_network_service();
+
+    let preferred_relay = network_service.lock().await.preferred_relay();
+    let peers = p2p_signaling.lock().await.get_peers().await;
+    for ip in ips {
+        let player_name = peers.iter().find(|p| p.addr.ip().to_string() == ip).map(|p| p.player_name.clone());
+        log::warn!("与 {} ({:?}) 的 P2P 直连失败，已走中继", ip, player_name);
+        let event = RelayedPeer { ip, player_name, preferred_relay: preferred_relay.clone() };
+        if let Err(e) = app_handle.emit(CONNECTION_RELAYED_EVENT, &event) {
+            log::warn!("发送中继连接事件失败: {}", e);
+        }
+    }
+}
+
+/// 综合各 peer 的延迟、丢包与连接类型计算房间健康分
+async fn collect_room_health(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::modules::room_health::RoomHealth, CommandError> {
+    use crate::modules::room_health::{room_health, PeerQuality};
+
+    let conns = get_peer_connection_types(app_handle, state.clone()).await?;
+    let p2p_signaling = state.get_p2p_signaling();
+    let names: std::collections::HashMap<String, String> = p2p_signaling
+        .lock()
+        .await
+        .get_peers()
+        .await
+        .into_iter()
+        .map(|p| (p.addr.ip().to_string(), p.player_name))
+        .collect();
+
+    let peers: Vec<PeerQuality> = conns
+        .into_iter()
+        .map(|c| PeerQuality {
+            player_name: names.get(&c.ip).cloned(),
+            relay: c.conn_type == "relay",
+            latency_ms: c.latency_ms,
+            loss_rate: c.loss_rate.unwrap_or(0),
+            ip: c.ip,
+        })
+        .collect();
+    Ok(room_health(&peers))
+}
+
+/// 获取房间健康分（0–100），分数偏低时附带主要拖后腿的 peer
+/// 
+/// # 返回
+/// * `Ok(RoomHealth)` - 房间分、各 peer 评分与拖后腿的 peer
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_room_health_score(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<crate::modules::room_health::RoomHealth, CommandError> {
+    collect_room_health(app_handle, state).await
+}
+
+/// 获取 EasyTier 路由中所有节点的虚拟 IP（不含本机）
+/// 
+/// # 返回
+/// * `Ok(Vec<String>)` - 节点虚拟 IP 列表（未连接时为空）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_network_peers(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    let parsed = query_easytier_peers(&app_handle, &state).await?;
+    let mut ips = Vec::new();
+    crate::modules::network_self_check::collect_peer_ips(&parsed, &mut ips);
+    let mut seen = std::collections::HashSet::new();
+    ips.retain(|ip| seen.insert(ip.clone()));
+    Ok(ips)
+}
+
+/// 对齐"EasyTier 看到的节点"与"P2P 发现到的玩家"，标记网络在线但尚未完成玩家握手的节点，
+/// 用于诊断"能 ping 通但看不到玩家"的问题
+#[tauri::command]
+pub async fn get_peer_alignment(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<PeerAlignment>, CommandError> {
+    let network_peers = get_network_peers(app_handle, state.clone()).await?;
+    
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    let players: Vec<(String, String, String)> = p2p_signaling
+        .lock()
+        .await
+        .get_peers()
+        .await
+        .into_iter()
+        .map(|p| (p.player_id, p.player_name, p.addr.ip().to_string()))
+        .collect();
+    Ok(network_diagnostics::align_peers(&network_peers, &players))
+}
+
+/// 查询虚拟网流量统计（总流量、各 peer 流量与本次大厅累计流量），供前端定时轮询绘制流量曲线
+#[tauri::command]
+pub async fn get_traffic_stats(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<TrafficStats, CommandError> {
+    let parsed = query_easytier_peers(&app_handle, &state).await?;
+    Ok(crate::modules::traffic_stats::collect_stats(&parsed))
+}
+
 // ==================== 窗口控制命令 ====================
 
 /// 设置窗口置顶状态
@@ -924,12 +2945,12 @@ pub async fn get_peer_connection_types(
 /// 
 /// # 返回
 /// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn set_always_on_top(
     always_on_top: bool,
     window: tauri::Window,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("设置窗口置顶状态: {}", always_on_top);
     
     window
@@ -946,12 +2967,12 @@ pub async fn set_always_on_top(
 /// 
 /// # 返回
 /// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn toggle_mini_mode(
     mini_mode: bool,
     window: tauri::Window,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("切换迷你模式: {}", mini_mode);
     
     if mini_mode {
@@ -993,28 +3014,27 @@ pub async fn toggle_mini_mode(
 
 /// 设置窗口透明度
 /// 
+/// macOS / Linux (X11) 设置系统窗口透明度；Windows 与不支持的环境返回 `css`，
+/// 由前端以背景 alpha 实现（见 `window_opacity` 模块说明）。
+/// 
 /// # 参数
-/// * `opacity` - 透明度值（0.0-1.0）
+/// * `opacity` - 透明度值（0.3-1.0，超出范围时截断）
 /// 
 /// # 返回
-/// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
+/// * `Ok(WindowOpacityMode)` - 实际采用的实现方式
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn set_window_opacity(
     opacity: f64,
     window: tauri::Window,
-) -> Result<(), String> {
-    let clamped_opacity = opacity.max(0.3).min(1.0);
-
-    // 注意：不再使用 WS_EX_LAYERED + SetLayeredWindowAttributes(LWA_ALPHA)。
-    // 该方式会用“整窗统一 alpha”覆盖 Tauri 的逐像素真透明（transparent:true），
-    // 导致窗口无法真正透明（圆角/留白处看不到桌面）。
-    // 透明度改由前端 CSS（.mini-window 背景 rgba 的 alpha）实现，可保留真透明。
-    // 这里仅广播事件，保持兼容。
+) -> Result<WindowOpacityMode, CommandError> {
+    let clamped_opacity = clamp_window_opacity(opacity);
+    let mode = apply_window_opacity(&window, clamped_opacity).await;
+
     window
         .emit("opacity-changed", clamped_opacity)
         .map_err(|e| format!("发送透明度事件失败: {}", e))?;
-    Ok(())
+    Ok(mode)
 }
 
 
@@ -1027,16 +3047,15 @@ pub async fn set_window_opacity(
 /// 
 /// # 返回
 /// * `Ok(())` - 发送成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn send_signaling_message(
     message: serde_json::Value,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("收到信令消息: {:?}", message);
     
-    let core = state.core.lock().await;
-    let p2p_signaling = core.get_p2p_signaling();
+    let p2p_signaling = state.get_p2p_signaling();
     let p2p_svc = p2p_signaling.lock().await;
     
     // 解析信令消息
@@ -1058,23 +3077,78 @@ pub async fn send_signaling_message(
             crate::modules::p2p_signaling::P2PMessage::IceCandidate { from, candidate }
         }
         _ => {
-            return Err("未知的信令消息类型".to_string());
+            return Err(CommandError::validation("未知的信令消息类型"));
         }
     };
     
     // 发送消息
     if let Some(target) = to {
         p2p_svc.send_to_player(target, p2p_message).await
-            .map_err(|e| e.to_string())?;
+            .map_err(CommandError::from)?;
     } else {
         p2p_svc.broadcast_to_all(p2p_message).await
-            .map_err(|e| e.to_string())?;
+            .map_err(CommandError::from)?;
     }
     
     log::debug!("信令消息已处理");
     Ok(())
 }
 
+/// 用大厅密钥加密语音信令字段（SDP / ICE candidate）
+/// 
+/// 发送方、接收方与消息类型作为附加认证数据，密文不能被改投或冒充其它消息。
+/// 
+/// # 参数
+/// * `kind` - 消息类型（offer / answer / ice-candidate）
+/// * `from` - 发送方玩家ID
+/// * `to` - 接收方玩家ID
+/// * `value` - 字段明文
+/// 
+/// # 返回
+/// * `Ok(String)` - 加密后的字段（未进入加密大厅时原样返回）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn seal_voice_signaling(
+    kind: String,
+    from: String,
+    to: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<String, CommandError> {
+    let p2p_signaling = state.get_p2p_signaling();
+    let aad = crate::modules::signaling_crypto::signaling_field_aad(&kind, &from, &to);
+    let sealed = p2p_signaling.lock().await.seal_signaling_field(&value, &aad).await;
+    Ok(sealed)
+}
+
+/// 解密收到的语音信令字段
+/// 
+/// # 参数
+/// * `kind` - 消息类型（offer / answer / ice-candidate）
+/// * `from` - 发送方玩家ID
+/// * `to` - 接收方玩家ID（本机）
+/// * `value` - 收到的字段
+/// 
+/// # 返回
+/// * `Ok(String)` - 字段明文
+/// * `Err(CommandError)` - 密钥不匹配、被篡改或未加密，应丢弃该消息
+#[tauri::command]
+pub async fn open_voice_signaling(
+    kind: String,
+    from: String,
+    to: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<String, CommandError> {
+    let p2p_signaling = state.get_p2p_signaling();
+    let aad = crate::modules::signaling_crypto::signaling_field_aad(&kind, &from, &to);
+    let result = p2p_signaling.lock().await.open_signaling_field(&value, &aad).await;
+    result.map_err(|e| {
+        log::warn!("丢弃来自 {} 的 {} 信令: {}", from, kind, e);
+        CommandError::from(e)
+    })
+}
+
 /// 广播状态更新
 /// 
 /// # 参数
@@ -1083,17 +3157,16 @@ pub async fn send_signaling_message(
 /// 
 /// # 返回
 /// * `Ok(())` - 广播成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn broadcast_status_update(
     player_id: String,
     mic_enabled: bool,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("广播状态更新: player={}, mic={}", player_id, mic_enabled);
     
-    let core = state.core.lock().await;
-    let p2p_signaling = core.get_p2p_signaling();
+    let p2p_signaling = state.get_p2p_signaling();
     let p2p_svc = p2p_signaling.lock().await;
     
     // 创建状态更新消息
@@ -1104,12 +3177,213 @@ pub async fn broadcast_status_update(
     
     // 广播消息
     p2p_svc.broadcast_to_all(message).await
-        .map_err(|e| e.to_string())?;
+        .map_err(CommandError::from)?;
     
     log::debug!("状态更新已广播");
     Ok(())
 }
 
+/// 设置本机玩家的准备状态
+/// 
+/// 更新本地玩家列表后广播给大厅内其他玩家，并通知本地前端（`player-ready` 事件）
+/// 
+/// # 参数
+/// * `player_id` - 本机玩家ID
+/// * `is_ready` - 是否已准备
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_ready_status(
+    player_id: String,
+    is_ready: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("设置准备状态: player={}, ready={}", player_id, is_ready);
+    
+    state
+        .get_lobby_manager()
+        .lock()
+        .await
+        .update_player_ready_status(&player_id, is_ready)
+        .map_err(CommandError::from)?;
+    
+    let message = crate::modules::p2p_signaling::P2PMessage::ReadyState {
+        player_id: player_id.clone(),
+        is_ready,
+    };
+    state.get_p2p_signaling().lock().await.broadcast_to_all(message).await
+        .map_err(CommandError::from)?;
+    
+    let _ = app.emit("player-ready", serde_json::json!({
+        "playerId": player_id,
+        "isReady": is_ready,
+    }));
+    Ok(())
+}
+
+/// 上报本地麦克风电平（语音活动检测）
+/// 
+/// 开始说话时立即广播 `SpeakingState { is_speaking: true }`；
+/// 停止说话后延迟 300ms 仍保持静音才广播 `is_speaking: false`，避免头像高亮频繁闪烁。
+/// 同时将电平量化为 0-8 级，等级变化且距上次广播不少于 200ms 时广播 `InputLevel`。
+/// 
+/// # 参数
+/// * `player_id` - 本地玩家ID
+/// * `level` - 麦克风线性 RMS（0.0-1.0）
+/// 
+/// # 返回
+/// * `Ok(bool)` - 当前是否处于说话状态
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn report_audio_level(
+    player_id: String,
+    level: f32,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<bool, CommandError> {
+    use crate::modules::voice_service::{SpeakingUpdate, SPEAKING_RELEASE_DELAY};
+    
+    let voice_service = state.get_voice_service();
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    let voice_svc = voice_service.lock().await;
+    let update = voice_svc.update_local_audio_level(&player_id, level).await;
+    let is_speaking = voice_svc.is_player_speaking(&player_id).await;
+    let input_level = voice_svc.next_input_level_broadcast(level).await;
+    drop(voice_svc);
+    
+    // 电平条：量化后限流广播，本地前端同样通过 peer-input-level 更新自己的电平
+    if let Some(input_level) = input_level {
+        let message = crate::modules::p2p_signaling::P2PMessage::InputLevel {
+            player_id: player_id.clone(),
+            level: input_level,
+        };
+        if let Err(e) = p2p_signaling.lock().await.broadcast_to_all(message).await {
+            log::debug!("广播输入电平失败: {}", e);
+        }
+        let _ = app.emit("peer-input-level", serde_json::json!({
+            "playerId": player_id,
+            "level": input_level,
+        }));
+    }
+    
+    match update {
+        SpeakingUpdate::Started | SpeakingUpdate::Stopped => {
+            broadcast_speaking_state(&app, &p2p_signaling, &player_id, update == SpeakingUpdate::Started).await;
+        }
+        SpeakingUpdate::ReleasePending => {
+            // 延迟到期后再检查一次，若期间未再出声则广播停止说话
+            let app = app.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(SPEAKING_RELEASE_DELAY).await;
+                let update = voice_service.lock().await.poll_local_speaking(&player_id).await;
+                if update == SpeakingUpdate::Stopped {
+                    broadcast_speaking_state(&app, &p2p_signaling, &player_id, false).await;
+                }
+            });
+        }
+        SpeakingUpdate::Unchanged => {}
+    }
+    
+    Ok(is_speaking)
+}
+
+/// 广播本地说话状态，并通知本地前端
+async fn broadcast_speaking_state(
+    app: &tauri::AppHandle,
+    p2p_signaling: &Arc<Mutex<crate::modules::p2p_signaling::P2PSignalingService>>,
+    player_id: &str,
+    is_speaking: bool,
+) {
+    let message = crate::modules::p2p_signaling::P2PMessage::SpeakingState {
+        player_id: player_id.to_string(),
+        is_speaking,
+    };
+    if let Err(e) = p2p_signaling.lock().await.broadcast_to_all(message).await {
+        log::warn!("广播说话状态失败: {}", e);
+    }
+    let _ = app.emit("player-speaking", serde_json::json!({
+        "playerId": player_id,
+        "isSpeaking": is_speaking,
+    }));
+}
+
+/// 对聊天消息添加或取消表情回应
+/// 
+/// 本地立即聚合并通知前端，同时广播给大厅内其他玩家
+/// 
+/// # 参数
+/// * `message_id` - 消息ID
+/// * `emoji` - 表情
+/// * `player_id` - 本机玩家ID
+/// * `added` - true 为添加回应，false 为取消回应
+/// 
+/// # 返回
+/// * `Ok(())` - 发送成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn send_message_reaction(
+    message_id: String,
+    emoji: String,
+    player_id: String,
+    added: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    let chat_service = state.get_chat_service();
+    let p2p_signaling = state.get_p2p_signaling();
+    
+    let Some(reactions) = chat_service.lock().await.apply_reaction(&message_id, &emoji, &player_id, added) else {
+        // 重复回应或无效回应，无需广播
+        return Ok(());
+    };
+    let _ = app.emit("message-reaction-update", serde_json::json!({
+        "messageId": message_id,
+        "reactions": reactions,
+    }));
+    
+    let message = crate::modules::p2p_signaling::P2PMessage::MessageReaction {
+        message_id,
+        emoji,
+        player_id,
+        added,
+    };
+    let result = p2p_signaling.lock().await.broadcast_to_all(message).await;
+    result.map_err(|e| {
+        log::warn!("广播表情回应失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 获取正在说话的玩家列表
+/// 
+/// # 返回
+/// * `Ok(Vec<String>)` - 正在说话的玩家ID列表
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_speaking_players(state: State<'_, AppState>) -> Result<Vec<String>, CommandError> {
+    let voice_service = state.get_voice_service();
+    
+    let speaking = voice_service.lock().await.get_speaking_players().await;
+    Ok(speaking)
+}
+
+/// 获取各玩家本次会话的说话时长与发言次数
+/// 
+/// # 返回
+/// * `Ok(HashMap<String, SpeakingActivity>)` - 玩家ID到发言统计的映射
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn get_speaking_activity(state: State<'_, AppState>) -> Result<std::collections::HashMap<String, SpeakingActivity>, CommandError> {
+    let voice_service = state.get_voice_service();
+    
+    let activity = voice_service.lock().await.get_speaking_activity().await;
+    Ok(activity)
+}
+
 /// 发送心跳
 /// 
 /// # 参数
@@ -1118,27 +3392,25 @@ pub async fn broadcast_status_update(
 /// 
 /// # 返回
 /// * `Ok(())` - 发送成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn send_heartbeat(
     player_id: String,
     timestamp: i64,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::debug!("收到心跳: player={}, timestamp={}", player_id, timestamp);
     
-    let core = state.core.lock().await;
-    let voice_service = core.get_voice_service();
+    let voice_service = state.get_voice_service();
     let voice_svc = voice_service.lock().await;
     
     voice_svc.send_heartbeat(&player_id).await
-        .map_err(|e| e.to_string())?;
+        .map_err(CommandError::from)?;
     
     log::debug!("心跳已发送");
     Ok(())
 }
 
-
 // ==================== 网络管理命令 ====================
 
 /// 强制停止所有EasyTier进程
@@ -1147,13 +3419,12 @@ pub async fn send_heartbeat(
 /// 
 /// # 返回
 /// * `Ok(())` - 停止成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn force_stop_easytier(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn force_stop_easytier(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("🔧 收到强制停止EasyTier进程命令");
     
-    let core = state.core.lock().await;
-    let network_service = core.get_network_service();
+    let network_service = state.get_network_service();
     let network_svc = network_service.lock().await;
     
     // 调用NetworkService的stop_easytier方法
@@ -1167,47 +3438,22 @@ pub async fn force_stop_easytier(state: State<'_, AppState>) -> Result<(), Strin
             log::info!("✅ EasyTier进程已强制停止并清理完成");
             Ok(())
         }
-        Err(e) => {
-            log::warn!("⚠️ 强制停止EasyTier进程时出现警告: {}", e);
-            // 即使出现错误，也返回成功，因为可能只是没有进程在运行
-            Ok(())
-        }
-    }
-}
-
-/// 【#4】取消创建/加入大厅过程中的连接（强制手动停止）
-///
-/// 关键点：create_lobby/join_lobby 在 start_easytier 的等待期间会一直持有
-/// network_service 锁，因此不能通过会抢同一把锁的 force_stop_easytier 来取消。
-/// 这里直接用 taskkill 终止 easytier-core 进程（不加任何锁），进程退出后
-/// start_easytier 的进程监控任务会把 is_running 置为 false，等待循环随即
-/// 返回错误，create_lobby/join_lobby 得以结束并释放锁。
-#[tauri::command]
-pub async fn cancel_lobby_connecting() -> Result<(), String> {
-    log::info!("🛑 收到取消连接命令，直接终止 easytier-core 进程以解除阻塞");
-
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        for image in ["easytier-core.exe", "easytier-cli.exe"] {
-            let _ = tokio::process::Command::new("taskkill")
-                .args(["/F", "/IM", image])
-                .creation_flags(CREATE_NO_WINDOW)
-                .output()
-                .await;
+        Err(e) => {
+            log::warn!("⚠️ 强制停止EasyTier进程时出现警告: {}", e);
+            // 即使出现错误，也返回成功，因为可能只是没有进程在运行
+            Ok(())
         }
     }
+}
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        let _ = tokio::process::Command::new("pkill")
-            .args(["-9", "-f", "easytier-core"])
-            .output()
-            .await;
-    }
-
-    log::info!("✅ 已发送终止信号给 easytier-core 进程");
+/// 取消正在进行的连接
+///
+/// 通知 start_easytier 的等待循环停止 EasyTier 进程并以「已取消连接」返回，
+/// create_lobby/join_lobby 随即结束并释放锁；不在连接中时无效果。
+#[tauri::command]
+pub async fn cancel_connecting() -> Result<(), CommandError> {
+    log::info!("🛑 收到取消连接命令");
+    crate::modules::network_service::request_connect_cancel();
     Ok(())
 }
 
@@ -1219,7 +3465,7 @@ pub async fn cancel_lobby_connecting() -> Result<(), String> {
 pub async fn download_and_run_installer(
     url: String,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use tauri::Emitter;
     use tokio::io::AsyncWriteExt;
     use futures_util::StreamExt;
@@ -1233,16 +3479,16 @@ pub async fn download_and_run_installer(
     let client = reqwest::Client::builder()
         .danger_accept_invalid_certs(false)
         .build()
-        .map_err(|e| format!("创建下载客户端失败: {}", e))?;
+        .map_err(|e| CommandError::network(format!("创建下载客户端失败: {}", e)))?;
 
     let resp = client
         .get(&url)
         .send()
         .await
-        .map_err(|e| format!("请求下载失败: {}", e))?;
+        .map_err(|e| CommandError::network(format!("请求下载失败: {}", e)))?;
 
     if !resp.status().is_success() {
-        return Err(format!("下载失败，服务器返回状态: {}", resp.status()));
+        return Err(CommandError::network(format!("下载失败，服务器返回状态: {}", resp.status())));
     }
 
     let total = resp.content_length().unwrap_or(0);
@@ -1250,15 +3496,15 @@ pub async fn download_and_run_installer(
 
     let mut file = tokio::fs::File::create(&tmp_path)
         .await
-        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("创建临时文件失败: {}", e)))?;
 
     let mut stream = resp.bytes_stream();
     let mut last_emit = std::time::Instant::now();
     while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("下载数据出错: {}", e))?;
+        let chunk = chunk.map_err(|e| CommandError::network(format!("下载数据出错: {}", e)))?;
         file.write_all(&chunk)
             .await
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("写入文件失败: {}", e)))?;
         downloaded += chunk.len() as u64;
 
         // 限制事件频率，避免过于频繁
@@ -1270,7 +3516,7 @@ pub async fn download_and_run_installer(
             last_emit = std::time::Instant::now();
         }
     }
-    file.flush().await.map_err(|e| format!("刷新文件失败: {}", e))?;
+    file.flush().await.map_err(|e| CommandError::io(format!("刷新文件失败: {}", e)))?;
     drop(file);
 
     // 最终进度
@@ -1286,7 +3532,7 @@ pub async fn download_and_run_installer(
     {
         std::process::Command::new(&tmp_path)
             .spawn()
-            .map_err(|e| format!("启动安装包失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::Process, format!("启动安装包失败: {}", e)))?;
     }
 
     // 稍作延迟后退出应用，让安装程序接管覆盖文件
@@ -1304,118 +3550,54 @@ pub async fn download_and_run_installer(
 /// 检查虚拟网卡是否存在
 /// 
 /// # 返回
-/// * `Ok(bool)` - true 表示虚拟网卡存在
-/// * `Err(String)` - 错误信息
+/// * `Ok(DiagnosticResult)` - 诊断结果（超时返回 success=false）
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn check_virtual_adapter() -> Result<bool, String> {
+pub async fn check_virtual_adapter() -> Result<DiagnosticResult, CommandError> {
     log::info!("检查虚拟网卡...");
-    
-    #[cfg(windows)]
-    {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        // 使用 ipconfig 命令查找 EasyTier 创建的虚拟网卡
-        let output = Command::new("ipconfig")
-            .arg("/all")
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| format!("执行 ipconfig 失败: {}", e))?;
-        
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        // 查找包含 "EasyTier" 或 "WinTun" 的网卡
-        let has_adapter = output_str.contains("EasyTier") || 
-                         output_str.contains("WinTun") ||
-                         output_str.contains("wintun");
-        
-        log::info!("虚拟网卡检查结果: {}", has_adapter);
-        Ok(has_adapter)
-    }
-    
-    #[cfg(not(windows))]
-    {
-        // 非 Windows 平台暂不支持
-        Ok(true)
-    }
+    Ok(network_diagnostics::check_virtual_adapter().await)
 }
 
 /// 检查防火墙规则
 /// 
 /// # 返回
-/// * `Ok(bool)` - true 表示防火墙规则正常
-/// * `Err(String)` - 错误信息
+/// * `Ok(DiagnosticResult)` - 诊断结果（超时返回 success=false）
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn check_firewall_rules() -> Result<bool, String> {
+pub async fn check_firewall_rules() -> Result<DiagnosticResult, CommandError> {
     log::info!("检查防火墙规则...");
-    
-    #[cfg(windows)]
-    {
-        use std::process::Command;
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        
-        // 检查 Windows 防火墙是否已存在 MCTier 的放行规则
-        // 注意：必须与 add_firewall_rules 中添加的规则名保持一致
-        let output = Command::new("netsh")
-            .args(&["advfirewall", "firewall", "show", "rule", "name=all"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| format!("执行 netsh 失败: {}", e))?;
-        
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        // 检查是否存在 MCTier 自身添加的放行规则
-        // add_firewall_rules 添加的规则名为：MCTier-in/-out、MCTier-EasyTier-in/-out
-        let has_rules = output_str.contains("MCTier");
-        
-        log::info!("防火墙规则检查结果: {}", has_rules);
-        Ok(has_rules)
-    }
-    
-    #[cfg(not(windows))]
-    {
-        Ok(true)
-    }
+    Ok(network_diagnostics::check_firewall_rules().await)
 }
 
 /// 查询当前是否以管理员身份运行
 #[tauri::command]
 pub async fn is_admin() -> bool {
-    #[cfg(windows)]
-    {
-        use windows::Win32::Foundation::HANDLE;
-        use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
-        use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
-        unsafe {
-            let mut token: HANDLE = HANDLE::default();
-            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).is_err() {
-                return false;
-            }
-            let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
-            let mut ret_len = 0u32;
-            let ok = GetTokenInformation(
-                token,
-                TokenElevation,
-                Some(&mut elevation as *mut _ as *mut _),
-                std::mem::size_of::<TOKEN_ELEVATION>() as u32,
-                &mut ret_len,
-            );
-            ok.is_ok() && elevation.TokenIsElevated != 0
-        }
-    }
-    #[cfg(not(windows))]
-    {
-        true
-    }
+    crate::modules::network_service::is_elevated()
+}
+
+/// 获取本机 EasyTier 版本
+/// 
+/// 首次调用时执行 `easytier-core --version`，之后返回缓存结果；
+/// 检测到已知不兼容版本时同时发送 `easytier-version-warning` 事件。
+#[tauri::command]
+pub async fn get_easytier_version(
+    app_handle: tauri::AppHandle,
+) -> Result<crate::modules::network_service::EasyTierVersion, CommandError> {
+    let path = crate::modules::resource_manager::ResourceManager::get_easytier_path(&app_handle)
+        .map_err(CommandError::from)?;
+    crate::modules::network_service::detect_easytier_version(&path, Some(&app_handle))
+        .await
+        .map_err(|e| {
+            log::error!("获取 EasyTier 版本失败: {}", e);
+            CommandError::from(e)
+        })
 }
 
 /// 一键添加防火墙放行规则（按程序放行，覆盖该程序所有端口）
 ///
 /// 为 MCTier 主程序与 easytier-core 添加入站/出站允许规则。需要管理员权限。
 #[tauri::command]
-pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String, String> {
+pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String, CommandError> {
     #[cfg(windows)]
     {
         const CREATE_NO_WINDOW: u32 = 0x08000000;
@@ -1430,7 +3612,7 @@ pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String,
         }
 
         if programs.is_empty() {
-            return Err("无法确定程序路径".to_string());
+            return Err(CommandError::new(ErrorCode::Io, "无法确定程序路径"));
         }
 
         let mut added = 0;
@@ -1459,7 +3641,7 @@ pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String,
                     .creation_flags(CREATE_NO_WINDOW)
                     .output()
                     .await
-                    .map_err(|e| format!("执行 netsh 失败: {}", e))?;
+                    .map_err(|e| CommandError::new(ErrorCode::Process, format!("执行 netsh 失败: {}", e)))?;
 
                 if output.status.success() {
                     added += 1;
@@ -1476,7 +3658,7 @@ pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String,
             log::info!("✅ 已添加 {} 条防火墙放行规则", added);
             Ok(format!("已添加 {} 条防火墙放行规则", added))
         } else {
-            Err(format!("添加防火墙规则失败（可能需要管理员权限）: {}", last_err))
+            Err(CommandError::permission(format!("添加防火墙规则失败（可能需要管理员权限）: {}", last_err)))
         }
     }
     #[cfg(not(windows))]
@@ -1487,78 +3669,45 @@ pub async fn add_firewall_rules(app_handle: tauri::AppHandle) -> Result<String,
 }
 
 /// 以管理员身份重启应用
+///
+/// 通过 ShellExecute 的 runas 动词弹出 UAC 授权；用户拒绝授权时返回错误并保持当前实例运行，
+/// 授权成功后当前实例退出，新实例等待其退出后再启动。非 Windows 平台返回不支持。
 #[tauri::command]
-pub async fn restart_as_admin(app_handle: tauri::AppHandle) -> Result<(), String> {
-    #[cfg(windows)]
-    {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-        let exe = std::env::current_exe().map_err(|e| format!("无法获取程序路径: {}", e))?;
-        let exe_str = exe.to_string_lossy().replace('\'', "''");
-
-        // 用 PowerShell 以管理员身份(RunAs)重新启动
-        let spawn = std::process::Command::new("powershell")
-            .args(&[
-                "-NoProfile",
-                "-WindowStyle", "Hidden",
-                "-Command",
-                &format!("Start-Process -FilePath '{}' -Verb RunAs", exe_str),
-            ])
-            .creation_flags(CREATE_NO_WINDOW)
-            .spawn();
-
-        match spawn {
-            Ok(_) => {
-                log::info!("已请求以管理员身份重启，当前实例即将退出");
-                // 稍等片刻让新进程的 UAC 弹出
-                tokio::time::sleep(std::time::Duration::from_millis(600)).await;
-                app_handle.exit(0);
-                Ok(())
-            }
-            Err(e) => Err(format!("以管理员身份重启失败: {}", e)),
-        }
-    }
-    #[cfg(not(windows))]
-    {
-        let _ = app_handle;
-        Err("当前平台不支持".to_string())
-    }
+pub async fn restart_as_admin(app_handle: tauri::AppHandle) -> Result<(), CommandError> {
+    // ShellExecute 会阻塞到用户在 UAC 弹窗中做出选择
+    tokio::task::spawn_blocking(crate::modules::network_service::relaunch_elevated)
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Process, format!("以管理员身份重启失败: {}", e)))??;
+    log::info!("已以管理员身份启动新实例，当前实例即将退出");
+    app_handle.exit(0);
+    Ok(())
 }
+
+/// Ping 虚拟 IP
 /// 
 /// # 参数
 /// * `ip` - 要 ping 的 IP 地址
 /// 
 /// # 返回
-/// * `Ok(bool)` - true 表示可以 ping 通
-/// * `Err(String)` - 错误信息
+/// * `Ok(DiagnosticResult)` - 诊断结果（超时返回 success=false）
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn ping_virtual_ip(ip: String) -> Result<bool, String> {
+pub async fn ping_virtual_ip(ip: String) -> Result<DiagnosticResult, CommandError> {
     log::info!("Ping 虚拟 IP: {}", ip);
-    
-    use std::process::Command;
-    
-    #[cfg(windows)]
-    let output = {
-        use std::os::windows::process::CommandExt;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        Command::new("ping")
-            .args(&["-n", "2", "-w", "1000", &ip])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .map_err(|e| format!("执行 ping 失败: {}", e))?
-    };
-    
-    #[cfg(not(windows))]
-    let output = Command::new("ping")
-        .args(&["-c", "2", "-W", "1", &ip])
-        .output()
-        .map_err(|e| format!("执行 ping 失败: {}", e))?;
-    
-    let success = output.status.success();
-    log::info!("Ping 结果: {}", success);
-    
-    Ok(success)
+    Ok(network_diagnostics::ping_virtual_ip(&ip).await)
+}
+
+/// Ping 虚拟 IP 并返回延迟与丢包
+/// 
+/// # 参数
+/// * `ip` - 要 ping 的 IP 地址
+/// 
+/// # 返回
+/// * `Ok(PingResult)` - 平均 / 最小 / 最大延迟与丢包率（不通时 success=false）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn ping_virtual_ip_detailed(ip: String) -> Result<network_diagnostics::PingResult, CommandError> {
+    Ok(network_diagnostics::ping_virtual_ip_detailed(&ip).await)
 }
 
 /// 检查 UDP 端口是否可用
@@ -1568,9 +3717,9 @@ pub async fn ping_virtual_ip(ip: String) -> Result<bool, String> {
 /// 
 /// # 返回
 /// * `Ok(bool)` - true 表示端口可用
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn check_udp_port(port: u16) -> Result<bool, String> {
+pub async fn check_udp_port(port: u16) -> Result<bool, CommandError> {
     log::info!("检查 UDP 端口: {}", port);
     
     use std::net::UdpSocket;
@@ -1597,9 +3746,9 @@ pub async fn check_udp_port(port: u16) -> Result<bool, String> {
 /// 
 /// # 返回
 /// * `Ok(())` - 操作成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn set_auto_start(enable: bool) -> Result<(), String> {
+pub async fn set_auto_start(enable: bool) -> Result<(), CommandError> {
     log::info!("设置开机自启动: {}", enable);
 
     #[cfg(windows)]
@@ -1608,7 +3757,7 @@ pub async fn set_auto_start(enable: bool) -> Result<(), String> {
         use std::os::windows::process::CommandExt;
         let app_name = "MCTier";
         let app_path = std::env::current_exe()
-            .map_err(|e| format!("获取程序路径失败: {}", e))?
+            .map_err(|e| CommandError::new(ErrorCode::Process, format!("获取程序路径失败: {}", e)))?
             .to_string_lossy()
             .replace("/", "\\");
 
@@ -1638,12 +3787,12 @@ pub async fn set_auto_start(enable: bool) -> Result<(), String> {
                 ])
                 .creation_flags(0x08000000)
                 .output()
-                .map_err(|e| format!("写入注册表失败: {}", e))?;
+                .map_err(|e| CommandError::new(ErrorCode::Config, format!("写入注册表失败: {}", e)))?;
 
             if !output.status.success() {
                 let error = String::from_utf8_lossy(&output.stderr);
                 log::error!("写入注册表开机自启失败: {}", error);
-                return Err(format!("写入注册表失败: {}", error));
+                return Err(CommandError::permission(format!("写入注册表失败: {}", error)));
             }
             log::info!("开机自启动已启用（无窗口模式），路径: {}", app_path);
             Ok(())
@@ -1658,7 +3807,7 @@ pub async fn set_auto_start(enable: bool) -> Result<(), String> {
                 ])
                 .creation_flags(0x08000000)
                 .output()
-                .map_err(|e| format!("删除注册表失败: {}", e))?;
+                .map_err(|e| CommandError::new(ErrorCode::Config, format!("删除注册表失败: {}", e)))?;
 
             if !output.status.success() {
                 log::warn!("删除注册表开机自启项时出现警告（可能本就不存在）");
@@ -1681,7 +3830,7 @@ pub async fn set_auto_start(enable: bool) -> Result<(), String> {
 /// # 返回
 /// * `Ok(bool)` - true=已启用，false=未启用
 #[tauri::command]
-pub async fn check_auto_start() -> Result<bool, String> {
+pub async fn check_auto_start() -> Result<bool, CommandError> {
     log::info!("检查开机自启动状态");
 
     #[cfg(windows)]
@@ -1697,7 +3846,7 @@ pub async fn check_auto_start() -> Result<bool, String> {
             ])
             .creation_flags(0x08000000)
             .output()
-            .map_err(|e| format!("查询注册表失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::Config, format!("查询注册表失败: {}", e)))?;
 
         let is_enabled = output.status.success();
         log::info!("开机自启动状态（注册表）: {}", is_enabled);
@@ -1721,17 +3870,16 @@ pub async fn check_auto_start() -> Result<bool, String> {
 /// 
 /// # 返回
 /// * `Ok(())` - 添加成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn add_player_domain(
     domain: String,
     ip: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("收到添加玩家域名映射命令: {} -> {}", domain, ip);
     
-    let core = state.core.lock().await;
-    let lobby_manager = core.get_lobby_manager();
+    let lobby_manager = state.get_lobby_manager();
     let manager = lobby_manager.lock().await;
     
     // 获取当前大厅信息
@@ -1739,14 +3887,14 @@ pub async fn add_player_domain(
         lobby.name.clone()
     } else {
         log::warn!("⚠️ 当前不在大厅中，无法添加域名映射");
-        return Err("当前不在大厅中".to_string());
+        return Err(CommandError::validation("当前不在大厅中"));
     };
     
     // 获取或创建HostsManager
     let hosts_manager = if let Some(hm) = manager.get_hosts_manager() {
         // 已存在，直接使用
         hm.add_entry(&domain, &ip)
-            .map_err(|e| format!("添加域名映射失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("添加域名映射失败: {}", e)))?;
         
         log::info!("✅ 域名映射已添加: {} -> {}", domain, ip);
         Ok(())
@@ -1757,7 +3905,7 @@ pub async fn add_player_domain(
         
         let new_hosts_manager = crate::modules::hosts_manager::HostsManager::new(&lobby_name);
         new_hosts_manager.add_entry(&domain, &ip)
-            .map_err(|e| format!("添加域名映射失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("添加域名映射失败: {}", e)))?;
         
         // 重新获取锁并设置HostsManager
         let mut manager = lobby_manager.lock().await;
@@ -1778,22 +3926,21 @@ pub async fn add_player_domain(
 /// 
 /// # 返回
 /// * `Ok(())` - 删除成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn remove_player_domain(
     domain: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("收到删除玩家域名映射命令: {}", domain);
     
-    let core = state.core.lock().await;
-    let lobby_manager = core.get_lobby_manager();
+    let lobby_manager = state.get_lobby_manager();
     let manager = lobby_manager.lock().await;
     
     // 获取HostsManager
     if let Some(hosts_manager) = manager.get_hosts_manager() {
         hosts_manager.remove_entry(&domain)
-            .map_err(|e| format!("删除域名映射失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("删除域名映射失败: {}", e)))?;
         
         log::info!("✅ 域名映射已删除: {}", domain);
         Ok(())
@@ -1804,7 +3951,6 @@ pub async fn remove_player_domain(
     }
 }
 
-
 // ==================== 文件共享操作命令 ====================
 
 use serde::{Deserialize, Serialize};
@@ -1827,9 +3973,9 @@ pub struct FileInfo {
 ///
 /// # 返回
 /// * `Ok(String)` - 文件夹名称
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_folder_name(path: String) -> Result<String, String> {
+pub async fn get_folder_name(path: String) -> Result<String, CommandError> {
     log::info!("获取文件夹名称: {}", path);
     
     let path_obj = Path::new(&path);
@@ -1838,10 +3984,10 @@ pub async fn get_folder_name(path: String) -> Result<String, String> {
         if let Some(name_str) = name.to_str() {
             Ok(name_str.to_string())
         } else {
-            Err("无法转换文件夹名称".to_string())
+            Err(CommandError::validation("无法转换文件夹名称"))
         }
     } else {
-        Err("无效的文件夹路径".to_string())
+        Err(CommandError::validation("无效的文件夹路径"))
     }
 }
 
@@ -1852,23 +3998,23 @@ pub async fn get_folder_name(path: String) -> Result<String, String> {
 ///
 /// # 返回
 /// * `Ok((file_count, total_size))` - 文件数量和总大小
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_folder_info(path: String) -> Result<serde_json::Value, String> {
+pub async fn get_folder_info(path: String) -> Result<serde_json::Value, CommandError> {
     log::info!("获取文件夹信息: {}", path);
     
     let path_obj = Path::new(&path);
     
     if !path_obj.exists() {
-        return Err("文件夹不存在".to_string());
+        return Err(CommandError::not_found("文件夹不存在"));
     }
     
     if !path_obj.is_dir() {
-        return Err("路径不是文件夹".to_string());
+        return Err(CommandError::validation("路径不是文件夹"));
     }
     
     let (file_count, total_size) = count_files_and_size(path_obj)
-        .map_err(|e| format!("统计文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("统计文件失败: {}", e)))?;
     
     Ok(serde_json::json!({
         "fileCount": file_count,
@@ -1905,41 +4051,41 @@ fn count_files_and_size(path: &Path) -> std::io::Result<(usize, u64)> {
 ///
 /// # 返回
 /// * `Ok(Vec<FileInfo>)` - 文件列表
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn list_directory_files(path: String) -> Result<Vec<FileInfo>, String> {
+pub async fn list_directory_files(path: String) -> Result<Vec<FileInfo>, CommandError> {
     log::info!("📂 列出目录文件: {}", path);
     
-    let path_obj = Path::new(&path);
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
     
     if !path_obj.exists() {
         log::error!("❌ 目录不存在: {}", path);
-        return Err("目录不存在".to_string());
+        return Err(CommandError::not_found("目录不存在"));
     }
     
     if !path_obj.is_dir() {
         log::error!("❌ 路径不是目录: {}", path);
-        return Err("路径不是目录".to_string());
+        return Err(CommandError::validation("路径不是目录"));
     }
     
     let mut files = Vec::new();
     
-    let entries = std::fs::read_dir(path_obj)
-        .map_err(|e| format!("读取目录失败: {}", e))?;
+    let entries = std::fs::read_dir(&path_obj)
+        .map_err(|e| CommandError::io(format!("读取目录失败: {}", e)))?;
     
     for entry in entries {
-        let entry = entry.map_err(|e| format!("读取条目失败: {}", e))?;
+        let entry = entry.map_err(|e| CommandError::io(format!("读取条目失败: {}", e)))?;
         let entry_path = entry.path();
         
         let metadata = entry_path.metadata()
-            .map_err(|e| format!("获取元数据失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("获取元数据失败: {}", e)))?;
         
         let name = entry.file_name()
             .to_str()
             .unwrap_or("未知")
             .to_string();
         
-        let relative_path = entry_path.strip_prefix(path_obj)
+        let relative_path = entry_path.strip_prefix(&path_obj)
             .unwrap_or(&entry_path)
             .to_str()
             .unwrap_or("")
@@ -1991,23 +4137,23 @@ pub async fn list_directory_files(path: String) -> Result<Vec<FileInfo>, String>
 ///
 /// # 返回
 /// * `Ok(Vec<u8>)` - 文件内容
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
+pub async fn read_file_bytes(path: String) -> Result<Vec<u8>, CommandError> {
     log::info!("读取文件: {}", path);
     
-    let path_obj = Path::new(&path);
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
     
     if !path_obj.exists() {
-        return Err("文件不存在".to_string());
+        return Err(CommandError::not_found("文件不存在"));
     }
     
     if !path_obj.is_file() {
-        return Err("路径不是文件".to_string());
+        return Err(CommandError::validation("路径不是文件"));
     }
     
-    std::fs::read(path_obj)
-        .map_err(|e| format!("读取文件失败: {}", e))
+    std::fs::read(&path_obj)
+        .map_err(|e| CommandError::new(ErrorCode::Io, format!("读取文件失败: {}", e)))
 }
 
 /// 写入文件内容（字节数组）
@@ -2018,30 +4164,30 @@ pub async fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
 ///
 /// # 返回
 /// * `Ok(())` - 写入成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn write_file_bytes(path: String, data: Vec<u8>) -> Result<(), String> {
+pub async fn write_file_bytes(path: String, data: Vec<u8>) -> Result<(), CommandError> {
     log::info!("写入文件: {} ({} 字节)", path, data.len());
     
-    let path_obj = Path::new(&path);
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
     
     // 确保父目录存在
     if let Some(parent) = path_obj.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("创建目录失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("创建目录失败: {}", e)))?;
     }
     
-    std::fs::write(path_obj, data)
-        .map_err(|e| format!("写入文件失败: {}", e))
+    std::fs::write(&path_obj, data)
+        .map_err(|e| CommandError::new(ErrorCode::Io, format!("写入文件失败: {}", e)))
 }
 
 /// 选择文件夹
 ///
 /// # 返回
 /// * `Ok(Option<String>)` - 选择的文件夹路径，None表示取消
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn select_folder() -> Result<Option<String>, String> {
+pub async fn select_folder() -> Result<Option<String>, CommandError> {
     log::info!("打开文件夹选择对话框");
     
     use rfd::FileDialog;
@@ -2053,9 +4199,10 @@ pub async fn select_folder() -> Result<Option<String>, String> {
     if let Some(path) = result {
         if let Some(path_str) = path.to_str() {
             log::info!("用户选择了文件夹: {}", path_str);
+            crate::modules::path_guard::allow(&path);
             Ok(Some(path_str.to_string()))
         } else {
-            Err("无法转换文件夹路径".to_string())
+            Err(CommandError::validation("无法转换文件夹路径"))
         }
     } else {
         log::info!("用户取消了选择");
@@ -2070,9 +4217,9 @@ pub async fn select_folder() -> Result<Option<String>, String> {
 ///
 /// # 返回
 /// * `Ok(Option<String>)` - 选择的保存路径，None表示取消
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn select_save_location(default_name: String) -> Result<Option<String>, String> {
+pub async fn select_save_location(default_name: String) -> Result<Option<String>, CommandError> {
     log::info!("打开保存位置选择对话框: {}", default_name);
     
     use rfd::FileDialog;
@@ -2085,9 +4232,13 @@ pub async fn select_save_location(default_name: String) -> Result<Option<String>
     if let Some(path) = result {
         if let Some(path_str) = path.to_str() {
             log::info!("用户选择了保存位置: {}", path_str);
+            // 放行所在目录：下载时还会写入同目录的 .part 临时文件
+            if let Some(parent) = path.parent() {
+                crate::modules::path_guard::allow(parent);
+            }
             Ok(Some(path_str.to_string()))
         } else {
-            Err("无法转换保存路径".to_string())
+            Err(CommandError::validation("无法转换保存路径"))
         }
     } else {
         log::info!("用户取消了选择");
@@ -2099,9 +4250,9 @@ pub async fn select_save_location(default_name: String) -> Result<Option<String>
 ///
 /// # 返回
 /// * `Ok(Option<String>)` - 选择的文件路径，None表示取消
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn select_file() -> Result<Option<String>, String> {
+pub async fn select_file() -> Result<Option<String>, CommandError> {
     log::info!("打开文件选择对话框");
     
     use rfd::FileDialog;
@@ -2114,9 +4265,10 @@ pub async fn select_file() -> Result<Option<String>, String> {
     if let Some(path) = result {
         if let Some(path_str) = path.to_str() {
             log::info!("用户选择了文件: {}", path_str);
+            crate::modules::path_guard::allow(&path);
             Ok(Some(path_str.to_string()))
         } else {
-            Err("无法转换文件路径".to_string())
+            Err(CommandError::validation("无法转换文件路径"))
         }
     } else {
         log::info!("用户取消了选择");
@@ -2124,6 +4276,19 @@ pub async fn select_file() -> Result<Option<String>, String> {
     }
 }
 
+/// 选择要上传到他人共享的文件（不限类型）
+#[tauri::command]
+pub async fn select_upload_file() -> Result<Option<String>, CommandError> {
+    let result = rfd::FileDialog::new().set_title("选择要上传的文件").pick_file();
+    match result {
+        Some(path) => path
+            .to_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| CommandError::validation("无法转换文件路径")),
+        None => Ok(None),
+    }
+}
+
 /// 打开文件所在文件夹并选中文件
 ///
 /// # 参数
@@ -2131,10 +4296,11 @@ pub async fn select_file() -> Result<Option<String>, String> {
 ///
 /// # 返回
 /// * `Ok(())` - 成功打开
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn open_file_location(path: String) -> Result<(), String> {
+pub async fn open_file_location(path: String) -> Result<(), CommandError> {
     log::info!("打开文件位置: {}", path);
+    crate::modules::path_guard::check_path(&path)?;
     
     use std::process::Command;
     
@@ -2151,7 +4317,7 @@ pub async fn open_file_location(path: String) -> Result<(), String> {
             }
             Err(e) => {
                 log::error!("打开文件位置失败: {}", e);
-                Err(format!("打开文件位置失败: {}", e))
+                Err(CommandError::new(ErrorCode::Io, format!("打开文件位置失败: {}", e)))
             }
         }
     }
@@ -2215,10 +4381,11 @@ pub async fn open_file_location(path: String) -> Result<(), String> {
 ///
 /// # 返回
 /// * `Ok(())` - 成功打开
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn open_folder(path: String) -> Result<(), String> {
+pub async fn open_folder(path: String) -> Result<(), CommandError> {
     log::info!("打开文件夹: {}", path);
+    crate::modules::path_guard::check_path(&path)?;
     
     use std::process::Command;
     
@@ -2235,7 +4402,7 @@ pub async fn open_folder(path: String) -> Result<(), String> {
             }
             Err(e) => {
                 log::error!("打开文件夹失败: {}", e);
-                Err(format!("打开文件夹失败: {}", e))
+                Err(CommandError::new(ErrorCode::Io, format!("打开文件夹失败: {}", e)))
             }
         }
     }
@@ -2289,27 +4456,26 @@ pub async fn open_folder(path: String) -> Result<(), String> {
 
 // ==================== HTTP 文件共享命令 ====================
 
-use crate::modules::file_transfer::{SharedFolder, FileInfo as FileTransferFileInfo};
+use crate::modules::file_transfer::{
+    download_complete_url, DownloadCompleteRequest, DownloadCompleteResponse, FileInfo as FileTransferFileInfo,
+    ImportSharesReport, ShareDownloadStats, SharedFolder,
+};
 
 /// 启动HTTP文件服务器
 #[tauri::command]
 pub async fn start_file_server(
     virtual_ip: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("启动HTTP文件服务器: {}", virtual_ip);
     
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
-    // 先尝试停止旧的服务器（如果存在）
+    // 先停止旧的服务器（如果存在），stop_server 会等待端口释放
     ft_service.stop_server().await;
     log::info!("已停止旧的HTTP文件服务器（如果存在）");
     
-    // 等待端口完全释放
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    
     // 设置虚拟IP
     ft_service.set_virtual_ip(virtual_ip);
     
@@ -2321,18 +4487,17 @@ pub async fn start_file_server(
         }
         Err(e) => {
             log::error!("❌ HTTP文件服务器启动失败: {}", e);
-            Err(e.to_string())
+            Err(CommandError::network(e.to_string()))
         }
     }
 }
 
 /// 停止HTTP文件服务器
 #[tauri::command]
-pub async fn stop_file_server(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn stop_file_server(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("停止HTTP文件服务器");
     
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
     ft_service.stop_server().await;
@@ -2342,9 +4507,8 @@ pub async fn stop_file_server(state: State<'_, AppState>) -> Result<(), String>
 
 /// 检查HTTP文件服务器状态
 #[tauri::command]
-pub async fn check_file_server_status(state: State<'_, AppState>) -> Result<bool, String> {
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+pub async fn check_file_server_status(state: State<'_, AppState>) -> Result<bool, CommandError> {
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
     // 检查服务器句柄是否存在
@@ -2358,13 +4522,19 @@ pub async fn check_file_server_status(state: State<'_, AppState>) -> Result<bool
 pub async fn add_shared_folder(
     share: SharedFolder,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("📁 添加共享文件夹: {} ({})", share.name, share.id);
     
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+    // 共享目录会暴露给大厅内其他玩家，只允许用户通过对话框选择过的目录
+    crate::modules::path_guard::check_path(&share.path)?;
+    
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
+    if ft_service.is_guest_mode() {
+        return Err(CommandError::permission("游客模式下不能共享文件"));
+    }
+    
     // 检查HTTP服务器是否已启动
     let is_running = ft_service.is_running();
     
@@ -2378,7 +4548,7 @@ pub async fn add_shared_folder(
             }
             Err(e) => {
                 log::error!("❌ HTTP文件服务器启动失败: {}", e);
-                return Err(format!("启动HTTP文件服务器失败: {}", e));
+                return Err(CommandError::network(format!("启动HTTP文件服务器失败: {}", e)));
             }
         }
     } else {
@@ -2386,7 +4556,53 @@ pub async fn add_shared_folder(
     }
     
     // 添加共享
-    ft_service.add_share(share)
+    ft_service.add_share(share).map_err(CommandError::from)
+}
+
+/// 导出当前共享列表（路径、名称、压缩设置与密码哈希，不含密码明文）
+/// 
+/// # 参数
+/// * `path` - 导出文件路径
+/// 
+/// # 返回
+/// * `Ok(usize)` - 导出的共享数量
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn export_shares(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, CommandError> {
+    log::info!("📤 导出共享列表: {}", path);
+    
+    let path = crate::modules::path_guard::check_path(&path)?;
+    let file_transfer = state.get_file_transfer();
+    let ft_service = file_transfer.lock().await;
+    ft_service.export_shares(&path).map_err(CommandError::from)
+}
+
+/// 从文件导入共享列表
+/// 
+/// 路径失效的共享会被跳过并在结果中报告；文件服务正在运行时导入后立即重新共享，否则在服务启动后生效。
+/// 
+/// # 参数
+/// * `path` - 导出文件路径
+/// * `owner_id` - 本机玩家ID
+/// 
+/// # 返回
+/// * `Ok(ImportSharesReport)` - 导入结果
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn import_shares(
+    path: String,
+    owner_id: String,
+    state: State<'_, AppState>,
+) -> Result<ImportSharesReport, CommandError> {
+    log::info!("📥 导入共享列表: {}", path);
+    
+    let path = crate::modules::path_guard::check_path(&path)?;
+    let file_transfer = state.get_file_transfer();
+    let ft_service = file_transfer.lock().await;
+    ft_service.import_shares(&path, &owner_id).map_err(CommandError::from)
 }
 
 /// 删除共享文件夹
@@ -2394,64 +4610,100 @@ pub async fn add_shared_folder(
 pub async fn remove_shared_folder(
     share_id: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::debug!("删除共享文件夹: {}", share_id);
     
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
-    ft_service.remove_share(&share_id)
+    ft_service.remove_share(&share_id).map_err(CommandError::from)
+}
+
+/// 设置共享或文件的备注
+/// 
+/// # 参数
+/// * `share_id` - 共享ID
+/// * `path` - 相对共享根目录的路径，为空表示共享级备注
+/// * `note` - 备注内容，为空表示删除
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_file_note(
+    share_id: String,
+    path: String,
+    note: String,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let file_transfer = state.get_file_transfer();
+    
+    let ft_service = file_transfer.lock().await;
+    ft_service.set_file_note(&share_id, &path, &note).await.map_err(CommandError::from)
 }
 
 /// 获取本地共享列表
 #[tauri::command]
-pub async fn get_local_shares(state: State<'_, AppState>) -> Result<Vec<SharedFolder>, String> {
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+pub async fn get_local_shares(state: State<'_, AppState>) -> Result<Vec<SharedFolder>, CommandError> {
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
     Ok(ft_service.get_shares())
 }
 
+/// 获取本地共享的下载统计（由下载端回执更新）
+#[tauri::command]
+pub async fn get_share_download_stats(
+    share_id: String,
+    state: State<'_, AppState>,
+) -> Result<ShareDownloadStats, CommandError> {
+    let file_transfer = state.get_file_transfer();
+    let ft_service = file_transfer.lock().await;
+
+    Ok(ft_service.get_download_stats(&share_id))
+}
+
 /// 清理过期共享
 #[tauri::command]
-pub async fn cleanup_expired_shares(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn cleanup_expired_shares(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::debug!("清理过期共享");
     
-    let core = state.core.lock().await;
-    let file_transfer = core.get_file_transfer();
+    let file_transfer = state.get_file_transfer();
     let ft_service = file_transfer.lock().await;
     
     ft_service.cleanup_expired_shares();
     Ok(())
 }
 
-/// 获取远程共享列表（通过HTTP API）
-#[tauri::command]
-pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, String> {
-    log::debug!("📡 正在获取远程共享列表: {}", peer_ip);
-    
-    let url = format!("http://{}:14539/api/shares", peer_ip);
-    log::info!("🔗 请求URL: {}", url);
-    
-    // 设置超时时间为5秒
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
+/// 查询远程共享列表的超时时间
+const REMOTE_SHARES_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 创建查询远程共享列表用的 HTTP 客户端
+fn remote_shares_client() -> Result<reqwest::Client, String> {
+    crate::modules::http_tls::client_builder()
+        .timeout(REMOTE_SHARES_TIMEOUT)
         .build()
         .map_err(|e| {
             log::error!("❌ 创建HTTP客户端失败: {}", e);
             format!("创建HTTP客户端失败: {}", e)
-        })?;
+        })
+}
+
+/// 通过 HTTP API 获取一个 peer 的共享列表
+async fn fetch_remote_shares(client: &reqwest::Client, peer_ip: &str) -> Result<Vec<SharedFolder>, CommandError> {
+    log::debug!("📡 正在获取远程共享列表: {}", peer_ip);
+    
+    let url = crate::modules::http_tls::peer_url(peer_ip, crate::modules::service_ports::peer_file_port(peer_ip), "/api/shares");
+    log::info!("🔗 请求URL: {}", url);
     
-    match client.get(&url).send().await {
+    match crate::modules::http_auth::send_signed(client.get(&url)).await {
         Ok(response) => {
             let status = response.status();
             log::info!("📥 收到响应，状态码: {}", status);
             
             if !status.is_success() {
                 log::error!("❌ HTTP请求失败，状态码: {}", status);
-                return Err(format!("HTTP请求失败: {}", status));
+                return Err(CommandError::network(format!("HTTP请求失败: {}", status)));
             }
             
             match response.json::<serde_json::Value>().await {
@@ -2460,7 +4712,14 @@ pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, Str
                     
                     if let Some(shares) = json.get("shares") {
                         match serde_json::from_value::<Vec<SharedFolder>>(shares.clone()) {
-                            Ok(shares_vec) => {
+                            Ok(mut shares_vec) => {
+                                for share in &mut shares_vec {
+                                    share.name = crate::modules::input_limits::clamp_remote(
+                                        "共享名称",
+                                        std::mem::take(&mut share.name),
+                                        crate::modules::input_limits::MAX_SHARE_NAME_CHARS,
+                                    );
+                                }
                                 log::debug!("✅ 成功获取 {} 个共享", shares_vec.len());
                                 for (i, share) in shares_vec.iter().enumerate() {
                                     log::debug!("  {}. {} (ID: {})", i + 1, share.name, share.id);
@@ -2469,7 +4728,7 @@ pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, Str
                             }
                             Err(e) => {
                                 log::error!("❌ 解析共享列表失败: {}", e);
-                                Err(format!("解析共享列表失败: {}", e))
+                                Err(CommandError::new(ErrorCode::Serialization, format!("解析共享列表失败: {}", e)))
                             }
                         }
                     } else {
@@ -2479,7 +4738,7 @@ pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, Str
                 }
                 Err(e) => {
                     log::error!("❌ 解析响应JSON失败: {}", e);
-                    Err(format!("解析响应失败: {}", e))
+                    Err(CommandError::new(ErrorCode::Serialization, format!("解析响应失败: {}", e)))
                 }
             }
         }
@@ -2488,13 +4747,72 @@ pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, Str
             log::error!("💡 可能原因:");
             log::error!("   1. 对方的HTTP文件服务器未启动");
             log::error!("   2. 虚拟网络连接不通（尝试ping {}）", peer_ip);
-            log::error!("   3. 防火墙阻止了14539端口");
+            log::error!("   3. 防火墙阻止了{}端口", crate::modules::service_ports::peer_file_port(peer_ip));
             log::error!("   4. 对方的虚拟IP地址不正确");
-            Err(format!("请求失败: {}", e))
+            Err(CommandError::network(format!("请求失败: {}", e)))
         }
     }
 }
 
+/// 获取远程共享列表（通过HTTP API）
+#[tauri::command]
+pub async fn get_remote_shares(peer_ip: String) -> Result<Vec<SharedFolder>, CommandError> {
+    let client = remote_shares_client()?;
+    fetch_remote_shares(&client, &peer_ip).await
+}
+
+/// 并发获取多个 peer 的共享列表
+/// 
+/// 所有 peer 同时查询、共用同一超时，单个 peer 失败不影响其他 peer，
+/// 返回每个 peer 各自的共享列表或错误信息。
+/// 
+/// # 参数
+/// * `peer_ips` - 要查询的 peer 虚拟 IP 列表（重复的只查询一次）
+#[tauri::command]
+pub async fn get_all_remote_shares(
+    peer_ips: Vec<String>,
+) -> Result<std::collections::HashMap<String, Result<Vec<SharedFolder>, CommandError>>, CommandError> {
+    let mut peer_ips = peer_ips;
+    peer_ips.sort();
+    peer_ips.dedup();
+    log::debug!("📡 并发获取 {} 个 peer 的共享列表", peer_ips.len());
+
+    let client = remote_shares_client()?;
+    let results = futures_util::future::join_all(
+        peer_ips.iter().map(|peer_ip| fetch_remote_shares(&client, peer_ip)),
+    )
+    .await;
+    Ok(peer_ips.into_iter().zip(results).collect())
+}
+
+/// 向大厅广播本机正在浏览 / 下载某个共享（已关闭或间隔内重复时不广播，失败只记录日志）
+fn announce_share_activity(
+    app_handle: &tauri::AppHandle,
+    share_id: &str,
+    activity: crate::modules::share_presence::ShareActivity,
+) {
+    use tauri::Manager;
+
+    let now = std::time::Instant::now();
+    if !crate::modules::share_presence::presence_slot().lock().should_announce(share_id, activity, now) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    let share_id = share_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        let p2p_signaling = state.get_p2p_signaling();
+        let p2p = p2p_signaling.lock().await;
+        let Some(player_id) = p2p.get_local_player_id().await else {
+            return;
+        };
+        let message = crate::modules::p2p_signaling::P2PMessage::BrowsingShare { player_id, share_id, activity };
+        if let Err(e) = p2p.broadcast_to_all(message).await {
+            log::debug!("广播共享浏览状态失败: {}", e);
+        }
+    });
+}
+
 /// 获取远程文件列表
 #[tauri::command]
 pub async fn get_remote_files(
@@ -2502,15 +4820,17 @@ pub async fn get_remote_files(
     share_id: String,
     path: Option<String>,
     password: Option<String>,
-) -> Result<Vec<FileTransferFileInfo>, String> {
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<FileTransferFileInfo>, CommandError> {
     log::info!("获取远程文件列表: {} / {} / {:?}", peer_ip, share_id, path);
+    announce_share_activity(&app_handle, &share_id, crate::modules::share_presence::ShareActivity::Browsing);
     
-    let mut url = format!("http://{}:14539/api/shares/{}/files", peer_ip, share_id);
+    let mut url = crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_file_port(&peer_ip), &format!("/api/shares/{}/files", share_id));
     if let Some(p) = path {
         url = format!("{}?path={}", url, urlencoding::encode(&p));
     }
     
-    let client = reqwest::Client::new();
+    let client = crate::modules::http_tls::client_builder().build().map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
     let mut req = client.get(&url);
     // 携带共享密码头，否则有密码保护的共享会返回 401
     if let Some(pwd) = password {
@@ -2519,22 +4839,34 @@ pub async fn get_remote_files(
         }
     }
     
-    match req.send().await {
+    match crate::modules::http_auth::send_signed(req).await {
         Ok(response) => {
             if response.status().as_u16() == 401 {
-                return Err("访问被拒绝：密码错误或未提供密码".to_string());
+                return Err(CommandError::permission("访问被拒绝：密码错误或未提供密码"));
             }
             match response.json::<serde_json::Value>().await {
                 Ok(json) => {
                     if let Some(files) = json.get("files") {
                         match serde_json::from_value::<Vec<FileTransferFileInfo>>(files.clone()) {
-                            Ok(files_vec) => {
+                            Ok(mut files_vec) => {
+                                // 只截断展示用的文件名，下载仍使用原始路径
+                                for file in &mut files_vec {
+                                    if let std::borrow::Cow::Owned(name) = crate::modules::input_limits::clamp_file_name(&file.name) {
+                                        file.name = name;
+                                    }
+                                    // 对方返回的类型与缩略图只用于展示，异常的直接丢弃
+                                    file.mime_type = file.mime_type.take().filter(|mime| mime.len() <= 100);
+                                    file.thumbnail_base64 = file
+                                        .thumbnail_base64
+                                        .take()
+                                        .filter(|data| data.len() <= crate::modules::file_transfer::MAX_THUMBNAIL_BASE64_LEN);
+                                }
                                 log::info!("✅ 获取到 {} 个文件", files_vec.len());
                                 Ok(files_vec)
                             }
                             Err(e) => {
                                 log::error!("❌ 解析文件列表失败: {}", e);
-                                Err(format!("解析文件列表失败: {}", e))
+                                Err(CommandError::new(ErrorCode::Serialization, format!("解析文件列表失败: {}", e)))
                             }
                         }
                     } else {
@@ -2543,13 +4875,13 @@ pub async fn get_remote_files(
                 }
                 Err(e) => {
                     log::error!("❌ 解析响应失败: {}", e);
-                    Err(format!("解析响应失败: {}", e))
+                    Err(CommandError::new(ErrorCode::Serialization, format!("解析响应失败: {}", e)))
                 }
             }
         }
         Err(e) => {
             log::error!("❌ 请求失败: {}", e);
-            Err(format!("请求失败: {}", e))
+            Err(CommandError::network(format!("请求失败: {}", e)))
         }
     }
 }
@@ -2560,17 +4892,17 @@ pub async fn verify_share_password(
     peer_ip: String,
     share_id: String,
     password: String,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     log::debug!("验证共享密码: {} / {}", peer_ip, share_id);
     
-    let url = format!("http://{}:14539/api/shares/{}/verify", peer_ip, share_id);
-    let client = reqwest::Client::new();
+    let url = crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_file_port(&peer_ip), &format!("/api/shares/{}/verify", share_id));
+    let client = crate::modules::http_tls::client_builder().build().map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
     
     let body = serde_json::json!({
         "password": password
     });
     
-    match client.post(&url).json(&body).send().await {
+    match crate::modules::http_auth::send_signed(client.post(&url).json(&body)).await {
         Ok(response) => {
             match response.json::<serde_json::Value>().await {
                 Ok(json) => {
@@ -2578,18 +4910,18 @@ pub async fn verify_share_password(
                         log::info!("✅ 密码验证结果: {}", success);
                         Ok(success)
                     } else {
-                        Err("无效的响应格式".to_string())
+                        Err(CommandError::new(ErrorCode::Serialization, "无效的响应格式"))
                     }
                 }
                 Err(e) => {
                     log::error!("❌ 解析响应失败: {}", e);
-                    Err(format!("解析响应失败: {}", e))
+                    Err(CommandError::new(ErrorCode::Serialization, format!("解析响应失败: {}", e)))
                 }
             }
         }
         Err(e) => {
             log::error!("❌ 请求失败: {}", e);
-            Err(format!("请求失败: {}", e))
+            Err(CommandError::network(format!("请求失败: {}", e)))
         }
     }
 }
@@ -2600,21 +4932,130 @@ pub async fn get_download_url(
     peer_ip: String,
     share_id: String,
     file_path: String,
-) -> Result<String, String> {
-    let url = format!(
-        "http://{}:14539/api/shares/{}/download/{}",
-        peer_ip,
-        share_id,
-        urlencoding::encode(&file_path)
+) -> Result<String, CommandError> {
+    let url = crate::modules::http_tls::peer_url(
+        &peer_ip,
+        crate::modules::service_ports::peer_file_port(&peer_ip),
+        &format!("/api/shares/{}/download/{}", share_id, urlencoding::encode(&file_path)),
     );
     Ok(url)
 }
 
+/// 获取远程共享中图片文件的缩略图
+///
+/// # 返回
+/// * `Ok(String)` - JPEG 缩略图（Base64）
+/// * `Err(CommandError)` - 错误信息（非图片、图片过大或无法解码时同样返回错误）
+#[tauri::command]
+pub async fn get_remote_file_thumbnail(
+    peer_ip: String,
+    share_id: String,
+    file_path: String,
+    password: Option<String>,
+) -> Result<String, CommandError> {
+    use base64::Engine as _;
+
+    let url = crate::modules::http_tls::peer_url(
+        &peer_ip,
+        crate::modules::service_ports::peer_file_port(&peer_ip),
+        &format!("/api/shares/{}/thumbnail/{}", share_id, urlencoding::encode(&file_path)),
+    );
+    let client = crate::modules::http_tls::client_builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
+    let mut req = client.get(&url);
+    if let Some(pwd) = password.filter(|pwd| !pwd.is_empty()) {
+        req = req.header("x-share-password", pwd);
+    }
+
+    let response = crate::modules::http_auth::send_signed(req)
+        .await
+        .map_err(|e| CommandError::network(format!("请求失败: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(CommandError::network(format!("获取缩略图失败: HTTP {}", response.status())));
+    }
+    let data = response.bytes().await.map_err(|e| CommandError::network(format!("读取缩略图失败: {}", e)))?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+    if encoded.len() > crate::modules::file_transfer::MAX_THUMBNAIL_BASE64_LEN {
+        return Err(CommandError::validation("缩略图过大"));
+    }
+    Ok(encoded)
+}
+
+/// 上传本地文件到其他玩家的可写共享
+///
+/// # 参数
+/// * `local_path` - 本地文件路径
+/// * `remote_path` - 相对对方共享目录的目标路径（不能包含 `..`）
+/// * `password` - 共享密码（有密码的共享需要提供）
+#[tauri::command]
+pub async fn upload_file_to_peer(
+    peer_ip: String,
+    share_id: String,
+    local_path: String,
+    remote_path: String,
+    password: Option<String>,
+) -> Result<crate::modules::file_transfer::UploadResponse, CommandError> {
+    log::info!("⬆️ 上传文件: {} -> {}/{}/{}", local_path, peer_ip, share_id, remote_path);
+    let local_path = crate::modules::path_guard::check_path(&local_path)?;
+
+    let file = tokio::fs::File::open(&local_path)
+        .await
+        .map_err(|e| CommandError::io(format!("打开本地文件失败: {}", e)))?;
+    let size = file.metadata().await.map(|m| m.len()).map_err(|e| CommandError::io(format!("读取文件信息失败: {}", e)))?;
+    // 流式请求体无法在签名时计算哈希，预先读一遍文件算出 SHA-256 参与大厅签名
+    let content_sha256 = {
+        use sha2::Digest;
+        let hasher = crate::modules::download_tasks::hash_file_prefix(&local_path, size)
+            .await
+            .map_err(|e| CommandError::io(format!("读取本地文件失败: {}", e)))?;
+        format!("{:x}", hasher.finalize())
+    };
+
+    let url = format!(
+        "{}?path={}",
+        crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_file_port(&peer_ip), &format!("/api/shares/{}/upload", share_id)),
+        urlencoding::encode(&remote_path)
+    );
+    let client = crate::modules::http_tls::client_builder()
+        .build()
+        .map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
+    let mut req = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_LENGTH, size)
+        .header(crate::modules::http_auth::CONTENT_SHA256_HEADER, content_sha256)
+        .body(reqwest::Body::from(file));
+    if let Some(pwd) = password.filter(|p| !p.is_empty()) {
+        req = req.header("x-share-password", pwd);
+    }
+
+    let response = crate::modules::http_auth::send_signed(req)
+        .await
+        .map_err(|e| CommandError::network(format!("上传失败: {}", e)))?;
+    match response.status().as_u16() {
+        200 => {}
+        401 => return Err(CommandError::permission("访问被拒绝：密码错误或未提供密码")),
+        403 => return Err(CommandError::permission("对方共享为只读，或目标路径不合法")),
+        404 => return Err(CommandError::not_found("共享不存在")),
+        409 => return Err(CommandError::validation("对方共享中已存在同名文件")),
+        413 => return Err(CommandError::validation("文件超过对方允许的上传大小上限")),
+        status => return Err(CommandError::network(format!("上传失败，服务器返回状态: {}", status))),
+    }
+    let result = response
+        .json::<crate::modules::file_transfer::UploadResponse>()
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Serialization, format!("解析响应失败: {}", e)))?;
+    log::info!("✅ 上传完成: {} ({} 字节)", result.path, result.size);
+    Ok(result)
+}
+
 /// 流式下载远程文件到本地磁盘（边下边写，避免大文件占满内存导致 OOM/卡死）
 ///
 /// - 自动携带共享密码头（x-share-password），解决有密码共享下载失败的问题
 /// - 通过 `download-progress` 事件上报进度（taskId/downloaded/total）
-/// - 支持通过 `cancel_remote_download` 取消
+/// - 支持通过 `cancel_remote_download` 取消，通过 `pause_download` / `resume_download` 暂停与续传
+/// - 完成后计算 SHA-256 并校验大小，向共享者上报下载回执
 #[tauri::command]
 pub async fn download_remote_file(
     task_id: String,
@@ -2624,68 +5065,181 @@ pub async fn download_remote_file(
     save_path: String,
     password: Option<String>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    use futures_util::StreamExt;
-    use tokio::io::AsyncWriteExt;
-
+) -> Result<(), CommandError> {
     log::info!("⬇️ 开始流式下载: task={} {}/{} -> {}", task_id, peer_ip, share_id, save_path);
+    crate::modules::path_guard::check_path(&save_path)?;
+    let record = crate::modules::download_tasks::DownloadTaskRecord::new(task_id, peer_ip, share_id, file_path, save_path);
+    run_remote_download(record, password, app_handle).await
+}
 
-    let cancel_flag = Arc::new(AtomicBool::new(false));
-    download_cancels().insert(task_id.clone(), cancel_flag.clone());
+/// 把对方的整个共享文件夹打包为 ZIP 流式下载到本地
+///
+/// 共享端边打包边发送，不生成临时文件；进度通过 `download-progress` 事件上报，
+/// 同样支持 `pause_download` / `resume_download` 续传与 `cancel_remote_download` 取消。
+#[tauri::command]
+pub async fn download_share_as_zip(
+    task_id: String,
+    peer_ip: String,
+    share_id: String,
+    save_path: String,
+    password: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("⬇️ 开始打包下载整个共享: task={} {}/{} -> {}", task_id, peer_ip, share_id, save_path);
+    crate::modules::path_guard::check_path(&save_path)?;
+    let record = crate::modules::download_tasks::DownloadTaskRecord::whole_share(task_id, peer_ip, share_id, save_path);
+    run_remote_download(record, password, app_handle).await
+}
 
-    // 用闭包包裹，确保无论成功失败都能清理取消标志
-    let result: Result<(), String> = async {
-        let url = format!(
-            "http://{}:14539/api/shares/{}/download/{}",
-            peer_ip,
-            share_id,
-            urlencoding::encode(&file_path)
-        );
+/// 暂停正在进行的远程文件下载（保留已下载部分，可通过 `resume_download` 续传）
+#[tauri::command]
+pub fn pause_download(id: String) -> Result<(), CommandError> {
+    let control = crate::modules::download_tasks::active_downloads()
+        .get(&id)
+        .map(|c| c.clone())
+        .ok_or_else(|| CommandError::not_found(format!("下载任务不存在或未在进行: {}", id)))?;
+    control.pause.store(true, Ordering::Relaxed);
+    log::info!("⏸️ 已请求暂停下载: {}", id);
+    Ok(())
+}
+
+/// 从断点继续已暂停（或上次异常退出中断）的下载
+///
+/// # 参数
+/// * `id` - 下载任务ID
+/// * `password` - 共享密码（任务记录不保存密码，有密码的共享需要重新提供）
+#[tauri::command]
+pub async fn resume_download(
+    id: String,
+    password: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    use crate::modules::download_tasks::{active_downloads, download_store};
+
+    if active_downloads().contains_key(&id) {
+        return Err(CommandError::validation("下载任务正在进行"));
+    }
+    let record = download_store()
+        .lock()
+        .get(&id)
+        .ok_or_else(|| CommandError::not_found(format!("下载任务不存在: {}", id)))?;
+    log::info!("▶️ 恢复下载: task={} 从 {} 字节处继续", id, record.downloaded);
+    run_remote_download(record, password, app_handle).await
+}
+
+/// 获取可续传的下载任务（含上次异常退出时未完成的下载）
+#[tauri::command]
+pub fn get_resumable_downloads() -> Vec<crate::modules::download_tasks::DownloadTaskRecord> {
+    crate::modules::download_tasks::download_store().lock().list()
+}
+
+/// 执行（或续传）单个远程文件下载
+///
+/// 暂停时返回 `Err("已暂停")` 并保留任务记录；取消时删除残留文件与任务记录。
+async fn run_remote_download(
+    mut record: crate::modules::download_tasks::DownloadTaskRecord,
+    password: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    use crate::modules::download_tasks::{
+        active_downloads, download_store, hash_file_prefix, plan_resume, write_stream, DownloadControl,
+        DownloadTaskStatus, ResumePlan, StreamOutcome, PROGRESS_PERSIST_INTERVAL,
+    };
+    use sha2::{Digest, Sha256};
+    use tokio::io::AsyncSeekExt;
+
+    announce_share_activity(&app_handle, &record.share_id, crate::modules::share_presence::ShareActivity::Downloading);
+
+    let task_id = record.task_id.clone();
+    let control = Arc::new(DownloadControl::default());
+    active_downloads().insert(task_id.clone(), control.clone());
+
+    // 用闭包包裹，确保无论成功失败都能清理控制标志
+    let result: Result<(), CommandError> = async {
+        let url = crate::modules::http_tls::peer_url(&record.peer_ip, crate::modules::service_ports::peer_file_port(&record.peer_ip), &record.remote_path());
 
-        let client = reqwest::Client::new();
+        // 本地残留文件比记录短时以文件为准（崩溃时记录可能领先于落盘的数据）
+        let local_len = tokio::fs::metadata(&record.save_path).await.map(|m| m.len()).unwrap_or(0);
+        let offset = record.downloaded.min(local_len);
+
+        let client = crate::modules::http_tls::client_builder().build().map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
         let mut req = client.get(&url);
         if let Some(pwd) = &password {
             if !pwd.is_empty() {
                 req = req.header("x-share-password", pwd);
             }
         }
+        if offset > 0 {
+            req = req.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+            if let Some(etag) = &record.etag {
+                req = req.header(reqwest::header::IF_RANGE, etag);
+            }
+        }
 
-        let resp = req.send().await.map_err(|e| format!("请求失败: {}", e))?;
+        let resp = crate::modules::http_auth::send_signed(req).await.map_err(|e| CommandError::network(format!("请求失败: {}", e)))?;
         let status = resp.status();
         if status.as_u16() == 401 {
-            return Err("访问被拒绝：密码错误或未提供密码".to_string());
+            return Err(CommandError::permission("访问被拒绝：密码错误或未提供密码"));
         }
-        if !status.is_success() {
-            return Err(format!("下载失败: HTTP {}", status));
+        if status.as_u16() == 503 && record.whole_share {
+            return Err(CommandError::network("对方正在处理其他打包下载，请稍后重试"));
         }
-
-        let total = resp.content_length().unwrap_or(0);
+        let content_range = resp
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let plan = plan_resume(offset, status.as_u16(), content_range.as_deref())?;
+        let remaining = resp.content_length().unwrap_or(0);
+        record.etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
 
         // 确保父目录存在
-        if let Some(parent) = std::path::Path::new(&save_path).parent() {
+        if let Some(parent) = std::path::Path::new(&record.save_path).parent() {
             let _ = tokio::fs::create_dir_all(parent).await;
         }
 
-        let mut file = tokio::fs::File::create(&save_path)
-            .await
-            .map_err(|e| format!("创建文件失败: {}", e))?;
-
-        let mut downloaded: u64 = 0;
-        let mut stream = resp.bytes_stream();
-        let mut last_emit = std::time::Instant::now();
-
-        while let Some(chunk) = stream.next().await {
-            // 检查取消
-            if cancel_flag.load(Ordering::Relaxed) {
-                drop(file);
-                let _ = tokio::fs::remove_file(&save_path).await;
-                return Err("已取消".to_string());
+        let (mut file, mut hasher) = match plan {
+            ResumePlan::Fresh => {
+                let file = tokio::fs::File::create(&record.save_path)
+                    .await
+                    .map_err(|e| CommandError::io(format!("创建文件失败: {}", e)))?;
+                (file, Sha256::new())
+            }
+            ResumePlan::Continue { offset } => {
+                let hasher = hash_file_prefix(std::path::Path::new(&record.save_path), offset)
+                    .await
+                    .map_err(|e| CommandError::io(format!("读取已下载部分失败: {}", e)))?;
+                let mut file = tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&record.save_path)
+                    .await
+                    .map_err(|e| CommandError::io(format!("打开文件失败: {}", e)))?;
+                file.set_len(offset).await.map_err(|e| CommandError::io(format!("截断文件失败: {}", e)))?;
+                file.seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| CommandError::io(format!("定位文件失败: {}", e)))?;
+                (file, hasher)
             }
+        };
+        let offset = match plan {
+            ResumePlan::Fresh => 0,
+            ResumePlan::Continue { offset } => offset,
+        };
+        let total = if remaining == 0 { 0 } else { offset + remaining };
+        record.downloaded = offset;
+        record.total = total;
+        record.status = DownloadTaskStatus::Downloading;
+        download_store().lock().upsert(record.clone());
 
-            let chunk = chunk.map_err(|e| format!("下载中断: {}", e))?;
-            file.write_all(&chunk)
-                .await
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+        let mut downloaded = offset;
+        let mut last_emit = std::time::Instant::now();
+        let mut last_persist = std::time::Instant::now();
+        let outcome = write_stream(&mut file, resp.bytes_stream(), &control, |chunk| {
+            hasher.update(chunk);
             downloaded += chunk.len() as u64;
 
             // 每 200ms 上报一次进度
@@ -2700,9 +5254,39 @@ pub async fn download_remote_file(
                 );
                 last_emit = std::time::Instant::now();
             }
+            // 定期持久化进度，异常退出后可从这里续传
+            if last_persist.elapsed() >= PROGRESS_PERSIST_INTERVAL {
+                record.downloaded = downloaded;
+                download_store().lock().upsert(record.clone());
+                last_persist = std::time::Instant::now();
+            }
+        })
+        .await;
+        drop(file);
+
+        match outcome {
+            Ok(StreamOutcome::Completed) => {}
+            Ok(StreamOutcome::Paused) => {
+                record.downloaded = downloaded;
+                record.status = DownloadTaskStatus::Paused;
+                download_store().lock().upsert(record.clone());
+                log::info!("⏸️ 下载已暂停: task={} ({} / {} 字节)", task_id, downloaded, total);
+                return Err("已暂停".into());
+            }
+            Ok(StreamOutcome::Cancelled) => {
+                let _ = tokio::fs::remove_file(&record.save_path).await;
+                download_store().lock().remove(&task_id);
+                return Err("已取消".into());
+            }
+            Err(e) => {
+                // 网络中断等错误：保留进度，之后可以续传
+                record.downloaded = downloaded;
+                record.status = DownloadTaskStatus::Paused;
+                download_store().lock().upsert(record.clone());
+                return Err(CommandError::network(e));
+            }
         }
-
-        file.flush().await.map_err(|e| format!("刷新文件失败: {}", e))?;
+        download_store().lock().remove(&task_id);
 
         // 最后上报一次 100% 进度
         let _ = app_handle.emit(
@@ -2715,21 +5299,85 @@ pub async fn download_remote_file(
         );
 
         log::info!("✅ 流式下载完成: task={} ({} 字节)", task_id, downloaded);
-        Ok(())
+
+        // 整个共享的打包下载没有对应的单个文件，不上报下载回执
+        if record.whole_share {
+            if total != 0 && downloaded != total {
+                return Err(CommandError::network(format!("打包下载不完整：期望 {} 字节，实际 {} 字节", total, downloaded)));
+            }
+            return Ok(());
+        }
+
+        // 向共享者上报下载回执，由共享者比对 SHA-256（上报失败时只能依据大小判断）
+        let receipt = DownloadCompleteRequest {
+            file_path: record.file_path.clone(),
+            size: downloaded,
+            sha256: Some(format!("{:x}", hasher.finalize())),
+            size_matched: total == 0 || downloaded == total,
+        };
+        if !receipt.size_matched {
+            log::warn!("⚠️ 下载大小不一致: task={} 期望 {} 字节，实际 {} 字节", task_id, total, downloaded);
+        }
+        let mut receipt_req = client
+            .post(download_complete_url(&record.peer_ip, &record.share_id))
+            .timeout(std::time::Duration::from_secs(5))
+            .json(&receipt);
+        if let Some(pwd) = &password {
+            if !pwd.is_empty() {
+                receipt_req = receipt_req.header("x-share-password", pwd);
+            }
+        }
+        let hash_verified = match crate::modules::http_auth::send_signed(receipt_req).await {
+            Ok(r) if r.status().is_success() => match r.json::<DownloadCompleteResponse>().await {
+                Ok(response) => Some(response.verified),
+                Err(e) => {
+                    log::warn!("⚠️ 解析下载回执响应失败（对方可能是旧版本）: {}", e);
+                    None
+                }
+            },
+            Ok(r) => {
+                log::warn!("⚠️ 上报下载回执失败: HTTP {}", r.status());
+                None
+            }
+            Err(e) => {
+                log::warn!("⚠️ 上报下载回执失败: {}", e);
+                None
+            }
+        };
+        log::info!("📬 下载回执: task={} size_matched={} hash_verified={:?}", task_id, receipt.size_matched, hash_verified);
+
+        if !receipt.size_matched {
+            return Err(CommandError::network(format!("下载不完整：期望 {} 字节，实际 {} 字节", total, downloaded)));
+        }
+        match hash_verified {
+            Some(false) => Err(CommandError::new(ErrorCode::File, "文件校验失败：SHA-256 与共享者的文件不一致")),
+            Some(true) => Ok(()),
+            None => {
+                log::warn!("⚠️ 未能与共享者比对 SHA-256，仅完成了大小检查: task={}", task_id);
+                Ok(())
+            }
+        }
     }
     .await;
 
-    download_cancels().remove(&task_id);
+    active_downloads().remove(&task_id);
     result
 }
 
-/// 取消正在进行的远程文件下载
+/// 取消远程文件下载（进行中的下载停止并删除残留文件；已暂停的任务直接丢弃）
 #[tauri::command]
 pub fn cancel_remote_download(task_id: String) {
     if let Some(flag) = download_cancels().get(&task_id) {
         flag.store(true, Ordering::Relaxed);
         log::info!("🛑 已请求取消下载: {}", task_id);
     }
+    if let Some(control) = crate::modules::download_tasks::active_downloads().get(&task_id) {
+        control.cancel.store(true, Ordering::Relaxed);
+        log::info!("🛑 已请求取消下载: {}", task_id);
+    } else if let Some(record) = crate::modules::download_tasks::download_store().lock().remove(&task_id) {
+        let _ = std::fs::remove_file(&record.save_path);
+        log::info!("🛑 已丢弃暂停的下载: {}", task_id);
+    }
 }
 
 /// 流式批量打包下载：POST file_paths 到对端 batch-download，边收边写盘到 save_path
@@ -2742,18 +5390,19 @@ pub async fn download_remote_batch(
     save_path: String,
     password: Option<String>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use futures_util::StreamExt;
     use tokio::io::AsyncWriteExt;
 
     log::info!("⬇️ 开始流式批量下载: task={} {}/{} ({} 个文件)", task_id, peer_ip, share_id, file_paths.len());
+    crate::modules::path_guard::check_path(&save_path)?;
 
     let cancel_flag = Arc::new(AtomicBool::new(false));
     download_cancels().insert(task_id.clone(), cancel_flag.clone());
 
-    let result: Result<(), String> = async {
-        let url = format!("http://{}:14539/api/shares/{}/batch-download", peer_ip, share_id);
-        let client = reqwest::Client::new();
+    let result: Result<(), CommandError> = async {
+        let url = crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_file_port(&peer_ip), &format!("/api/shares/{}/batch-download", share_id));
+        let client = crate::modules::http_tls::client_builder().build().map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
         let mut req = client
             .post(&url)
             .json(&serde_json::json!({ "file_paths": file_paths }));
@@ -2763,13 +5412,13 @@ pub async fn download_remote_batch(
             }
         }
 
-        let resp = req.send().await.map_err(|e| format!("请求失败: {}", e))?;
+        let resp = crate::modules::http_auth::send_signed(req).await.map_err(|e| CommandError::network(format!("请求失败: {}", e)))?;
         let status = resp.status();
         if status.as_u16() == 401 {
-            return Err("访问被拒绝：密码错误或未提供密码".to_string());
+            return Err(CommandError::permission("访问被拒绝：密码错误或未提供密码"));
         }
         if !status.is_success() {
-            return Err(format!("打包下载失败: HTTP {}", status));
+            return Err(CommandError::network(format!("打包下载失败: HTTP {}", status)));
         }
 
         let total = resp.content_length().unwrap_or(0);
@@ -2778,7 +5427,7 @@ pub async fn download_remote_batch(
         }
         let mut file = tokio::fs::File::create(&save_path)
             .await
-            .map_err(|e| format!("创建文件失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("创建文件失败: {}", e)))?;
 
         let mut downloaded: u64 = 0;
         let mut stream = resp.bytes_stream();
@@ -2787,10 +5436,10 @@ pub async fn download_remote_batch(
             if cancel_flag.load(Ordering::Relaxed) {
                 drop(file);
                 let _ = tokio::fs::remove_file(&save_path).await;
-                return Err("已取消".to_string());
+                return Err("已取消".into());
             }
-            let chunk = chunk.map_err(|e| format!("下载中断: {}", e))?;
-            file.write_all(&chunk).await.map_err(|e| format!("写入文件失败: {}", e))?;
+            let chunk = chunk.map_err(|e| CommandError::network(format!("下载中断: {}", e)))?;
+            file.write_all(&chunk).await.map_err(|e| CommandError::io(format!("写入文件失败: {}", e)))?;
             downloaded += chunk.len() as u64;
             if last_emit.elapsed().as_millis() >= 200 {
                 let _ = app_handle.emit(
@@ -2800,7 +5449,7 @@ pub async fn download_remote_batch(
                 last_emit = std::time::Instant::now();
             }
         }
-        file.flush().await.map_err(|e| format!("刷新文件失败: {}", e))?;
+        file.flush().await.map_err(|e| CommandError::io(format!("刷新文件失败: {}", e)))?;
         let _ = app_handle.emit(
             "download-progress",
             serde_json::json!({ "taskId": task_id, "downloaded": downloaded, "total": if total == 0 { downloaded } else { total } }),
@@ -2961,16 +5610,28 @@ pub async fn detect_security_software() -> Vec<String> {
     }
 }
 
+/// 子服务健康检查（设置页"诊断"按钮一键展示，也可附在问题反馈里）
+///
+/// # 返回
+/// * `Ok(HealthReport)` - EasyTier 进程、虚拟 IP、文件 / 聊天服务器、P2P 信令与成员数量的汇总
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn health_check(state: State<'_, AppState>) -> Result<crate::modules::app_core::HealthReport, CommandError> {
+    let report = state.core.lock().await.health_check().await;
+    if !report.issues.is_empty() {
+        log::warn!("健康检查发现问题: {:?}", report.issues);
+    }
+    Ok(report)
+}
+
 /// 一键导出日志：将日志目录打包为 zip，返回生成的 zip 路径
 #[tauri::command]
-pub async fn export_logs(_app_handle: tauri::AppHandle) -> Result<String, String> {
-    // 日志目录：%LOCALAPPDATA%/MCTier（与 get_log_file_path 保持一致）
-    let log_dir = dirs::data_local_dir()
-        .map(|d| d.join("MCTier"))
-        .ok_or_else(|| "无法获取日志目录".to_string())?;
+pub async fn export_logs(_app_handle: tauri::AppHandle) -> Result<String, CommandError> {
+    // 日志目录：%LOCALAPPDATA%/MCTier，含轮转出的历史日志
+    let log_dir = crate::modules::log_file::log_dir();
 
     if !log_dir.exists() {
-        return Err("日志目录不存在".to_string());
+        return Err(CommandError::not_found("日志目录不存在"));
     }
 
     // 输出到桌面（无法获取时回退到日志目录）
@@ -2985,16 +5646,16 @@ pub async fn export_logs(_app_handle: tauri::AppHandle) -> Result<String, String
     // 在阻塞线程里打包，避免阻塞异步运行时
     let log_dir_clone = log_dir.clone();
     let zip_path_clone = zip_path.clone();
-    tokio::task::spawn_blocking(move || -> Result<(), String> {
+    tokio::task::spawn_blocking(move || -> Result<(), CommandError> {
         let zip_file = std::fs::File::create(&zip_path_clone)
-            .map_err(|e| format!("创建zip失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::File, format!("创建zip失败: {}", e)))?;
         let mut zip = zip::ZipWriter::new(zip_file);
         let options = zip::write::SimpleFileOptions::default()
             .compression_method(zip::CompressionMethod::Deflated)
             .compression_level(Some(6));
 
         let entries = std::fs::read_dir(&log_dir_clone)
-            .map_err(|e| format!("读取日志目录失败: {}", e))?;
+            .map_err(|e| CommandError::io(format!("读取日志目录失败: {}", e)))?;
         let mut count = 0;
         for entry in entries.flatten() {
             let path = entry.path();
@@ -3014,29 +5675,38 @@ pub async fn export_logs(_app_handle: tauri::AppHandle) -> Result<String, String
                 }
             }
         }
-        zip.finish().map_err(|e| format!("完成zip失败: {}", e))?;
+        zip.finish().map_err(|e| CommandError::new(ErrorCode::File, format!("完成zip失败: {}", e)))?;
         if count == 0 {
-            return Err("没有可导出的日志文件".to_string());
+            return Err(CommandError::not_found("没有可导出的日志文件"));
         }
         Ok(())
     })
     .await
     .map_err(|e| format!("打包任务失败: {}", e))??;
 
+    // 允许前端随后通过 open_file_location 定位导出的日志包
+    crate::modules::path_guard::allow(&zip_path);
     Ok(zip_path.to_string_lossy().to_string())
 }
 
-/// 诊断文件共享连接
+/// 诊断与对方的连接
+/// 
+/// 除 ping 虚拟 IP 与访问文件服务外，逐个探测关键端口（文件共享 TCP、聊天 TCP、P2P 信令 UDP，
+/// 使用对方宣告的端口，默认 14539 / 14540 / 47777），返回每个端口的可达状态与针对性建议，
+/// 用于定位"语音通但文件不通"等情况。
 /// 
 /// # 参数
 /// * `peer_ip` - 对方的虚拟IP
 /// 
 /// # 返回
-/// * `Ok(String)` - 诊断结果（JSON格式）
-/// * `Err(String)` - 错误信息
+/// * `Ok(String)` - 诊断结果（JSON格式：tests / ports / suggestions）
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, String> {
+pub async fn diagnose_file_share_connection(peer_ip: String, state: State<'_, AppState>) -> Result<String, CommandError> {
+    use network_diagnostics::{check_service_port, PeerService, DEFAULT_SIGNALING_PORT};
+
     log::info!("🔍 开始诊断文件共享连接: {}", peer_ip);
+    let ip: std::net::IpAddr = peer_ip.parse().map_err(|_| CommandError::validation(format!("无效的虚拟IP: {}", peer_ip)))?;
     
     let mut results = serde_json::json!({
         "peer_ip": peer_ip,
@@ -3045,8 +5715,7 @@ pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, S
     
     // 测试1: Ping虚拟IP
     log::info!("📡 测试1: Ping虚拟IP...");
-    let ping_result = ping_virtual_ip(peer_ip.clone()).await;
-    let ping_success = ping_result.is_ok() && ping_result.unwrap_or(false);
+    let ping_success = network_diagnostics::ping_virtual_ip(&peer_ip).await.success;
     results["tests"].as_array_mut().unwrap().push(serde_json::json!({
         "name": "Ping虚拟IP",
         "success": ping_success,
@@ -3057,15 +5726,34 @@ pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, S
         }
     }));
     
-    // 测试2: 检查HTTP服务器端口
-    log::info!("🔌 测试2: 检查HTTP服务器端口...");
-    let url = format!("http://{}:14539/api/shares", peer_ip);
-    let client = reqwest::Client::builder()
+    // 测试2: 逐个探测关键端口（信令使用对方实际绑定的端口，并以是否收到过对方信令辅助判断 UDP）
+    log::info!("🔌 测试2: 探测关键端口...");
+    let p2p_signaling = state.get_p2p_signaling();
+    let signaling_peer = p2p_signaling.lock().await.get_peers().await.into_iter().find(|p| p.addr.ip() == ip);
+    let signaling_seen = signaling_peer.is_some();
+    let signaling_port = signaling_peer.map(|p| p.addr.port()).unwrap_or(DEFAULT_SIGNALING_PORT);
+    let (file_port, chat_port, signaling) = tokio::join!(
+        check_service_port(ip, PeerService::FileShare, crate::modules::service_ports::peer_file_port(&peer_ip), signaling_seen),
+        check_service_port(ip, PeerService::Chat, crate::modules::service_ports::peer_chat_port(&peer_ip), signaling_seen),
+        check_service_port(ip, PeerService::Signaling, signaling_port, signaling_seen),
+    );
+    let ports = vec![file_port, chat_port, signaling];
+    
+    let mut suggestions: Vec<String> = Vec::new();
+    if !ping_success && ports.iter().all(|p| !p.reachable) {
+        suggestions.push("虚拟网络未连通，请确认双方都在同一大厅且 EasyTier 已连接".to_string());
+    }
+    suggestions.extend(ports.iter().filter_map(|p| p.suggestion.clone()));
+    
+    // 测试3: 检查HTTP服务器（端口可达但 TLS / 服务异常时在这里暴露）
+    log::info!("🌐 测试3: 检查HTTP服务器...");
+    let url = crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_file_port(&peer_ip), "/api/shares");
+    let client = crate::modules::http_tls::client_builder()
         .timeout(std::time::Duration::from_secs(3))
         .build()
-        .map_err(|e| format!("创建HTTP客户端失败: {}", e))?;
+        .map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
     
-    let http_result = client.get(&url).send().await;
+    let http_result = crate::modules::http_auth::send_signed(client.get(&url)).await;
     let http_message = if http_result.is_ok() {
         "✅ HTTP文件服务器可访问".to_string()
     } else {
@@ -3078,9 +5766,9 @@ pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, S
         "message": http_message
     }));
     
-    // 测试3: 获取共享列表
+    // 测试4: 获取共享列表
     if http_result.is_ok() {
-        log::info!("📋 测试3: 获取共享列表...");
+        log::info!("📋 测试4: 获取共享列表...");
         match get_remote_shares(peer_ip.clone()).await {
             Ok(shares) => {
                 results["tests"].as_array_mut().unwrap().push(serde_json::json!({
@@ -3097,8 +5785,13 @@ pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, S
                 }));
             }
         }
+    } else if ports[0].reachable {
+        suggestions.push("文件共享端口可连接但 HTTP 请求失败，对方文件服务可能异常或双方的 TLS 设置不一致".to_string());
     }
     
+    results["ports"] = serde_json::to_value(&ports).map_err(|e| CommandError::new(ErrorCode::Serialization, e.to_string()))?;
+    results["suggestions"] = serde_json::json!(suggestions);
+    
     log::info!("✅ 诊断完成");
     
     Ok(serde_json::to_string_pretty(&results).unwrap())
@@ -3114,50 +5807,56 @@ pub async fn diagnose_file_share_connection(peer_ip: String) -> Result<String, S
 /// 
 /// # 返回
 /// * `Ok(Vec<String>)` - 解压的文件列表
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn extract_zip(zip_path: String, extract_dir: String) -> Result<Vec<String>, String> {
+pub async fn extract_zip(zip_path: String, extract_dir: String) -> Result<Vec<String>, CommandError> {
     log::info!("📦 解压ZIP文件: {} -> {}", zip_path, extract_dir);
     
     use std::fs::File;
-    use std::path::Path;
     use zip::ZipArchive;
     
+    let zip_path = crate::modules::path_guard::check_path(&zip_path)?;
+    let extract_dir = crate::modules::path_guard::check_path(&extract_dir)?;
+    
     // 打开ZIP文件
     let file = File::open(&zip_path)
-        .map_err(|e| format!("打开ZIP文件失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::File, format!("打开ZIP文件失败: {}", e)))?;
     
     let mut archive = ZipArchive::new(file)
-        .map_err(|e| format!("读取ZIP文件失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::File, format!("读取ZIP文件失败: {}", e)))?;
     
     let mut extracted_files = Vec::new();
     
     // 解压所有文件
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)
-            .map_err(|e| format!("读取ZIP条目失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::File, format!("读取ZIP条目失败: {}", e)))?;
         
-        let outpath = Path::new(&extract_dir).join(file.name());
+        // 条目名含 .. 或绝对路径时会逃出解压目录，直接拒绝
+        let Some(entry_path) = file.enclosed_name() else {
+            return Err(CommandError::validation(format!("ZIP 条目路径非法: {}", file.name())));
+        };
+        let outpath = extract_dir.join(entry_path);
         
         if file.is_dir() {
             log::info!("📁 创建目录: {:?}", outpath);
             std::fs::create_dir_all(&outpath)
-                .map_err(|e| format!("创建目录失败: {}", e))?;
+                .map_err(|e| CommandError::io(format!("创建目录失败: {}", e)))?;
         } else {
             log::info!("📄 解压文件: {:?}", outpath);
             
             // 确保父目录存在
             if let Some(parent) = outpath.parent() {
                 std::fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建父目录失败: {}", e))?;
+                    .map_err(|e| CommandError::io(format!("创建父目录失败: {}", e)))?;
             }
             
             // 写入文件
             let mut outfile = File::create(&outpath)
-                .map_err(|e| format!("创建文件失败: {}", e))?;
+                .map_err(|e| CommandError::io(format!("创建文件失败: {}", e)))?;
             
             std::io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("写入文件失败: {}", e))?;
+                .map_err(|e| CommandError::io(format!("写入文件失败: {}", e)))?;
             
             extracted_files.push(outpath.to_string_lossy().to_string());
         }
@@ -3174,16 +5873,17 @@ pub async fn extract_zip(zip_path: String, extract_dir: String) -> Result<Vec<St
 /// 
 /// # 返回
 /// * `Ok(())` - 成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn delete_file(path: String) -> Result<(), String> {
+pub async fn delete_file(path: String) -> Result<(), CommandError> {
     log::info!("🗑️ 删除文件: {}", path);
     
     use tokio::fs;
     
-    fs::remove_file(&path)
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
+    fs::remove_file(&path_obj)
         .await
-        .map_err(|e| format!("删除文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("删除文件失败: {}", e)))?;
     
     log::info!("✅ 文件已删除: {}", path);
     Ok(())
@@ -3197,27 +5897,28 @@ pub async fn delete_file(path: String) -> Result<(), String> {
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn save_file(path: String, data: Vec<u8>) -> Result<(), String> {
+pub async fn save_file(path: String, data: Vec<u8>) -> Result<(), CommandError> {
     log::info!("保存文件: {}, 大小: {} bytes", path, data.len());
     
     use tokio::fs;
-    use std::path::Path;
+    
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
     
     // 确保父目录存在
-    if let Some(parent) = Path::new(&path).parent() {
+    if let Some(parent) = path_obj.parent() {
         if !parent.exists() {
             fs::create_dir_all(parent)
                 .await
-                .map_err(|e| format!("创建目录失败: {}", e))?;
+                .map_err(|e| CommandError::io(format!("创建目录失败: {}", e)))?;
         }
     }
     
     // 写入文件
-    fs::write(&path, data)
+    fs::write(&path_obj, data)
         .await
-        .map_err(|e| format!("写入文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("写入文件失败: {}", e)))?;
     
     log::info!("✅ 文件保存成功: {}", path);
     Ok(())
@@ -3230,9 +5931,9 @@ pub async fn save_file(path: String, data: Vec<u8>) -> Result<(), String> {
 /// 
 /// # 返回
 /// * `Ok(String)` - 保存的文件路径
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn save_chat_image(image_data: String) -> Result<String, String> {
+pub async fn save_chat_image(image_data: String) -> Result<String, CommandError> {
     use tokio::fs;
     use base64::{Engine as _, engine::general_purpose};
     
@@ -3241,13 +5942,13 @@ pub async fn save_chat_image(image_data: String) -> Result<String, String> {
     // 解码Base64数据
     let bytes = general_purpose::STANDARD
         .decode(&image_data)
-        .map_err(|e| format!("Base64解码失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::Serialization, format!("Base64解码失败: {}", e)))?;
     
     log::info!("解码后图片大小: {} bytes", bytes.len());
     
     // 获取下载目录
     let download_dir = dirs::download_dir()
-        .ok_or_else(|| "无法获取下载目录".to_string())?;
+        .ok_or_else(|| CommandError::not_found("无法获取下载目录"))?;
     
     // 生成文件名
     let timestamp = std::time::SystemTime::now()
@@ -3265,7 +5966,7 @@ pub async fn save_chat_image(image_data: String) -> Result<String, String> {
     // 写入文件
     fs::write(&file_path, bytes)
         .await
-        .map_err(|e| format!("写入文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("写入文件失败: {}", e)))?;
     
     log::info!("✅ 聊天图片保存成功: {}", path_str);
     Ok(path_str)
@@ -3278,17 +5979,18 @@ pub async fn save_chat_image(image_data: String) -> Result<String, String> {
 /// 
 /// # 返回
 /// * `Ok(Vec<u8>)` - 文件内容
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn read_file(path: String) -> Result<Vec<u8>, String> {
+pub async fn read_file(path: String) -> Result<Vec<u8>, CommandError> {
     log::info!("读取文件: {}", path);
     
     use tokio::fs;
     
     // 读取文件
-    let data = fs::read(&path)
+    let path_obj = crate::modules::path_guard::check_path(&path)?;
+    let data = fs::read(&path_obj)
         .await
-        .map_err(|e| format!("读取文件失败: {}", e))?;
+        .map_err(|e| CommandError::io(format!("读取文件失败: {}", e)))?;
     
     log::info!("✅ 文件读取成功: {}, 大小: {} bytes", path, data.len());
     Ok(data)
@@ -3296,7 +5998,9 @@ pub async fn read_file(path: String) -> Result<Vec<u8>, String> {
 
 // ==================== P2P 聊天命令 ====================
 
-use crate::modules::chat_service::{ChatMessage as ChatServiceMessage, MessageType, SendMessageRequest};
+use crate::modules::chat_service::{
+    prepare_chat_image, sanitize_references, ChatMessage as ChatServiceMessage, MessageType, SendMessageRequest, DEFAULT_CHAT_IMAGE_MAX_BYTES,
+};
 
 /// 发送P2P聊天消息
 /// 
@@ -3307,11 +6011,14 @@ use crate::modules::chat_service::{ChatMessage as ChatServiceMessage, MessageTyp
 /// * `message_type` - 消息类型（text/image）
 /// * `image_data` - 图片数据（可选）
 /// * `peer_ips` - 目标玩家的虚拟IP列表
+/// * `reply_to` - 回复（引用）的消息 ID（可选）
+/// * `mentions` - 被 @ 的玩家 ID（可选）
 /// 
 /// # 返回
 /// * `Ok(())` - 发送成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn send_p2p_chat_message(
     player_id: String,
     player_name: String,
@@ -3319,14 +6026,12 @@ pub async fn send_p2p_chat_message(
     message_type: String,
     image_data: Option<Vec<u8>>,
     peer_ips: Vec<String>,
+    reply_to: Option<String>,
+    mentions: Option<Vec<String>>,
     state: State<'_, AppState>,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, CommandError> {
     log::info!("💬 发送P2P聊天消息: {} - {}", player_name, content);
     
-    let core = state.core.lock().await;
-    let chat_service = core.get_chat_service();
-    let chat_svc = chat_service.lock().await;
-    
     // 解析消息类型
     let msg_type = match message_type.as_str() {
         "image" => MessageType::Image,
@@ -3338,15 +6043,45 @@ pub async fn send_p2p_chat_message(
         _ => MessageType::Text,
     };
     
+    // 图片消息：校验大小并重编码压缩，失败时直接返回错误，不发送损坏数据
+    let image_data = if msg_type == MessageType::Image {
+        let config_manager = state.get_config_manager();
+        let max_bytes = config_manager
+            .lock()
+            .await
+            .get_config()
+            .chat_image_max_bytes
+            .unwrap_or(DEFAULT_CHAT_IMAGE_MAX_BYTES);
+        let raw = image_data.unwrap_or_default();
+        let prepared = tokio::task::spawn_blocking(move || prepare_chat_image(&raw, max_bytes))
+            .await
+            .map_err(|e| format!("图片处理任务失败: {}", e))?
+            .map_err(|e| {
+                log::warn!("⚠️ 图片消息被拒绝: {}", e);
+                CommandError::validation(e.to_string())
+            })?;
+        Some(prepared)
+    } else {
+        image_data
+    };
+    
+    let (reply_to, mentions) = sanitize_references(reply_to, mentions.unwrap_or_default());
+    
+    let chat_service = state.get_chat_service();
+    let chat_svc = chat_service.lock().await;
+    
     // 创建消息
     let message = ChatServiceMessage {
-        id: format!("msg-{}-{}", player_id, std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis()),
+        id: ChatServiceMessage::new_id(&player_id),
         player_id: player_id.clone(),
         player_name: player_name.clone(),
         content: content.clone(),
         message_type: msg_type.clone(),
         timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
         image_data: image_data.clone(),
+        seq: 0,
+        reply_to: reply_to.clone(),
+        mentions: mentions.clone(),
     };
     
     // 保存到本地消息队列
@@ -3357,7 +6092,6 @@ pub async fn send_p2p_chat_message(
     let my_virtual_ip = chat_svc.get_virtual_ip();
     
     drop(chat_svc);
-    drop(core);
     
     // 【修复】过滤掉自己的IP
     let other_peer_ips: Vec<String> = peer_ips.into_iter()
@@ -3375,15 +6109,15 @@ pub async fn send_p2p_chat_message(
     let total = other_peer_ips.len();
 
     // 【优化】使用并发发送，提高图片传输速度
-    let client = reqwest::Client::builder()
+    let client = crate::modules::http_tls::client_builder()
         .timeout(std::time::Duration::from_secs(10)) // 设置超时
         .build()
-        .map_err(|e| format!("创建HTTP客户端失败: {}", e))?;
+        .map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
     
     let mut tasks = Vec::new();
     
     for peer_ip in other_peer_ips {
-        let url = format!("http://{}:14540/api/chat/send", peer_ip);
+        let url = crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_chat_port(&peer_ip), "/api/chat/send");
         let request = SendMessageRequest {
             id: Some(message_id.clone()),
             player_id: player_id.clone(),
@@ -3391,6 +6125,8 @@ pub async fn send_p2p_chat_message(
             content: content.clone(),
             message_type: msg_type.clone(),
             image_data: image_data.clone(),
+            reply_to: reply_to.clone(),
+            mentions: mentions.clone(),
         };
         
         let client_clone = client.clone();
@@ -3400,7 +6136,7 @@ pub async fn send_p2p_chat_message(
         let task = tokio::spawn(async move {
             for attempt in 0..2 {
                 let start = std::time::Instant::now();
-                match client_clone.post(&url_clone).json(&request).send().await {
+                match crate::modules::http_auth::send_signed(client_clone.post(&url_clone).json(&request)).await {
                     Ok(response) => {
                         let elapsed = start.elapsed();
                         if response.status().is_success() {
@@ -3440,34 +6176,29 @@ pub async fn send_p2p_chat_message(
 
 /// 获取P2P聊天消息
 /// 
+/// 按各成员的序号游标增量拉取消息，去重后合并进本机消息队列，再按本机序号返回。
+/// 
 /// # 参数
 /// * `peer_ips` - 玩家的虚拟IP列表
-/// * `since` - 获取此时间戳之后的消息（可选）
+/// * `since` - 本机序号游标：只返回 `seq` 大于该值的消息（可选），下次调用传入返回消息中最大的 `seq`
 /// 
 /// # 返回
-/// * `Ok(Vec<ChatMessage>)` - 消息列表
-/// * `Err(String)` - 错误信息
+/// * `Ok(Vec<ChatMessage>)` - 消息列表（按时间戳排序，时间戳相同时按本机序号）
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn get_p2p_chat_messages(
     peer_ips: Vec<String>,
     since: Option<u64>,
     state: State<'_, AppState>,
-) -> Result<Vec<ChatServiceMessage>, String> {
-    let core = state.core.lock().await;
-    let chat_service = core.get_chat_service();
+) -> Result<Vec<ChatServiceMessage>, CommandError> {
+    let chat_service = state.get_chat_service();
     let chat_svc = chat_service.lock().await;
     
-    // 获取本地消息
-    let mut all_messages = chat_svc.get_local_messages(since);
-    
     // 【修复】获取本机虚拟IP，避免从自己这里重复获取消息
     let my_virtual_ip = chat_svc.get_virtual_ip();
     
-    drop(chat_svc);
-    drop(core);
-    
     // 【修复】过滤掉自己的IP，只从其他玩家获取消息
-    let other_peer_ips: Vec<String> = peer_ips.into_iter()
+    let other_peers: Vec<(String, Option<u64>)> = peer_ips.into_iter()
         .filter(|ip| {
             if let Some(ref my_ip) = my_virtual_ip {
                 ip != my_ip
@@ -3475,71 +6206,82 @@ pub async fn get_p2p_chat_messages(
                 true
             }
         })
+        .map(|ip| {
+            let cursor = chat_svc.peer_cursor(&ip);
+            (ip, cursor)
+        })
         .collect();
     
-    log::info!("📥 [ChatService] 从 {} 个其他玩家获取消息 (排除自己)", other_peer_ips.len());
+    drop(chat_svc);
+    
+    log::info!("📥 [ChatService] 从 {} 个其他玩家获取消息 (排除自己)", other_peers.len());
     
     // 【优化】创建HTTP客户端，设置更短的超时时间以减少延迟
-    let client = reqwest::Client::builder()
+    let client = crate::modules::http_tls::client_builder()
         .timeout(std::time::Duration::from_millis(800)) // 800ms超时
         .connect_timeout(std::time::Duration::from_millis(300)) // 300ms连接超时
         .build()
-        .map_err(|e| format!("创建HTTP客户端失败: {}", e))?;
+        .map_err(|e| CommandError::network(format!("创建HTTP客户端失败: {}", e)))?;
 
     // 【#13 修复】并发从所有其他玩家获取消息。
     // 之前是顺序 await，某个玩家若发送了大图片，其响应体大、耗时长，会阻塞
     // 拉取其它所有玩家的消息（队头阻塞）。改为每个 peer 一个并发任务后，
     // 单个大响应不再拖慢其他人的消息接收。
     let mut tasks = Vec::new();
-    for peer_ip in other_peer_ips {
-        let url = if let Some(ts) = since {
-            format!("http://{}:14540/api/chat/messages?since={}", peer_ip, ts)
+    for (peer_ip, cursor) in other_peers {
+        // 旧版本不认识 after_seq，会返回全部消息，合并时按 ID 去重
+        let url = if let Some(seq) = cursor {
+            crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_chat_port(&peer_ip), &format!("/api/chat/messages?after_seq={}", seq))
         } else {
-            format!("http://{}:14540/api/chat/messages", peer_ip)
+            crate::modules::http_tls::peer_url(&peer_ip, crate::modules::service_ports::peer_chat_port(&peer_ip), "/api/chat/messages")
         };
         let client_clone = client.clone();
-        let peer_ip_clone = peer_ip.clone();
         tasks.push(tokio::spawn(async move {
-            match client_clone.get(&url).send().await {
+            match crate::modules::http_auth::send_signed(client_clone.get(&url)).await {
                 Ok(response) => {
                     if response.status().is_success() {
                         match response.json::<Vec<ChatServiceMessage>>().await {
                             Ok(messages) => {
-                                log::debug!("✅ 从 {} 获取到 {} 条消息", peer_ip_clone, messages.len());
-                                messages
+                                log::debug!("✅ 从 {} 获取到 {} 条消息", peer_ip, messages.len());
+                                Some((peer_ip, messages))
                             }
                             Err(e) => {
-                                log::warn!("⚠️ 解析消息失败 ({}): {}", peer_ip_clone, e);
-                                Vec::new()
+                                log::warn!("⚠️ 解析消息失败 ({}): {}", peer_ip, e);
+                                None
                             }
                         }
                     } else {
-                        log::warn!("⚠️ HTTP请求失败 ({}): 状态码 {}", peer_ip_clone, response.status());
-                        Vec::new()
+                        log::warn!("⚠️ HTTP请求失败 ({}): 状态码 {}", peer_ip, response.status());
+                        None
                     }
                 }
                 Err(e) => {
                     // 超时或连接失败不打印警告，避免日志刷屏
-                    log::debug!("⚠️ 获取消息失败 ({}): {}", peer_ip_clone, e);
-                    Vec::new()
+                    log::debug!("⚠️ 获取消息失败 ({}): {}", peer_ip, e);
+                    None
                 }
             }
         }));
     }
 
-    // 汇总所有并发任务的结果
-    for task in tasks {
-        if let Ok(messages) = task.await {
-            all_messages.extend(messages);
+    // 汇总所有并发任务的结果，合并进本机消息队列（按 ID 去重并分配本机序号）
+    let fetched: Vec<(String, Vec<ChatServiceMessage>)> = futures_util::future::join_all(tasks)
+        .await
+        .into_iter()
+        .filter_map(|result| result.ok().flatten())
+        .collect();
+    let chat_svc = chat_service.lock().await;
+    for (peer_ip, messages) in fetched {
+        let added = chat_svc.merge_peer_messages(&peer_ip, messages);
+        if added > 0 {
+            log::debug!("📥 [ChatService] 合并 {} 条来自 {} 的新消息", added, peer_ip);
         }
     }
+    let mut all_messages = chat_svc.get_local_messages(since);
+    drop(chat_svc);
     
-    // 按时间戳排序
-    all_messages.sort_by_key(|msg| msg.timestamp);
-    
-    // 去重（基于消息ID）
-    let mut seen_ids = std::collections::HashSet::new();
-    all_messages.retain(|msg| seen_ids.insert(msg.id.clone()));
+    // 按时间戳排序；发送方时钟可能有偏差，时间戳相同时保持本机收到的顺序
+    all_messages.sort_by_key(|msg| (msg.timestamp, msg.seq));
     
     Ok(all_messages)
 }
@@ -3548,15 +6290,14 @@ pub async fn get_p2p_chat_messages(
 /// 
 /// # 返回
 /// * `Ok(())` - 清空成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn clear_p2p_chat_messages(
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("🗑️ 清空本地聊天消息");
     
-    let core = state.core.lock().await;
-    let chat_service = core.get_chat_service();
+    let chat_service = state.get_chat_service();
     let chat_svc = chat_service.lock().await;
     
     chat_svc.clear_local_messages();
@@ -3564,6 +6305,21 @@ pub async fn clear_p2p_chat_messages(
     Ok(())
 }
 
+/// 生成一条本地系统消息（如"X 加入了大厅"）
+///
+/// 加入本机聊天记录并通过 `chat-system-message` 事件推送给前端，不转发给其他成员，各端各自生成。
+pub fn post_system_message(app: &tauri::AppHandle, content: String) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(state) = app.try_state::<AppState>() else {
+            return;
+        };
+        let chat_service = state.get_chat_service();
+        let message = chat_service.lock().await.add_system_message(content);
+        let _ = app.emit("chat-system-message", message);
+    });
+}
+
 
 // ==================== 屏幕共享命令 ====================
 
@@ -3580,13 +6336,13 @@ pub async fn clear_p2p_chat_messages(
 /// 
 /// # 返回
 /// * `Ok(())` - 成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn open_screen_viewer_window(
     share_id: String,
     player_name: String,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     log::info!("打开屏幕查看窗口: share_id={}, player_name={}", share_id, player_name);
     
     use tauri::Manager;
@@ -3632,7 +6388,7 @@ pub async fn open_screen_viewer_window(
 /// 打开弹幕覆盖窗口：置顶、透明、无边框、鼠标穿透、覆盖整个主屏幕。
 /// 用于在玩游戏时让聊天消息以弹幕形式飘过屏幕顶部，且不遮挡操作。
 #[tauri::command]
-pub async fn open_danmaku_window(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn open_danmaku_window(app: tauri::AppHandle) -> Result<(), CommandError> {
     use tauri::Manager;
     use tauri::WebviewWindowBuilder;
 
@@ -3679,7 +6435,7 @@ pub async fn open_danmaku_window(app: tauri::AppHandle) -> Result<(), String> {
 
 /// 关闭弹幕覆盖窗口
 #[tauri::command]
-pub async fn close_danmaku_window(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn close_danmaku_window(app: tauri::AppHandle) -> Result<(), CommandError> {
     use tauri::Manager;
     if let Some(window) = app.get_webview_window("danmaku") {
         let _ = window.close();
@@ -3690,7 +6446,7 @@ pub async fn close_danmaku_window(app: tauri::AppHandle) -> Result<(), String> {
 
 /// 切换弹幕窗口的鼠标穿透（用于点击弹幕暂停/复制/下载时临时关闭穿透）
 #[tauri::command]
-pub async fn set_danmaku_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), String> {
+pub async fn set_danmaku_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), CommandError> {
     use tauri::Manager;
     if let Some(window) = app.get_webview_window("danmaku") {
         let _ = window.set_ignore_cursor_events(ignore);
@@ -3701,7 +6457,7 @@ pub async fn set_danmaku_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> R
 /// 打开游戏内 HUD 浮层窗口：置顶、透明、无边框、鼠标穿透，置于主屏右上角。
 /// 显示队友延迟/丢包与"谁在说话"，玩游戏时一眼掌握全队状态。
 #[tauri::command]
-pub async fn open_game_hud_window(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn open_game_hud_window(app: tauri::AppHandle) -> Result<(), CommandError> {
     use tauri::Manager;
     use tauri::WebviewWindowBuilder;
     let label = "gamehud";
@@ -3751,7 +6507,7 @@ pub async fn open_game_hud_window(app: tauri::AppHandle) -> Result<(), String> {
 
 /// 关闭游戏内 HUD 浮层窗口
 #[tauri::command]
-pub async fn close_game_hud_window(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn close_game_hud_window(app: tauri::AppHandle) -> Result<(), CommandError> {
     use tauri::Manager;
     if let Some(window) = app.get_webview_window("gamehud") {
         let _ = window.close();
@@ -3761,7 +6517,7 @@ pub async fn close_game_hud_window(app: tauri::AppHandle) -> Result<(), String>
 
 /// 切换 HUD 窗口鼠标穿透（悬停在 HUD 卡片上时关闭穿透以便拖动）
 #[tauri::command]
-pub async fn set_gamehud_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), String> {
+pub async fn set_gamehud_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), CommandError> {
     use tauri::Manager;
     if let Some(window) = app.get_webview_window("gamehud") {
         let _ = window.set_ignore_cursor_events(ignore);
@@ -3771,7 +6527,7 @@ pub async fn set_gamehud_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> R
 
 /// 获取鼠标相对 HUD 窗口的逻辑坐标（穿透模式下命中检测 HUD 卡片用）
 #[tauri::command]
-pub async fn gamehud_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f64)>, String> {
+pub async fn gamehud_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f64)>, CommandError> {
     use tauri::Manager;
     let window = match app.get_webview_window("gamehud") {
         Some(w) => w,
@@ -3794,7 +6550,7 @@ pub async fn gamehud_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f6
 /// 获取鼠标相对弹幕窗口的逻辑坐标（用于在穿透模式下命中检测弹幕）。
 /// 返回 None 表示窗口不存在或取不到坐标。
 #[tauri::command]
-pub async fn danmaku_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f64)>, String> {
+pub async fn danmaku_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f64)>, CommandError> {
     use tauri::Manager;
     let window = match app.get_webview_window("danmaku") {
         Some(w) => w,
@@ -3816,13 +6572,13 @@ pub async fn danmaku_cursor_pos(app: tauri::AppHandle) -> Result<Option<(f64, f6
 
 /// 保存弹幕图片（data URL）到系统下载文件夹，返回保存的完整路径。
 #[tauri::command]
-pub async fn save_danmaku_image(data_url: String) -> Result<String, String> {
+pub async fn save_danmaku_image(data_url: String) -> Result<String, CommandError> {
     use base64::{engine::general_purpose::STANDARD, Engine};
 
     // 解析 data URL：data:image/<ext>;base64,<payload>
     let (meta, payload) = data_url
         .split_once(',')
-        .ok_or_else(|| "无效的图片数据".to_string())?;
+        .ok_or_else(|| CommandError::validation("无效的图片数据"))?;
     let ext = if meta.contains("png") {
         "png"
     } else if meta.contains("gif") {
@@ -3834,16 +6590,16 @@ pub async fn save_danmaku_image(data_url: String) -> Result<String, String> {
     };
     let bytes = STANDARD
         .decode(payload.trim())
-        .map_err(|e| format!("图片解码失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::Serialization, format!("图片解码失败: {}", e)))?;
 
     let dir = dirs::download_dir()
         .or_else(dirs::picture_dir)
         .or_else(dirs::home_dir)
-        .ok_or_else(|| "找不到下载目录".to_string())?;
+        .ok_or_else(|| CommandError::not_found("找不到下载目录"))?;
     let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
     let filename = format!("MCTier_弹幕图片_{}.{}", ts, ext);
     let path = dir.join(&filename);
-    std::fs::write(&path, &bytes).map_err(|e| format!("保存失败: {}", e))?;
+    std::fs::write(&path, &bytes).map_err(|e| CommandError::io(format!("保存失败: {}", e)))?;
     Ok(path.to_string_lossy().to_string())
 }
 
@@ -3851,72 +6607,36 @@ pub async fn save_danmaku_image(data_url: String) -> Result<String, String> {
 /// 
 /// # 返回
 /// * `Ok(())` - 成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn open_log_folder() -> Result<(), String> {
-    log::info!("打开日志文件夹");
-    
-    // 获取日志文件路径
-    let log_path = if let Some(data_dir) = dirs::data_local_dir() {
-        data_dir.join("MCTier")
-    } else {
-        std::env::current_dir()
-            .map_err(|e| format!("获取当前目录失败: {}", e))?
-    };
-    
-    log::info!("日志文件夹路径: {:?}", log_path);
+pub async fn open_log_folder() -> Result<(), CommandError> {
+    let log_dir = crate::modules::log_file::log_dir();
+    log::info!("日志文件夹路径: {:?}", log_dir);
     
     // 确保目录存在
-    if !log_path.exists() {
-        return Err("日志文件夹不存在".to_string());
-    }
-    
-    // 打开文件夹
-    #[cfg(target_os = "windows")]
-    {
-        use std::process::Command;
-        match Command::new("explorer.exe")
-            .arg(&log_path)
-            .spawn()
-        {
-            Ok(_) => {
-                log::info!("✅ 成功打开日志文件夹");
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("❌ 打开日志文件夹失败: {}", e);
-                Err(format!("打开日志文件夹失败: {}", e))
-            }
-        }
+    if !log_dir.exists() {
+        return Err(CommandError::not_found("日志文件夹不存在"));
     }
     
-    #[cfg(not(target_os = "windows"))]
-    {
-        Err("当前平台不支持此功能".to_string())
-    }
+    open_folder(log_dir.to_string_lossy().to_string()).await
 }
 
 /// 打开日志文件（使用默认文本编辑器）
 /// 
 /// # 返回
 /// * `Ok(())` - 成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn open_log_file() -> Result<(), String> {
+pub async fn open_log_file() -> Result<(), CommandError> {
     log::info!("打开日志文件");
     
-    // 获取日志文件路径
-    let log_path = if let Some(data_dir) = dirs::data_local_dir() {
-        data_dir.join("MCTier").join("mctier.log")
-    } else {
-        std::path::PathBuf::from("mctier.log")
-    };
+    let log_path = crate::modules::log_file::log_file_path();
     
     log::info!("日志文件路径: {:?}", log_path);
     
     // 确保文件存在
     if !log_path.exists() {
-        return Err("日志文件不存在".to_string());
+        return Err(CommandError::not_found("日志文件不存在"));
     }
     
     // 打开文件
@@ -3934,7 +6654,7 @@ pub async fn open_log_file() -> Result<(), String> {
             }
             Err(e) => {
                 log::error!("❌ 打开日志文件失败: {}", e);
-                Err(format!("打开日志文件失败: {}", e))
+                Err(CommandError::new(ErrorCode::Io, format!("打开日志文件失败: {}", e)))
             }
         }
     }
@@ -3949,16 +6669,60 @@ pub async fn open_log_file() -> Result<(), String> {
 /// 
 /// # 返回
 /// * `Ok(String)` - 日志文件路径
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_log_file_path() -> Result<String, String> {
-    let log_path = if let Some(data_dir) = dirs::data_local_dir() {
-        data_dir.join("MCTier").join("mctier.log")
-    } else {
-        std::path::PathBuf::from("mctier.log")
-    };
+pub async fn get_log_file_path() -> Result<String, CommandError> {
+    Ok(crate::modules::log_file::log_file_path().to_string_lossy().to_string())
+}
+
+/// 运行时调整日志级别（保存到配置，下次启动时沿用）
+/// 
+/// # 参数
+/// * `level` - 日志级别（error / warn / info / debug / trace）
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_log_level(
+    level: crate::modules::log_file::LogLevel,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    // 先记录再调整，调低级别时这条日志也能写入
+    log::info!("收到设置日志级别命令: {:?}", level);
+    crate::modules::log_file::set_log_level(level);
+    
+    let config_manager = state.get_config_manager();
+    
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_log_level(level).await.map_err(|e| {
+        log::error!("保存日志级别失败: {}", e);
+        CommandError::from(e)
+    })
+}
+
+/// 设置关闭主窗口时是否最小化到托盘（开启后关闭窗口不退出，只有托盘「退出」才关闭应用）
+/// 
+/// # 参数
+/// * `enabled` - 是否启用
+/// 
+/// # 返回
+/// * `Ok(())` - 设置成功
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn set_close_to_tray(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    log::info!("收到设置关闭时最小化到托盘命令: {}", enabled);
+    
+    let config_manager = state.get_config_manager();
     
-    Ok(log_path.to_string_lossy().to_string())
+    let mut config_mgr = config_manager.lock().await;
+    config_mgr.set_close_to_tray(enabled).await.map_err(|e| {
+        log::error!("保存关闭时最小化到托盘设置失败: {}", e);
+        CommandError::from(e)
+    })
 }
 
 /// 保存设置配置（开机自启 + 自动大厅）
@@ -4010,15 +6774,14 @@ pub async fn save_settings(
     subnet_proxy_cidrs: Option<String>,
     app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use crate::modules::config_manager::{AutoLobbyConfig, EasyTierNode};
     log::info!("保存设置: auto_startup={}, auto_lobby_enabled={}, use_private_server={}, always_on_top={:?}, remember_window_position={:?}, voice_volume={:?}, enable_gpu_rendering={:?}, mic_hotkey={:?}, global_mute_hotkey={:?}, push_to_talk_hotkey={:?}, enable_exit_node={:?}, subnet_proxy_cidrs={:?}, virtual_domain={:?}", 
         auto_startup, auto_lobby_enabled, use_private_server, always_on_top, remember_window_position, voice_volume, enable_gpu_rendering, mic_hotkey, global_mute_hotkey, push_to_talk_hotkey, enable_exit_node, subnet_proxy_cidrs, virtual_domain);
 
     // 1. 保存配置到文件
     {
-        let core = state.core.lock().await;
-        let config_manager = core.get_config_manager();
+        let config_manager = state.get_config_manager();
         let mut cfg_mgr = config_manager.lock().await;
         cfg_mgr.update_config(|config| {
             config.auto_startup = Some(auto_startup);
@@ -4159,7 +6922,7 @@ pub async fn save_settings(
                         .collect();
                 }
             }
-        }).await.map_err(|e| format!("保存配置失败: {}", e))?;
+        }).await.map_err(|e| CommandError::new(ErrorCode::Config, format!("保存配置失败: {}", e)))?;
     }
 
     // 2. 应用窗口置顶设置到主窗口
@@ -4185,11 +6948,10 @@ pub async fn save_settings(
 
 /// 读取当前设置配置
 #[tauri::command]
-pub async fn get_settings(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+pub async fn get_settings(state: State<'_, AppState>) -> Result<serde_json::Value, CommandError> {
     log::info!("开始读取设置配置");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let cfg_mgr = config_manager.lock().await;
     let config = cfg_mgr.get_config();
 
@@ -4249,6 +7011,7 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<serde_json::Valu
         "autoLobbyEnabled": auto_lobby.enabled,
         "lobbyName": auto_lobby.lobby_name,
         "lobbyPassword": auto_lobby.lobby_password,
+        "lobbyPasswordPolicy": config.lobby_password_policy.unwrap_or_default(),
         "playerName": auto_lobby.player_name,
         "useDomain": auto_lobby.use_domain,
         "virtualDomain": auto_lobby.virtual_domain,
@@ -4281,17 +7044,16 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<serde_json::Valu
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn save_voice_volume(volume: f64, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn save_voice_volume(volume: f64, state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("保存语音音量: {}", volume);
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     cfg_mgr.set_voice_volume(volume).await
-        .map_err(|e| format!("保存音量失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::Config, format!("保存音量失败: {}", e)))?;
     
     log::info!("语音音量保存成功");
     Ok(())
@@ -4303,13 +7065,12 @@ pub async fn save_voice_volume(volume: f64, state: State<'_, AppState>) -> Resul
 /// 
 /// # 返回
 /// * `Ok(())` - 重置成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn reset_config_to_default(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn reset_config_to_default(state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("收到重置配置命令");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     match cfg_mgr.reset_to_default().await {
@@ -4319,7 +7080,7 @@ pub async fn reset_config_to_default(state: State<'_, AppState>) -> Result<(), S
         }
         Err(e) => {
             log::error!("重置配置失败: {}", e);
-            Err(format!("重置配置失败: {}", e))
+            Err(CommandError::new(ErrorCode::Config, format!("重置配置失败: {}", e)))
         }
     }
 }
@@ -4334,17 +7095,17 @@ pub async fn reset_config_to_default(state: State<'_, AppState>) -> Result<(), S
 /// 
 /// # 返回
 /// * `Ok(())` - 导出成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn export_config(export_path: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn export_config(export_path: String, state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("导出配置到: {}", export_path);
+    let export_path = crate::modules::path_guard::check_path(&export_path)?;
 
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let cfg_mgr = config_manager.lock().await;
 
-    cfg_mgr.export_config(std::path::PathBuf::from(export_path)).await
-        .map_err(|e| format!("导出配置失败: {}", e))?;
+    cfg_mgr.export_config(export_path).await
+        .map_err(|e| CommandError::new(ErrorCode::Config, format!("导出配置失败: {}", e)))?;
 
     log::info!("配置导出成功");
     Ok(())
@@ -4358,22 +7119,64 @@ pub async fn export_config(export_path: String, state: State<'_, AppState>) -> R
 /// 
 /// # 返回
 /// * `Ok(())` - 导入成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn import_config(import_path: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn import_config(import_path: String, state: State<'_, AppState>) -> Result<(), CommandError> {
     log::info!("从文件导入配置: {}", import_path);
+    let import_path = crate::modules::path_guard::check_path(&import_path)?;
 
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
 
-    cfg_mgr.import_config(std::path::PathBuf::from(import_path)).await
-        .map_err(|e| format!("导入配置失败: {}", e))?;
+    cfg_mgr.import_config(import_path).await
+        .map_err(|e| CommandError::new(ErrorCode::Config, format!("导入配置失败: {}", e)))?;
 
     log::info!("配置导入成功");
     Ok(())
 }
 
+/// 导出配置为配置码，用于在其它设备上同步
+/// 
+/// # 参数
+/// * `exclude_machine_specific` - 是否排除窗口位置、音频设备等本机相关字段（默认排除）
+/// * `state` - 应用状态
+/// 
+/// # 返回
+/// * `Ok(String)` - 配置码（base64 编码的 JSON）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn export_config_data(
+    exclude_machine_specific: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, CommandError> {
+    let config_manager = state.get_config_manager();
+    let cfg_mgr = config_manager.lock().await;
+
+    cfg_mgr.export_config_data(exclude_machine_specific.unwrap_or(true))
+        .map_err(|e| CommandError::new(ErrorCode::Config, format!("导出配置失败: {}", e)))
+}
+
+/// 从配置码或 JSON 导入配置
+/// 
+/// # 参数
+/// * `data` - 配置码或 JSON 文本
+/// * `state` - 应用状态
+/// 
+/// # 返回
+/// * `Ok(ConfigImportReport)` - 导入成功，附带生效与被忽略的字段
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn import_config_data(
+    data: String,
+    state: State<'_, AppState>,
+) -> Result<crate::modules::config_manager::ConfigImportReport, CommandError> {
+    let config_manager = state.get_config_manager();
+    let mut cfg_mgr = config_manager.lock().await;
+
+    cfg_mgr.import_config_data(&data).await
+        .map_err(|e| CommandError::new(ErrorCode::Config, format!("导入配置失败: {}", e)))
+}
+
 // ==================== GPU 设置命令 ====================
 
 /// 重启应用并应用 GPU 设置
@@ -4384,16 +7187,16 @@ pub async fn import_config(import_path: String, state: State<'_, AppState>) -> R
 /// 
 /// # 返回
 /// * `Ok(())` - 重启成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn restart_app_with_gpu_settings(enable_gpu: bool, app: tauri::AppHandle) -> Result<(), String> {
+pub async fn restart_app_with_gpu_settings(enable_gpu: bool, app: tauri::AppHandle) -> Result<(), CommandError> {
     log::info!("重启应用以应用 GPU 设置: enable_gpu={}", enable_gpu);
     
     use std::process::Command;
     
     // 获取当前可执行文件路径
     let exe_path = std::env::current_exe()
-        .map_err(|e| format!("获取程序路径失败: {}", e))?;
+        .map_err(|e| CommandError::new(ErrorCode::Process, format!("获取程序路径失败: {}", e)))?;
     
     #[cfg(windows)]
     {
@@ -4421,7 +7224,7 @@ pub async fn restart_app_with_gpu_settings(enable_gpu: bool, app: tauri::AppHand
             .args(["-WindowStyle", "Hidden", "-Command", &ps_script])
             .creation_flags(0x08000000) // CREATE_NO_WINDOW
             .spawn()
-            .map_err(|e| format!("启动新进程失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::Process, format!("启动新进程失败: {}", e)))?;
     }
     
     #[cfg(not(windows))]
@@ -4436,7 +7239,7 @@ pub async fn restart_app_with_gpu_settings(enable_gpu: bool, app: tauri::AppHand
         }
         
         cmd.spawn()
-            .map_err(|e| format!("启动新进程失败: {}", e))?;
+            .map_err(|e| CommandError::new(ErrorCode::Process, format!("启动新进程失败: {}", e)))?;
     }
     
     log::info!("新进程已启动，准备退出当前进程");
@@ -4471,7 +7274,7 @@ pub async fn restart_app_with_gpu_settings(enable_gpu: bool, app: tauri::AppHand
 /// 
 /// # 返回
 /// * `Ok(())` - 保存成功
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
 pub async fn save_exit_node_advanced_config(
     enable_socks5: Option<bool>,
@@ -4487,7 +7290,7 @@ pub async fn save_exit_node_advanced_config(
     enable_quic_proxy: Option<bool>,
     latency_first: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     use crate::modules::config_manager::PortForwardRule;
     
     log::info!("保存出口节点高级配置");
@@ -4503,8 +7306,7 @@ pub async fn save_exit_node_advanced_config(
     log::info!("  - enable_quic_proxy: {:?}", enable_quic_proxy);
     log::info!("  - latency_first: {:?}", latency_first);
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let mut cfg_mgr = config_manager.lock().await;
     
     cfg_mgr.update_config(|config| {
@@ -4571,7 +7373,7 @@ pub async fn save_exit_node_advanced_config(
                 exit_config.latency_first = latency;
             }
         }
-    }).await.map_err(|e| format!("保存出口节点高级配置失败: {}", e))?;
+    }).await.map_err(|e| CommandError::new(ErrorCode::Config, format!("保存出口节点高级配置失败: {}", e)))?;
     
     log::info!("出口节点高级配置保存成功");
     Ok(())
@@ -4581,13 +7383,12 @@ pub async fn save_exit_node_advanced_config(
 /// 
 /// # 返回
 /// * `Ok(serde_json::Value)` - 出口节点高级配置
-/// * `Err(String)` - 错误信息
+/// * `Err(CommandError)` - 错误信息
 #[tauri::command]
-pub async fn get_exit_node_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+pub async fn get_exit_node_advanced_config(state: State<'_, AppState>) -> Result<serde_json::Value, CommandError> {
     log::info!("获取出口节点高级配置");
     
-    let core = state.core.lock().await;
-    let config_manager = core.get_config_manager();
+    let config_manager = state.get_config_manager();
     let cfg_mgr = config_manager.lock().await;
     let config = cfg_mgr.get_config();
     
@@ -4608,3 +7409,18 @@ pub async fn get_exit_node_advanced_config(state: State<'_, AppState>) -> Result
         "latencyFirst": exit_config.latency_first,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_commands_reject_path_outside_allowlist() {
+        let outside = if cfg!(windows) { "C:\\Windows\\System32" } else { "/etc" };
+        let err = list_directory_files(outside.to_string()).await.unwrap_err();
+        assert!(err.message.contains("权限错误"), "{}", err.message);
+
+        let err = extract_zip(format!("{}/a.zip", outside), outside.to_string()).await.unwrap_err();
+        assert!(err.message.contains("权限错误"), "{}", err.message);
+    }
+}
@@ -0,0 +1,147 @@
+// 虚拟网流量统计
+//
+// 流量数据来自 easytier-cli 的 peer 统计（每个 peer 的累计 rx/tx 字节）。
+// 除了 EasyTier 进程启动以来的累计值，还维护一份"本次大厅"会话统计：进入大厅时清零，
+// 之后每次查询按各 peer 计数器的增量累加。peer 重连后计数器会从 0 重新开始，
+// 此时把新的计数值整体计为增量，避免会话流量倒退。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+
+/// 流量统计结果
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficStats {
+    /// 所有 peer 的累计接收字节
+    pub total_rx_bytes: u64,
+    /// 所有 peer 的累计发送字节
+    pub total_tx_bytes: u64,
+    /// 各 peer 的累计收发字节（虚拟 IP -> (rx, tx)）
+    pub per_peer: HashMap<String, (u64, u64)>,
+    /// 本次大厅累计接收字节
+    pub session_rx_bytes: u64,
+    /// 本次大厅累计发送字节
+    pub session_tx_bytes: u64,
+}
+
+/// 从 easytier-cli 的 JSON 输出中提取各 peer 的收发字节（跳过本机）
+pub fn parse_peer_traffic(value: &serde_json::Value) -> HashMap<String, (u64, u64)> {
+    fn walk(v: &serde_json::Value, out: &mut HashMap<String, (u64, u64)>) {
+        match v {
+            serde_json::Value::Array(arr) => arr.iter().for_each(|x| walk(x, out)),
+            serde_json::Value::Object(map) => {
+                let ip = map.get("ipv4").and_then(|x| x.as_str()).unwrap_or("");
+                let cost = map.get("cost").and_then(|x| x.as_str()).unwrap_or("");
+                if !ip.is_empty() && !cost.is_empty() && !cost.eq_ignore_ascii_case("local") {
+                    let stats = map.get("stats");
+                    let rx = stats.and_then(|s| s.get("rx_bytes")).and_then(|x| x.as_u64());
+                    let tx = stats.and_then(|s| s.get("tx_bytes")).and_then(|x| x.as_u64());
+                    if let (Some(rx), Some(tx)) = (rx, tx) {
+                        // 同一 IP 保留首个
+                        out.entry(ip.to_string()).or_insert((rx, tx));
+                    }
+                }
+                map.values().for_each(|x| walk(x, out));
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = HashMap::new();
+    walk(value, &mut out);
+    out
+}
+
+/// 本次大厅的流量会话
+#[derive(Debug, Default)]
+pub struct TrafficSession {
+    /// 上一次采样时各 peer 的计数器
+    last: HashMap<String, (u64, u64)>,
+    /// 会话累计接收字节
+    rx: u64,
+    /// 会话累计发送字节
+    tx: u64,
+}
+
+impl TrafficSession {
+    /// 清零会话统计（进入 / 退出大厅时调用）
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 记录一次采样并返回会话累计 (rx, tx)
+    ///
+    /// 首次出现的 peer 连接建立于本次会话内，整个计数值都计入会话；
+    /// 计数器变小说明 peer 重连后重新计数，同样把新的计数值整体计入。
+    pub fn record(&mut self, per_peer: &HashMap<String, (u64, u64)>) -> (u64, u64) {
+        for (ip, &(rx, tx)) in per_peer {
+            let (last_rx, last_tx) = self.last.get(ip).copied().unwrap_or((0, 0));
+            self.rx += if rx >= last_rx { rx - last_rx } else { rx };
+            self.tx += if tx >= last_tx { tx - last_tx } else { tx };
+        }
+        // 离开的 peer 不再保留，重新出现时按新连接计算
+        self.last = per_peer.clone();
+        (self.rx, self.tx)
+    }
+}
+
+fn session_slot() -> &'static Mutex<TrafficSession> {
+    static SESSION: OnceLock<Mutex<TrafficSession>> = OnceLock::new();
+    SESSION.get_or_init(|| Mutex::new(TrafficSession::default()))
+}
+
+/// 进入 / 退出大厅时清零本次大厅流量
+pub fn reset_session() {
+    session_slot().lock().reset();
+}
+
+/// 根据 easytier-cli 输出生成流量统计，并更新本次大厅累计值
+pub fn collect_stats(value: &serde_json::Value) -> TrafficStats {
+    let per_peer = parse_peer_traffic(value);
+    let (session_rx_bytes, session_tx_bytes) = session_slot().lock().record(&per_peer);
+    TrafficStats {
+        total_rx_bytes: per_peer.values().map(|(rx, _)| rx).sum(),
+        total_tx_bytes: per_peer.values().map(|(_, tx)| tx).sum(),
+        per_peer,
+        session_rx_bytes,
+        session_tx_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peers(entries: &[(&str, u64, u64)]) -> HashMap<String, (u64, u64)> {
+        entries.iter().map(|&(ip, rx, tx)| (ip.to_string(), (rx, tx))).collect()
+    }
+
+    #[test]
+    fn test_parse_peer_traffic() {
+        let value = serde_json::json!([
+            { "ipv4": "10.126.126.1", "cost": "Local", "stats": { "rx_bytes": 999, "tx_bytes": 999 } },
+            { "ipv4": "10.126.126.2", "cost": "p2p", "stats": { "rx_bytes": 100, "tx_bytes": 50 } },
+            { "ipv4": "10.126.126.3", "cost": "relay(2)", "stats": { "rx_bytes": 7, "tx_bytes": 3 } },
+            { "ipv4": "10.126.126.4", "cost": "p2p" }
+        ]);
+
+        assert_eq!(
+            parse_peer_traffic(&value),
+            peers(&[("10.126.126.2", 100, 50), ("10.126.126.3", 7, 3)])
+        );
+    }
+
+    #[test]
+    fn test_session_accumulates_deltas() {
+        let mut session = TrafficSession::default();
+        assert_eq!(session.record(&peers(&[("a", 100, 10)])), (100, 10));
+        assert_eq!(session.record(&peers(&[("a", 150, 30), ("b", 5, 5)])), (155, 35));
+        // a 重连后计数器归零重新开始
+        assert_eq!(session.record(&peers(&[("a", 20, 2), ("b", 5, 5)])), (175, 37));
+
+        session.reset();
+        assert_eq!(session.record(&peers(&[("a", 30, 4)])), (30, 4));
+    }
+}
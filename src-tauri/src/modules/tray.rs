@@ -0,0 +1,247 @@
+// 系统托盘
+//
+// 关闭主窗口时可选择最小化到托盘，后台静默运行（保持组网与语音），只有托盘菜单的「退出」才真正走关闭流程。
+// 托盘菜单显示当前状态（所在大厅 / 麦克风开关），并可直接切换麦克风；菜单文本随界面语言由前端更新。
+// 托盘的状态与悬停提示只由本模块维护，voice_feedback 只按状态重绘图标角标。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use log::{error, info};
+use parking_lot::Mutex;
+use tauri::menu::{Menu, MenuBuilder, MenuItem};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::modules::voice_feedback::{announce, update_badge, VoiceCue};
+use crate::modules::voice_service::MicMode;
+
+/// 托盘图标 id
+pub const TRAY_ID: &str = "main-tray";
+/// 菜单项：显示主窗口
+pub const MENU_SHOW: &str = "show_main";
+/// 菜单项：切换麦克风
+pub const MENU_TOGGLE_MIC: &str = "toggle_mic";
+/// 菜单项：当前状态（仅展示，不可点击）
+pub const MENU_STATUS: &str = "tray_status";
+/// 菜单项：退出应用
+pub const MENU_EXIT: &str = "exit_app";
+/// 托盘状态刷新间隔
+pub const TRAY_STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 托盘菜单文本（默认中文，前端按界面语言覆盖）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayTexts {
+    /// 显示主窗口
+    pub show: String,
+    /// 退出应用
+    pub exit: String,
+    /// 麦克风关闭时的切换项
+    pub mic_on: String,
+    /// 麦克风开启时的切换项
+    pub mic_off: String,
+    /// 未在大厅中时的状态
+    pub idle: String,
+    /// 在大厅中时的状态前缀（后接大厅名称）
+    pub in_lobby: String,
+}
+
+impl Default for TrayTexts {
+    fn default() -> Self {
+        Self {
+            show: "显示 MCTier".to_string(),
+            exit: "退出 MCTier".to_string(),
+            mic_on: "打开麦克风".to_string(),
+            mic_off: "关闭麦克风".to_string(),
+            idle: "未加入大厅".to_string(),
+            in_lobby: "大厅".to_string(),
+        }
+    }
+}
+
+/// 托盘展示的当前状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrayStatus {
+    /// 所在大厅名称（未在大厅中时为 None）
+    pub lobby_name: Option<String>,
+    /// 麦克风是否开启
+    pub mic_enabled: bool,
+    /// 听筒是否全局静音
+    pub speaker_muted: bool,
+}
+
+impl TrayStatus {
+    /// 应用一次麦克风 / 听筒切换
+    pub fn apply(&mut self, cue: VoiceCue) {
+        match cue {
+            VoiceCue::MicOn => self.mic_enabled = true,
+            VoiceCue::MicOff => self.mic_enabled = false,
+            VoiceCue::SpeakerMuted => self.speaker_muted = true,
+            VoiceCue::SpeakerUnmuted => self.speaker_muted = false,
+        }
+    }
+
+    /// 状态菜单项文本
+    pub fn status_label(&self, texts: &TrayTexts) -> String {
+        match &self.lobby_name {
+            Some(name) => format!("{}: {}", texts.in_lobby, name),
+            None => texts.idle.clone(),
+        }
+    }
+
+    /// 麦克风切换菜单项文本（显示点击后的动作）
+    pub fn mic_label<'a>(&self, texts: &'a TrayTexts) -> &'a str {
+        if self.mic_enabled {
+            &texts.mic_off
+        } else {
+            &texts.mic_on
+        }
+    }
+
+    /// 托盘图标悬停提示
+    pub fn tooltip(&self, texts: &TrayTexts) -> String {
+        format!("MCTier - {}", self.status_label(texts))
+    }
+}
+
+#[derive(Default)]
+struct TrayState {
+    texts: TrayTexts,
+    status: TrayStatus,
+}
+
+fn tray_state() -> &'static Mutex<TrayState> {
+    static STATE: OnceLock<Mutex<TrayState>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(TrayState::default()))
+}
+
+/// 是否已进入退出流程（此后主窗口的关闭请求不再拦截）
+static QUITTING: AtomicBool = AtomicBool::new(false);
+
+/// 标记应用正在退出
+pub fn mark_quitting() {
+    QUITTING.store(true, Ordering::SeqCst);
+}
+
+/// 应用是否正在退出
+pub fn is_quitting() -> bool {
+    QUITTING.load(Ordering::SeqCst)
+}
+
+/// 按当前文本与状态构建托盘菜单
+///
+/// 麦克风切换项只在大厅中可用。
+pub fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let (texts, status) = {
+        let state = tray_state().lock();
+        (state.texts.clone(), state.status.clone())
+    };
+    let status_item = MenuItem::with_id(app, MENU_STATUS, status.status_label(&texts), false, None::<&str>)?;
+    let show_item = MenuItem::with_id(app, MENU_SHOW, &texts.show, true, None::<&str>)?;
+    let mic_item = MenuItem::with_id(
+        app,
+        MENU_TOGGLE_MIC,
+        status.mic_label(&texts),
+        status.lobby_name.is_some(),
+        None::<&str>,
+    )?;
+    let exit_item = MenuItem::with_id(app, MENU_EXIT, &texts.exit, true, None::<&str>)?;
+    MenuBuilder::new(app)
+        .item(&status_item)
+        .separator()
+        .item(&show_item)
+        .item(&mic_item)
+        .separator()
+        .item(&exit_item)
+        .build()
+}
+
+/// 重新生成托盘菜单、悬停提示与图标角标
+pub fn refresh(app: &AppHandle) -> tauri::Result<()> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    let (tooltip, status) = {
+        let state = tray_state().lock();
+        (state.status.tooltip(&state.texts), state.status.clone())
+    };
+    tray.set_menu(Some(build_menu(app)?))?;
+    tray.set_tooltip(Some(tooltip))?;
+    update_badge(app, &status);
+    Ok(())
+}
+
+/// 更新托盘菜单文本
+pub fn set_texts(app: &AppHandle, texts: TrayTexts) -> tauri::Result<()> {
+    tray_state().lock().texts = texts;
+    refresh(app)
+}
+
+/// 更新托盘展示的状态（状态未变化时不重建菜单）
+pub fn update_status(app: &AppHandle, status: TrayStatus) -> tauri::Result<()> {
+    {
+        let mut state = tray_state().lock();
+        if state.status == status {
+            return Ok(());
+        }
+        state.status = status;
+    }
+    refresh(app)
+}
+
+/// 麦克风 / 听筒切换成功后立即更新托盘（不等定期刷新）
+pub fn apply_voice_cue(app: &AppHandle, cue: VoiceCue) {
+    let mut status = tray_state().lock().status.clone();
+    status.apply(cue);
+    if let Err(e) = update_status(app, status) {
+        error!("更新托盘状态失败: {}", e);
+    }
+}
+
+/// 托盘菜单切换麦克风（与麦克风快捷键行为一致，按键说话模式下忽略）
+pub async fn toggle_mic(app: AppHandle) {
+    let state = app.state::<crate::modules::tauri_commands::AppState>();
+    let mode = state.get_voice_service().lock().await.get_mic_mode().await;
+    if mode == MicMode::PushToTalk {
+        info!("按键说话模式下忽略托盘麦克风开关");
+        return;
+    }
+    let result = state.get_voice_service().lock().await.toggle_mic().await;
+    match result {
+        Ok(enabled) => {
+            let _ = app.emit("mic-toggled", enabled);
+            announce(&app, VoiceCue::mic(enabled));
+        }
+        Err(e) => error!("切换麦克风失败: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tray_status_labels() {
+        let texts = TrayTexts::default();
+        let idle = TrayStatus::default();
+        assert_eq!(idle.status_label(&texts), "未加入大厅");
+        assert_eq!(idle.mic_label(&texts), "打开麦克风");
+        assert_eq!(idle.tooltip(&texts), "MCTier - 未加入大厅");
+
+        let in_lobby = TrayStatus { lobby_name: Some("周末生存".to_string()), mic_enabled: true, speaker_muted: false };
+        assert_eq!(in_lobby.status_label(&texts), "大厅: 周末生存");
+        assert_eq!(in_lobby.mic_label(&texts), "关闭麦克风");
+        assert_eq!(in_lobby.tooltip(&texts), "MCTier - 大厅: 周末生存");
+    }
+
+    #[test]
+    fn test_tray_status_apply_cue() {
+        let mut status = TrayStatus::default();
+        status.apply(VoiceCue::MicOn);
+        status.apply(VoiceCue::SpeakerMuted);
+        assert!(status.mic_enabled && status.speaker_muted);
+        status.apply(VoiceCue::MicOff);
+        status.apply(VoiceCue::SpeakerUnmuted);
+        assert_eq!(status, TrayStatus::default());
+    }
+}
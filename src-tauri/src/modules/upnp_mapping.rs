@@ -0,0 +1,262 @@
+// P2P 信令端口的 UPnP 自动映射
+//
+// 部分受限网络下在路由器上主动打开端口能改善连通性。启用后，P2P 信令启动时通过 UPnP（IGD）
+// 在网关上把信令 UDP 端口映射到本机，退出时撤销映射。映射带租期，运行期间定期续约；
+// 程序异常退出时映射也会随租期到期自动失效。
+// 找不到网关、网关拒绝映射等任何失败都只记录日志，不影响信令本身。
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::Duration;
+
+/// 映射租期（秒）
+pub const UPNP_LEASE_SECS: u32 = 3600;
+
+/// 续约间隔（租期的一半）
+pub const UPNP_RENEW_INTERVAL: Duration = Duration::from_secs(UPNP_LEASE_SECS as u64 / 2);
+
+/// 网关搜索超时
+const UPNP_SEARCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 映射描述（显示在路由器管理页面）
+const UPNP_MAPPING_DESCRIPTION: &str = "MCTier P2P Signaling";
+
+/// UPnP 映射错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum UpnpError {
+    #[error("未找到支持 UPnP 的网关: {0}")]
+    GatewayNotFound(String),
+
+    #[error("无法确定本机局域网地址")]
+    NoLocalAddress,
+
+    #[error("添加端口映射失败: {0}")]
+    AddPortFailed(String),
+
+    #[error("删除端口映射失败: {0}")]
+    RemovePortFailed(String),
+}
+
+/// 端口映射请求（UDP）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortMappingRequest {
+    /// 网关上的外部端口
+    pub external_port: u16,
+    /// 映射到的本机地址
+    pub local_addr: SocketAddrV4,
+    /// 租期（秒）
+    pub lease_secs: u32,
+    /// 映射描述
+    pub description: String,
+}
+
+/// 构造信令端口的映射请求（外部端口与本机端口相同）
+pub fn build_mapping_request(port: u16, local_ip: Ipv4Addr) -> PortMappingRequest {
+    PortMappingRequest {
+        external_port: port,
+        local_addr: SocketAddrV4::new(local_ip, port),
+        lease_secs: UPNP_LEASE_SECS,
+        description: UPNP_MAPPING_DESCRIPTION.to_string(),
+    }
+}
+
+/// UPnP 网关
+pub trait UpnpGateway: Send + Sync {
+    /// 本机在网关所在局域网中的地址
+    fn local_ip(&self) -> Option<Ipv4Addr>;
+    /// 添加（或续约）UDP 端口映射
+    fn add_port(&self, request: &PortMappingRequest) -> Result<(), UpnpError>;
+    /// 删除 UDP 端口映射
+    fn remove_port(&self, external_port: u16) -> Result<(), UpnpError>;
+}
+
+/// 网关发现
+pub trait UpnpDiscovery {
+    /// 在局域网中搜索 UPnP 网关
+    fn discover(&self) -> Result<Box<dyn UpnpGateway>, UpnpError>;
+}
+
+/// 已建立的端口映射
+pub struct UpnpPortMapping {
+    gateway: Box<dyn UpnpGateway>,
+    request: PortMappingRequest,
+}
+
+impl std::fmt::Debug for UpnpPortMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpnpPortMapping").field("request", &self.request).finish()
+    }
+}
+
+impl UpnpPortMapping {
+    /// 搜索网关并映射端口
+    ///
+    /// # 参数
+    /// * `discovery` - 网关发现实现
+    /// * `port` - 信令 UDP 端口
+    pub fn establish(discovery: &dyn UpnpDiscovery, port: u16) -> Result<Self, UpnpError> {
+        let gateway = discovery.discover()?;
+        let local_ip = gateway.local_ip().ok_or(UpnpError::NoLocalAddress)?;
+        let request = build_mapping_request(port, local_ip);
+        gateway.add_port(&request)?;
+        Ok(Self { gateway, request })
+    }
+
+    /// 映射请求
+    pub fn request(&self) -> &PortMappingRequest {
+        &self.request
+    }
+
+    /// 续约（重新添加同一映射以刷新租期）
+    pub fn renew(&self) -> Result<(), UpnpError> {
+        self.gateway.add_port(&self.request)
+    }
+
+    /// 撤销映射
+    pub fn remove(&self) -> Result<(), UpnpError> {
+        self.gateway.remove_port(self.request.external_port)
+    }
+}
+
+/// 尝试映射信令端口，失败时静默降级（返回 None）
+pub fn try_map_port(discovery: &dyn UpnpDiscovery, port: u16) -> Option<UpnpPortMapping> {
+    match UpnpPortMapping::establish(discovery, port) {
+        Ok(mapping) => {
+            log::info!(
+                "✅ [UPnP] 已在网关映射 UDP 端口 {} -> {}",
+                mapping.request.external_port,
+                mapping.request.local_addr
+            );
+            Some(mapping)
+        }
+        Err(e) => {
+            log::info!("[UPnP] 端口映射不可用，忽略: {}", e);
+            None
+        }
+    }
+}
+
+/// 基于 igd-next 的网关发现
+pub struct IgdDiscovery;
+
+impl UpnpDiscovery for IgdDiscovery {
+    fn discover(&self) -> Result<Box<dyn UpnpGateway>, UpnpError> {
+        let options = igd_next::SearchOptions {
+            timeout: Some(UPNP_SEARCH_TIMEOUT),
+            ..Default::default()
+        };
+        let gateway = igd_next::search_gateway(options).map_err(|e| UpnpError::GatewayNotFound(e.to_string()))?;
+        Ok(Box::new(IgdGateway(gateway)))
+    }
+}
+
+/// igd-next 网关
+struct IgdGateway(igd_next::Gateway);
+
+impl UpnpGateway for IgdGateway {
+    fn local_ip(&self) -> Option<Ipv4Addr> {
+        // 向网关"连接"一个 UDP 套接字（不发包），由系统路由选出本机出口地址
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+        socket.connect(self.0.addr).ok()?;
+        match socket.local_addr().ok()?.ip() {
+            std::net::IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+            _ => None,
+        }
+    }
+
+    fn add_port(&self, request: &PortMappingRequest) -> Result<(), UpnpError> {
+        self.0
+            .add_port(
+                igd_next::PortMappingProtocol::UDP,
+                request.external_port,
+                SocketAddr::V4(request.local_addr),
+                request.lease_secs,
+                &request.description,
+            )
+            .map_err(|e| UpnpError::AddPortFailed(e.to_string()))
+    }
+
+    fn remove_port(&self, external_port: u16) -> Result<(), UpnpError> {
+        self.0
+            .remove_port(igd_next::PortMappingProtocol::UDP, external_port)
+            .map_err(|e| UpnpError::RemovePortFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 记录网关调用的 mock
+    #[derive(Clone, Default)]
+    struct MockGateway {
+        calls: Arc<Mutex<Vec<String>>>,
+        reject_add: bool,
+    }
+
+    impl UpnpGateway for MockGateway {
+        fn local_ip(&self) -> Option<Ipv4Addr> {
+            Some(Ipv4Addr::new(192, 168, 1, 20))
+        }
+
+        fn add_port(&self, request: &PortMappingRequest) -> Result<(), UpnpError> {
+            self.calls.lock().unwrap().push(format!("add {} -> {}", request.external_port, request.local_addr));
+            if self.reject_add {
+                return Err(UpnpError::AddPortFailed("ConflictInMappingEntry".to_string()));
+            }
+            Ok(())
+        }
+
+        fn remove_port(&self, external_port: u16) -> Result<(), UpnpError> {
+            self.calls.lock().unwrap().push(format!("remove {}", external_port));
+            Ok(())
+        }
+    }
+
+    struct MockDiscovery(Option<MockGateway>);
+
+    impl UpnpDiscovery for MockDiscovery {
+        fn discover(&self) -> Result<Box<dyn UpnpGateway>, UpnpError> {
+            match &self.0 {
+                Some(gateway) => Ok(Box::new(gateway.clone())),
+                None => Err(UpnpError::GatewayNotFound("timeout".to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_mapping_request() {
+        let request = build_mapping_request(47777, Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(request.external_port, 47777);
+        assert_eq!(request.local_addr, "192.168.1.20:47777".parse().unwrap());
+        assert_eq!(request.lease_secs, UPNP_LEASE_SECS);
+    }
+
+    #[test]
+    fn test_map_renew_and_remove() {
+        let gateway = MockGateway::default();
+        let mapping = try_map_port(&MockDiscovery(Some(gateway.clone())), 47777).unwrap();
+        mapping.renew().unwrap();
+        mapping.remove().unwrap();
+
+        assert_eq!(
+            *gateway.calls.lock().unwrap(),
+            vec![
+                "add 47777 -> 192.168.1.20:47777".to_string(),
+                "add 47777 -> 192.168.1.20:47777".to_string(),
+                "remove 47777".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failures_degrade_silently() {
+        // 找不到网关
+        assert!(try_map_port(&MockDiscovery(None), 47777).is_none());
+
+        // 网关拒绝映射
+        let gateway = MockGateway { reject_add: true, ..Default::default() };
+        assert!(try_map_port(&MockDiscovery(Some(gateway.clone())), 47777).is_none());
+        assert_eq!(gateway.calls.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,259 @@
+// 麦克风 / 听筒静音状态反馈
+//
+// 麦克风与全局静音快捷键在游戏全屏时也生效，但此时看不到界面变化，不知道切换是否成功。
+// 状态切换成功后播放一段简短提示音（开麦上扬、关麦下沉，听筒静音 / 取消静音为低音版本），
+// 并通知托盘（tray 模块）更新状态；本模块只负责在托盘图标右下角画出状态角标。提示音可在配置中关闭，托盘状态始终更新。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::image::Image;
+use tauri::AppHandle;
+
+use crate::modules::tray::{self, TrayStatus};
+
+/// 每个音符的时长
+const NOTE_DURATION: Duration = Duration::from_millis(70);
+/// 音符首尾淡入淡出时长（避免爆音）
+const NOTE_FADE: Duration = Duration::from_millis(8);
+/// 提示音音量（0.0-1.0）
+const CUE_VOLUME: f32 = 0.25;
+
+/// 麦克风开启时的角标颜色
+const BADGE_MIC_ON: [u8; 4] = [0x52, 0xc4, 0x1a, 0xff];
+/// 麦克风关闭时的角标颜色
+const BADGE_MIC_OFF: [u8; 4] = [0xf5, 0x22, 0x2d, 0xff];
+/// 麦克风开启但听筒静音时的角标颜色
+const BADGE_SPEAKER_MUTED: [u8; 4] = [0xfa, 0xad, 0x14, 0xff];
+
+/// 需要反馈的状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceCue {
+    /// 开麦
+    MicOn,
+    /// 关麦
+    MicOff,
+    /// 听筒静音
+    SpeakerMuted,
+    /// 取消听筒静音
+    SpeakerUnmuted,
+}
+
+impl VoiceCue {
+    /// 麦克风切换到 `enabled` 对应的反馈
+    pub fn mic(enabled: bool) -> Self {
+        if enabled { VoiceCue::MicOn } else { VoiceCue::MicOff }
+    }
+
+    /// 听筒静音切换到 `muted` 对应的反馈
+    pub fn speaker(muted: bool) -> Self {
+        if muted { VoiceCue::SpeakerMuted } else { VoiceCue::SpeakerUnmuted }
+    }
+
+    /// 提示音依次演奏的频率（Hz）
+    fn notes(self) -> &'static [f32] {
+        match self {
+            VoiceCue::MicOn => &[660.0, 880.0],
+            VoiceCue::MicOff => &[880.0, 587.0],
+            VoiceCue::SpeakerMuted => &[440.0, 330.0],
+            VoiceCue::SpeakerUnmuted => &[330.0, 440.0],
+        }
+    }
+}
+
+/// 托盘状态对应的角标颜色
+fn badge_color(status: &TrayStatus) -> [u8; 4] {
+    match (status.mic_enabled, status.speaker_muted) {
+        (false, _) => BADGE_MIC_OFF,
+        (true, true) => BADGE_SPEAKER_MUTED,
+        (true, false) => BADGE_MIC_ON,
+    }
+}
+
+/// 提示音开关
+static SOUND_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 设置是否播放提示音
+pub fn set_sound_enabled(enabled: bool) {
+    SOUND_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 生成提示音的单声道采样
+pub fn render_cue(cue: VoiceCue, sample_rate: u32) -> Vec<f32> {
+    let samples_for = |d: Duration| (sample_rate as u128 * d.as_millis() / 1000) as usize;
+    let note_len = samples_for(NOTE_DURATION);
+    let fade_len = samples_for(NOTE_FADE).max(1);
+    let mut samples = Vec::with_capacity(note_len * cue.notes().len());
+    for &freq in cue.notes() {
+        for i in 0..note_len {
+            let envelope = (i.min(note_len - 1 - i) as f32 / fade_len as f32).min(1.0);
+            let phase = 2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32;
+            samples.push(phase.sin() * envelope * CUE_VOLUME);
+        }
+    }
+    samples
+}
+
+/// 在托盘图标右下角画一个带白边的圆形角标
+pub fn draw_badge(rgba: &mut [u8], width: u32, height: u32, color: [u8; 4]) {
+    let size = width.min(height) as f32;
+    let radius = size * 0.22;
+    let border = (size * 0.04).max(1.0);
+    let cx = width as f32 - radius - border;
+    let cy = height as f32 - radius - border;
+    for y in 0..height {
+        for x in 0..width {
+            let dist = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+            let pixel = if dist <= radius {
+                color
+            } else if dist <= radius + border {
+                [0xff, 0xff, 0xff, 0xff]
+            } else {
+                continue;
+            };
+            let offset = ((y * width + x) * 4) as usize;
+            if let Some(target) = rgba.get_mut(offset..offset + 4) {
+                target.copy_from_slice(&pixel);
+            }
+        }
+    }
+}
+
+/// 在后台线程播放提示音（cpal 输出流不能跨线程移动）
+fn play_cue(cue: VoiceCue) {
+    std::thread::spawn(move || {
+        if let Err(e) = play_cue_blocking(cue) {
+            log::warn!("⚠️ 播放状态提示音失败: {}", e);
+        }
+    });
+}
+
+fn play_cue_blocking(cue: VoiceCue) -> Result<(), String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| "没有可用的输出设备".to_string())?;
+    let config = device
+        .default_output_config()
+        .map_err(|e| format!("读取输出设备配置失败: {}", e))?;
+    let stream_config = config.config();
+    let channels = stream_config.channels as usize;
+    let samples = render_cue(cue, stream_config.sample_rate.0);
+    let duration = Duration::from_secs_f32(samples.len() as f32 / stream_config.sample_rate.0 as f32);
+
+    // 每个声道写同一个采样，播完后补零
+    let mut frames = samples.into_iter();
+    let mut next_frame = move || frames.next().unwrap_or(0.0);
+    let on_error = |e: cpal::StreamError| log::warn!("提示音输出流出错: {}", e);
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => device.build_output_stream(
+            &stream_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill(next_frame());
+                }
+            },
+            on_error,
+            None,
+        ),
+        SampleFormat::I16 => device.build_output_stream(
+            &stream_config,
+            move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill((next_frame() * i16::MAX as f32) as i16);
+                }
+            },
+            on_error,
+            None,
+        ),
+        SampleFormat::U16 => device.build_output_stream(
+            &stream_config,
+            move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill((next_frame() * 32767.0 + 32768.0) as u16);
+                }
+            },
+            on_error,
+            None,
+        ),
+        other => return Err(format!("不支持的采样格式: {:?}", other)),
+    }
+    .map_err(|e| format!("打开输出设备失败: {}", e))?;
+    stream.play().map_err(|e| format!("启动输出流失败: {}", e))?;
+    // 多等一小段时间，确保缓冲区里的尾音播完
+    std::thread::sleep(duration + Duration::from_millis(100));
+    Ok(())
+}
+
+/// 按托盘状态重绘图标角标（由 tray 模块在状态变化时调用）
+pub fn update_badge(app: &AppHandle, status: &TrayStatus) {
+    let Some(tray) = app.tray_by_id(tray::TRAY_ID) else {
+        return;
+    };
+    let Some(icon) = app.default_window_icon() else {
+        return;
+    };
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    draw_badge(&mut rgba, width, height, badge_color(status));
+    if let Err(e) = tray.set_icon(Some(Image::new_owned(rgba, width, height))) {
+        log::warn!("⚠️ 更新托盘图标失败: {}", e);
+    }
+}
+
+/// 麦克风 / 听筒静音切换成功后调用：播放提示音（若启用）并更新托盘
+pub fn announce(app: &AppHandle, cue: VoiceCue) {
+    if SOUND_ENABLED.load(Ordering::Relaxed) {
+        play_cue(cue);
+    }
+    tray::apply_voice_cue(app, cue);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cue() {
+        let on = render_cue(VoiceCue::MicOn, 48_000);
+        let off = render_cue(VoiceCue::MicOff, 48_000);
+        // 两个音符，每个 70ms
+        assert_eq!(on.len(), 2 * 3360);
+        assert_eq!(on.len(), off.len());
+        assert_ne!(on, off);
+        // 首尾淡入淡出，整体不超过音量上限
+        assert_eq!(on[0], 0.0);
+        assert!(on.last().unwrap().abs() < 0.01);
+        assert!(on.iter().all(|s| s.abs() <= CUE_VOLUME));
+        assert!(on.iter().any(|s| s.abs() > CUE_VOLUME * 0.9));
+    }
+
+    #[test]
+    fn test_badge_color() {
+        let mut status = TrayStatus::default();
+        assert_eq!(badge_color(&status), BADGE_MIC_OFF);
+        status.apply(VoiceCue::MicOn);
+        assert_eq!(badge_color(&status), BADGE_MIC_ON);
+        status.apply(VoiceCue::SpeakerMuted);
+        assert_eq!(badge_color(&status), BADGE_SPEAKER_MUTED);
+        status.apply(VoiceCue::MicOff);
+        assert_eq!(badge_color(&status), BADGE_MIC_OFF);
+    }
+
+    #[test]
+    fn test_draw_badge() {
+        let (width, height) = (32u32, 32u32);
+        let mut rgba = vec![0u8; (width * height * 4) as usize];
+        draw_badge(&mut rgba, width, height, BADGE_MIC_OFF);
+        let pixel = |x: u32, y: u32| {
+            let offset = ((y * width + x) * 4) as usize;
+            [rgba[offset], rgba[offset + 1], rgba[offset + 2], rgba[offset + 3]]
+        };
+        // 右下角画上角标，左上角保持原样
+        assert_eq!(pixel(24, 24), BADGE_MIC_OFF);
+        assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(31, 24), [0xff, 0xff, 0xff, 0xff]);
+    }
+}
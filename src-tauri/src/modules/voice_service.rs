@@ -1,7 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
@@ -18,7 +19,7 @@ pub enum DeviceType {
 }
 
 /// 音频设备信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AudioDevice {
     /// 设备唯一标识符
     pub id: String,
@@ -30,6 +31,409 @@ pub struct AudioDevice {
     pub is_default: bool,
 }
 
+/// 玩家音量增益下限（0%，等价于静音）
+pub const MIN_PLAYER_VOLUME: f32 = 0.0;
+
+/// 玩家音量增益上限（200%）
+pub const MAX_PLAYER_VOLUME: f32 = 2.0;
+
+/// 玩家默认音量增益（100%）
+pub const DEFAULT_PLAYER_VOLUME: f32 = 1.0;
+
+/// 判定为"正在说话"的麦克风 RMS 阈值（线性，0.0-1.0），可通过 `set_vad_threshold` 调整
+///
+/// 与前端说话检测器（SpeakingDetector）比较的是同一个线性 RMS，正常说话约 0.05。
+pub const SPEAKING_LEVEL_THRESHOLD: f32 = 0.02;
+
+/// 可设置的说话阈值上限，再高正常说话也难以触发
+pub const MAX_VAD_THRESHOLD: f32 = 0.2;
+
+/// 校准时推荐阈值相对环境底噪的倍数（2 倍 RMS 约高出 6dB）
+pub const VAD_CALIBRATION_GAIN: f32 = 2.0;
+
+/// 校准时推荐阈值额外加上的余量，避免极安静环境下推荐值过低
+pub const VAD_CALIBRATION_MARGIN: f32 = 0.005;
+
+/// 校准默认采集时长（秒）
+pub const DEFAULT_VAD_CALIBRATION_SECS: u64 = 3;
+
+/// 校准最长采集时长（秒）
+pub const MAX_VAD_CALIBRATION_SECS: u64 = 10;
+
+/// 停止说话后延迟广播 is_speaking=false 的时间，避免头像高亮频繁闪烁
+pub const SPEAKING_RELEASE_DELAY: Duration = Duration::from_millis(300);
+
+/// 信令队列最大长度，超出时丢弃最老的消息（无人取走时避免无限增长）
+pub const MAX_SIGNALING_QUEUE_LEN: usize = 1000;
+
+/// 本地说话状态更新结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeakingUpdate {
+    /// 状态未变化，无需广播
+    Unchanged,
+    /// 开始说话，应立即广播 is_speaking=true
+    Started,
+    /// 声音刚低于阈值，进入延迟释放窗口，应在延迟后再次检查
+    ReleasePending,
+    /// 静音持续超过延迟，应广播 is_speaking=false
+    Stopped,
+}
+
+/// 说话状态防抖器
+///
+/// 开始说话立即生效；停止说话需持续静音超过释放延迟才生效，期间再次出声会取消释放。
+#[derive(Debug)]
+pub struct SpeakingDebouncer {
+    /// 当前是否处于说话状态
+    speaking: bool,
+    /// 开始静音的时间（处于释放窗口时有值）
+    silent_since: Option<Instant>,
+    /// 释放延迟
+    release_delay: Duration,
+}
+
+impl SpeakingDebouncer {
+    /// 创建新的防抖器
+    pub fn new(release_delay: Duration) -> Self {
+        Self {
+            speaking: false,
+            silent_since: None,
+            release_delay,
+        }
+    }
+
+    /// 当前是否处于说话状态
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// 根据本次检测结果更新状态
+    ///
+    /// # 参数
+    /// * `above_threshold` - 本次电平是否超过阈值
+    /// * `now` - 当前时间
+    pub fn update(&mut self, above_threshold: bool, now: Instant) -> SpeakingUpdate {
+        if above_threshold {
+            self.silent_since = None;
+            if self.speaking {
+                return SpeakingUpdate::Unchanged;
+            }
+            self.speaking = true;
+            return SpeakingUpdate::Started;
+        }
+
+        if !self.speaking {
+            return SpeakingUpdate::Unchanged;
+        }
+        match self.silent_since {
+            None => {
+                self.silent_since = Some(now);
+                SpeakingUpdate::ReleasePending
+            }
+            Some(_) => self.poll(now),
+        }
+    }
+
+    /// 检查延迟释放是否到期（不改变静音起始时间）
+    pub fn poll(&mut self, now: Instant) -> SpeakingUpdate {
+        match self.silent_since {
+            Some(since) if self.speaking && now.duration_since(since) >= self.release_delay => {
+                self.speaking = false;
+                self.silent_since = None;
+                SpeakingUpdate::Stopped
+            }
+            _ => SpeakingUpdate::Unchanged,
+        }
+    }
+
+    /// 重置为未说话状态
+    pub fn reset(&mut self) {
+        self.speaking = false;
+        self.silent_since = None;
+    }
+}
+
+impl Default for SpeakingDebouncer {
+    fn default() -> Self {
+        Self::new(SPEAKING_RELEASE_DELAY)
+    }
+}
+
+/// 玩家发言统计（按会话累计）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakingActivity {
+    /// 累计说话时长（秒，含正在进行的发言）
+    pub speaking_seconds: u64,
+    /// 发言次数（每次从开始说话到停止说话计一次）
+    pub message_count: u32,
+}
+
+/// 说话时长与发言次数累计器
+#[derive(Debug, Default)]
+pub struct SpeakingActivityTracker {
+    /// 已结束发言的累计时长与发言次数
+    totals: HashMap<String, (Duration, u32)>,
+    /// 正在说话的玩家及开始时间
+    started: HashMap<String, Instant>,
+}
+
+impl SpeakingActivityTracker {
+    /// 记录开始说话（已在说话时忽略）
+    pub fn start(&mut self, player_id: &str, now: Instant) {
+        if self.started.contains_key(player_id) {
+            return;
+        }
+        self.started.insert(player_id.to_string(), now);
+        self.totals.entry(player_id.to_string()).or_default().1 += 1;
+    }
+
+    /// 记录停止说话，把本次发言时长计入累计
+    pub fn stop(&mut self, player_id: &str, now: Instant) {
+        if let Some(since) = self.started.remove(player_id) {
+            self.totals.entry(player_id.to_string()).or_default().0 += now.saturating_duration_since(since);
+        }
+    }
+
+    /// 获取指定玩家的统计
+    pub fn activity(&self, player_id: &str, now: Instant) -> SpeakingActivity {
+        let (total, count) = self.totals.get(player_id).copied().unwrap_or_default();
+        let ongoing = self
+            .started
+            .get(player_id)
+            .map(|since| now.saturating_duration_since(*since))
+            .unwrap_or_default();
+        SpeakingActivity {
+            speaking_seconds: (total + ongoing).as_secs(),
+            message_count: count,
+        }
+    }
+
+    /// 获取所有玩家的统计
+    pub fn all(&self, now: Instant) -> HashMap<String, SpeakingActivity> {
+        self.totals
+            .keys()
+            .map(|id| (id.clone(), self.activity(id, now)))
+            .collect()
+    }
+
+    /// 清空统计（新会话）
+    pub fn reset(&mut self) {
+        self.totals.clear();
+        self.started.clear();
+    }
+}
+
+/// 广播的输入电平最高等级（0 表示静音）
+pub const INPUT_LEVEL_STEPS: u8 = 8;
+
+/// 两次输入电平广播的最小间隔（每人最多每秒 5 条）
+pub const INPUT_LEVEL_MIN_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 将麦克风电平（0.0-1.0）量化为 0..=INPUT_LEVEL_STEPS 的等级
+///
+/// 低于说话阈值视为静音；按平方根映射，使小音量也有可见的变化。
+pub fn quantize_input_level(level: f32) -> u8 {
+    if !level.is_finite() || level < SPEAKING_LEVEL_THRESHOLD {
+        return 0;
+    }
+    let scaled = (level.min(1.0).sqrt() * INPUT_LEVEL_STEPS as f32).ceil() as u8;
+    scaled.clamp(1, INPUT_LEVEL_STEPS)
+}
+
+/// 输入电平广播限流器
+///
+/// 只在等级变化时广播，且两次广播间隔不小于最小间隔；初始静音不广播。
+#[derive(Debug)]
+pub struct InputLevelThrottle {
+    /// 最近一次广播的等级与时间
+    last_sent: Option<(u8, Instant)>,
+    /// 最小广播间隔
+    min_interval: Duration,
+}
+
+impl InputLevelThrottle {
+    /// 创建新的限流器
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            last_sent: None,
+            min_interval,
+        }
+    }
+
+    /// 判断本次等级是否需要广播，需要时记录为最近一次广播
+    ///
+    /// # 参数
+    /// * `level` - 量化后的等级
+    /// * `now` - 当前时间
+    pub fn should_send(&mut self, level: u8, now: Instant) -> bool {
+        match self.last_sent {
+            Some((last, _)) if last == level => return false,
+            Some((_, at)) if now.duration_since(at) < self.min_interval => return false,
+            None if level == 0 => return false,
+            _ => {}
+        }
+        self.last_sent = Some((level, now));
+        true
+    }
+
+    /// 重置限流状态
+    pub fn reset(&mut self) {
+        self.last_sent = None;
+    }
+}
+
+impl Default for InputLevelThrottle {
+    fn default() -> Self {
+        Self::new(INPUT_LEVEL_MIN_INTERVAL)
+    }
+}
+
+/// 响度均衡：玩家的有效电平样本数达到该值后才参与均衡
+pub const LOUDNESS_MIN_SAMPLES: u32 = 10;
+
+/// 长期平均电平的平滑系数（越小越平稳）
+const LOUDNESS_EMA_ALPHA: f32 = 0.05;
+
+/// 归一化增益下限
+pub const MIN_NORMALIZATION_GAIN: f32 = 0.5;
+
+/// 归一化增益上限
+pub const MAX_NORMALIZATION_GAIN: f32 = 2.0;
+
+/// 增益变化超过该值才重新下发给前端
+pub const NORMALIZATION_GAIN_STEP: f32 = 0.05;
+
+/// 将量化后的输入电平等级还原为近似振幅（`quantize_input_level` 的逆映射）
+pub fn input_level_amplitude(level: u8) -> f32 {
+    (level.min(INPUT_LEVEL_STEPS) as f32 / INPUT_LEVEL_STEPS as f32).powi(2)
+}
+
+/// 计算使玩家平均振幅接近目标振幅的增益，结果限制在增益上下限之间
+///
+/// # 参数
+/// * `average` - 玩家的长期平均振幅
+/// * `target` - 目标振幅
+pub fn normalization_gain(average: f32, target: f32) -> f32 {
+    if !average.is_finite() || !target.is_finite() || average <= 0.0 || target <= 0.0 {
+        return DEFAULT_PLAYER_VOLUME;
+    }
+    (target / average).clamp(MIN_NORMALIZATION_GAIN, MAX_NORMALIZATION_GAIN)
+}
+
+/// 跨玩家响度均衡
+///
+/// 根据各玩家上报的输入电平记录说话时的长期平均振幅（静音样本不计入），
+/// 以所有已校准玩家的几何平均振幅为目标，给出每个玩家的建议增益。
+/// 已校准的玩家少于两人时没有可比较的对象，增益均为 1.0。
+#[derive(Debug, Default)]
+pub struct LoudnessNormalizer {
+    /// 玩家ID -> (平均振幅, 样本数)
+    averages: HashMap<String, (f32, u32)>,
+    /// 最近一次下发的增益
+    last_sent: HashMap<String, f32>,
+}
+
+impl LoudnessNormalizer {
+    /// 记录一次玩家输入电平
+    ///
+    /// # 参数
+    /// * `player_id` - 玩家ID
+    /// * `level` - 量化后的电平等级，0（静音）会被忽略
+    pub fn record(&mut self, player_id: &str, level: u8) {
+        if level == 0 {
+            return;
+        }
+        let amplitude = input_level_amplitude(level);
+        let entry = self.averages.entry(player_id.to_string()).or_insert((amplitude, 0));
+        if entry.1 > 0 {
+            entry.0 += (amplitude - entry.0) * LOUDNESS_EMA_ALPHA;
+        }
+        entry.1 = entry.1.saturating_add(1);
+    }
+
+    /// 计算所有已校准玩家的建议增益
+    pub fn gains(&self) -> HashMap<String, f32> {
+        let calibrated: Vec<(&String, f32)> = self.averages
+            .iter()
+            .filter(|(_, (_, samples))| *samples >= LOUDNESS_MIN_SAMPLES)
+            .map(|(id, (average, _))| (id, *average))
+            .collect();
+        if calibrated.len() < 2 {
+            return calibrated.into_iter().map(|(id, _)| (id.clone(), DEFAULT_PLAYER_VOLUME)).collect();
+        }
+        let target = (calibrated.iter().map(|(_, a)| a.ln()).sum::<f32>() / calibrated.len() as f32).exp();
+        calibrated
+            .into_iter()
+            .map(|(id, average)| (id.clone(), normalization_gain(average, target)))
+            .collect()
+    }
+
+    /// 与上次下发相比有玩家增益明显变化（或玩家增减）时返回全部建议增益
+    pub fn take_changed_gains(&mut self) -> Option<HashMap<String, f32>> {
+        let gains = self.gains();
+        let changed = gains.len() != self.last_sent.len()
+            || gains.iter().any(|(id, gain)| {
+                self.last_sent
+                    .get(id)
+                    .is_none_or(|last| (gain - last).abs() >= NORMALIZATION_GAIN_STEP)
+            });
+        if !changed {
+            return None;
+        }
+        self.last_sent = gains.clone();
+        Some(gains)
+    }
+
+    /// 移除玩家
+    pub fn remove(&mut self, player_id: &str) {
+        self.averages.remove(player_id);
+    }
+
+    /// 重置所有记录
+    pub fn reset(&mut self) {
+        self.averages.clear();
+        self.last_sent.clear();
+    }
+}
+
+/// 麦克风模式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MicMode {
+    /// 开关模式：快捷键切换麦克风开/关
+    #[default]
+    Toggle,
+    /// 按键说话：按住按键时发声，松开即静音
+    PushToTalk,
+    /// 语音激活：麦克风保持开启，由声音检测决定是否发声
+    VoiceActivated,
+}
+
+impl MicMode {
+    /// 模式名称（与序列化格式一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MicMode::Toggle => "toggle",
+            MicMode::PushToTalk => "push_to_talk",
+            MicMode::VoiceActivated => "voice_activated",
+        }
+    }
+}
+
+impl std::str::FromStr for MicMode {
+    type Err = VoiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "toggle" => Ok(MicMode::Toggle),
+            "push_to_talk" | "pushtotalk" | "ptt" => Ok(MicMode::PushToTalk),
+            "voice_activated" | "voiceactivated" | "vad" => Ok(MicMode::VoiceActivated),
+            other => Err(VoiceError::OperationFailed(format!("未知的麦克风模式: {}", other))),
+        }
+    }
+}
+
 /// 玩家状态信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerStatus {
@@ -79,6 +483,11 @@ pub enum SignalingMessage {
         player_id: String,
         timestamp: i64,
     },
+    /// 说话状态消息（语音活动检测）
+    SpeakingState {
+        player_id: String,
+        is_speaking: bool,
+    },
 }
 
 /// 语音服务错误类型
@@ -106,6 +515,330 @@ impl From<VoiceError> for AppError {
     }
 }
 
+/// 通过 cpal 枚举系统音频设备（Windows 下为 WASAPI）
+/// 
+/// 设备 ID 使用设备名称；默认设备通过与 default_input_device / default_output_device 的名称比对确定。
+/// 枚举失败或没有音频设备时返回空列表。
+fn enumerate_system_audio_devices() -> Vec<AudioDevice> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+    
+    let host = cpal::default_host();
+    let mut devices = Vec::new();
+    
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    match host.input_devices() {
+        Ok(inputs) => {
+            for device in inputs {
+                match device.name() {
+                    Ok(name) => push_unique_device(&mut devices, name, DeviceType::Microphone, &default_input),
+                    Err(e) => log::warn!("读取输入设备名称失败: {}", e),
+                }
+            }
+        }
+        Err(e) => log::warn!("枚举输入设备失败: {}", e),
+    }
+    
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+    match host.output_devices() {
+        Ok(outputs) => {
+            for device in outputs {
+                match device.name() {
+                    Ok(name) => push_unique_device(&mut devices, name, DeviceType::Speaker, &default_output),
+                    Err(e) => log::warn!("读取输出设备名称失败: {}", e),
+                }
+            }
+        }
+        Err(e) => log::warn!("枚举输出设备失败: {}", e),
+    }
+    
+    devices
+}
+
+/// 在阻塞线程中重新枚举系统音频设备（用于热插拔检测）
+///
+/// 枚举异常或一个设备都没有时返回 `None`：偶发的枚举失败同样表现为空列表，
+/// 按空列表刷新会把所有设备当作已拔出并清空当前选择，因此由调用方跳过本次刷新。
+pub async fn scan_audio_devices() -> Option<Vec<AudioDevice>> {
+    match tokio::task::spawn_blocking(enumerate_system_audio_devices).await {
+        Ok(devices) if !devices.is_empty() => Some(devices),
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("重新枚举音频设备时发生异常: {}", e);
+            None
+        }
+    }
+}
+
+/// 音频设备热插拔检测间隔
+pub const AUDIO_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// 选中设备失效后的自动切换
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceFallback {
+    /// 设备类型
+    pub device_type: DeviceType,
+    /// 失效的设备ID
+    pub previous: String,
+    /// 切换到的设备ID（没有可用设备时为 None）
+    pub current: Option<String>,
+}
+
+/// 一次设备列表刷新的结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioDeviceRefresh {
+    /// 设备列表是否有变化
+    pub devices_changed: bool,
+    /// 选中设备失效后的自动切换
+    pub fallbacks: Vec<DeviceFallback>,
+}
+
+/// 选中设备不在新列表中时选择替代设备：优先系统默认设备，其次同类型第一个设备
+///
+/// 返回 None 表示选中设备仍然可用（或原本就未选择），无需切换。
+fn pick_fallback_device(devices: &[AudioDevice], selected: Option<&str>, device_type: &DeviceType) -> Option<Option<String>> {
+    let selected = selected?;
+    let same_type = || devices.iter().filter(|d| &d.device_type == device_type);
+    if same_type().any(|d| d.id == selected) {
+        return None;
+    }
+    let replacement = same_type()
+        .find(|d| d.is_default)
+        .or_else(|| same_type().next())
+        .map(|d| d.id.clone());
+    Some(replacement)
+}
+
+/// 添加设备到列表（同类型同名设备只保留一个，避免以名称作为 ID 时冲突）
+fn push_unique_device(
+    devices: &mut Vec<AudioDevice>,
+    name: String,
+    device_type: DeviceType,
+    default_name: &Option<String>,
+) {
+    if devices.iter().any(|d| d.device_type == device_type && d.id == name) {
+        return;
+    }
+    
+    let is_default = default_name.as_deref() == Some(name.as_str());
+    devices.push(AudioDevice {
+        id: name.clone(),
+        name,
+        device_type,
+        is_default,
+    });
+}
+
+/// 麦克风电平推送间隔（约 20Hz）
+pub const MIC_LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 电平条下限（dBFS），更小的声音显示为 0
+const MIC_LEVEL_FLOOR_DB: f32 = -60.0;
+
+/// 计算一段采样的 RMS（采样值范围 -1.0..=1.0）
+pub fn rms(samples: impl IntoIterator<Item = f32>) -> f32 {
+    let (sum, count) = samples
+        .into_iter()
+        .fold((0.0f64, 0usize), |(sum, count), s| (sum + (s as f64) * (s as f64), count + 1));
+    if count == 0 {
+        return 0.0;
+    }
+    (sum / count as f64).sqrt() as f32
+}
+
+/// 将 RMS 映射为 0.0-1.0 的电平条数值
+///
+/// 按分贝线性映射（-60dBFS → 0，0dBFS → 1），说话声音能占到电平条的中段，
+/// 直接使用 RMS 时正常说话只有 0.05 左右，几乎看不到变化。
+pub fn mic_level_from_rms(rms: f32) -> f32 {
+    if !rms.is_finite() || rms <= 0.0 {
+        return 0.0;
+    }
+    let db = 20.0 * rms.log10();
+    ((db - MIC_LEVEL_FLOOR_DB) / -MIC_LEVEL_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// 限制说话阈值到 0.0..=MAX_VAD_THRESHOLD，非法值回退默认阈值
+pub fn clamp_vad_threshold(value: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(0.0, MAX_VAD_THRESHOLD)
+    } else {
+        SPEAKING_LEVEL_THRESHOLD
+    }
+}
+
+/// 说话阈值校准结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadCalibration {
+    /// 环境底噪 RMS（线性，0.0-1.0）
+    pub noise_level: f32,
+    /// 推荐的说话阈值
+    pub recommended: f32,
+}
+
+/// 根据采集到的环境音 RMS 推荐说话阈值
+///
+/// 取 95 分位作为底噪（忽略偶发的碰撞声），乘以倍数再加上余量后作为阈值；没有样本时推荐默认阈值。
+pub fn recommend_vad_threshold(samples: &[f32]) -> VadCalibration {
+    let mut levels: Vec<f32> = samples.iter().copied().filter(|l| l.is_finite()).collect();
+    if levels.is_empty() {
+        return VadCalibration {
+            noise_level: 0.0,
+            recommended: SPEAKING_LEVEL_THRESHOLD,
+        };
+    }
+    levels.sort_by(|a, b| a.total_cmp(b));
+    let index = ((levels.len() - 1) as f32 * 0.95).round() as usize;
+    let noise_level = levels[index].clamp(0.0, 1.0);
+    VadCalibration {
+        noise_level,
+        recommended: clamp_vad_threshold(noise_level * VAD_CALIBRATION_GAIN + VAD_CALIBRATION_MARGIN),
+    }
+}
+
+/// 麦克风电平表
+///
+/// 在独立线程上打开一个只读的 cpal 输入流（共享模式，不独占设备，
+/// 与前端 WebRTC 的实际语音采集互不影响），每个采样块计算一次电平。
+/// cpal 的 Stream 不能跨线程移动，所以由采集线程持有，丢弃本结构体时线程随之退出并关闭输入流。
+pub struct MicLevelMeter {
+    /// 打开的设备名称（`None` 表示系统默认麦克风）
+    device: Option<String>,
+    /// 最近一次线性 RMS（f32 位模式）
+    rms: Arc<std::sync::atomic::AtomicU32>,
+    /// 输入流运行中出错（如设备被拔出）
+    failed: Arc<AtomicBool>,
+    /// 丢弃时通知采集线程退出
+    _stop: std::sync::mpsc::Sender<()>,
+}
+
+impl MicLevelMeter {
+    /// 打开麦克风并开始测量（阻塞到输入流启动成功或失败）
+    ///
+    /// # 参数
+    /// * `device` - 设备名称，`None` 或找不到时使用系统默认麦克风
+    ///
+    /// # 返回
+    /// * `Err(VoiceError)` - 没有麦克风、无麦克风权限或设备被占用
+    pub fn start(device: Option<String>) -> Result<Self, VoiceError> {
+        let rms = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let failed = Arc::new(AtomicBool::new(false));
+        let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<(), VoiceError>>();
+
+        let thread_rms = rms.clone();
+        let thread_failed = failed.clone();
+        let thread_device = device.clone();
+        std::thread::Builder::new()
+            .name("mic-level".to_string())
+            .spawn(move || match open_level_stream(thread_device.as_deref(), thread_rms, thread_failed) {
+                Ok(stream) => {
+                    let _ = ready_tx.send(Ok(()));
+                    // 发送端被丢弃时 recv 返回错误，随后关闭输入流
+                    let _ = stop_rx.recv();
+                    drop(stream);
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                }
+            })
+            .map_err(|e| VoiceError::InitializationFailed(format!("无法创建电平采集线程: {}", e)))?;
+
+        ready_rx
+            .recv()
+            .map_err(|_| VoiceError::InitializationFailed("电平采集线程异常退出".to_string()))??;
+        Ok(Self {
+            device,
+            rms,
+            failed,
+            _stop: stop_tx,
+        })
+    }
+
+    /// 打开的设备名称
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    /// 最近一次电平（0.0-1.0，按分贝映射，用于电平条显示）
+    pub fn level(&self) -> f32 {
+        mic_level_from_rms(self.rms())
+    }
+
+    /// 最近一次线性 RMS（与说话阈值同一刻度）
+    pub fn rms(&self) -> f32 {
+        f32::from_bits(self.rms.load(Ordering::Relaxed))
+    }
+
+    /// 输入流是否已出错
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+/// 打开输入流，每个采样块把线性 RMS 写入 `rms`
+fn open_level_stream(
+    device: Option<&str>,
+    rms_level: Arc<std::sync::atomic::AtomicU32>,
+    failed: Arc<AtomicBool>,
+) -> Result<cpal::Stream, VoiceError> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    let host = cpal::default_host();
+    let named = device.and_then(|name| {
+        host.input_devices()
+            .ok()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+    });
+    let input = match named {
+        Some(d) => d,
+        None => host.default_input_device().ok_or(VoiceError::DeviceNotFound)?,
+    };
+    let config = input
+        .default_input_config()
+        .map_err(|e| VoiceError::InitializationFailed(format!("读取麦克风配置失败: {}", e)))?;
+
+    let store = move |value: f32| rms_level.store(value.to_bits(), Ordering::Relaxed);
+    let on_error = move |e: cpal::StreamError| {
+        log::warn!("麦克风电平输入流出错: {}", e);
+        failed.store(true, Ordering::Relaxed);
+    };
+    let stream_config = config.config();
+    let stream = match config.sample_format() {
+        SampleFormat::F32 => input.build_input_stream(
+            &stream_config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| store(rms(data.iter().copied())),
+            on_error,
+            None,
+        ),
+        SampleFormat::I16 => input.build_input_stream(
+            &stream_config,
+            move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                store(rms(data.iter().map(|&s| s as f32 / i16::MAX as f32)))
+            },
+            on_error,
+            None,
+        ),
+        SampleFormat::U16 => input.build_input_stream(
+            &stream_config,
+            move |data: &[u16], _: &cpal::InputCallbackInfo| {
+                store(rms(data.iter().map(|&s| (s as f32 - 32768.0) / 32768.0)))
+            },
+            on_error,
+            None,
+        ),
+        other => {
+            return Err(VoiceError::InitializationFailed(format!("不支持的采样格式: {:?}", other)));
+        }
+    }
+    .map_err(|e| VoiceError::InitializationFailed(format!("打开麦克风失败: {}", e)))?;
+    stream
+        .play()
+        .map_err(|e| VoiceError::InitializationFailed(format!("启动麦克风失败: {}", e)))?;
+    Ok(stream)
+}
+
 /// 语音服务
 /// 
 /// 负责管理 WebRTC 语音通信、音频设备、麦克风状态和玩家静音状态
@@ -116,23 +849,53 @@ pub struct VoiceService {
     /// 当前麦克风是否开启
     mic_enabled: Arc<AtomicBool>,
     
+    /// 麦克风模式（开关 / 按键说话 / 语音激活）
+    mic_mode: Arc<RwLock<MicMode>>,
+    
     /// 被静音的玩家集合（玩家ID）
     muted_players: Arc<RwLock<HashSet<String>>>,
     
+    /// 玩家音量增益映射（玩家ID -> 增益，1.0 表示 100%）
+    player_volumes: Arc<RwLock<HashMap<String, f32>>>,
+    
     /// 全局静音状态
     global_muted: Arc<AtomicBool>,
     
+    /// 正在说话的玩家集合（玩家ID）
+    speaking_players: Arc<RwLock<HashSet<String>>>,
+    
+    /// 各玩家说话时长与发言次数（按会话累计）
+    speaking_activity: Arc<RwLock<SpeakingActivityTracker>>,
+    
+    /// 本地说话状态防抖器
+    local_speaking: Arc<RwLock<SpeakingDebouncer>>,
+    
+    /// 本地输入电平广播限流器
+    input_level_throttle: Arc<RwLock<InputLevelThrottle>>,
+    
     /// 玩家状态映射（玩家ID -> 状态）
     player_statuses: Arc<RwLock<HashMap<String, PlayerStatus>>>,
     
-    /// 信令消息队列
-    signaling_queue: Arc<Mutex<Vec<SignalingMessage>>>,
+    /// 信令消息队列（有界，见 `MAX_SIGNALING_QUEUE_LEN`）
+    signaling_queue: Arc<Mutex<VecDeque<SignalingMessage>>>,
     
     /// 当前选择的麦克风设备ID
     selected_mic_device: Arc<RwLock<Option<String>>>,
     
     /// 当前选择的扬声器设备ID
     selected_speaker_device: Arc<RwLock<Option<String>>>,
+    
+    /// 游客模式（只收听，不允许开启麦克风）
+    guest_mode: Arc<AtomicBool>,
+    
+    /// 是否启用跨玩家响度均衡
+    loudness_normalization: Arc<AtomicBool>,
+    
+    /// 跨玩家响度均衡器
+    loudness_normalizer: Arc<RwLock<LoudnessNormalizer>>,
+    
+    /// 说话阈值（f32 位模式）
+    vad_threshold: Arc<std::sync::atomic::AtomicU32>,
 }
 
 impl VoiceService {
@@ -146,12 +909,22 @@ impl VoiceService {
         Self {
             audio_devices: Arc::new(RwLock::new(Vec::new())),
             mic_enabled: Arc::new(AtomicBool::new(false)),
+            mic_mode: Arc::new(RwLock::new(MicMode::default())),
             muted_players: Arc::new(RwLock::new(HashSet::new())),
+            player_volumes: Arc::new(RwLock::new(HashMap::new())),
             global_muted: Arc::new(AtomicBool::new(false)),
+            speaking_players: Arc::new(RwLock::new(HashSet::new())),
+            speaking_activity: Arc::new(RwLock::new(SpeakingActivityTracker::default())),
+            local_speaking: Arc::new(RwLock::new(SpeakingDebouncer::default())),
+            input_level_throttle: Arc::new(RwLock::new(InputLevelThrottle::default())),
             player_statuses: Arc::new(RwLock::new(HashMap::new())),
-            signaling_queue: Arc::new(Mutex::new(Vec::new())),
+            signaling_queue: Arc::new(Mutex::new(VecDeque::new())),
             selected_mic_device: Arc::new(RwLock::new(None)),
             selected_speaker_device: Arc::new(RwLock::new(None)),
+            guest_mode: Arc::new(AtomicBool::new(false)),
+            loudness_normalization: Arc::new(AtomicBool::new(false)),
+            loudness_normalizer: Arc::new(RwLock::new(LoudnessNormalizer::default())),
+            vad_threshold: Arc::new(std::sync::atomic::AtomicU32::new(SPEAKING_LEVEL_THRESHOLD.to_bits())),
         }
     }
     
@@ -168,11 +941,7 @@ impl VoiceService {
         // 枚举音频设备
         match self.enumerate_audio_devices().await {
             Ok(devices) => {
-                log::info!("成功枚举 {} 个音频设备", devices.len());
-                
-                // 设置默认设备
-                self.set_default_devices(&devices).await;
-                
+                self.initialize_with_devices(devices).await;
                 Ok(())
             }
             Err(e) => {
@@ -184,37 +953,42 @@ impl VoiceService {
         }
     }
     
+    /// 使用给定的设备列表初始化语音服务
+    /// 
+    /// # 参数
+    /// * `devices` - 音频设备列表
+    pub async fn initialize_with_devices(&self, devices: Vec<AudioDevice>) {
+        log::info!("成功枚举 {} 个音频设备", devices.len());
+        
+        // 更新内部设备列表
+        *self.audio_devices.write().await = devices.clone();
+        
+        // 设置默认设备
+        self.set_default_devices(&devices).await;
+    }
+    
     /// 枚举可用的音频设备
     /// 
+    /// 在阻塞线程中通过 cpal 枚举系统设备，枚举过程中的错误或 panic 只会导致返回空列表
+    /// 
     /// # 返回
-    /// * `Ok(Vec<AudioDevice>)` - 音频设备列表
+    /// * `Ok(Vec<AudioDevice>)` - 音频设备列表（无设备时为空）
     /// * `Err(VoiceError)` - 枚举失败
     async fn enumerate_audio_devices(&self) -> Result<Vec<AudioDevice>, VoiceError> {
         log::info!("开始枚举音频设备");
         
-        // 注意：这里是模拟实现，实际项目中需要使用 cpal 或其他音频库
-        // 来真正枚举系统音频设备
-        let mut devices = Vec::new();
-        
-        // 添加默认麦克风设备
-        devices.push(AudioDevice {
-            id: "default_mic".to_string(),
-            name: "默认麦克风".to_string(),
-            device_type: DeviceType::Microphone,
-            is_default: true,
-        });
-        
-        // 添加默认扬声器设备
-        devices.push(AudioDevice {
-            id: "default_speaker".to_string(),
-            name: "默认扬声器".to_string(),
-            device_type: DeviceType::Speaker,
-            is_default: true,
-        });
-        
-        // 更新内部设备列表
-        let mut audio_devices = self.audio_devices.write().await;
-        *audio_devices = devices.clone();
+        let devices = match tokio::task::spawn_blocking(|| {
+            log::info!("音频主机: {:?}", cpal::default_host().id());
+            enumerate_system_audio_devices()
+        })
+        .await
+        {
+            Ok(devices) => devices,
+            Err(e) => {
+                log::error!("枚举音频设备时发生异常: {}", e);
+                Vec::new()
+            }
+        };
         
         log::info!("音频设备枚举完成，共 {} 个设备", devices.len());
         
@@ -222,24 +996,71 @@ impl VoiceService {
     }
     
     /// 设置默认音频设备
+    /// 
+    /// 只在尚未选择设备或已选设备不在列表中时使用系统默认设备，
+    /// 重复初始化（每次进入大厅）不会覆盖已恢复或用户选择的设备。
     async fn set_default_devices(&self, devices: &[AudioDevice]) {
+        let is_available = |id: &str, device_type: &DeviceType| {
+            devices.iter().any(|d| d.id == id && &d.device_type == device_type)
+        };
+        
         // 查找默认麦克风
         if let Some(default_mic) = devices.iter()
             .find(|d| d.device_type == DeviceType::Microphone && d.is_default) {
             let mut selected = self.selected_mic_device.write().await;
-            *selected = Some(default_mic.id.clone());
-            log::info!("设置默认麦克风: {}", default_mic.name);
+            if !selected.as_deref().is_some_and(|id| is_available(id, &DeviceType::Microphone)) {
+                *selected = Some(default_mic.id.clone());
+                log::info!("设置默认麦克风: {}", default_mic.name);
+            }
         }
         
         // 查找默认扬声器
         if let Some(default_speaker) = devices.iter()
             .find(|d| d.device_type == DeviceType::Speaker && d.is_default) {
             let mut selected = self.selected_speaker_device.write().await;
-            *selected = Some(default_speaker.id.clone());
-            log::info!("设置默认扬声器: {}", default_speaker.name);
+            if !selected.as_deref().is_some_and(|id| is_available(id, &DeviceType::Speaker)) {
+                *selected = Some(default_speaker.id.clone());
+                log::info!("设置默认扬声器: {}", default_speaker.name);
+            }
         }
     }
     
+    /// 用重新枚举的设备列表更新服务状态（热插拔）
+    /// 
+    /// 当前选中的麦克风/扬声器已被拔出时自动回退到系统默认设备。
+    /// 
+    /// # 参数
+    /// * `devices` - 重新枚举得到的设备列表
+    pub async fn apply_device_list(&self, devices: Vec<AudioDevice>) -> AudioDeviceRefresh {
+        let mut refresh = AudioDeviceRefresh::default();
+        {
+            let mut current = self.audio_devices.write().await;
+            if *current == devices {
+                return refresh;
+            }
+            *current = devices.clone();
+            refresh.devices_changed = true;
+        }
+        
+        for (device_type, slot) in [
+            (DeviceType::Microphone, &self.selected_mic_device),
+            (DeviceType::Speaker, &self.selected_speaker_device),
+        ] {
+            let mut selected = slot.write().await;
+            if let Some(replacement) = pick_fallback_device(&devices, selected.as_deref(), &device_type) {
+                let previous = selected.take().unwrap_or_default();
+                log::warn!("音频设备 {} 已移除，切换到: {:?}", previous, replacement);
+                *selected = replacement.clone();
+                refresh.fallbacks.push(DeviceFallback {
+                    device_type,
+                    previous,
+                    current: replacement,
+                });
+            }
+        }
+        refresh
+    }
+    
     /// 获取可用的音频设备列表
     /// 
     /// # 返回
@@ -260,9 +1081,13 @@ impl VoiceService {
     pub async fn set_mic_enabled(&self, enabled: bool) -> Result<bool, VoiceError> {
         log::info!("设置麦克风状态: {}", if enabled { "开启" } else { "关闭" });
         
-        // 检查是否有选择的麦克风设备
-        let selected_device = self.selected_mic_device.read().await;
-        if selected_device.is_none() {
+        if enabled && self.is_guest_mode() {
+            log::warn!("游客模式下不能开启麦克风");
+            return Err(VoiceError::OperationFailed("游客模式下不能开启麦克风".to_string()));
+        }
+        
+        // 开麦需要已选择的麦克风设备，关麦总是允许
+        if enabled && self.selected_mic_device.read().await.is_none() {
             log::warn!("未选择麦克风设备");
             return Err(VoiceError::DeviceNotFound);
         }
@@ -275,6 +1100,24 @@ impl VoiceService {
         Ok(enabled)
     }
     
+    /// 设置游客模式
+    /// 
+    /// 进入游客模式时会立即关闭麦克风；游客仍可接收语音。
+    /// 
+    /// # 参数
+    /// * `guest` - 是否为游客
+    pub fn set_guest_mode(&self, guest: bool) {
+        self.guest_mode.store(guest, Ordering::SeqCst);
+        if guest {
+            self.mic_enabled.store(false, Ordering::SeqCst);
+        }
+    }
+    
+    /// 是否处于游客模式
+    pub fn is_guest_mode(&self) -> bool {
+        self.guest_mode.load(Ordering::SeqCst)
+    }
+    
     /// 切换麦克风状态
     /// 
     /// # 返回
@@ -285,6 +1128,31 @@ impl VoiceService {
         self.set_mic_enabled(!current).await
     }
     
+    /// 设置麦克风模式
+    /// 
+    /// 切换到按键说话模式时会先通过 `set_mic_enabled` 关闭麦克风，等待按键按下再发声
+    /// 
+    /// # 参数
+    /// * `mode` - 新的麦克风模式
+    /// 
+    /// # 返回
+    /// 麦克风是否因此被关闭（调用方需要像其它开关麦路径一样通知前端并广播状态）
+    pub async fn set_mic_mode(&self, mode: MicMode) -> bool {
+        log::info!("设置麦克风模式: {}", mode.as_str());
+        
+        *self.mic_mode.write().await = mode;
+        
+        if mode == MicMode::PushToTalk && self.is_mic_enabled() {
+            return self.set_mic_enabled(false).await.is_ok();
+        }
+        false
+    }
+    
+    /// 获取当前麦克风模式
+    pub async fn get_mic_mode(&self) -> MicMode {
+        *self.mic_mode.read().await
+    }
+    
     /// 获取当前麦克风状态
     /// 
     /// # 返回
@@ -334,6 +1202,44 @@ impl VoiceService {
         muted_players.contains(player_id)
     }
     
+    /// 设置指定玩家的音量增益
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家唯一标识符
+    /// * `volume` - 音量增益（0.0-2.0，即 0%-200%），超出范围会被截断
+    /// 
+    /// # 返回
+    /// * `Ok(f32)` - 实际生效的音量增益
+    /// * `Err(VoiceError)` - 音量值无效
+    pub async fn set_player_volume(&self, player_id: &str, volume: f32) -> Result<f32, VoiceError> {
+        if !volume.is_finite() {
+            return Err(VoiceError::OperationFailed(format!("无效的音量值: {}", volume)));
+        }
+        
+        let clamped = volume.clamp(MIN_PLAYER_VOLUME, MAX_PLAYER_VOLUME);
+        self.player_volumes.write().await.insert(player_id.to_string(), clamped);
+        
+        log::info!("玩家 {} 音量已设置为 {:.0}%", player_id, clamped * 100.0);
+        
+        Ok(clamped)
+    }
+    
+    /// 获取指定玩家的音量增益
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家唯一标识符
+    /// 
+    /// # 返回
+    /// * `f32` - 音量增益，未设置时为 1.0（100%）
+    pub async fn get_player_volume(&self, player_id: &str) -> f32 {
+        self.player_volumes
+            .read()
+            .await
+            .get(player_id)
+            .copied()
+            .unwrap_or(DEFAULT_PLAYER_VOLUME)
+    }
+    
     /// 全局静音或取消静音所有玩家
     /// 
     /// # 参数
@@ -382,8 +1288,7 @@ impl VoiceService {
         };
         
         // 将消息加入信令队列
-        let mut queue = self.signaling_queue.lock().await;
-        queue.push(message);
+        self.push_signaling(message).await;
         
         log::info!("状态广播已加入队列");
         
@@ -434,6 +1339,9 @@ impl VoiceService {
                 // 移除静音状态
                 let mut muted_players = self.muted_players.write().await;
                 muted_players.remove(player_id);
+                
+                // 移除说话状态（发言统计保留到会话结束）
+                self.set_player_speaking(player_id, false).await;
             }
             SignalingMessage::StatusUpdate { player_id, mic_enabled } => {
                 log::info!("玩家 {} 状态更新: 麦克风 {}", player_id, mic_enabled);
@@ -462,11 +1370,161 @@ impl VoiceService {
                     status.timestamp = Utc::now();
                 }
             }
+            SignalingMessage::SpeakingState { player_id, is_speaking } => {
+                log::debug!("玩家 {} 说话状态: {}", player_id, is_speaking);
+                self.set_player_speaking(player_id, *is_speaking).await;
+            }
         }
         
         Ok(())
     }
     
+    /// 更新玩家说话状态
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家 ID
+    /// * `is_speaking` - 是否正在说话
+    /// 
+    /// # 返回
+    /// * `bool` - 状态是否发生变化
+    pub async fn set_player_speaking(&self, player_id: &str, is_speaking: bool) -> bool {
+        let mut speaking = self.speaking_players.write().await;
+        let mut activity = self.speaking_activity.write().await;
+        if is_speaking {
+            activity.start(player_id, Instant::now());
+            speaking.insert(player_id.to_string())
+        } else {
+            activity.stop(player_id, Instant::now());
+            speaking.remove(player_id)
+        }
+    }
+    
+    /// 获取各玩家的说话时长与发言次数（玩家ID -> 统计）
+    pub async fn get_speaking_activity(&self) -> HashMap<String, SpeakingActivity> {
+        self.speaking_activity.read().await.all(Instant::now())
+    }
+    
+    /// 检查玩家是否正在说话
+    pub async fn is_player_speaking(&self, player_id: &str) -> bool {
+        self.speaking_players.read().await.contains(player_id)
+    }
+    
+    /// 获取正在说话的玩家列表
+    pub async fn get_speaking_players(&self) -> Vec<String> {
+        self.speaking_players.read().await.iter().cloned().collect()
+    }
+    
+    /// 上报本地麦克风电平，进行语音活动检测
+    /// 
+    /// 麦克风关闭时视为静音。状态变为开始/停止说话时会更新说话集合并加入信令队列，
+    /// 返回 `ReleasePending` 时调用方应在 `SPEAKING_RELEASE_DELAY` 后调用 `poll_local_speaking`。
+    /// 
+    /// # 参数
+    /// * `player_id` - 本地玩家 ID
+    /// * `level` - 麦克风线性 RMS（0.0-1.0）
+    pub async fn update_local_audio_level(&self, player_id: &str, level: f32) -> SpeakingUpdate {
+        let above = self.is_mic_enabled() && level.is_finite() && level >= self.get_vad_threshold();
+        let update = self.local_speaking.write().await.update(above, Instant::now());
+        self.apply_local_speaking_update(player_id, update).await;
+        update
+    }
+    
+    /// 检查本地说话状态的延迟释放是否到期
+    /// 
+    /// # 参数
+    /// * `player_id` - 本地玩家 ID
+    pub async fn poll_local_speaking(&self, player_id: &str) -> SpeakingUpdate {
+        let update = self.local_speaking.write().await.poll(Instant::now());
+        self.apply_local_speaking_update(player_id, update).await;
+        update
+    }
+    
+    /// 计算本次需要广播的输入电平等级
+    /// 
+    /// 麦克风关闭时按静音处理；受限流控制，不需要广播时返回 None。
+    /// 
+    /// # 参数
+    /// * `level` - 麦克风线性 RMS（0.0-1.0）
+    pub async fn next_input_level_broadcast(&self, level: f32) -> Option<u8> {
+        let quantized = if self.is_mic_enabled() { quantize_input_level(level) } else { 0 };
+        self.input_level_throttle
+            .write()
+            .await
+            .should_send(quantized, Instant::now())
+            .then_some(quantized)
+    }
+    
+    /// 设置说话阈值，立即作用于后续的语音活动检测
+    /// 
+    /// # 返回
+    /// 限制范围后实际生效的阈值
+    pub fn set_vad_threshold(&self, value: f32) -> f32 {
+        let threshold = clamp_vad_threshold(value);
+        self.vad_threshold.store(threshold.to_bits(), Ordering::SeqCst);
+        log::info!("说话阈值: {:.3}", threshold);
+        threshold
+    }
+    
+    /// 当前说话阈值（线性 RMS）
+    pub fn get_vad_threshold(&self) -> f32 {
+        f32::from_bits(self.vad_threshold.load(Ordering::SeqCst))
+    }
+    
+    /// 启用或关闭跨玩家响度均衡（关闭时清空已记录的电平）
+    pub async fn set_loudness_normalization(&self, enabled: bool) {
+        self.loudness_normalization.store(enabled, Ordering::SeqCst);
+        if !enabled {
+            self.loudness_normalizer.write().await.reset();
+        }
+        log::info!("跨玩家响度均衡: {}", if enabled { "开启" } else { "关闭" });
+    }
+    
+    /// 是否启用跨玩家响度均衡
+    pub fn is_loudness_normalization_enabled(&self) -> bool {
+        self.loudness_normalization.load(Ordering::SeqCst)
+    }
+    
+    /// 记录其他玩家上报的输入电平
+    /// 
+    /// # 参数
+    /// * `player_id` - 玩家ID
+    /// * `level` - 量化后的电平等级
+    /// 
+    /// # 返回
+    /// * `Some(gains)` - 建议增益发生变化，需要下发给前端
+    /// * `None` - 未启用响度均衡或增益无明显变化
+    pub async fn record_peer_input_level(&self, player_id: &str, level: u8) -> Option<HashMap<String, f32>> {
+        if !self.is_loudness_normalization_enabled() {
+            return None;
+        }
+        let mut normalizer = self.loudness_normalizer.write().await;
+        normalizer.record(player_id, level);
+        normalizer.take_changed_gains()
+    }
+    
+    async fn apply_local_speaking_update(&self, player_id: &str, update: SpeakingUpdate) {
+        let is_speaking = match update {
+            SpeakingUpdate::Started => true,
+            SpeakingUpdate::Stopped => false,
+            _ => return,
+        };
+        self.set_player_speaking(player_id, is_speaking).await;
+        self.push_signaling(SignalingMessage::SpeakingState {
+            player_id: player_id.to_string(),
+            is_speaking,
+        })
+        .await;
+    }
+    
+    /// 将消息加入信令队列，超出上限时丢弃最老的消息
+    async fn push_signaling(&self, message: SignalingMessage) {
+        let mut queue = self.signaling_queue.lock().await;
+        if queue.len() >= MAX_SIGNALING_QUEUE_LEN {
+            queue.pop_front();
+        }
+        queue.push_back(message);
+    }
+    
     /// 获取信令队列中的所有消息
     /// 
     /// 此方法会清空队列并返回所有消息
@@ -522,6 +1580,13 @@ impl VoiceService {
         let mut muted_players = self.muted_players.write().await;
         muted_players.remove(player_id);
         
+        // 移除音量设置
+        self.player_volumes.write().await.remove(player_id);
+        
+        // 移除说话状态
+        self.speaking_players.write().await.remove(player_id);
+        self.loudness_normalizer.write().await.remove(player_id);
+        
         log::info!("玩家 {} 已移除", player_id);
         
         Ok(())
@@ -539,6 +1604,7 @@ impl VoiceService {
         
         // 关闭麦克风
         self.mic_enabled.store(false, Ordering::SeqCst);
+        self.guest_mode.store(false, Ordering::SeqCst);
         
         // 清除全局静音
         self.global_muted.store(false, Ordering::SeqCst);
@@ -551,6 +1617,16 @@ impl VoiceService {
         let mut muted_players = self.muted_players.write().await;
         muted_players.clear();
         
+        // 清除所有音量设置
+        self.player_volumes.write().await.clear();
+        
+        // 清除说话状态
+        self.speaking_players.write().await.clear();
+        self.local_speaking.write().await.reset();
+        self.speaking_activity.write().await.reset();
+        self.input_level_throttle.write().await.reset();
+        self.loudness_normalizer.write().await.reset();
+        
         // 清空信令队列
         let mut queue = self.signaling_queue.lock().await;
         queue.clear();
@@ -651,7 +1727,7 @@ impl VoiceService {
     
     /// 检查是否应该播放指定玩家的音频
     /// 
-    /// 考虑全局静音和单个玩家静音状态
+    /// 考虑全局静音、单个玩家静音状态以及玩家音量（音量为 0 等价于静音）
     /// 
     /// # 参数
     /// * `player_id` - 玩家唯一标识符
@@ -669,6 +1745,11 @@ impl VoiceService {
             return false;
         }
         
+        // 音量为 0 等价于静音
+        if self.get_player_volume(player_id).await <= MIN_PLAYER_VOLUME {
+            return false;
+        }
+        
         true
     }
     
@@ -688,8 +1769,7 @@ impl VoiceService {
             timestamp: Utc::now().timestamp(),
         };
         
-        let mut queue = self.signaling_queue.lock().await;
-        queue.push(message);
+        self.push_signaling(message).await;
         
         Ok(())
     }
@@ -731,6 +1811,24 @@ impl Default for VoiceService {
 mod tests {
     use super::*;
     
+    /// 测试用的模拟设备列表（测试环境不一定有真实音频设备）
+    fn mock_devices() -> Vec<AudioDevice> {
+        vec![
+            AudioDevice {
+                id: "default_mic".to_string(),
+                name: "默认麦克风".to_string(),
+                device_type: DeviceType::Microphone,
+                is_default: true,
+            },
+            AudioDevice {
+                id: "default_speaker".to_string(),
+                name: "默认扬声器".to_string(),
+                device_type: DeviceType::Speaker,
+                is_default: true,
+            },
+        ]
+    }
+    
     #[tokio::test]
     async fn test_voice_service_creation() {
         let service = VoiceService::new();
@@ -740,18 +1838,43 @@ mod tests {
     
     #[tokio::test]
     async fn test_initialize() {
+        // 真实枚举在无音频设备的环境下也不应失败
         let service = VoiceService::new();
         let result = service.initialize().await;
         assert!(result.is_ok());
         
+        let service = VoiceService::new();
+        service.initialize_with_devices(mock_devices()).await;
         let devices = service.get_audio_devices().await;
         assert!(!devices.is_empty());
+        assert_eq!(service.get_selected_microphone().await, Some("default_mic".to_string()));
+        assert_eq!(service.get_selected_speaker().await, Some("default_speaker".to_string()));
+    }
+    
+    #[test]
+    fn test_push_unique_device() {
+        let mut devices = Vec::new();
+        let default_name = Some("麦克风 (USB)".to_string());
+        
+        push_unique_device(&mut devices, "麦克风 (USB)".to_string(), DeviceType::Microphone, &default_name);
+        push_unique_device(&mut devices, "麦克风 (Realtek)".to_string(), DeviceType::Microphone, &default_name);
+        // 同类型同名设备只保留一个
+        push_unique_device(&mut devices, "麦克风 (USB)".to_string(), DeviceType::Microphone, &default_name);
+        // 不同类型允许同名
+        push_unique_device(&mut devices, "麦克风 (USB)".to_string(), DeviceType::Speaker, &None);
+        
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[0].id, "麦克风 (USB)");
+        assert!(devices[0].is_default);
+        assert!(!devices[1].is_default);
+        assert_eq!(devices[2].device_type, DeviceType::Speaker);
+        assert!(!devices[2].is_default);
     }
     
     #[tokio::test]
     async fn test_mic_toggle() {
         let service = VoiceService::new();
-        service.initialize().await.unwrap();
+        service.initialize_with_devices(mock_devices()).await;
         
         // 初始状态应该是关闭
         assert!(!service.is_mic_enabled());
@@ -770,7 +1893,7 @@ mod tests {
     #[tokio::test]
     async fn test_set_mic_enabled() {
         let service = VoiceService::new();
-        service.initialize().await.unwrap();
+        service.initialize_with_devices(mock_devices()).await;
         
         let result = service.set_mic_enabled(true).await;
         assert!(result.is_ok());
@@ -783,6 +1906,26 @@ mod tests {
         assert!(!service.is_mic_enabled());
     }
     
+    #[tokio::test]
+    async fn test_guest_mode_keeps_mic_off() {
+        let service = VoiceService::new();
+        service.initialize_with_devices(mock_devices()).await;
+        service.set_mic_enabled(true).await.unwrap();
+        
+        // 进入游客模式立即关麦，之后无法再开麦
+        service.set_guest_mode(true);
+        assert!(!service.is_mic_enabled());
+        assert!(service.set_mic_enabled(true).await.is_err());
+        assert!(service.toggle_mic().await.is_err());
+        assert!(!service.is_mic_enabled());
+        assert!(service.set_mic_enabled(false).await.is_ok());
+        
+        // 离开大厅后恢复为普通模式
+        service.cleanup().await.unwrap();
+        assert!(!service.is_guest_mode());
+        assert!(service.set_mic_enabled(true).await.unwrap());
+    }
+    
     #[tokio::test]
     async fn test_mute_player() {
         let service = VoiceService::new();
@@ -934,7 +2077,7 @@ mod tests {
     #[tokio::test]
     async fn test_cleanup() {
         let service = VoiceService::new();
-        service.initialize().await.unwrap();
+        service.initialize_with_devices(mock_devices()).await;
         
         // 设置一些状态
         service.set_mic_enabled(true).await.unwrap();
@@ -964,7 +2107,7 @@ mod tests {
     #[tokio::test]
     async fn test_select_microphone() {
         let service = VoiceService::new();
-        service.initialize().await.unwrap();
+        service.initialize_with_devices(mock_devices()).await;
         
         // 选择默认麦克风
         let result = service.select_microphone("default_mic").await;
@@ -978,10 +2121,70 @@ mod tests {
         assert!(result.is_err());
     }
     
+    #[tokio::test]
+    async fn test_unplugged_device_falls_back_to_default() {
+        let service = VoiceService::new();
+        let mut devices = mock_devices();
+        devices.push(AudioDevice {
+            id: "usb_headset".to_string(),
+            name: "USB 耳机".to_string(),
+            device_type: DeviceType::Microphone,
+            is_default: false,
+        });
+        service.initialize_with_devices(devices.clone()).await;
+        service.select_microphone("usb_headset").await.unwrap();
+        
+        // 列表未变化
+        assert_eq!(service.apply_device_list(devices).await, AudioDeviceRefresh::default());
+        
+        // 拔出 USB 耳机：回退到默认麦克风，扬声器不受影响
+        let refresh = service.apply_device_list(mock_devices()).await;
+        assert!(refresh.devices_changed);
+        assert_eq!(
+            refresh.fallbacks,
+            vec![DeviceFallback {
+                device_type: DeviceType::Microphone,
+                previous: "usb_headset".to_string(),
+                current: Some("default_mic".to_string()),
+            }]
+        );
+        assert_eq!(service.get_selected_microphone().await, Some("default_mic".to_string()));
+        assert_eq!(service.get_selected_speaker().await, Some("default_speaker".to_string()));
+        
+        // 所有麦克风都被拔出
+        let speakers_only: Vec<AudioDevice> = mock_devices().into_iter().filter(|d| d.device_type == DeviceType::Speaker).collect();
+        let refresh = service.apply_device_list(speakers_only).await;
+        assert_eq!(refresh.fallbacks[0].current, None);
+        assert_eq!(service.get_selected_microphone().await, None);
+    }
+    
+    #[tokio::test]
+    async fn test_reinitialize_keeps_selected_device() {
+        let service = VoiceService::new();
+        let mut devices = mock_devices();
+        devices.push(AudioDevice {
+            id: "usb_headset".to_string(),
+            name: "USB 耳机".to_string(),
+            device_type: DeviceType::Microphone,
+            is_default: false,
+        });
+        service.initialize_with_devices(devices.clone()).await;
+        service.select_microphone("usb_headset").await.unwrap();
+        
+        // 进入大厅时再次初始化，保留已选择的设备
+        service.initialize_with_devices(devices).await;
+        assert_eq!(service.get_selected_microphone().await, Some("usb_headset".to_string()));
+        assert_eq!(service.get_selected_speaker().await, Some("default_speaker".to_string()));
+        
+        // 已选设备不在新列表中时回退到系统默认
+        service.initialize_with_devices(mock_devices()).await;
+        assert_eq!(service.get_selected_microphone().await, Some("default_mic".to_string()));
+    }
+    
     #[tokio::test]
     async fn test_select_speaker() {
         let service = VoiceService::new();
-        service.initialize().await.unwrap();
+        service.initialize_with_devices(mock_devices()).await;
         
         // 选择默认扬声器
         let result = service.select_speaker("default_speaker").await;
@@ -1039,6 +2242,28 @@ mod tests {
         }
     }
     
+    #[tokio::test]
+    async fn test_signaling_queue_is_bounded() {
+        let service = VoiceService::new();
+        
+        for _ in 0..MAX_SIGNALING_QUEUE_LEN + 10 {
+            service.send_heartbeat("player_123").await.unwrap();
+        }
+        service
+            .broadcast_status(PlayerStatus {
+                player_id: "player_123".to_string(),
+                mic_enabled: true,
+                timestamp: Utc::now(),
+            })
+            .await
+            .unwrap();
+        
+        let messages = service.get_signaling_messages().await;
+        assert_eq!(messages.len(), MAX_SIGNALING_QUEUE_LEN);
+        // 丢弃的是最老的消息，最新的状态广播仍在队尾
+        assert!(matches!(messages.last(), Some(SignalingMessage::StatusUpdate { mic_enabled: true, .. })));
+    }
+    
     #[tokio::test]
     async fn test_check_heartbeat_timeout() {
         let service = VoiceService::new();
@@ -1083,4 +2308,324 @@ mod tests {
         assert_eq!(muted.len(), 2);
         assert!(!muted.contains(&"player_2".to_string()));
     }
+    
+    #[test]
+    fn test_mic_mode_parse() {
+        assert_eq!("toggle".parse::<MicMode>().unwrap(), MicMode::Toggle);
+        assert_eq!("push_to_talk".parse::<MicMode>().unwrap(), MicMode::PushToTalk);
+        assert_eq!("push-to-talk".parse::<MicMode>().unwrap(), MicMode::PushToTalk);
+        assert_eq!("VoiceActivated".parse::<MicMode>().unwrap(), MicMode::VoiceActivated);
+        assert!("unknown".parse::<MicMode>().is_err());
+        
+        // 序列化格式与 as_str 一致
+        for mode in [MicMode::Toggle, MicMode::PushToTalk, MicMode::VoiceActivated] {
+            let json = serde_json::to_string(&mode).unwrap();
+            assert_eq!(json, format!("\"{}\"", mode.as_str()));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_set_mic_mode() {
+        let service = VoiceService::new();
+        service.initialize_with_devices(mock_devices()).await;
+        assert_eq!(service.get_mic_mode().await, MicMode::Toggle);
+        
+        service.set_mic_enabled(true).await.unwrap();
+        
+        // 切换到按键说话模式时关闭麦克风，并告知调用方需要广播
+        assert!(service.set_mic_mode(MicMode::PushToTalk).await);
+        assert_eq!(service.get_mic_mode().await, MicMode::PushToTalk);
+        assert!(!service.is_mic_enabled());
+        // 麦克风本来就关着时无需广播
+        assert!(!service.set_mic_mode(MicMode::PushToTalk).await);
+        
+        // 其它模式不改变麦克风状态
+        service.set_mic_enabled(true).await.unwrap();
+        assert!(!service.set_mic_mode(MicMode::VoiceActivated).await);
+        assert!(service.is_mic_enabled());
+    }
+    
+    #[tokio::test]
+    async fn test_player_volume_default_and_set() {
+        let service = VoiceService::new();
+        assert_eq!(service.get_player_volume("player_123").await, DEFAULT_PLAYER_VOLUME);
+        
+        let applied = service.set_player_volume("player_123", 1.5).await.unwrap();
+        assert_eq!(applied, 1.5);
+        assert_eq!(service.get_player_volume("player_123").await, 1.5);
+        assert!(service.should_play_audio("player_123").await);
+    }
+    
+    #[tokio::test]
+    async fn test_player_volume_clamp() {
+        let service = VoiceService::new();
+        
+        // 负数截断为 0
+        assert_eq!(service.set_player_volume("player_123", -0.5).await.unwrap(), MIN_PLAYER_VOLUME);
+        // 超过 200% 截断为 200%
+        assert_eq!(service.set_player_volume("player_123", 3.0).await.unwrap(), MAX_PLAYER_VOLUME);
+        assert_eq!(service.get_player_volume("player_123").await, MAX_PLAYER_VOLUME);
+        // 非法数值不生效
+        assert!(service.set_player_volume("player_123", f32::NAN).await.is_err());
+        assert_eq!(service.get_player_volume("player_123").await, MAX_PLAYER_VOLUME);
+    }
+    
+    #[tokio::test]
+    async fn test_zero_volume_is_muted() {
+        let service = VoiceService::new();
+        
+        service.set_player_volume("player_123", 0.0).await.unwrap();
+        assert!(!service.should_play_audio("player_123").await);
+        
+        service.set_player_volume("player_123", 0.1).await.unwrap();
+        assert!(service.should_play_audio("player_123").await);
+        
+        // 移除玩家后音量恢复默认
+        service.remove_player("player_123").await.unwrap();
+        assert_eq!(service.get_player_volume("player_123").await, DEFAULT_PLAYER_VOLUME);
+    }
+    
+    #[test]
+    fn test_speaking_activity_accumulates_duration() {
+        let mut tracker = SpeakingActivityTracker::default();
+        let t0 = Instant::now();
+        
+        tracker.start("p1", t0);
+        // 已在说话时重复开始不重复计次
+        tracker.start("p1", t0 + Duration::from_secs(1));
+        assert_eq!(tracker.activity("p1", t0 + Duration::from_secs(2)).speaking_seconds, 2);
+        tracker.stop("p1", t0 + Duration::from_secs(3));
+        
+        tracker.start("p1", t0 + Duration::from_secs(10));
+        tracker.stop("p1", t0 + Duration::from_secs(15));
+        // 未在说话时停止不影响累计
+        tracker.stop("p1", t0 + Duration::from_secs(20));
+        
+        let activity = tracker.activity("p1", t0 + Duration::from_secs(30));
+        assert_eq!(activity, SpeakingActivity { speaking_seconds: 8, message_count: 2 });
+        assert_eq!(tracker.activity("p2", t0), SpeakingActivity::default());
+        
+        tracker.reset();
+        assert!(tracker.all(t0).is_empty());
+    }
+    
+    #[test]
+    fn test_speaking_debouncer_release_delay() {
+        let mut debouncer = SpeakingDebouncer::new(Duration::from_millis(300));
+        let t0 = Instant::now();
+        
+        assert_eq!(debouncer.update(true, t0), SpeakingUpdate::Started);
+        assert_eq!(debouncer.update(true, t0 + Duration::from_millis(50)), SpeakingUpdate::Unchanged);
+        
+        // 静音未满 300ms 不释放
+        assert_eq!(debouncer.update(false, t0 + Duration::from_millis(100)), SpeakingUpdate::ReleasePending);
+        assert_eq!(debouncer.update(false, t0 + Duration::from_millis(300)), SpeakingUpdate::Unchanged);
+        assert!(debouncer.is_speaking());
+        
+        // 静音满 300ms 后释放
+        assert_eq!(debouncer.poll(t0 + Duration::from_millis(400)), SpeakingUpdate::Stopped);
+        assert!(!debouncer.is_speaking());
+        assert_eq!(debouncer.poll(t0 + Duration::from_millis(500)), SpeakingUpdate::Unchanged);
+    }
+    
+    #[test]
+    fn test_speaking_debouncer_cancel_release() {
+        let mut debouncer = SpeakingDebouncer::new(Duration::from_millis(300));
+        let t0 = Instant::now();
+        
+        debouncer.update(true, t0);
+        assert_eq!(debouncer.update(false, t0 + Duration::from_millis(100)), SpeakingUpdate::ReleasePending);
+        // 释放窗口内再次出声，取消释放
+        assert_eq!(debouncer.update(true, t0 + Duration::from_millis(200)), SpeakingUpdate::Unchanged);
+        assert_eq!(debouncer.poll(t0 + Duration::from_millis(600)), SpeakingUpdate::Unchanged);
+        assert!(debouncer.is_speaking());
+    }
+    
+    #[test]
+    fn test_mic_level_from_samples() {
+        assert_eq!(rms(std::iter::empty()), 0.0);
+        assert!((rms([0.5, -0.5, 0.5, -0.5]) - 0.5).abs() < 1e-6);
+        assert!((rms([1.0, -1.0]) - 1.0).abs() < 1e-6);
+        
+        assert_eq!(mic_level_from_rms(0.0), 0.0);
+        assert_eq!(mic_level_from_rms(f32::NAN), 0.0);
+        // 低于 -60dBFS 视为静音，满幅为 1
+        assert_eq!(mic_level_from_rms(0.0005), 0.0);
+        assert!((mic_level_from_rms(1.0) - 1.0).abs() < 1e-6);
+        assert_eq!(mic_level_from_rms(2.0), 1.0);
+        // 正常说话（约 -26dBFS）落在电平条中段
+        let speech = mic_level_from_rms(0.05);
+        assert!(speech > 0.4 && speech < 0.7, "{}", speech);
+    }
+    
+    #[test]
+    fn test_quantize_input_level() {
+        assert_eq!(quantize_input_level(0.0), 0);
+        assert_eq!(quantize_input_level(SPEAKING_LEVEL_THRESHOLD / 2.0), 0);
+        assert_eq!(quantize_input_level(f32::NAN), 0);
+        assert_eq!(quantize_input_level(SPEAKING_LEVEL_THRESHOLD), 2);
+        assert_eq!(quantize_input_level(0.25), 4);
+        assert_eq!(quantize_input_level(1.0), INPUT_LEVEL_STEPS);
+        assert_eq!(quantize_input_level(3.0), INPUT_LEVEL_STEPS);
+        
+        // 电平越大等级不减
+        let levels: Vec<u8> = (0..=100).map(|i| quantize_input_level(i as f32 / 100.0)).collect();
+        assert!(levels.windows(2).all(|w| w[0] <= w[1]));
+    }
+    
+    #[test]
+    fn test_input_level_throttle() {
+        let mut throttle = InputLevelThrottle::new(Duration::from_millis(200));
+        let t0 = Instant::now();
+        
+        // 初始静音不广播，首次出声立即广播
+        assert!(!throttle.should_send(0, t0));
+        assert!(throttle.should_send(3, t0));
+        // 等级不变不广播
+        assert!(!throttle.should_send(3, t0 + Duration::from_millis(500)));
+        // 间隔内的变化被抑制
+        assert!(!throttle.should_send(5, t0 + Duration::from_millis(100)));
+        assert!(throttle.should_send(5, t0 + Duration::from_millis(200)));
+        // 回落到静音也需广播一次
+        assert!(throttle.should_send(0, t0 + Duration::from_millis(400)));
+        assert!(!throttle.should_send(0, t0 + Duration::from_millis(800)));
+        
+        // 高频上报时每秒最多 5 条
+        let mut throttle = InputLevelThrottle::new(Duration::from_millis(200));
+        let sent = (0..100)
+            .filter(|i| throttle.should_send((i % 8 + 1) as u8, t0 + Duration::from_millis(i * 10)))
+            .count();
+        assert_eq!(sent, 5);
+    }
+    
+    #[tokio::test]
+    async fn test_local_audio_level_updates_speaking_players() {
+        let service = VoiceService::new();
+        service.initialize_with_devices(mock_devices()).await;
+        
+        // 麦克风关闭时不判定为说话
+        assert_eq!(service.update_local_audio_level("me", 0.5).await, SpeakingUpdate::Unchanged);
+        
+        service.set_mic_enabled(true).await.unwrap();
+        assert_eq!(service.update_local_audio_level("me", 0.5).await, SpeakingUpdate::Started);
+        assert!(service.is_player_speaking("me").await);
+        
+        let messages = service.get_signaling_messages().await;
+        assert!(messages.iter().any(|m| matches!(m, SignalingMessage::SpeakingState { is_speaking: true, .. })));
+        
+        assert_eq!(service.update_local_audio_level("me", 0.0).await, SpeakingUpdate::ReleasePending);
+        assert!(service.is_player_speaking("me").await);
+    }
+    
+    #[tokio::test]
+    async fn test_vad_threshold_and_calibration() {
+        let service = VoiceService::new();
+        service.initialize_with_devices(mock_devices()).await;
+        service.set_mic_enabled(true).await.unwrap();
+        assert_eq!(service.get_vad_threshold(), SPEAKING_LEVEL_THRESHOLD);
+        
+        // 提高阈值后，原本会触发的电平不再判定为说话
+        assert_eq!(service.set_vad_threshold(0.04), 0.04);
+        assert_eq!(service.update_local_audio_level("me", 0.03).await, SpeakingUpdate::Unchanged);
+        assert_eq!(service.update_local_audio_level("me", 0.05).await, SpeakingUpdate::Started);
+        assert_eq!(service.set_vad_threshold(2.0), MAX_VAD_THRESHOLD);
+        assert_eq!(service.set_vad_threshold(f32::NAN), SPEAKING_LEVEL_THRESHOLD);
+        
+        // 底噪取 95 分位，偶发的一次大声不影响推荐值
+        let mut samples = vec![0.01; 60];
+        samples[10] = 0.3;
+        let calibration = recommend_vad_threshold(&samples);
+        assert_eq!(calibration.noise_level, 0.01);
+        assert!((calibration.recommended - 0.025).abs() < 1e-6);
+        // 安静房间的推荐值仍低于正常说话的 RMS（约 0.05）
+        assert!(recommend_vad_threshold(&[0.003; 30]).recommended < 0.05);
+        assert_eq!(recommend_vad_threshold(&[]).recommended, SPEAKING_LEVEL_THRESHOLD);
+        assert_eq!(recommend_vad_threshold(&[0.15]).recommended, MAX_VAD_THRESHOLD);
+    }
+    
+    #[tokio::test]
+    async fn test_remote_speaking_state() {
+        let service = VoiceService::new();
+        
+        service.handle_signaling(SignalingMessage::SpeakingState {
+            player_id: "player_123".to_string(),
+            is_speaking: true,
+        }).await.unwrap();
+        assert_eq!(service.get_speaking_players().await, vec!["player_123".to_string()]);
+        
+        service.remove_player("player_123").await.unwrap();
+        assert!(!service.is_player_speaking("player_123").await);
+    }
+    
+    #[test]
+    fn test_normalization_gain() {
+        assert_eq!(normalization_gain(0.1, 0.1), 1.0);
+        assert!((normalization_gain(0.2, 0.1) - 0.5).abs() < 1e-6);
+        assert!((normalization_gain(0.1, 0.15) - 1.5).abs() < 1e-6);
+        // 超出范围时截断
+        assert_eq!(normalization_gain(0.01, 1.0), MAX_NORMALIZATION_GAIN);
+        assert_eq!(normalization_gain(1.0, 0.01), MIN_NORMALIZATION_GAIN);
+        // 无效平均值不调整
+        assert_eq!(normalization_gain(0.0, 0.1), DEFAULT_PLAYER_VOLUME);
+        assert_eq!(normalization_gain(f32::NAN, 0.1), DEFAULT_PLAYER_VOLUME);
+    }
+    
+    #[test]
+    fn test_loudness_normalizer_equalizes_players() {
+        let mut normalizer = LoudnessNormalizer::default();
+        for _ in 0..LOUDNESS_MIN_SAMPLES {
+            normalizer.record("loud", 6);
+            normalizer.record("quiet", 3);
+            // 静音样本不计入平均
+            normalizer.record("quiet", 0);
+        }
+        
+        let gains = normalizer.gains();
+        let loud = gains["loud"];
+        let quiet = gains["quiet"];
+        assert!(loud < 1.0 && quiet > 1.0);
+        // 调整后两人的振幅一致
+        let loud_out = input_level_amplitude(6) * loud;
+        let quiet_out = input_level_amplitude(3) * quiet;
+        assert!((loud_out - quiet_out).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_loudness_normalizer_needs_calibration() {
+        let mut normalizer = LoudnessNormalizer::default();
+        for _ in 0..LOUDNESS_MIN_SAMPLES {
+            normalizer.record("a", 6);
+        }
+        normalizer.record("b", 2);
+        
+        // 只有一名玩家完成校准时不做调整
+        assert_eq!(normalizer.gains(), HashMap::from([("a".to_string(), 1.0)]));
+        assert!(normalizer.take_changed_gains().is_some());
+        assert!(normalizer.take_changed_gains().is_none());
+        
+        for _ in 0..LOUDNESS_MIN_SAMPLES {
+            normalizer.record("b", 2);
+        }
+        let gains = normalizer.take_changed_gains().unwrap();
+        assert_eq!(gains.len(), 2);
+        assert!(gains["b"] > gains["a"]);
+        // 电平稳定时不重复下发
+        normalizer.record("a", 6);
+        assert!(normalizer.take_changed_gains().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_peer_input_level_requires_normalization_enabled() {
+        let service = VoiceService::new();
+        for _ in 0..LOUDNESS_MIN_SAMPLES {
+            assert!(service.record_peer_input_level("a", 6).await.is_none());
+        }
+        
+        service.set_loudness_normalization(true).await;
+        assert!(service.record_peer_input_level("a", 6).await.is_none());
+        for _ in 1..LOUDNESS_MIN_SAMPLES {
+            service.record_peer_input_level("a", 6).await;
+        }
+        assert!(service.record_peer_input_level("b", 6).await.is_none());
+    }
 }
@@ -0,0 +1,506 @@
+// 后端管理的语音连接（把 WebRTC 音频管道分阶段迁移到 Rust）
+//
+// 第一阶段只让两端后端在 Rust 内完成 SDP offer/answer 协商，不依赖前端：
+// 1. 发起方 `establish_voice_connection` 生成 offer，经 P2P 信令（`VoiceOffer`）单播给对方
+// 2. 对方后端校验 offer 中的 Opus 音频描述，生成 answer（`VoiceAnswer`）回复，双方会话进入 Negotiated
+// 3. 任一方 `close_voice_connection` 发送 `VoiceClose`，双方移除会话
+// 双方同时发起时按玩家 ID 仲裁：ID 较小的一方保留 offer，另一方转为应答。
+// 音频采集、Opus 编码与 RTP 传输仍由前端 WebRTC 负责，后续阶段接入 webrtc crate 后挂在已协商的会话上。
+// 每次会话状态变化推送 `voice-connection-state` 事件。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::{Emitter, State};
+
+use crate::modules::error::CommandError;
+use crate::modules::p2p_signaling::P2PMessage;
+use crate::modules::tauri_commands::AppState;
+
+/// Opus 在 SDP 中使用的动态负载类型（与浏览器 WebRTC 默认值一致）
+const OPUS_PAYLOAD_TYPE: u8 = 111;
+/// 接受的远端 SDP 最大长度
+const MAX_SDP_LEN: usize = 8 * 1024;
+
+/// 会话状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VoiceSessionState {
+    /// 已发出 offer，等待 answer
+    Offering,
+    /// offer/answer 已完成
+    Negotiated,
+    /// 会话已关闭
+    Closed,
+}
+
+/// 本机在会话中的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VoiceSessionRole {
+    /// 发起 offer
+    Offerer,
+    /// 回复 answer
+    Answerer,
+}
+
+/// 与一名玩家的语音会话
+#[derive(Debug, Clone)]
+pub struct VoiceSession {
+    /// 会话 ID（写入 SDP 的 o= 行，offer 与 answer 共用）
+    pub session_id: String,
+    /// 对方虚拟 IP
+    pub peer_ip: String,
+    pub role: VoiceSessionRole,
+    pub state: VoiceSessionState,
+    /// 本机 SDP
+    pub local_sdp: String,
+    /// 对方 SDP
+    pub remote_sdp: Option<String>,
+    /// 协商出的 Opus 负载类型
+    pub payload_type: u8,
+}
+
+/// 语音会话错误类型
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum VoiceSessionError {
+    #[error("已存在与 {0} 的语音连接")]
+    AlreadyExists(String),
+
+    #[error("没有与 {0} 的语音连接")]
+    NotFound(String),
+
+    #[error("会话 ID 不匹配（期望 {expected}，收到 {actual}）")]
+    SessionMismatch { expected: String, actual: String },
+
+    #[error("双方同时发起连接，保留本机的 offer")]
+    Glare,
+
+    #[error("无效的 SDP: {0}")]
+    InvalidSdp(String),
+
+    #[error("对方不支持 Opus 音频")]
+    UnsupportedCodec,
+}
+
+/// 生成 SDP（只描述一路 Opus 音频；媒体端口 9 表示由后续阶段的 ICE 确定）
+fn build_sdp(session_id: &str, local_ip: &str, payload_type: u8) -> String {
+    [
+        "v=0".to_string(),
+        format!("o=mctier {} 1 IN IP4 {}", session_id, local_ip),
+        "s=MCTier".to_string(),
+        format!("c=IN IP4 {}", local_ip),
+        "t=0 0".to_string(),
+        format!("m=audio 9 RTP/AVP {}", payload_type),
+        format!("a=rtpmap:{} opus/48000/2", payload_type),
+        format!("a=fmtp:{} minptime=10;useinbandfec=1", payload_type),
+        "a=sendrecv".to_string(),
+        String::new(),
+    ]
+    .join("\r\n")
+}
+
+/// 解析远端 SDP，返回（会话 ID，Opus 负载类型）
+fn parse_sdp(sdp: &str) -> Result<(String, u8), VoiceSessionError> {
+    if sdp.len() > MAX_SDP_LEN {
+        return Err(VoiceSessionError::InvalidSdp(format!("超过 {} 字节", MAX_SDP_LEN)));
+    }
+    let mut session_id = None;
+    let mut audio_formats: Option<Vec<&str>> = None;
+    let mut opus_types = Vec::new();
+    for line in sdp.lines().map(str::trim) {
+        if let Some(origin) = line.strip_prefix("o=") {
+            session_id = origin.split_whitespace().nth(1).map(str::to_string);
+        } else if let Some(media) = line.strip_prefix("m=audio ") {
+            // m=audio <端口> <协议> <负载类型...>
+            audio_formats = Some(media.split_whitespace().skip(2).collect());
+        } else if let Some(rtpmap) = line.strip_prefix("a=rtpmap:") {
+            if let Some((payload_type, codec)) = rtpmap.split_once(' ') {
+                if codec.to_ascii_lowercase().starts_with("opus/48000") {
+                    opus_types.push(payload_type);
+                }
+            }
+        }
+    }
+
+    let session_id = session_id.ok_or_else(|| VoiceSessionError::InvalidSdp("缺少 o= 行".to_string()))?;
+    let audio_formats = audio_formats.ok_or_else(|| VoiceSessionError::InvalidSdp("缺少音频媒体描述".to_string()))?;
+    let payload_type = opus_types
+        .into_iter()
+        .find(|pt| audio_formats.contains(pt))
+        .and_then(|pt| pt.parse::<u8>().ok())
+        .ok_or(VoiceSessionError::UnsupportedCodec)?;
+    Ok((session_id, payload_type))
+}
+
+/// 按对方玩家 ID 管理的语音会话
+#[derive(Debug, Default)]
+pub struct VoiceSessionManager {
+    sessions: HashMap<String, VoiceSession>,
+}
+
+impl VoiceSessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取与指定玩家的会话
+    pub fn get(&self, peer_id: &str) -> Option<&VoiceSession> {
+        self.sessions.get(peer_id)
+    }
+
+    /// 向对方发起连接，返回 offer SDP
+    ///
+    /// # 参数
+    /// * `peer_id` - 对方玩家 ID
+    /// * `peer_ip` - 对方虚拟 IP
+    /// * `local_ip` - 本机虚拟 IP
+    /// * `session_id` - 新会话 ID
+    pub fn create_offer(
+        &mut self,
+        peer_id: &str,
+        peer_ip: &str,
+        local_ip: &str,
+        session_id: &str,
+    ) -> Result<String, VoiceSessionError> {
+        if self.sessions.contains_key(peer_id) {
+            return Err(VoiceSessionError::AlreadyExists(peer_id.to_string()));
+        }
+        let local_sdp = build_sdp(session_id, local_ip, OPUS_PAYLOAD_TYPE);
+        self.sessions.insert(
+            peer_id.to_string(),
+            VoiceSession {
+                session_id: session_id.to_string(),
+                peer_ip: peer_ip.to_string(),
+                role: VoiceSessionRole::Offerer,
+                state: VoiceSessionState::Offering,
+                local_sdp: local_sdp.clone(),
+                remote_sdp: None,
+                payload_type: OPUS_PAYLOAD_TYPE,
+            },
+        );
+        Ok(local_sdp)
+    }
+
+    /// 接受对方的 offer，返回 answer SDP
+    ///
+    /// 已有会话时以新的 offer 为准（对方重连）；双方同时发起时玩家 ID 较小的一方保留自己的 offer。
+    ///
+    /// # 参数
+    /// * `local_player_id` - 本机玩家 ID
+    /// * `peer_id` - 对方玩家 ID
+    /// * `peer_ip` - 对方虚拟 IP
+    /// * `local_ip` - 本机虚拟 IP
+    /// * `offer_sdp` - 对方的 offer
+    pub fn accept_offer(
+        &mut self,
+        local_player_id: &str,
+        peer_id: &str,
+        peer_ip: &str,
+        local_ip: &str,
+        offer_sdp: &str,
+    ) -> Result<String, VoiceSessionError> {
+        let (session_id, payload_type) = parse_sdp(offer_sdp)?;
+        if self
+            .sessions
+            .get(peer_id)
+            .is_some_and(|s| s.state == VoiceSessionState::Offering && local_player_id < peer_id)
+        {
+            return Err(VoiceSessionError::Glare);
+        }
+        let local_sdp = build_sdp(&session_id, local_ip, payload_type);
+        self.sessions.insert(
+            peer_id.to_string(),
+            VoiceSession {
+                session_id,
+                peer_ip: peer_ip.to_string(),
+                role: VoiceSessionRole::Answerer,
+                state: VoiceSessionState::Negotiated,
+                local_sdp: local_sdp.clone(),
+                remote_sdp: Some(offer_sdp.to_string()),
+                payload_type,
+            },
+        );
+        Ok(local_sdp)
+    }
+
+    /// 接受对方对本机 offer 的 answer
+    pub fn accept_answer(&mut self, peer_id: &str, answer_sdp: &str) -> Result<(), VoiceSessionError> {
+        let (session_id, payload_type) = parse_sdp(answer_sdp)?;
+        let session = self
+            .sessions
+            .get_mut(peer_id)
+            .filter(|s| s.role == VoiceSessionRole::Offerer)
+            .ok_or_else(|| VoiceSessionError::NotFound(peer_id.to_string()))?;
+        if session.session_id != session_id {
+            return Err(VoiceSessionError::SessionMismatch {
+                expected: session.session_id.clone(),
+                actual: session_id,
+            });
+        }
+        session.state = VoiceSessionState::Negotiated;
+        session.remote_sdp = Some(answer_sdp.to_string());
+        session.payload_type = payload_type;
+        Ok(())
+    }
+
+    /// 关闭与指定玩家的会话；指定会话 ID 时只关闭匹配的会话（忽略过期的关闭消息）
+    pub fn close(&mut self, peer_id: &str, session_id: Option<&str>) -> Option<VoiceSession> {
+        if session_id.is_some_and(|id| self.sessions.get(peer_id).is_some_and(|s| s.session_id != id)) {
+            return None;
+        }
+        self.sessions.remove(peer_id).map(|mut session| {
+            session.state = VoiceSessionState::Closed;
+            session
+        })
+    }
+
+    /// 关闭全部会话（离开大厅时调用）
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+}
+
+/// 全局语音会话表
+pub fn voice_sessions() -> &'static Mutex<VoiceSessionManager> {
+    static SESSIONS: OnceLock<Mutex<VoiceSessionManager>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(VoiceSessionManager::new()))
+}
+
+/// 推送会话状态变化
+fn emit_state(app: &tauri::AppHandle, peer_id: &str, state: VoiceSessionState, role: Option<VoiceSessionRole>) {
+    let _ = app.emit("voice-connection-state", serde_json::json!({
+        "peerId": peer_id,
+        "state": state,
+        "role": role,
+    }));
+}
+
+/// 处理对方发来的 offer：生成 answer 并单播回复
+///
+/// 由 P2P 信令接收循环在后台任务中调用。
+pub async fn handle_remote_offer(app: tauri::AppHandle, from: String, peer_ip: String, sdp: String) {
+    use tauri::Manager;
+
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let p2p_signaling = state.get_p2p_signaling();
+    let p2p_svc = p2p_signaling.lock().await;
+    let (Some(local_id), Some(local_ip)) = (p2p_svc.get_local_player_id().await, p2p_svc.local_virtual_ip().await) else {
+        return;
+    };
+
+    let answer = voice_sessions().lock().accept_offer(&local_id, &from, &peer_ip, &local_ip, &sdp);
+    let answer = match answer {
+        Ok(answer) => answer,
+        Err(VoiceSessionError::Glare) => {
+            log::info!("🎙️ [VoiceSession] 与 {} 同时发起连接，等待对方应答本机的 offer", from);
+            return;
+        }
+        Err(e) => {
+            log::warn!("⚠️ [VoiceSession] 拒绝 {} 的 offer: {}", from, e);
+            return;
+        }
+    };
+    let message = P2PMessage::VoiceAnswer {
+        from: local_id,
+        to: from.clone(),
+        sdp: answer,
+    };
+    if let Err(e) = p2p_svc.send_to_player(&from, message).await {
+        log::warn!("⚠️ [VoiceSession] 回复 answer 给 {} 失败: {}", from, e);
+        voice_sessions().lock().close(&from, None);
+        return;
+    }
+    log::info!("🎙️ [VoiceSession] 已应答 {} 的 offer", from);
+    emit_state(&app, &from, VoiceSessionState::Negotiated, Some(VoiceSessionRole::Answerer));
+}
+
+/// 处理对方对本机 offer 的 answer
+pub fn handle_remote_answer(app: &tauri::AppHandle, from: &str, sdp: &str) {
+    match voice_sessions().lock().accept_answer(from, sdp) {
+        Ok(()) => {
+            log::info!("🎙️ [VoiceSession] 与 {} 的 offer/answer 已完成", from);
+            emit_state(app, from, VoiceSessionState::Negotiated, Some(VoiceSessionRole::Offerer));
+        }
+        Err(e) => log::warn!("⚠️ [VoiceSession] 忽略 {} 的 answer: {}", from, e),
+    }
+}
+
+/// 处理对方关闭连接，或对方已离开大厅
+pub fn handle_remote_close(app: &tauri::AppHandle, from: &str, session_id: Option<&str>) {
+    if voice_sessions().lock().close(from, session_id).is_some() {
+        log::info!("🎙️ [VoiceSession] {} 已关闭语音连接", from);
+        emit_state(app, from, VoiceSessionState::Closed, None);
+    }
+}
+
+/// 与指定玩家建立后端语音连接（当前阶段完成 offer/answer 协商）
+///
+/// 协商结果通过 `voice-connection-state` 事件推送。
+///
+/// # 参数
+/// * `peer_id` - 对方玩家 ID
+/// * `peer_ip` - 对方虚拟 IP
+///
+/// # 返回
+/// * `Ok(())` - offer 已发出
+/// * `Err(CommandError)` - 未进入大厅、已有连接或发送失败
+#[tauri::command]
+pub async fn establish_voice_connection(
+    peer_id: String,
+    peer_ip: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    log::info!("🎙️ [VoiceSession] 建立与 {} ({}) 的语音连接", peer_id, peer_ip);
+
+    let p2p_signaling = state.get_p2p_signaling();
+    let p2p_svc = p2p_signaling.lock().await;
+    let (Some(local_id), Some(local_ip)) = (p2p_svc.get_local_player_id().await, p2p_svc.local_virtual_ip().await) else {
+        return Err(CommandError::validation("未进入大厅"));
+    };
+    if !p2p_svc.has_peer(&peer_id).await {
+        return Err(CommandError::not_found(format!("玩家不存在: {}", peer_id)));
+    }
+
+    let session_id = format!("{}", rand::random::<u64>());
+    let offer = voice_sessions()
+        .lock()
+        .create_offer(&peer_id, &peer_ip, &local_ip, &session_id)
+        .map_err(|e| CommandError::validation(e.to_string()))?;
+    let message = P2PMessage::VoiceOffer {
+        from: local_id,
+        to: peer_id.clone(),
+        sdp: offer,
+    };
+    if let Err(e) = p2p_svc.send_to_player(&peer_id, message).await {
+        voice_sessions().lock().close(&peer_id, None);
+        return Err(e.into());
+    }
+
+    emit_state(&app, &peer_id, VoiceSessionState::Offering, Some(VoiceSessionRole::Offerer));
+    Ok(())
+}
+
+/// 关闭与指定玩家的后端语音连接，并通知对方
+///
+/// # 参数
+/// * `peer_id` - 对方玩家 ID
+///
+/// # 返回
+/// * `Ok(())` - 已关闭（没有连接时同样返回成功）
+/// * `Err(CommandError)` - 错误信息
+#[tauri::command]
+pub async fn close_voice_connection(
+    peer_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), CommandError> {
+    let Some(session) = voice_sessions().lock().close(&peer_id, None) else {
+        return Ok(());
+    };
+    log::info!("🎙️ [VoiceSession] 关闭与 {} 的语音连接", peer_id);
+    emit_state(&app, &peer_id, VoiceSessionState::Closed, None);
+
+    let p2p_signaling = state.get_p2p_signaling();
+    let p2p_svc = p2p_signaling.lock().await;
+    if let Some(local_id) = p2p_svc.get_local_player_id().await {
+        let message = P2PMessage::VoiceClose {
+            from: local_id,
+            to: peer_id.clone(),
+            session_id: session.session_id,
+        };
+        if let Err(e) = p2p_svc.send_to_player(&peer_id, message).await {
+            log::debug!("通知 {} 关闭语音连接失败: {}", peer_id, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_answer_roundtrip() {
+        let mut alice = VoiceSessionManager::new();
+        let mut bob = VoiceSessionManager::new();
+
+        let offer = alice.create_offer("bob", "10.126.126.2", "10.126.126.1", "42").unwrap();
+        assert_eq!(alice.get("bob").unwrap().state, VoiceSessionState::Offering);
+        assert_eq!(
+            alice.create_offer("bob", "10.126.126.2", "10.126.126.1", "43"),
+            Err(VoiceSessionError::AlreadyExists("bob".to_string()))
+        );
+
+        let answer = bob.accept_offer("bob", "alice", "10.126.126.1", "10.126.126.2", &offer).unwrap();
+        let session = bob.get("alice").unwrap();
+        assert_eq!(session.role, VoiceSessionRole::Answerer);
+        assert_eq!(session.state, VoiceSessionState::Negotiated);
+        assert_eq!(session.session_id, "42");
+        assert!(answer.contains("c=IN IP4 10.126.126.2"));
+
+        alice.accept_answer("bob", &answer).unwrap();
+        let session = alice.get("bob").unwrap();
+        assert_eq!(session.state, VoiceSessionState::Negotiated);
+        assert_eq!(session.payload_type, OPUS_PAYLOAD_TYPE);
+
+        // 过期会话的关闭消息被忽略
+        assert!(alice.close("bob", Some("41")).is_none());
+        assert_eq!(alice.close("bob", Some("42")).unwrap().state, VoiceSessionState::Closed);
+        assert!(alice.get("bob").is_none());
+    }
+
+    #[test]
+    fn test_answer_must_match_offer() {
+        let mut alice = VoiceSessionManager::new();
+        assert_eq!(
+            alice.accept_answer("bob", &build_sdp("42", "10.126.126.2", OPUS_PAYLOAD_TYPE)),
+            Err(VoiceSessionError::NotFound("bob".to_string()))
+        );
+
+        alice.create_offer("bob", "10.126.126.2", "10.126.126.1", "42").unwrap();
+        assert_eq!(
+            alice.accept_answer("bob", &build_sdp("7", "10.126.126.2", OPUS_PAYLOAD_TYPE)),
+            Err(VoiceSessionError::SessionMismatch { expected: "42".to_string(), actual: "7".to_string() })
+        );
+        assert_eq!(alice.get("bob").unwrap().state, VoiceSessionState::Offering);
+    }
+
+    #[test]
+    fn test_glare_resolved_by_player_id() {
+        let mut alice = VoiceSessionManager::new();
+        let mut bob = VoiceSessionManager::new();
+        let alice_offer = alice.create_offer("bob", "10.126.126.2", "10.126.126.1", "1").unwrap();
+        let bob_offer = bob.create_offer("alice", "10.126.126.1", "10.126.126.2", "2").unwrap();
+
+        // "alice" < "bob"：alice 保留自己的 offer，bob 转为应答
+        assert_eq!(
+            alice.accept_offer("alice", "bob", "10.126.126.2", "10.126.126.1", &bob_offer),
+            Err(VoiceSessionError::Glare)
+        );
+        let answer = bob.accept_offer("bob", "alice", "10.126.126.1", "10.126.126.2", &alice_offer).unwrap();
+        alice.accept_answer("bob", &answer).unwrap();
+        assert_eq!(alice.get("bob").unwrap().state, VoiceSessionState::Negotiated);
+        assert_eq!(bob.get("alice").unwrap().role, VoiceSessionRole::Answerer);
+    }
+
+    #[test]
+    fn test_parse_sdp_requires_opus() {
+        // 浏览器风格的 SDP：Opus 使用其它负载类型同样可以识别
+        let browser = "v=0\r\no=- 99 2 IN IP4 127.0.0.1\r\ns=-\r\nt=0 0\r\nm=audio 9 UDP/TLS/RTP/SAVPF 109 0\r\na=rtpmap:109 opus/48000/2\r\na=rtpmap:0 PCMU/8000\r\n";
+        assert_eq!(parse_sdp(browser), Ok(("99".to_string(), 109)));
+
+        let pcmu_only = "v=0\r\no=- 99 2 IN IP4 127.0.0.1\r\nm=audio 9 RTP/AVP 0\r\na=rtpmap:0 PCMU/8000\r\n";
+        assert_eq!(parse_sdp(pcmu_only), Err(VoiceSessionError::UnsupportedCodec));
+        // rtpmap 声明了 Opus 但 m= 行没有列出
+        let unlisted = "v=0\r\no=- 99 2 IN IP4 127.0.0.1\r\nm=audio 9 RTP/AVP 0\r\na=rtpmap:111 opus/48000/2\r\n";
+        assert_eq!(parse_sdp(unlisted), Err(VoiceSessionError::UnsupportedCodec));
+        assert!(matches!(parse_sdp("v=0\r\nm=audio 9 RTP/AVP 111\r\n"), Err(VoiceSessionError::InvalidSdp(_))));
+        assert!(matches!(parse_sdp(&"a".repeat(MAX_SDP_LEN + 1)), Err(VoiceSessionError::InvalidSdp(_))));
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use serde::{Deserialize, Serialize};
 use tauri::Emitter;
 use crate::modules::error::AppError;
@@ -94,7 +94,20 @@ pub struct CandidateData {
 struct ClientInfo {
     player_id: String,
     player_name: String,
-    sender: Arc<RwLock<futures_util::stream::SplitSink<tokio_tungstenite::WebSocketStream<TcpStream>, Message>>>,
+    /// 发往该客户端的消息队列
+    ///
+    /// 每个连接只有一个写任务持有 SplitSink，其它任务（转发、广播）只往队列里投递，
+    /// 多个转发同时发生时不会交错写同一个连接，也不会在持有 clients 锁时等待慢客户端。
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+impl ClientInfo {
+    /// 投递一条信令消息（连接已断开时静默丢弃）
+    fn send(&self, message: &SignalingMessage) {
+        if let Ok(json) = serde_json::to_string(message) {
+            let _ = self.sender.send(Message::Text(json));
+        }
+    }
 }
 
 /// WebSocket 信令服务器
@@ -212,8 +225,19 @@ impl WebSocketSignalingServer {
         
         log::info!("✅ WebSocket 连接已建立: {}", addr);
         
-        let (write, mut read) = ws_stream.split();
-        let write = Arc::new(RwLock::new(write));
+        let (mut write, mut read) = ws_stream.split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        
+        // 写任务：唯一持有 SplitSink，按投递顺序逐条发送；队列关闭或写失败时关闭连接
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let Err(e) = write.send(msg).await {
+                    log::warn!("发送消息失败 ({}): {}", addr, e);
+                    break;
+                }
+            }
+            let _ = write.close().await;
+        });
         
         let mut client_id: Option<String> = None;
         
@@ -230,32 +254,36 @@ impl WebSocketSignalingServer {
                                     SignalingMessage::Register { client_id: cid, player_name } => {
                                         log::info!("客户端注册: {} ({})", player_name, cid);
                                         
+                                        // 同一连接换了 ID 重新注册，旧 ID 视为离开
+                                        if let Some(old) = client_id.take().filter(|old| *old != cid) {
+                                            Self::remove_client(&clients, &app_handle, &old, &tx).await;
+                                        }
+                                        
                                         // 保存客户端信息
                                         let client_info = ClientInfo {
                                             player_id: cid.clone(),
                                             player_name: player_name.clone(),
-                                            sender: Arc::clone(&write),
+                                            sender: tx.clone(),
                                         };
                                         
-                                        clients.write().await.insert(cid.clone(), client_info);
+                                        let players: Vec<PlayerInfo> = {
+                                            let mut clients = clients.write().await;
+                                            clients.insert(cid.clone(), client_info.clone());
+                                            log::info!("当前在线: {} 人", clients.len());
+                                            
+                                            clients
+                                                .iter()
+                                                .filter(|(id, _)| **id != cid)
+                                                .map(|(_, info)| PlayerInfo {
+                                                    player_id: info.player_id.clone(),
+                                                    player_name: info.player_name.clone(),
+                                                })
+                                                .collect()
+                                        };
                                         client_id = Some(cid.clone());
                                         
-                                        log::info!("当前在线: {} 人", clients.read().await.len());
-                                        
                                         // 发送当前在线玩家列表
-                                        let players: Vec<PlayerInfo> = clients.read().await
-                                            .iter()
-                                            .filter(|(id, _)| **id != cid)
-                                            .map(|(_, info)| PlayerInfo {
-                                                player_id: info.player_id.clone(),
-                                                player_name: info.player_name.clone(),
-                                            })
-                                            .collect();
-                                        
-                                        let players_list = SignalingMessage::PlayersList { players };
-                                        if let Ok(json) = serde_json::to_string(&players_list) {
-                                            let _ = write.write().await.send(Message::Text(json)).await;
-                                        }
+                                        client_info.send(&SignalingMessage::PlayersList { players });
                                         
                                         // 通知其他客户端有新玩家加入
                                         Self::broadcast_except(
@@ -278,23 +306,18 @@ impl WebSocketSignalingServer {
                                     SignalingMessage::Offer { from, to, offer, .. } => {
                                         log::info!("转发 Offer from {} to {}", from, to);
                                         
+                                        let clients = clients.read().await;
                                         // 获取发送者名称
-                                        let player_name = clients.read().await
-                                            .get(&from)
-                                            .map(|info| info.player_name.clone());
+                                        let player_name = clients.get(&from).map(|info| info.player_name.clone());
                                         
                                         // 转发到目标客户端
-                                        if let Some(target) = clients.read().await.get(&to) {
-                                            let forward_msg = SignalingMessage::Offer {
+                                        if let Some(target) = clients.get(&to) {
+                                            target.send(&SignalingMessage::Offer {
                                                 from,
                                                 to,
                                                 offer,
                                                 player_name,
-                                            };
-                                            
-                                            if let Ok(json) = serde_json::to_string(&forward_msg) {
-                                                let _ = target.sender.write().await.send(Message::Text(json)).await;
-                                            }
+                                            });
                                         } else {
                                             log::warn!("目标客户端不存在: {}", to);
                                         }
@@ -304,11 +327,7 @@ impl WebSocketSignalingServer {
                                         
                                         // 转发到目标客户端
                                         if let Some(target) = clients.read().await.get(&to) {
-                                            let forward_msg = SignalingMessage::Answer { from, to, answer };
-                                            
-                                            if let Ok(json) = serde_json::to_string(&forward_msg) {
-                                                let _ = target.sender.write().await.send(Message::Text(json)).await;
-                                            }
+                                            target.send(&SignalingMessage::Answer { from, to, answer });
                                         } else {
                                             log::warn!("目标客户端不存在: {}", to);
                                         }
@@ -318,11 +337,7 @@ impl WebSocketSignalingServer {
                                         
                                         // 转发到目标客户端
                                         if let Some(target) = clients.read().await.get(&to) {
-                                            let forward_msg = SignalingMessage::IceCandidate { from, to, candidate };
-                                            
-                                            if let Ok(json) = serde_json::to_string(&forward_msg) {
-                                                let _ = target.sender.write().await.send(Message::Text(json)).await;
-                                            }
+                                            target.send(&SignalingMessage::IceCandidate { from, to, candidate });
                                         } else {
                                             log::warn!("目标客户端不存在: {}", to);
                                         }
@@ -348,31 +363,52 @@ impl WebSocketSignalingServer {
             }
         }
         
-        // 客户端断开连接，清理资源
+        // 读循环结束（正常关闭、流结束或出错），清理该连接注册的客户端
         if let Some(cid) = client_id {
             log::info!("客户端断开: {}", cid);
-            clients.write().await.remove(&cid);
-            
-            // 通知其他客户端
-            Self::broadcast_except(
-                &clients,
-                &cid,
-                SignalingMessage::PlayerLeft {
-                    player_id: cid.clone(),
-                },
-            ).await;
-            
-            // 通知前端
-            if let Some(app) = app_handle.read().await.as_ref() {
-                let _ = app.emit("player-left", serde_json::json!({
-                    "playerId": cid,
-                }));
-            }
+            Self::remove_client(&clients, &app_handle, &cid, &tx).await;
         }
         
         Ok(())
     }
     
+    /// 移除客户端并通知其他客户端和前端
+    ///
+    /// 只移除仍属于该连接的记录：同一 ID 已经从新连接重新注册时，旧连接断开不影响新连接。
+    async fn remove_client(
+        clients: &Arc<RwLock<HashMap<String, ClientInfo>>>,
+        app_handle: &Arc<RwLock<Option<tauri::AppHandle>>>,
+        client_id: &str,
+        connection: &mpsc::UnboundedSender<Message>,
+    ) {
+        let removed = {
+            let mut clients = clients.write().await;
+            match clients.get(client_id) {
+                Some(info) if info.sender.same_channel(connection) => clients.remove(client_id).is_some(),
+                _ => false,
+            }
+        };
+        if !removed {
+            return;
+        }
+        
+        // 通知其他客户端
+        Self::broadcast_except(
+            clients,
+            client_id,
+            SignalingMessage::PlayerLeft {
+                player_id: client_id.to_string(),
+            },
+        ).await;
+        
+        // 通知前端
+        if let Some(app) = app_handle.read().await.as_ref() {
+            let _ = app.emit("player-left", serde_json::json!({
+                "playerId": client_id,
+            }));
+        }
+    }
+    
     /// 广播消息（排除指定客户端）
     async fn broadcast_except(
         clients: &Arc<RwLock<HashMap<String, ClientInfo>>>,
@@ -380,10 +416,9 @@ impl WebSocketSignalingServer {
         message: SignalingMessage,
     ) {
         if let Ok(json) = serde_json::to_string(&message) {
-            let clients_read = clients.read().await;
-            for (id, client) in clients_read.iter() {
+            for (id, client) in clients.read().await.iter() {
                 if id != exclude_id {
-                    let _ = client.sender.write().await.send(Message::Text(json.clone())).await;
+                    let _ = client.sender.send(Message::Text(json.clone()));
                 }
             }
         }
@@ -395,8 +430,10 @@ impl WebSocketSignalingServer {
         
         *self.is_running.write().await = false;
         
-        // 关闭所有客户端连接
-        self.clients.write().await.clear();
+        // 关闭所有客户端连接（写任务发出 Close 帧，客户端回应后读循环结束）
+        for client in self.clients.write().await.drain().map(|(_, client)| client) {
+            let _ = client.sender.send(Message::Close(None));
+        }
         
         Ok(())
     }
@@ -406,3 +443,138 @@ impl WebSocketSignalingServer {
         self.clients.read().await.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+    type Clients = Arc<RwLock<HashMap<String, ClientInfo>>>;
+
+    /// 在随机端口上启动只处理连接的服务端
+    async fn spawn_server() -> (String, Clients) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let clients: Clients = Arc::new(RwLock::new(HashMap::new()));
+        let server_clients = clients.clone();
+        tokio::spawn(async move {
+            let app_handle = Arc::new(RwLock::new(None));
+            loop {
+                let (stream, addr) = listener.accept().await.unwrap();
+                let (clients, app_handle) = (server_clients.clone(), app_handle.clone());
+                tokio::spawn(async move {
+                    let _ = WebSocketSignalingServer::handle_connection(stream, addr, clients, app_handle).await;
+                });
+            }
+        });
+        (url, clients)
+    }
+
+    async fn register(url: &str, id: &str) -> Client {
+        let (mut ws, _) = connect_async(url).await.unwrap();
+        let register = SignalingMessage::Register { client_id: id.to_string(), player_name: id.to_string() };
+        ws.send(Message::Text(serde_json::to_string(&register).unwrap())).await.unwrap();
+        // 第一条回复是在线玩家列表
+        assert!(matches!(recv(&mut ws).await, SignalingMessage::PlayersList { .. }));
+        ws
+    }
+
+    async fn recv(ws: &mut Client) -> SignalingMessage {
+        loop {
+            let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+                .await
+                .expect("等待消息超时")
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = msg {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    async fn wait_for_count(clients: &Clients, expected: usize) {
+        for _ in 0..100 {
+            if clients.read().await.len() == expected {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("在线人数未变为 {}", expected);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_removes_client_and_broadcasts_player_left() {
+        let (url, clients) = spawn_server().await;
+        let mut alice = register(&url, "alice").await;
+        let bob = register(&url, "bob").await;
+        assert!(matches!(recv(&mut alice).await, SignalingMessage::PlayerJoined { player_id, .. } if player_id == "bob"));
+
+        // 不发 Close 帧直接断开 TCP
+        drop(bob);
+        wait_for_count(&clients, 1).await;
+        assert!(matches!(recv(&mut alice).await, SignalingMessage::PlayerLeft { player_id } if player_id == "bob"));
+        assert!(clients.read().await.contains_key("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_connection_does_not_remove_reregistered_client() {
+        let (url, clients) = spawn_server().await;
+        let mut watcher = register(&url, "watcher").await;
+        let old = register(&url, "carol").await;
+        let _ = recv(&mut watcher).await;
+        // 同一玩家从新连接重新注册（如网络切换后重连）
+        let _new = register(&url, "carol").await;
+        let _ = recv(&mut watcher).await;
+
+        drop(old);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(clients.read().await.contains_key("carol"));
+        assert_eq!(clients.read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_forwarding_to_one_client() {
+        let (url, _clients) = spawn_server().await;
+        let mut target = register(&url, "target").await;
+        let mut senders = Vec::new();
+        for i in 0..4 {
+            senders.push(register(&url, &format!("sender-{}", i)).await);
+        }
+        for _ in 0..4 {
+            assert!(matches!(recv(&mut target).await, SignalingMessage::PlayerJoined { .. }));
+        }
+
+        // 多个连接同时向同一目标转发，目标收到的每条消息都完整可解析
+        const PER_SENDER: usize = 25;
+        let tasks: Vec<_> = senders
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut ws)| {
+                tokio::spawn(async move {
+                    for n in 0..PER_SENDER {
+                        let candidate = SignalingMessage::IceCandidate {
+                            from: format!("sender-{}", i),
+                            to: "target".to_string(),
+                            candidate: CandidateData {
+                                candidate: format!("candidate {} {}", i, n),
+                                sdp_m_line_index: Some(0),
+                                sdp_mid: None,
+                            },
+                        };
+                        ws.send(Message::Text(serde_json::to_string(&candidate).unwrap())).await.unwrap();
+                    }
+                    ws
+                })
+            })
+            .collect();
+        let mut received = 0;
+        while received < 4 * PER_SENDER {
+            assert!(matches!(recv(&mut target).await, SignalingMessage::IceCandidate { .. }));
+            received += 1;
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+}
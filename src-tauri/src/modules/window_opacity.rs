@@ -0,0 +1,113 @@
+// 窗口透明度
+//
+// macOS 通过 NSWindow setAlphaValue、Linux (X11) 通过 GTK 窗口 opacity 设置真实的整窗透明度。
+// Windows 不使用 WS_EX_LAYERED + SetLayeredWindowAttributes(LWA_ALPHA)：整窗统一 alpha 会覆盖
+// Tauri 的逐像素真透明（transparent:true），圆角与留白处看不到桌面，因此 Windows 与其它不支持的环境
+// （Wayland、无合成器的 X11）由前端以 CSS 背景 alpha 实现，这里只做范围校验并返回降级结果。
+
+use serde::Serialize;
+
+/// 允许设置的最低透明度（再低窗口内容几乎看不清）
+pub const MIN_WINDOW_OPACITY: f64 = 0.3;
+/// 允许设置的最高透明度（完全不透明）
+pub const MAX_WINDOW_OPACITY: f64 = 1.0;
+
+/// 透明度的实现方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowOpacityMode {
+    /// 系统窗口透明度已生效，前端背景保持不透明
+    Native,
+    /// 当前平台不支持，由前端 CSS 背景 alpha 实现
+    Css,
+}
+
+/// 把透明度限制到允许范围，非法值（NaN）视为完全不透明
+pub fn clamp_window_opacity(opacity: f64) -> f64 {
+    if opacity.is_nan() {
+        return MAX_WINDOW_OPACITY;
+    }
+    opacity.clamp(MIN_WINDOW_OPACITY, MAX_WINDOW_OPACITY)
+}
+
+/// 设置窗口的系统透明度
+///
+/// 窗口操作需要在主线程执行，这里投递到主线程后等待结果。
+///
+/// # 参数
+/// * `window` - 目标窗口
+/// * `opacity` - 已限制到允许范围的透明度
+///
+/// # 返回
+/// 实际采用的实现方式
+pub async fn apply_window_opacity(window: &tauri::Window, opacity: f64) -> WindowOpacityMode {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let target = window.clone();
+    if let Err(e) = window.run_on_main_thread(move || {
+        let _ = tx.send(apply_native(&target, opacity));
+    }) {
+        log::warn!("⚠️ 无法在主线程设置窗口透明度，降级为 CSS 透明: {}", e);
+        return WindowOpacityMode::Css;
+    }
+    match rx.await {
+        Ok(Ok(())) => WindowOpacityMode::Native,
+        Ok(Err(reason)) => {
+            log::info!("当前平台不支持系统窗口透明度（{}），降级为 CSS 透明", reason);
+            WindowOpacityMode::Css
+        }
+        Err(_) => WindowOpacityMode::Css,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_native(window: &tauri::Window, opacity: f64) -> Result<(), String> {
+    use objc2::msg_send;
+    use objc2::runtime::AnyObject;
+
+    let ns_window = window.ns_window().map_err(|e| format!("获取 NSWindow 失败: {}", e))?;
+    if ns_window.is_null() {
+        return Err("NSWindow 为空".to_string());
+    }
+    // SAFETY: ns_window 是 Tauri 持有的有效 NSWindow 指针，且当前在主线程调用
+    unsafe {
+        let ns_window = &*(ns_window as *mut AnyObject);
+        let _: () = msg_send![ns_window, setAlphaValue: opacity];
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn apply_native(window: &tauri::Window, opacity: f64) -> Result<(), String> {
+    use gtk::prelude::{GtkWindowExt, WidgetExt};
+
+    // GTK3 在 Wayland 下不支持顶层窗口透明度，设置了也不生效
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"));
+    if wayland {
+        return Err("Wayland 会话".to_string());
+    }
+    let gtk_window = window.gtk_window().map_err(|e| format!("获取 GTK 窗口失败: {}", e))?;
+    if !gtk_window.screen().is_some_and(|screen| screen.is_composited()) {
+        return Err("X11 未运行合成器".to_string());
+    }
+    gtk_window.set_opacity(opacity);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn apply_native(_window: &tauri::Window, _opacity: f64) -> Result<(), String> {
+    Err("Windows 使用逐像素透明".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_window_opacity() {
+        assert_eq!(clamp_window_opacity(0.0), MIN_WINDOW_OPACITY);
+        assert_eq!(clamp_window_opacity(0.75), 0.75);
+        assert_eq!(clamp_window_opacity(5.0), MAX_WINDOW_OPACITY);
+        assert_eq!(clamp_window_opacity(f64::NAN), MAX_WINDOW_OPACITY);
+    }
+}
@@ -1,5 +1,5 @@
 import { useEffect, useState } from 'react';
-import { ConfigProvider, theme, App as AntdApp } from 'antd';
+import { ConfigProvider, theme, App as AntdApp, message, Alert, Button } from 'antd';
 import zhCN from 'antd/locale/zh_CN';
 import enUS from 'antd/locale/en_US';
 import { invoke } from '@tauri-apps/api/core';
@@ -7,6 +7,7 @@ import { listen } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { useTranslation } from 'react-i18next';
 import { tl, getLanguage } from './i18n';
+import { errorMessage } from './services/error/commandError';
 import { ErrorBoundary, MainWindow, MiniWindow } from './components';
 import { GlobalTooltip } from './components/GlobalTooltip/GlobalTooltip';
 import { GlobalButtonTheme } from './components/GlobalTooltip/GlobalButtonTheme';
@@ -19,7 +20,7 @@ import { hotkeyManager, webrtcClient, audioService, fileShareService } from './s
 import { screenShareService } from './services/screenShare/ScreenShareService';
 import { speakingDetector } from './services/voice/SpeakingDetector';
 import { versionCheckService } from './services/version/VersionCheckService';
-import type { UserConfig } from './types';
+import type { PresenceStatus, UserConfig } from './types';
 import './App.css';
 
 function App() {
@@ -101,6 +102,10 @@ function App() {
         await invoke('set_tray_menu_texts', {
           showText: tl('显示 MCTier', 'Show MCTier'),
           exitText: tl('退出 MCTier', 'Exit MCTier'),
+          micOnText: tl('打开麦克风', 'Turn Mic On'),
+          micOffText: tl('关闭麦克风', 'Turn Mic Off'),
+          idleText: tl('未加入大厅', 'Not in a lobby'),
+          inLobbyText: tl('大厅', 'Lobby'),
         });
       } catch (error) {
         console.error('同步托盘菜单语言失败:', error);
@@ -191,16 +196,20 @@ function App() {
   useEffect(() => {
     let unlisten: (() => void) | undefined;
     const setup = async () => {
-      unlisten = await listen<string>('deep-link-join', (event) => {
+      unlisten = await listen<string>('deep-link-join', async (event) => {
         try {
-          const raw = String(event.payload || '');
-          const m = raw.match(/^mctier:\/\/join\/?\?(.*)$/i);
-          if (!m) return;
-          const params = new URLSearchParams(m[1]);
-          const name = params.get('name') || '';
-          const pwd = params.get('pwd') || '';
-          if (!name) return;
-          (window as any).__deepLinkConfig = { lobbyName: name, password: pwd };
+          const params = await invoke<{
+            name: string;
+            serverNode?: string | null;
+            useDomain: boolean;
+            password?: string | null;
+          }>('parse_invite_link', { link: String(event.payload || '') });
+          (window as any).__deepLinkConfig = {
+            lobbyName: params.name,
+            password: params.password ?? '',
+            serverNode: params.serverNode ?? undefined,
+            useDomain: params.useDomain,
+          };
           window.dispatchEvent(new CustomEvent('mctier-open-join'));
         } catch (e) {
           console.warn('解析 deep link 失败（忽略）:', e);
@@ -211,6 +220,72 @@ function App() {
     return () => { if (unlisten) unlisten(); };
   }, []);
 
+  // 重名玩家的显示后缀与在线状态：后端发现新玩家时在 player-joined 中附带 displaySuffix / presence
+  useEffect(() => {
+    const unlisteners: Array<() => void> = [];
+    const setup = async () => {
+      unlisteners.push(await listen<{ playerId: string; displaySuffix?: string | null; presence?: PresenceStatus }>('player-joined', (event) => {
+        useAppStore.getState().setDisplaySuffix(event.payload.playerId, event.payload.displaySuffix ?? null);
+        if (event.payload.presence) {
+          useAppStore.getState().setPlayerPresence(event.payload.playerId, event.payload.presence);
+        }
+      }));
+      unlisteners.push(await listen<{ playerId: string; presence: PresenceStatus }>('player-presence-update', (event) => {
+        useAppStore.getState().setPlayerPresence(event.payload.playerId, event.payload.presence);
+      }));
+      unlisteners.push(await listen<{ playerId: string }>('player-left', (event) => {
+        useAppStore.getState().setDisplaySuffix(event.payload.playerId, null);
+      }));
+    };
+    void setup();
+    return () => unlisteners.forEach((u) => u());
+  }, []);
+
+  // 服务端口被占用时后端自动改用其它端口，提示用户实际端口（便于配置防火墙）
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    const setup = async () => {
+      unlisten = await listen<{ service: string; configuredPort: number; actualPort: number }>(
+        'service-port-changed',
+        (event) => {
+          const { service, configuredPort, actualPort } = event.payload;
+          const label = service === 'signaling' ? 'P2P 信令' : service === 'file_share' ? '文件共享' : '聊天';
+          message.warning(`${label}端口 ${configuredPort} 被占用，已改用 ${actualPort}`);
+        },
+      );
+    };
+    void setup();
+    return () => { if (unlisten) unlisten(); };
+  }, []);
+
+  // 非管理员启动提示：后端启动时推送 elevation-required，挂载时也主动查询一次（避免错过事件）
+  const [elevationRequired, setElevationRequired] = useState(false);
+  const [restartingAsAdmin, setRestartingAsAdmin] = useState(false);
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    const setup = async () => {
+      unlisten = await listen('elevation-required', () => setElevationRequired(true));
+      try {
+        if (!(await invoke<boolean>('is_admin'))) setElevationRequired(true);
+      } catch (e) {
+        console.warn('查询管理员权限失败（忽略）:', e);
+      }
+    };
+    void setup();
+    return () => { if (unlisten) unlisten(); };
+  }, []);
+
+  const handleRestartAsAdmin = async () => {
+    setRestartingAsAdmin(true);
+    try {
+      await invoke('restart_as_admin');
+    } catch (e) {
+      message.error(`${tl('以管理员身份重启失败', 'Failed to restart as administrator')}: ${errorMessage(e)}`);
+    } finally {
+      setRestartingAsAdmin(false);
+    }
+  };
+
   // 全局禁用右键菜单
   useEffect(() => {
     const handleContextMenu = (e: MouseEvent) => {
@@ -345,6 +420,13 @@ function App() {
           console.warn('加载用户配置失败，使用默认配置:', error);
         }
 
+        // 配置文件被外部修改（手动编辑或其他实例保存）后，后端重新加载并推送新配置
+        const unlistenConfigReloaded = await listen<UserConfig>('config-reloaded', (event) => {
+          const { updateConfig } = useAppStore.getState();
+          updateConfig(event.payload);
+          message.info(tl('配置文件已在外部修改，已重新加载', 'Config file changed on disk and was reloaded'));
+        });
+
         // 初始化快捷键管理器
         await hotkeyManager.initialize();
 
@@ -371,12 +453,113 @@ function App() {
           console.log('全局听筒状态已更新:', newState ? '静音' : '开启');
         });
 
+        // 监听大厅创建者重新选举（原创建者离开后由虚拟 IP 最小的成员接任）
+        const unlistenCreatorChanged = await listen<{ creatorVirtualIp: string; isCreator: boolean }>('creator-changed', (event) => {
+          const { lobby: currentLobby, setLobby } = useAppStore.getState();
+          if (currentLobby) {
+            setLobby({ ...currentLobby, creatorVirtualIp: event.payload.creatorVirtualIp });
+          }
+          console.log('大厅创建者已变更:', event.payload.creatorVirtualIp, event.payload.isCreator ? '（本机）' : '');
+        });
+
+        // 监听其他玩家浏览 / 下载本机共享的提示
+        const unlistenPeerBrowsing = await listen<{ playerName?: string; shareName: string; activity: 'browsing' | 'downloading' }>('peer-browsing', (event) => {
+          const { playerName, shareName, activity } = event.payload;
+          const name = playerName || tl('有玩家', 'Someone');
+          if (activity === 'downloading') {
+            message.info(tl(`${name} 正在下载你的共享「${shareName}」`, `${name} is downloading from your share "${shareName}"`));
+          } else {
+            message.info(tl(`${name} 正在浏览你的共享「${shareName}」`, `${name} is browsing your share "${shareName}"`));
+          }
+        });
+
+        // 监听与某个玩家的连接回退为中继（P2P 打洞失败），提示延迟可能偏高
+        const unlistenConnectionRelayed = await listen<{ ip: string; playerName?: string | null; preferredRelay?: string | null }>('connection-relayed', (event) => {
+          const { ip, playerName, preferredRelay } = event.payload;
+          const name = playerName || ip;
+          const hint = preferredRelay
+            ? tl(`（经由偏好中继 ${preferredRelay}）`, ` (via preferred relay ${preferredRelay})`)
+            : tl('，跨国联机可指定偏好中继节点', '; for cross-region play, set a preferred relay node');
+          message.warning({
+            key: `connection-relayed-${ip}`,
+            content: tl(`与 ${name} 的 P2P 直连失败，已走中继，延迟可能偏高${hint}`, `Direct connection to ${name} failed, using relay and latency may be higher${hint}`),
+            duration: 6,
+          });
+        });
+
+        // 监听虚拟 IP 冲突（DHCP 偶发分配到相同 IP），需要让出时后端会自动重新分配
+        const unlistenIpConflict = await listen<{ virtualIp: string; otherPlayerId: string; yielding: boolean }>('ip-conflict', (event) => {
+          const { virtualIp, yielding } = event.payload;
+          message.warning({
+            key: 'ip-conflict',
+            content: yielding
+              ? tl(`虚拟 IP ${virtualIp} 与其他玩家冲突，正在重新分配…`, `Virtual IP ${virtualIp} conflicts with another player, reassigning…`)
+              : tl(`虚拟 IP ${virtualIp} 与其他玩家冲突，等待对方重新分配`, `Virtual IP ${virtualIp} conflicts with another player, waiting for them to reassign`),
+            duration: 6,
+          });
+        });
+
+        // 监听聊天中有人 @ 了本机玩家（在聊天室内时消息本身会高亮，不再弹提示）
+        const unlistenChatMention = await listen<{ player_name: string; content: string }>('chat-mention', (event) => {
+          if ((window as any).__isInChatRoom__) return;
+          const { player_name: name, content } = event.payload;
+          const preview = content.length > 30 ? `${content.slice(0, 30)}…` : content;
+          message.info({
+            content: tl(`${name} 在聊天中 @ 了你：${preview}`, `${name} mentioned you in chat: ${preview}`),
+            duration: 5,
+          });
+        });
+
+        // 监听其他玩家向本机可写共享上传文件
+        const unlistenFileUploaded = await listen<{ shareName: string; filePath: string }>('file-uploaded-by-peer', (event) => {
+          const { shareName, filePath } = event.payload;
+          message.info(tl(`有玩家向共享「${shareName}」上传了 ${filePath}`, `A player uploaded ${filePath} to your share "${shareName}"`));
+        });
+
+        // 监听 EasyTier 连接阶段（连接节点、打洞、回退中继等），以同一个 key 原地刷新提示
+        const unlistenEasyTierStatus = await listen<{ phase: string; raw: string }>('easytier-status', (event) => {
+          const { phase, raw } = event.payload;
+          console.log('EasyTier 连接阶段:', phase, raw);
+          const key = 'easytier-status';
+          switch (phase) {
+            case 'connecting_server':
+              message.loading({ key, content: tl('正在连接服务器节点…', 'Connecting to server node…'), duration: 0 });
+              break;
+            case 'handshaking':
+              message.loading({ key, content: tl('正在握手…', 'Handshaking…'), duration: 0 });
+              break;
+            case 'hole_punching':
+              message.loading({ key, content: tl('正在打洞…', 'Hole punching…'), duration: 0 });
+              break;
+            case 'relay_fallback':
+              message.warning({ key, content: tl('P2P 直连失败，已回退中继', 'P2P direct connection failed, fell back to relay'), duration: 4 });
+              break;
+            case 'peer_connected':
+              message.success({ key, content: tl('已与玩家建立连接', 'Connected to peer'), duration: 2 });
+              break;
+            case 'peer_connect_failed':
+              message.warning({ key, content: tl('连接玩家失败，正在重试…', 'Failed to connect to peer, retrying…'), duration: 4 });
+              break;
+            case 'ip_assigned':
+              message.success({ key, content: tl('已获得虚拟 IP', 'Virtual IP assigned'), duration: 2 });
+              break;
+          }
+        });
+
         console.log('应用初始化完成');
 
         // 返回清理函数
         return () => {
           unlistenMicToggled();
           unlistenGlobalMuteToggled();
+          unlistenConfigReloaded();
+          unlistenCreatorChanged();
+          unlistenPeerBrowsing();
+          unlistenConnectionRelayed();
+          unlistenChatMention();
+          unlistenIpConflict();
+          unlistenFileUploaded();
+          unlistenEasyTierStatus();
           unlistenClose();
         };
       } catch (error) {
@@ -488,6 +671,7 @@ function App() {
           speakingDetector.setCallback((playerId, speaking) => {
             setPlayerSpeaking(playerId, speaking);
           });
+          void speakingDetector.loadLocalThreshold();
 
           webrtcClient.onRemoteStream((playerId, stream) => {
             console.log(`WebRTC: 接收到远程音频流 - ${playerId}`);
@@ -500,7 +684,7 @@ function App() {
             const selfId = useAppStore.getState().currentPlayerId;
             if (!selfId) return;
             if (stream) {
-              try { speakingDetector.attach(selfId, stream); } catch (e) { console.warn('本机说话检测接入失败:', e); }
+              try { speakingDetector.attach(selfId, stream, true); } catch (e) { console.warn('本机说话检测接入失败:', e); }
             } else {
               speakingDetector.detach(selfId);
             }
@@ -672,6 +856,20 @@ function App() {
           <GlobalTooltip />
           <GlobalButtonTheme />
           <div className="app-container">
+            {elevationRequired && appState !== 'in-lobby' && (
+              <Alert
+                className="elevation-banner"
+                type="warning"
+                banner
+                showIcon
+                message={tl('请以管理员身份运行 MCTier，否则无法创建或加入大厅', 'Please run MCTier as administrator, otherwise lobbies cannot be created or joined')}
+                action={
+                  <Button size="small" type="primary" loading={restartingAsAdmin} onClick={handleRestartAsAdmin}>
+                    {tl('以管理员身份重启', 'Restart as admin')}
+                  </Button>
+                }
+              />
+            )}
             {/* 根据应用状态显示不同的界面 */}
             {appState === 'in-lobby' && lobby ? <MiniWindow /> : <MainWindow />}
           </div>
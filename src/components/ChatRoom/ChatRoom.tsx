@@ -179,6 +179,19 @@ export const ChatRoom: React.FC = () => {
     return `> @${replyTo.playerName} ${summary}\n${body}`;
   };
 
+  // 解析正文中的 @名字 为玩家 ID（@所有人 展开为全部其他玩家），随消息发送供对方识别提及
+  const collectMentionIds = (body: string): string[] => {
+    const ids = new Set<string>();
+    for (const match of body.matchAll(/@([^\s@]{1,20})/g)) {
+      const name = match[1];
+      const isEveryone = name === '所有人' || name === '全体' || name.toLowerCase() === 'all';
+      players
+        .filter((p) => p.id !== currentPlayerId && (isEveryone || p.name === name))
+        .forEach((p) => ids.add(p.id));
+    }
+    return Array.from(ids);
+  };
+
   const focusInputSoon = useCallback(() => {
     window.setTimeout(() => {
       textAreaRef.current?.focus?.();
@@ -197,6 +210,8 @@ export const ChatRoom: React.FC = () => {
     const text = inputValue.trim();
     // 引用回复：在正文前加入 "> @名字 摘要" 引用行（与安卓端格式一致，跨端互通）
     const messageContent = buildReplyContent(text);
+    const replyToId = replyTo?.id;
+    const mentionIds = collectMentionIds(text);
     
     // 清空输入框
     setInputValue('');
@@ -211,6 +226,8 @@ export const ChatRoom: React.FC = () => {
         content: messageContent,
         timestamp: Date.now(),
         type: 'text',
+        replyTo: replyToId,
+        mentions: mentionIds.length > 0 ? mentionIds : undefined,
       };
       
       // 立即添加到本地消息列表
@@ -221,7 +238,7 @@ export const ChatRoom: React.FC = () => {
       scrollToBottom(false);
       
       // 发送到P2P网络
-      const res = await p2pChatService.sendTextMessage(messageContent);
+      const res = await p2pChatService.sendTextMessage(messageContent, { replyTo: replyToId, mentions: mentionIds });
       console.log('✅ [ChatRoom] 文本消息已发送到P2P网络', res);
       // 回执：有其他玩家但一个都没送达时，提示可能未送达
       if (res && res.total > 0 && res.delivered === 0) {
@@ -814,8 +831,19 @@ export const ChatRoom: React.FC = () => {
                     <div style={{ flex: 1, height: 1, background: 'rgba(255,120,117,0.4)' }} />
                   </div>
                 )}
+                {message.type === 'system' ? (
+                  <motion.div
+                    className="chat-system-message"
+                    initial={{ opacity: 0 }}
+                    animate={{ opacity: 1 }}
+                    exit={{ opacity: 0 }}
+                    transition={{ duration: 0.2 }}
+                  >
+                    <span>{message.content}</span>
+                  </motion.div>
+                ) : (
                 <motion.div
-                  className={`chat-message ${isOwnMessage ? 'own' : 'other'}`}
+                  className={`chat-message ${isOwnMessage ? 'own' : 'other'}${!isOwnMessage && !!currentPlayerId && !!message.mentions?.includes(currentPlayerId) ? ' mentioned' : ''}`}
                   initial={{ opacity: 0, y: 20 }}
                   animate={{ opacity: 1, y: 0 }}
                   exit={{ opacity: 0, y: -20 }}
@@ -904,6 +932,7 @@ export const ChatRoom: React.FC = () => {
                 </span>
                 </div>
               </motion.div>
+                )}
               </React.Fragment>
             );
           })}
@@ -2,13 +2,17 @@
  * 一键连接诊断面板
  * 聚合每位成员的连接类型(P2P直连/中继)、延迟、丢包，给出整体评分与优化建议。
  * 数据来源：get_peer_connection_types(EasyTier 自身统计) + measure_peers_latency(兜底 RTT)。
+ * 整体评分取后端 get_room_health_score（与 room-health 事件同一公式），失败时回退到本地估算。
+ * 每位成员可单独做端口检查（diagnose_file_share_connection），逐项列出文件/聊天/信令端口是否可达。
+ * 有成员走中继时可在此指定偏好中继节点（set_preferred_relay），下次进入大厅生效。
  */
 
 import React, { useCallback, useEffect, useState } from 'react';
-import { Modal, Button, Tag, Spin, Empty, Progress } from 'antd';
+import { Modal, Button, Tag, Spin, Empty, Progress, Input, message } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { useAppStore } from '../../stores/appStore';
 
 interface ConnectionDiagnosticModalProps {
@@ -23,6 +27,48 @@ interface PeerConn {
   lossRate?: number;
 }
 
+interface PortCheck {
+  service: 'file_share' | 'chat' | 'signaling';
+  port: number;
+  protocol: 'tcp' | 'udp';
+  status: 'open' | 'closed' | 'no_response';
+  reachable: boolean;
+  detail: string;
+  suggestion?: string;
+}
+
+interface PortReport {
+  ports: PortCheck[];
+  suggestions: string[];
+}
+
+const SERVICE_LABELS: Record<PortCheck['service'], [string, string]> = {
+  file_share: ['文件共享', 'File sharing'],
+  chat: ['聊天', 'Chat'],
+  signaling: ['P2P 信令（语音）', 'P2P signaling (voice)'],
+};
+
+interface PeerHealth {
+  ip: string;
+  playerName?: string | null;
+  score: number;
+  mainIssue?: 'high_latency' | 'unreachable' | 'packet_loss' | 'relay' | null;
+}
+
+interface RoomHealth {
+  score: number;
+  peers: PeerHealth[];
+  laggards: PeerHealth[];
+  relayedCount: number;
+}
+
+const ISSUE_LABELS: Record<NonNullable<PeerHealth['mainIssue']>, [string, string]> = {
+  high_latency: ['延迟偏高', 'high latency'],
+  unreachable: ['不可达', 'unreachable'],
+  packet_loss: ['丢包', 'packet loss'],
+  relay: ['中继连接', 'relayed'],
+};
+
 interface Row {
   name: string;
   ip: string;
@@ -36,6 +82,45 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
   const { players, currentPlayerId } = useAppStore();
   const [loading, setLoading] = useState(false);
   const [rows, setRows] = useState<Row[]>([]);
+  const [portReports, setPortReports] = useState<Record<string, PortReport>>({});
+  const [checkingIp, setCheckingIp] = useState<string | null>(null);
+  const [roomHealth, setRoomHealth] = useState<RoomHealth | null>(null);
+  const [preferredRelay, setPreferredRelay] = useState('');
+  const [savingRelay, setSavingRelay] = useState(false);
+
+  useEffect(() => {
+    if (!visible) return;
+    invoke<{ preferred_relay?: string | null }>('get_config')
+      .then((config) => setPreferredRelay(config.preferred_relay ?? ''))
+      .catch(() => {});
+  }, [visible]);
+
+  const savePreferredRelay = async () => {
+    setSavingRelay(true);
+    try {
+      await invoke('set_preferred_relay', { node: preferredRelay.trim() || null });
+      message.success(tl('已保存，重新进入大厅后生效', 'Saved; takes effect after rejoining the lobby'));
+    } catch (e) {
+      message.error(`${tl('保存失败', 'Save failed')}: ${errorMessage(e)}`);
+    } finally {
+      setSavingRelay(false);
+    }
+  };
+
+  // 逐个端口检查某位成员的连通性
+  const checkPorts = async (ip: string) => {
+    if (checkingIp) return;
+    setCheckingIp(ip);
+    try {
+      const raw = await invoke<string>('diagnose_file_share_connection', { peerIp: ip });
+      const report = JSON.parse(raw) as PortReport;
+      setPortReports(prev => ({ ...prev, [ip]: { ports: report.ports || [], suggestions: report.suggestions || [] } }));
+    } catch (e) {
+      setPortReports(prev => ({ ...prev, [ip]: { ports: [], suggestions: [errorMessage(e)] } }));
+    } finally {
+      setCheckingIp(null);
+    }
+  };
 
   const run = useCallback(async () => {
     if (loading) return;
@@ -45,10 +130,12 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
       const ips = others.map(p => p.virtualIp as string);
       if (ips.length === 0) { setRows([]); return; }
 
-      const [conns, lats] = await Promise.all([
+      const [conns, lats, health] = await Promise.all([
         invoke<PeerConn[]>('get_peer_connection_types').catch(() => [] as PeerConn[]),
         invoke<{ ip: string; latencyMs: number | null; lossRate: number }[]>('measure_peers_latency', { peerIps: ips }).catch(() => []),
+        invoke<RoomHealth>('get_room_health_score').catch(() => null),
       ]);
+      setRoomHealth(health);
       const connMap = new Map(conns.map(c => [c.ip, c]));
       const latMap = new Map(lats.map(l => [l.ip, l]));
 
@@ -84,13 +171,14 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
   const avgLatency = reachable.length > 0 ? Math.round(reachable.reduce((s, r) => s + (r.latency || 0), 0) / reachable.length) : null;
   const maxLoss = rows.reduce((m, r) => Math.max(m, r.loss), 0);
 
-  // 评分（0~100）：基础100，中继/高延迟/丢包/离线扣分
+  // 本地估算（0~100）：基础100，中继/高延迟/丢包/离线扣分；后端评分可用时以后端为准
   let score = 100;
   if (avgLatency != null) { if (avgLatency > 200) score -= 30; else if (avgLatency > 100) score -= 15; }
   score -= relayCount * 12;
   score -= offlineCount * 20;
   score -= Math.min(30, maxLoss);
   score = Math.max(0, Math.min(100, score));
+  if (roomHealth && roomHealth.peers.length > 0) score = roomHealth.score;
 
   const suggestions: string[] = [];
   if (rows.length === 0) {
@@ -100,6 +188,11 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
     if (avgLatency != null && avgLatency > 150) suggestions.push(tl('平均延迟偏高。可尝试更换更近的 EasyTier 节点，或开启「延迟优先」。', 'Average latency is high. Try a closer EasyTier node or enable "Latency first".'));
     if (maxLoss >= 10) suggestions.push(tl('存在明显丢包，建议检查本地网络/WiFi 信号，或改用有线网络。', 'Noticeable packet loss. Check your local network/Wi-Fi or use a wired connection.'));
     if (offlineCount > 0) suggestions.push(tl(`${offlineCount} 位成员暂时不可达，可能对方未就绪或网络受限。`, `${offlineCount} member(s) are unreachable; they may not be ready or are network-restricted.`));
+    if (roomHealth && roomHealth.laggards.length > 0) {
+      const nameOf = (p: PeerHealth) => rows.find(r => r.ip === p.ip)?.name || p.playerName || p.ip;
+      const list = roomHealth.laggards.map(p => `${nameOf(p)}${p.mainIssue ? `(${tl(...ISSUE_LABELS[p.mainIssue])})` : ''}`).join(', ');
+      suggestions.unshift(tl(`主要拖后腿的成员：${list}`, `Members dragging the room down: ${list}`));
+    }
     if (suggestions.length === 0) suggestions.push(tl('连接质量良好，无需调整。', 'Connection quality is good. No action needed.'));
   }
 
@@ -133,7 +226,8 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
 
           <div style={{ display: 'flex', flexDirection: 'column', gap: 8, marginBottom: 14 }}>
             {rows.map((r) => (
-              <div key={r.ip} style={{ display: 'flex', alignItems: 'center', justifyContent: 'space-between', gap: 10, border: '1px solid rgba(255,255,255,0.12)', borderRadius: 8, padding: '8px 12px' }}>
+              <div key={r.ip} style={{ border: '1px solid rgba(255,255,255,0.12)', borderRadius: 8, padding: '8px 12px' }}>
+              <div style={{ display: 'flex', alignItems: 'center', justifyContent: 'space-between', gap: 10 }}>
                 <div style={{ minWidth: 0, flex: 1, fontWeight: 600, whiteSpace: 'nowrap', overflow: 'hidden', textOverflow: 'ellipsis' }}>{r.name}</div>
                 {r.connType === 'p2p' && <Tag color="green">{tl('直连', 'P2P')}</Tag>}
                 {r.connType === 'relay' && <Tag color="gold">{tl('中继', 'Relay')}</Tag>}
@@ -142,10 +236,44 @@ export const ConnectionDiagnosticModal: React.FC<ConnectionDiagnosticModalProps>
                   {r.latency == null ? tl('不可达', 'Offline') : `${r.latency}ms`}
                 </Tag>
                 {r.loss > 0 && <Tag color="volcano">{tl('丢包', 'Loss')} {r.loss}%</Tag>}
+                <Button size="small" onClick={() => void checkPorts(r.ip)} loading={checkingIp === r.ip} disabled={checkingIp !== null && checkingIp !== r.ip}>
+                  {tl('端口检查', 'Check ports')}
+                </Button>
+              </div>
+              {portReports[r.ip] && (
+                <div style={{ marginTop: 8, fontSize: 12, lineHeight: 1.8 }}>
+                  {portReports[r.ip].ports.map((p) => (
+                    <div key={p.service} style={{ color: p.reachable ? '#52c41a' : '#ff4d4f' }}>
+                      {p.reachable ? '✓' : '✗'} {tl(...SERVICE_LABELS[p.service])} {p.protocol.toUpperCase()} {p.port}
+                      <span style={{ color: 'rgba(255,255,255,0.6)' }}>　{p.reachable ? tl('可达', 'Reachable') : p.status === 'closed' ? tl('被拒绝', 'Refused') : tl('无响应', 'No response')}</span>
+                    </div>
+                  ))}
+                  {portReports[r.ip].suggestions.map((s, i) => (
+                    <div key={i} style={{ color: 'rgba(255,255,255,0.8)' }}>· {s}</div>
+                  ))}
+                </div>
+              )}
               </div>
             ))}
           </div>
 
+          {relayCount > 0 && (
+            <div style={{ marginBottom: 14 }}>
+              <div style={{ fontSize: 12, color: 'rgba(255,255,255,0.8)', marginBottom: 6 }}>
+                {tl('偏好中继节点：跨国联机时指定一个两地延迟都低的节点（房间成员设置同一节点效果最好）', 'Preferred relay: for cross-region play, pick a node with low latency for everyone (works best when all members use the same node)')}
+              </div>
+              <div style={{ display: 'flex', gap: 8 }}>
+                <Input
+                  size="small"
+                  value={preferredRelay}
+                  onChange={(e) => setPreferredRelay(e.target.value)}
+                  placeholder="tcp://relay.example.com:11010"
+                />
+                <Button size="small" onClick={() => void savePreferredRelay()} loading={savingRelay}>{tl('保存', 'Save')}</Button>
+              </div>
+            </div>
+          )}
+
           <div style={{ background: 'rgba(124,207,0,0.08)', border: '1px solid rgba(124,207,0,0.25)', borderRadius: 8, padding: '10px 12px' }}>
             <div style={{ fontWeight: 600, fontSize: 13, marginBottom: 6, color: '#7ccf00' }}>{tl('优化建议', 'Suggestions')}</div>
             {suggestions.map((s, i) => (
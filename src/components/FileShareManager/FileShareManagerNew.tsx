@@ -11,9 +11,12 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../../stores/appStore';
 import type { SharedFolder, FileInfo } from '../../types/fileShare';
+import type { CommandError } from '../../types';
 import { FolderIcon, DownloadIcon, ShareIcon, CloseIcon, BackIcon, TrashIcon } from '../icons';
+import { FileTypeIcon } from './FileTypeIcon';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage, isCommandError } from '../../services/error/commandError';
 import './FileShareManager.css';
 
 // 简化的远程共享类型
@@ -29,7 +32,7 @@ interface DownloadTask {
   fileName: string;
   fileSize: number;
   downloaded: number;
-  status: 'downloading' | 'completed' | 'failed';
+  status: 'downloading' | 'paused' | 'completed' | 'failed';
   url: string;
   savePath: string;
   headers?: HeadersInit;
@@ -55,6 +58,7 @@ export const FileShareManagerNew: React.FC = () => {
   // 文件浏览状态
   const [selectedShare, setSelectedShare] = useState<SimpleRemoteShare | null>(null);
   const [currentPath, setCurrentPath] = useState('');
+  const [uploading, setUploading] = useState(false);
   const [files, setFiles] = useState<FileInfo[]>([]);
   const [loadingFiles, setLoadingFiles] = useState(false);
   const [selectedFiles, setSelectedFiles] = useState<Set<string>>(new Set());
@@ -113,46 +117,41 @@ export const FileShareManagerNew: React.FC = () => {
     const allShares: SimpleRemoteShare[] = [];
     const now = Math.floor(Date.now() / 1000);
     
-    // 1. 加载自己的共享
+    // 自己和其他玩家的共享一次并发查询，单个玩家失败不影响其他玩家
+    const owners: { name: string; ip: string }[] = [];
     if (lobby?.virtualIp) {
-      try {
-        const shares = await invoke<SharedFolder[]>('get_remote_shares', { peerIp: lobby.virtualIp });
-        
-        shares.forEach(share => {
-          // 过滤掉过期的共享
-          if (!share.expire_time || share.expire_time > now) {
-            allShares.push({
-              share,
-              ownerName: `${config.playerName || '我'} (我)`,
-              ownerIp: lobby.virtualIp!
-            });
-          }
-        });
-      } catch (error) {
-        console.error('获取自己的共享失败:', error);
-      }
+      owners.push({ name: `${config.playerName || '我'} (我)`, ip: lobby.virtualIp });
     }
-    
-    // 2. 加载其他玩家的共享
     for (const player of players) {
       if (player.virtualIp) {
-        try {
-          const shares = await invoke<SharedFolder[]>('get_remote_shares', { peerIp: player.virtualIp });
-          
-          shares.forEach(share => {
-            // 过滤掉过期的共享
-            if (!share.expire_time || share.expire_time > now) {
-              allShares.push({
-                share,
-                ownerName: player.name,
-                ownerIp: player.virtualIp!
-              });
-            }
+        owners.push({ name: player.name, ip: player.virtualIp });
+      }
+    }
+
+    let results: Record<string, { Ok: SharedFolder[] } | { Err: CommandError }> = {};
+    try {
+      results = await invoke('get_all_remote_shares', { peerIps: owners.map(o => o.ip) });
+    } catch (error) {
+      console.error('获取共享列表失败:', error);
+    }
+
+    for (const owner of owners) {
+      const result = results[owner.ip];
+      if (!result) continue;
+      if ('Err' in result) {
+        console.error(`获取 ${owner.name} 的共享失败:`, result.Err.message);
+        continue;
+      }
+      result.Ok.forEach(share => {
+        // 过滤掉过期的共享
+        if (!share.expires_at || share.expires_at > now) {
+          allShares.push({
+            share,
+            ownerName: owner.name,
+            ownerIp: owner.ip
           });
-        } catch (error) {
-          console.error(`获取 ${player.name} 的共享失败:`, error);
         }
-      }
+      });
     }
     
     // 检查当前正在浏览的共享是否还存在
@@ -179,6 +178,17 @@ export const FileShareManagerNew: React.FC = () => {
     loadLocalShares();
   }, []);
 
+  // 后端清理过期共享后刷新本地共享列表
+  useEffect(() => {
+    const unlistenPromise = listen<{ removedShareIds: string[] }>('shares-updated', (event) => {
+      console.log(`⏰ [FileShareManager] ${event.payload.removedShareIds.length} 个共享已过期，刷新列表`);
+      loadLocalShares();
+    });
+    return () => {
+      unlistenPromise.then(unlisten => unlisten()).catch(() => {});
+    };
+  }, []);
+
   // 【事件驱动】监听文件共享事件
   useEffect(() => {
     console.log('📡 [FileShareManager] 设置文件共享事件监听器');
@@ -201,8 +211,8 @@ export const FileShareManagerNew: React.FC = () => {
           id: shareId,
           name: shareName,
           path: '',
-          password: hasPassword ? 'protected' : undefined,
-          expire_time: undefined,
+          has_password: hasPassword,
+          expires_at: undefined,
           compress_before_send: false,
           owner_id: playerId,
           created_at: Date.now() / 1000,
@@ -275,7 +285,7 @@ export const FileShareManagerNew: React.FC = () => {
       const expiryCheckInterval = setInterval(() => {
         const now = Math.floor(Date.now() / 1000);
         setRemoteShares(prev => {
-          const filtered = prev.filter(s => !s.share.expire_time || s.share.expire_time > now);
+          const filtered = prev.filter(s => !s.share.expires_at || s.share.expires_at > now);
           
           // 如果有共享被过滤掉，说明有过期的
           if (filtered.length < prev.length) {
@@ -345,7 +355,7 @@ export const FileShareManagerNew: React.FC = () => {
   const handleBrowseShare = async (remoteShare: SimpleRemoteShare) => {
     pendingBrowsePathRef.current = '';
 
-    if (remoteShare.share.password) {
+    if (remoteShare.share.has_password) {
       setPendingShare(remoteShare);
       setShowPasswordModal(true);
       return;
@@ -359,7 +369,7 @@ export const FileShareManagerNew: React.FC = () => {
       const targetPath = pendingBrowsePathRef.current || '';
       let verifiedPassword: string | undefined;
 
-      if (remoteShare.share.password) {
+      if (remoteShare.share.has_password) {
         const passwordToVerify = password ?? sharePasswordMap[getShareKey(remoteShare.ownerIp, remoteShare.share.id)] ?? '';
         const valid = await invoke<boolean>('verify_share_password', {
           peerIp: remoteShare.ownerIp,
@@ -394,20 +404,23 @@ export const FileShareManagerNew: React.FC = () => {
   const loadFiles = async (remoteShare: SimpleRemoteShare, path: string, passwordOverride?: string) => {
     setLoadingFiles(true);
     try {
-      const response = await fetch(
-        `http://${remoteShare.ownerIp}:14539/api/shares/${remoteShare.share.id}/files${path ? `?path=${encodeURIComponent(path)}` : ''}`,
-        {
-          headers: getSharePasswordHeader(remoteShare.ownerIp, remoteShare.share.id, passwordOverride),
-        }
-      );
-
-      if (!response.ok) {
-        if (response.status === 401) {
+      // 通过后端请求：启用 HTTPS 时由后端按大厅证书指纹校验对方身份
+      const shareKey = getShareKey(remoteShare.ownerIp, remoteShare.share.id);
+      let fileList: FileInfo[];
+      try {
+        fileList = await invoke<FileInfo[]>('get_remote_files', {
+          peerIp: remoteShare.ownerIp,
+          shareId: remoteShare.share.id,
+          path: path || null,
+          password: passwordOverride ?? sharePasswordMap[shareKey] ?? null,
+        });
+      } catch (error) {
+        if (isCommandError(error, 'permission')) {
           const retryPath = path;
           message.error(tl('访问被拒绝，请重新输入密码', 'Access denied, please re-enter the password'));
           setSharePasswordMap(prev => {
             const next = { ...prev };
-            delete next[getShareKey(remoteShare.ownerIp, remoteShare.share.id)];
+            delete next[shareKey];
             return next;
           });
 
@@ -417,19 +430,14 @@ export const FileShareManagerNew: React.FC = () => {
           setPasswordInput('');
           return;
         }
-        throw new Error(`HTTP ${response.status}`);
+        throw error;
       }
 
-      const payload = await response.json();
-      const fileList = (payload?.files ?? []) as FileInfo[];
       setFiles(fileList);
       setCurrentPath(path);
       setSelectedFiles(new Set());
-    } catch (error) {
-      const errorMessage = String(error);
-      if (!errorMessage.includes('HTTP 401')) {
-        message.error(tl('加载文件列表失败', 'Failed to load file list'));
-      }
+    } catch {
+      message.error(tl('加载文件列表失败', 'Failed to load file list'));
     } finally {
       setLoadingFiles(false);
     }
@@ -502,12 +510,12 @@ export const FileShareManagerNew: React.FC = () => {
       
       message.success(tl('开始下载文件', 'Download started'));
     } catch (error) {
-      message.error(`${tl('下载失败', 'Download failed')}: ${error}`);
+      message.error(`${tl('下载失败', 'Download failed')}: ${errorMessage(error)}`);
     }
   };
 
   // 实际执行下载（流式：由后端边下边写盘，避免大文件占满内存导致卡死/崩溃）
-  const startDownload = async (taskId: string, url: string, savePath: string, fileSize: number, headers?: HeadersInit) => {
+  const startDownload = async (taskId: string, url: string, savePath: string, fileSize: number, headers?: HeadersInit, resume: boolean = false) => {
     try {
       // 从下载 URL 解析 peerIp / shareId / filePath（URL 由本组件构造，格式固定）
       // 形如 http://10.x.x.x:14539/api/shares/{shareId}/download/{filePath}
@@ -533,15 +541,28 @@ export const FileShareManagerNew: React.FC = () => {
         task.id === taskId ? { ...task, lastUpdateTime: Date.now(), lastDownloaded: 0 } : task
       ));
 
-      // 调用后端流式下载命令（边下边写盘 + 进度事件 + 可取消）
-      await invoke('download_remote_file', {
-        taskId,
-        peerIp,
-        shareId,
-        filePath,
-        savePath,
-        password,
-      });
+      // 调用后端流式下载命令（边下边写盘 + 进度事件 + 可取消/暂停）；恢复时从断点续传
+      if (resume) {
+        await invoke('resume_download', { id: taskId, password });
+      } else if (dlIdx < 0 && rest.endsWith('/download-all')) {
+        // 整个共享打包下载：形如 /api/shares/{shareId}/download-all
+        await invoke('download_share_as_zip', {
+          taskId,
+          peerIp,
+          shareId: rest.substring(0, rest.length - '/download-all'.length),
+          savePath,
+          password,
+        });
+      } else {
+        await invoke('download_remote_file', {
+          taskId,
+          peerIp,
+          shareId,
+          filePath,
+          savePath,
+          password,
+        });
+      }
 
       // 完成
       setDownloads(prev => prev.map(task =>
@@ -549,12 +570,19 @@ export const FileShareManagerNew: React.FC = () => {
       ));
       message.success(tl('下载完成', 'Download complete'));
     } catch (error: any) {
-      const errStr = String(error);
+      const errStr = errorMessage(error);
       // 用户主动取消不视为失败
       if (errStr.includes('已取消')) {
         console.log('❌ [FileShareManager] 下载被取消:', taskId);
         return;
       }
+      // 暂停：保留已下载部分，等待恢复
+      if (errStr.includes('已暂停')) {
+        setDownloads(prev => prev.map(task =>
+          task.id === taskId ? { ...task, status: 'paused' as const, speed: 0 } : task
+        ));
+        return;
+      }
       setDownloads(prev => prev.map(task =>
         task.id === taskId ? { ...task, status: 'failed' as const, error: errStr, speed: 0 } : task
       ));
@@ -662,14 +690,14 @@ export const FileShareManagerNew: React.FC = () => {
             
             // 更新任务状态为失败
             setDownloads(prev => prev.map(task =>
-              task.id === taskId ? { ...task, status: 'failed' as const, error: String(error), speed: 0 } : task
+              task.id === taskId ? { ...task, status: 'failed' as const, error: errorMessage(error), speed: 0 } : task
             ));
-            message.error(`${tl('下载失败', 'Download failed')}: ${error}`);
+            message.error(`${tl('下载失败', 'Download failed')}: ${errorMessage(error)}`);
           }
         })();
       } catch (error) {
         console.error('❌ [FileShareManager] 批量下载失败:', error);
-        message.error(`${tl('批量下载失败', 'Batch download failed')}: ${error}`);
+        message.error(`${tl('批量下载失败', 'Batch download failed')}: ${errorMessage(error)}`);
       }
     } else if (!selectedShare.share.compress_before_send && selectedFileList.length > 1) {
       // 【修复】如果没有启用"先压后发"，提示用户
@@ -730,12 +758,63 @@ export const FileShareManagerNew: React.FC = () => {
     }
   };
 
+  // 把整个共享文件夹打包为一个 ZIP 下载（共享端流式打包，支持暂停与续传）
+  const handleDownloadAll = async () => {
+    if (!selectedShare) return;
+    const saveDir = await invoke<string | null>('select_folder');
+    if (!saveDir) return;
+
+    const zipName = `${selectedShare.share.name}.zip`;
+    const savePath = `${saveDir}/${zipName}`;
+    const downloadUrl = `http://${selectedShare.ownerIp}:14539/api/shares/${selectedShare.share.id}/download-all`;
+    const downloadHeaders = getSharePasswordHeader(selectedShare.ownerIp, selectedShare.share.id);
+    const taskId = `download_all_${Date.now()}_${Math.random()}`;
+    const newTask: DownloadTask = {
+      id: taskId,
+      fileName: zipName,
+      fileSize: 0, // 由进度事件补上总大小
+      downloaded: 0,
+      status: 'downloading',
+      url: downloadUrl,
+      headers: downloadHeaders,
+      savePath
+    };
+
+    setDownloads(prev => [...prev, newTask]);
+    startDownload(taskId, downloadUrl, savePath, 0, downloadHeaders);
+    message.success(tl('开始打包下载整个共享', 'Downloading the whole share as ZIP'));
+  };
+
   // 进入文件夹（修复路径拼接问题）
   const handleEnterFolder = async (folder: FileInfo) => {
     if (!selectedShare || !folder.is_dir) return;
-    // 修复：folder.name 是文件夹名称，需要拼接到当前路径
-    const newPath = currentPath ? `${currentPath}/${folder.name}` : folder.name;
-    await loadFiles(selectedShare, newPath);
+    // folder.path 是相对共享根目录的完整路径（展示用的 name 可能被截断）
+    await loadFiles(selectedShare, folder.path);
+  };
+
+  // 上传文件到可写共享的当前目录
+  const handleUploadFile = async () => {
+    if (!selectedShare) return;
+    const localPath = await invoke<string | null>('select_upload_file');
+    if (!localPath) return;
+    const fileName = localPath.split(/[\\/]/).pop() || localPath;
+    const remotePath = currentPath ? `${currentPath}/${fileName}` : fileName;
+    setUploading(true);
+    try {
+      await invoke('upload_file_to_peer', {
+        peerIp: selectedShare.ownerIp,
+        shareId: selectedShare.share.id,
+        localPath,
+        remotePath,
+        password: sharePasswordMap[getShareKey(selectedShare.ownerIp, selectedShare.share.id)] ?? null,
+      });
+      message.success(tl(`已上传「${fileName}」`, `Uploaded "${fileName}"`));
+      await loadFiles(selectedShare, currentPath);
+    } catch (error) {
+      message.error(`${tl('上传失败', 'Upload failed')}: ${errorMessage(error)}`);
+    } finally {
+      setUploading(false);
+    }
   };
 
   // 返回上级
@@ -840,6 +919,23 @@ export const FileShareManagerNew: React.FC = () => {
     message.success(tl('已取消下载', 'Download canceled'));
   };
 
+  // 暂停下载（后端保留已下载部分）
+  const handlePauseDownload = async (taskId: string) => {
+    try {
+      await invoke('pause_download', { id: taskId });
+    } catch (error) {
+      message.error(`${tl('暂停失败', 'Pause failed')}: ${errorMessage(error)}`);
+    }
+  };
+
+  // 从断点恢复下载
+  const handleResumeDownload = (task: DownloadTask) => {
+    setDownloads(prev => prev.map(t =>
+      t.id === task.id ? { ...t, status: 'downloading' as const, error: undefined } : t
+    ));
+    startDownload(task.id, task.url, task.savePath, task.fileSize, task.headers, true);
+  };
+
   // 打开文件所在文件夹
   const handleOpenFileLocation = async (task: DownloadTask) => {
     try {
@@ -851,7 +947,7 @@ export const FileShareManagerNew: React.FC = () => {
         await invoke('open_file_location', { path: task.savePath });
       }
     } catch (error) {
-      message.error(`${tl('打开文件夹失败', 'Failed to open folder')}: ${error}`);
+      message.error(`${tl('打开文件夹失败', 'Failed to open folder')}: ${errorMessage(error)}`);
     }
   };
 
@@ -935,7 +1031,7 @@ export const FileShareManagerNew: React.FC = () => {
                         <FolderIcon size={24} className="share-icon" />
                         <div className="share-info">
                           <div className="share-name">{share.name}</div>
-                          <div className="share-meta">{share.password && '🔒 '}{share.compress_before_send && '📦 '}{share.expire_time && `⏰ ${formatTime(share.expire_time)}`}</div>
+                          <div className="share-meta">{share.has_password && '🔒 '}{share.compress_before_send && '📦 '}{share.writable && '📤 '}{share.expires_at && `⏰ ${formatTime(share.expires_at)}`}</div>
                         </div>
                         <button className="delete-share-btn" onClick={() => handleDeleteShare(share.id)} title={tl('删除共享', 'Delete share')}><TrashIcon size={16} /></button>
                       </motion.div>
@@ -959,14 +1055,17 @@ export const FileShareManagerNew: React.FC = () => {
                           </div>
                           {/* 右上角状态图标 */}
                           <div className="share-status-icons">
-                            {remoteShare.share.password && (
+                            {remoteShare.share.has_password && (
                               <div className="status-icon lock-icon" title={tl('需要密码', 'Password required')}>🔒</div>
                             )}
                             {remoteShare.share.compress_before_send && (
                               <div className="status-icon compress-icon" title={tl('先压后发', 'Compress before send')}>📦</div>
                             )}
-                            {remoteShare.share.expire_time && (
-                              <div className="status-icon expiry-icon" title={tl(`有效期至 ${new Date(remoteShare.share.expire_time * 1000).toLocaleString()}`, `Expires at ${new Date(remoteShare.share.expire_time * 1000).toLocaleString()}`)}>⏰</div>
+                            {remoteShare.share.writable && (
+                              <div className="status-icon writable-icon" title={tl('允许上传', 'Uploads allowed')}>📤</div>
+                            )}
+                            {remoteShare.share.expires_at && (
+                              <div className="status-icon expiry-icon" title={tl(`有效期至 ${new Date(remoteShare.share.expires_at * 1000).toLocaleString()}`, `Expires at ${new Date(remoteShare.share.expires_at * 1000).toLocaleString()}`)}>⏰</div>
                             )}
                           </div>
                         </motion.div>
@@ -984,6 +1083,10 @@ export const FileShareManagerNew: React.FC = () => {
                           {selectedFiles.size === files.filter(f => !f.is_dir).length && files.filter(f => !f.is_dir).length > 0 ? tl('取消全选', 'Deselect all') : tl('全选', 'Select all')}
                         </Button>
                       </div>
+                      <Button size="small" onClick={handleDownloadAll} icon={<DownloadIcon size={16} />} title={tl('把整个共享打包为 ZIP 下载', 'Download the whole share as a ZIP')}>{tl('全部打包下载', 'Download all')}</Button>
+                      {selectedShare.share.writable && (
+                        <Button size="small" onClick={handleUploadFile} loading={uploading} title={tl('上传文件到当前目录', 'Upload a file to this folder')}>{tl('上传', 'Upload')}</Button>
+                      )}
                       <Button size="small" onClick={handleExitShareBrowser} icon={<CloseIcon size={16} />} title={tl('关闭', 'Close')} style={{ marginLeft: 'auto' }} />
                     </div>
                     <div className="file-list">
@@ -1018,7 +1121,14 @@ export const FileShareManagerNew: React.FC = () => {
                                 }}
                                 onClick={() => file.is_dir && handleEnterFolder(file)}
                               >
-                                {file.is_dir && <FolderIcon size={20} />}
+                                {file.is_dir ? <FolderIcon size={20} /> : selectedShare && (
+                                  <FileTypeIcon
+                                    file={file}
+                                    peerIp={selectedShare.ownerIp}
+                                    shareId={selectedShare.share.id}
+                                    password={sharePasswordMap[getShareKey(selectedShare.ownerIp, selectedShare.share.id)]}
+                                  />
+                                )}
                                 <div style={{ flex: 1, minWidth: 0 }}>
                                   <div className="file-name" style={{ 
                                     overflow: 'hidden', 
@@ -1026,6 +1136,13 @@ export const FileShareManagerNew: React.FC = () => {
                                     whiteSpace: 'nowrap' 
                                   }} title={file.name}>{file.name}</div>
                                   <div className="file-meta">{!file.is_dir && formatSize(file.size)}</div>
+                                  {file.note && (
+                                    <div className="file-meta" title={file.note} style={{
+                                      overflow: 'hidden',
+                                      textOverflow: 'ellipsis',
+                                      whiteSpace: 'nowrap'
+                                    }}>📝 {file.note}</div>
+                                  )}
                                 </div>
                               </div>
                               {!file.is_dir && (
@@ -1125,7 +1242,7 @@ export const FileShareManagerNew: React.FC = () => {
                 <div className="transfer-list">
                   {(() => {
                     const filteredDownloads = transferSubTab === 'downloading'
-                      ? downloads.filter(d => d.status === 'downloading' || d.status === 'failed')
+                      ? downloads.filter(d => d.status === 'downloading' || d.status === 'paused' || d.status === 'failed')
                       : downloads.filter(d => d.status === 'completed');
                     
                     if (filteredDownloads.length === 0) {
@@ -1159,6 +1276,23 @@ export const FileShareManagerNew: React.FC = () => {
                                 <CloseIcon size={12} />
                               </button>
                             )}
+                            {/* 暂停/恢复按钮（批量打包下载不支持续传） */}
+                            {!task.isBatchDownload && (task.status === 'downloading' || task.status === 'paused') && (
+                              <button
+                                className="transfer-pause-btn"
+                                onClick={(e) => {
+                                  e.stopPropagation();
+                                  if (task.status === 'paused') {
+                                    handleResumeDownload(task);
+                                  } else {
+                                    handlePauseDownload(task.id);
+                                  }
+                                }}
+                                title={task.status === 'paused' ? tl('继续下载', 'Resume download') : tl('暂停下载', 'Pause download')}
+                              >
+                                {task.status === 'paused' ? '▶' : '⏸'}
+                              </button>
+                            )}
                             
                             <div className="transfer-info">
                               <div className="transfer-name" title={task.fileName}>{task.fileName}</div>
@@ -1166,7 +1300,7 @@ export const FileShareManagerNew: React.FC = () => {
                                 <Progress 
                                   percent={Math.round((task.downloaded / task.fileSize) * 100)} 
                                   size="small" 
-                                  status={task.status === 'failed' ? 'exception' : task.status === 'completed' ? 'success' : 'active'}
+                                  status={task.status === 'failed' ? 'exception' : task.status === 'completed' ? 'success' : task.status === 'paused' ? 'normal' : 'active'}
                                   strokeColor={task.status === 'completed' ? '#52c41a' : undefined}
                                 />
                               </div>
@@ -1174,6 +1308,7 @@ export const FileShareManagerNew: React.FC = () => {
                                 {formatSize(task.downloaded)} / {formatSize(task.fileSize)}
                                 {task.status === 'downloading' && task.speed && ` - ${formatSpeed(task.speed)}`}
                                 {task.status === 'downloading' && !task.speed && ' - 下载中'}
+                                {task.status === 'paused' && ' - 已暂停'}
                                 {task.status === 'completed' && ' - 已完成'}
                                 {task.status === 'failed' && ` - 失败: ${task.error}`}
                               </div>
@@ -1218,6 +1353,7 @@ const AddShareDialog: React.FC<AddShareDialogProps> = ({ visible, onClose, onSuc
   const [expiryHours, setExpiryHours] = useState(0);
   const [expiryMinutes, setExpiryMinutes] = useState(0);
   const [compressBeforeSend, setCompressBeforeSend] = useState(false);
+  const [writable, setWritable] = useState(false);
   const [loading, setLoading] = useState(false);
 
   const handleSelectFolder = async () => {
@@ -1229,7 +1365,7 @@ const AddShareDialog: React.FC<AddShareDialogProps> = ({ visible, onClose, onSuc
         setFolderName(name || tl('未命名文件夹', 'Unnamed folder'));
       }
     } catch (error) {
-      message.error(`${tl('选择文件夹失败', 'Failed to select folder')}: ${error}`);
+      message.error(`${tl('选择文件夹失败', 'Failed to select folder')}: ${errorMessage(error)}`);
     }
   };
 
@@ -1258,8 +1394,10 @@ const AddShareDialog: React.FC<AddShareDialogProps> = ({ visible, onClose, onSuc
         name: folderName,
         path: folderPath,
         password: hasPassword ? password : undefined,
-        expire_time: expiryTimestamp,
+        has_password: hasPassword,
+        expires_at: expiryTimestamp,
         compress_before_send: compressBeforeSend,
+        writable,
         owner_id: 'local',
         created_at: Math.floor(Date.now() / 1000),
       };
@@ -1287,7 +1425,7 @@ const AddShareDialog: React.FC<AddShareDialogProps> = ({ visible, onClose, onSuc
       message.success(tl('共享文件夹已添加', 'Shared folder added'));
       onSuccess();
     } catch (error) {
-      message.error(`${tl('添加共享失败', 'Failed to add share')}: ${error}`);
+      message.error(`${tl('添加共享失败', 'Failed to add share')}: ${errorMessage(error)}`);
     } finally {
       setLoading(false);
     }
@@ -1335,6 +1473,15 @@ const AddShareDialog: React.FC<AddShareDialogProps> = ({ visible, onClose, onSuc
             {tl('开启后，其他玩家批量下载多个文件时，会先自动打包成ZIP压缩包再下载', 'When enabled, batch downloads of multiple files are packed into a ZIP archive first')}
           </div>
         </div>
+        <div className="form-item">
+          <label>
+            <Switch checked={writable} onChange={setWritable} />
+            <span style={{ marginLeft: 8 }}>{tl('允许其他玩家上传', 'Allow Uploads')}</span>
+          </label>
+          <div style={{ marginTop: 4, fontSize: 12, color: '#888' }}>
+            {tl('开启后，其他玩家可以向该文件夹上传文件（不会覆盖已有文件）', 'When enabled, other players can upload files into this folder (existing files are never overwritten)')}
+          </div>
+        </div>
       </div>
     </Modal>
   );
@@ -0,0 +1,95 @@
+/**
+ * 远程共享文件的类型图标
+ * 图片文件显示缩略图（列表未内联时按需向共享者请求并缓存），其他文件按 MIME 类型显示图标
+ */
+
+import React, { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import type { FileInfo } from '../../types/fileShare';
+
+const THUMBNAIL_MIME_TYPES = ['image/png', 'image/jpeg', 'image/gif', 'image/webp'];
+const ARCHIVE_MIME_TYPES = [
+  'application/zip',
+  'application/x-modrinth-modpack+zip',
+  'application/java-archive',
+  'application/x-7z-compressed',
+  'application/vnd.rar',
+  'application/gzip',
+  'application/x-tar',
+];
+
+// 已获取的缩略图（key: 共享者IP__共享ID__路径），失败记为 null 不再重复请求
+const thumbnailCache = new Map<string, string | null>();
+
+const iconFor = (mime?: string): string => {
+  if (!mime) return '📎';
+  if (mime.startsWith('image/')) return '🖼️';
+  if (mime.startsWith('audio/')) return '🎵';
+  if (mime.startsWith('video/')) return '🎬';
+  if (ARCHIVE_MIME_TYPES.includes(mime)) return '📦';
+  if (mime === 'application/pdf') return '📕';
+  if (mime === 'application/vnd.microsoft.portable-executable') return '⚙️';
+  if (mime.startsWith('text/') || mime === 'application/json' || mime === 'application/toml' || mime === 'application/yaml' || mime === 'application/xml') return '📄';
+  return '📎';
+};
+
+interface FileTypeIconProps {
+  file: FileInfo;
+  peerIp: string;
+  shareId: string;
+  password?: string | null;
+  size?: number;
+}
+
+export const FileTypeIcon: React.FC<FileTypeIconProps> = ({ file, peerIp, shareId, password, size = 20 }) => {
+  const cacheKey = `${peerIp}__${shareId}__${file.path}`;
+  const [thumbnail, setThumbnail] = useState<string | null>(
+    file.thumbnail_base64 ?? thumbnailCache.get(cacheKey) ?? null
+  );
+  const canThumbnail = !!file.mime_type && THUMBNAIL_MIME_TYPES.includes(file.mime_type);
+
+  useEffect(() => {
+    if (file.thumbnail_base64) {
+      thumbnailCache.set(cacheKey, file.thumbnail_base64);
+      setThumbnail(file.thumbnail_base64);
+      return;
+    }
+    if (!canThumbnail || thumbnailCache.has(cacheKey)) {
+      setThumbnail(thumbnailCache.get(cacheKey) ?? null);
+      return;
+    }
+    let cancelled = false;
+    invoke<string>('get_remote_file_thumbnail', {
+      peerIp,
+      shareId,
+      filePath: file.path,
+      password: password ?? null,
+    })
+      .then((data) => {
+        thumbnailCache.set(cacheKey, data);
+        if (!cancelled) setThumbnail(data);
+      })
+      .catch((error) => {
+        console.debug('获取缩略图失败:', file.path, error);
+        thumbnailCache.set(cacheKey, null);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [cacheKey, canThumbnail, file.path, file.thumbnail_base64, password, peerIp, shareId]);
+
+  if (thumbnail) {
+    return (
+      <img
+        src={`data:image/jpeg;base64,${thumbnail}`}
+        alt={file.name}
+        style={{ width: size * 2, height: size * 2, objectFit: 'cover', borderRadius: 4, flexShrink: 0 }}
+      />
+    );
+  }
+  return (
+    <span style={{ fontSize: size * 0.8, width: size, textAlign: 'center', flexShrink: 0 }} title={file.mime_type}>
+      {iconFor(file.mime_type)}
+    </span>
+  );
+};
@@ -1,10 +1,11 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
-import { Form, Input, Button, Select, Space, Typography, Modal, Switch, App as AntdApp } from 'antd';
+import { Form, Input, Button, Select, Space, Typography, Modal, Switch, Progress, App as AntdApp } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { readText } from '@tauri-apps/plugin-clipboard-manager';
 import { useAppStore } from '../../stores';
-import type { Lobby, UserConfig } from '../../types';
+import type { Lobby, LobbyCreateResult, UserConfig } from '../../types';
 import { WarningIcon, StarIcon, DiceIcon } from '../icons';
 import { useEscapeKey } from '../../hooks';
 import { FavoriteLobbyManager, type FavoriteLobby } from '../FavoriteLobbyManager/FavoriteLobbyManager';
@@ -20,11 +21,49 @@ import './LobbyForm.css';
 const { Title } = Typography;
 const { Option } = Select;
 
+/** 创建大厅时的密码强度策略（与后端 PasswordPolicy 一致） */
+type PasswordPolicy = 'strict' | 'relaxed' | 'none';
+
+/** 各策略的最少字符数 */
+const PASSWORD_MIN_CHARS: Record<PasswordPolicy, number> = { strict: 8, relaxed: 4, none: 1 };
+
+/** 密码最多字符数 */
+const PASSWORD_MAX_CHARS = 32;
+
 interface LobbyFormProps {
   mode: 'create' | 'join';
   onClose: () => void;
 }
 
+/** 等待虚拟 IP 期间后端推送的连接进度（easytier-connect-progress） */
+interface ConnectProgress {
+  elapsedSecs: number;
+  timeoutSecs: number;
+  phase: string | null;
+}
+
+/** 连接阶段说明（与后端 ConnectionPhase 的 serde 名称一致） */
+const connectPhaseLabel = (phase: string | null) => {
+  switch (phase) {
+    case 'connecting_server':
+      return tl('正在连接服务器节点…', 'Connecting to server node…');
+    case 'handshaking':
+      return tl('正在握手…', 'Handshaking…');
+    case 'hole_punching':
+      return tl('正在打洞…', 'Hole punching…');
+    case 'relay_fallback':
+      return tl('P2P 直连失败，已回退中继', 'P2P failed, using relay');
+    case 'peer_connected':
+      return tl('已与玩家建立连接', 'Connected to peer');
+    case 'peer_connect_failed':
+      return tl('连接玩家失败，正在重试…', 'Failed to connect to peer, retrying…');
+    case 'ip_assigned':
+      return tl('已获得虚拟 IP', 'Virtual IP assigned');
+    default:
+      return tl('正在启动虚拟网络…', 'Starting virtual network…');
+  }
+};
+
 interface LobbyFormValues {
   lobbyName: string;
   password: string;
@@ -33,6 +72,7 @@ interface LobbyFormValues {
   customEasytierServer?: string;
   customSignalingServer?: string;
   useDomain: boolean;
+  relayMode?: boolean;
 }
 
 // 官方 EasyTier 服务器节点（使用海波节点作为官方中继）
@@ -164,6 +204,7 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
   const { setAppState, setLobby, config } = useAppStore();
   const [form] = Form.useForm<LobbyFormValues>();
   const [loading, setLoading] = useState(false);
+  const [passwordPolicy, setPasswordPolicy] = useState<PasswordPolicy>('strict');
   const [showCustomServer, setShowCustomServer] = useState(config.preferredServer === 'custom');
   const [showFavoritesModal, setShowFavoritesModal] = useState(false);
   const [showRecentModal, setShowRecentModal] = useState(false);
@@ -183,6 +224,9 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
   // 节点延迟测试结果：value -> 延迟(ms) | null(不可达) | 'testing'(测速中)
   const [nodeLatencies, setNodeLatencies] = useState<Record<string, number | null | 'testing'>>({});
   const [testingNodes, setTestingNodes] = useState(false);
+  const [connectProgress, setConnectProgress] = useState<ConnectProgress | null>(null);
+  // 用户主动取消连接时不再弹出失败提示
+  const cancelledRef = useRef(false);
   
   // 滚动提示相关状态
   const scrollContainerRef = useRef<HTMLDivElement>(null);
@@ -196,6 +240,20 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
     }
   });
   
+  // 连接过程中跟随后端推送刷新进度条
+  useEffect(() => {
+    if (!loading) {
+      setConnectProgress(null);
+      return;
+    }
+    const unlisten = listen<ConnectProgress>('easytier-connect-progress', (event) => {
+      setConnectProgress(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [loading]);
+
   // 检查是否可以滚动
   useEffect(() => {
     const checkScroll = () => {
@@ -353,6 +411,8 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
           privateSignalingServer: settings.privateSignalingServer ?? 'wss://mctier.pmhs.top/signaling',
         });
         
+        setPasswordPolicy(settings.lobbyPasswordPolicy ?? 'strict');
+
         // 加载自定义节点
         const nodes = settings.customEasytierNodes || [];
         setCustomNodes(nodes);
@@ -368,6 +428,19 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
     loadPrivateServerConfig();
   }, []);
 
+  // 切换密码强度要求（保存到配置，后续创建大厅都按该要求校验）
+  const handlePasswordPolicyChange = async (policy: PasswordPolicy) => {
+    setPasswordPolicy(policy);
+    try {
+      await invoke('set_lobby_password_policy', { policy });
+    } catch (error) {
+      console.error('保存密码强度要求失败:', error);
+    }
+    if (form.getFieldValue('password')) {
+      void form.validateFields(['password']).catch(() => undefined);
+    }
+  };
+
   // 检测自动大厅配置，自动填充并提交
   useEffect(() => {
     const autoConfig = (window as any).__autoLobbyConfig;
@@ -388,23 +461,38 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
     }, 300);
   }, [form, mode, config.preferredServer]);
   
-  // 检测邀请 deep link 预填（仅填表，不自动提交）：mctier://join?name=&pwd=
+  // 检测邀请 deep link 预填（仅填表，不自动提交）：mctier://join?name=&server=&domain=&pwd=
   useEffect(() => {
     const apply = () => {
       const dl = (window as any).__deepLinkConfig;
       if (!dl) return;
       delete (window as any).__deepLinkConfig;
-      form.setFieldsValue({
+      const fields: Partial<LobbyFormValues> = {
         lobbyName: dl.lobbyName ?? '',
         password: dl.password ?? '',
         playerName: config.playerName || '',
-      });
+        useDomain: dl.useDomain ?? false,
+      };
+      // 链接带有邀请者使用的节点：内置节点直接选中，其他节点以自定义节点填入
+      const node = String(dl.serverNode || '').trim();
+      if (node && !privateServerConfig.usePrivateServer) {
+        if (serverNodes.some((n) => n.value === node)) {
+          fields.serverNode = node;
+          setShowCustomServer(false);
+        } else {
+          fields.serverNode = 'custom';
+          fields.customEasytierServer = node;
+          fields.customSignalingServer = 'wss://mctier.pmhs.top/signaling';
+          setShowCustomServer(true);
+        }
+      }
+      form.setFieldsValue(fields);
     };
     apply();
     const onDeepLink = () => apply();
     window.addEventListener('mctier-deep-link', onDeepLink as EventListener);
     return () => window.removeEventListener('mctier-deep-link', onDeepLink as EventListener);
-  }, [form, config.playerName]);
+  }, [form, config.playerName, serverNodes, privateServerConfig.usePrivateServer]);
 
   // 从剪贴板识别大厅信息的函数
   const recognizeClipboard = async (isAuto = false) => {
@@ -438,8 +526,8 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
         
         console.log('匹配到大厅信息:', { lobbyName, password: password ? '***' : '(空)' });
         
-        // 验证格式是否合理（大厅名称至少4个字符，密码至少8个字符）
-        if (lobbyName.length >= 4 && password.length >= 8) {
+        // 验证格式是否合理（大厅名称至少4个字符，密码非空；密码强度由创建者的策略决定）
+        if (lobbyName.length >= 4 && password.length > 0) {
           form.setFieldsValue({
             lobbyName,
             password,
@@ -544,6 +632,7 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
     // 记录本次实际尝试的节点选择，便于失败时提供「换节点重试」
     const failedNodeValue = overrideNode ?? values.serverNode;
     try {
+      cancelledRef.current = false;
       setLoading(true);
       setAppState('connecting');
 
@@ -659,7 +748,7 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
       });
       
       // 调用后端命令
-      const lobby = await invoke<Lobby>(commandName, {
+      const response = await invoke<Lobby | LobbyCreateResult>(commandName, {
         name: values.lobbyName.trim(),
         password: values.password.trim(),
         playerName: values.playerName.trim(),
@@ -668,8 +757,13 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
         signalingServer: signalingServer,
         useDomain: values.useDomain === true, // 明确转换为布尔值
         virtualDomain: virtualDomain, // 传递虚拟域名
+        networkMode: values.relayMode === true ? 'relay' : 'tun',
       });
       
+      // 创建大厅时额外返回各子服务的启动状态
+      const createResult = mode === 'create' ? (response as LobbyCreateResult) : null;
+      const lobby = createResult ? createResult.lobby : (response as Lobby);
+
       console.log('✅ 后端命令调用成功，返回的大厅信息:', lobby);
 
       // 保存玩家名称到前端store
@@ -734,9 +828,29 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
         mode === 'create' ? tl('大厅创建成功！', 'Lobby created!') : tl('成功加入大厅！', 'Joined the lobby!')
       );
 
+      // 部分子服务启动失败：大厅可用，但提示哪些功能受影响
+      if (createResult) {
+        const serviceNames: Record<string, string> = {
+          network: tl('虚拟网络', 'Network'),
+          file: tl('文件共享', 'File sharing'),
+          chat: tl('聊天', 'Chat'),
+          signaling: tl('玩家信令', 'Signaling'),
+        };
+        Object.entries(createResult.services).forEach(([service, status]) => {
+          if (status.status === 'failed' || status.status === 'degraded') {
+            message.warning(`${serviceNames[service] ?? service}: ${status.reason}`, 6);
+          }
+        });
+      }
+
       // 关闭表单
       onClose();
     } catch (error) {
+      if (cancelledRef.current) {
+        console.log('用户已取消连接:', error);
+        setAppState('idle');
+        return;
+      }
       console.error('操作失败:', error);
       console.error('错误详情:', JSON.stringify(error, null, 2));
       setAppState('error');
@@ -899,17 +1013,18 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
     onClose();
   };
 
-  // 【#4】创建/加入过程中强制手动停止：杀掉 EasyTier 进程，
-  // 后端 create_lobby/join_lobby 会因进程被终止而返回错误，从而解除阻塞
+  // 【#4】创建/加入过程中取消连接：后端等待循环停止 EasyTier 进程并返回，
+  // create_lobby/join_lobby 随即结束，从而解除阻塞
   const [forceStopping, setForceStopping] = useState(false);
   const handleForceStop = async () => {
     if (forceStopping) return;
     setForceStopping(true);
+    cancelledRef.current = true;
     try {
-      message.info(tl('正在强制停止…', 'Force stopping...'));
-      await invoke('cancel_lobby_connecting');
+      message.info(tl('正在取消连接…', 'Cancelling connection...'));
+      await invoke('cancel_connecting');
     } catch (e) {
-      console.warn('强制停止时出错（忽略）:', e);
+      console.warn('取消连接时出错（忽略）:', e);
     } finally {
       setLoading(false);
       setForceStopping(false);
@@ -1069,19 +1184,44 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
             <Form.Item
               label={tl('密码', 'Password')}
               name="password"
+              extra={
+                mode === 'create' ? (
+                  <span style={{ color: 'rgba(255,255,255,0.45)', fontSize: 12 }}>
+                    {tl('密码要求：', 'Password rule: ')}
+                    <Select
+                      size="small"
+                      variant="borderless"
+                      value={passwordPolicy}
+                      disabled={loading}
+                      onChange={(value) => void handlePasswordPolicyChange(value)}
+                      options={[
+                        { value: 'strict', label: tl('严格（字母+数字，8 位以上）', 'Strict (letters + digits, 8+)') },
+                        { value: 'relaxed', label: tl('宽松（4 位以上，可用中文）', 'Relaxed (4+, any characters)') },
+                        { value: 'none', label: tl('不限制', 'None') },
+                      ]}
+                    />
+                  </span>
+                ) : undefined
+              }
               rules={[
                 { required: true, message: tl('请输入密码', 'Please enter a password') },
                 { whitespace: true, message: tl('密码不能为空白字符', 'Password cannot be only whitespace') },
-                { min: 8, max: 32, message: tl('密码长度为 8-32 个字符', 'Password must be 8-32 characters') },
                 {
-                  validator: (_, value) => {
-                    if (!value) return Promise.resolve();
-                    const hasLetter = /[a-zA-Z]/.test(value);
-                    const hasDigit = /[0-9]/.test(value);
-                    if (!hasLetter) {
+                  validator: (_, value: string | undefined) => {
+                    const trimmed = value?.trim() ?? '';
+                    if (!trimmed) return Promise.resolve();
+                    // 按字符计数，中文算 1 个字符；加入时强度由创建者决定，只检查上限
+                    const chars = Array.from(trimmed).length;
+                    const policy: PasswordPolicy = mode === 'create' ? passwordPolicy : 'none';
+                    const min = PASSWORD_MIN_CHARS[policy];
+                    if (chars < min || chars > PASSWORD_MAX_CHARS) {
+                      return Promise.reject(new Error(tl(`密码长度为 ${min}-${PASSWORD_MAX_CHARS} 个字符`, `Password must be ${min}-${PASSWORD_MAX_CHARS} characters`)));
+                    }
+                    if (policy !== 'strict') return Promise.resolve();
+                    if (!/\p{L}/u.test(trimmed)) {
                       return Promise.reject(new Error(tl('密码必须包含至少一个字母', 'Password must contain at least one letter')));
                     }
-                    if (!hasDigit) {
+                    if (!/\p{N}/u.test(trimmed)) {
                       return Promise.reject(new Error(tl('密码必须包含至少一个数字', 'Password must contain at least one digit')));
                     }
                     return Promise.resolve();
@@ -1090,7 +1230,13 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
               ]}
             >
               <Input.Password
-                placeholder={tl('输入密码（至少8个字符，包含字母和数字）', 'Password (min 8 chars, letters and digits)')}
+                placeholder={
+                  mode !== 'create'
+                    ? tl('输入大厅密码', 'Enter the lobby password')
+                    : passwordPolicy === 'strict'
+                      ? tl('输入密码（至少8个字符，包含字母和数字）', 'Password (min 8 chars, letters and digits)')
+                      : tl(`输入密码（至少${PASSWORD_MIN_CHARS[passwordPolicy]}个字符，可用中文）`, `Password (min ${PASSWORD_MIN_CHARS[passwordPolicy]} chars, any characters)`)
+                }
                 size="large"
                 disabled={loading}
                 autoComplete="new-password"
@@ -1236,6 +1382,32 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
               <Switch disabled={loading} />
             </Form.Item>
 
+            <Form.Item
+              label={tl('纯中继模式', 'Relay-only mode')}
+              name="relayMode"
+              valuePropName="checked"
+              tooltip={tl(
+                '不创建虚拟网卡，无需管理员权限；仅转发 Minecraft 端口（25565），加入者需在游戏中直连 127.0.0.1',
+                'No virtual adapter and no admin rights needed; only Minecraft port 25565 is forwarded, joiners connect to 127.0.0.1 in game'
+              )}
+            >
+              <Switch disabled={loading} />
+            </Form.Item>
+
+            {loading && connectProgress && (
+              <div className="lobby-form-connect-progress">
+                <div className="lobby-form-connect-progress-label">
+                  <span>{connectPhaseLabel(connectProgress.phase)}</span>
+                  <span>{connectProgress.elapsedSecs}s / {connectProgress.timeoutSecs}s</span>
+                </div>
+                <Progress
+                  percent={Math.min(100, Math.round((connectProgress.elapsedSecs / Math.max(1, connectProgress.timeoutSecs)) * 100))}
+                  showInfo={false}
+                  size="small"
+                />
+              </div>
+            )}
+
             <Form.Item className="lobby-form-actions">
               <Space size="middle" style={{ width: '100%' }}>
                 <motion.div
@@ -1250,7 +1422,7 @@ export const LobbyForm: React.FC<LobbyFormProps> = ({ mode, onClose }) => {
                     loading={forceStopping}
                     block
                   >
-                    {loading ? tl('强制停止', 'Force Stop') : tl('取消', 'Cancel')}
+                    {loading ? tl('取消连接', 'Cancel Connecting') : tl('取消', 'Cancel')}
                   </Button>
                 </motion.div>
                 <motion.div
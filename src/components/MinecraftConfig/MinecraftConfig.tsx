@@ -4,6 +4,7 @@ import { Button, Input, Select, message, Space, Typography, Modal } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { CloseIcon } from '../icons';
 import { useEscapeKey } from '../../hooks';
 import './MinecraftConfig.css';
@@ -91,7 +92,7 @@ export const MinecraftConfig: React.FC<MinecraftConfigProps> = ({ onClose }) =>
       });
     } catch (error) {
       console.error('配置失败:', error);
-      message.error(`${tl('配置失败', 'Configuration failed')}: ${error}`);
+      message.error(`${tl('配置失败', 'Configuration failed')}: ${errorMessage(error)}`);
     } finally {
       setConfiguring(false);
     }
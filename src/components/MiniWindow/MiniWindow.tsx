@@ -1,7 +1,7 @@
 ﻿﻿import React, { useState, useEffect } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { invoke } from '@tauri-apps/api/core';
-import { Modal, Spin, Tooltip, App as AntdApp } from 'antd';import { open } from '@tauri-apps/plugin-shell';
+import { Dropdown, Modal, Spin, Tooltip, App as AntdApp } from 'antd';import { open } from '@tauri-apps/plugin-shell';
 import QRCodeLib from 'qrcode';
 import { writeText } from '@tauri-apps/plugin-clipboard-manager';
 import { useAppStore } from '../../stores';
@@ -10,13 +10,16 @@ import { audioService } from '../../services';
 import { p2pChatService } from '../../services/chat/P2PChatService';
 import { speakingDetector } from '../../services/voice/SpeakingDetector';
 import { playerVolumeMemory } from '../../services/voice/playerVolumeMemory';
+import { persistedMutes } from '../../services/voice/persistedMutes';
 import { recentService } from '../../services/recent/recentService';
 import { statsService } from '../../services/stats/statsService';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { versionCheckService } from '../../services/version/VersionCheckService';
 import { listen, emitTo } from '@tauri-apps/api/event';
-import type { ChatMessage } from '../../types';
+import type { ChatMessage, PresenceStatus } from '../../types';
+import { PresenceDot, PRESENCE_STATUSES, presenceLabel } from '../PresenceDot/PresenceDot';
 import { MicIcon, SpeakerIcon, CloseCircleIcon, CollapseIcon, CloseIcon, WarningTriangleIcon, InfoIcon, ScreenShareIcon, CrownIcon } from '../icons';
 import { ChatRoom } from '../ChatRoom/ChatRoom';
 import { FileShareManagerNew } from '../FileShareManager/FileShareManagerNew';
@@ -150,6 +153,7 @@ async function buildInvitePoster(name: string, pwd: string): Promise<HTMLCanvasE
 
 export const MiniWindow: React.FC = () => {
   useTranslation(); // 订阅语言切换，配合 tl 双语取词在切换时重渲染
+  const displaySuffixes = useAppStore((state) => state.displaySuffixes);
   const {
     lobby,
     players,
@@ -196,6 +200,8 @@ export const MiniWindow: React.FC = () => {
 
   const [collapsed, setCollapsed] = useState(false);
   const [opacity, setOpacity] = useState(config.opacity ?? 0.95);
+  // 后端已设置系统窗口透明度（macOS / Linux）时背景保持不透明，否则用背景 alpha 模拟
+  const [nativeOpacity, setNativeOpacity] = useState(false);
   const [isLeaving, setIsLeaving] = useState(false);
   const [showConnectionHelp, setShowConnectionHelp] = useState(false);
   const [currentView, setCurrentView] = useState<'lobby' | 'chat' | 'fileShare' | 'screenShare'>('lobby');
@@ -407,8 +413,9 @@ export const MiniWindow: React.FC = () => {
         setOpacity(initialOpacity);
         
         // 设置窗口透明度
-        await invoke('set_window_opacity', { opacity: initialOpacity });
-        console.log('进入大厅，透明度已设置为:', initialOpacity);
+        const mode = await invoke<'native' | 'css'>('set_window_opacity', { opacity: initialOpacity });
+        setNativeOpacity(mode === 'native');
+        console.log('进入大厅，透明度已设置为:', initialOpacity, mode);
       } catch (error) {
         console.error('设置透明度失败:', error);
       }
@@ -459,6 +466,12 @@ export const MiniWindow: React.FC = () => {
     // 设置消息接收回调（只设置一次）
     p2pChatService.onMessage((message) => {
       console.log('📨 [MiniWindow] 收到P2P消息:', message);
+
+      // 系统消息只进聊天记录，不弹幕、不响提示音
+      if (message.type === 'system') {
+        addChatMessage(message);
+        return;
+      }
       
       // 查找发送者名称
       let senderName = tl('未知玩家', 'Unknown player');
@@ -577,6 +590,20 @@ export const MiniWindow: React.FC = () => {
     } catch (e) {
       console.warn('恢复记忆音量失败（忽略）:', e);
     }
+
+    // 恢复跨会话记住的静音（仅对尚未静音的玩家）
+    persistedMutes.load().then((mutes) => {
+      const st = useAppStore.getState();
+      players.forEach((p) => {
+        if (p.id === currentPlayerId || st.mutedPlayers.has(p.id)) return;
+        if (persistedMutes.matches(mutes, p.id, p.name)) {
+          console.log('🔇 恢复记住的静音:', p.name);
+          st.mutePlayer(p.id);
+        }
+      });
+    }).catch((e) => {
+      console.warn('恢复静音记录失败（忽略）:', e);
+    });
     console.log('✅ [MiniWindow] P2P聊天服务已更新连接');
   }, [players.length, lobby?.virtualIp, currentPlayerId]);
 
@@ -838,8 +865,9 @@ export const MiniWindow: React.FC = () => {
     
     try {
       // 调用后端设置真实的窗口透明度
-      await invoke('set_window_opacity', { opacity: newOpacity });
-      console.log('窗口透明度已更改为:', newOpacity);
+      const mode = await invoke<'native' | 'css'>('set_window_opacity', { opacity: newOpacity });
+      setNativeOpacity(mode === 'native');
+      console.log('窗口透明度已更改为:', newOpacity, mode);
       
       // 保存透明度到配置文件
       await invoke('save_opacity', { opacity: newOpacity });
@@ -953,7 +981,7 @@ export const MiniWindow: React.FC = () => {
       // 关闭加载提示
       setIsRejoining(false);
       
-      message.error(tl(`重新加入大厅失败: ${error}`, `Failed to rejoin the lobby: ${error}`));
+      message.error(tl(`重新加入大厅失败: ${errorMessage(error)}`, `Failed to rejoin the lobby: ${errorMessage(error)}`));
       
       // 如果失败，返回主界面
       const { setAppState, clearLobby } = useAppStore.getState();
@@ -1126,6 +1154,17 @@ export const MiniWindow: React.FC = () => {
   };
 
   // 复制虚拟IP或虚拟域名
+  // 手动设置在线状态（设为在线即恢复自动检测游戏中 / 离开）
+  const handleSetPresence = async (status: PresenceStatus) => {
+    try {
+      const effective = await invoke<PresenceStatus>('set_presence', { status });
+      if (currentPlayerId) useAppStore.getState().setPlayerPresence(currentPlayerId, effective);
+    } catch (error) {
+      console.error('设置在线状态失败:', error);
+      message.error(tl('设置在线状态失败', 'Failed to set status'));
+    }
+  };
+
   const handleCopyVirtualIp = async () => {
     if (!lobby) return;
     
@@ -1463,7 +1502,7 @@ Password: ${lobby.password || ''}
             key="lobby"
             className={`mini-window ${collapsed ? 'collapsed' : ''}`}
             style={{
-              background: `rgba(20, 20, 30, ${opacity})` // 动态设置背景透明度
+              background: `rgba(20, 20, 30, ${nativeOpacity ? 1 : opacity})` // 动态设置背景透明度
             }}
             initial={{ opacity: 1 }}
             animate={{ opacity: 1 }}
@@ -1670,11 +1709,20 @@ Password: ${lobby.password || ''}
                     }}
                   >
                     <div className="mini-player-info">
-                      <div className={`player-avatar ${currentPlayerId && speakingPlayers.has(currentPlayerId) ? 'speaking' : ''}`}>
-                        <span className="mini-player-initial">
-                          {Array.from((useAppStore.getState().config.playerName || tl('我', 'Me')).trim())[0] || tl('我', 'Me')}
-                        </span>
-                      </div>
+                      <Dropdown
+                        trigger={['click']}
+                        menu={{
+                          items: PRESENCE_STATUSES.map((status) => ({ key: status, label: presenceLabel(status) })),
+                          onClick: ({ key }) => void handleSetPresence(key as PresenceStatus),
+                        }}
+                      >
+                        <div className={`player-avatar ${currentPlayerId && speakingPlayers.has(currentPlayerId) ? 'speaking' : ''}`} style={{ cursor: 'pointer' }}>
+                          <span className="mini-player-initial">
+                            {Array.from((useAppStore.getState().config.playerName || tl('我', 'Me')).trim())[0] || tl('我', 'Me')}
+                          </span>
+                          <PresenceDot presence={players.find((p) => p.id === currentPlayerId)?.presence} />
+                        </div>
+                      </Dropdown>
                       <div className="player-details">
                         <span className="mini-player-name">
                           {useAppStore.getState().config.playerName || tl('我', 'Me')} ({tl('我', 'Me')})
@@ -1726,6 +1774,7 @@ Password: ${lobby.password || ''}
                             <div className="mini-player-avatar-col">
                               <div className={`player-avatar ${speakingPlayers.has(player.id) && isSameVoiceGroup(player.id) ? 'speaking' : ''}`}>
                                 <span className="mini-player-initial">{Array.from((player.name || '?').trim())[0] || '?'}</span>
+                                <PresenceDot presence={player.presence} />
                               </div>
                               {player.virtualIp && peerConnTypes[player.virtualIp] && (
                                 <span
@@ -1740,6 +1789,7 @@ Password: ${lobby.password || ''}
                             <div className="player-details">
                               <span className="mini-player-name">
                                 {player.name}
+                                {displaySuffixes[player.id] && <span className="player-name-suffix">{displaySuffixes[player.id]}</span>}
                                 {hostId === player.id && (
                                   <CrownIcon size={13} style={{ marginLeft: 4, verticalAlign: 'middle' }} />
                                 )}
@@ -2140,9 +2190,13 @@ Password: ${lobby.password || ''}
               title={tl('复制可一键加入的邀请链接，发给好友在浏览器打开即可加入', 'Copy a one-click invite link; send it to a friend to open in a browser and join')}
               onClick={async () => {
                 if (!lobby) return;
-                const dl = `mctier://join?name=${encodeURIComponent(lobby.name)}&pwd=${encodeURIComponent(lobby.password || '')}`;
-                try { await writeText(dl); message.success(tl('邀请链接已复制，发给好友在浏览器打开即可加入', 'Invite link copied. Send it to a friend to open in a browser and join')); }
-                catch { message.error(tl('复制失败', 'Copy failed')); }
+                try {
+                  const dl = await invoke<string>('generate_invite_link', { includePassword: true });
+                  await writeText(dl);
+                  message.success(tl('邀请链接已复制，发给好友在浏览器打开即可加入', 'Invite link copied. Send it to a friend to open in a browser and join'));
+                } catch {
+                  message.error(tl('复制失败', 'Copy failed'));
+                }
               }}
             >
               <svg width="15" height="15" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" strokeLinecap="round" strokeLinejoin="round">
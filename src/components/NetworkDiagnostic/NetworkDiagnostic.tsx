@@ -4,6 +4,8 @@ import { CheckCircleOutlined, CloseCircleOutlined, LoadingOutlined } from '@ant-
 import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
+import type { NetworkCheckResult } from '../../types';
 import './NetworkDiagnostic.css';
 
 const { Title, Paragraph, Text } = Typography;
@@ -14,6 +16,17 @@ interface NetworkDiagnosticProps {
   virtualIp?: string;
 }
 
+interface PingResult {
+  success: boolean;
+  avgLatencyMs: number | null;
+  packetLoss: number;
+  minMs: number | null;
+  maxMs: number | null;
+}
+
+const formatPing = (ping: PingResult) =>
+  `${tl('平均', 'avg')} ${ping.avgLatencyMs}ms（${ping.minMs}~${ping.maxMs}ms），${tl('丢包', 'loss')} ${ping.packetLoss}%`;
+
 interface DiagnosticResult {
   name: string;
   status: 'success' | 'error' | 'warning' | 'checking';
@@ -21,6 +34,52 @@ interface DiagnosticResult {
   solution?: string;
 }
 
+type DoctorCheck = 'permission' | 'driver' | 'firewall' | 'network' | 'port' | 'services';
+
+interface DoctorStep {
+  check: DoctorCheck;
+  outcome: 'healthy' | 'fixed' | 'needs_manual' | 'check_failed';
+  problem: string | null;
+  detail: string | null;
+}
+
+interface DoctorReport {
+  steps: DoctorStep[];
+  fixed: number;
+  needsManual: number;
+}
+
+const doctorCheckName = (check: DoctorCheck) => {
+  switch (check) {
+    case 'permission': return tl('管理员权限', 'Administrator Permission');
+    case 'driver': return tl('虚拟网卡驱动', 'Virtual Adapter Driver');
+    case 'firewall': return tl('防火墙规则', 'Firewall Rules');
+    case 'network': return tl('虚拟网络连接', 'Virtual Network');
+    case 'port': return tl('端口占用', 'Port Usage');
+    case 'services': return tl('文件 / 聊天服务', 'File / Chat Services');
+  }
+};
+
+// 巡检记录转换为诊断结果条目
+const doctorStepToResult = (step: DoctorStep): DiagnosticResult => {
+  const name = doctorCheckName(step.check);
+  switch (step.outcome) {
+    case 'healthy':
+      return { name, status: 'success', message: tl('✓ 正常', '✓ OK') };
+    case 'fixed':
+      return { name, status: 'success', message: `${tl('✓ 已修复：', '✓ Fixed: ')}${step.problem ?? ''}` };
+    case 'needs_manual':
+      return {
+        name,
+        status: 'error',
+        message: `✗ ${step.problem ?? ''}`,
+        solution: step.detail ?? tl('该问题需要手动处理', 'This issue must be resolved manually'),
+      };
+    case 'check_failed':
+      return { name, status: 'warning', message: `${tl('⚠ 检查失败：', '⚠ Check failed: ')}${step.detail ?? ''}` };
+  }
+};
+
 export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
   visible,
   onClose,
@@ -31,6 +90,7 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
   const [isChecking, setIsChecking] = useState(false);
   const [fixing, setFixing] = useState(false);
   const [isAdmin, setIsAdmin] = useState(true);
+  const [livePing, setLivePing] = useState<PingResult | null>(null);
 
   // 一键添加防火墙放行规则
   const handleAddFirewall = async () => {
@@ -40,8 +100,31 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
       message.success(msg || tl('已添加防火墙放行规则', 'Firewall allow rules added'));
       await runDiagnostic();
     } catch (error) {
-      message.error(`${tl('添加防火墙规则失败', 'Failed to add firewall rules')}：${error}。${tl('请尝试以管理员身份重启后重试', 'Please restart as administrator and retry')}`);
+      message.error(`${tl('添加防火墙规则失败', 'Failed to add firewall rules')}：${errorMessage(error)}。${tl('请尝试以管理员身份重启后重试', 'Please restart as administrator and retry')}`);
+    } finally {
+      setFixing(false);
+    }
+  };
+
+  // 一键巡检：按顺序检查并自动修复可修复的问题
+  const handleAutoDoctor = async () => {
+    setFixing(true);
+    setIsChecking(true);
+    setResults([]);
+    try {
+      const report = await invoke<DoctorReport>('auto_doctor');
+      setResults(report.steps.map(doctorStepToResult));
+      if (report.needsManual > 0) {
+        message.warning(tl(`巡检完成：已修复 ${report.fixed} 项，${report.needsManual} 项需手动处理`, `Check complete: ${report.fixed} fixed, ${report.needsManual} need manual action`));
+      } else if (report.fixed > 0) {
+        message.success(tl(`巡检完成：已修复 ${report.fixed} 项`, `Check complete: ${report.fixed} fixed`));
+      } else {
+        message.success(tl('巡检完成：未发现问题', 'Check complete: no issues found'));
+      }
+    } catch (error) {
+      message.error(`${tl('一键巡检失败', 'Auto check failed')}：${errorMessage(error)}`);
     } finally {
+      setIsChecking(false);
       setFixing(false);
     }
   };
@@ -51,7 +134,7 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
     try {
       await invoke('restart_as_admin');
     } catch (error) {
-      message.error(`${tl('以管理员身份重启失败', 'Failed to restart as administrator')}：${error}`);
+      message.error(`${tl('以管理员身份重启失败', 'Failed to restart as administrator')}：${errorMessage(error)}`);
     }
   };
 
@@ -79,11 +162,12 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
     setResults([...checks]);
 
     try {
-      const hasVirtualAdapter = await invoke<boolean>('check_virtual_adapter');
+      const adapterCheck = await invoke<NetworkCheckResult>('check_virtual_adapter');
+      const hasVirtualAdapter = adapterCheck.success;
       checks[0] = {
         name: tl('虚拟网卡检查', 'Virtual Adapter Check'),
         status: hasVirtualAdapter ? 'success' : 'error',
-        message: hasVirtualAdapter ? tl('✓ 虚拟网卡已创建', '✓ Virtual adapter created') : tl('✗ 虚拟网卡未找到', '✗ Virtual adapter not found'),
+        message: hasVirtualAdapter ? tl('✓ 虚拟网卡已创建', '✓ Virtual adapter created') : `✗ ${adapterCheck.detail}`,
         solution: hasVirtualAdapter
           ? undefined
           : tl('请检查 WinTun 驱动是否正常安装,或尝试重启软件', 'Please check whether the WinTun driver is installed correctly, or try restarting the app'),
@@ -107,11 +191,12 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
     setResults([...checks]);
 
     try {
-      const firewallOk = await invoke<boolean>('check_firewall_rules');
+      const firewallCheck = await invoke<NetworkCheckResult>('check_firewall_rules');
+      const firewallOk = firewallCheck.success;
       checks[1] = {
         name: tl('防火墙规则检查', 'Firewall Rule Check'),
         status: firewallOk ? 'success' : 'warning',
-        message: firewallOk ? tl('✓ 防火墙规则正常', '✓ Firewall rules OK') : tl('⚠ 防火墙可能阻止连接', '⚠ Firewall may be blocking connections'),
+        message: firewallOk ? tl('✓ 防火墙规则正常', '✓ Firewall rules OK') : `⚠ ${firewallCheck.detail}`,
         solution: firewallOk
           ? undefined
           : tl('建议在 Windows 防火墙中允许 Minecraft 和 MCTier 的网络访问', 'Allow network access for Minecraft and MCTier in the Windows Firewall'),
@@ -136,11 +221,14 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
       setResults([...checks]);
 
       try {
-        const canPing = await invoke<boolean>('ping_virtual_ip', { ip: virtualIp });
+        const ping = await invoke<PingResult>('ping_virtual_ip_detailed', { ip: virtualIp });
+        const canPing = ping.success;
         checks[2] = {
           name: tl('网络连通性检查', 'Connectivity Check'),
-          status: canPing ? 'success' : 'error',
-          message: canPing ? tl('✓ 虚拟网络连通正常', '✓ Virtual network connectivity OK') : tl('✗ 无法 ping 通虚拟 IP', '✗ Cannot ping the virtual IP'),
+          status: canPing ? (ping.packetLoss > 0 ? 'warning' : 'success') : 'error',
+          message: canPing
+            ? `${tl('✓ 虚拟网络连通正常', '✓ Virtual network connectivity OK')}：${formatPing(ping)}`
+            : `✗ ${tl(`无法 ping 通 ${virtualIp}`, `Unable to ping ${virtualIp}`)}`,
           solution: canPing ? undefined : tl('虚拟网络可能未正确建立，请尝试重新创建大厅', 'The virtual network may not be established correctly, try recreating the lobby'),
         };
       } catch {
@@ -226,11 +314,38 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
     }
   }, [visible, virtualIp]);
 
+  // 弹窗打开期间持续测量延迟，显示实时数字
+  useEffect(() => {
+    if (!visible || !virtualIp) {
+      setLivePing(null);
+      return;
+    }
+    let cancelled = false;
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const measure = async () => {
+      try {
+        const ping = await invoke<PingResult>('ping_virtual_ip_detailed', { ip: virtualIp });
+        if (!cancelled) setLivePing(ping);
+      } catch {
+        // 忽略单次测量失败
+      }
+      if (!cancelled) timer = setTimeout(() => void measure(), 3000);
+    };
+    void measure();
+    return () => {
+      cancelled = true;
+      if (timer) clearTimeout(timer);
+    };
+  }, [visible, virtualIp]);
+
   return (
     <Modal
       open={visible}
       onCancel={onClose}
       footer={[
+        <Button key="doctor" type="primary" ghost onClick={() => void handleAutoDoctor()} loading={fixing} disabled={isChecking}>
+          {tl('一键巡检修复', 'Auto Check & Fix')}
+        </Button>,
         <Button key="firewall" onClick={() => void handleAddFirewall()} loading={fixing} disabled={isChecking}>
           {tl('一键放行防火墙', 'Allow Firewall')}
         </Button>,
@@ -260,6 +375,15 @@ export const NetworkDiagnostic: React.FC<NetworkDiagnosticProps> = ({
           {tl('正在检查网络配置，帮助您解决 Minecraft 联机问题...', 'Checking network configuration to help resolve multiplayer issues...')}
         </Paragraph>
 
+        {livePing && (
+          <Paragraph className="diagnostic-desc">
+            {tl('实时延迟', 'Live latency')}：
+            {livePing.success
+              ? <Text strong>{formatPing(livePing)}</Text>
+              : <Text type="danger">{tl('不可达', 'Unreachable')}</Text>}
+          </Paragraph>
+        )}
+
         <div className="diagnostic-results">
           {results.map((result, index) => (
             <div key={index} className={`diagnostic-item diagnostic-${result.status}`}>
@@ -17,6 +17,8 @@ import {
 import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
+import type { NetworkCheckResult } from '../../types';
 import './OnboardingWizard.css';
 
 const { Title, Paragraph, Text } = Typography;
@@ -77,7 +79,7 @@ export const OnboardingWizard: React.FC<OnboardingWizardProps> = ({ visible, onC
 
     let firewall: CheckState = 'warn';
     try {
-      firewall = (await invoke<boolean>('check_firewall_rules')) ? 'ok' : 'warn';
+      firewall = (await invoke<NetworkCheckResult>('check_firewall_rules')).success ? 'ok' : 'warn';
     } catch {
       firewall = 'warn';
     }
@@ -107,7 +109,7 @@ export const OnboardingWizard: React.FC<OnboardingWizardProps> = ({ visible, onC
       message.success(msg || tl('已添加防火墙放行规则', 'Firewall rules added'));
       await runChecks();
     } catch (error) {
-      message.error(`${tl('添加防火墙规则失败：', 'Failed to add firewall rules: ')}${error}${tl('。可尝试以管理员身份重启后重试', '. Try restarting as administrator.')}`);
+      message.error(`${tl('添加防火墙规则失败：', 'Failed to add firewall rules: ')}${errorMessage(error)}${tl('。可尝试以管理员身份重启后重试', '. Try restarting as administrator.')}`);
     } finally {
       setFixing(false);
     }
@@ -117,7 +119,7 @@ export const OnboardingWizard: React.FC<OnboardingWizardProps> = ({ visible, onC
     try {
       await invoke('restart_as_admin');
     } catch (error) {
-      message.error(`${tl('以管理员身份重启失败：', 'Failed to restart as administrator: ')}${error}`);
+      message.error(`${tl('以管理员身份重启失败：', 'Failed to restart as administrator: ')}${errorMessage(error)}`);
     }
   };
 
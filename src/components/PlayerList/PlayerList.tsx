@@ -4,6 +4,7 @@ import { Tooltip, Empty, Slider } from 'antd';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
 import { MicrophoneIcon, VolumeIcon } from '../icons';
+import { PresenceDot } from '../PresenceDot/PresenceDot';
 import { useAppStore } from '../../stores';
 import './PlayerList.css';
 
@@ -14,6 +15,7 @@ import './PlayerList.css';
 export const PlayerList: React.FC = () => {
   useTranslation();
   const players = useAppStore((state) => state.players);
+  const displaySuffixes = useAppStore((state) => state.displaySuffixes);
   const togglePlayerMute = useAppStore((state) => state.togglePlayerMute);
   const mutedPlayers = useAppStore((state) => state.mutedPlayers);
   const setPlayerVolume = useAppStore((state) => state.setPlayerVolume);
@@ -101,10 +103,14 @@ export const PlayerList: React.FC = () => {
                 transition={{ delay: index * 0.05 + 0.1, type: 'spring', stiffness: 500 }}
               >
                 <span className="player-initial">{getInitial(player.name)}</span>
+                <PresenceDot presence={player.presence} size={10} />
               </motion.div>
 
               <div className="player-item-info">
-                <div className="player-item-name">{player.name}</div>
+                <div className="player-item-name">
+                  {player.name}
+                  {displaySuffixes[player.id] && <span className="player-name-suffix">{displaySuffixes[player.id]}</span>}
+                </div>
                 <div className="player-item-status">
                   {player.micEnabled ? (
                     <motion.span
@@ -0,0 +1,39 @@
+import React from 'react';
+import { Tooltip } from 'antd';
+import { tl } from '../../i18n';
+import type { PresenceStatus } from '../../types';
+import './PresenceDot.css';
+
+/** 可选的在线状态（顺序即菜单顺序） */
+export const PRESENCE_STATUSES: PresenceStatus[] = ['online', 'away', 'in_game', 'busy'];
+
+/** 在线状态的显示名称 */
+export const presenceLabel = (presence: PresenceStatus): string => {
+  switch (presence) {
+    case 'away':
+      return tl('离开', 'Away');
+    case 'in_game':
+      return tl('游戏中', 'In game');
+    case 'busy':
+      return tl('忙碌', 'Busy');
+    default:
+      return tl('在线', 'Online');
+  }
+};
+
+interface PresenceDotProps {
+  presence?: PresenceStatus;
+  size?: number;
+}
+
+/**
+ * 头像右下角的在线状态点
+ */
+export const PresenceDot: React.FC<PresenceDotProps> = ({ presence = 'online', size = 9 }) => (
+  <Tooltip title={presenceLabel(presence)}>
+    <span
+      className={`presence-dot presence-${presence}`}
+      style={{ width: size, height: size }}
+    />
+  </Tooltip>
+);
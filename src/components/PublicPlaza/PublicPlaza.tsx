@@ -9,6 +9,7 @@ import { Modal, Button, Empty, Spin, Typography, Tag, message } from 'antd';
 import { fetchPublicLobbies, type PublicLobby } from '../../services/lobby/publicLobbies';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 
 const { Text } = Typography;
 
@@ -32,7 +33,7 @@ export const PublicPlaza: React.FC<PublicPlazaProps> = ({ visible, onClose, onJo
       const list = await fetchPublicLobbies(signalingServer);
       setLobbies(list);
     } catch (e) {
-      message.error(tl(`获取公开大厅失败：${e}`, `Failed to fetch public lobbies: ${e}`));
+      message.error(tl(`获取公开大厅失败：${errorMessage(e)}`, `Failed to fetch public lobbies: ${errorMessage(e)}`));
       setLobbies([]);
     } finally {
       setLoading(false);
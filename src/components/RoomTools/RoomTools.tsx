@@ -11,6 +11,7 @@ import React, { useState, useEffect } from 'react';
 import { Modal, Tabs, Button, InputNumber, Select, Input, Space, Typography, Checkbox, message } from 'antd';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { useAppStore } from '../../stores';
 import type { TodoItem } from '../../stores/appStore';
 import { p2pChatService } from '../../services/chat/P2PChatService';
@@ -84,7 +85,7 @@ export const RoomTools: React.FC<RoomToolsProps> = ({ visible, onClose, onOpenWo
         await p2pChatService.sendTextMessage(content);
         message.success(tl('已广播到聊天室', 'Broadcast to chat'));
       } catch (e) {
-        message.error(`${tl('广播失败', 'Broadcast failed')}：${e}`);
+        message.error(`${tl('广播失败', 'Broadcast failed')}：${errorMessage(e)}`);
       } finally {
         setRolling(false);
       }
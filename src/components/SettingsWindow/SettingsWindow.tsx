@@ -1,6 +1,6 @@
 import React, { useState, useEffect, useCallback, useRef } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
-import { Form, Input, Switch, message, Tooltip, App, Slider, Button } from 'antd';
+import { Form, Input, Switch, message, Tooltip, App, Slider, Button, Modal, Select } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
 import { useEscapeKey } from '../../hooks';
 import { RestartConfirmModal } from '../RestartConfirmModal/RestartConfirmModal';
@@ -8,7 +8,9 @@ import { GlobalAdvancedConfigPanel } from '../GlobalAdvancedConfigPanel/GlobalAd
 import { StatsPanel } from '../StatsPanel/StatsPanel';
 import { useTranslation } from 'react-i18next';
 import { setLanguage, getLanguage, tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { audioService, type SoundType } from '../../services/audio/AudioService';
+import { persistedMutes, type PersistedMutes } from '../../services/voice/persistedMutes';
 import { DanmakuSettings } from '../Danmaku/DanmakuSettings';
 import { GameHudSettings } from '../GameHud/GameHudSettings';
 import { VoiceChangerPicker } from '../VoiceChanger/VoiceChangerPicker';
@@ -24,6 +26,7 @@ export const SettingsWindow: React.FC<{ onClose: () => void }> = ({ onClose }) =
   const [alwaysOnTop, setAlwaysOnTop] = useState(true);
   const [rememberWindowPosition, setRememberWindowPosition] = useState(false);
   const [enableGpuRendering, setEnableGpuRendering] = useState(true);
+  const [closeToTray, setCloseToTray] = useState(false);
   const [showRestartModal, setShowRestartModal] = useState(false);
   const [showStats, setShowStats] = useState(false);
   const { t } = useTranslation();
@@ -34,6 +37,23 @@ export const SettingsWindow: React.FC<{ onClose: () => void }> = ({ onClose }) =
 
   useEscapeKey(onClose, true);
 
+  useEffect(() => {
+    invoke<{ close_to_tray?: boolean }>('get_config')
+      .then((config) => setCloseToTray(config.close_to_tray ?? false))
+      .catch((e) => console.error('读取托盘设置失败:', e));
+  }, []);
+
+  // 关闭主窗口时最小化到托盘（后台继续组网与语音，托盘菜单「退出」才真正关闭）
+  const handleCloseToTrayChange = async (v: boolean) => {
+    setCloseToTray(v);
+    try {
+      await invoke('set_close_to_tray', { enabled: v });
+    } catch (error) {
+      setCloseToTray(!v);
+      message.error(`${tl('保存失败', 'Save failed')}: ${errorMessage(error)}`);
+    }
+  };
+
   // 提取加载设置的逻辑为独立函数，方便重用
   const loadSettings = useCallback(async () => {
     // 设置超时保护
@@ -312,6 +332,13 @@ export const SettingsWindow: React.FC<{ onClose: () => void }> = ({ onClose }) =
                   await saveAll({ rememberWindowPosition: v });
                 }} className="settings-switch" />
               </div>
+              <div className="settings-toggle-row">
+                <div className="settings-toggle-info">
+                  <span className="settings-toggle-label">{tl('关闭时最小化到托盘', 'Minimize to Tray on Close')}</span>
+                  <span className="settings-toggle-desc">{tl('关闭窗口后在后台继续运行，可从托盘菜单退出', 'Keep running in the background after closing; exit from the tray menu')}</span>
+                </div>
+                <Switch checked={closeToTray} onChange={handleCloseToTrayChange} className="settings-switch" />
+              </div>
               <div className="settings-toggle-row">
                 <div className="settings-toggle-info">
                   <span className="settings-toggle-label">{tl('启用 GPU 渲染', 'Enable GPU Rendering')}</span>
@@ -583,6 +610,21 @@ export const SettingsWindow: React.FC<{ onClose: () => void }> = ({ onClose }) =
               <VoiceChangerPicker />
             </motion.div>
 
+            <motion.div className="settings-card" variants={itemVariants}>
+              <div className="settings-card-header">
+                <div className="settings-card-icon settings-card-icon-pink">
+                  <svg width="15" height="15" viewBox="0 0 24 24" fill="currentColor">
+                    <path d="M16.5 12A4.5 4.5 0 0 0 14 7.97v2.21l2.45 2.45c.03-.2.05-.41.05-.63zm2.5 0c0 .94-.2 1.82-.54 2.64l1.51 1.51A8.8 8.8 0 0 0 21 12c0-4.28-2.99-7.86-7-8.77v2.06c2.89.86 5 3.54 5 6.71zM4.27 3 3 4.27 7.73 9H3v6h4l5 5v-6.73l4.25 4.25c-.67.52-1.42.93-2.25 1.18v2.06a8.99 8.99 0 0 0 3.69-1.81L19.73 21 21 19.73l-9-9L4.27 3zM12 4 9.91 6.09 12 8.18V4z"/>
+                  </svg>
+                </div>
+                <span className="settings-card-title">{tl('静音记忆', 'Mute Memory')}</span>
+              </div>
+              <div className="settings-card-desc">
+                {tl('被你静音的玩家会被记住，下次加入大厅时自动恢复静音', 'Players you mute are remembered and muted again when you next join a lobby')}
+              </div>
+              <MuteMemorySettings />
+            </motion.div>
+
             <motion.div className="settings-card" variants={itemVariants}>
               <div className="settings-card-header">
                 <div className="settings-card-icon settings-card-icon-yellow">
@@ -819,6 +861,52 @@ const SoundThemeManager: React.FC = () => {
   );
 };
 
+const MuteMemorySettings: React.FC = () => {
+  const { message: antdMessage } = App.useApp();
+  const [mutes, setMutes] = useState<PersistedMutes | null>(null);
+
+  const reload = useCallback(() => {
+    persistedMutes.load().then(setMutes).catch((e) => console.error('加载静音记录失败:', e));
+  }, []);
+
+  useEffect(() => { reload(); }, [reload]);
+
+  const count = mutes ? mutes.player_ids.length + mutes.player_names.length : 0;
+
+  return (
+    <>
+      <div className="settings-toggle-row">
+        <div className="settings-toggle-info">
+          <span className="settings-toggle-label">{tl('按昵称记住静音', 'Remember Mutes by Name')}</span>
+          <span className="settings-toggle-desc">{tl('玩家 ID 每次启动都会变化，开启后按昵称识别被静音的玩家', 'Player IDs change on every launch; identify muted players by nickname instead')}</span>
+        </div>
+        <Switch checked={mutes?.by_name ?? false} disabled={!mutes} onChange={async (v) => {
+          try {
+            await persistedMutes.setByName(v);
+            reload();
+          } catch (e) {
+            antdMessage.error(`${tl('保存失败', 'Save failed')}: ${errorMessage(e)}`);
+          }
+        }} className="settings-switch" />
+      </div>
+      <div className="settings-toggle-row">
+        <div className="settings-toggle-info">
+          <span className="settings-toggle-label">{tl('已记住', 'Remembered')} {count} {tl('条静音记录', 'muted entries')}</span>
+        </div>
+        <Button size="small" danger disabled={count === 0} onClick={async () => {
+          try {
+            await persistedMutes.clear();
+            reload();
+            antdMessage.success(tl('已清空静音记录', 'Mute memory cleared'));
+          } catch (e) {
+            antdMessage.error(`${tl('清空失败', 'Clear failed')}: ${errorMessage(e)}`);
+          }
+        }}>{tl('清空', 'Clear')}</Button>
+      </div>
+    </>
+  );
+};
+
 const CustomNodeManager: React.FC = () => {
   useTranslation();
   const { modal } = App.useApp();
@@ -1146,6 +1234,70 @@ const ConfigManager: React.FC = () => {
   const [exporting, setExporting] = useState(false);
   const [importing, setImporting] = useState(false);
   const [exportingLogs, setExportingLogs] = useState(false);
+  const [checkingHealth, setCheckingHealth] = useState(false);
+  const [syncImportVisible, setSyncImportVisible] = useState(false);
+  const [syncCode, setSyncCode] = useState('');
+  const [logLevel, setLogLevel] = useState('info');
+
+  useEffect(() => {
+    invoke<{ log_level?: string }>('get_config')
+      .then((config) => setLogLevel(config.log_level ?? 'info'))
+      .catch((e) => console.error('读取日志级别失败:', e));
+  }, []);
+
+  // 运行时调整日志级别（排查问题时可临时调到 debug）
+  const handleLogLevelChange = async (level: string) => {
+    const previous = logLevel;
+    setLogLevel(level);
+    try {
+      await invoke('set_log_level', { level });
+    } catch (error) {
+      setLogLevel(previous);
+      message.error(`${tl('设置日志级别失败', 'Failed to set log level')}: ${errorMessage(error)}`);
+    }
+  };
+
+  const handleOpenLogFolder = async () => {
+    try {
+      await invoke('open_log_folder');
+    } catch (error) {
+      message.error(`${tl('打开日志目录失败', 'Failed to open log folder')}: ${errorMessage(error)}`);
+    }
+  };
+
+  // 复制配置码（不含窗口位置、音频设备等本机相关设置），用于在其它设备上同步
+  const handleCopySyncCode = async () => {
+    try {
+      const code = await invoke<string>('export_config_data', { excludeMachineSpecific: true });
+      await navigator.clipboard.writeText(code);
+      message.success(tl('配置码已复制，在另一台设备的设置中粘贴即可同步', 'Config code copied. Paste it in settings on another device to sync'));
+    } catch (error) {
+      console.error('导出配置码失败:', error);
+      message.error(`${tl('导出配置失败', 'Failed to export config')}: ${errorMessage(error)}`);
+    }
+  };
+
+  // 从配置码导入
+  const handleImportSyncCode = async () => {
+    if (!syncCode.trim()) return;
+    try {
+      setImporting(true);
+      const report = await invoke<{ applied: string[]; warnings: string[] }>('import_config_data', { data: syncCode });
+      window.dispatchEvent(new CustomEvent('configImported'));
+      setSyncImportVisible(false);
+      setSyncCode('');
+      if (report.warnings.length > 0) {
+        message.warning(tl(`配置已导入，${report.warnings.length} 项不兼容已忽略`, `Config imported; ${report.warnings.length} incompatible item(s) ignored`));
+      } else {
+        message.success(tl('配置导入成功，设置已更新', 'Config imported successfully, settings updated'));
+      }
+    } catch (error) {
+      console.error('导入配置码失败:', error);
+      message.error(`${tl('导入配置失败', 'Failed to import config')}: ${errorMessage(error)}`);
+    } finally {
+      setImporting(false);
+    }
+  };
 
   // 导出配置
   const handleExport = async () => {
@@ -1172,13 +1324,13 @@ const ConfigManager: React.FC = () => {
         message.success(tl('配置已导出成功', 'Config exported successfully'));
       } catch (error) {
         console.error('导出配置失败:', error);
-        message.error(`${tl('导出配置失败', 'Failed to export config')}: ${error}`);
+        message.error(`${tl('导出配置失败', 'Failed to export config')}: ${errorMessage(error)}`);
       } finally {
         setExporting(false);
       }
     } catch (error) {
       console.error('导出配置失败:', error);
-      message.error(`${tl('导出配置失败', 'Failed to export config')}: ${error}`);
+      message.error(`${tl('导出配置失败', 'Failed to export config')}: ${errorMessage(error)}`);
       setExporting(false);
     }
   };
@@ -1206,13 +1358,13 @@ const ConfigManager: React.FC = () => {
         message.success(tl('配置导入成功，设置已更新', 'Config imported successfully, settings updated'));
       } catch (error) {
         console.error('导入配置失败:', error);
-        message.error(`${tl('导入配置失败', 'Failed to import config')}: ${error}`);
+        message.error(`${tl('导入配置失败', 'Failed to import config')}: ${errorMessage(error)}`);
       } finally {
         setImporting(false);
       }
     } catch (error) {
       console.error('导入配置失败:', error);
-      message.error(`${tl('导入配置失败', 'Failed to import config')}: ${error}`);
+      message.error(`${tl('导入配置失败', 'Failed to import config')}: ${errorMessage(error)}`);
       setImporting(false);
     }
   };
@@ -1231,12 +1383,70 @@ const ConfigManager: React.FC = () => {
       }
     } catch (error) {
       console.error('导出日志失败:', error);
-      message.error(`${tl('导出日志失败', 'Failed to export logs')}: ${error}`);
+      message.error(`${tl('导出日志失败', 'Failed to export logs')}: ${errorMessage(error)}`);
     } finally {
       setExportingLogs(false);
     }
   };
 
+  // 一键诊断：汇总各子服务状态，可复制后附在问题反馈里
+  const handleHealthCheck = async () => {
+    try {
+      setCheckingHealth(true);
+      const report = await invoke<{
+        appState: string;
+        easytierRunning: boolean;
+        virtualIp: string | null;
+        fileServer: string | null;
+        shareCount: number;
+        p2pSignalingPort: number | null;
+        chatServer: string | null;
+        peerCount: number;
+        issues: string[];
+      }>('health_check');
+      const yes = tl('是', 'Yes');
+      const no = tl('否', 'No');
+      const none = tl('未启动', 'Not running');
+      const rows: Array<[string, string]> = [
+        [tl('应用状态', 'App state'), report.appState],
+        [tl('EasyTier 进程', 'EasyTier process'), report.easytierRunning ? yes : no],
+        [tl('虚拟 IP', 'Virtual IP'), report.virtualIp ?? tl('未分配', 'Not assigned')],
+        [tl('文件服务器', 'File server'), report.fileServer ?? none],
+        [tl('P2P 信令端口', 'P2P signaling port'), report.p2pSignalingPort ? String(report.p2pSignalingPort) : none],
+        [tl('聊天服务器', 'Chat server'), report.chatServer ?? none],
+        [tl('发现的成员', 'Discovered peers'), String(report.peerCount)],
+      ];
+      Modal.info({
+        title: report.issues.length === 0 ? tl('诊断完成：各服务正常', 'Diagnosis: all services OK') : tl('诊断发现问题', 'Diagnosis found issues'),
+        width: 480,
+        content: (
+          <div>
+            {rows.map(([label, value]) => (
+              <div key={label}>{label}: {value}</div>
+            ))}
+            {report.issues.map((issue) => (
+              <div key={issue} style={{ color: '#ff7875', marginTop: 6 }}>⚠ {issue}</div>
+            ))}
+          </div>
+        ),
+        okText: tl('复制报告', 'Copy report'),
+        onOk: async () => {
+          try {
+            await navigator.clipboard.writeText(JSON.stringify(report, null, 2));
+            message.success(tl('诊断报告已复制', 'Report copied'));
+          } catch {
+            message.error(tl('复制失败，请重试', 'Copy failed, please retry'));
+          }
+        },
+      });
+    } catch (error) {
+      console.error('健康检查失败:', error);
+      message.error(`${tl('诊断失败', 'Diagnosis failed')}: ${errorMessage(error)}`);
+    } finally {
+      setCheckingHealth(false);
+    }
+  };
+
   return (
     <div className="config-manager">
       <div className="config-manager-buttons">
@@ -1311,7 +1521,87 @@ const ConfigManager: React.FC = () => {
             </>
           )}
         </motion.button>
+        <motion.button
+          className="config-btn config-btn-import"
+          onClick={handleOpenLogFolder}
+          whileHover={{ scale: 1.02 }}
+          whileTap={{ scale: 0.98 }}
+        >
+          <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+            <path d="M22 19a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V5a2 2 0 0 1 2-2h5l2 3h9a2 2 0 0 1 2 2z"></path>
+          </svg>
+          <span>{tl('日志目录', 'Log Folder')}</span>
+        </motion.button>
+        <motion.button
+          className="config-btn config-btn-export"
+          onClick={handleHealthCheck}
+          disabled={checkingHealth}
+          whileHover={{ scale: 1.02 }}
+          whileTap={{ scale: 0.98 }}
+          title={tl('检查 EasyTier、虚拟 IP、文件 / 聊天服务与 P2P 信令的运行状态', 'Check EasyTier, virtual IP, file/chat servers and P2P signaling')}
+        >
+          {checkingHealth ? (
+            <>
+              <span className="config-btn-spinner" />
+              <span>{tl('诊断中...', 'Checking...')}</span>
+            </>
+          ) : (
+            <>
+              <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+                <polyline points="22 12 18 12 15 21 9 3 6 12 2 12"></polyline>
+              </svg>
+              <span>{tl('诊断', 'Diagnose')}</span>
+            </>
+          )}
+        </motion.button>
+        <motion.button
+          className="config-btn config-btn-export"
+          onClick={handleCopySyncCode}
+          whileHover={{ scale: 1.02 }}
+          whileTap={{ scale: 0.98 }}
+          title={tl('复制一串配置码，在另一台设备粘贴即可同步设置（不含窗口位置、音频设备等本机设置）', 'Copy a config code and paste it on another device to sync settings (excludes window position, audio device and other machine-specific settings)')}
+        >
+          <span>{tl('复制配置码', 'Copy Config Code')}</span>
+        </motion.button>
+        <motion.button
+          className="config-btn config-btn-import"
+          onClick={() => setSyncImportVisible(true)}
+          disabled={importing}
+          whileHover={{ scale: 1.02 }}
+          whileTap={{ scale: 0.98 }}
+        >
+          <span>{tl('粘贴配置码', 'Paste Config Code')}</span>
+        </motion.button>
       </div>
+      <div className="settings-toggle-row">
+        <div className="settings-toggle-info">
+          <span className="settings-toggle-label">{tl('日志级别', 'Log Level')}</span>
+          <span className="settings-toggle-desc">{tl('排查问题时可调到 debug，日志文件会自动轮转并限制总大小', 'Switch to debug when troubleshooting; log files rotate automatically with a total size cap')}</span>
+        </div>
+        <Select
+          size="small"
+          value={logLevel}
+          onChange={(v) => void handleLogLevelChange(v)}
+          style={{ width: 100 }}
+          options={['error', 'warn', 'info', 'debug', 'trace'].map((l) => ({ value: l, label: l }))}
+        />
+      </div>
+      <Modal
+        open={syncImportVisible}
+        title={tl('从配置码导入', 'Import from config code')}
+        onCancel={() => setSyncImportVisible(false)}
+        onOk={() => void handleImportSyncCode()}
+        okButtonProps={{ disabled: !syncCode.trim(), loading: importing }}
+        okText={tl('导入', 'Import')}
+        cancelText={tl('取消', 'Cancel')}
+      >
+        <Input.TextArea
+          value={syncCode}
+          onChange={(e) => setSyncCode(e.target.value)}
+          rows={5}
+          placeholder={tl('粘贴在另一台设备上复制的配置码', 'Paste the config code copied on another device')}
+        />
+      </Modal>
     </div>
   );
 };
@@ -2,6 +2,7 @@ import React, { useState, useEffect } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { Button, Tooltip, message, Slider } from 'antd';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
 import { MicrophoneIcon, VolumeIcon } from '../icons';
@@ -26,10 +27,12 @@ export const VoiceControls: React.FC = () => {
   const [muteLoading, setMuteLoading] = useState(false);
   const [volume, setVolume] = useState(100); // 音量百分比 (0-100)
   const [volumeLoading, setVolumeLoading] = useState(true);
+  // 后端推送的麦克风实时电平（0-1），为 null 时指示器退回装饰动画
+  const [micLevel, setMicLevel] = useState<number | null>(null);
   
-  // 使用固定的快捷键提示
-  const micHotkey = 'Ctrl+M';
-  const globalMuteHotkey = 'Ctrl+T';
+  // 快捷键提示跟随配置中的绑定
+  const micHotkey = useAppStore((state) => state.config.micHotkey) ?? 'Ctrl+M';
+  const globalMuteHotkey = useAppStore((state) => state.config.globalMuteHotkey) ?? 'Ctrl+T';
 
   // 加载音量设置
   useEffect(() => {
@@ -49,6 +52,16 @@ export const VoiceControls: React.FC = () => {
     loadVolume();
   }, []);
 
+  // 监听麦克风实时电平
+  useEffect(() => {
+    const unlistenLevel = listen<number>('mic-level', (event) => setMicLevel(event.payload));
+    const unlistenUnavailable = listen('mic-level-unavailable', () => setMicLevel(null));
+    return () => {
+      void unlistenLevel.then((fn) => fn());
+      void unlistenUnavailable.then((fn) => fn());
+    };
+  }, []);
+
   // 同步麦克风状态到WebRTC客户端
   useEffect(() => {
     const syncMicState = async () => {
@@ -191,10 +204,22 @@ export const VoiceControls: React.FC = () => {
               exit={{ opacity: 0, scale: 0 }}
               transition={{ duration: 0.2 }}
             >
-              <div className="activity-bar" />
-              <div className="activity-bar" />
-              <div className="activity-bar" />
-              <div className="activity-bar" />
+              {micLevel === null ? (
+                <>
+                  <div className="activity-bar" />
+                  <div className="activity-bar" />
+                  <div className="activity-bar" />
+                  <div className="activity-bar" />
+                </>
+              ) : (
+                [0.55, 0.8, 1, 0.7].map((weight, index) => (
+                  <div
+                    key={index}
+                    className="activity-bar live"
+                    style={{ height: `${Math.max(10, Math.round(micLevel * weight * 100))}%` }}
+                  />
+                ))
+              )}
             </motion.div>
           )}
         </AnimatePresence>
@@ -3,6 +3,7 @@
  * - 选择麦克风(输入)与扬声器(输出)设备
  * - 麦克风试音：实时电平条
  * - 扬声器试音：在选定输出设备上播放测试音
+ * - 说话检测阈值：手动调节或采集环境音自动校准
  * 说明：输入设备会在下次开启/重开麦克风时生效；输出设备对已连接对端实时生效。
  *
  * 该模块导出两部分：
@@ -11,10 +12,13 @@
  */
 
 import React, { useState, useEffect, useRef } from 'react';
-import { Modal, Select, Button, Typography, Space, Progress, message } from 'antd';
+import { Modal, Select, Button, Typography, Space, Progress, Slider, message } from 'antd';
+import { invoke } from '@tauri-apps/api/core';
 import { useTranslation } from 'react-i18next';
 import { tl } from '../../i18n';
+import { errorMessage } from '../../services/error/commandError';
 import { audioDevices } from '../../services/voice/audioDevices';
+import { speakingDetector } from '../../services/voice/SpeakingDetector';
 import { webrtcClient } from '../../services';
 
 const { Text } = Typography;
@@ -24,6 +28,15 @@ interface DeviceOption {
   label: string;
 }
 
+/** 说话阈值校准结果（calibrate_vad_threshold 返回） */
+interface VadCalibration {
+  noiseLevel: number;
+  recommended: number;
+}
+
+/** 说话阈值上限（线性 RMS，与后端 MAX_VAD_THRESHOLD 一致） */
+const MAX_VAD_THRESHOLD = 0.2;
+
 interface VoiceDevicePanelProps {
   /** 面板是否处于激活状态（如所在弹窗是否打开），用于控制设备枚举与试音清理 */
   active?: boolean;
@@ -47,6 +60,10 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
   const ctxRef = useRef<AudioContext | null>(null);
   const rafRef = useRef<number | null>(null);
 
+  // 说话检测阈值
+  const [vadThreshold, setVadThreshold] = useState<number | null>(null);
+  const [calibrating, setCalibrating] = useState(false);
+
   // 下拉框渲染到自身父节点，避免在透明窗口/弹窗中出现层级错误（显示在弹窗背后无法点击）
   const popupContainer = (triggerNode: HTMLElement) =>
     (triggerNode.parentElement as HTMLElement) || document.body;
@@ -73,7 +90,7 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
       setOutputs(outs);
       setSupportsOutput(typeof (HTMLMediaElement.prototype as any).setSinkId === 'function');
     } catch (e) {
-      message.error(`${tl('枚举音频设备失败', 'Failed to enumerate audio devices')}：${e}`);
+      message.error(`${tl('枚举音频设备失败', 'Failed to enumerate audio devices')}：${errorMessage(e)}`);
     }
   };
 
@@ -99,6 +116,9 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
       setInputId(audioDevices.getInputDeviceId());
       setOutputId(audioDevices.getOutputDeviceId());
       void loadDevices();
+      invoke<number>('get_vad_threshold')
+        .then(setVadThreshold)
+        .catch((e) => console.error('获取说话阈值失败:', e));
     } else {
       stopMicTest();
     }
@@ -158,7 +178,35 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
       };
       rafRef.current = window.requestAnimationFrame(tick);
     } catch (e) {
-      message.error(`${tl('无法打开麦克风试音', 'Unable to start microphone test')}：${e}`);
+      message.error(`${tl('无法打开麦克风试音', 'Unable to start microphone test')}：${errorMessage(e)}`);
+    }
+  };
+
+  const applyVadThreshold = async (value: number) => {
+    try {
+      const applied = await invoke<number>('set_vad_threshold', { value });
+      setVadThreshold(applied);
+      speakingDetector.setLocalThreshold(applied);
+    } catch (e) {
+      message.error(`${tl('设置说话阈值失败', 'Failed to set the speaking threshold')}：${errorMessage(e)}`);
+    }
+  };
+
+  // 校准：采集几秒环境音，按底噪推荐阈值并直接应用
+  const calibrateVadThreshold = async () => {
+    if (testing) stopMicTest();
+    setCalibrating(true);
+    try {
+      const result = await invoke<VadCalibration>('calibrate_vad_threshold', { durationSecs: 3 });
+      await applyVadThreshold(result.recommended);
+      message.success(tl(
+        `校准完成，阈值已设为 ${Math.round(result.recommended * 100)}%`,
+        `Calibrated, threshold set to ${Math.round(result.recommended * 100)}%`,
+      ));
+    } catch (e) {
+      message.error(`${tl('校准失败', 'Calibration failed')}：${errorMessage(e)}`);
+    } finally {
+      setCalibrating(false);
     }
   };
 
@@ -188,7 +236,7 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
         ctx.close().catch(() => {});
       }, 600);
     } catch (e) {
-      message.error(`${tl('扬声器试音失败', 'Speaker test failed')}：${e}`);
+      message.error(`${tl('扬声器试音失败', 'Speaker test failed')}：${errorMessage(e)}`);
     }
   };
 
@@ -216,6 +264,31 @@ export const VoiceDevicePanel: React.FC<VoiceDevicePanelProps> = ({ active = tru
         </div>
       </div>
 
+      <div>
+        <Text strong>{tl('麦克风灵敏度', 'Microphone Sensitivity')}</Text>
+        <div>
+          <Text type="secondary" style={{ fontSize: 12 }}>
+            {tl('电平高于阈值才判定为说话；底噪大时调高，说话不响应时调低', 'Only levels above the threshold count as speaking; raise it for noisy rooms, lower it if your voice is not detected')}
+          </Text>
+        </div>
+        <Slider
+          min={0}
+          max={MAX_VAD_THRESHOLD}
+          step={0.005}
+          value={vadThreshold ?? 0}
+          disabled={vadThreshold === null || calibrating}
+          tooltip={{ formatter: (v) => `${Math.round((v ?? 0) * 100)}%` }}
+          onChange={(v) => setVadThreshold(v)}
+          onChangeComplete={(v) => void applyVadThreshold(v)}
+        />
+        <Space>
+          <Button size="small" loading={calibrating} onClick={() => void calibrateVadThreshold()}>
+            {calibrating ? tl('正在采集环境音…', 'Sampling ambient noise…') : tl('自动校准', 'Auto Calibrate')}
+          </Button>
+          <Text type="secondary" style={{ fontSize: 12 }}>{tl('校准时请保持安静约 3 秒', 'Stay quiet for about 3 seconds while calibrating')}</Text>
+        </Space>
+      </div>
+
       <div>
         <Text strong>{tl('扬声器（输出）', 'Speaker (Output)')}</Text>
         {supportsOutput ? (
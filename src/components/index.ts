@@ -18,6 +18,9 @@ export { StatusWindow } from './StatusWindow/StatusWindow';
 // 玩家列表组件
 export { PlayerList } from './PlayerList/PlayerList';
 
+// 在线状态点
+export { PresenceDot, PRESENCE_STATUSES, presenceLabel } from './PresenceDot/PresenceDot';
+
 // 语音控制组件
 export { VoiceControls } from './VoiceControls/VoiceControls';
 
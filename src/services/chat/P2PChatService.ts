@@ -6,6 +6,7 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import type { ChatMessage } from '../../types';
 
 interface BackendChatMessage {
@@ -13,9 +14,11 @@ interface BackendChatMessage {
   player_id: string;
   player_name: string;
   content: string;
-  message_type: 'text' | 'image';
+  message_type: 'text' | 'image' | 'system';
   timestamp: number;
   image_data?: number[]; // Uint8Array转换为number[]
+  reply_to?: string | null; // 旧版本没有该字段
+  mentions?: string[];
 }
 
 // 本机聊天服务器端口（服务器现在仅绑定在虚拟网卡 IP 上，不再监听 0.0.0.0，
@@ -25,6 +28,7 @@ const CHAT_SERVER_PORT = 14540;
 class P2PChatService {
   private selfEventSource: EventSource | null = null; // 仅订阅“自己”的消息流
   private selfReconnectTimer: number | null = null;
+  private systemUnlisten: UnlistenFn | null = null; // 系统消息由后端通过 Tauri 事件推送（不走 SSE）
   private isListening: boolean = false;
   private onMessageCallback?: (message: ChatMessage) => void;
   private peerIps: string[] = [];
@@ -79,6 +83,17 @@ class P2PChatService {
     }
     this.isListening = true;
     this.connectToSelfStream();
+    if (!this.systemUnlisten) {
+      void listen<BackendChatMessage>('chat-system-message', (event) => {
+        this.handleMessage(event.payload);
+      }).then((unlisten) => {
+        if (this.isListening) {
+          this.systemUnlisten = unlisten;
+        } else {
+          unlisten();
+        }
+      });
+    }
   }
 
   /**
@@ -216,6 +231,8 @@ class P2PChatService {
       timestamp: msg.timestamp * 1000, // 转换为毫秒
       type: msg.message_type,
       imageData: msg.image_data ? this.arrayToBase64(msg.image_data) : undefined,
+      replyTo: msg.reply_to ?? undefined,
+      mentions: msg.mentions && msg.mentions.length > 0 ? msg.mentions : undefined,
     };
 
     // 回调通知新消息
@@ -223,8 +240,11 @@ class P2PChatService {
       this.onMessageCallback(chatMessage);
     }
 
-    // 只有在不在聊天室界面时才播放音效
+    // 只有在不在聊天室界面时才播放音效（系统消息不提示音）
     const isInChatRoom = (window as any).__isInChatRoom__;
+    if (msg.message_type === 'system') {
+      return;
+    }
     if (!isInChatRoom) {
       this.playNewMessageSound();
     } else {
@@ -298,6 +318,11 @@ class P2PChatService {
   private stopListening(): void {
     this.isListening = false;
 
+    if (this.systemUnlisten) {
+      this.systemUnlisten();
+      this.systemUnlisten = null;
+    }
+
     if (this.selfReconnectTimer) {
       clearTimeout(this.selfReconnectTimer);
       this.selfReconnectTimer = null;
@@ -315,8 +340,13 @@ class P2PChatService {
 
   /**
    * 发送文本消息，返回送达统计 {delivered, total}
+   * @param options.replyTo 回复（引用）的消息 ID
+   * @param options.mentions 被 @ 的玩家 ID
    */
-  async sendTextMessage(content: string): Promise<{ delivered: number; total: number }> {
+  async sendTextMessage(
+    content: string,
+    options: { replyTo?: string; mentions?: string[] } = {}
+  ): Promise<{ delivered: number; total: number }> {
     if (!this.currentPlayerId) {
       throw new Error('未初始化：缺少玩家ID');
     }
@@ -329,6 +359,8 @@ class P2PChatService {
         messageType: 'text',
         imageData: null,
         peerIps: this.peerIps,
+        replyTo: options.replyTo ?? null,
+        mentions: options.mentions ?? null,
       });
       console.log('✅ [P2PChatService] 文本消息已发送', res);
       return res ?? { delivered: 0, total: 0 };
@@ -383,6 +415,27 @@ class P2PChatService {
     }
   }
 
+  /**
+   * 对消息添加或取消表情回应，统计结果通过 message-reaction-update 事件推送
+   */
+  async sendReaction(messageId: string, emoji: string, added: boolean): Promise<void> {
+    if (!this.currentPlayerId) {
+      throw new Error('未初始化：缺少玩家ID');
+    }
+
+    try {
+      await invoke('send_message_reaction', {
+        messageId,
+        emoji,
+        playerId: this.currentPlayerId,
+        added,
+      });
+    } catch (error) {
+      console.error('❌ [P2PChatService] 发送表情回应失败:', error);
+      throw error;
+    }
+  }
+
   /**
    * 清空本地消息
    */
@@ -0,0 +1,52 @@
+/**
+ * 后端命令错误处理
+ * invoke 失败时 reject 的值为 { code, message, detail? }，这里统一取出错误码与说明，
+ * 同时兼容前端自身抛出的 Error 与旧式字符串错误。
+ */
+
+import { tl } from '../../i18n';
+import type { CommandError, CommandErrorCode } from '../../types';
+
+/** 判断是否为后端命令错误，可指定错误码 */
+export function isCommandError(error: unknown, code?: CommandErrorCode): error is CommandError {
+  if (typeof error !== 'object' || error === null) return false;
+  const candidate = error as Partial<CommandError>;
+  if (typeof candidate.code !== 'string' || typeof candidate.message !== 'string') return false;
+  return code === undefined || candidate.code === code;
+}
+
+/** 取出错误码（非后端命令错误时为 undefined） */
+export function commandErrorCode(error: unknown): CommandErrorCode | undefined {
+  return isCommandError(error) ? error.code : undefined;
+}
+
+/** 错误分类的本地化名称 */
+export function commandErrorLabel(code: CommandErrorCode): string {
+  switch (code) {
+    case 'validation': return tl('输入无效', 'Invalid input');
+    case 'network': return tl('网络错误', 'Network error');
+    case 'permission': return tl('权限不足', 'Permission denied');
+    case 'not_found': return tl('目标不存在', 'Not found');
+    case 'audio': return tl('音频设备错误', 'Audio device error');
+    case 'voice': return tl('语音服务错误', 'Voice service error');
+    case 'config': return tl('配置错误', 'Configuration error');
+    case 'process': return tl('进程错误', 'Process error');
+    case 'io': return tl('读写错误', 'I/O error');
+    case 'file': return tl('文件错误', 'File error');
+    case 'serialization': return tl('数据格式错误', 'Data format error');
+    default: return tl('未知错误', 'Unknown error');
+  }
+}
+
+/**
+ * 任意错误转为可展示的文字
+ *
+ * 后端说明为中文，英文界面下前置错误分类名称，便于非中文用户理解。
+ */
+export function errorMessage(error: unknown): string {
+  if (isCommandError(error)) {
+    return tl(error.message, `${commandErrorLabel(error.code)}: ${error.message}`);
+  }
+  if (error instanceof Error) return error.message;
+  return String(error);
+}
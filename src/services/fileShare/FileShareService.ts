@@ -4,7 +4,7 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
-import { SharedFolder, FileInfo, PlayerShare } from '../../types/fileShare';
+import { SharedFolder, FileInfo, PlayerShare, ImportSharesReport } from '../../types/fileShare';
 
 class FileShareService {
   private localShares: SharedFolder[] = [];
@@ -82,6 +82,35 @@ class FileShareService {
     }
   }
 
+  /**
+   * 导出共享列表到文件（不含密码明文）
+   */
+  async exportShares(path: string): Promise<number> {
+    try {
+      const count = await invoke<number>('export_shares', { path });
+      console.log(`✅ 已导出 ${count} 个共享`);
+      return count;
+    } catch (error) {
+      console.error('❌ 导出共享列表失败:', error);
+      throw error;
+    }
+  }
+
+  /**
+   * 从文件导入共享列表，路径失效的条目会在结果中报告
+   */
+  async importShares(path: string, ownerId: string): Promise<ImportSharesReport> {
+    try {
+      const report = await invoke<ImportSharesReport>('import_shares', { path, ownerId });
+      console.log(`✅ 已导入 ${report.imported.length} 个共享，跳过 ${report.skipped.length} 个`);
+      await this.getLocalShares(); // 刷新列表
+      return report;
+    } catch (error) {
+      console.error('❌ 导入共享列表失败:', error);
+      throw error;
+    }
+  }
+
   /**
    * 清理过期共享
    */
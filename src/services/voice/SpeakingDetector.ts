@@ -3,8 +3,12 @@
  * - 对本机麦克风流与各远程音频流做"只读"音量分析（不影响播放）
  * - 通过 RMS 能量+阈值+去抖，判定某人是否正在说话
  * - 仅用于 UI 高亮提示，不参与音频传输
+ * - 本机麦克风使用语音设置中的说话阈值（与后端 set_vad_threshold 同一线性 RMS 刻度）
+ * - 本机麦克风的 RMS 限流上报给后端（report_audio_level），由后端广播说话状态与输入电平
  */
 
+import { invoke } from '@tauri-apps/api/core';
+
 type SpeakingCallback = (playerId: string, speaking: boolean) => void;
 
 interface DetectorEntry {
@@ -12,6 +16,8 @@ interface DetectorEntry {
   analyser: AnalyserNode;
   data: Uint8Array;
   speaking: boolean;
+  /** 是否为本机麦克风流 */
+  local: boolean;
   /** 低于阈值持续计数，用于延迟判定"停止说话"，避免频闪 */
   silenceFrames: number;
 }
@@ -22,8 +28,14 @@ class SpeakingDetector {
   private rafId: number | null = null;
   private callback?: SpeakingCallback;
 
-  // 阈值：RMS（0~1）。说话通常在 0.02 以上
+  // 远程流阈值：RMS（0~1）。说话通常在 0.02 以上
   private readonly SPEAK_THRESHOLD = 0.02;
+  // 本机麦克风阈值，由语音设置（滑块 / 自动校准）调整
+  private localThreshold = this.SPEAK_THRESHOLD;
+  // 本机电平上报间隔（毫秒），后端停止说话的判定延迟为 300ms
+  private readonly REPORT_INTERVAL_MS = 100;
+  private lastReportAt = 0;
+  private reportInFlight = false;
   // 连续静音帧数达到此值才判定停止（约 ~0.5s @ 监测间隔）
   private readonly SILENCE_HOLD = 12;
 
@@ -31,6 +43,22 @@ class SpeakingDetector {
     this.callback = cb;
   }
 
+  /** 设置本机麦克风的说话阈值（线性 RMS） */
+  setLocalThreshold(value: number): void {
+    if (Number.isFinite(value) && value >= 0) {
+      this.localThreshold = value;
+    }
+  }
+
+  /** 从后端读取已保存的说话阈值 */
+  async loadLocalThreshold(): Promise<void> {
+    try {
+      this.setLocalThreshold(await invoke<number>('get_vad_threshold'));
+    } catch (e) {
+      console.warn('[SpeakingDetector] 读取说话阈值失败:', e);
+    }
+  }
+
   private ensureContext(): AudioContext | null {
     if (!this.ctx) {
       try {
@@ -46,8 +74,8 @@ class SpeakingDetector {
     return this.ctx;
   }
 
-  /** 接入一个流进行说话检测 */
-  attach(playerId: string, stream: MediaStream): void {
+  /** 接入一个流进行说话检测（local 为本机麦克风流） */
+  attach(playerId: string, stream: MediaStream, local = false): void {
     if (!stream || stream.getAudioTracks().length === 0) return;
     const ctx = this.ensureContext();
     if (!ctx) return;
@@ -63,7 +91,7 @@ class SpeakingDetector {
       source.connect(analyser);
       // 注意：不连接到 destination，避免二次播放/回声
       const data = new Uint8Array(analyser.fftSize);
-      this.entries.set(playerId, { source, analyser, data, speaking: false, silenceFrames: 0 });
+      this.entries.set(playerId, { source, analyser, data, speaking: false, local, silenceFrames: 0 });
       this.startLoop();
     } catch (e) {
       console.warn(`[SpeakingDetector] attach 失败 (${playerId}):`, e);
@@ -79,6 +107,10 @@ class SpeakingDetector {
         entry.analyser.disconnect();
       } catch { /* ignore */ }
       this.entries.delete(playerId);
+      if (entry.local) {
+        // 麦克风关闭：上报静音，让后端广播停止说话
+        void invoke('report_audio_level', { playerId, level: 0 }).catch(() => {});
+      }
       if (entry.speaking && this.callback) {
         this.callback(playerId, false);
       }
@@ -107,8 +139,12 @@ class SpeakingDetector {
           sum += v * v;
         }
         const rms = Math.sqrt(sum / entry.data.length);
+        if (entry.local) {
+          this.reportLocalLevel(playerId, rms);
+        }
 
-        if (rms >= this.SPEAK_THRESHOLD) {
+        const threshold = entry.local ? this.localThreshold : this.SPEAK_THRESHOLD;
+        if (rms >= threshold) {
           entry.silenceFrames = 0;
           if (!entry.speaking) {
             entry.speaking = true;
@@ -127,6 +163,19 @@ class SpeakingDetector {
     this.rafId = window.requestAnimationFrame(tick);
   }
 
+  /** 限流上报本机麦克风 RMS，上一次调用未返回时跳过 */
+  private reportLocalLevel(playerId: string, rms: number): void {
+    const now = performance.now();
+    if (this.reportInFlight || now - this.lastReportAt < this.REPORT_INTERVAL_MS) return;
+    this.lastReportAt = now;
+    this.reportInFlight = true;
+    invoke('report_audio_level', { playerId, level: rms })
+      .catch((e) => console.debug('[SpeakingDetector] 上报麦克风电平失败:', e))
+      .finally(() => {
+        this.reportInFlight = false;
+      });
+  }
+
   private stopLoop(): void {
     if (this.rafId !== null) {
       window.cancelAnimationFrame(this.rafId);
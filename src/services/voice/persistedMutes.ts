@@ -0,0 +1,46 @@
+/**
+ * 静音记忆（跨会话持久化到后端配置）
+ * - 默认按玩家 ID 记住，玩家 ID 每次启动都会变化，可开启"按昵称记住静音"
+ * - 玩家加入大厅时自动恢复其静音状态
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+
+export interface PersistedMutes {
+  by_name: boolean;
+  player_ids: string[];
+  player_names: string[];
+}
+
+let cache: PersistedMutes | null = null;
+
+export const persistedMutes = {
+  /** 读取记住的静音玩家（首次读取后缓存） */
+  async load(): Promise<PersistedMutes> {
+    if (!cache) {
+      cache = await invoke<PersistedMutes>('get_persisted_mutes');
+    }
+    return cache;
+  },
+  /** 判断玩家是否应被静音 */
+  matches(mutes: PersistedMutes, playerId: string, playerName?: string): boolean {
+    if (mutes.player_ids.includes(playerId)) return true;
+    const name = playerName?.trim();
+    return mutes.by_name && !!name && mutes.player_names.includes(name);
+  },
+  /** 记录一次静音 / 取消静音（同时同步到后端语音服务） */
+  async record(playerId: string, playerName: string | undefined, muted: boolean): Promise<void> {
+    cache = null;
+    await invoke('mute_player', { playerId, muted, playerName: playerName ?? null });
+  },
+  /** 设置是否按昵称记住静音 */
+  async setByName(enabled: boolean): Promise<void> {
+    cache = null;
+    await invoke('set_remember_mutes_by_name', { enabled });
+  },
+  /** 清空记住的静音玩家 */
+  async clear(): Promise<void> {
+    cache = null;
+    await invoke('clear_persisted_mutes');
+  },
+};
@@ -62,6 +62,8 @@ export class WebRTCClient {
   private reconnectTimers: Map<string, number> = new Map();
   private knownPlayers: Set<string> = new Set();
   private pendingPlayerLeaveTimers: Map<string, number> = new Map();
+  // 后端下发的跨玩家响度均衡增益（未启用或未校准时为空）
+  private normalizationGains: Map<string, number> = new Map();
   private readonly transientLeaveConfirmMs: number = 3000; // 减少到3秒，加快响应速度
 
   // ICE 服务器配置
@@ -756,19 +758,25 @@ export class WebRTCClient {
         case 'offer':
           // 收到 offer
           console.log(`📥 收到 Offer from ${message.from}`);
-          await this.handleWebSocketOffer(message);
+          if (await this.openSignalingMessage(message)) {
+            await this.handleWebSocketOffer(message);
+          }
           break;
           
         case 'answer':
           // 收到 answer
           console.log(`📥 收到 Answer from ${message.from}`);
-          await this.handleWebSocketAnswer(message);
+          if (await this.openSignalingMessage(message)) {
+            await this.handleWebSocketAnswer(message);
+          }
           break;
           
         case 'ice-candidate':
           // 收到 ICE 候选
           console.log(`🧊 收到 ICE Candidate from ${message.from}`);
-          await this.handleWebSocketIceCandidate(message);
+          if (await this.openSignalingMessage(message)) {
+            await this.handleWebSocketIceCandidate(message);
+          }
           break;
           
         case 'status-update':
@@ -1321,7 +1329,7 @@ export class WebRTCClient {
               to: peerId,
               answer: {
                 type: answer.type,
-                sdp: answer.sdp,
+                sdp: await this.sealSignalingField('answer', peerId, answer.sdp ?? ''),
               },
             });
 
@@ -1395,7 +1403,7 @@ export class WebRTCClient {
         to: peerId,
         answer: {
           type: answer.type,
-          sdp: answer.sdp,
+          sdp: await this.sealSignalingField('answer', peerId, answer.sdp ?? ''),
         },
       });
 
@@ -1486,6 +1494,38 @@ export class WebRTCClient {
     }
   }
 
+  /**
+   * 用大厅密钥加密语音信令字段（SDP / ICE candidate），未进入加密大厅时原样返回
+   */
+  private async sealSignalingField(kind: 'offer' | 'answer' | 'ice-candidate', to: string, value: string): Promise<string> {
+    return invoke<string>('seal_voice_signaling', { kind, from: this.localPlayerId, to, value });
+  }
+
+  /**
+   * 解密收到的语音信令字段，失败（密钥不匹配、被篡改或未加密）时返回 false，消息应被丢弃
+   */
+  private async openSignalingMessage(message: any): Promise<boolean> {
+    const open = (value: string) => invoke<string>('open_voice_signaling', {
+      kind: message.type,
+      from: message.from,
+      to: this.localPlayerId,
+      value,
+    });
+    try {
+      if (message.type === 'offer' && message.offer) {
+        message.offer = { ...message.offer, sdp: await open(message.offer.sdp ?? '') };
+      } else if (message.type === 'answer' && message.answer) {
+        message.answer = { ...message.answer, sdp: await open(message.answer.sdp ?? '') };
+      } else if (message.type === 'ice-candidate' && message.candidate) {
+        message.candidate = { ...message.candidate, candidate: await open(message.candidate.candidate ?? '') };
+      }
+      return true;
+    } catch (error) {
+      console.warn(`⚠️ 丢弃无法解密的 ${message.type} from ${message.from}:`, error);
+      return false;
+    }
+  }
+
   /**
    * 发送WebSocket消息（公开方法，供外部调用）
    */
@@ -1533,7 +1573,7 @@ export class WebRTCClient {
         to: peerId,
         offer: {
           type: offer.type,
-          sdp: offer.sdp,
+          sdp: await this.sealSignalingField('offer', peerId, offer.sdp ?? ''),
         },
       });
 
@@ -1550,13 +1590,14 @@ export class WebRTCClient {
   }
 
   private async sendOfferWithRetry(peerId: string, offer: RTCSessionDescriptionInit, context: string): Promise<boolean> {
+    const sealedSdp = await this.sealSignalingField('offer', peerId, offer.sdp ?? '');
     const sent = this.sendWebSocketMessage({
       type: 'offer',
       from: this.localPlayerId,
       to: peerId,
       offer: {
         type: offer.type,
-        sdp: offer.sdp,
+        sdp: sealedSdp,
       },
     });
 
@@ -1574,7 +1615,7 @@ export class WebRTCClient {
       to: peerId,
       offer: {
         type: offer.type,
-        sdp: offer.sdp,
+        sdp: sealedSdp,
       },
     });
 
@@ -1605,6 +1646,34 @@ export class WebRTCClient {
         }
       });
 
+      // 监听手动修复连接事件：本机发起修复时主动重新协商，对方发起时仅清理旧连接等待新 offer
+      await listen<{ peerId: string; initiate: boolean }>('webrtc-repair-peer', async (event) => {
+        const { peerId, initiate } = event.payload;
+        if (!peerId || peerId === this.localPlayerId) return;
+        console.log(`🔧 修复与 ${peerId} 的连接（initiate=${initiate}）`);
+        this.clearPeerReconnectState(peerId);
+        if (initiate) {
+          this.reconnectingPeers.add(peerId);
+          try {
+            await this.handleReconnect(peerId, true);
+          } finally {
+            this.reconnectingPeers.delete(peerId);
+          }
+        } else {
+          this.removePeerConnection(peerId);
+        }
+      });
+
+      // 监听响度均衡增益：更新增益后按现有音量/静音状态重新应用到各玩家
+      await listen<{ gains: Record<string, number> }>('voice-normalization-gains', (event) => {
+        this.normalizationGains = new Map(Object.entries(event.payload.gains ?? {}));
+        this.peerConnections.forEach((pc, peerId) => {
+          if (pc.audioElement) {
+            this.applyCurrentAudioState(peerId, pc.audioElement);
+          }
+        });
+      });
+
       console.log('✅ 后端事件监听器已设置');
     } catch (error) {
       console.error('❌ 设置后端事件监听器失败:', error);
@@ -1906,7 +1975,7 @@ export class WebRTCClient {
               from: this.localPlayerId,
               to: peerId,
               candidate: {
-                candidate: event.candidate.candidate,
+                candidate: await this.sealSignalingField('ice-candidate', peerId, event.candidate.candidate),
                 sdpMLineIndex: event.candidate.sdpMLineIndex,
                 sdpMid: event.candidate.sdpMid,
               },
@@ -2381,7 +2450,8 @@ export class WebRTCClient {
           const vol = playerVolumes && playerVolumes.has(playerId)
             ? playerVolumes.get(playerId)!
             : 1.0;
-          audioElement.volume = Math.max(0, Math.min(1, vol));
+          const gain = this.normalizationGains.get(playerId) ?? 1.0;
+          audioElement.volume = Math.max(0, Math.min(1, vol * gain));
         }
 
         console.log(`🎚️ 已对 ${playerId} 应用现有音频状态: muted=${audioElement.muted}, volume=${audioElement.volume}`);
@@ -2478,7 +2548,8 @@ export class WebRTCClient {
    */
   setPlayerVolume(playerId: string, volume: number): void {
     try {
-      const clampedVolume = Math.max(0, Math.min(1, volume));
+      const gain = this.normalizationGains.get(playerId) ?? 1.0;
+      const clampedVolume = Math.max(0, Math.min(1, volume * gain));
       const pc = this.peerConnections.get(playerId);
       if (pc && pc.audioElement) {
         pc.audioElement.volume = clampedVolume;
@@ -2747,6 +2818,7 @@ export class WebRTCClient {
       this.reconnectTimers.clear();
       this.reconnectingPeers.clear();
       this.knownPlayers.clear();
+      this.normalizationGains.clear();
       
       // 重置重连计数
       this.reconnectAttempts = 0;
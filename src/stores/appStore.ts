@@ -6,10 +6,12 @@
 import { create } from 'zustand';
 import { devtools } from 'zustand/middleware';
 import { webrtcClient } from '../services';
+import { persistedMutes } from '../services/voice/persistedMutes';
 import type {
   AppState,
   Lobby,
   Player,
+  PresenceStatus,
   UserConfig,
   WindowPosition,
   ChatMessage,
@@ -70,6 +72,14 @@ interface AppStore {
   clearPlayers: () => void;
   /** 根据 ID 获取玩家 */
   getPlayerById: (playerId: string) => Player | undefined;
+  /** 重名玩家的显示后缀（玩家 ID -> "#2" 等，由后端 player-joined 事件下发） */
+  displaySuffixes: Record<string, string>;
+  /** 设置玩家的显示后缀（null 表示无后缀） */
+  setDisplaySuffix: (playerId: string, suffix: string | null) => void;
+  /** 玩家在线状态（玩家 ID -> 状态，P2P 信令可能早于玩家加入列表到达，先记录在这里） */
+  playerPresences: Record<string, PresenceStatus>;
+  /** 设置玩家在线状态 */
+  setPlayerPresence: (playerId: string, presence: PresenceStatus) => void;
 
   // ==================== 语音状态管理 ====================
   /** 本地麦克风是否开启 */
@@ -251,6 +261,8 @@ const initialState = {
   // 玩家列表
   currentPlayerId: null,
   players: [],
+  displaySuffixes: {},
+  playerPresences: {},
 
   // 语音状态
   micEnabled: false, // 麦克风默认关闭（保护隐私）
@@ -356,7 +368,7 @@ export const useAppStore = create<AppStore>()(
               return state;
             }
             return {
-              players: [...state.players, player],
+              players: [...state.players, { ...player, presence: player.presence ?? state.playerPresences[player.id] }],
             };
           },
           false,
@@ -390,8 +402,35 @@ export const useAppStore = create<AppStore>()(
         );
       },
 
+      setPlayerPresence: (playerId: string, presence: PresenceStatus) => {
+        set(
+          (state) => ({
+            playerPresences: { ...state.playerPresences, [playerId]: presence },
+            players: state.players.map((p) => (p.id === playerId ? { ...p, presence } : p)),
+          }),
+          false,
+          'setPlayerPresence'
+        );
+      },
+
+      setDisplaySuffix: (playerId: string, suffix: string | null) => {
+        set(
+          (state) => {
+            const displaySuffixes = { ...state.displaySuffixes };
+            if (suffix) {
+              displaySuffixes[playerId] = suffix;
+            } else {
+              delete displaySuffixes[playerId];
+            }
+            return { displaySuffixes };
+          },
+          false,
+          'setDisplaySuffix'
+        );
+      },
+
       clearPlayers: () => {
-        set({ players: [] }, false, 'clearPlayers');
+        set({ players: [], displaySuffixes: {}, playerPresences: {} }, false, 'clearPlayers');
         // 清除静音列表
         set({ mutedPlayers: new Set() }, false, 'clearPlayers/clearMuted');
         // 清除玩家音量设置
@@ -444,6 +483,12 @@ export const useAppStore = create<AppStore>()(
           'togglePlayerMute'
         );
         get().applyVoiceGroupRouting();
+        // 记住静音状态，下次加入大厅时自动恢复
+        const muted = get().mutedPlayers.has(playerId);
+        const playerName = get().players.find((p) => p.id === playerId)?.name;
+        persistedMutes.record(playerId, playerName, muted).catch((error) => {
+          console.warn('保存静音记录失败（忽略）:', error);
+        });
       },
 
       mutePlayer: (playerId: string) => {
@@ -10,11 +10,32 @@ export interface SharedFolder {
   id: string;
   name: string;
   path: string;
-  password?: string;
-  expire_time?: number; // Unix timestamp
+  password?: string; // 仅添加共享时提交，列表响应中不会返回
+  has_password?: boolean;
+  expires_at?: number; // 过期时间（Unix 时间戳，秒）
   compress_before_send?: boolean; // 是否启用"先压后发"策略
+  writable?: boolean; // 是否允许其他玩家上传
   owner_id: string;
   created_at: number;
+  note?: string; // 共享级备注
+}
+
+/**
+ * 导入共享列表时被跳过的条目
+ */
+export interface SkippedShare {
+  name: string;
+  path: string;
+  reason: string;
+}
+
+/**
+ * 共享列表导入结果
+ */
+export interface ImportSharesReport {
+  imported: SharedFolder[];
+  skipped: SkippedShare[];
+  shared: boolean; // 文件服务未运行时为 false，共享在服务启动后生效
 }
 
 /**
@@ -26,6 +47,9 @@ export interface FileInfo {
   size: number;
   is_dir: boolean;
   modified: number;
+  note?: string; // 文件备注
+  mime_type?: string; // 按扩展名识别的 MIME 类型
+  thumbnail_base64?: string; // 图片缩略图（JPEG，Base64），未生成时需按需获取
 }
 
 /**
@@ -31,6 +31,30 @@ export interface Lobby {
   signalingServer?: string;
 }
 
+/**
+ * 大厅子服务的启动状态
+ */
+export type ServiceStatus =
+  | { status: 'running' }
+  | { status: 'onDemand' }
+  | { status: 'degraded'; reason: string }
+  | { status: 'failed'; reason: string };
+
+/**
+ * 创建大厅的结果（大厅 + 各子服务启动状态）
+ */
+export interface LobbyCreateResult {
+  /** 创建的大厅 */
+  lobby: Lobby;
+  /** 子服务（network / file / chat / signaling）的启动状态 */
+  services: Record<string, ServiceStatus>;
+}
+
+/**
+ * 玩家在线状态
+ */
+export type PresenceStatus = 'online' | 'away' | 'in_game' | 'busy';
+
 /**
  * 玩家信息
  */
@@ -49,8 +73,14 @@ export interface Player {
   micEnabled: boolean;
   /** 是否被静音 */
   isMuted: boolean;
+  /** 在线状态（未收到时视为在线） */
+  presence?: PresenceStatus;
   /** 加入时间 */
   joinedAt: string;
+  /** 本次会话累计说话时长（秒） */
+  speakingSeconds?: number;
+  /** 本次会话发言次数 */
+  messageCount?: number;
 }
 
 /**
@@ -65,6 +95,8 @@ export interface WindowPosition {
   width?: number;
   /** 高度 */
   height?: number;
+  /** 所在显示器名称 */
+  monitor?: string | null;
 }
 
 /**
@@ -166,10 +198,14 @@ export interface ChatMessage {
   content: string;
   /** 发送时间戳 */
   timestamp: number;
-  /** 消息类型 */
-  type?: 'text' | 'image';
+  /** 消息类型（system 为本机生成的系统通知，不可由用户发送） */
+  type?: 'text' | 'image' | 'system';
   /** 图片数据（Base64） */
   imageData?: string;
+  /** 回复（引用）的消息 ID */
+  replyTo?: string;
+  /** 被 @ 的玩家 ID */
+  mentions?: string[];
 }
 
 /**
@@ -180,6 +216,20 @@ export interface AdvancedNetworkConfig {
   virtualDomain?: string;
 }
 
+/**
+ * 后端网络诊断结果
+ */
+export interface NetworkCheckResult {
+  /** 诊断项名称 */
+  name: string;
+  /** 是否通过 */
+  success: boolean;
+  /** 结果说明（超时时为"诊断超时"） */
+  detail: string;
+  /** 耗时（毫秒） */
+  duration_ms: number;
+}
+
 // 导出文件共享相关类型
 export * from './fileShare';
 
@@ -208,3 +258,54 @@ export interface ScreenShare {
   /** 正在查看的玩家名称 */
   viewerName?: string;
 }
+
+/**
+ * 配置字段元数据（get_config_schema 返回，用于自动渲染设置界面）
+ */
+export interface ConfigFieldSchema {
+  /** 字段名（与配置 JSON 的键一致） */
+  name: string;
+  /** 显示名称 */
+  label: string;
+  /** 字段类型 */
+  type: 'bool' | 'integer' | 'number' | 'string' | 'enum' | 'object' | 'list';
+  /** 分组（internal 表示由程序维护，不在设置界面展示） */
+  group: 'general' | 'voice' | 'hotkeys' | 'window' | 'network' | 'lobby' | 'sharing' | 'security' | 'internal';
+  /** 默认值 */
+  default: unknown;
+  /** 最小值（数值类型） */
+  min?: number;
+  /** 最大值（数值类型） */
+  max?: number;
+  /** 可选值（枚举类型） */
+  options?: string[];
+}
+
+/**
+ * 后端命令错误分类（与 Rust 端 ErrorCode 一致）
+ */
+export type CommandErrorCode =
+  | 'validation'
+  | 'network'
+  | 'permission'
+  | 'not_found'
+  | 'audio'
+  | 'voice'
+  | 'config'
+  | 'process'
+  | 'io'
+  | 'file'
+  | 'serialization'
+  | 'unknown';
+
+/**
+ * 后端命令返回的错误（invoke 失败时 reject 的值）
+ */
+export interface CommandError {
+  /** 错误分类 */
+  code: CommandErrorCode;
+  /** 可直接展示的错误说明（中文） */
+  message: string;
+  /** 补充信息 */
+  detail?: string;
+}